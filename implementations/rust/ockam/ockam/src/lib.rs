@@ -26,7 +26,7 @@ pub use ockam_macros::{node, test};
 // ---
 
 // Export node implementation
-pub use ockam_node::{Context, DelayedEvent, Executor, NodeBuilder, WorkerBuilder};
+pub use ockam_node::{Context, DelayedEvent, Executor, NodeBuilder, ResourceLimits, WorkerBuilder};
 // ---
 
 mod delay;
@@ -126,5 +126,8 @@ pub use ockam_transport_tcp::{TcpTransport, TCP};
 #[cfg(feature = "ockam_transport_tcp")]
 /// Tcp
 pub mod tcp {
-    pub use ockam_transport_tcp::{InletOptions, OutletOptions};
+    pub use ockam_transport_tcp::{
+        InletOptions, OutletOptions, TcpConnectionMetrics, TcpTransportCounters,
+        TcpTransportMetrics,
+    };
 }
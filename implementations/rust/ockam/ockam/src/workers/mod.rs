@@ -1,7 +1,12 @@
 //! A collection of utility workers for various use cases.
 //!
 //! Currently, this contains an echoer worker which is used in many examples,
-//! and is useful for debugging.
+//! and is useful for debugging, and a worker wrapper recording the messages
+//! it receives so they can be replayed.
 mod echoer;
+#[cfg(feature = "std")]
+mod recording;
 
 pub use echoer::*;
+#[cfg(feature = "std")]
+pub use recording::*;
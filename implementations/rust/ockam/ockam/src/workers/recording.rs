@@ -0,0 +1,287 @@
+use crate::{Context, OckamError, Result, Routed, Worker};
+use ockam_core::compat::{boxed::Box, vec::Vec};
+use ockam_core::errcode::{Kind, Origin};
+use ockam_core::{Decodable, Encodable, Error, LocalMessage, Message, Route, TransportMessage};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+fn io_error(e: io::Error) -> Error {
+    Error::new(Origin::Ockam, Kind::Io, e)
+}
+
+/// A message received by a [`RecordingWorker`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Message)]
+pub struct RecordedMessage {
+    /// Time between the start of the recording and the message, in microseconds
+    pub elapsed_micros: u64,
+    /// Onward route of the message after the recording worker
+    pub onward_route: Route,
+    /// Return route of the message
+    pub return_route: Route,
+    /// Encoded body of the message
+    pub payload: Vec<u8>,
+}
+
+impl RecordedMessage {
+    /// Time between the start of the recording and the message
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_micros(self.elapsed_micros)
+    }
+}
+
+/// A worker wrapper writing every message it receives to a file before
+/// handing it to the wrapped worker
+///
+/// Each message is written as its length, a big-endian `u32`, followed by
+/// the encoded [`RecordedMessage`]. Read the recording back with
+/// [`read_recording`], or replay it with [`Replay`].
+///
+/// ```rust
+/// use ockam::workers::{Echoer, RecordingWorker};
+/// # use ockam::{Context, Result};
+/// # async fn test(ctx: Context) -> Result<()> {
+/// let worker = RecordingWorker::new(Echoer, "echoer.recording")?;
+/// ctx.start_worker("echoer", worker).await?;
+/// # Ok(()) }
+/// ```
+pub struct RecordingWorker<W> {
+    inner: W,
+    file: BufWriter<File>,
+    started: Instant,
+}
+
+impl<W> RecordingWorker<W> {
+    /// Wrap `inner`, recording to the file at `path`
+    ///
+    /// The file is created, or truncated if it exists.
+    pub fn new(inner: W, path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::create(path).map_err(io_error)?;
+        Ok(Self {
+            inner,
+            file: BufWriter::new(file),
+            started: Instant::now(),
+        })
+    }
+
+    fn record(&mut self, msg: &LocalMessage) -> Result<()> {
+        let transport = msg.transport();
+        let mut onward_route = transport.onward_route.clone();
+        onward_route.step()?;
+        let record = RecordedMessage {
+            elapsed_micros: self.started.elapsed().as_micros() as u64,
+            onward_route,
+            return_route: transport.return_route.clone(),
+            payload: transport.payload.clone(),
+        };
+
+        let bytes = record.encode()?;
+        self.file
+            .write_all(&(bytes.len() as u32).to_be_bytes())
+            .and_then(|_| self.file.write_all(&bytes))
+            // Keep the recording complete if the node is killed
+            .and_then(|_| self.file.flush())
+            .map_err(io_error)
+    }
+}
+
+#[crate::worker]
+impl<W> Worker for RecordingWorker<W>
+where
+    W: Worker<Context = Context>,
+{
+    type Message = W::Message;
+    type Context = Context;
+
+    async fn initialize(&mut self, ctx: &mut Context) -> Result<()> {
+        self.started = Instant::now();
+        self.inner.initialize(ctx).await
+    }
+
+    async fn shutdown(&mut self, ctx: &mut Context) -> Result<()> {
+        self.file.flush().map_err(io_error)?;
+        self.inner.shutdown(ctx).await
+    }
+
+    async fn handle_message(&mut self, ctx: &mut Context, msg: Routed<W::Message>) -> Result<()> {
+        self.record(msg.local_message())?;
+        self.inner.handle_message(ctx, msg).await
+    }
+}
+
+/// Read all the messages recorded by a [`RecordingWorker`] to `path`
+pub fn read_recording(path: impl AsRef<Path>) -> Result<Vec<RecordedMessage>> {
+    let mut file = BufReader::new(File::open(path).map_err(io_error)?);
+    let mut messages = Vec::new();
+    loop {
+        let mut len = [0u8; 4];
+        match file.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(io_error(e)),
+        }
+        let mut bytes = vec![0u8; u32::from_be_bytes(len) as usize];
+        file.read_exact(&mut bytes).map_err(io_error)?;
+        messages.push(RecordedMessage::decode(&bytes)?);
+    }
+    Ok(messages)
+}
+
+/// What a [`Replay`] does with the return routes of the recorded messages
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReplayReturnRoute {
+    /// Send the messages without a return route, so they can't be answered
+    Discard,
+    /// Send the answers to the given route instead
+    Redirect(Route),
+}
+
+/// Send recorded messages again, to another route
+///
+/// Messages are sent with the delays they were recorded with, divided by the
+/// replay speed. Their original return routes usually lead to workers which
+/// are gone, so they are discarded unless redirected.
+///
+/// ```rust
+/// use ockam::workers::{Replay, ReplayReturnRoute};
+/// # use ockam::{route, Context, Result};
+/// # async fn test(ctx: Context) -> Result<()> {
+/// // Replay ten times faster, collecting the answers at "collector"
+/// let sent = Replay::from_file("echoer.recording")?
+///     .with_speed(10.0)
+///     .with_return_route(ReplayReturnRoute::Redirect(route!["collector"]))
+///     .run(&ctx, route!["echoer"])
+///     .await?;
+/// # Ok(()) }
+/// ```
+pub struct Replay {
+    messages: Vec<RecordedMessage>,
+    speed: f64,
+    return_route: ReplayReturnRoute,
+}
+
+impl Replay {
+    /// Replay the given messages at their original timing
+    pub fn new(messages: Vec<RecordedMessage>) -> Self {
+        Self {
+            messages,
+            speed: 1.0,
+            return_route: ReplayReturnRoute::Discard,
+        }
+    }
+
+    /// Replay the messages recorded to `path`
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::new(read_recording(path)?))
+    }
+
+    /// Divide the delays between messages by `speed`
+    ///
+    /// The speed must be positive, [`f64::INFINITY`] sends all the messages
+    /// without delay.
+    pub fn with_speed(self, speed: f64) -> Self {
+        Self { speed, ..self }
+    }
+
+    /// Set what to do with the return routes of the messages
+    pub fn with_return_route(self, return_route: ReplayReturnRoute) -> Self {
+        Self {
+            return_route,
+            ..self
+        }
+    }
+
+    /// Return the messages to replay
+    pub fn messages(&self) -> &[RecordedMessage] {
+        &self.messages
+    }
+
+    /// Send the messages to `target`, followed by their recorded onward
+    /// route, returning how many were sent
+    pub async fn run(&self, ctx: &Context, target: impl Into<Route>) -> Result<usize> {
+        if self.speed.is_nan() || self.speed <= 0.0 {
+            return Err(OckamError::InvalidParameter.into());
+        }
+
+        let target = target.into();
+        let started = Instant::now();
+        for msg in &self.messages {
+            let due = msg.elapsed().div_f64(self.speed);
+            if let Some(delay) = due.checked_sub(started.elapsed()) {
+                ctx.sleep(delay).await;
+            }
+
+            let onward_route: Route = msg
+                .onward_route
+                .clone()
+                .modify()
+                .prepend_route(target.clone())
+                .into();
+            let return_route = match &self.return_route {
+                ReplayReturnRoute::Discard => Route::new().into(),
+                ReplayReturnRoute::Redirect(route) => route.clone(),
+            };
+            let transport = TransportMessage::v1(onward_route, return_route, msg.payload.clone());
+            ctx.forward(LocalMessage::new(transport, Vec::new()))
+                .await?;
+        }
+
+        Ok(self.messages.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workers::Echoer;
+    use ockam_core::{route, Address};
+
+    #[ockam_macros::test]
+    async fn record_and_replay(ctx: &mut Context) -> Result<()> {
+        let path = std::env::temp_dir().join(format!(
+            "ockam-recording-{}",
+            Address::random_local().address()
+        ));
+
+        let worker = RecordingWorker::new(Echoer, &path)?;
+        ctx.start_worker("recorded_echoer", worker).await?;
+        for msg in ["a", "b", "c"] {
+            let reply: String = ctx
+                .send_and_receive(route!["recorded_echoer"], msg.to_string())
+                .await?;
+            assert_eq!(reply, msg);
+        }
+
+        let messages = read_recording(&path)?;
+        assert_eq!(messages.len(), 3);
+        assert!(messages[0].onward_route.iter().next().is_none());
+        assert!(messages
+            .windows(2)
+            .all(|m| m[0].elapsed_micros <= m[1].elapsed_micros));
+
+        // Answers are redirected to a collector rather than to the
+        // contexts which sent the original messages
+        ctx.start_worker("echoer", Echoer).await?;
+        let mut collector = ctx.new_detached(Address::from("collector")).await?;
+        let sent = Replay::new(messages)
+            .with_speed(f64::INFINITY)
+            .with_return_route(ReplayReturnRoute::Redirect(route!["collector"]))
+            .run(ctx, route!["echoer"])
+            .await?;
+        assert_eq!(sent, 3);
+        for msg in ["a", "b", "c"] {
+            assert_eq!(collector.receive::<String>().await?, msg.to_string());
+        }
+
+        assert!(Replay::from_file(&path)?
+            .with_speed(0.0)
+            .run(ctx, route!["echoer"])
+            .await
+            .is_err());
+
+        std::fs::remove_file(&path).unwrap();
+        ctx.stop().await
+    }
+}
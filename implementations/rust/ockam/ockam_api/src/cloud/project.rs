@@ -174,6 +174,8 @@ impl OktaConfig<'_> {
 pub struct OktaAuth0 {
     pub tenant_url: String,
     pub client_id: String,
+    #[serde(default)]
+    pub certificate: String,
 }
 
 impl From<OktaConfig<'_>> for OktaAuth0 {
@@ -181,6 +183,7 @@ impl From<OktaConfig<'_>> for OktaAuth0 {
         Self {
             tenant_url: c.tenant_url.to_string(),
             client_id: c.client_id.to_string(),
+            certificate: c.certificate.to_string(),
         }
     }
 }
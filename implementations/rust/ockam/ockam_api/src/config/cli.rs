@@ -13,6 +13,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::{
     env,
+    net::SocketAddr,
     path::{Path, PathBuf},
 };
 
@@ -40,6 +41,10 @@ pub struct OckamConfig {
     pub lookup: ConfigLookup,
 
     pub default_identity: Option<Vec<u8>>,
+    /// Change histories imported with `identity import`, which can only
+    /// be used to verify these identities
+    #[serde(default)]
+    pub imported_identities: BTreeMap<String, Vec<u8>>,
     pub default_vault_path: Option<PathBuf>,
     /// Default node
     pub default: Option<String>,
@@ -60,6 +65,7 @@ impl ConfigValues for OckamConfig {
             nodes: BTreeMap::new(),
             lookup: default_lookup(),
             default_identity: None,
+            imported_identities: BTreeMap::new(),
             default_vault_path: None,
             default: None,
         }
@@ -113,6 +119,13 @@ pub struct NodeConfigOld {
     verbose: u8,
     pub pid: Option<i32>,
     state_dir: Option<PathBuf>,
+    /// The node was started without the node manager API
+    #[serde(default)]
+    pub no_api: bool,
+    /// The externally reachable address of the node, when it differs
+    /// from the address it listens on
+    #[serde(default)]
+    pub advertised_address: Option<SocketAddr>,
 }
 
 fn default_name() -> String {
@@ -144,6 +157,8 @@ impl NodeConfigOld {
             verbose,
             pid,
             state_dir,
+            no_api: false,
+            advertised_address: None,
         }
     }
 
@@ -170,6 +185,14 @@ impl NodeConfigOld {
     pub fn state_dir(&self) -> Option<&Path> {
         self.state_dir.as_deref()
     }
+
+    pub fn no_api(&self) -> bool {
+        self.no_api
+    }
+
+    pub fn advertised_address(&self) -> Option<SocketAddr> {
+        self.advertised_address
+    }
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
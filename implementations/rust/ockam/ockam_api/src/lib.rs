@@ -6,6 +6,8 @@ pub mod echoer;
 pub mod error;
 pub mod identity;
 pub mod nodes;
+pub mod storage_codec;
+pub mod storage_encryption;
 pub mod uppercase;
 pub mod vault;
 pub mod verifier;
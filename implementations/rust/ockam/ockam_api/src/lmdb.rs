@@ -1,9 +1,13 @@
-use lmdb::{Database, Environment, Transaction};
+use lmdb::{Cursor, Database, Environment, Transaction};
 use ockam_abac::{Action, Expr, PolicyStorage, Resource};
 use ockam_core::async_trait;
 use ockam_core::errcode::{Kind, Origin};
 use ockam_core::{Error, Result};
-use ockam_identity::authenticated_storage::AuthenticatedStorage;
+use ockam_identity::authenticated_storage::{
+    AuthenticatedStorage, AuthenticatedStorageCounters, AuthenticatedStorageEvent,
+    AuthenticatedStorageListener, AuthenticatedStorageListeners, AuthenticatedStorageMetrics,
+    AuthenticatedStorageTransaction, AuthenticatedStorageWrite,
+};
 use ockam_node::tokio::task::{self, JoinError};
 use std::fmt;
 use std::path::Path;
@@ -14,6 +18,8 @@ use std::sync::Arc;
 pub struct LmdbStorage {
     env: Arc<Environment>,
     map: Database,
+    listeners: AuthenticatedStorageListeners,
+    counters: AuthenticatedStorageCounters,
 }
 
 impl fmt::Debug for LmdbStorage {
@@ -38,6 +44,8 @@ impl LmdbStorage {
             Ok(LmdbStorage {
                 env: Arc::new(env),
                 map,
+                listeners: Default::default(),
+                counters: Default::default(),
             })
         };
         task::spawn_blocking(t).await.map_err(map_join_err)?
@@ -80,15 +88,100 @@ impl AuthenticatedStorage for LmdbStorage {
                 Err(e) => Err(map_lmdb_err(e)),
             }
         };
-        task::spawn_blocking(t).await.map_err(map_join_err)?
+        let val = task::spawn_blocking(t).await.map_err(map_join_err)??;
+        self.counters.record_get(val.is_some());
+        Ok(val)
     }
 
     async fn set(&self, id: &str, key: String, val: Vec<u8>) -> Result<()> {
-        self.write(format!("{id}:{key}"), val).await
+        self.write(format!("{id}:{key}"), val).await?;
+        self.counters.record_set();
+        self.listeners.notify(AuthenticatedStorageEvent::Set {
+            id: id.to_string(),
+            key,
+        });
+        Ok(())
     }
 
     async fn del(&self, id: &str, key: &str) -> Result<()> {
-        self.delete(format!("{id}:{key}")).await
+        self.delete(format!("{id}:{key}")).await?;
+        self.counters.record_del();
+        self.listeners.notify(AuthenticatedStorageEvent::Deleted {
+            id: id.to_string(),
+            key: key.to_string(),
+        });
+        Ok(())
+    }
+
+    fn subscribe(&self, listener: Arc<dyn AuthenticatedStorageListener>) -> Result<()> {
+        self.listeners.subscribe(listener);
+        Ok(())
+    }
+
+    async fn commit(&self, transaction: AuthenticatedStorageTransaction) -> Result<()> {
+        let events: Vec<_> = transaction.writes().iter().map(|w| w.event()).collect();
+        let d = self.clone();
+        let t = move || {
+            // Dropping the lmdb transaction on error aborts it
+            let mut w = d.env.begin_rw_txn().map_err(map_lmdb_err)?;
+            for write in transaction.into_writes() {
+                match write {
+                    AuthenticatedStorageWrite::Set { id, key, val } => w
+                        .put(
+                            d.map,
+                            &format!("{id}:{key}"),
+                            &val,
+                            lmdb::WriteFlags::empty(),
+                        )
+                        .map_err(map_lmdb_err)?,
+                    AuthenticatedStorageWrite::Del { id, key } => {
+                        match w.del(d.map, &format!("{id}:{key}"), None) {
+                            Ok(()) | Err(lmdb::Error::NotFound) => {}
+                            Err(e) => return Err(map_lmdb_err(e)),
+                        }
+                    }
+                }
+            }
+            w.commit().map_err(map_lmdb_err)
+        };
+        task::spawn_blocking(t).await.map_err(map_join_err)??;
+        for event in events {
+            self.counters.record_event(&event);
+            self.listeners.notify(event);
+        }
+        Ok(())
+    }
+
+    /// The entry count includes the policies stored in the same database
+    async fn metrics(&self) -> Result<AuthenticatedStorageMetrics> {
+        let d = self.clone();
+        let t = move || -> Result<usize> {
+            let r = d.env.begin_ro_txn().map_err(map_lmdb_err)?;
+            let mut cursor = r.open_ro_cursor(d.map).map_err(map_lmdb_err)?;
+            Ok(cursor.iter().count())
+        };
+        let entries = task::spawn_blocking(t).await.map_err(map_join_err)??;
+        Ok(self.counters.metrics(entries))
+    }
+
+    /// The entries include the policies stored in the same database
+    async fn entries(&self) -> Result<Vec<(String, String, Vec<u8>)>> {
+        let d = self.clone();
+        let t = move || {
+            let r = d.env.begin_ro_txn().map_err(map_lmdb_err)?;
+            let mut cursor = r.open_ro_cursor(d.map).map_err(map_lmdb_err)?;
+            let mut entries = Vec::new();
+            for item in cursor.iter() {
+                let (k, v) = item.map_err(map_lmdb_err)?;
+                // Keys are written as `{id}:{key}`
+                let split = std::str::from_utf8(k).ok().and_then(|k| k.split_once(':'));
+                if let Some((id, key)) = split {
+                    entries.push((id.to_string(), key.to_string(), v.to_vec()))
+                }
+            }
+            Ok(entries)
+        };
+        task::spawn_blocking(t).await.map_err(map_join_err)?
     }
 }
 
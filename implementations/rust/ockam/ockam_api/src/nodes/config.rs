@@ -1,6 +1,9 @@
 use crate::config::{Config, ConfigValues};
+use crate::nodes::models::vault::VaultBackend;
+use crate::HexByteVec;
 pub use commands::*;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
 #[derive(Debug)]
@@ -31,13 +34,43 @@ pub struct NodeStateConfig {
     pub authenticated_storage_path: Option<PathBuf>,
     /// Vault info
     pub vault_path: Option<PathBuf>,
+    /// Where the vault keeps its secrets, software if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vault_backend: Option<VaultBackend>,
+    /// Vaults created with a name, which an identity can be created with
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub vaults: BTreeMap<String, NamedVault>,
     /// Exported identity value
     pub identity: Option<Vec<u8>>,
     /// Identity was overridden
     pub identity_was_overridden: bool,
+    /// Last credential obtained from the project authority
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cached_credential: Option<CachedCredential>,
+    /// Vault key id of the pre-shared key required on secure channels, the key itself stays in the vault
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_shared_key: Option<String>,
+    /// The change history of the node's identity can't be changed
+    #[serde(default)]
+    pub read_only_identity: bool,
     pub commands: Commands,
 }
 
+/// A vault created with a name, see [`NodeStateConfig::vaults`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NamedVault {
+    pub path: PathBuf,
+    pub backend: VaultBackend,
+}
+
+/// A CBOR-encoded credential and when the authority issued it to the node
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedCredential {
+    pub credential: HexByteVec,
+    /// Unix timestamp, in seconds
+    pub validated_at: u64,
+}
+
 impl ConfigValues for NodeStateConfig {
     fn default_values(_config_dir: &Path) -> Self {
         Self::default()
@@ -9,4 +9,9 @@ pub mod models;
 pub const NODEMANAGER_ADDR: &str = "_internal.nodemanager";
 
 /// The main node-manager service running on remote nodes
-pub use service::{IdentityOverride, NodeManager, NodeManagerWorker};
+pub use service::{
+    CredentialChecks, CredentialOutagePolicy, IdentityOverride, NodeManager, NodeManagerWorker,
+};
+
+/// Create and start a node without going through the CLI
+pub use service::builder::{NodeBuilder, NodeService, RunningNode};
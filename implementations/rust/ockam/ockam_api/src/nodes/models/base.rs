@@ -3,7 +3,10 @@
 // TODO: split up this file into sub modules
 
 use minicbor::{Decode, Encode};
+use ockam::tcp::{TcpTransportCounters, TcpTransportMetrics};
 use ockam_core::compat::borrow::Cow;
+use ockam_node::WorkerInfo;
+use serde::Serialize;
 
 #[cfg(feature = "tag")]
 use ockam_core::TypeTag;
@@ -45,3 +48,88 @@ impl<'a> NodeStatus<'a> {
         }
     }
 }
+
+/// Response body describing a worker or processor running on a node
+#[derive(Debug, Clone, Decode, Encode, Serialize)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct WorkerStatus<'a> {
+    #[cfg(feature = "tag")]
+    #[serde(skip)]
+    #[n(0)] tag: TypeTag<3914207>,
+    /// All addresses of the worker, starting with the primary one
+    #[b(1)] pub addresses: Vec<Cow<'a, str>>,
+    #[b(2)] pub cluster: Option<Cow<'a, str>>,
+    #[n(3)] pub processor: bool,
+}
+
+impl From<WorkerInfo> for WorkerStatus<'_> {
+    fn from(info: WorkerInfo) -> Self {
+        Self {
+            #[cfg(feature = "tag")]
+            tag: TypeTag,
+            addresses: info
+                .addresses
+                .iter()
+                .map(|a| a.to_string().into())
+                .collect(),
+            cluster: info.cluster.map(Cow::Owned),
+            processor: info.processor,
+        }
+    }
+}
+
+/// Response body for the list of workers running on a node
+#[derive(Debug, Clone, Decode, Encode, Serialize)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct WorkerList<'a> {
+    #[cfg(feature = "tag")]
+    #[serde(skip)]
+    #[n(0)] tag: TypeTag<7302848>,
+    #[b(1)] pub list: Vec<WorkerStatus<'a>>
+}
+
+impl<'a> WorkerList<'a> {
+    pub fn new(list: Vec<WorkerStatus<'a>>) -> Self {
+        Self {
+            #[cfg(feature = "tag")]
+            tag: TypeTag,
+            list,
+        }
+    }
+}
+
+/// Response body for the traffic metrics of a node
+///
+/// The counters count from the node's start, or from their last reset.
+/// Heartbeats are not counted, as by the per-connection traffic and the
+/// metrics endpoint of the node.
+#[derive(Debug, Clone, Decode, Encode, Serialize)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct MetricsStatus {
+    #[cfg(feature = "tag")]
+    #[serde(skip)]
+    #[n(0)] tag: TypeTag<4620186>,
+    /// Open TCP connections, a gauge which is never reset
+    #[n(1)] pub tcp_active_connections: u64,
+    #[n(2)] pub tcp_messages_sent: u64,
+    #[n(3)] pub tcp_messages_received: u64,
+    #[n(4)] pub tcp_bytes_sent: u64,
+    #[n(5)] pub tcp_bytes_received: u64,
+}
+
+impl MetricsStatus {
+    pub fn new(tcp: &TcpTransportMetrics, counters: TcpTransportCounters) -> Self {
+        Self {
+            #[cfg(feature = "tag")]
+            tag: TypeTag,
+            tcp_active_connections: tcp.active_connections() as u64,
+            tcp_messages_sent: counters.messages_sent,
+            tcp_messages_received: counters.messages_received,
+            tcp_bytes_sent: counters.bytes_sent,
+            tcp_bytes_received: counters.bytes_received,
+        }
+    }
+}
@@ -2,11 +2,46 @@ use minicbor::{Decode, Encode};
 use ockam_core::compat::borrow::Cow;
 use serde::Serialize;
 
-use ockam_core::CowBytes;
+use ockam_core::{CowBytes, CowStr};
 
 #[cfg(feature = "tag")]
 use ockam_core::TypeTag;
 
+/// Request body when instructing a node to create an Identity, requests
+/// without a body create it with the node's vault
+#[derive(Debug, Clone, Decode, Encode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct CreateIdentityRequest<'a> {
+    #[cfg(feature = "tag")]
+    #[n(0)] tag: TypeTag<3981247>,
+    /// Name of a vault created with
+    /// [`CreateVaultRequest::with_name`](super::vault::CreateVaultRequest::with_name),
+    /// which becomes the node's vault
+    #[b(1)] pub vault: Option<CowStr<'a>>,
+}
+
+impl<'a> CreateIdentityRequest<'a> {
+    pub fn new() -> Self {
+        Self {
+            #[cfg(feature = "tag")]
+            tag: TypeTag,
+            vault: None,
+        }
+    }
+
+    pub fn with_vault(mut self, vault: impl Into<CowStr<'a>>) -> Self {
+        self.vault = Some(vault.into());
+        self
+    }
+}
+
+impl Default for CreateIdentityRequest<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Response body when instructing a node to create a Secure Channel
 #[derive(Debug, Clone, Decode, Encode)]
 #[rustfmt::skip]
@@ -7,7 +7,7 @@ use ockam_core::compat::borrow::Cow;
 #[cfg(feature = "tag")]
 use ockam_core::TypeTag;
 use ockam_core::{route, Address, CowStr, Result};
-use ockam_identity::IdentityIdentifier;
+use ockam_identity::{IdentityIdentifier, SecureChannelNegotiation};
 use ockam_multiaddr::MultiAddr;
 use serde::Serialize;
 
@@ -33,7 +33,11 @@ pub struct CreateSecureChannelRequest<'a> {
     #[b(1)] pub addr: CowStr<'a>,
     #[b(2)] pub authorized_identifiers: Option<Vec<CowStr<'a>>>,
     #[n(3)] pub credential_exchange_mode: CredentialExchangeMode,
-    #[n(4)] pub timeout: Option<Duration>
+    #[n(4)] pub timeout: Option<Duration>,
+    /// Shown with the channel to identify it, need not be unique
+    #[b(5)] pub label: Option<CowStr<'a>>,
+    /// Prove the pre-shared key the node was created with
+    #[n(6)] pub pre_shared_key: Option<bool>,
 }
 
 impl<'a> CreateSecureChannelRequest<'a> {
@@ -50,8 +54,20 @@ impl<'a> CreateSecureChannelRequest<'a> {
                 .map(|x| x.into_iter().map(|y| y.to_string().into()).collect()),
             credential_exchange_mode,
             timeout: None,
+            label: None,
+            pre_shared_key: None,
         }
     }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into().into());
+        self
+    }
+
+    pub fn with_pre_shared_key(mut self) -> Self {
+        self.pre_shared_key = Some(true);
+        self
+    }
 }
 
 /// Response body when instructing a node to create a Secure Channel
@@ -96,19 +112,115 @@ pub struct CreateSecureChannelListenerRequest<'a> {
     #[n(0)] tag: TypeTag<8112242>,
     #[b(1)] pub addr: Cow<'a, str>,
     #[b(2)] pub authorized_identifiers: Option<Vec<CowStr<'a>>>,
+    /// Only trust initiators which presented a valid credential, absent
+    /// from the requests of older clients
+    #[n(3)] pub check_credential: Option<bool>,
+    /// Require the pre-shared key the node was created with
+    #[n(4)] pub pre_shared_key: Option<bool>,
 }
 
 impl<'a> CreateSecureChannelListenerRequest<'a> {
-    pub fn new(addr: &Address, authorized_identifiers: Option<Vec<IdentityIdentifier>>) -> Self {
+    pub fn new(
+        addr: &Address,
+        authorized_identifiers: Option<Vec<IdentityIdentifier>>,
+        check_credential: bool,
+    ) -> Self {
         Self {
             #[cfg(feature = "tag")]
             tag: TypeTag,
             addr: addr.to_string().into(),
             authorized_identifiers: authorized_identifiers
                 .map(|x| x.into_iter().map(|y| y.to_string().into()).collect()),
+            check_credential: Some(check_credential),
+            pre_shared_key: None,
+        }
+    }
+
+    pub fn with_pre_shared_key(mut self) -> Self {
+        self.pre_shared_key = Some(true);
+        self
+    }
+}
+
+/// Request body when instructing a node to change which initiators a
+/// Secure Channel Listener trusts
+#[derive(Debug, Clone, Decode, Encode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct UpdateSecureChannelListenerRequest<'a> {
+    #[cfg(feature = "tag")]
+    #[n(0)] tag: TypeTag<6093417>,
+    #[b(1)] pub addr: Cow<'a, str>,
+    /// Identifiers to trust from now on
+    #[b(2)] pub add_identifiers: Vec<CowStr<'a>>,
+    /// Identifiers to stop trusting
+    #[b(3)] pub remove_identifiers: Vec<CowStr<'a>>,
+}
+
+impl<'a> UpdateSecureChannelListenerRequest<'a> {
+    pub fn new(
+        addr: &Address,
+        add_identifiers: Vec<IdentityIdentifier>,
+        remove_identifiers: Vec<IdentityIdentifier>,
+    ) -> Self {
+        Self {
+            #[cfg(feature = "tag")]
+            tag: TypeTag,
+            addr: addr.to_string().into(),
+            add_identifiers: add_identifiers
+                .into_iter()
+                .map(|x| x.to_string().into())
+                .collect(),
+            remove_identifiers: remove_identifiers
+                .into_iter()
+                .map(|x| x.to_string().into())
+                .collect(),
+        }
+    }
+}
+
+/// A Secure Channel Listener and the initiators it trusts
+#[derive(Debug, Clone, Decode, Encode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct SecureChannelListenerStatus<'a> {
+    #[cfg(feature = "tag")]
+    #[n(0)] tag: TypeTag<3716054>,
+    #[b(1)] pub addr: Cow<'a, str>,
+    #[b(2)] pub trust_policy: Cow<'a, str>,
+}
+
+impl<'a> SecureChannelListenerStatus<'a> {
+    pub fn new(addr: impl Into<Cow<'a, str>>, trust_policy: impl Into<Cow<'a, str>>) -> Self {
+        Self {
+            #[cfg(feature = "tag")]
+            tag: TypeTag,
+            addr: addr.into(),
+            trust_policy: trust_policy.into(),
+        }
+    }
+}
+
+/// Response body for listing Secure Channel Listeners
+#[derive(Debug, Clone, Decode, Encode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct SecureChannelListenerList<'a> {
+    #[cfg(feature = "tag")]
+    #[n(0)] tag: TypeTag<5288934>,
+    #[b(1)] pub list: Vec<SecureChannelListenerStatus<'a>>,
+}
+
+impl<'a> SecureChannelListenerList<'a> {
+    pub fn new(list: Vec<SecureChannelListenerStatus<'a>>) -> Self {
+        Self {
+            #[cfg(feature = "tag")]
+            tag: TypeTag,
+            list,
         }
     }
 }
+
 #[derive(Debug, Clone, Decode, Encode)]
 #[rustfmt::skip]
 #[cbor(map)]
@@ -175,10 +287,21 @@ pub struct ShowSecureChannelResponse<'a> {
     #[b(1)] pub channel: Option<Cow<'a, str>>,
     #[b(2)] pub route: Option<Cow<'a, str>>,
     #[b(4)] pub authorized_identifiers: Option<Vec<CowStr<'a>>>,
+    /// Parameters the channel was negotiated with, unknown if it's stopped
+    #[b(5)] pub cipher_suite: Option<CowStr<'a>>,
+    #[n(6)] pub pre_shared_key: Option<bool>,
+    #[n(7)] pub handshake_padding: Option<u64>,
+    #[n(8)] pub sequence_numbers: Option<bool>,
+    #[n(9)] pub byte_budget: Option<u64>,
+    #[b(10)] pub label: Option<CowStr<'a>>,
+    #[b(11)] pub compression: Option<CowStr<'a>>,
 }
 
 impl<'a> ShowSecureChannelResponse<'a> {
-    pub fn new(info: Option<&SecureChannelInfo>) -> Self {
+    pub fn new(
+        info: Option<&SecureChannelInfo>,
+        negotiation: Option<&SecureChannelNegotiation>,
+    ) -> Self {
         Self {
             #[cfg(feature = "tag")]
             tag: TypeTag,
@@ -190,6 +313,13 @@ impl<'a> ShowSecureChannelResponse<'a> {
                         .map(|ids| ids.iter().map(|iid| iid.to_string().into()).collect())
                 })
                 .unwrap_or(None),
+            cipher_suite: negotiation.map(|n| n.cipher_suite().to_string().into()),
+            pre_shared_key: negotiation.map(|n| n.pre_shared_key()),
+            handshake_padding: negotiation.and_then(|n| n.handshake_padding().map(|p| p as u64)),
+            sequence_numbers: negotiation.map(|n| n.sequence_numbers()),
+            byte_budget: negotiation.and_then(|n| n.byte_budget()),
+            label: info.and_then(|info| info.label().map(|label| label.to_string().into())),
+            compression: negotiation.map(|n| n.compression().to_string().into()),
         }
     }
 }
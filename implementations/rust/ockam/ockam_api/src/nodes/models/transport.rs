@@ -1,3 +1,4 @@
+use crate::nodes::registry::SecureChannelInfo;
 use minicbor::{Decode, Encode};
 use ockam_core::compat::borrow::Cow;
 use std::fmt::{self, Display};
@@ -73,6 +74,8 @@ pub enum TransportType {
     #[n(1)] Ble,
     /// Websocket transport
     #[n(2)] WebSocket,
+    /// Unix domain socket transport
+    #[n(3)] Uds,
 }
 
 impl Display for TransportType {
@@ -81,6 +84,7 @@ impl Display for TransportType {
             Self::Tcp => "TCP",
             Self::Ble => "BLE",
             Self::WebSocket => "Websocket",
+            Self::Uds => "UDS",
         })
     }
 }
@@ -124,6 +128,18 @@ pub struct TransportStatus<'a> {
     /// We use this as a kind of URI to be able to address a transport
     /// by a unique value for specific updates and deletion events.
     #[n(5)] pub tid: Cow<'a, str>,
+    /// Bytes received on the connection, unset for listeners
+    #[n(6)] pub bytes_in: Option<u64>,
+    /// Bytes sent on the connection, unset for listeners
+    #[n(7)] pub bytes_out: Option<u64>,
+    /// When the connection was established, in seconds since the Unix epoch
+    #[n(8)] pub connected_at: Option<u64>,
+    /// Secure channels of the node running over the connection, unset for
+    /// listeners and for nodes that don't report them
+    #[b(9)] pub secure_channels: Option<Vec<TransportChannel<'a>>>,
+    /// Address of the worker sending over a UDS connection, whose socket
+    /// path can't be written as a multiaddr
+    #[b(10)] pub worker_addr: Option<Cow<'a, str>>,
 }
 
 impl<'a> TransportStatus<'a> {
@@ -140,6 +156,57 @@ impl<'a> TransportStatus<'a> {
             tm,
             payload: payload.into(),
             tid: tid.into(),
+            bytes_in: None,
+            bytes_out: None,
+            connected_at: None,
+            secure_channels: None,
+            worker_addr: None,
+        }
+    }
+
+    /// Attach the traffic counters of an open connection
+    pub fn with_traffic(mut self, bytes_in: u64, bytes_out: u64, connected_at: u64) -> Self {
+        self.bytes_in = Some(bytes_in);
+        self.bytes_out = Some(bytes_out);
+        self.connected_at = Some(connected_at);
+        self
+    }
+
+    /// Attach the address of the worker sending over the connection
+    pub fn with_worker_addr(mut self, worker_addr: impl Into<Cow<'a, str>>) -> Self {
+        self.worker_addr = Some(worker_addr.into());
+        self
+    }
+
+    /// Attach the secure channels running over the connection
+    pub fn with_secure_channels(mut self, secure_channels: Vec<TransportChannel<'a>>) -> Self {
+        self.secure_channels = Some(secure_channels);
+        self
+    }
+}
+
+/// A secure channel running over a transport
+#[derive(Debug, Clone, Decode, Encode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct TransportChannel<'a> {
+    #[cfg(feature = "tag")]
+    #[n(0)] tag: TypeTag<2364027>,
+    /// Local address of the channel
+    #[b(1)] pub address: Cow<'a, str>,
+    /// Identifiers the channel was restricted to, if any
+    #[b(2)] pub authorized_identifiers: Option<Vec<Cow<'a, str>>>,
+}
+
+impl<'a> TransportChannel<'a> {
+    pub fn new(info: &SecureChannelInfo) -> Self {
+        Self {
+            #[cfg(feature = "tag")]
+            tag: TypeTag,
+            address: info.addr().to_string().into(),
+            authorized_identifiers: info
+                .authorized_identifiers()
+                .map(|ids| ids.iter().map(|id| id.to_string().into()).collect()),
         }
     }
 }
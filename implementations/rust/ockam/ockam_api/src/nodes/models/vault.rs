@@ -1,9 +1,40 @@
+use core::fmt;
+
 use minicbor::{Decode, Encode};
+use serde::{Deserialize, Serialize};
 
 use ockam_core::CowStr;
 #[cfg(feature = "tag")]
 use ockam_core::TypeTag;
 
+/// Where a Vault keeps its secrets
+#[derive(Debug, Clone, PartialEq, Eq, Decode, Encode, Serialize, Deserialize)]
+#[rustfmt::skip]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum VaultBackend {
+    /// Secrets stored in a file, see [`CreateVaultRequest::path`]
+    #[n(0)] Software,
+    /// Secrets stored in a hardware security module, through its PKCS#11 library
+    #[n(1)] Pkcs11 {
+        #[n(0)] module: String,
+        #[n(1)] slot: u64,
+    },
+    /// Secrets stored in the AWS Key Management Service
+    #[n(2)] AwsKms {
+        #[n(0)] region: String,
+    },
+}
+
+impl fmt::Display for VaultBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Software => "software",
+            Self::Pkcs11 { .. } => "pkcs11",
+            Self::AwsKms { .. } => "aws-kms",
+        })
+    }
+}
+
 /// Request body when instructing a node to create a Vault
 #[derive(Debug, Clone, Decode, Encode)]
 #[rustfmt::skip]
@@ -12,6 +43,10 @@ pub struct CreateVaultRequest<'a> {
     #[cfg(feature = "tag")]
     #[n(0)] tag: TypeTag<8008758>,
     #[b(1)] pub path: Option<CowStr<'a>>,
+    #[n(2)] pub backend: Option<VaultBackend>,
+    /// Record the vault under this name rather than making it the node's
+    /// vault, see [`CreateIdentityRequest`](super::identity::CreateIdentityRequest)
+    #[b(3)] pub name: Option<CowStr<'a>>,
 }
 
 impl<'a> CreateVaultRequest<'a> {
@@ -20,6 +55,18 @@ impl<'a> CreateVaultRequest<'a> {
             #[cfg(feature = "tag")]
             tag: TypeTag,
             path: path.map(|p| p.into()),
+            backend: None,
+            name: None,
         }
     }
+
+    pub fn with_backend(mut self, backend: VaultBackend) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
+    pub fn with_name(mut self, name: impl Into<CowStr<'a>>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
 }
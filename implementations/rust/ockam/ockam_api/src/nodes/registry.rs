@@ -1,7 +1,7 @@
 use crate::nodes::service::Alias;
 use ockam_core::compat::collections::BTreeMap;
 use ockam_core::{Address, Route};
-use ockam_identity::IdentityIdentifier;
+use ockam_identity::{IdentityIdentifier, ReloadableTrustPolicy};
 
 #[derive(Default)]
 pub(crate) struct SecureChannelRegistry {
@@ -31,6 +31,13 @@ impl SecureChannelRegistry {
         self.channels.retain(|x| x.addr() != addr)
     }
 
+    /// Attach `label` to the channel at `addr`, replacing its previous label
+    pub fn set_label(&mut self, addr: &Address, label: String) {
+        if let Some(channel) = self.channels.iter_mut().find(|x| x.addr() == addr) {
+            channel.label = Some(label)
+        }
+    }
+
     pub fn list(&self) -> &[SecureChannelInfo] {
         &self.channels
     }
@@ -43,6 +50,8 @@ pub struct SecureChannelInfo {
     // Local address of the created channel
     addr: Address,
     authorized_identifiers: Option<Vec<IdentityIdentifier>>,
+    // Label given by the operator to identify the channel, not unique
+    label: Option<String>,
 }
 
 impl SecureChannelInfo {
@@ -55,6 +64,7 @@ impl SecureChannelInfo {
             addr,
             route,
             authorized_identifiers,
+            label: None,
         }
     }
 
@@ -69,10 +79,56 @@ impl SecureChannelInfo {
     pub fn authorized_identifiers(&self) -> Option<&Vec<IdentityIdentifier>> {
         self.authorized_identifiers.as_ref()
     }
+
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
 }
 
-#[derive(Default)]
-pub(crate) struct SecureChannelListenerInfo {}
+pub(crate) struct SecureChannelListenerInfo {
+    trust_policy: String,
+    authorized_identifiers: Option<AuthorizedIdentifiers>,
+    pre_shared_key: bool,
+}
+
+/// The identifiers trusted by a listener, and its policy trusting them
+pub(crate) struct AuthorizedIdentifiers {
+    pub(crate) identifiers: Vec<IdentityIdentifier>,
+    pub(crate) trust_policy: ReloadableTrustPolicy,
+}
+
+impl SecureChannelListenerInfo {
+    pub(crate) fn new(
+        trust_policy: String,
+        authorized_identifiers: Option<AuthorizedIdentifiers>,
+        pre_shared_key: bool,
+    ) -> Self {
+        Self {
+            trust_policy,
+            authorized_identifiers,
+            pre_shared_key,
+        }
+    }
+
+    /// Which initiators the listener trusts
+    pub(crate) fn trust_policy(&self) -> &str {
+        &self.trust_policy
+    }
+
+    /// Whether the listener requires the node's pre-shared key
+    pub(crate) fn pre_shared_key(&self) -> bool {
+        self.pre_shared_key
+    }
+
+    pub(crate) fn set_trust_policy(&mut self, trust_policy: String) {
+        self.trust_policy = trust_policy
+    }
+
+    /// The identifiers trusted by the listener, if it trusts identifiers
+    pub(crate) fn authorized_identifiers_mut(&mut self) -> Option<&mut AuthorizedIdentifiers> {
+        self.authorized_identifiers.as_mut()
+    }
+}
 
 #[derive(Default)]
 pub(crate) struct VaultServiceInfo {}
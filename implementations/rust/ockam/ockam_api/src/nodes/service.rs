@@ -11,17 +11,24 @@ use ockam_core::compat::{
     sync::{Arc, Mutex},
 };
 use ockam_core::errcode::{Kind, Origin};
+use ockam_core::vault::KeyId;
 use ockam_core::AsyncTryClone;
+use ockam_identity::authenticated_storage::mem::InMemoryStorage;
+use ockam_identity::authenticated_storage::quota::{QuotaStorage, StorageQuota};
+use ockam_identity::authenticated_storage::{AuthenticatedStorage, SharedAuthenticatedStorage};
+use ockam_identity::credential::Timestamp;
 use ockam_identity::{Identity, IdentityIdentifier, PublicIdentity};
 use ockam_multiaddr::proto::{Project, Secure};
 use ockam_multiaddr::{MultiAddr, Protocol};
 use ockam_node::tokio;
 use ockam_node::tokio::task::JoinHandle;
+#[cfg(unix)]
+use ockam_transport_uds::UdsTransport;
 use ockam_vault::storage::FileStorage;
 use ockam_vault::Vault;
 use std::collections::BTreeMap;
 use std::error::Error as _;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use super::models::secure_channel::CredentialExchangeMode;
@@ -31,12 +38,15 @@ use crate::config::lookup::ProjectLookup;
 use crate::error::ApiError;
 use crate::lmdb::LmdbStorage;
 use crate::nodes::config::NodeConfig;
-use crate::nodes::models::base::NodeStatus;
+use crate::nodes::models::base::{MetricsStatus, NodeStatus, WorkerList, WorkerStatus};
 use crate::nodes::models::transport::{TransportMode, TransportType};
+use crate::nodes::models::vault::VaultBackend;
 use crate::session::util::starts_with_host_tcp_secure;
 use crate::session::{Medic, Sessions};
+use crate::storage_encryption::{StorageEncryption, STORAGE_KEYS_FILE};
 use crate::{multiaddr_to_route, try_address_to_multiaddr, DefaultAddress};
 
+pub mod builder;
 pub mod message;
 
 mod credentials;
@@ -48,6 +58,9 @@ mod services;
 mod transport;
 mod vault;
 
+pub use credentials::{CredentialChecks, CredentialOutagePolicy};
+use credentials::{CredentialRefresher, CREDENTIAL_RETRY_INTERVAL};
+
 const TARGET: &str = "ockam_api::nodemanager::service";
 
 pub(crate) type Alias = String;
@@ -103,15 +116,27 @@ pub struct NodeManager {
     api_transport_id: Alias,
     transports: BTreeMap<Alias, (TransportType, TransportMode, String)>,
     tcp_transport: TcpTransport,
+    #[cfg(unix)]
+    uds_transport: Option<UdsTransport>,
     pub(crate) controller_identity_id: IdentityIdentifier,
     skip_defaults: bool,
-    enable_credential_checks: bool,
+    credential_checks: CredentialChecks,
+    credential_outage_policy: CredentialOutagePolicy,
+    // The identity's credential was loaded from the node state, not issued just now
+    credential_from_cache: bool,
+    // Expiry of the credential issued to the identity by the authority, if any
+    credential_expires_at: Option<Timestamp>,
+    // Get a credential in the background once the node is started
+    prefetch_credential: bool,
     vault: Option<Vault>,
     identity: Option<Identity<Vault>>,
     project_id: Option<String>,
     projects: Arc<BTreeMap<String, ProjectLookup>>,
     authorities: Option<Authorities>,
-    pub(crate) authenticated_storage: LmdbStorage,
+    pub(crate) authenticated_storage: SharedAuthenticatedStorage,
+    /// Keys encrypting the values of the authenticated storage, with the
+    /// storage holding the encrypted values
+    storage_encryption: Option<(StorageEncryption, SharedAuthenticatedStorage)>,
     pub(crate) registry: Registry,
     sessions: Arc<Mutex<Sessions>>,
     medic: JoinHandle<Result<(), ockam_core::Error>>,
@@ -136,6 +161,9 @@ impl NodeManagerWorker {
 pub struct IdentityOverride {
     pub identity: Vec<u8>,
     pub vault_path: PathBuf,
+    /// Use the vault at `vault_path` rather than a copy of it in the
+    /// node's directory, so that its keys stay in a single place
+    pub shared_vault: bool,
 }
 
 impl NodeManager {
@@ -169,9 +197,17 @@ pub struct NodeManagerGeneralOptions {
     node_name: String,
     node_dir: PathBuf,
     skip_defaults: bool,
-    enable_credential_checks: bool,
+    credential_checks: CredentialChecks,
+    credential_outage_policy: CredentialOutagePolicy,
     // Should be passed only when creating fresh node and we want it to get default root Identity
     identity_override: Option<IdentityOverride>,
+    // In-memory storage is used when not set, see [`NodeManager::node_dir_authenticated_storage`]
+    authenticated_storage: Option<SharedAuthenticatedStorage>,
+    // Imported into the node's vault when the node is created
+    pre_shared_key: Option<Vec<u8>>,
+    read_only_identity: bool,
+    prefetch_credential: bool,
+    storage_quota: Option<StorageQuota>,
 }
 
 impl NodeManagerGeneralOptions {
@@ -179,17 +215,57 @@ impl NodeManagerGeneralOptions {
         node_name: String,
         node_dir: PathBuf,
         skip_defaults: bool,
-        enable_credential_checks: bool,
+        credential_checks: CredentialChecks,
         identity_override: Option<IdentityOverride>,
+        authenticated_storage: Option<SharedAuthenticatedStorage>,
     ) -> Self {
         Self {
             node_name,
             node_dir,
             skip_defaults,
-            enable_credential_checks,
+            credential_checks,
+            credential_outage_policy: CredentialOutagePolicy::default(),
             identity_override,
+            authenticated_storage,
+            pre_shared_key: None,
+            read_only_identity: false,
+            prefetch_credential: false,
+            storage_quota: None,
         }
     }
+
+    /// What to do when the project authority is unreachable, fail closed by default
+    pub fn with_credential_outage_policy(mut self, policy: CredentialOutagePolicy) -> Self {
+        self.credential_outage_policy = policy;
+        self
+    }
+
+    /// Require this pre-shared key on the node's default secure channel
+    /// listener, and on the channels created or listeners started with it
+    pub fn with_pre_shared_key(mut self, key: Vec<u8>) -> Self {
+        self.pre_shared_key = Some(key);
+        self
+    }
+
+    /// Forbid changes to the change history of the node's identity, such
+    /// as key rotations, for as long as the node exists
+    pub fn with_read_only_identity(mut self) -> Self {
+        self.read_only_identity = true;
+        self
+    }
+
+    /// Get a credential from the project authority when the node starts,
+    /// rather than when its first secure channel needs one
+    pub fn with_credential_prefetch(mut self) -> Self {
+        self.prefetch_credential = true;
+        self
+    }
+
+    /// Reject the writes taking the node's authenticated storage past `quota`
+    pub fn with_storage_quota(mut self, quota: StorageQuota) -> Self {
+        self.storage_quota = Some(quota);
+        self
+    }
 }
 
 pub struct NodeManagerProjectsOptions<'a> {
@@ -215,6 +291,8 @@ impl<'a> NodeManagerProjectsOptions<'a> {
 pub struct NodeManagerTransportOptions {
     api_transport: (TransportType, TransportMode, String),
     tcp_transport: TcpTransport,
+    #[cfg(unix)]
+    uds_listener: Option<(UdsTransport, PathBuf)>,
 }
 
 impl NodeManagerTransportOptions {
@@ -225,11 +303,50 @@ impl NodeManagerTransportOptions {
         Self {
             api_transport,
             tcp_transport,
+            #[cfg(unix)]
+            uds_listener: None,
         }
     }
+
+    /// Register `uds_transport`, listening on the socket at `path`, next
+    /// to the TCP transport
+    #[cfg(unix)]
+    pub fn with_uds_transport(mut self, uds_transport: UdsTransport, path: PathBuf) -> Self {
+        self.uds_listener = Some((uds_transport, path));
+        self
+    }
 }
 
 impl NodeManager {
+    /// LMDB storage persisted in `node_dir`, as used by nodes created with the ockam CLI
+    ///
+    /// The values of new storages are encrypted, see [`StorageEncryption`].
+    /// Storages created before nodes encrypted them are left as they are.
+    pub async fn node_dir_authenticated_storage(
+        node_dir: &Path,
+    ) -> Result<SharedAuthenticatedStorage> {
+        let config = NodeConfig::new(node_dir).map_err(map_anyhow_err)?;
+        let state = config.state();
+
+        // Check if we had existing AuthenticatedStorage, create with default location otherwise
+        let authenticated_storage_path = state.read().authenticated_storage_path.clone();
+        let authenticated_storage_path = match authenticated_storage_path {
+            Some(p) => p,
+            None => {
+                let default_location = node_dir.join("authenticated_storage.lmdb");
+                StorageEncryption::create(node_dir.join(STORAGE_KEYS_FILE))?;
+
+                state.write().authenticated_storage_path = Some(default_location.clone());
+                state.persist_config_updates().map_err(map_anyhow_err)?;
+
+                default_location
+            }
+        };
+        Ok(SharedAuthenticatedStorage::new(
+            LmdbStorage::new(&authenticated_storage_path).await?,
+        ))
+    }
+
     /// Create a new NodeManager with the node name from the ockam CLI
     pub async fn create(
         ctx: &Context,
@@ -240,35 +357,66 @@ impl NodeManager {
         let api_transport_id = random_alias();
         let mut transports = BTreeMap::new();
         transports.insert(api_transport_id.clone(), transport_options.api_transport);
+        #[cfg(unix)]
+        let uds_transport = match transport_options.uds_listener {
+            Some((uds_transport, path)) => {
+                transports.insert(
+                    random_alias(),
+                    (
+                        TransportType::Uds,
+                        TransportMode::Listen,
+                        path.display().to_string(),
+                    ),
+                );
+                Some(uds_transport)
+            }
+            None => None,
+        };
 
         let config = NodeConfig::new(&general_options.node_dir).map_err(map_anyhow_err)?;
         let state = config.state();
 
-        // Check if we had existing AuthenticatedStorage, create with default location otherwise
-        let authenticated_storage_path = state.read().authenticated_storage_path.clone();
-        let authenticated_storage = {
-            let authenticated_storage_path = match authenticated_storage_path {
-                Some(p) => p,
-                None => {
-                    let default_location =
-                        general_options.node_dir.join("authenticated_storage.lmdb");
-
-                    state.write().authenticated_storage_path = Some(default_location.clone());
-                    state.persist_config_updates().map_err(map_anyhow_err)?;
-
-                    default_location
+        let storage_encryption = match &general_options.authenticated_storage {
+            Some(storage) => {
+                StorageEncryption::open(general_options.node_dir.join(STORAGE_KEYS_FILE))?
+                    .map(|encryption| (encryption, storage.clone()))
+            }
+            None => None,
+        };
+        let authenticated_storage: SharedAuthenticatedStorage =
+            match (&storage_encryption, general_options.authenticated_storage) {
+                (Some((encryption, _)), Some(storage)) => {
+                    SharedAuthenticatedStorage::new(encryption.storage(storage))
+                }
+                (None, Some(storage)) => storage,
+                (_, None) => {
+                    warn!(
+                        node = %general_options.node_name,
+                        "No authenticated storage was given, using an in-memory one: \
+                         the identities and attributes learned by the node are lost when it stops"
+                    );
+                    SharedAuthenticatedStorage::new(InMemoryStorage::new())
                 }
             };
-            LmdbStorage::new(&authenticated_storage_path).await?
+        let authenticated_storage = match general_options.storage_quota {
+            Some(quota) => {
+                SharedAuthenticatedStorage::new(QuotaStorage::new(authenticated_storage, quota))
+            }
+            None => authenticated_storage,
         };
 
         // Skip override if we already had vault
         if state.read().vault_path.is_none() {
             if let Some(identity_override) = general_options.identity_override {
-                // Copy vault file, update config
-                let vault_path = Self::default_vault_path(&general_options.node_dir);
-                std::fs::copy(&identity_override.vault_path, &vault_path)
-                    .map_err(|_| ApiError::generic("Error while copying default node"))?;
+                // Copy vault file unless it is shared, update config
+                let vault_path = if identity_override.shared_vault {
+                    identity_override.vault_path
+                } else {
+                    let vault_path = Self::default_vault_path(&general_options.node_dir);
+                    std::fs::copy(&identity_override.vault_path, &vault_path)
+                        .map_err(|_| ApiError::generic("Error while copying default node"))?;
+                    vault_path
+                };
 
                 state.write().vault_path = Some(vault_path);
                 state.write().identity = Some(identity_override.identity);
@@ -279,6 +427,9 @@ impl NodeManager {
         }
 
         // Check if we had existing Vault
+        if let Some(backend) = &state.read().vault_backend {
+            Self::check_vault_backend(backend)?;
+        }
         let vault_path = state.read().vault_path.clone();
         let vault = match vault_path {
             Some(vault_path) => {
@@ -290,6 +441,11 @@ impl NodeManager {
             None => None,
         };
 
+        if general_options.read_only_identity && !state.read().read_only_identity {
+            state.write().read_only_identity = true;
+            state.persist_config_updates().map_err(map_anyhow_err)?;
+        }
+
         // Check if we had existing Identity
         let identity_info = state.read().identity.clone();
         let identity = match identity_info {
@@ -299,12 +455,17 @@ impl NodeManager {
             },
             None => None,
         };
+        if let Some(identity) = &identity {
+            identity.set_read_only(state.read().read_only_identity);
+        }
 
-        if general_options.enable_credential_checks
+        if general_options.credential_checks.is_on()
             && (projects_options.ac.is_none() || projects_options.project_id.is_none())
         {
-            error!("Invalid NodeManager options: enable_credential_checks was provided, while not enough \
-                information was provided to enforce the checks");
+            error!(
+                "Invalid NodeManager options: credential checks are on, while not enough \
+                information was provided to enforce the checks"
+            );
             return Err(ockam_core::Error::new(
                 Origin::Ockam,
                 Kind::Invalid,
@@ -322,15 +483,22 @@ impl NodeManager {
             api_transport_id,
             transports,
             tcp_transport: transport_options.tcp_transport,
+            #[cfg(unix)]
+            uds_transport,
             controller_identity_id: Self::load_controller_identity_id()?,
             skip_defaults: general_options.skip_defaults,
-            enable_credential_checks: general_options.enable_credential_checks,
+            credential_checks: general_options.credential_checks,
+            credential_outage_policy: general_options.credential_outage_policy,
+            credential_from_cache: false,
+            credential_expires_at: None,
+            prefetch_credential: general_options.prefetch_credential,
             vault,
             identity,
             projects: Arc::new(projects_options.projects),
             project_id: projects_options.project_id,
             authorities: None,
             authenticated_storage,
+            storage_encryption,
             registry: Default::default(),
             medic: {
                 let ctx = ctx.async_try_clone().await?;
@@ -347,6 +515,15 @@ impl NodeManager {
             }
         }
 
+        if let Some(key) = general_options.pre_shared_key {
+            s.import_pre_shared_key(&key).await?;
+        }
+
+        // Resume the rotation of the storage key the node was stopped in
+        if matches!(&s.storage_encryption, Some((encryption, _)) if encryption.is_rotating()) {
+            s.rotate_storage_key_impl()?;
+        }
+
         s.start_echoer_service_impl(ctx, DefaultAddress::ECHO_SERVICE.into())
             .await?;
 
@@ -370,10 +547,30 @@ impl NodeManager {
         Ok(())
     }
 
+    /// Import `key` into the node's vault, for its listeners and the
+    /// channels created with the pre-shared key
+    async fn import_pre_shared_key(&mut self, key: &[u8]) -> Result<()> {
+        let key_id = ockam_identity::import_pre_shared_key(self.vault()?, key).await?;
+
+        self.config.state().write().pre_shared_key = Some(key_id);
+        self.config
+            .state()
+            .persist_config_updates()
+            .map_err(map_anyhow_err)?;
+
+        Ok(())
+    }
+
+    /// Vault key id of the pre-shared key the node was bootstrapped with, if any
+    pub(crate) fn pre_shared_key(&self) -> Option<KeyId> {
+        self.config.state().read().pre_shared_key.clone()
+    }
+
     async fn create_defaults(&mut self, ctx: &Context) -> Result<()> {
         // Create default vault and identity, if they don't exists already
-        self.create_vault_impl(None, true).await?;
-        self.create_identity_impl(ctx, true).await?;
+        self.create_vault_impl(None, VaultBackend::Software, true)
+            .await?;
+        self.create_identity_impl(ctx, true, None).await?;
 
         Ok(())
     }
@@ -391,9 +588,13 @@ impl NodeManager {
 
         ForwardingService::create(ctx).await?;
 
+        // Nodes created with a pre-shared key require it on their default
+        // listener only, other listeners require it when asked to
         self.create_secure_channel_listener_impl(
             DefaultAddress::SECURE_CHANNEL_LISTENER.into(),
             None, // Not checking identifiers here in favor of credentials check
+            false,
+            self.pre_shared_key().is_some(),
         )
         .await?;
 
@@ -516,13 +717,55 @@ impl NodeManagerWorker {
                     ))
                     .to_vec()?
             }
+            (Get, ["node", "workers"]) => Response::ok(req.id())
+                .body(WorkerList::new(
+                    ctx.list_worker_info()
+                        .await?
+                        .into_iter()
+                        .map(WorkerStatus::from)
+                        .collect(),
+                ))
+                .to_vec()?,
+            (Get, ["node", "metrics"]) => {
+                let node_manager = self.node_manager.read().await;
+                let metrics = node_manager.tcp_transport.metrics();
+                Response::ok(req.id())
+                    .body(MetricsStatus::new(&metrics, metrics.counters()))
+                    .to_vec()?
+            }
+            // Returns the counters as they were when reset, so that none of
+            // the traffic is missed by a monitoring loop computing deltas
+            (Post, ["node", "metrics", "actions", "reset"]) => {
+                let node_manager = self.node_manager.read().await;
+                let metrics = node_manager.tcp_transport.metrics();
+                info!("Resetting the metrics counters of the node");
+                Response::ok(req.id())
+                    .body(MetricsStatus::new(&metrics, metrics.reset_counters()))
+                    .to_vec()?
+            }
 
             // ==*== Tcp Connection ==*==
             // TODO: Get all tcp connections
             (Get, ["node", "tcp", "connection"]) => {
                 let node_manager = self.node_manager.read().await;
-                self.get_tcp_con_or_list(req, &node_manager.transports, TransportMode::Connect)
-                    .to_vec()?
+                self.get_tcp_con_or_list(
+                    req,
+                    &node_manager.transports,
+                    &node_manager.tcp_transport,
+                    &node_manager.registry,
+                    TransportMode::Connect,
+                )
+                .to_vec()?
+            }
+            (Get, ["node", "tcp", "connection", tid]) => {
+                let node_manager = self.node_manager.read().await;
+                self.get_tcp_connection(
+                    req,
+                    &node_manager.transports,
+                    &node_manager.tcp_transport,
+                    &node_manager.registry,
+                    tid,
+                )?
             }
             (Post, ["node", "tcp", "connection"]) => {
                 self.add_transport(req, dec).await?.to_vec()?
@@ -537,6 +780,8 @@ impl NodeManagerWorker {
                 self.get_tcp_con_or_list(
                     req,
                     &node_manager.transports.clone(),
+                    &node_manager.tcp_transport,
+                    &node_manager.registry,
                     TransportMode::Listen,
                 )
                 .to_vec()?
@@ -548,9 +793,10 @@ impl NodeManagerWorker {
 
             // ==*== Vault ==*==
             (Post, ["node", "vault"]) => self.create_vault(req, dec).await?.to_vec()?,
+            (Post, ["node", "vault", "rotate"]) => self.rotate_storage_key(req).await?.to_vec()?,
 
             // ==*== Identity ==*==
-            (Post, ["node", "identity"]) => self.create_identity(ctx, req).await?.to_vec()?,
+            (Post, ["node", "identity"]) => self.create_identity(ctx, req, dec).await?.to_vec()?,
             (Post, ["node", "identity", "actions", "show", "short"]) => {
                 self.short_identity(req).await?.to_vec()?
             }
@@ -578,6 +824,11 @@ impl NodeManagerWorker {
                 self.list_secure_channel_listener(req, &node_manager.registry)
                     .to_vec()?
             }
+            (Get, ["node", "secure_channel_listener", "status"]) => {
+                let node_manager = self.node_manager.read().await;
+                self.list_secure_channel_listener_status(req, &node_manager.registry)
+                    .to_vec()?
+            }
             (Post, ["node", "secure_channel"]) => {
                 self.create_secure_channel(req, dec).await?.to_vec()?
             }
@@ -591,6 +842,10 @@ impl NodeManagerWorker {
                 .create_secure_channel_listener(req, dec)
                 .await?
                 .to_vec()?,
+            (Put, ["node", "secure_channel_listener"]) => self
+                .update_secure_channel_listener(req, dec)
+                .await?
+                .to_vec()?,
 
             // ==*== Services ==*==
             (Post, ["node", "services", "vault"]) => {
@@ -722,6 +977,16 @@ impl Worker for NodeManagerWorker {
         if !node_manger.skip_defaults {
             node_manger.initialize_defaults(ctx).await?;
         }
+        let prefetch_credential = node_manger.prefetch_credential;
+        drop(node_manger);
+
+        // The node doesn't wait for the authority to start, its channels
+        // ask for a credential until the refresher got one
+        if prefetch_credential {
+            let refresher =
+                CredentialRefresher::new(self.node_manager.clone(), CREDENTIAL_RETRY_INTERVAL);
+            ctx.start_worker(Address::random_local(), refresher).await?;
+        }
 
         Ok(())
     }
@@ -774,47 +1039,601 @@ impl Worker for NodeManagerWorker {
 
 #[cfg(test)]
 pub(crate) mod tests {
+    use crate::nodes::models::secure_channel::{
+        CreateSecureChannelListenerRequest, CreateSecureChannelRequest,
+        CreateSecureChannelResponse, ShowSecureChannelRequest, ShowSecureChannelResponse,
+        UpdateSecureChannelListenerRequest,
+    };
+    use crate::nodes::models::transport::{CreateTransport, TransportList, TransportStatus};
     use crate::nodes::NodeManager;
-    use ockam::{route, Route};
+    use minicbor::Encode;
+    use ockam::{route, Route, TCP};
+    use ockam_core::api::RequestBuilder;
+    use ockam_identity::TrustEveryonePolicy;
 
     use super::*;
 
     impl NodeManager {
         pub(crate) async fn test_create(ctx: &Context) -> Result<Route> {
-            let node_dir = tempfile::tempdir().unwrap();
+            Self::test_create_with_transports(ctx, |options| options).await
+        }
+
+        /// Create a node manager whose transport options are changed by
+        /// `with_transports`, returning its route
+        pub(crate) async fn test_create_with_transports(
+            ctx: &Context,
+            with_transports: impl FnOnce(NodeManagerTransportOptions) -> NodeManagerTransportOptions,
+        ) -> Result<Route> {
             let node_manager = "manager";
+            let mut node_man = Self::test_create_without_identity(ctx, with_transports).await?;
+
+            // Initialize identity
+            node_man
+                .create_vault_impl(None, VaultBackend::Software, false)
+                .await?;
+            node_man.create_identity_impl(ctx, false, None).await?;
+
+            let node_manager_worker = NodeManagerWorker::new(node_man);
+
+            // Initialize node_man worker and return its route
+            ctx.start_worker(node_manager, node_manager_worker).await?;
+            Ok(route![node_manager])
+        }
+
+        /// Create a node manager without a vault nor an identity, which
+        /// isn't started
+        pub(crate) async fn test_create_without_identity(
+            ctx: &Context,
+            with_transports: impl FnOnce(NodeManagerTransportOptions) -> NodeManagerTransportOptions,
+        ) -> Result<NodeManager> {
+            let node_dir = tempfile::tempdir().unwrap();
             let transport = TcpTransport::create(ctx).await?;
             let node_address = transport.listen("127.0.0.1:0").await?;
-            let mut node_man = NodeManager::create(
+            NodeManager::create(
                 ctx,
                 NodeManagerGeneralOptions::new(
                     "node".to_string(),
                     node_dir.into_path(),
                     true,
-                    false,
+                    CredentialChecks::Off,
+                    None,
                     None,
                 ),
                 NodeManagerProjectsOptions::new(None, None, Default::default()),
-                NodeManagerTransportOptions::new(
+                with_transports(NodeManagerTransportOptions::new(
                     (
                         TransportType::Tcp,
                         TransportMode::Listen,
                         node_address.to_string(),
                     ),
                     transport,
+                )),
+            )
+            .await
+        }
+    }
+
+    async fn send_request<T: Encode<()>>(
+        ctx: &Context,
+        route: Route,
+        req: RequestBuilder<'_, T>,
+    ) -> Result<Vec<u8>> {
+        let mut buf = vec![];
+        req.encode(&mut buf)?;
+        let response: Vec<u8> = ctx.send_and_receive(route, buf).await?;
+        let mut dec = Decoder::new(&response);
+        let header = dec.decode::<Response>()?;
+        assert_eq!(header.status(), Some(Status::Ok));
+        Ok(response)
+    }
+
+    async fn list_workers(ctx: &Context, route: Route) -> Result<Vec<Vec<String>>> {
+        let response = send_request(ctx, route, Request::get("/node/workers")).await?;
+        let mut dec = Decoder::new(&response);
+        dec.decode::<Response>()?;
+        let workers = dec.decode::<WorkerList>()?;
+        Ok(workers
+            .list
+            .iter()
+            .map(|w| w.addresses.iter().map(|a| a.to_string()).collect())
+            .collect())
+    }
+
+    #[ockam_macros::test]
+    async fn list_workers_shows_secure_channel_workers(ctx: &mut Context) -> Result<()> {
+        let route = NodeManager::test_create(ctx).await?;
+        let listener: Address = "listener".into();
+        send_request(
+            ctx,
+            route.clone(),
+            Request::post("/node/secure_channel_listener").body(
+                CreateSecureChannelListenerRequest::new(&listener, None, false),
+            ),
+        )
+        .await?;
+        let before = list_workers(ctx, route.clone()).await?;
+        assert!(before.iter().any(|w| w[0] == listener.to_string()));
+
+        let response = send_request(
+            ctx,
+            route.clone(),
+            Request::post("/node/secure_channel").body(CreateSecureChannelRequest::new(
+                &"/service/listener".parse().unwrap(),
+                None,
+                CredentialExchangeMode::None,
+            )),
+        )
+        .await?;
+        let mut dec = Decoder::new(&response);
+        dec.decode::<Response>()?;
+        let encryptor = dec
+            .decode::<CreateSecureChannelResponse>()?
+            .addr
+            .to_string();
+
+        let after = list_workers(ctx, route).await?;
+        let new: Vec<_> = after.iter().filter(|w| !before.contains(w)).collect();
+        assert!(new.iter().any(|w| w.contains(&encryptor)));
+        // Both ends of the channel run in this node, each with a decryptor
+        // next to its encryptor
+        assert!(new.iter().filter(|w| !w.contains(&encryptor)).count() >= 3);
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn show_tcp_connection(ctx: &mut Context) -> Result<()> {
+        let route = NodeManager::test_create(ctx).await?;
+
+        // Connect the node to its own listener
+        let response = send_request(ctx, route.clone(), Request::get("/node/tcp/listener")).await?;
+        let mut dec = Decoder::new(&response);
+        dec.decode::<Response>()?;
+        let listener = dec.decode::<TransportList>()?.list[0].payload.to_string();
+        let response = send_request(
+            ctx,
+            route.clone(),
+            Request::post("/node/tcp/connection").body(CreateTransport::new(
+                TransportType::Tcp,
+                TransportMode::Connect,
+                listener.as_str(),
+            )),
+        )
+        .await?;
+        let mut dec = Decoder::new(&response);
+        dec.decode::<Response>()?;
+        let tid = dec.decode::<TransportStatus>()?.tid.to_string();
+
+        let path = format!("/node/tcp/connection/{}", tid);
+        let response = send_request(ctx, route.clone(), Request::get(path.as_str())).await?;
+        let mut dec = Decoder::new(&response);
+        dec.decode::<Response>()?;
+        let status = dec.decode::<TransportStatus>()?;
+        assert_eq!(status.tid, tid);
+        assert_eq!(status.tm, TransportMode::Connect);
+        assert_eq!(status.payload, listener);
+        assert!(status.bytes_in.is_some());
+        assert!(status.bytes_out.is_some());
+        assert!(status.connected_at.is_some());
+
+        // Unknown connections are not found
+        let mut buf = vec![];
+        Request::get("/node/tcp/connection/unknown").encode(&mut buf)?;
+        let response: Vec<u8> = ctx.send_and_receive(route, buf).await?;
+        let header = Decoder::new(&response).decode::<Response>()?;
+        assert_eq!(header.status(), Some(Status::NotFound));
+
+        ctx.stop().await
+    }
+
+    /// Path of a socket file in the temporary directory, unique to a test
+    #[cfg(unix)]
+    fn socket_path() -> PathBuf {
+        let suffix: u64 = ockam_core::compat::rand::random();
+        std::env::temp_dir().join(format!("ockam-api-{}.sock", suffix))
+    }
+
+    #[cfg(unix)]
+    #[ockam_macros::test]
+    async fn create_uds_transport_without_uds(ctx: &mut Context) -> Result<()> {
+        let route = NodeManager::test_create(ctx).await?;
+        let mut buf = vec![];
+        Request::post("/node/tcp/connection")
+            .body(CreateTransport::new(
+                TransportType::Uds,
+                TransportMode::Listen,
+                socket_path().display().to_string(),
+            ))
+            .encode(&mut buf)?;
+        let response: Vec<u8> = ctx.send_and_receive(route.clone(), buf).await?;
+        let header = Decoder::new(&response).decode::<Response>()?;
+        assert_eq!(header.status(), Some(Status::BadRequest));
+
+        ctx.stop().await
+    }
+
+    #[cfg(unix)]
+    #[ockam_macros::test]
+    async fn create_uds_transports(ctx: &mut Context) -> Result<()> {
+        use ockam_transport_uds::UDS;
+
+        let uds = UdsTransport::create(ctx).await?;
+        let route = NodeManager::test_create_with_transports(ctx, |options| {
+            options.with_uds_transport(uds, socket_path())
+        })
+        .await?;
+
+        // Listen on another socket, then connect to it
+        let path = socket_path().display().to_string();
+        let mut worker_addr = None;
+        for mode in [TransportMode::Listen, TransportMode::Connect] {
+            let response = send_request(
+                ctx,
+                route.clone(),
+                Request::post("/node/tcp/connection").body(CreateTransport::new(
+                    TransportType::Uds,
+                    mode,
+                    path.as_str(),
+                )),
+            )
+            .await?;
+            let mut dec = Decoder::new(&response);
+            dec.decode::<Response>()?;
+            let status = dec.decode::<TransportStatus>()?;
+            assert!(matches!(status.tt, TransportType::Uds));
+            assert_eq!(status.tm, mode);
+            assert_eq!(status.payload, path);
+            if mode == TransportMode::Connect {
+                worker_addr = status.worker_addr.map(|a| a.to_string());
+            }
+        }
+
+        // Messages go over the socket, by its path or through the sender
+        // of the connection
+        let worker_addr: Address = worker_addr.expect("the sender of the connection").into();
+        let mut child = ctx.new_detached(Address::random_local()).await?;
+        for r in [
+            route![(UDS, path.as_str()), child.address()],
+            route![worker_addr, child.address()],
+        ] {
+            child.send(r, "Hello".to_string()).await?;
+            let msg = child.receive::<String>().await?;
+            assert_eq!(msg.take().body(), "Hello");
+        }
+
+        ctx.stop().await
+    }
+
+    async fn node_metrics(
+        ctx: &Context,
+        route: Route,
+        req: RequestBuilder<'_, ()>,
+    ) -> Result<MetricsStatus> {
+        let response = send_request(ctx, route, req).await?;
+        let mut dec = Decoder::new(&response);
+        dec.decode::<Response>()?;
+        Ok(dec.decode::<MetricsStatus>()?)
+    }
+
+    #[ockam_macros::test]
+    async fn reset_metrics_counters(ctx: &mut Context) -> Result<()> {
+        let route = NodeManager::test_create(ctx).await?;
+        let response = send_request(ctx, route.clone(), Request::get("/node/tcp/listener")).await?;
+        let mut dec = Decoder::new(&response);
+        dec.decode::<Response>()?;
+        let listener = dec.decode::<TransportList>()?.list[0].payload.to_string();
+
+        // Send messages to the node over its own TCP listener
+        let mut child = ctx.new_detached(Address::random_local()).await?;
+        for _ in 0..3 {
+            child
+                .send(
+                    route![(TCP, listener.as_str()), child.address()],
+                    "Hello".to_string(),
+                )
+                .await?;
+            child.receive::<String>().await?;
+        }
+
+        let before = node_metrics(ctx, route.clone(), Request::get("/node/metrics")).await?;
+        assert_eq!(before.tcp_messages_sent, 3);
+        assert_eq!(before.tcp_messages_received, 3);
+        assert!(before.tcp_bytes_sent > 0);
+        assert_eq!(before.tcp_bytes_sent, before.tcp_bytes_received);
+        assert_eq!(before.tcp_active_connections, 2);
+
+        // Resetting returns the counters as they were when reset
+        let reset = node_metrics(
+            ctx,
+            route.clone(),
+            Request::post("/node/metrics/actions/reset"),
+        )
+        .await?;
+        assert_eq!(reset.tcp_messages_sent, before.tcp_messages_sent);
+        assert_eq!(reset.tcp_bytes_received, before.tcp_bytes_received);
+
+        // The counters start again from zero, the gauges are kept
+        let after = node_metrics(ctx, route.clone(), Request::get("/node/metrics")).await?;
+        assert_eq!(after.tcp_messages_sent, 0);
+        assert_eq!(after.tcp_messages_received, 0);
+        assert_eq!(after.tcp_bytes_sent, 0);
+        assert_eq!(after.tcp_bytes_received, 0);
+        assert_eq!(after.tcp_active_connections, 2);
+
+        child
+            .send(
+                route![(TCP, listener.as_str()), child.address()],
+                "Hello".to_string(),
+            )
+            .await?;
+        child.receive::<String>().await?;
+        let next = node_metrics(ctx, route, Request::get("/node/metrics")).await?;
+        assert_eq!(next.tcp_messages_sent, 1);
+        assert_eq!(next.tcp_messages_received, 1);
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn labeled_secure_channel_is_listed_with_its_label(ctx: &mut Context) -> Result<()> {
+        let route = NodeManager::test_create(ctx).await?;
+        let listener: Address = "listener".into();
+        send_request(
+            ctx,
+            route.clone(),
+            Request::post("/node/secure_channel_listener").body(
+                CreateSecureChannelListenerRequest::new(&listener, None, false),
+            ),
+        )
+        .await?;
+        send_request(
+            ctx,
+            route.clone(),
+            Request::post("/node/secure_channel").body(
+                CreateSecureChannelRequest::new(
+                    &"/service/listener".parse().unwrap(),
+                    None,
+                    CredentialExchangeMode::None,
+                )
+                .with_label("billing"),
+            ),
+        )
+        .await?;
+
+        let response =
+            send_request(ctx, route.clone(), Request::get("/node/secure_channel")).await?;
+        let mut dec = Decoder::new(&response);
+        dec.decode::<Response>()?;
+        let channels = dec.decode::<Vec<String>>()?;
+        assert_eq!(channels.len(), 1);
+
+        let response = send_request(
+            ctx,
+            route,
+            Request::get("/node/show_secure_channel")
+                .body(ShowSecureChannelRequest::new(&channels[0].as_str().into())),
+        )
+        .await?;
+        let mut dec = Decoder::new(&response);
+        dec.decode::<Response>()?;
+        let show = dec.decode::<ShowSecureChannelResponse>()?;
+        assert_eq!(show.label.as_deref(), Some("billing"));
+
+        ctx.stop().await
+    }
+
+    /// Storage provided by an embedder, kept alive across node restarts
+    #[derive(Clone, Default)]
+    struct EmbedderStorage {
+        inner: InMemoryStorage,
+    }
+
+    #[ockam_core::async_trait]
+    impl AuthenticatedStorage for EmbedderStorage {
+        async fn get(&self, id: &str, key: &str) -> Result<Option<Vec<u8>>> {
+            self.inner.get(id, key).await
+        }
+
+        async fn set(&self, id: &str, key: String, val: Vec<u8>) -> Result<()> {
+            self.inner.set(id, key, val).await
+        }
+
+        async fn del(&self, id: &str, key: &str) -> Result<()> {
+            self.inner.del(id, key).await
+        }
+    }
+
+    async fn create_with_storage(
+        ctx: &Context,
+        node_dir: &Path,
+        transport: &TcpTransport,
+        authenticated_storage: Option<SharedAuthenticatedStorage>,
+    ) -> Result<NodeManager> {
+        // The echoer of a previous node manager is still running
+        let _ = ctx.stop_worker(DefaultAddress::ECHO_SERVICE).await;
+        NodeManager::create(
+            ctx,
+            NodeManagerGeneralOptions::new(
+                "node".to_string(),
+                node_dir.to_path_buf(),
+                true,
+                CredentialChecks::Off,
+                None,
+                authenticated_storage,
+            ),
+            NodeManagerProjectsOptions::new(None, None, Default::default()),
+            NodeManagerTransportOptions::new(
+                (
+                    TransportType::Tcp,
+                    TransportMode::Listen,
+                    "127.0.0.1:0".to_string(),
                 ),
+                transport.async_try_clone().await?,
+            ),
+        )
+        .await
+    }
+
+    #[ockam_macros::test]
+    async fn injected_storage_survives_restart(ctx: &mut Context) -> Result<()> {
+        let node_dir = tempfile::tempdir().unwrap();
+        let transport = TcpTransport::create(ctx).await?;
+        let storage = EmbedderStorage::default();
+
+        let node_man = create_with_storage(
+            ctx,
+            node_dir.path(),
+            &transport,
+            Some(SharedAuthenticatedStorage::new(storage.clone())),
+        )
+        .await?;
+        node_man
+            .authenticated_storage
+            .set("member", "role".to_string(), b"admin".to_vec())
+            .await?;
+        drop(node_man);
+
+        // The node comes back up with the embedder's storage
+        let node_man = create_with_storage(
+            ctx,
+            node_dir.path(),
+            &transport,
+            Some(SharedAuthenticatedStorage::new(storage)),
+        )
+        .await?;
+        let role = node_man.authenticated_storage.get("member", "role").await?;
+        assert_eq!(role, Some(b"admin".to_vec()));
+        drop(node_man);
+
+        // Without an injected storage attributes only live in memory
+        let node_man = create_with_storage(ctx, node_dir.path(), &transport, None).await?;
+        let role = node_man.authenticated_storage.get("member", "role").await?;
+        assert_eq!(role, None);
+        drop(node_man);
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn listener_trusts_identifiers_added_at_runtime(ctx: &mut Context) -> Result<()> {
+        let route = NodeManager::test_create(ctx).await?;
+        let vault = Vault::create();
+        let storage = InMemoryStorage::new();
+        let peer = Identity::create(ctx, &vault).await?;
+        let other = Identity::create(ctx, &vault).await?;
+
+        let listener: Address = "listener".into();
+        send_request(
+            ctx,
+            route.clone(),
+            Request::post("/node/secure_channel_listener").body(
+                CreateSecureChannelListenerRequest::new(
+                    &listener,
+                    Some(vec![other.identifier().clone()]),
+                    false,
+                ),
+            ),
+        )
+        .await?;
+
+        let connect = || {
+            peer.create_secure_channel_extended(
+                route![listener.clone()],
+                TrustEveryonePolicy,
+                &storage,
+                Duration::from_secs(2),
             )
+        };
+
+        // The peer isn't trusted by the listener yet
+        assert!(connect().await.is_err());
+
+        send_request(
+            ctx,
+            route,
+            Request::put("/node/secure_channel_listener").body(
+                UpdateSecureChannelListenerRequest::new(
+                    &listener,
+                    vec![peer.identifier().clone()],
+                    vec![],
+                ),
+            ),
+        )
+        .await?;
+        connect().await?;
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn create_identity_with_named_vault(ctx: &mut Context) -> Result<()> {
+        let mut node_man =
+            NodeManager::test_create_without_identity(ctx, |options| options).await?;
+
+        node_man
+            .create_named_vault_impl("v1", None, VaultBackend::Software)
             .await?;
+        assert!(node_man
+            .create_named_vault_impl("v1", None, VaultBackend::Software)
+            .await
+            .is_err());
+        #[cfg(not(feature = "hsm-stub"))]
+        assert!(node_man
+            .create_named_vault_impl(
+                "hsm",
+                None,
+                VaultBackend::Pkcs11 {
+                    module: "libhsm.so".to_string(),
+                    slot: 0,
+                },
+            )
+            .await
+            .is_err());
+        // Named vaults only become the node's vault with an identity
+        assert!(node_man.vault().is_err());
+
+        assert!(node_man
+            .create_identity_impl(ctx, false, Some("v2"))
+            .await
+            .is_err());
+        node_man
+            .create_identity_impl(ctx, false, Some("v1"))
+            .await?;
+        {
+            let state = node_man.config.state().read();
+            assert_eq!(state.vault_path, Some(state.vaults["v1"].path.clone()));
+            assert_eq!(state.vault_backend, Some(VaultBackend::Software));
+        }
 
-            // Initialize identity
-            node_man.create_vault_impl(None, false).await?;
-            node_man.create_identity_impl(ctx, false).await?;
+        ctx.stop().await
+    }
 
-            let node_manager_worker = NodeManagerWorker::new(node_man);
+    #[cfg(feature = "hsm-stub")]
+    #[ockam_macros::test]
+    async fn create_identity_with_stub_hsm_vault(ctx: &mut Context) -> Result<()> {
+        let mut node_man =
+            NodeManager::test_create_without_identity(ctx, |options| options).await?;
+        let backend = VaultBackend::Pkcs11 {
+            module: "libhsm.so".to_string(),
+            slot: 3,
+        };
 
-            // Initialize node_man worker and return its route
-            ctx.start_worker(node_manager, node_manager_worker).await?;
-            Ok(route![node_manager])
+        node_man
+            .create_named_vault_impl("hsm", None, backend.clone())
+            .await?;
+        node_man
+            .create_identity_impl(ctx, false, Some("hsm"))
+            .await?;
+        {
+            let state = node_man.config.state().read();
+            assert_eq!(state.vault_backend, Some(backend));
+            assert!(state
+                .vault_path
+                .as_ref()
+                .unwrap()
+                .ends_with("pkcs11-slot-3.json"));
         }
+
+        ctx.stop().await
     }
 }
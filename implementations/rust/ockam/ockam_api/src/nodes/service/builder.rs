@@ -0,0 +1,274 @@
+//! Typed builder to create and start a node from a library
+
+use super::{NodeManagerGeneralOptions, NodeManagerProjectsOptions, NodeManagerTransportOptions};
+use crate::config::cli::AuthoritiesConfig;
+use crate::config::lookup::ProjectLookup;
+use crate::error::ApiError;
+use crate::nodes::models::transport::{TransportMode, TransportType};
+use crate::nodes::{
+    CredentialChecks, CredentialOutagePolicy, IdentityOverride, NodeManager, NodeManagerWorker,
+    NODEMANAGER_ADDR,
+};
+use ockam::compat::asynchronous::RwLock;
+use ockam::{route, Address, Context, Result, Route, TcpTransport};
+use ockam_core::compat::sync::Arc;
+use ockam_core::AsyncTryClone;
+use ockam_identity::authenticated_storage::{AuthenticatedStorage, SharedAuthenticatedStorage};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+/// A service the [`NodeBuilder`] starts on the node, in addition to the defaults
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeService {
+    Vault(Address),
+    Identity(Address),
+    Authenticated(Address),
+    Uppercase(Address),
+    Echoer(Address),
+}
+
+/// Creates and starts a node, with the same options as `ockam node create`
+///
+/// ```ignore
+/// let node = NodeBuilder::new("n1", node_dir)
+///     .listen_address("127.0.0.1:4000")
+///     .service(NodeService::Uppercase("uppercase".into()))
+///     .start(&ctx)
+///     .await?;
+/// ```
+pub struct NodeBuilder {
+    node_name: String,
+    node_dir: PathBuf,
+    listen_address: String,
+    skip_defaults: bool,
+    credential_checks: CredentialChecks,
+    credential_outage_policy: CredentialOutagePolicy,
+    prefetch_credential: bool,
+    identity_override: Option<IdentityOverride>,
+    authenticated_storage: Option<SharedAuthenticatedStorage>,
+    authorities: Option<AuthoritiesConfig>,
+    project_id: Option<String>,
+    projects: BTreeMap<String, ProjectLookup>,
+    services: Vec<NodeService>,
+}
+
+impl NodeBuilder {
+    /// Node named `node_name`, storing its state in `node_dir`
+    pub fn new(node_name: impl Into<String>, node_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            node_name: node_name.into(),
+            node_dir: node_dir.into(),
+            listen_address: "127.0.0.1:0".to_string(),
+            skip_defaults: false,
+            credential_checks: CredentialChecks::Off,
+            credential_outage_policy: CredentialOutagePolicy::default(),
+            prefetch_credential: false,
+            identity_override: None,
+            authenticated_storage: None,
+            authorities: None,
+            project_id: None,
+            projects: BTreeMap::new(),
+            services: Vec::new(),
+        }
+    }
+
+    /// TCP address to listen on, a random local port by default
+    pub fn listen_address(mut self, listen_address: impl Into<String>) -> Self {
+        self.listen_address = listen_address.into();
+        self
+    }
+
+    /// Use an existing identity and vault instead of creating new ones
+    pub fn identity(mut self, identity_override: IdentityOverride) -> Self {
+        self.identity_override = Some(identity_override);
+        self
+    }
+
+    /// Store authenticated attributes in `storage` instead of in memory
+    pub fn authenticated_storage(mut self, storage: impl AuthenticatedStorage) -> Self {
+        self.authenticated_storage = Some(SharedAuthenticatedStorage::new(storage));
+        self
+    }
+
+    /// Make the node a member of the project `project_id`, trusting its authorities
+    pub fn project(
+        mut self,
+        project_id: impl Into<String>,
+        authorities: AuthoritiesConfig,
+    ) -> Self {
+        self.project_id = Some(project_id.into());
+        self.authorities = Some(authorities);
+        self
+    }
+
+    /// Projects the node can resolve in `/project/<name>` addresses
+    pub fn projects(mut self, projects: BTreeMap<String, ProjectLookup>) -> Self {
+        self.projects = projects;
+        self
+    }
+
+    /// Start an additional service on the node
+    pub fn service(mut self, service: NodeService) -> Self {
+        self.services.push(service);
+        self
+    }
+
+    /// Don't create the default vault and identity nor start the default services
+    pub fn skip_defaults(mut self) -> Self {
+        self.skip_defaults = true;
+        self
+    }
+
+    /// Whether credentials are exchanged with the project's members, see
+    /// [`NodeBuilder::project`]. Off by default
+    pub fn credential_checks(mut self, credential_checks: CredentialChecks) -> Self {
+        self.credential_checks = credential_checks;
+        self
+    }
+
+    /// What to do when the project authority is unreachable, fail closed by default
+    pub fn credential_outage_policy(mut self, policy: CredentialOutagePolicy) -> Self {
+        self.credential_outage_policy = policy;
+        self
+    }
+
+    /// Get a credential from the project authority as the node starts, and
+    /// reuse it for every secure channel until it nearly expires
+    pub fn prefetch_credential(mut self) -> Self {
+        self.prefetch_credential = true;
+        self
+    }
+
+    /// Start the node on `ctx` and return a handle to it
+    pub async fn start(self, ctx: &Context) -> Result<RunningNode> {
+        if self.node_name.is_empty() {
+            return Err(ApiError::generic("Node name must not be empty"));
+        }
+
+        let tcp = TcpTransport::create(ctx).await?;
+        let listen_address = tcp.listen(&self.listen_address).await?;
+
+        let general_options = NodeManagerGeneralOptions::new(
+            self.node_name.clone(),
+            self.node_dir,
+            self.skip_defaults,
+            self.credential_checks,
+            self.identity_override,
+            self.authenticated_storage,
+        )
+        .with_credential_outage_policy(self.credential_outage_policy);
+        let general_options = if self.prefetch_credential {
+            general_options.with_credential_prefetch()
+        } else {
+            general_options
+        };
+        let mut node_manager = NodeManager::create(
+            ctx,
+            general_options,
+            NodeManagerProjectsOptions::new(
+                self.authorities.as_ref(),
+                self.project_id,
+                self.projects,
+            ),
+            NodeManagerTransportOptions::new(
+                (
+                    TransportType::Tcp,
+                    TransportMode::Listen,
+                    listen_address.to_string(),
+                ),
+                tcp.async_try_clone().await?,
+            ),
+        )
+        .await?;
+
+        for service in self.services {
+            node_manager.start_node_service_impl(ctx, service).await?;
+        }
+
+        let mut node_manager_worker = NodeManagerWorker::new(node_manager);
+        let node_manager = node_manager_worker.get().clone();
+        ctx.start_worker(NODEMANAGER_ADDR, node_manager_worker)
+            .await?;
+
+        Ok(RunningNode {
+            node_name: self.node_name,
+            listen_address,
+            tcp,
+            node_manager,
+        })
+    }
+}
+
+/// Handle to a node started with [`NodeBuilder::start`]
+pub struct RunningNode {
+    node_name: String,
+    listen_address: SocketAddr,
+    tcp: TcpTransport,
+    node_manager: Arc<RwLock<NodeManager>>,
+}
+
+impl RunningNode {
+    pub fn node_name(&self) -> &str {
+        &self.node_name
+    }
+
+    /// Address the node's TCP listener is bound to
+    pub fn listen_address(&self) -> SocketAddr {
+        self.listen_address
+    }
+
+    pub fn tcp(&self) -> &TcpTransport {
+        &self.tcp
+    }
+
+    /// Route to the node manager API
+    pub fn api_route(&self) -> Route {
+        route![NODEMANAGER_ADDR]
+    }
+
+    pub fn node_manager(&self) -> &Arc<RwLock<NodeManager>> {
+        &self.node_manager
+    }
+
+    /// Stop the node manager API
+    pub async fn stop(self, ctx: &Context) -> Result<()> {
+        ctx.stop_worker(NODEMANAGER_ADDR).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nodes::models::base::NodeStatus;
+    use minicbor::Decoder;
+    use ockam_core::api::{Request, Response, Status};
+
+    #[ockam_macros::test]
+    async fn start_node_with_builder(ctx: &mut Context) -> Result<()> {
+        let node_dir = tempfile::tempdir().unwrap();
+        let node = NodeBuilder::new("builder-node", node_dir.path())
+            .listen_address("127.0.0.1:0")
+            .service(NodeService::Uppercase("custom_uppercase".into()))
+            .start(ctx)
+            .await?;
+        assert_ne!(node.listen_address().port(), 0);
+
+        let mut buf = vec![];
+        Request::get("/node").encode(&mut buf)?;
+        let response: Vec<u8> = ctx.send_and_receive(node.api_route(), buf).await?;
+        let mut dec = Decoder::new(&response);
+        let header = dec.decode::<Response>()?;
+        assert_eq!(header.status(), Some(Status::Ok));
+        let status = dec.decode::<NodeStatus>()?;
+        assert_eq!(status.node_name, "builder-node");
+
+        let reply: String = ctx
+            .send_and_receive(route!["custom_uppercase"], "hello".to_string())
+            .await?;
+        assert_eq!(reply, "HELLO");
+
+        node.stop(ctx).await?;
+        ctx.stop().await
+    }
+}
@@ -1,28 +1,269 @@
 use crate::authenticator::direct::Client;
 use crate::error::ApiError;
 use crate::multiaddr_to_route;
+use crate::nodes::config::CachedCredential;
 use crate::nodes::models::credentials::{GetCredentialRequest, PresentCredentialRequest};
 use crate::nodes::service::map_multiaddr_err;
 use crate::nodes::NodeManager;
 use crate::DefaultAddress;
 use minicbor::Decoder;
-use ockam::Result;
+use ockam::compat::asynchronous::RwLock;
+use ockam::{Address, Context, DelayedEvent, Result, Route, Routed, Worker};
 use ockam_core::api::{Request, Response, ResponseBuilder};
+use ockam_core::compat::sync::Arc;
 use ockam_core::{route, AsyncTryClone};
+use ockam_identity::authenticated_storage::SharedAuthenticatedStorage;
+use ockam_identity::credential::{Credential, CredentialData, Timestamp, Unverified};
+use ockam_identity::{
+    Identity, IdentityIdentifier, PublicIdentity, SecureChannelOptions,
+    TrustMultiIdentifiersPolicy, DEFAULT_HANDSHAKE_TIMEOUT,
+};
 use ockam_multiaddr::MultiAddr;
+use ockam_node::tokio::time::timeout;
+use ockam_vault::Vault;
 use std::str::FromStr;
+use std::time::Duration;
 
-use super::NodeManagerWorker;
+use super::{map_anyhow_err, NodeManagerWorker};
+
+/// Whether a node exchanges and verifies credentials on the secure
+/// channels it creates
+///
+/// With `On`, every secure channel created with a credential exchange mode
+/// runs that exchange once the handshake is done, and the channel is
+/// closed if the peer's credential isn't attested by one of the project's
+/// authorities. With `Off`, the exchange is skipped whatever mode was
+/// requested, so the peer's credential, valid or not, is never looked at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CredentialChecks {
+    On,
+    Off,
+}
+
+impl Default for CredentialChecks {
+    fn default() -> Self {
+        CredentialChecks::Off
+    }
+}
+
+impl CredentialChecks {
+    pub fn is_on(&self) -> bool {
+        *self == CredentialChecks::On
+    }
+}
+
+/// What a node does when it needs a credential while the project
+/// authority is unreachable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CredentialOutagePolicy {
+    /// Channels which need a credential can't be created
+    FailClosed,
+    /// Keep using the last credential issued by the authority, as long
+    /// as it was issued less than `grace_period` ago and hasn't expired
+    GracePeriod {
+        grace_period: Duration,
+        /// How long to wait for the authority before using the cached credential
+        authority_timeout: Duration,
+    },
+}
+
+impl Default for CredentialOutagePolicy {
+    fn default() -> Self {
+        CredentialOutagePolicy::FailClosed
+    }
+}
+
+impl CredentialOutagePolicy {
+    fn authority_timeout(&self) -> Option<Duration> {
+        match self {
+            CredentialOutagePolicy::FailClosed => None,
+            CredentialOutagePolicy::GracePeriod {
+                authority_timeout, ..
+            } => Some(*authority_timeout),
+        }
+    }
+}
+
+/// How long before its credential expires a node gets a new one
+const CREDENTIAL_REFRESH_MARGIN: Duration = Duration::from_secs(5 * 60);
+
+/// How long a node waits before asking the authority for a credential
+/// again, after failing to get one or being issued a short-lived one
+pub(super) const CREDENTIAL_RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Gets a credential for the node in the background once the node is
+/// started, then a new one before each credential expires, so that secure
+/// channels don't wait for the authority
+pub(super) struct CredentialRefresher {
+    node_manager: Arc<RwLock<NodeManager>>,
+    retry_interval: Duration,
+    refresh: Option<DelayedEvent<Vec<u8>>>,
+}
+
+impl CredentialRefresher {
+    pub(super) fn new(node_manager: Arc<RwLock<NodeManager>>, retry_interval: Duration) -> Self {
+        Self {
+            node_manager,
+            retry_interval,
+            refresh: None,
+        }
+    }
+}
+
+#[ockam_core::async_trait]
+impl Worker for CredentialRefresher {
+    type Message = Vec<u8>;
+    type Context = Context;
+
+    async fn initialize(&mut self, ctx: &mut Context) -> Result<()> {
+        let mut refresh = DelayedEvent::create(ctx, ctx.address(), vec![]).await?;
+        refresh.schedule(Duration::ZERO).await?;
+        self.refresh = Some(refresh);
+        Ok(())
+    }
+
+    async fn handle_message(&mut self, _ctx: &mut Context, _msg: Routed<Vec<u8>>) -> Result<()> {
+        let delay = match self.refresh_credential().await {
+            // A credential from the cache is replaced as soon as the
+            // authority issues one
+            Ok(()) => self
+                .node_manager
+                .read()
+                .await
+                .credential_refresh_delay()
+                .map_or(self.retry_interval, |delay| delay.max(self.retry_interval)),
+            Err(err) => {
+                warn!(
+                    %err,
+                    "Credential check: failed to get a credential, retrying in {:?}",
+                    self.retry_interval
+                );
+                self.retry_interval
+            }
+        };
+        debug!("Credential check: refreshing the credential in {:?}", delay);
+        if let Some(refresh) = &mut self.refresh {
+            refresh.schedule(delay).await?;
+        }
+        Ok(())
+    }
+}
+
+impl CredentialRefresher {
+    /// Get a credential if the node needs one
+    ///
+    /// The node manager isn't locked while waiting for the authority, only
+    /// to read what the request needs and then to store its result.
+    async fn refresh_credential(&self) -> Result<()> {
+        let fetch = {
+            let node_manager = self.node_manager.read().await;
+            if !node_manager.needs_credential().await? {
+                debug!("Credential check: credential already exists...");
+                return Ok(());
+            }
+            node_manager.credential_fetch().await?
+        };
+        debug!("Credential check: requesting...");
+        let fetched = fetch.run().await;
+        let mut node_manager = self.node_manager.write().await;
+        match fetched {
+            Ok(credential) => node_manager.store_credential(credential).await,
+            Err(err) => node_manager.use_cached_credential(err).await,
+        }
+    }
+}
+
+/// What a node needs to get a credential from its authority
+pub(super) struct CredentialFetch {
+    identity: Identity<Vault>,
+    authority: IdentityIdentifier,
+    route: Route,
+    authorities: Vec<PublicIdentity>,
+    storage: SharedAuthenticatedStorage,
+    timeout: Duration,
+}
+
+impl CredentialFetch {
+    /// Get a credential from the authority, over a secure channel of its
+    /// own which is closed afterwards
+    pub(super) async fn run(self) -> Result<Credential<'static>> {
+        debug!("Create secure channel to project authority");
+        let options = SecureChannelOptions::new().with_timeout(self.timeout);
+        let policy = TrustMultiIdentifiersPolicy::new(vec![self.authority.clone()]);
+        let sc = self
+            .identity
+            .create_secure_channel_with_options(self.route.clone(), policy, &self.storage, options)
+            .await?;
+        debug!("Created secure channel to project authority");
+
+        let credential = match timeout(self.timeout, self.request(&sc)).await {
+            Ok(credential) => credential,
+            Err(_) => Err(ApiError::generic("timeout getting a credential")),
+        };
+        if let Err(err) = self.identity.stop_secure_channel(&sc).await {
+            warn!(%err, "Credential check: failed to close the secure channel to the authority");
+        }
+        credential
+    }
+
+    async fn request(&self, sc: &Address) -> Result<Credential<'static>> {
+        let mut client = Client::new(
+            route![sc.clone(), DefaultAddress::AUTHENTICATOR],
+            self.identity.ctx(),
+        )
+        .await?;
+        let credential = client.credential().await?.to_owned();
+        debug!("Got credential");
+
+        self.identity
+            .verify_self_credential(&credential, self.authorities.iter())
+            .await?;
+        debug!("Verified self credential");
+        Ok(credential)
+    }
+}
 
 impl NodeManager {
+    /// How long until the credential issued by the authority expires
+    /// within [`CREDENTIAL_REFRESH_MARGIN`], unless the node has none
+    fn credential_refresh_delay(&self) -> Option<Duration> {
+        if self.credential_from_cache {
+            return None;
+        }
+        let expires_at = u64::from(self.credential_expires_at?);
+        let now = u64::from(Timestamp::now()?);
+        Some(Duration::from_secs(
+            expires_at.saturating_sub(now + CREDENTIAL_REFRESH_MARGIN.as_secs()),
+        ))
+    }
+
+    /// Whether the credential issued by the authority expires within
+    /// [`CREDENTIAL_REFRESH_MARGIN`]
+    pub(super) fn credential_expires_soon(&self) -> bool {
+        let expires_at = match self.credential_expires_at {
+            Some(expires_at) => u64::from(expires_at),
+            None => return false,
+        };
+        match Timestamp::now() {
+            Some(now) => u64::from(now) + CREDENTIAL_REFRESH_MARGIN.as_secs() >= expires_at,
+            None => true,
+        }
+    }
+
     pub(super) async fn get_credential_impl(&mut self, overwrite: bool) -> Result<()> {
         debug!("Credential check: looking for identity");
-        let identity = self.identity()?.async_try_clone().await?;
-
-        if identity.credential().await.is_some() && !overwrite {
+        if self.identity()?.credential().await.is_some() && !overwrite {
             return Err(ApiError::generic("credential already exists"));
         }
 
+        let credential = self.credential_fetch().await?.run().await?;
+        self.store_credential(credential).await
+    }
+
+    /// What the node needs to get a credential from its authority
+    pub(super) async fn credential_fetch(&self) -> Result<CredentialFetch> {
+        let identity = self.identity()?.async_try_clone().await?;
+
         debug!("Credential check: looking for authorities...");
         let authorities = self.authorities()?;
 
@@ -34,8 +275,6 @@ impl NodeManager {
 
         debug!("Getting credential from : {}", authority.addr);
 
-        let allowed = vec![authority.identity.identifier().clone()];
-
         let route = match multiaddr_to_route(&authority.addr) {
             Some(route) => route,
             None => {
@@ -44,27 +283,81 @@ impl NodeManager {
             }
         };
 
-        debug!("Create secure channel to project authority");
-        let sc = self
-            .create_secure_channel_internal(&identity, route, Some(allowed), None)
-            .await?;
-        debug!("Created secure channel to project authority");
+        Ok(CredentialFetch {
+            identity,
+            authority: authority.identity.identifier().clone(),
+            route,
+            authorities: authorities.public_identities(),
+            storage: self.authenticated_storage.clone(),
+            timeout: self
+                .credential_outage_policy
+                .authority_timeout()
+                .unwrap_or(DEFAULT_HANDSHAKE_TIMEOUT),
+        })
+    }
 
-        // Borrow checker issues...
-        let authorities = self.authorities()?;
+    /// Use the credential the authority just issued
+    pub(super) async fn store_credential(&mut self, credential: Credential<'static>) -> Result<()> {
+        self.identity()?
+            .set_credential(Some(credential.to_owned()))
+            .await;
+        self.credential_from_cache = false;
+        self.credential_expires_at = Some(
+            CredentialData::<Unverified>::try_from(&credential)
+                .map_err(|_| ApiError::generic("invalid credential"))?
+                .unverified_expires_at(),
+        );
 
-        let mut client =
-            Client::new(route![sc, DefaultAddress::AUTHENTICATOR], identity.ctx()).await?;
-        let credential = client.credential().await?;
-        debug!("Got credential");
+        // Keep it around in case the authority becomes unreachable
+        let validated_at = Timestamp::now()
+            .ok_or_else(|| ApiError::generic("invalid system time"))?
+            .into();
+        let state = self.config.state();
+        state.write().cached_credential = Some(CachedCredential {
+            credential: minicbor::to_vec(&credential)?.into(),
+            validated_at,
+        });
+        state.persist_config_updates().map_err(map_anyhow_err)?;
+
+        Ok(())
+    }
+
+    /// Use the cached credential if the outage policy allows it, return
+    /// `err` (the reason the authority couldn't issue one) otherwise
+    pub(super) async fn use_cached_credential(&mut self, err: ockam_core::Error) -> Result<()> {
+        let grace_period = match self.credential_outage_policy {
+            CredentialOutagePolicy::FailClosed => return Err(err),
+            CredentialOutagePolicy::GracePeriod { grace_period, .. } => grace_period,
+        };
+        let cached = match self.config.state().read().cached_credential.clone() {
+            Some(cached) => cached,
+            None => return Err(err),
+        };
+
+        let now =
+            u64::from(Timestamp::now().ok_or_else(|| ApiError::generic("invalid system time"))?);
+        if now.saturating_sub(cached.validated_at) > grace_period.as_secs() {
+            warn!("Credential check: cached credential is past the grace period");
+            return Err(err);
+        }
 
-        identity
+        let credential: Credential = minicbor::decode(cached.credential.as_slice())?;
+        let identity = self.identity()?;
+        let authorities = self.authorities()?;
+        if let Err(e) = identity
             .verify_self_credential(&credential, authorities.public_identities().iter())
-            .await?;
-        debug!("Verified self credential");
+            .await
+        {
+            warn!("Credential check: cached credential is invalid: {}", e);
+            return Err(err);
+        }
 
+        warn!(
+            "Credential check: authority unreachable ({}), using the cached credential",
+            err
+        );
         identity.set_credential(Some(credential.to_owned())).await;
-
+        self.credential_from_cache = true;
         Ok(())
     }
 }
@@ -116,3 +409,429 @@ impl NodeManagerWorker {
         Ok(response)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::cli::{AuthoritiesConfig, Authority};
+    use crate::nodes::models::secure_channel::CredentialExchangeMode;
+    use crate::nodes::models::transport::{TransportMode, TransportType};
+    use crate::nodes::service::{
+        NodeManagerGeneralOptions, NodeManagerProjectsOptions, NodeManagerTransportOptions,
+    };
+    use ockam::{Address, Context, TcpTransport, TCP};
+    use ockam_core::compat::sync::Arc;
+    use ockam_core::{Routed, Worker};
+    use ockam_identity::authenticated_storage::mem::InMemoryStorage;
+    use ockam_identity::{Identity, IdentitySecureChannelLocalInfo, TrustEveryonePolicy};
+    use ockam_vault::Vault;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Pretend the authority issued `credential` to the node `age` seconds ago
+    fn cache_credential(node_manager: &NodeManager, credential: &Credential<'_>, age: u64) {
+        let now = u64::from(Timestamp::now().unwrap());
+        node_manager.config.state().write().cached_credential = Some(CachedCredential {
+            credential: minicbor::to_vec(credential).unwrap().into(),
+            validated_at: now - age,
+        });
+    }
+
+    #[ockam_macros::test]
+    async fn cached_credential_during_authority_outage(ctx: &mut Context) -> Result<()> {
+        let tcp = TcpTransport::create(ctx).await?;
+        let listen_address = tcp.listen("127.0.0.1:0").await?;
+
+        // Nothing runs at the authority's address
+        let authority = Identity::create(ctx, &Vault::create()).await?;
+        let mut authorities = AuthoritiesConfig::default();
+        authorities.add_authority(
+            authority.identifier().clone(),
+            Authority::new(
+                authority.export().await?,
+                MultiAddr::from_str("/service/authority").map_err(map_multiaddr_err)?,
+            ),
+        );
+
+        // A peer checking the credentials presented on its secure channels
+        let peer = Identity::create(ctx, &Vault::create()).await?;
+        let storage = InMemoryStorage::new();
+        peer.create_secure_channel_listener("listener", TrustEveryonePolicy, &storage)
+            .await?;
+        peer.start_credentials_exchange_worker(
+            vec![authority.to_public().await?],
+            "credentials",
+            false,
+            storage,
+        )
+        .await?;
+
+        let node_dir = tempfile::tempdir().unwrap();
+        let policy = CredentialOutagePolicy::GracePeriod {
+            grace_period: Duration::from_secs(60),
+            authority_timeout: Duration::from_secs(1),
+        };
+        let mut node_manager = NodeManager::create(
+            ctx,
+            NodeManagerGeneralOptions::new(
+                "node".to_string(),
+                node_dir.path().to_path_buf(),
+                false,
+                CredentialChecks::On,
+                None,
+                None,
+            )
+            .with_credential_outage_policy(policy),
+            NodeManagerProjectsOptions::new(
+                Some(&authorities),
+                Some("project".to_string()),
+                Default::default(),
+            ),
+            NodeManagerTransportOptions::new(
+                (
+                    TransportType::Tcp,
+                    TransportMode::Listen,
+                    listen_address.to_string(),
+                ),
+                tcp.async_try_clone().await?,
+            ),
+        )
+        .await?;
+
+        let subject = node_manager.identity()?.identifier().clone();
+        let credential = authority
+            .issue_credential(Credential::builder(subject).with_attribute("project_id", b"project"))
+            .await?;
+        let peer_route = route![(TCP, listen_address.to_string()), "listener"];
+
+        // Issued within the grace period, the cached credential is presented
+        cache_credential(&node_manager, &credential, 30);
+        node_manager
+            .create_secure_channel_impl(
+                peer_route.clone(),
+                None,
+                CredentialExchangeMode::Oneway,
+                None,
+            )
+            .await?;
+
+        // Past the grace period, the authority has to be reached again
+        cache_credential(&node_manager, &credential, 120);
+        let res = node_manager
+            .create_secure_channel_impl(peer_route, None, CredentialExchangeMode::Oneway, None)
+            .await;
+        assert!(res.is_err());
+
+        ctx.stop().await
+    }
+
+    /// Authority issuing a credential valid for `validity` to whoever asks
+    /// for one, counting the credentials it issued
+    struct StubAuthority {
+        identity: Identity<Vault>,
+        validity: Duration,
+        issued: Arc<AtomicUsize>,
+    }
+
+    #[ockam_core::async_trait]
+    impl Worker for StubAuthority {
+        type Message = Vec<u8>;
+        type Context = Context;
+
+        async fn handle_message(&mut self, ctx: &mut Context, msg: Routed<Vec<u8>>) -> Result<()> {
+            let info = IdentitySecureChannelLocalInfo::find_info(msg.local_message())?;
+            let req: Request = Decoder::new(msg.as_body()).decode()?;
+            let credential = self
+                .identity
+                .issue_credential(
+                    Credential::builder(info.their_identity_id().clone())
+                        .with_attribute("project_id", b"project")
+                        .valid_for(self.validity),
+                )
+                .await?;
+            self.issued.fetch_add(1, Ordering::SeqCst);
+            ctx.send(
+                msg.return_route(),
+                Response::ok(req.id()).body(credential).to_vec()?,
+            )
+            .await
+        }
+    }
+
+    /// Node refreshing its credential, see [`node_refreshing_its_credential`]
+    struct RefreshingNode {
+        node_manager: Arc<RwLock<NodeManager>>,
+        /// Number of credentials the authority issued so far
+        issued: Arc<AtomicUsize>,
+        listen_address: String,
+        _node_dir: tempfile::TempDir,
+    }
+
+    /// Start a node refreshing its credential in the background, every
+    /// `retry_interval` at most, from an authority issuing credentials
+    /// valid for `validity`, and a peer checking them on `channels`
+    /// listeners
+    async fn node_refreshing_its_credential(
+        ctx: &Context,
+        validity: Duration,
+        retry_interval: Duration,
+        channels: usize,
+    ) -> Result<RefreshingNode> {
+        let tcp = TcpTransport::create(ctx).await?;
+        let listen_address = tcp.listen("127.0.0.1:0").await?;
+
+        let authority = Identity::create(ctx, &Vault::create()).await?;
+        authority
+            .create_secure_channel_listener(
+                "authority_listener",
+                TrustEveryonePolicy,
+                &InMemoryStorage::new(),
+            )
+            .await?;
+        let issued = Arc::new(AtomicUsize::new(0));
+        ctx.start_worker(
+            DefaultAddress::AUTHENTICATOR,
+            StubAuthority {
+                identity: authority.async_try_clone().await?,
+                validity,
+                issued: issued.clone(),
+            },
+        )
+        .await?;
+        let mut authorities = AuthoritiesConfig::default();
+        authorities.add_authority(
+            authority.identifier().clone(),
+            Authority::new(
+                authority.export().await?,
+                MultiAddr::from_str("/service/authority_listener").map_err(map_multiaddr_err)?,
+            ),
+        );
+
+        let peer = Identity::create(ctx, &Vault::create()).await?;
+        let storage = InMemoryStorage::new();
+        for i in 0..channels {
+            peer.create_secure_channel_listener(
+                format!("listener{}", i),
+                TrustEveryonePolicy,
+                &storage,
+            )
+            .await?;
+        }
+        peer.start_credentials_exchange_worker(
+            vec![authority.to_public().await?],
+            DefaultAddress::CREDENTIAL_SERVICE,
+            false,
+            storage,
+        )
+        .await?;
+
+        let node_dir = tempfile::tempdir().unwrap();
+        let node_manager = NodeManager::create(
+            ctx,
+            NodeManagerGeneralOptions::new(
+                "node".to_string(),
+                node_dir.path().to_path_buf(),
+                false,
+                CredentialChecks::On,
+                None,
+                None,
+            )
+            .with_credential_prefetch(),
+            NodeManagerProjectsOptions::new(
+                Some(&authorities),
+                Some("project".to_string()),
+                Default::default(),
+            ),
+            NodeManagerTransportOptions::new(
+                (
+                    TransportType::Tcp,
+                    TransportMode::Listen,
+                    listen_address.to_string(),
+                ),
+                tcp.async_try_clone().await?,
+            ),
+        )
+        .await?;
+        // Creating the node doesn't wait for the authority
+        assert_eq!(issued.load(Ordering::SeqCst), 0);
+
+        let node_manager = Arc::new(RwLock::new(node_manager));
+        let refresher = CredentialRefresher::new(node_manager.clone(), retry_interval);
+        ctx.start_worker(Address::random_local(), refresher).await?;
+        Ok(RefreshingNode {
+            node_manager,
+            issued,
+            listen_address: listen_address.to_string(),
+            _node_dir: node_dir,
+        })
+    }
+
+    /// Wait until the authority issued at least `count` credentials
+    async fn wait_for_credentials(issued: &AtomicUsize, count: usize) {
+        for _ in 0..100 {
+            if issued.load(Ordering::SeqCst) >= count {
+                return;
+            }
+            ockam_node::tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        panic!("The authority should issue {} credentials", count);
+    }
+
+    /// Start a node prefetching its credential from an authority issuing
+    /// credentials valid for `validity`, and create secure channels to
+    /// `channels` listeners of a peer checking them
+    ///
+    /// Return how many credentials the authority issued.
+    async fn channels_with_prefetched_credential(
+        ctx: &Context,
+        validity: Duration,
+        channels: usize,
+    ) -> Result<usize> {
+        let node =
+            node_refreshing_its_credential(ctx, validity, CREDENTIAL_RETRY_INTERVAL, channels)
+                .await?;
+        wait_for_credentials(&node.issued, 1).await;
+
+        for i in 0..channels {
+            let peer_route = route![(TCP, node.listen_address.clone()), format!("listener{}", i)];
+            node.node_manager
+                .write()
+                .await
+                .create_secure_channel_impl(peer_route, None, CredentialExchangeMode::Oneway, None)
+                .await?;
+        }
+        Ok(node.issued.load(Ordering::SeqCst))
+    }
+
+    #[ockam_macros::test]
+    async fn prefetched_credential_is_reused(ctx: &mut Context) -> Result<()> {
+        let issued = channels_with_prefetched_credential(ctx, Duration::from_secs(3600), 3).await?;
+        assert_eq!(issued, 1);
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn credential_is_refreshed_before_it_expires(ctx: &mut Context) -> Result<()> {
+        // Expiring within the refresh margin, the credential is never reused
+        let validity = CREDENTIAL_REFRESH_MARGIN / 2;
+        let issued = channels_with_prefetched_credential(ctx, validity, 2).await?;
+        assert_eq!(issued, 3);
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn credential_is_refreshed_in_the_background(ctx: &mut Context) -> Result<()> {
+        // Without any channel, a credential about to expire is replaced
+        let validity = CREDENTIAL_REFRESH_MARGIN / 2;
+        let node =
+            node_refreshing_its_credential(ctx, validity, Duration::from_millis(100), 0).await?;
+        wait_for_credentials(&node.issued, 3).await;
+
+        ctx.stop().await
+    }
+
+    /// Exchange credentials over a new channel with a peer whose
+    /// credential was issued by an authority the node doesn't trust
+    async fn exchange_with_untrusted_peer(
+        ctx: &Context,
+        credential_checks: CredentialChecks,
+    ) -> Result<(NodeManager, Result<Address>)> {
+        let tcp = TcpTransport::create(ctx).await?;
+        let listen_address = tcp.listen("127.0.0.1:0").await?;
+
+        let authority = Identity::create(ctx, &Vault::create()).await?;
+        let mut authorities = AuthoritiesConfig::default();
+        authorities.add_authority(
+            authority.identifier().clone(),
+            Authority::new(
+                authority.export().await?,
+                MultiAddr::from_str("/service/authority").map_err(map_multiaddr_err)?,
+            ),
+        );
+
+        let rogue_authority = Identity::create(ctx, &Vault::create()).await?;
+        let peer = Identity::create(ctx, &Vault::create()).await?;
+        let peer_credential = rogue_authority
+            .issue_credential(
+                Credential::builder(peer.identifier().clone())
+                    .with_attribute("project_id", b"project"),
+            )
+            .await?;
+        peer.set_credential(Some(peer_credential.to_owned())).await;
+        let storage = InMemoryStorage::new();
+        peer.create_secure_channel_listener("listener", TrustEveryonePolicy, &storage)
+            .await?;
+        peer.start_credentials_exchange_worker(
+            vec![authority.to_public().await?],
+            DefaultAddress::CREDENTIAL_SERVICE,
+            true,
+            storage,
+        )
+        .await?;
+
+        let node_dir = tempfile::tempdir().unwrap();
+        let mut node_manager = NodeManager::create(
+            ctx,
+            NodeManagerGeneralOptions::new(
+                "node".to_string(),
+                node_dir.path().to_path_buf(),
+                false,
+                credential_checks,
+                None,
+                None,
+            ),
+            NodeManagerProjectsOptions::new(
+                Some(&authorities),
+                Some("project".to_string()),
+                Default::default(),
+            ),
+            NodeManagerTransportOptions::new(
+                (
+                    TransportType::Tcp,
+                    TransportMode::Listen,
+                    listen_address.to_string(),
+                ),
+                tcp.async_try_clone().await?,
+            ),
+        )
+        .await?;
+
+        let identity = node_manager.identity()?;
+        let credential = authority
+            .issue_credential(
+                Credential::builder(identity.identifier().clone())
+                    .with_attribute("project_id", b"project"),
+            )
+            .await?;
+        identity.set_credential(Some(credential.to_owned())).await;
+
+        let peer_route = route![(TCP, listen_address.to_string()), "listener"];
+        let res = node_manager
+            .create_secure_channel_impl(peer_route, None, CredentialExchangeMode::Mutual, None)
+            .await;
+        Ok((node_manager, res))
+    }
+
+    #[ockam_macros::test]
+    async fn invalid_credential_is_rejected_with_checks_on(ctx: &mut Context) -> Result<()> {
+        let (node_manager, res) = exchange_with_untrusted_peer(ctx, CredentialChecks::On).await?;
+        assert!(res.is_err());
+        assert!(node_manager.registry.secure_channels.list().is_empty());
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn invalid_credential_is_ignored_with_checks_off(ctx: &mut Context) -> Result<()> {
+        let (node_manager, res) = exchange_with_untrusted_peer(ctx, CredentialChecks::Off).await?;
+        let sc_addr = res?;
+        assert!(node_manager
+            .registry
+            .secure_channels
+            .get_by_addr(&sc_addr)
+            .is_some());
+
+        ctx.stop().await
+    }
+}
@@ -1,18 +1,22 @@
 use super::{map_anyhow_err, NodeManagerWorker};
 use crate::nodes::models::identity::{
-    CreateIdentityResponse, LongIdentityResponse, ShortIdentityResponse,
+    CreateIdentityRequest, CreateIdentityResponse, LongIdentityResponse, ShortIdentityResponse,
 };
 use crate::nodes::NodeManager;
+use minicbor::Decoder;
 use ockam::identity::{Identity, IdentityIdentifier};
 use ockam::{Context, Result};
 use ockam_core::api::{Request, Response, ResponseBuilder};
 use ockam_core::errcode::{Kind, Origin};
 
 impl NodeManager {
+    /// Create the identity of the node, with the vault recorded under
+    /// `vault_name` if given, which then becomes the node's vault
     pub(super) async fn create_identity_impl(
         &mut self,
         ctx: &Context,
         reuse_if_exists: bool,
+        vault_name: Option<&str>,
     ) -> Result<IdentityIdentifier> {
         if let Some(identity) = &self.identity {
             return if reuse_if_exists {
@@ -27,6 +31,9 @@ impl NodeManager {
             };
         }
 
+        if let Some(name) = vault_name {
+            self.use_named_vault(name).await?;
+        }
         let vault = self.vault()?;
 
         let identity = Identity::create(ctx, vault).await?;
@@ -37,6 +44,7 @@ impl NodeManager {
         state.write().identity = Some(exported_identity);
         state.persist_config_updates().map_err(map_anyhow_err)?;
 
+        identity.set_read_only(state.read().read_only_identity);
         self.identity = Some(identity);
 
         Ok(identifier)
@@ -48,9 +56,17 @@ impl NodeManagerWorker {
         &mut self,
         ctx: &Context,
         req: &Request<'_>,
+        dec: &mut Decoder<'_>,
     ) -> Result<ResponseBuilder<CreateIdentityResponse<'_>>> {
         let mut node_manager = self.node_manager.write().await;
-        let identifier = node_manager.create_identity_impl(ctx, false).await?;
+        let req_body: CreateIdentityRequest = if req.has_body() {
+            dec.decode()?
+        } else {
+            CreateIdentityRequest::new()
+        };
+        let identifier = node_manager
+            .create_identity_impl(ctx, false, req_body.vault.as_deref())
+            .await?;
 
         let response =
             Response::ok(req.id()).body(CreateIdentityResponse::new(identifier.to_string()));
@@ -2,10 +2,10 @@ use std::str::FromStr;
 
 use minicbor::{Decode, Encode};
 
+use ockam_core::Result;
 #[cfg(feature = "tag")]
 use ockam_core::TypeTag;
 use ockam_core::{CowBytes, CowStr};
-use ockam_core::{Result, Route};
 use ockam_multiaddr::MultiAddr;
 
 use crate::error::ApiError;
@@ -31,11 +31,9 @@ impl<'a> SendMessage<'a> {
         }
     }
 
-    pub fn route(&self) -> Result<Route> {
-        let maddr = MultiAddr::from_str(self.route.as_ref())
-            .map_err(|_err| ApiError::generic(&format!("Invalid route: {}", self.route)))?;
-        crate::multiaddr_to_route(&maddr)
-            .ok_or_else(|| ApiError::generic(&format!("Invalid MultiAddr: {}", maddr)))
+    pub fn multiaddr(&self) -> Result<MultiAddr> {
+        MultiAddr::from_str(self.route.as_ref())
+            .map_err(|_err| ApiError::generic(&format!("Invalid route: {}", self.route)))
     }
 }
 
@@ -59,13 +57,29 @@ mod node {
             dec: &mut Decoder<'_>,
         ) -> Result<Vec<u8>> {
             let req_body: super::SendMessage = dec.decode()?;
-            let route = req_body.route()?;
+            let maddr = req_body.multiaddr()?;
             let msg = req_body.message.to_vec();
             let msg_length = msg.len();
 
             trace!(target: TARGET, route = %req_body.route, msg_l = %msg_length, "sending message");
 
+            // The `/secure` hops of the route go through secure channels
+            // which only carry this message
+            let (route, channels) = {
+                let mut node_manager = self.node_manager.write().await;
+                node_manager
+                    .create_secure_channels_along(&maddr, None)
+                    .await?
+            };
+
             let res: Result<Vec<u8>> = ctx.send_and_receive(route, msg).await;
+
+            self.node_manager
+                .read()
+                .await
+                .close_secure_channels(&channels)
+                .await;
+
             match res {
                 Ok(r) => Ok(Response::builder(req.id(), Status::Ok).body(r).to_vec()?),
                 Err(err) => {
@@ -5,73 +5,97 @@ use crate::error::ApiError;
 use crate::nodes::models::secure_channel::{
     CreateSecureChannelListenerRequest, CreateSecureChannelRequest, CreateSecureChannelResponse,
     CredentialExchangeMode, DeleteSecureChannelRequest, DeleteSecureChannelResponse,
-    ShowSecureChannelRequest, ShowSecureChannelResponse,
+    SecureChannelListenerList, SecureChannelListenerStatus, ShowSecureChannelRequest,
+    ShowSecureChannelResponse, UpdateSecureChannelListenerRequest,
 };
-use crate::nodes::registry::Registry;
+use crate::nodes::registry::{AuthorizedIdentifiers, Registry, SecureChannelListenerInfo};
 use crate::nodes::NodeManager;
-use crate::DefaultAddress;
+use crate::{multiaddr_to_route, try_address_to_multiaddr, DefaultAddress};
 use minicbor::Decoder;
 use ockam::identity::TrustEveryonePolicy;
 use ockam::{Address, Result, Route};
 use ockam_core::api::{Request, Response, ResponseBuilder};
-use ockam_core::{route, AsyncTryClone};
-use ockam_identity::{Identity, IdentityIdentifier, TrustMultiIdentifiersPolicy};
-use ockam_multiaddr::MultiAddr;
+use ockam_core::vault::KeyId;
+use ockam_core::{route, AsyncTryClone, CowStr};
+use ockam_identity::{
+    Identity, IdentityIdentifier, ReloadableTrustPolicy, SecureChannelListenerOptions,
+    SecureChannelOptions, TrustCredentialPolicy, TrustMultiIdentifiersPolicy, TrustPolicy,
+    DEFAULT_HANDSHAKE_TIMEOUT,
+};
+use ockam_multiaddr::proto::Secure;
+use ockam_multiaddr::{MultiAddr, Protocol};
 use ockam_vault::Vault;
 
 impl NodeManager {
-    async fn get_credential_if_needed(&mut self) -> Result<()> {
-        let identity = self.identity()?;
-
-        if identity.credential().await.is_some() {
+    /// Get a credential from the authority unless the identity already has
+    /// one which was issued just now and doesn't expire soon
+    pub(super) async fn get_credential_if_needed(&mut self) -> Result<()> {
+        if !self.needs_credential().await? {
             debug!("Credential check: credential already exists...");
             return Ok(());
         }
 
         debug!("Credential check: requesting...");
-        self.get_credential_impl(false).await?;
+        if let Err(err) = self.get_credential_impl(true).await {
+            return self.use_cached_credential(err).await;
+        }
         debug!("Credential check: got new credential...");
 
         Ok(())
     }
 
+    /// Whether the node has no credential from the authority, or one
+    /// expiring soon
+    pub(super) async fn needs_credential(&self) -> Result<bool> {
+        Ok(self.identity()?.credential().await.is_none()
+            || self.credential_from_cache
+            || self.credential_expires_soon())
+    }
+
     pub(crate) async fn create_secure_channel_internal(
         &mut self,
         identity: &Identity<Vault>,
         sc_route: Route,
         authorized_identifiers: Option<Vec<IdentityIdentifier>>,
         timeout: Option<Duration>,
+        pre_shared_key: Option<KeyId>,
     ) -> Result<Address> {
-        // If channel was already created, do nothing.
-        if let Some(channel) = self.registry.secure_channels.get_by_route(&sc_route) {
-            let addr = channel.addr();
-            debug!(%addr, "Using cached secure channel");
-            return Ok(addr.clone());
+        // If channel was already created, do nothing. Channels proving the
+        // pre-shared key are not registered apart from the others, so they
+        // are always created
+        if pre_shared_key.is_none() {
+            if let Some(channel) = self.registry.secure_channels.get_by_route(&sc_route) {
+                let addr = channel.addr();
+                debug!(%addr, "Using cached secure channel");
+                return Ok(addr.clone());
+            }
         }
         // Else, create it.
 
         debug!(%sc_route, "Creating secure channel");
-        let timeout = timeout.unwrap_or(Duration::from_secs(120));
+        let mut options =
+            SecureChannelOptions::new().with_timeout(timeout.unwrap_or(DEFAULT_HANDSHAKE_TIMEOUT));
+        if let Some(key_id) = pre_shared_key {
+            options = options.with_pre_shared_key(key_id);
+        }
         let sc_addr = match authorized_identifiers.clone() {
             Some(ids) => {
-                identity
-                    .create_secure_channel_extended(
-                        sc_route.clone(),
-                        TrustMultiIdentifiersPolicy::new(ids),
-                        &self.authenticated_storage,
-                        timeout,
-                    )
-                    .await
+                self.create_secure_channel_with_options(
+                    identity,
+                    sc_route.clone(),
+                    TrustMultiIdentifiersPolicy::new(ids),
+                    options,
+                )
+                .await
             }
             None => {
-                identity
-                    .create_secure_channel_extended(
-                        sc_route.clone(),
-                        TrustEveryonePolicy,
-                        &self.authenticated_storage,
-                        timeout,
-                    )
-                    .await
+                self.create_secure_channel_with_options(
+                    identity,
+                    sc_route.clone(),
+                    TrustEveryonePolicy,
+                    options,
+                )
+                .await
             }
         }?;
 
@@ -90,20 +114,69 @@ impl NodeManager {
         authorized_identifiers: Option<Vec<IdentityIdentifier>>,
         credential_exchange_mode: CredentialExchangeMode,
         timeout: Option<Duration>,
+    ) -> Result<Address> {
+        self.create_secure_channel_with_pre_shared_key_impl(
+            sc_route,
+            authorized_identifiers,
+            credential_exchange_mode,
+            timeout,
+            false,
+        )
+        .await
+    }
+
+    /// Create a secure channel, proving the node's pre-shared key if
+    /// `pre_shared_key` is set
+    ///
+    /// Fails if the node wasn't created with a pre-shared key.
+    pub(super) async fn create_secure_channel_with_pre_shared_key_impl(
+        &mut self,
+        sc_route: Route,
+        authorized_identifiers: Option<Vec<IdentityIdentifier>>,
+        credential_exchange_mode: CredentialExchangeMode,
+        timeout: Option<Duration>,
+        pre_shared_key: bool,
     ) -> Result<Address> {
         let identity = self.identity()?.async_try_clone().await?;
+        let pre_shared_key = self.scoped_pre_shared_key(pre_shared_key)?;
 
         let sc_addr = self
-            .create_secure_channel_internal(&identity, sc_route, authorized_identifiers, timeout)
+            .create_secure_channel_internal(
+                &identity,
+                sc_route,
+                authorized_identifiers,
+                timeout,
+                pre_shared_key,
+            )
             .await?;
 
-        let actual_exchange_mode = if self.enable_credential_checks {
+        let actual_exchange_mode = if self.credential_checks.is_on() {
             credential_exchange_mode
         } else {
             CredentialExchangeMode::None
         };
 
-        match actual_exchange_mode {
+        // A channel whose credential exchange failed must not be used
+        if let Err(err) = self
+            .exchange_credentials(&identity, &sc_addr, actual_exchange_mode)
+            .await
+        {
+            warn!(%sc_addr, %err, "Closing secure channel, credential exchange failed");
+            self.delete_secure_channel(&sc_addr).await?;
+            return Err(err);
+        }
+
+        // Return secure channel address
+        Ok(sc_addr)
+    }
+
+    async fn exchange_credentials(
+        &mut self,
+        identity: &Identity<Vault>,
+        sc_addr: &Address,
+        credential_exchange_mode: CredentialExchangeMode,
+    ) -> Result<()> {
+        match credential_exchange_mode {
             CredentialExchangeMode::None => {
                 debug!(%sc_addr, "No credential presentation");
             }
@@ -129,55 +202,288 @@ impl NodeManager {
                 debug!(%sc_addr, "Mutual credential presentation success");
             }
         }
-
-        // Return secure channel address
-        Ok(sc_addr)
+        Ok(())
     }
 
+    /// Create a secure channel listener, requiring the node's pre-shared
+    /// key if `pre_shared_key` is set
+    ///
+    /// Fails if `pre_shared_key` is set and the node wasn't created with a
+    /// pre-shared key.
     pub(super) async fn create_secure_channel_listener_impl(
         &mut self,
         addr: Address,
         authorized_identifiers: Option<Vec<IdentityIdentifier>>,
+        check_credential: bool,
+        pre_shared_key: bool,
     ) -> Result<()> {
         info!(
             "Handling request to create a new secure channel listener: {}",
             addr
         );
 
+        let pre_shared_key = self.scoped_pre_shared_key(pre_shared_key)?;
         let identity = self.identity()?;
+        let trust_policy = Self::describe_listener_trust_policy(
+            authorized_identifiers.as_deref(),
+            check_credential,
+            pre_shared_key.is_some(),
+        );
+        let mut options = SecureChannelListenerOptions::new();
+        if let Some(key_id) = pre_shared_key {
+            options = options.with_pre_shared_key(key_id);
+        }
 
-        match authorized_identifiers {
-            Some(ids) => {
-                identity
-                    .create_secure_channel_listener(
-                        addr.clone(),
-                        TrustMultiIdentifiersPolicy::new(ids),
-                        &self.authenticated_storage,
-                    )
-                    .await
+        let mut trusted_identifiers = None;
+        match (authorized_identifiers, check_credential) {
+            (Some(_), true) => {
+                return Err(ApiError::generic(
+                    "Authorized identifiers and credential checks can't be combined",
+                ))
             }
-            None => {
-                identity
-                    .create_secure_channel_listener(
-                        addr.clone(),
-                        TrustEveryonePolicy,
-                        &self.authenticated_storage,
-                    )
-                    .await
+            (Some(ids), false) => {
+                // The identifiers can be changed while the listener runs
+                let policy =
+                    ReloadableTrustPolicy::new(TrustMultiIdentifiersPolicy::new(ids.clone()));
+                trusted_identifiers = Some(AuthorizedIdentifiers {
+                    identifiers: ids,
+                    trust_policy: policy.clone(),
+                });
+                self.create_secure_channel_listener_with_options(
+                    identity,
+                    addr.clone(),
+                    policy,
+                    options,
+                )
+                .await
+            }
+            (None, true) => {
+                self.create_secure_channel_listener_with_options(
+                    identity,
+                    addr.clone(),
+                    TrustCredentialPolicy::new(self.authenticated_storage.clone()),
+                    options,
+                )
+                .await
+            }
+            (None, false) => {
+                self.create_secure_channel_listener_with_options(
+                    identity,
+                    addr.clone(),
+                    TrustEveryonePolicy,
+                    options,
+                )
+                .await
             }
         }?;
 
-        self.registry
+        self.registry.secure_channel_listeners.insert(
+            addr,
+            SecureChannelListenerInfo::new(
+                trust_policy,
+                trusted_identifiers,
+                pre_shared_key.is_some(),
+            ),
+        );
+
+        Ok(())
+    }
+
+    /// Change the identifiers trusted by the listener at `addr`
+    ///
+    /// Only the handshakes which start afterwards are affected, the channels
+    /// the listener already established are kept.
+    pub(super) fn update_secure_channel_listener_impl(
+        &mut self,
+        addr: &Address,
+        add_identifiers: Vec<IdentityIdentifier>,
+        remove_identifiers: Vec<IdentityIdentifier>,
+    ) -> Result<()> {
+        info!(%addr, "Handling request to update a secure channel listener");
+
+        let info = self
+            .registry
             .secure_channel_listeners
-            .insert(addr, Default::default());
+            .get_mut(addr)
+            .ok_or_else(|| {
+                ApiError::generic(&format!("secure channel listener {addr} not found"))
+            })?;
+        let pre_shared_key = info.pre_shared_key();
+        let trusted = info.authorized_identifiers_mut().ok_or_else(|| {
+            ApiError::generic(&format!(
+                "secure channel listener {addr} doesn't trust authorized identifiers"
+            ))
+        })?;
+
+        trusted
+            .identifiers
+            .retain(|id| !remove_identifiers.contains(id));
+        for id in add_identifiers {
+            if !trusted.identifiers.contains(&id) {
+                trusted.identifiers.push(id);
+            }
+        }
+        trusted
+            .trust_policy
+            .reload(TrustMultiIdentifiersPolicy::new(
+                trusted.identifiers.clone(),
+            ));
+
+        let trust_policy =
+            Self::describe_listener_trust_policy(Some(&trusted.identifiers), false, pre_shared_key);
+        info.set_trust_policy(trust_policy);
+        Ok(())
+    }
 
+    /// Describe which initiators a listener created with these options trusts
+    fn describe_listener_trust_policy(
+        authorized_identifiers: Option<&[IdentityIdentifier]>,
+        check_credential: bool,
+        pre_shared_key: bool,
+    ) -> String {
+        let policy = match authorized_identifiers {
+            Some(ids) => {
+                let ids: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+                format!("trusts identifiers [{}]", ids.join(", "))
+            }
+            None if check_credential => {
+                "trusts initiators presenting a valid credential".to_string()
+            }
+            None => "trusts everyone".to_string(),
+        };
+        if pre_shared_key {
+            format!("{policy}, with the node's pre-shared key")
+        } else {
+            policy
+        }
+    }
+
+    /// The node's pre-shared key if `pre_shared_key` is set, failing if
+    /// the node wasn't created with one
+    fn scoped_pre_shared_key(&self, pre_shared_key: bool) -> Result<Option<KeyId>> {
+        if !pre_shared_key {
+            return Ok(None);
+        }
+        self.pre_shared_key()
+            .map(Some)
+            .ok_or_else(|| ApiError::generic("the node wasn't created with a pre-shared key"))
+    }
+
+    async fn create_secure_channel_with_options(
+        &self,
+        identity: &Identity<Vault>,
+        sc_route: Route,
+        trust_policy: impl TrustPolicy,
+        options: SecureChannelOptions,
+    ) -> Result<Address> {
+        identity
+            .create_secure_channel_with_options(
+                sc_route,
+                trust_policy,
+                &self.authenticated_storage,
+                options,
+            )
+            .await
+    }
+
+    async fn create_secure_channel_listener_with_options(
+        &self,
+        identity: &Identity<Vault>,
+        addr: Address,
+        trust_policy: impl TrustPolicy,
+        options: SecureChannelListenerOptions,
+    ) -> Result<()> {
+        identity
+            .create_secure_channel_listener_with_options(
+                addr,
+                trust_policy,
+                &self.authenticated_storage,
+                options,
+            )
+            .await?;
         Ok(())
     }
 
+    /// Create a secure channel to each `/secure` hop of `addr`, in order,
+    /// each one through the channels before it
+    ///
+    /// Returns the route to the end of `addr` through the channels, and
+    /// the channels, which the caller closes with
+    /// [`close_secure_channels`](Self::close_secure_channels) once done
+    /// with them. The channels are neither looked up among the node's
+    /// channels nor registered, so closing them doesn't close a channel
+    /// used by anyone else.
+    pub(super) async fn create_secure_channels_along(
+        &mut self,
+        addr: &MultiAddr,
+        timeout: Option<Duration>,
+    ) -> Result<(Route, Vec<Address>)> {
+        let invalid = || ApiError::generic("invalid multiaddr");
+        multiaddr_to_route(addr).ok_or_else(invalid)?;
+
+        let identity = self.identity()?.async_try_clone().await?;
+        let mut channels = Vec::new();
+        let mut through = MultiAddr::default();
+        let mut rest = addr.clone();
+        while let Some(pos) = rest.iter().position(|p| p.code() == Secure::CODE) {
+            let (hop, tail) = rest.split(pos + 1);
+            let hop = through.try_with(&hop).map_err(map_multiaddr_err)?;
+            debug!(addr = %hop, "creating secure channel");
+            let r = multiaddr_to_route(&hop).ok_or_else(invalid)?;
+            let options = SecureChannelOptions::new()
+                .with_timeout(timeout.unwrap_or(DEFAULT_HANDSHAKE_TIMEOUT));
+            let channel = match self
+                .create_secure_channel_with_options(&identity, r, TrustEveryonePolicy, options)
+                .await
+            {
+                Ok(channel) => channel,
+                Err(err) => {
+                    self.close_secure_channels(&channels).await;
+                    return Err(err);
+                }
+            };
+            through = try_address_to_multiaddr(&channel)?;
+            channels.push(channel);
+            rest = tail;
+        }
+
+        let addr = through.try_with(&rest).map_err(map_multiaddr_err)?;
+        let route = multiaddr_to_route(&addr).ok_or_else(invalid)?;
+        Ok((route, channels))
+    }
+
+    /// Close the channels created by
+    /// [`create_secure_channels_along`](Self::create_secure_channels_along),
+    /// the last one first
+    ///
+    /// Failures are only logged, the other channels are still closed.
+    pub(super) async fn close_secure_channels(&self, channels: &[Address]) {
+        let identity = match self.identity() {
+            Ok(identity) => identity,
+            Err(err) => {
+                warn!(%err, "failed to close secure channels");
+                return;
+            }
+        };
+        for channel in channels.iter().rev() {
+            if let Err(err) = identity.stop_secure_channel(channel).await {
+                warn!(%channel, %err, "failed to close secure channel");
+            }
+        }
+    }
+
     pub(super) async fn delete_secure_channel(&mut self, addr: &Address) -> Result<()> {
         debug!(%addr, "deleting secure channel");
+        if self.registry.secure_channels.get_by_addr(addr).is_none() {
+            return Err(ApiError::generic(&format!(
+                "secure channel {addr} not found"
+            )));
+        }
         let identity = self.identity()?;
-        identity.stop_secure_channel(addr).await?;
+        // The other end may already have closed the channel
+        if let Err(err) = identity.stop_secure_channel(addr).await {
+            debug!(%addr, %err, "secure channel was already stopped");
+        }
         self.registry.secure_channels.remove_by_addr(addr);
         Ok(())
     }
@@ -213,6 +519,23 @@ impl NodeManagerWorker {
         )
     }
 
+    /// The listeners with the initiators they trust
+    pub(super) fn list_secure_channel_listener_status(
+        &self,
+        req: &Request<'_>,
+        registry: &Registry,
+    ) -> ResponseBuilder<SecureChannelListenerList> {
+        Response::ok(req.id()).body(SecureChannelListenerList::new(
+            registry
+                .secure_channel_listeners
+                .iter()
+                .map(|(addr, info)| {
+                    SecureChannelListenerStatus::new(addr.to_string(), info.trust_policy())
+                })
+                .collect(),
+        ))
+    }
+
     pub(super) async fn create_secure_channel<'a>(
         &mut self,
         req: &Request<'_>,
@@ -224,10 +547,18 @@ impl NodeManagerWorker {
             authorized_identifiers,
             credential_exchange_mode,
             timeout,
+            label,
+            pre_shared_key,
             ..
         } = dec.decode()?;
 
-        info!("Handling request to create a new secure channel: {}", addr);
+        match &label {
+            Some(label) => info!(
+                "Handling request to create a new secure channel '{}': {}",
+                label, addr
+            ),
+            None => info!("Handling request to create a new secure channel: {}", addr),
+        }
 
         let authorized_identifiers = match authorized_identifiers {
             Some(ids) => {
@@ -247,14 +578,23 @@ impl NodeManagerWorker {
             .ok_or_else(|| ApiError::generic("Invalid Multiaddr"))?;
 
         let channel = node_manager
-            .create_secure_channel_impl(
+            .create_secure_channel_with_pre_shared_key_impl(
                 route,
                 authorized_identifiers,
                 credential_exchange_mode,
                 timeout,
+                pre_shared_key.unwrap_or(false),
             )
             .await?;
 
+        if let Some(label) = label {
+            info!(%channel, "Labeled secure channel '{}'", label);
+            node_manager
+                .registry
+                .secure_channels
+                .set_label(&channel, label.to_string());
+        }
+
         let response = Response::ok(req.id()).body(CreateSecureChannelResponse::new(&channel));
 
         Ok(response)
@@ -298,8 +638,12 @@ impl NodeManagerWorker {
             .registry
             .secure_channels
             .get_by_addr(&sc_address);
+        let negotiation = node_manager
+            .identity
+            .as_ref()
+            .and_then(|identity| identity.secure_channel_negotiation(&sc_address));
 
-        Ok(Response::ok(req.id()).body(ShowSecureChannelResponse::new(info)))
+        Ok(Response::ok(req.id()).body(ShowSecureChannelResponse::new(info, negotiation.as_ref())))
     }
 
     pub(super) async fn create_secure_channel_listener(
@@ -311,6 +655,8 @@ impl NodeManagerWorker {
         let CreateSecureChannelListenerRequest {
             addr,
             authorized_identifiers,
+            check_credential,
+            pre_shared_key,
             ..
         } = dec.decode()?;
 
@@ -332,11 +678,47 @@ impl NodeManagerWorker {
         }
 
         node_manager
-            .create_secure_channel_listener_impl(addr, authorized_identifiers)
+            .create_secure_channel_listener_impl(
+                addr,
+                authorized_identifiers,
+                check_credential.unwrap_or(false),
+                pre_shared_key.unwrap_or(false),
+            )
             .await?;
 
         let response = Response::ok(req.id());
 
         Ok(response)
     }
+
+    pub(super) async fn update_secure_channel_listener(
+        &mut self,
+        req: &Request<'_>,
+        dec: &mut Decoder<'_>,
+    ) -> Result<ResponseBuilder<()>> {
+        let mut node_manager = self.node_manager.write().await;
+        let UpdateSecureChannelListenerRequest {
+            addr,
+            add_identifiers,
+            remove_identifiers,
+            ..
+        } = dec.decode()?;
+
+        let parse = |ids: Vec<CowStr<'_>>| {
+            ids.into_iter()
+                .map(|x| IdentityIdentifier::try_from(x.0.as_ref()))
+                .collect::<Result<Vec<IdentityIdentifier>>>()
+        };
+        let add_identifiers = parse(add_identifiers)?;
+        let remove_identifiers = parse(remove_identifiers)?;
+
+        let addr = Address::from(addr.as_ref());
+        node_manager.update_secure_channel_listener_impl(
+            &addr,
+            add_identifiers,
+            remove_identifiers,
+        )?;
+
+        Ok(Response::ok(req.id()))
+    }
 }
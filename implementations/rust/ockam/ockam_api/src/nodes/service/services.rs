@@ -15,6 +15,7 @@ use minicbor::Decoder;
 use ockam::{Address, AsyncTryClone, Context, Result};
 use ockam_core::api::{Request, Response, ResponseBuilder};
 
+use super::builder::NodeService;
 use super::NodeManagerWorker;
 
 impl NodeManager {
@@ -149,6 +150,22 @@ impl NodeManager {
         Ok(())
     }
 
+    pub(super) async fn start_node_service_impl(
+        &mut self,
+        ctx: &Context,
+        service: NodeService,
+    ) -> Result<()> {
+        match service {
+            NodeService::Vault(addr) => self.start_vault_service_impl(ctx, addr).await,
+            NodeService::Identity(addr) => self.start_identity_service_impl(ctx, addr).await,
+            NodeService::Authenticated(addr) => {
+                self.start_authenticated_service_impl(ctx, addr).await
+            }
+            NodeService::Uppercase(addr) => self.start_uppercase_service_impl(ctx, addr).await,
+            NodeService::Echoer(addr) => self.start_echoer_service_impl(ctx, addr).await,
+        }
+    }
+
     #[cfg(feature = "direct-authenticator")]
     pub(super) async fn start_direct_authenticator_service_impl(
         &mut self,
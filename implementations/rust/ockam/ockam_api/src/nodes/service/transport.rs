@@ -1,11 +1,15 @@
 use std::collections::BTreeMap;
+use std::time::UNIX_EPOCH;
 
+use crate::error::ApiError;
 use crate::nodes::models::transport::{
-    CreateTransport, DeleteTransport, TransportList, TransportMode, TransportStatus, TransportType,
+    CreateTransport, DeleteTransport, TransportChannel, TransportList, TransportMode,
+    TransportStatus, TransportType,
 };
+use crate::nodes::registry::Registry;
 use crate::nodes::service::{random_alias, Alias};
 use minicbor::Decoder;
-use ockam::Result;
+use ockam::{Result, TcpTransport, TCP};
 use ockam_core::api::{Request, Response, ResponseBuilder};
 
 use super::NodeManagerWorker;
@@ -15,17 +19,41 @@ impl NodeManagerWorker {
         &self,
         req: &Request<'a>,
         transports: &'a BTreeMap<Alias, (TransportType, TransportMode, String)>,
+        tcp_transport: &TcpTransport,
+        registry: &Registry,
         mode: TransportMode,
     ) -> ResponseBuilder<TransportList<'a>> {
         Response::ok(req.id()).body(TransportList::new(
             transports
                 .iter()
                 .filter(|(_, (_, tm, _))| *tm == mode)
-                .map(|(tid, (tt, tm, addr))| TransportStatus::new(*tt, *tm, addr, tid))
+                .map(|(tid, transport)| transport_status(tid, transport, tcp_transport, registry))
                 .collect(),
         ))
     }
 
+    /// Detailed status of the TCP connection `tid`
+    pub(super) fn get_tcp_connection(
+        &self,
+        req: &Request<'_>,
+        transports: &BTreeMap<Alias, (TransportType, TransportMode, String)>,
+        tcp_transport: &TcpTransport,
+        registry: &Registry,
+        tid: &str,
+    ) -> Result<Vec<u8>> {
+        match transports.get_key_value(tid) {
+            Some((tid, transport)) if transport.1 == TransportMode::Connect => {
+                Response::ok(req.id())
+                    .body(transport_status(tid, transport, tcp_transport, registry))
+                    .to_vec()
+            }
+            _ => {
+                warn!(%tid, "Unknown TCP connection");
+                Response::not_found(req.id()).to_vec()
+            }
+        }
+    }
+
     pub(super) async fn add_transport<'a>(
         &self,
         req: &Request<'_>,
@@ -53,16 +81,35 @@ impl NodeManagerWorker {
                 .connect(&addr)
                 .await
                 .map(|ockam_addr| ockam_addr.to_string()),
+            #[cfg(unix)]
+            (Uds, _) => match &node_manager.uds_transport {
+                Some(uds) if tm == Listen => uds.listen(&addr).await.map(|_| addr.clone()),
+                Some(uds) => uds
+                    .connect(&addr)
+                    .await
+                    .map(|ockam_addr| ockam_addr.to_string()),
+                None => Err(ApiError::generic("The node has no UDS transport")),
+            },
+            #[cfg(not(unix))]
+            (Uds, _) => Err(ApiError::generic(
+                "UDS transports are only supported on unix",
+            )),
             _ => unimplemented!(),
         };
 
         let response = match res {
-            Ok(_) => {
+            Ok(worker_addr) => {
                 let tid = random_alias();
                 node_manager
                     .transports
                     .insert(tid.clone(), (tt, tm, addr.clone()));
-                Response::ok(req.id()).body(TransportStatus::new(tt, tm, addr, tid))
+                let status = TransportStatus::new(tt, tm, addr, tid);
+                // Routes through a UDS connection start at its sender
+                let status = match (tt, tm) {
+                    (Uds, Connect) => status.with_worker_addr(worker_addr),
+                    _ => status,
+                };
+                Response::ok(req.id()).body(status)
             }
             Err(msg) => Response::bad_request(req.id()).body(TransportStatus::new(
                 tt,
@@ -105,3 +152,44 @@ impl NodeManagerWorker {
         }
     }
 }
+
+/// Status of the transport `tid`, with the traffic and the secure channels
+/// of a connection
+fn transport_status<'a>(
+    tid: &'a str,
+    (tt, tm, addr): &'a (TransportType, TransportMode, String),
+    tcp_transport: &TcpTransport,
+    registry: &Registry,
+) -> TransportStatus<'a> {
+    let status = TransportStatus::new(*tt, *tm, addr.as_str(), tid);
+    if *tm != TransportMode::Connect {
+        return status;
+    }
+    let status = status.with_secure_channels(channels_over(registry, addr));
+    match tcp_transport.connection_metrics(addr) {
+        Some(metrics) => {
+            let connected_at = metrics
+                .connected_at
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default();
+            status.with_traffic(metrics.bytes_in, metrics.bytes_out, connected_at)
+        }
+        None => status,
+    }
+}
+
+/// Secure channels whose route starts with the TCP connection to `addr`
+fn channels_over<'a>(registry: &Registry, addr: &str) -> Vec<TransportChannel<'a>> {
+    registry
+        .secure_channels
+        .list()
+        .iter()
+        .filter(|info| {
+            info.route().iter().next().map_or(false, |hop| {
+                hop.transport_type() == TCP && hop.address() == addr
+            })
+        })
+        .map(TransportChannel::new)
+        .collect()
+}
@@ -1,5 +1,6 @@
 use super::{map_anyhow_err, NodeManagerWorker};
-use crate::nodes::models::vault::CreateVaultRequest;
+use crate::nodes::config::NamedVault;
+use crate::nodes::models::vault::{CreateVaultRequest, VaultBackend};
 use crate::nodes::NodeManager;
 use minicbor::Decoder;
 use ockam::vault::storage::FileStorage;
@@ -7,6 +8,7 @@ use ockam::vault::Vault;
 use ockam::Result;
 use ockam_core::api::{Request, Response, ResponseBuilder};
 use ockam_core::errcode::{Kind, Origin};
+use ockam_node::tokio;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
@@ -15,9 +17,77 @@ impl NodeManager {
         node_dir.join("vault.json")
     }
 
+    /// Fail if vaults can't be kept in `backend`
+    ///
+    /// Software vaults are always available. With the `hsm-stub` feature,
+    /// pkcs11 vaults are emulated by a file per slot in the node's
+    /// directory, so the CLI can be tested without an HSM. Other backends
+    /// are accepted by the API so that the CLI can record them, and will
+    /// be enabled as their vault implementations land.
+    pub(super) fn check_vault_backend(backend: &VaultBackend) -> Result<()> {
+        match backend {
+            VaultBackend::Software => Ok(()),
+            #[cfg(feature = "hsm-stub")]
+            VaultBackend::Pkcs11 { .. } => Ok(()),
+            backend => Err(ockam_core::Error::new(
+                Origin::Application,
+                Kind::Unsupported,
+                format!("{} vaults are not supported by this node", backend),
+            )),
+        }
+    }
+
+    /// File of a vault kept in `backend`, `path` if one was given
+    fn vault_file(
+        &self,
+        name: Option<&str>,
+        path: Option<PathBuf>,
+        backend: &VaultBackend,
+    ) -> PathBuf {
+        match (backend, path, name) {
+            (VaultBackend::Pkcs11 { slot, .. }, _, _) => {
+                self.node_dir.join(format!("pkcs11-slot-{}.json", slot))
+            }
+            (_, Some(path), _) => path,
+            (_, None, Some(name)) => self.node_dir.join(format!("vault-{}.json", name)),
+            (_, None, None) => Self::default_vault_path(&self.node_dir),
+        }
+    }
+
+    async fn open_vault(path: PathBuf) -> Result<Vault> {
+        let vault_storage = FileStorage::create(path).await?;
+        Ok(Vault::new(Some(Arc::new(vault_storage))))
+    }
+
+    /// Encrypt the values of the authenticated storage with a new key in
+    /// the background, see
+    /// [`StorageEncryption::rotate`](crate::storage_encryption::StorageEncryption::rotate)
+    ///
+    /// Values stay readable during the rotation, and a node stopped before
+    /// the rotation completed resumes it when it starts again.
+    pub(super) fn rotate_storage_key_impl(&self) -> Result<()> {
+        let (encryption, storage) = match &self.storage_encryption {
+            Some((encryption, storage)) => (encryption.clone(), storage.clone()),
+            None => {
+                return Err(ockam_core::Error::new(
+                    Origin::Application,
+                    Kind::Unsupported,
+                    "The node's storage is not encrypted",
+                ))
+            }
+        };
+        tokio::spawn(async move {
+            if let Err(err) = encryption.rotate(&storage).await {
+                error!(%err, "Failed to rotate the storage key, it will resume when the node restarts");
+            }
+        });
+        Ok(())
+    }
+
     pub(super) async fn create_vault_impl(
         &mut self,
         path: Option<PathBuf>,
+        backend: VaultBackend,
         reuse_if_exists: bool,
     ) -> Result<()> {
         if self.vault.is_some() {
@@ -33,13 +103,79 @@ impl NodeManager {
             };
         }
 
-        let path = path.unwrap_or_else(|| Self::default_vault_path(&self.node_dir));
+        Self::check_vault_backend(&backend)?;
+        let path = self.vault_file(None, path, &backend);
+        let vault = Self::open_vault(path.clone()).await?;
+
+        let state = self.config.state();
+        state.write().vault_path = Some(path);
+        state.write().vault_backend = Some(backend);
+        state.persist_config_updates().map_err(map_anyhow_err)?;
+
+        self.vault = Some(vault);
+
+        Ok(())
+    }
+
+    /// Create a vault recorded under `name`, which doesn't become the
+    /// node's vault until an identity is created with it
+    pub(super) async fn create_named_vault_impl(
+        &mut self,
+        name: &str,
+        path: Option<PathBuf>,
+        backend: VaultBackend,
+    ) -> Result<()> {
+        // The name is part of the vault's file name
+        let valid_name = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+        if name.is_empty() || !name.chars().all(valid_name) {
+            return Err(ockam_core::Error::new(
+                Origin::Application,
+                Kind::Invalid,
+                "Vault names may only contain letters, digits, '-' and '_'",
+            ));
+        }
+
+        let state = self.config.state();
+        if state.read().vaults.contains_key(name) {
+            return Err(ockam_core::Error::new(
+                Origin::Application,
+                Kind::AlreadyExists,
+                format!("Vault {} already exists", name),
+            ));
+        }
+
+        Self::check_vault_backend(&backend)?;
+        let path = self.vault_file(Some(name), path, &backend);
+        Self::open_vault(path.clone()).await?;
 
-        let vault_storage = FileStorage::create(path.clone()).await?;
-        let vault = Vault::new(Some(Arc::new(vault_storage)));
+        state
+            .write()
+            .vaults
+            .insert(name.to_string(), NamedVault { path, backend });
+        state.persist_config_updates().map_err(map_anyhow_err)?;
 
+        Ok(())
+    }
+
+    /// Make the vault recorded under `name` the node's vault
+    pub(super) async fn use_named_vault(&mut self, name: &str) -> Result<()> {
         let state = self.config.state();
+        let NamedVault { path, backend } = match state.read().vaults.get(name) {
+            Some(vault) => vault.clone(),
+            None => {
+                return Err(ockam_core::Error::new(
+                    Origin::Application,
+                    Kind::NotFound,
+                    format!("Vault {} doesn't exist", name),
+                ))
+            }
+        };
+
+        Self::check_vault_backend(&backend)?;
+        let vault = Self::open_vault(path.clone()).await?;
+
         state.write().vault_path = Some(path);
+        state.write().vault_backend = Some(backend);
         state.persist_config_updates().map_err(map_anyhow_err)?;
 
         self.vault = Some(vault);
@@ -58,11 +194,28 @@ impl NodeManagerWorker {
         let req_body: CreateVaultRequest = dec.decode()?;
 
         let path = req_body.path.map(|p| PathBuf::from(p.0.as_ref()));
+        let backend = req_body.backend.unwrap_or(VaultBackend::Software);
 
-        node_manager.create_vault_impl(path, false).await?;
+        match req_body.name {
+            Some(name) => {
+                node_manager
+                    .create_named_vault_impl(&name, path, backend)
+                    .await?
+            }
+            None => node_manager.create_vault_impl(path, backend, false).await?,
+        }
 
         let response = Response::ok(req.id());
 
         Ok(response)
     }
+
+    pub(super) async fn rotate_storage_key(
+        &mut self,
+        req: &Request<'_>,
+    ) -> Result<ResponseBuilder> {
+        let node_manager = self.node_manager.read().await;
+        node_manager.rotate_storage_key_impl()?;
+        Ok(Response::ok(req.id()))
+    }
 }
@@ -0,0 +1,125 @@
+use flate2::read::{DeflateDecoder, DeflateEncoder};
+use flate2::Compression;
+use ockam_core::errcode::{Kind, Origin};
+use ockam_core::{Error, Result};
+use ockam_identity::authenticated_storage::ValueCodec;
+use std::io::Read;
+
+/// Compresses the values of a
+/// [`CodecStorage`](ockam_identity::authenticated_storage::CodecStorage)
+/// with deflate
+///
+/// Pair it with another codec, e.g. `(DeflateCodec::default(), other)`,
+/// to compress values before the other codec encodes them.
+///
+/// Values which decompress to more than a maximum size, 16 MiB by default,
+/// fail to decode, so that a small corrupted or crafted value can't
+/// exhaust the memory of the node reading it.
+#[derive(Clone, Copy, Debug)]
+pub struct DeflateCodec {
+    level: Compression,
+    max_size: u64,
+}
+
+/// Largest value decoded by a [`DeflateCodec`] by default
+pub const DEFAULT_MAX_DECOMPRESSED_SIZE: u64 = 16 * 1024 * 1024;
+
+impl Default for DeflateCodec {
+    fn default() -> Self {
+        Self {
+            level: Compression::default(),
+            max_size: DEFAULT_MAX_DECOMPRESSED_SIZE,
+        }
+    }
+}
+
+impl DeflateCodec {
+    /// Compress with the given level, from 0 (no compression) to 9 (best)
+    pub fn with_level(level: u32) -> Self {
+        Self {
+            level: Compression::new(level.min(9)),
+            ..Default::default()
+        }
+    }
+
+    /// Fail to decode the values which decompress to more than `max_size` bytes
+    pub fn with_max_size(mut self, max_size: u64) -> Self {
+        self.max_size = max_size;
+        self
+    }
+}
+
+impl ValueCodec for DeflateCodec {
+    fn encode(&self, val: Vec<u8>) -> Result<Vec<u8>> {
+        let mut compressed = Vec::new();
+        DeflateEncoder::new(val.as_slice(), self.level)
+            .read_to_end(&mut compressed)
+            .map_err(map_io_err)?;
+        Ok(compressed)
+    }
+
+    fn decode(&self, val: Vec<u8>) -> Result<Vec<u8>> {
+        // One byte past the maximum tells a value which is too large
+        let mut decompressed = Vec::new();
+        DeflateDecoder::new(val.as_slice())
+            .take(self.max_size.saturating_add(1))
+            .read_to_end(&mut decompressed)
+            .map_err(map_io_err)?;
+        if decompressed.len() as u64 > self.max_size {
+            return Err(Error::new(
+                Origin::Application,
+                Kind::ResourceExhausted,
+                format!("value decompresses to more than {} bytes", self.max_size),
+            ));
+        }
+        Ok(decompressed)
+    }
+}
+
+fn map_io_err(err: std::io::Error) -> Error {
+    Error::new(Origin::Application, Kind::Io, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_decompressing_past_the_maximum_fail_to_decode() {
+        let codec = DeflateCodec::default().with_max_size(1024);
+        let bomb = codec.encode(vec![0; 1025]).unwrap();
+        assert!(bomb.len() < 64);
+        let err = codec.decode(bomb).unwrap_err();
+        assert_eq!(err.code().kind, Kind::ResourceExhausted);
+
+        let value = codec.encode(vec![0; 1024]).unwrap();
+        assert_eq!(codec.decode(value).unwrap(), vec![0; 1024]);
+    }
+
+    #[cfg(feature = "lmdb")]
+    #[ockam_macros::test]
+    async fn compressible_values_are_smaller_on_disk(ctx: &mut ockam::Context) -> Result<()> {
+        use crate::lmdb::LmdbStorage;
+        use ockam_identity::authenticated_storage::{AuthenticatedStorage, CodecStorage};
+
+        let dir = tempfile::tempdir().unwrap();
+        let lmdb = LmdbStorage::new(dir.path().join("lmdb")).await?;
+        let storage = CodecStorage::new(lmdb.clone(), DeflateCodec::default());
+
+        let plaintext = b"role=admin;".repeat(4096);
+        storage
+            .set("alice", "attributes".to_string(), plaintext.clone())
+            .await?;
+
+        let on_disk = lmdb.get("alice", "attributes").await?.unwrap();
+        assert!(on_disk.len() < plaintext.len());
+        assert_eq!(storage.get("alice", "attributes").await?, Some(plaintext));
+
+        // Corrupted values fail to decode rather than read as garbage
+        lmdb.set("alice", "attributes".to_string(), vec![0xff; 16])
+            .await?;
+        assert!(storage.get("alice", "attributes").await.is_err());
+
+        ctx.stop().await
+    }
+}
@@ -0,0 +1,451 @@
+use aes_gcm::aead::{generic_array::GenericArray, Aead, NewAead, Payload};
+use aes_gcm::Aes256Gcm;
+use ockam_core::async_trait;
+use ockam_core::compat::rand::random;
+use ockam_core::errcode::{Kind, Origin};
+use ockam_core::{Error, Result};
+use ockam_identity::authenticated_storage::{
+    AuthenticatedStorage, AuthenticatedStorageListener, AuthenticatedStorageMetrics,
+    AuthenticatedStorageTransaction, CodecStorage, ValueCodec,
+};
+use ockam_node::tokio::sync::{Mutex, RwLock as AsyncRwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+/// Name of the file holding the storage keys of a node, in its directory
+pub const STORAGE_KEYS_FILE: &str = "storage_keys.json";
+
+const KEY_ID_LEN: usize = 4;
+const NONCE_LEN: usize = 12;
+
+#[derive(Clone, Serialize, Deserialize)]
+struct StorageKey(#[serde(with = "hex::serde")] [u8; 32]);
+
+/// Keys of an encrypted storage
+///
+/// Values are encrypted with the `current` key. The other keys are only
+/// kept while a rotation re-encrypts the values they encrypted.
+#[derive(Clone, Serialize, Deserialize)]
+struct StorageKeys {
+    current: u32,
+    keys: BTreeMap<u32, StorageKey>,
+}
+
+impl StorageKeys {
+    fn new() -> Self {
+        Self {
+            current: 0,
+            keys: BTreeMap::from([(0, StorageKey(random()))]),
+        }
+    }
+
+    fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read(path).map_err(map_io_err)?;
+        serde_json::from_slice(&contents).map_err(|e| {
+            Error::new(
+                Origin::Application,
+                Kind::Invalid,
+                format!("invalid storage keys at {}: {}", path.display(), e),
+            )
+        })
+    }
+
+    /// Replace the keys at `path`, so a crash leaves either the old or the
+    /// new keys behind
+    fn save(&self, path: &Path) -> Result<()> {
+        let contents = serde_json::to_vec(self)
+            .map_err(|e| Error::new(Origin::Application, Kind::Serialization, e))?;
+        let tmp = path.with_extension("tmp");
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(&tmp).map_err(map_io_err)?;
+        file.write_all(&contents).map_err(map_io_err)?;
+        file.sync_all().map_err(map_io_err)?;
+        std::fs::rename(&tmp, path).map_err(map_io_err)
+    }
+
+    fn cipher(&self, key_id: u32) -> Result<Aes256Gcm> {
+        match self.keys.get(&key_id) {
+            Some(key) => Ok(Aes256Gcm::new(GenericArray::from_slice(&key.0))),
+            None => Err(Error::new(
+                Origin::Application,
+                Kind::NotFound,
+                format!("the storage key {} is unknown", key_id),
+            )),
+        }
+    }
+}
+
+/// Encryption of the values of a node's storage with keys kept next to it
+///
+/// The keys can be rotated while the storage is in use, see
+/// [`StorageEncryption::rotate`].
+#[derive(Clone)]
+pub struct StorageEncryption {
+    path: PathBuf,
+    keys: Arc<RwLock<StorageKeys>>,
+    /// Held while a rotation runs
+    rotation: Arc<Mutex<()>>,
+    /// Held by the writes of the [`EncryptedStorage`]s, and by a rotation
+    /// while it re-encrypts a value
+    writes: Arc<AsyncRwLock<()>>,
+}
+
+impl StorageEncryption {
+    /// Create a new key, saved at `path`
+    pub fn create(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let keys = StorageKeys::new();
+        keys.save(&path)?;
+        Ok(Self::with_keys(path, keys))
+    }
+
+    /// Load the keys saved at `path`, `None` if there are none
+    pub fn open(path: impl Into<PathBuf>) -> Result<Option<Self>> {
+        let path = path.into();
+        if !path.exists() {
+            return Ok(None);
+        }
+        let keys = StorageKeys::load(&path)?;
+        Ok(Some(Self::with_keys(path, keys)))
+    }
+
+    fn with_keys(path: PathBuf, keys: StorageKeys) -> Self {
+        Self {
+            path,
+            keys: Arc::new(RwLock::new(keys)),
+            rotation: Default::default(),
+            writes: Default::default(),
+        }
+    }
+
+    /// Codec encrypting values with the current key, for a
+    /// [`CodecStorage`](ockam_identity::authenticated_storage::CodecStorage)
+    pub fn codec(&self) -> StorageEncryptionCodec {
+        StorageEncryptionCodec {
+            keys: self.keys.clone(),
+        }
+    }
+
+    /// `storage` with its values encrypted with the current key
+    pub fn storage<S: AuthenticatedStorage + Clone>(&self, storage: S) -> EncryptedStorage<S> {
+        EncryptedStorage {
+            storage: CodecStorage::new(storage, self.codec()),
+            writes: self.writes.clone(),
+        }
+    }
+
+    /// Whether a rotation was started and didn't complete
+    pub fn is_rotating(&self) -> bool {
+        self.keys.read().unwrap().keys.len() > 1
+    }
+
+    /// Encrypt the values of `storage`, which holds the values encrypted
+    /// by [`StorageEncryption::storage`], with a new key, then forget the
+    /// previous keys. Returns the number of values re-encrypted
+    ///
+    /// New values are encrypted with the new key as soon as the rotation
+    /// starts, and values encrypted with the previous keys can be read
+    /// until the rotation completes. The keys are saved before and after
+    /// the values are re-encrypted, so a rotation which was interrupted
+    /// resumes with the same new key, skipping the values it already
+    /// re-encrypted.
+    pub async fn rotate(&self, storage: &impl AuthenticatedStorage) -> Result<usize> {
+        let _rotation = self.rotation.lock().await;
+        let current = {
+            let mut keys = self.keys.write().unwrap();
+            if keys.keys.len() == 1 {
+                let mut rotated = keys.clone();
+                rotated.current += 1;
+                rotated.keys.insert(rotated.current, StorageKey(random()));
+                rotated.save(&self.path)?;
+                *keys = rotated;
+            }
+            keys.current
+        };
+        info!(key = current, "Rotating the storage key");
+
+        let codec = self.codec();
+        let mut reencrypted = 0;
+        for (id, key, val) in storage.entries().await? {
+            if key_id(&val)? == current {
+                continue;
+            }
+            let plaintext = codec.decode(val.clone())?;
+            // The value can't be written while it's re-encrypted
+            let _writes = self.writes.write().await;
+            // Values written since the entries were listed are encrypted
+            // with the current key already
+            if storage.get(&id, &key).await?.as_ref() != Some(&val) {
+                continue;
+            }
+            storage.set(&id, key, codec.encode(plaintext)?).await?;
+            reencrypted += 1;
+        }
+
+        let mut keys = self.keys.read().unwrap().clone();
+        keys.keys.retain(|id, _| *id == current);
+        keys.save(&self.path)?;
+        *self.keys.write().unwrap() = keys;
+        info!(key = current, reencrypted, "Rotated the storage key");
+        Ok(reencrypted)
+    }
+}
+
+/// A storage whose values are encrypted by a [`StorageEncryption`]
+///
+/// Its writes wait while a rotation re-encrypts a value, so they are
+/// never overwritten by the value being re-encrypted.
+#[derive(Clone)]
+pub struct EncryptedStorage<S: AuthenticatedStorage + Clone> {
+    storage: CodecStorage<S, StorageEncryptionCodec>,
+    writes: Arc<AsyncRwLock<()>>,
+}
+
+#[async_trait]
+impl<S: AuthenticatedStorage + Clone> AuthenticatedStorage for EncryptedStorage<S> {
+    async fn get(&self, id: &str, key: &str) -> Result<Option<Vec<u8>>> {
+        self.storage.get(id, key).await
+    }
+
+    async fn set(&self, id: &str, key: String, val: Vec<u8>) -> Result<()> {
+        let _writes = self.writes.read().await;
+        self.storage.set(id, key, val).await
+    }
+
+    async fn del(&self, id: &str, key: &str) -> Result<()> {
+        let _writes = self.writes.read().await;
+        self.storage.del(id, key).await
+    }
+
+    fn subscribe(&self, listener: Arc<dyn AuthenticatedStorageListener>) -> Result<()> {
+        self.storage.subscribe(listener)
+    }
+
+    async fn commit(&self, transaction: AuthenticatedStorageTransaction) -> Result<()> {
+        let _writes = self.writes.read().await;
+        self.storage.commit(transaction).await
+    }
+
+    async fn metrics(&self) -> Result<AuthenticatedStorageMetrics> {
+        self.storage.metrics().await
+    }
+
+    async fn entries(&self) -> Result<Vec<(String, String, Vec<u8>)>> {
+        self.storage.entries().await
+    }
+}
+
+/// Encrypts values with AES-256-GCM under the current storage key,
+/// prefixing them with the id of the key and the nonce
+pub struct StorageEncryptionCodec {
+    keys: Arc<RwLock<StorageKeys>>,
+}
+
+impl ValueCodec for StorageEncryptionCodec {
+    fn encode(&self, val: Vec<u8>) -> Result<Vec<u8>> {
+        let keys = self.keys.read().unwrap();
+        let key_id = keys.current.to_be_bytes();
+        let nonce: [u8; NONCE_LEN] = random();
+        let ciphertext = keys
+            .cipher(keys.current)?
+            .encrypt(
+                GenericArray::from_slice(&nonce),
+                Payload {
+                    msg: &val,
+                    aad: &key_id,
+                },
+            )
+            .map_err(|_| encryption_err("failed to encrypt a value"))?;
+        Ok([&key_id[..], &nonce, &ciphertext].concat())
+    }
+
+    fn decode(&self, val: Vec<u8>) -> Result<Vec<u8>> {
+        let id = key_id(&val)?;
+        let nonce = &val[KEY_ID_LEN..KEY_ID_LEN + NONCE_LEN];
+        self.keys
+            .read()
+            .unwrap()
+            .cipher(id)?
+            .decrypt(
+                GenericArray::from_slice(nonce),
+                Payload {
+                    msg: &val[KEY_ID_LEN + NONCE_LEN..],
+                    aad: &val[..KEY_ID_LEN],
+                },
+            )
+            .map_err(|_| encryption_err("failed to decrypt a value"))
+    }
+}
+
+/// Id of the key which encrypted `val`
+fn key_id(val: &[u8]) -> Result<u32> {
+    if val.len() < KEY_ID_LEN + NONCE_LEN {
+        return Err(encryption_err("the value is too short to be encrypted"));
+    }
+    let mut id = [0; KEY_ID_LEN];
+    id.copy_from_slice(&val[..KEY_ID_LEN]);
+    Ok(u32::from_be_bytes(id))
+}
+
+fn encryption_err(msg: &str) -> Error {
+    Error::new(Origin::Application, Kind::Invalid, msg)
+}
+
+fn map_io_err(err: std::io::Error) -> Error {
+    Error::new(Origin::Application, Kind::Io, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ockam::Context;
+    use ockam_identity::authenticated_storage::mem::InMemoryStorage;
+    use ockam_node::tokio;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    /// Fails the writes once `writes_left` is down to zero, as a node
+    /// stopped in the middle of a rotation
+    #[derive(Clone)]
+    struct InterruptedStorage {
+        storage: InMemoryStorage,
+        writes_left: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl AuthenticatedStorage for InterruptedStorage {
+        async fn get(&self, id: &str, key: &str) -> Result<Option<Vec<u8>>> {
+            self.storage.get(id, key).await
+        }
+
+        async fn set(&self, id: &str, key: String, val: Vec<u8>) -> Result<()> {
+            let left = self.writes_left.load(Ordering::SeqCst);
+            if left == 0 {
+                return Err(map_io_err(std::io::ErrorKind::Interrupted.into()));
+            }
+            self.writes_left.store(left - 1, Ordering::SeqCst);
+            self.storage.set(id, key, val).await
+        }
+
+        async fn del(&self, id: &str, key: &str) -> Result<()> {
+            self.storage.del(id, key).await
+        }
+
+        async fn entries(&self) -> Result<Vec<(String, String, Vec<u8>)>> {
+            self.storage.entries().await
+        }
+    }
+
+    /// Waits after reading a value, as a storage slow to answer
+    #[derive(Clone)]
+    struct SlowStorage(InMemoryStorage);
+
+    #[async_trait]
+    impl AuthenticatedStorage for SlowStorage {
+        async fn get(&self, id: &str, key: &str) -> Result<Option<Vec<u8>>> {
+            let val = self.0.get(id, key).await?;
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            Ok(val)
+        }
+
+        async fn set(&self, id: &str, key: String, val: Vec<u8>) -> Result<()> {
+            self.0.set(id, key, val).await
+        }
+
+        async fn del(&self, id: &str, key: &str) -> Result<()> {
+            self.0.del(id, key).await
+        }
+
+        async fn entries(&self) -> Result<Vec<(String, String, Vec<u8>)>> {
+            self.0.entries().await
+        }
+    }
+
+    #[ockam_macros::test]
+    async fn rotated_values_are_read_without_the_old_key(ctx: &mut Context) -> Result<()> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(STORAGE_KEYS_FILE);
+        let inner = InterruptedStorage {
+            storage: InMemoryStorage::new(),
+            writes_left: Arc::new(AtomicUsize::new(usize::MAX)),
+        };
+
+        let encryption = StorageEncryption::create(&path)?;
+        let storage = encryption.storage(inner.clone());
+        for i in 0..10 {
+            storage
+                .set(
+                    "alice",
+                    format!("key{}", i),
+                    format!("value{}", i).into_bytes(),
+                )
+                .await?;
+        }
+        let old_value = inner.get("alice", "key0").await?.unwrap();
+        assert!(!old_value.windows(6).any(|w| w == b"value0"));
+
+        // The node stops after re-encrypting some of the values
+        inner.writes_left.store(4, Ordering::SeqCst);
+        assert!(encryption.rotate(&inner).await.is_err());
+
+        // It resumes the rotation once restarted, meanwhile every value is
+        // still readable
+        let encryption = StorageEncryption::open(&path)?.unwrap();
+        assert!(encryption.is_rotating());
+        let storage = encryption.storage(inner.clone());
+        for i in 0..10 {
+            let value = storage.get("alice", &format!("key{}", i)).await?;
+            assert_eq!(value, Some(format!("value{}", i).into_bytes()));
+        }
+        inner.writes_left.store(usize::MAX, Ordering::SeqCst);
+        assert_eq!(encryption.rotate(&inner).await?, 6);
+        assert!(!encryption.is_rotating());
+
+        // Only the new key is left, and it reads every value
+        let encryption = StorageEncryption::open(&path)?.unwrap();
+        let codec = encryption.codec();
+        assert!(codec.decode(old_value).is_err());
+        let storage = encryption.storage(inner);
+        for i in 0..10 {
+            let value = storage.get("alice", &format!("key{}", i)).await?;
+            assert_eq!(value, Some(format!("value{}", i).into_bytes()));
+        }
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn values_written_during_a_rotation_are_kept(ctx: &mut Context) -> Result<()> {
+        let dir = tempfile::tempdir().unwrap();
+        let encryption = StorageEncryption::create(dir.path().join(STORAGE_KEYS_FILE))?;
+        let inner = SlowStorage(InMemoryStorage::new());
+        let storage = encryption.storage(inner.clone());
+        storage
+            .set("alice", "key".to_string(), b"old".to_vec())
+            .await?;
+
+        // The value is written while the rotation re-encrypts it
+        let rotation = {
+            let encryption = encryption.clone();
+            let inner = inner.clone();
+            tokio::spawn(async move { encryption.rotate(&inner).await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        storage
+            .set("alice", "key".to_string(), b"new".to_vec())
+            .await?;
+        rotation.await.unwrap()?;
+
+        assert_eq!(storage.get("alice", "key").await?, Some(b"new".to_vec()));
+        ctx.stop().await
+    }
+}
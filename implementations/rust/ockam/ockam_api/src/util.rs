@@ -3,11 +3,21 @@ use crate::error::ApiError;
 use anyhow::anyhow;
 use core::str::FromStr;
 use ockam::{Address, Error, TCP};
-use ockam_core::{Route, LOCAL};
-use ockam_multiaddr::proto::{DnsAddr, Ip4, Ip6, Node, Project, Secure, Service, Space, Tcp};
-use ockam_multiaddr::{MultiAddr, Protocol};
+use ockam_core::{Route, TransportType, LOCAL};
+use ockam_multiaddr::proto::{DnsAddr, Ip4, Ip6, Node, Project, Secure, Service, Space, Tcp, Udp};
+use ockam_multiaddr::{MultiAddr, ProtoValue, Protocol};
+use ockam_transport_udp::UDP;
 use std::net::{SocketAddrV4, SocketAddrV6};
 
+/// Transport type and port of a `/tcp` or `/udp` protocol value
+fn transport_port(p: &ProtoValue) -> Option<(TransportType, u16)> {
+    match p.code() {
+        Tcp::CODE => Some((TCP, *p.cast::<Tcp>()?)),
+        Udp::CODE => Some((UDP, *p.cast::<Udp>()?)),
+        _ => None,
+    }
+}
+
 /// Go through a multiaddr and remove all instances of
 /// `/node/<whatever>` out of it and replaces it with a fully
 /// qualified address to the target
@@ -60,25 +70,22 @@ pub fn multiaddr_to_route(ma: &MultiAddr) -> Option<Route> {
         match p.code() {
             Ip4::CODE => {
                 let ip4 = p.cast::<Ip4>()?;
-                let tcp = it.next()?.cast::<Tcp>()?;
-                let add = Address::new(TCP, SocketAddrV4::new(*ip4, *tcp).to_string());
+                let (tt, port) = transport_port(&it.next()?)?;
+                let add = Address::new(tt, SocketAddrV4::new(*ip4, port).to_string());
                 rb = rb.append(add)
             }
             Ip6::CODE => {
                 let ip6 = p.cast::<Ip6>()?;
-                let tcp = it.next()?.cast::<Tcp>()?;
-                let add = Address::new(TCP, SocketAddrV6::new(*ip6, *tcp, 0, 0).to_string());
+                let (tt, port) = transport_port(&it.next()?)?;
+                let add = Address::new(tt, SocketAddrV6::new(*ip6, port, 0, 0).to_string());
                 rb = rb.append(add)
             }
             DnsAddr::CODE => {
                 let host = p.cast::<DnsAddr>()?;
-                if let Some(p) = it.peek() {
-                    if p.code() == Tcp::CODE {
-                        let tcp = p.cast::<Tcp>()?;
-                        rb = rb.append(Address::new(TCP, format!("{}:{}", &*host, *tcp)));
-                        let _ = it.next();
-                        continue;
-                    }
+                if let Some((tt, port)) = it.peek().and_then(transport_port) {
+                    rb = rb.append(Address::new(tt, format!("{}:{}", &*host, port)));
+                    let _ = it.next();
+                    continue;
                 }
                 rb = rb.append(Address::new(TCP, &*host))
             }
@@ -117,13 +124,8 @@ pub fn multiaddr_to_addr(ma: &MultiAddr) -> Option<Address> {
     match p.code() {
         DnsAddr::CODE => {
             let host = p.cast::<DnsAddr>()?;
-            if let Some(p) = it.peek() {
-                if p.code() == Tcp::CODE {
-                    let tcp = p.cast::<Tcp>()?;
-                    return Some(Address::new(TCP, format!("{}:{}", &*host, *tcp)));
-                }
-            }
-            None
+            let (tt, port) = it.peek().and_then(transport_port)?;
+            Some(Address::new(tt, format!("{}:{}", &*host, port)))
         }
         Service::CODE => {
             let local = p.cast::<Service>()?;
@@ -166,6 +168,21 @@ pub fn try_address_to_multiaddr(a: &Address) -> Result<MultiAddr, Error> {
                 ma.push_back(DnsAddr::new(a.address()))?
             }
         }
+        UDP => {
+            if let Ok(sa) = SocketAddrV4::from_str(a.address()) {
+                ma.push_back(Ip4::new(*sa.ip()))?;
+                ma.push_back(Udp::new(sa.port()))?
+            } else if let Ok(sa) = SocketAddrV6::from_str(a.address()) {
+                ma.push_back(Ip6::new(*sa.ip()))?;
+                ma.push_back(Udp::new(sa.port()))?
+            } else if let Some((host, port)) = a.address().split_once(':') {
+                ma.push_back(DnsAddr::new(host))?;
+                let n = u16::from_str(port).map_err(ApiError::wrap)?;
+                ma.push_back(Udp::new(n))?
+            } else {
+                return Err(ApiError::message(format!("UDP address {a} has no port")));
+            }
+        }
         LOCAL => ma.push_back(Service::new(a.address()))?,
         other => {
             error!(target: "ockam_api", transport = %other, "unsupported transport type");
@@ -1,5 +1,6 @@
+use ockam_core::errcode::Origin;
 use ockam_core::vault::KeyId;
-use ockam_core::{Address, Message};
+use ockam_core::{Address, Error, Message};
 use serde::{Deserialize, Serialize};
 
 /// Key Exchange completed message
@@ -24,6 +25,21 @@ impl KeyExchangeCompleted {
     }
 }
 
+/// Outcome of a key exchange, sent to the key exchange callback
+#[derive(Serialize, Deserialize, Debug, Message)]
+pub enum KeyExchangeOutcome {
+    /// The key exchange completed
+    Completed(KeyExchangeCompleted),
+    /// A vault operation failed, which aborted the key exchange
+    Failed(Error),
+}
+
+/// Whether `err` comes from the vault, e.g. a HSM which disconnected or a
+/// KMS which throttled a request, rather than from the other end
+pub fn is_vault_failure(err: &Error) -> bool {
+    err.code().origin == Origin::Vault
+}
+
 pub(crate) struct ChannelKeys {
     pub(crate) key: KeyId,
     pub(crate) nonce: u64,
@@ -18,16 +18,45 @@ pub enum SecureChannelError {
     InvalidHubResponse,
     /// Invalid LocalInfo type
     InvalidLocalInfoType,
+    /// Associated data is malformed or too long.
+    InvalidAssociatedData,
+    /// Associated data doesn't match the expected value.
+    AssociatedDataMismatch,
+    /// The key escrow recovery key isn't an X25519 public key.
+    InvalidRecoveryKey,
+    /// Escrowed keys can't be decrypted with the recovery key.
+    InvalidEscrowedKeys,
+    /// The frame of an encrypted message is malformed.
+    InvalidFrame,
+    /// The frame of an encrypted message is in a version this end doesn't speak.
+    UnsupportedFrameVersion,
+    /// Associated data was sent to an end which can't authenticate it.
+    AssociatedDataNotNegotiated,
+    /// Keys were rotated on a channel whose other end can't follow.
+    RekeyNotNegotiated,
+    /// The key of an encrypted message is too old or too far ahead.
+    InvalidKeyEpoch,
+    /// The key escrow storage can't keep more keys.
+    KeyEscrowFull,
 }
 
 impl From<SecureChannelError> for Error {
     fn from(e: SecureChannelError) -> Self {
         use SecureChannelError::*;
         let kind = match e {
-            KeyExchange | KeyExchangeNotComplete => Kind::Protocol,
-            InvalidInternalState | InvalidNonce | InvalidHubResponse | InvalidLocalInfoType => {
-                Kind::Invalid
+            KeyExchange | KeyExchangeNotComplete | InvalidFrame | InvalidKeyEpoch => Kind::Protocol,
+            UnsupportedFrameVersion | AssociatedDataNotNegotiated | RekeyNotNegotiated => {
+                Kind::Unsupported
             }
+            InvalidInternalState
+            | InvalidNonce
+            | InvalidHubResponse
+            | InvalidLocalInfoType
+            | InvalidAssociatedData
+            | AssociatedDataMismatch
+            | InvalidRecoveryKey
+            | InvalidEscrowedKeys => Kind::Invalid,
+            KeyEscrowFull => Kind::ResourceExhausted,
         };
 
         Self::new(Origin::Channel, kind, e)
@@ -44,6 +73,28 @@ impl core::fmt::Display for SecureChannelError {
             Self::KeyExchangeNotComplete => "key exchange process did not complete.".fmt(f),
             Self::InvalidHubResponse => "invalid response received from the Hub.".fmt(f),
             Self::InvalidLocalInfoType => "invalid LocalInfo type".fmt(f),
+            Self::InvalidAssociatedData => "associated data is malformed or too long.".fmt(f),
+            Self::AssociatedDataMismatch => {
+                "associated data doesn't match the expected value.".fmt(f)
+            }
+            Self::InvalidRecoveryKey => "the recovery key isn't an X25519 public key.".fmt(f),
+            Self::InvalidEscrowedKeys => {
+                "escrowed keys can't be decrypted with the recovery key.".fmt(f)
+            }
+            Self::InvalidFrame => "the frame of an encrypted message is malformed.".fmt(f),
+            Self::UnsupportedFrameVersion => {
+                "the frame of an encrypted message is in an unsupported version.".fmt(f)
+            }
+            Self::AssociatedDataNotNegotiated => {
+                "the other end of the channel doesn't support associated data.".fmt(f)
+            }
+            Self::RekeyNotNegotiated => {
+                "the other end of the channel doesn't support rekeys.".fmt(f)
+            }
+            Self::InvalidKeyEpoch => {
+                "the key of an encrypted message is too old or too far ahead.".fmt(f)
+            }
+            Self::KeyEscrowFull => "the key escrow storage can't keep more keys.".fmt(f),
         }
     }
 }
@@ -0,0 +1,244 @@
+//! Frames carrying the messages of an established secure channel
+//!
+//! Each end offers the latest frame version it speaks in the payload of
+//! its first key exchange message, and both use the latest version both
+//! speak. The payloads are authenticated by the key exchange, so they
+//! can't be tampered with to downgrade the channel. An end predating
+//! frame versions sends empty payloads and ignores ours, and only speaks
+//! [`LEGACY_FRAME_VERSION`].
+
+use crate::SecureChannelError;
+use ockam_core::compat::vec::Vec;
+use ockam_core::Result;
+
+/// The nonce, then the cipher text, with no associated data
+pub(crate) const LEGACY_FRAME_VERSION: u8 = 0;
+
+/// The version, the nonce, the length of the associated data, the
+/// associated data, then the cipher text
+///
+/// The version and the associated data are authenticated by the AEAD tag.
+pub(crate) const ASSOCIATED_DATA_FRAME_VERSION: u8 = 1;
+
+/// The version, the epoch of the key, then as
+/// [`ASSOCIATED_DATA_FRAME_VERSION`]
+///
+/// Both ends rotate the keys of the channel only when they speak it.
+pub(crate) const REKEY_FRAME_VERSION: u8 = 2;
+
+/// Latest frame version we speak
+pub(crate) const LATEST_FRAME_VERSION: u8 = REKEY_FRAME_VERSION;
+
+/// Payload of a key exchange message offering the frame versions we speak
+pub(crate) fn frame_version_offer() -> Vec<u8> {
+    vec![LATEST_FRAME_VERSION]
+}
+
+/// Latest frame version spoken by both ends, given the payload of the
+/// first key exchange message of the other end
+pub(crate) fn negotiate_frame_version(their_payload: &[u8]) -> u8 {
+    match their_payload.first() {
+        Some(theirs) => LATEST_FRAME_VERSION.min(*theirs),
+        None => LEGACY_FRAME_VERSION,
+    }
+}
+
+/// An encrypted message, as sent between the ends of a channel
+pub(crate) struct Frame<'a> {
+    pub(crate) version: u8,
+    /// How many times the key was rotated, always 0 before
+    /// [`REKEY_FRAME_VERSION`]
+    pub(crate) epoch: u32,
+    pub(crate) nonce: u64,
+    pub(crate) associated_data: &'a [u8],
+    pub(crate) cipher_text: &'a [u8],
+}
+
+impl<'a> Frame<'a> {
+    /// Decode a frame in the version given by its first byte
+    ///
+    /// A legacy frame starts with the nonce, whose first byte is zero
+    /// until 2^56 messages were sent.
+    pub(crate) fn decode(payload: &'a [u8]) -> Result<Self> {
+        let (version, rest) = match payload.first() {
+            Some(&LEGACY_FRAME_VERSION) => (LEGACY_FRAME_VERSION, payload),
+            Some(&version) if version <= LATEST_FRAME_VERSION => (version, &payload[1..]),
+            Some(_) => return Err(SecureChannelError::UnsupportedFrameVersion.into()),
+            None => return Err(SecureChannelError::InvalidFrame.into()),
+        };
+
+        let (epoch, rest) = if version >= REKEY_FRAME_VERSION {
+            if rest.len() < 4 {
+                return Err(SecureChannelError::InvalidFrame.into());
+            }
+            let (epoch, rest) = rest.split_at(4);
+            let epoch = u32::from_be_bytes([epoch[0], epoch[1], epoch[2], epoch[3]]);
+            (epoch, rest)
+        } else {
+            (0, rest)
+        };
+
+        if rest.len() < 8 {
+            return Err(SecureChannelError::InvalidFrame.into());
+        }
+        let (nonce, rest) = rest.split_at(8);
+        let mut nonce_bytes = [0; 8];
+        nonce_bytes.copy_from_slice(nonce);
+        let nonce = u64::from_be_bytes(nonce_bytes);
+
+        if version == LEGACY_FRAME_VERSION {
+            return Ok(Self {
+                version,
+                epoch,
+                nonce,
+                associated_data: &[],
+                cipher_text: rest,
+            });
+        }
+
+        if rest.len() < 2 {
+            return Err(SecureChannelError::InvalidFrame.into());
+        }
+        let (associated_data_len, rest) = rest.split_at(2);
+        let associated_data_len =
+            u16::from_be_bytes([associated_data_len[0], associated_data_len[1]]) as usize;
+        if rest.len() < associated_data_len {
+            return Err(SecureChannelError::InvalidFrame.into());
+        }
+        let (associated_data, cipher_text) = rest.split_at(associated_data_len);
+        Ok(Self {
+            version,
+            epoch,
+            nonce,
+            associated_data,
+            cipher_text,
+        })
+    }
+
+    /// Encode the frame in its version
+    pub(crate) fn encode(&self) -> Result<Vec<u8>> {
+        let mut res = Vec::new();
+        if self.version == LEGACY_FRAME_VERSION {
+            if !self.associated_data.is_empty() {
+                return Err(SecureChannelError::AssociatedDataNotNegotiated.into());
+            }
+        } else {
+            res.push(self.version);
+        }
+        if self.version >= REKEY_FRAME_VERSION {
+            res.extend_from_slice(&self.epoch.to_be_bytes());
+        } else if self.epoch != 0 {
+            return Err(SecureChannelError::RekeyNotNegotiated.into());
+        }
+        res.extend_from_slice(&self.nonce.to_be_bytes());
+        if self.version != LEGACY_FRAME_VERSION {
+            let associated_data_len: u16 = self
+                .associated_data
+                .len()
+                .try_into()
+                .map_err(|_| SecureChannelError::InvalidAssociatedData)?;
+            res.extend_from_slice(&associated_data_len.to_be_bytes());
+            res.extend_from_slice(self.associated_data);
+        }
+        res.extend_from_slice(self.cipher_text);
+        Ok(res)
+    }
+
+    /// Associated data of the AEAD encryption of a frame of `version`
+    /// carrying `associated_data`
+    pub(crate) fn aead_associated_data(version: u8, associated_data: &[u8]) -> Vec<u8> {
+        if version == LEGACY_FRAME_VERSION {
+            return Vec::new();
+        }
+        let mut res = vec![version];
+        res.extend_from_slice(associated_data);
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ockam_core::errcode::Kind;
+
+    #[test]
+    fn frames_round_trip() {
+        for version in [
+            LEGACY_FRAME_VERSION,
+            ASSOCIATED_DATA_FRAME_VERSION,
+            REKEY_FRAME_VERSION,
+        ] {
+            let associated_data: &[u8] = if version == LEGACY_FRAME_VERSION {
+                &[]
+            } else {
+                b"tenant-1"
+            };
+            let epoch = if version >= REKEY_FRAME_VERSION { 3 } else { 0 };
+            let frame = Frame {
+                version,
+                epoch,
+                nonce: 7,
+                associated_data,
+                cipher_text: &[1, 2, 3],
+            };
+            let encoded = frame.encode().unwrap();
+            let decoded = Frame::decode(&encoded).unwrap();
+            assert_eq!(decoded.version, version);
+            assert_eq!(decoded.epoch, epoch);
+            assert_eq!(decoded.nonce, 7);
+            assert_eq!(decoded.associated_data, associated_data);
+            assert_eq!(decoded.cipher_text, &[1, 2, 3]);
+        }
+    }
+
+    #[test]
+    fn invalid_frames_are_rejected() {
+        for payload in [
+            &[][..],
+            &[0, 0, 0],
+            &[1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 5, 1],
+            &[2, 0, 0],
+        ] {
+            let err = Frame::decode(payload).err().unwrap();
+            assert_eq!(err.code().kind, Kind::Protocol);
+        }
+        let err = Frame::decode(&[LATEST_FRAME_VERSION + 1; 16])
+            .err()
+            .unwrap();
+        assert_eq!(err.code().kind, Kind::Unsupported);
+
+        let legacy_with_associated_data = Frame {
+            version: LEGACY_FRAME_VERSION,
+            epoch: 0,
+            nonce: 7,
+            associated_data: b"tenant-1",
+            cipher_text: &[1, 2, 3],
+        };
+        let err = legacy_with_associated_data.encode().err().unwrap();
+        assert_eq!(err.code().kind, Kind::Unsupported);
+
+        let rotated_without_epochs = Frame {
+            version: ASSOCIATED_DATA_FRAME_VERSION,
+            epoch: 1,
+            nonce: 7,
+            associated_data: &[],
+            cipher_text: &[1, 2, 3],
+        };
+        let err = rotated_without_epochs.encode().err().unwrap();
+        assert_eq!(err.code().kind, Kind::Unsupported);
+    }
+
+    #[test]
+    fn frame_version_is_negotiated() {
+        assert_eq!(
+            negotiate_frame_version(&frame_version_offer()),
+            LATEST_FRAME_VERSION
+        );
+        assert_eq!(negotiate_frame_version(&[]), LEGACY_FRAME_VERSION);
+        assert_eq!(
+            negotiate_frame_version(&[ASSOCIATED_DATA_FRAME_VERSION]),
+            ASSOCIATED_DATA_FRAME_VERSION
+        );
+        assert_eq!(negotiate_frame_version(&[9]), LATEST_FRAME_VERSION);
+    }
+}
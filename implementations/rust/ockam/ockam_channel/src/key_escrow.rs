@@ -0,0 +1,428 @@
+use crate::{Frame, SecureChannelEncryptor, SecureChannelError, SecureChannelVault};
+use ockam_core::compat::{
+    boxed::Box,
+    sync::{Arc, Mutex},
+    vec::Vec,
+};
+use ockam_core::vault::{
+    KeyId, PublicKey, SecretAttributes, SecretKey, SecretPersistence, SecretType,
+    AES256_SECRET_LENGTH_U32, AES256_SECRET_LENGTH_USIZE, CURVE25519_SECRET_LENGTH_U32,
+};
+use ockam_core::{async_trait, Address, Decodable, Encodable, Error, Result, TransportMessage};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// HKDF info binding the key agreed with the recovery key to key escrow
+const ESCROW_INFO: &[u8] = b"ockam secure channel key escrow";
+
+/// Every escrow key encrypts a single message, under this nonce
+const ESCROW_NONCE: [u8; 12] = [0; 12];
+
+/// Records a [`KeyEscrow`] created with [`KeyEscrow::new`] keeps until
+/// they are exported
+pub const DEFAULT_KEY_ESCROW_CAPACITY: usize = 1024;
+
+/// Escrows the keys of secure channels, encrypted to a recovery key held
+/// apart from the nodes, so that its holder can decrypt the messages
+/// captured on these channels
+///
+/// Whoever holds the recovery key can read every escrowed channel, so this
+/// is only meant for environments where plaintext recovery is mandated.
+/// Channels only escrow their keys when explicitly given a `KeyEscrow`,
+/// and every escrow is logged as a warning. The keys of each channel are
+/// encrypted with a key agreed between a fresh X25519 key and the recovery
+/// key, then kept in a [`KeyEscrowStorage`], which clones share, until they
+/// are [exported](Self::export). The handshake, and the encryption of the
+/// messages, are unchanged.
+///
+/// A channel whose keys can't be kept, e.g. because the storage is full,
+/// isn't established.
+#[derive(Clone)]
+pub struct KeyEscrow {
+    recovery_key: PublicKey,
+    storage: Arc<dyn KeyEscrowStorage>,
+}
+
+impl KeyEscrow {
+    /// Escrow the keys of channels to `recovery_key`, an X25519 public key,
+    /// keeping at most [`DEFAULT_KEY_ESCROW_CAPACITY`] records in memory
+    pub fn new(recovery_key: PublicKey) -> Result<Self> {
+        Self::with_storage(
+            recovery_key,
+            InMemoryKeyEscrowStorage::new(DEFAULT_KEY_ESCROW_CAPACITY),
+        )
+    }
+
+    /// Escrow the keys of channels to `recovery_key`, an X25519 public key,
+    /// keeping the records in `storage`
+    pub fn with_storage(recovery_key: PublicKey, storage: impl KeyEscrowStorage) -> Result<Self> {
+        if recovery_key.stype() != SecretType::X25519 {
+            return Err(SecureChannelError::InvalidRecoveryKey.into());
+        }
+        warn!(
+            "SecureChannel key escrow is enabled: the holder of the recovery key \
+             can decrypt the channels given this escrow"
+        );
+        Ok(Self {
+            recovery_key,
+            storage: Arc::new(storage),
+        })
+    }
+
+    /// Public key the channel keys are encrypted to
+    pub fn recovery_key(&self) -> &PublicKey {
+        &self.recovery_key
+    }
+
+    /// Remove the keys escrowed so far from the storage, oldest first
+    pub async fn take_records(&self) -> Result<Vec<EscrowedChannelKeys>> {
+        self.storage.take().await
+    }
+
+    /// Remove the keys escrowed so far from the storage, encoded to be
+    /// handed to the holder of the recovery key, who reads them with
+    /// [`EscrowedChannelKeys::import`]
+    pub async fn export(&self) -> Result<Vec<u8>> {
+        self.take_records().await?.encode()
+    }
+
+    /// Encrypt the keys of the channel whose decryptor is at `channel` to
+    /// the recovery key, and record them
+    pub(crate) async fn escrow<V: SecureChannelVault>(
+        &self,
+        vault: &V,
+        channel: &Address,
+        encrypt_key: &KeyId,
+        decrypt_key: &KeyId,
+    ) -> Result<()> {
+        let mut keys = vault.secret_export(encrypt_key).await?.as_ref().to_vec();
+        keys.extend_from_slice(vault.secret_export(decrypt_key).await?.as_ref());
+        let keys = SecretKey::new(keys);
+
+        let ephemeral_secret = vault
+            .secret_generate(SecretAttributes::new(
+                SecretType::X25519,
+                SecretPersistence::Ephemeral,
+                CURVE25519_SECRET_LENGTH_U32,
+            ))
+            .await?;
+        let ephemeral_public_key = vault.secret_public_key_get(&ephemeral_secret).await;
+        let escrow_key = agree_escrow_key(vault, &ephemeral_secret, &self.recovery_key).await;
+        vault.secret_destroy(ephemeral_secret).await?;
+        let (ephemeral_public_key, escrow_key) = (ephemeral_public_key?, escrow_key?);
+
+        let sealed_keys = vault
+            .aead_aes_gcm_encrypt(
+                &escrow_key,
+                keys.as_ref(),
+                &ESCROW_NONCE,
+                ephemeral_public_key.data(),
+            )
+            .await;
+        vault.secret_destroy(escrow_key).await?;
+
+        self.storage
+            .store(EscrowedChannelKeys {
+                channel: channel.clone(),
+                ephemeral_public_key,
+                sealed_keys: sealed_keys?.to_vec(),
+            })
+            .await?;
+        warn!("Escrowed the keys of SecureChannel {}", channel);
+        Ok(())
+    }
+}
+
+/// Derive the key the channel keys are encrypted with from the X25519 key
+/// agreement between `secret` and `peer_public_key`
+async fn agree_escrow_key<V: SecureChannelVault>(
+    vault: &V,
+    secret: &KeyId,
+    peer_public_key: &PublicKey,
+) -> Result<KeyId> {
+    let shared_secret = vault.ec_diffie_hellman(secret, peer_public_key).await?;
+    let attributes = SecretAttributes::new(
+        SecretType::Aes,
+        SecretPersistence::Ephemeral,
+        AES256_SECRET_LENGTH_U32,
+    );
+    let keys = vault
+        .hkdf_sha256(&shared_secret, ESCROW_INFO, None, vec![attributes])
+        .await;
+    vault.secret_destroy(shared_secret).await?;
+    keys?
+        .pop()
+        .ok_or_else(|| SecureChannelError::InvalidInternalState.into())
+}
+
+/// Keeps the keys escrowed by a [`KeyEscrow`] until they are exported
+///
+/// Storages are bounded: once full, they fail to keep more keys until the
+/// records they hold are taken.
+#[async_trait]
+pub trait KeyEscrowStorage: Send + Sync + 'static {
+    /// Keep `record`, failing with [`SecureChannelError::KeyEscrowFull`]
+    /// if the storage is full
+    async fn store(&self, record: EscrowedChannelKeys) -> Result<()>;
+
+    /// Remove and return the records kept so far, oldest first
+    async fn take(&self) -> Result<Vec<EscrowedChannelKeys>>;
+}
+
+/// Keeps escrowed keys in memory, where they are lost when the node stops
+pub struct InMemoryKeyEscrowStorage {
+    capacity: usize,
+    records: Mutex<Vec<EscrowedChannelKeys>>,
+}
+
+impl InMemoryKeyEscrowStorage {
+    /// Keep at most `capacity` records
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            records: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl KeyEscrowStorage for InMemoryKeyEscrowStorage {
+    async fn store(&self, record: EscrowedChannelKeys) -> Result<()> {
+        let mut records = self.records.lock().unwrap();
+        if records.len() >= self.capacity {
+            return Err(SecureChannelError::KeyEscrowFull.into());
+        }
+        records.push(record);
+        Ok(())
+    }
+
+    async fn take(&self) -> Result<Vec<EscrowedChannelKeys>> {
+        Ok(core::mem::take(&mut *self.records.lock().unwrap()))
+    }
+}
+
+#[cfg(feature = "std")]
+pub use file_storage::FileKeyEscrowStorage;
+
+#[cfg(feature = "std")]
+mod file_storage {
+    use super::{EscrowedChannelKeys, KeyEscrowStorage};
+    use crate::SecureChannelError;
+    use ockam_core::errcode::{Kind, Origin};
+    use ockam_core::{async_trait, Decodable, Encodable, Error, Result};
+    use ockam_node::tokio::{sync::Mutex, task};
+    use std::fs::{File, OpenOptions};
+    use std::io::{Read, Write};
+    use std::path::{Path, PathBuf};
+
+    /// Keeps escrowed keys in a file, so they survive restarts
+    ///
+    /// Every record is appended and flushed to disk before the channel
+    /// whose keys it holds is established.
+    pub struct FileKeyEscrowStorage {
+        path: PathBuf,
+        capacity: usize,
+        /// Number of records in the file
+        count: Mutex<usize>,
+    }
+
+    impl FileKeyEscrowStorage {
+        /// Keep at most `capacity` records in the file at `path`,
+        /// including the ones it already holds
+        pub async fn open(path: impl AsRef<Path>, capacity: usize) -> Result<Self> {
+            let path = path.as_ref().to_path_buf();
+            let p = path.clone();
+            let count = blocking(move || Ok(read_records(&p)?.len())).await?;
+            Ok(Self {
+                path,
+                capacity,
+                count: Mutex::new(count),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl KeyEscrowStorage for FileKeyEscrowStorage {
+        async fn store(&self, record: EscrowedChannelKeys) -> Result<()> {
+            let mut count = self.count.lock().await;
+            if *count >= self.capacity {
+                return Err(SecureChannelError::KeyEscrowFull.into());
+            }
+            let encoded = record.encode()?;
+            let path = self.path.clone();
+            blocking(move || {
+                let mut file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .map_err(io_error)?;
+                file.write_all(&(encoded.len() as u32).to_be_bytes())
+                    .map_err(io_error)?;
+                file.write_all(&encoded).map_err(io_error)?;
+                file.sync_data().map_err(io_error)
+            })
+            .await?;
+            *count += 1;
+            Ok(())
+        }
+
+        async fn take(&self) -> Result<Vec<EscrowedChannelKeys>> {
+            let mut count = self.count.lock().await;
+            let path = self.path.clone();
+            let records = blocking(move || {
+                let records = read_records(&path)?;
+                if !records.is_empty() {
+                    let file = File::create(&path).map_err(io_error)?;
+                    file.sync_all().map_err(io_error)?;
+                }
+                Ok(records)
+            })
+            .await?;
+            *count = 0;
+            Ok(records)
+        }
+    }
+
+    /// Read the length prefixed records of the file at `path`, none if it
+    /// doesn't exist
+    fn read_records(path: &Path) -> Result<Vec<EscrowedChannelKeys>> {
+        let mut data = Vec::new();
+        match File::open(path) {
+            Ok(mut file) => {
+                file.read_to_end(&mut data).map_err(io_error)?;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(io_error(e)),
+        }
+
+        let mut records = Vec::new();
+        let mut rest = data.as_slice();
+        while !rest.is_empty() {
+            if rest.len() < 4 {
+                return Err(SecureChannelError::InvalidEscrowedKeys.into());
+            }
+            let (len, tail) = rest.split_at(4);
+            let len = u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize;
+            if tail.len() < len {
+                return Err(SecureChannelError::InvalidEscrowedKeys.into());
+            }
+            let (record, tail) = tail.split_at(len);
+            records.push(EscrowedChannelKeys::decode(record)?);
+            rest = tail;
+        }
+        Ok(records)
+    }
+
+    fn io_error(e: std::io::Error) -> Error {
+        Error::new(Origin::Channel, Kind::Io, e)
+    }
+
+    /// Run file operations off the async runtime
+    async fn blocking<T, F>(f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> Result<T> + Send + 'static,
+    {
+        task::spawn_blocking(f)
+            .await
+            .map_err(|e| Error::new(Origin::Channel, Kind::Io, e))?
+    }
+}
+
+/// Keys of a secure channel encrypted to a recovery key by a [`KeyEscrow`]
+///
+/// Holds nothing readable without the recovery key, so it can be stored
+/// with the rest of the node's data.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EscrowedChannelKeys {
+    channel: Address,
+    ephemeral_public_key: PublicKey,
+    sealed_keys: Vec<u8>,
+}
+
+impl EscrowedChannelKeys {
+    /// Read the records returned by [`KeyEscrow::export`]
+    pub fn import(exported: &[u8]) -> Result<Vec<Self>> {
+        Vec::<Self>::decode(exported)
+    }
+
+    /// Address of the decryptor of the channel which escrowed its keys
+    pub fn channel(&self) -> &Address {
+        &self.channel
+    }
+
+    /// Decrypt the keys with the recovery key, whose secret is at
+    /// `recovery_secret` in `vault`
+    pub async fn recover<V: SecureChannelVault>(
+        &self,
+        vault: &V,
+        recovery_secret: &KeyId,
+    ) -> Result<RecoveredChannelKeys> {
+        let escrow_key =
+            agree_escrow_key(vault, recovery_secret, &self.ephemeral_public_key).await?;
+        let keys = vault
+            .aead_aes_gcm_decrypt(
+                &escrow_key,
+                &self.sealed_keys,
+                &ESCROW_NONCE,
+                self.ephemeral_public_key.data(),
+            )
+            .await;
+        vault.secret_destroy(escrow_key).await?;
+        let keys = SecretKey::new(
+            keys.map_err(|_| SecureChannelError::InvalidEscrowedKeys)?
+                .to_vec(),
+        );
+        if keys.as_ref().len() != 2 * AES256_SECRET_LENGTH_USIZE {
+            return Err(SecureChannelError::InvalidEscrowedKeys.into());
+        }
+
+        let (encrypt_key, decrypt_key) = keys.as_ref().split_at(AES256_SECRET_LENGTH_USIZE);
+        Ok(RecoveredChannelKeys {
+            keys: [
+                SecretKey::new(encrypt_key.to_vec()),
+                SecretKey::new(decrypt_key.to_vec()),
+            ],
+        })
+    }
+}
+
+/// Keys of a secure channel recovered from a [`KeyEscrow`], which decrypt
+/// the messages captured on the channel in both directions
+pub struct RecoveredChannelKeys {
+    keys: [SecretKey; 2],
+}
+
+impl RecoveredChannelKeys {
+    /// Decrypt a message captured between both ends of the channel, sent
+    /// by either end, using `vault` for the decryption
+    pub async fn decrypt<V: SecureChannelVault>(
+        &self,
+        vault: &V,
+        captured: &[u8],
+    ) -> Result<TransportMessage> {
+        let payload = Vec::<u8>::decode(captured)?;
+        let frame = Frame::decode(&payload)?;
+        let (_, nonce) = SecureChannelEncryptor::<V>::convert_nonce_from_u64(frame.nonce);
+        let associated_data = Frame::aead_associated_data(frame.version, frame.associated_data);
+
+        let attributes = SecretAttributes::new(
+            SecretType::Aes,
+            SecretPersistence::Ephemeral,
+            AES256_SECRET_LENGTH_U32,
+        );
+        let mut last_error: Option<Error> = None;
+        for key in &self.keys {
+            let key_id = vault.secret_import(key.as_ref(), attributes).await?;
+            let plain_text = vault
+                .aead_aes_gcm_decrypt(&key_id, frame.cipher_text, &nonce, &associated_data)
+                .await;
+            vault.secret_destroy(key_id).await?;
+            match plain_text {
+                Ok(plain_text) => return TransportMessage::decode(&plain_text),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| SecureChannelError::InvalidInternalState.into()))
+    }
+}
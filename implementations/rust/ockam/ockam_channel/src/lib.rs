@@ -24,7 +24,10 @@ extern crate alloc;
 
 mod common;
 mod error;
+mod frame;
+mod key_escrow;
 mod local_info;
+mod rekey;
 mod secure_channel;
 mod secure_channel_decryptor;
 mod secure_channel_encryptor;
@@ -33,7 +36,10 @@ mod traits;
 
 pub use common::*;
 pub use error::*;
+pub(crate) use frame::*;
+pub use key_escrow::*;
 pub use local_info::*;
+pub(crate) use rekey::*;
 pub use secure_channel::*;
 pub use secure_channel_decryptor::*;
 pub(crate) use secure_channel_encryptor::*;
@@ -42,10 +48,21 @@ pub use traits::*;
 
 #[cfg(test)]
 mod tests {
-    use crate::SecureChannel;
+    use crate::{
+        Frame, KeyEscrow, SecureChannel, SecureChannelAssociatedData, SecureChannelListener,
+        LEGACY_FRAME_VERSION,
+    };
+    use core::time::Duration;
     use ockam_core::compat::string::{String, ToString};
-    use ockam_core::{AsyncTryClone, Result, Route};
-    use ockam_key_exchange_core::NewKeyExchanger;
+    use ockam_core::compat::sync::{Arc, Mutex};
+    use ockam_core::compat::vec::Vec;
+    use ockam_core::vault::{
+        SecretAttributes, SecretPersistence, SecretType, SecretVault, CURVE25519_SECRET_LENGTH_U32,
+    };
+    use ockam_core::{
+        async_trait, Any, AsyncTryClone, Decodable, LocalMessage, Result, Route, Routed, Worker,
+    };
+    use ockam_key_exchange_core::{CompletedKeyExchange, KeyExchanger, NewKeyExchanger};
     use ockam_key_exchange_xx::XXNewKeyExchanger;
     use ockam_node::Context;
     use ockam_vault::Vault;
@@ -79,4 +96,395 @@ mod tests {
         assert_eq!(ctx.receive::<String>().await?, test_msg);
         ctx.stop().await
     }
+
+    #[ockam_macros::test]
+    async fn empty_messages_round_trip(ctx: &mut Context) -> Result<()> {
+        let vault = Vault::create();
+        let new_key_exchanger = XXNewKeyExchanger::new(vault.async_try_clone().await?);
+        SecureChannel::create_listener_extended(
+            ctx,
+            "secure_channel_listener".to_string(),
+            new_key_exchanger.async_try_clone().await?,
+            vault.async_try_clone().await?,
+        )
+        .await?;
+        let initiator = SecureChannel::create_extended(
+            ctx,
+            Route::new().append("secure_channel_listener"),
+            None,
+            new_key_exchanger.initiator().await?,
+            vault,
+        )
+        .await?;
+        let route = Route::new().append(initiator.address()).append("app");
+
+        // An empty payload is delivered as an empty message, and doesn't
+        // disturb the following messages
+        ctx.send(route.clone(), ()).await?;
+        ctx.send(route, "Hello, channel".to_string()).await?;
+        let msg = ctx.receive::<Any>().await?.take();
+        assert!(msg.payload().is_empty());
+        assert_eq!(ctx.receive::<String>().await?, "Hello, channel");
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn associated_data_must_match(ctx: &mut Context) -> Result<()> {
+        let vault = Vault::create();
+        let new_key_exchanger = XXNewKeyExchanger::new(vault.async_try_clone().await?);
+        SecureChannel::create_listener_with_associated_data(
+            ctx,
+            "secure_channel_listener".to_string(),
+            new_key_exchanger.async_try_clone().await?,
+            vault.async_try_clone().await?,
+            b"tenant-1".to_vec(),
+        )
+        .await?;
+        let initiator = SecureChannel::create_extended(
+            ctx,
+            Route::new().append("secure_channel_listener"),
+            None,
+            new_key_exchanger.initiator().await?,
+            vault,
+        )
+        .await?;
+        let route = Route::new().append(initiator.address()).append("app");
+
+        // Mismatching associated data is rejected
+        ctx.send_with_local_info(
+            route.clone(),
+            "Wrong tenant".to_string(),
+            vec![SecureChannelAssociatedData::new(b"tenant-2".to_vec()).to_local_info()],
+        )
+        .await?;
+        assert!(ctx.receive_timeout::<String>(1).await.is_err());
+
+        // Missing associated data is rejected
+        ctx.send(route.clone(), "No tenant".to_string()).await?;
+        assert!(ctx.receive_timeout::<String>(1).await.is_err());
+
+        // Matching associated data is accepted and passed on to the receiver
+        let test_msg = "Hello, tenant".to_string();
+        let associated_data = SecureChannelAssociatedData::new(b"tenant-1".to_vec());
+        ctx.send_with_local_info(
+            route,
+            test_msg.clone(),
+            vec![associated_data.to_local_info()],
+        )
+        .await?;
+        let msg = ctx.receive::<String>().await?.take();
+        assert_eq!(
+            SecureChannelAssociatedData::find_info(msg.local_message()),
+            Some(associated_data)
+        );
+        assert_eq!(msg.body(), test_msg);
+
+        ctx.stop().await
+    }
+
+    /// Key exchanger of an end predating frame versions, which sends empty
+    /// payloads and ignores the ones it receives
+    struct PreFrameVersions<K>(K);
+
+    #[async_trait]
+    impl<K: KeyExchanger + Send + Sync> KeyExchanger for PreFrameVersions<K> {
+        async fn name(&self) -> Result<String> {
+            self.0.name().await
+        }
+
+        async fn generate_request(&mut self, _payload: &[u8]) -> Result<Vec<u8>> {
+            self.0.generate_request(&[]).await
+        }
+
+        async fn handle_response(&mut self, response: &[u8]) -> Result<Vec<u8>> {
+            self.0.handle_response(response).await?;
+            Ok(Vec::new())
+        }
+
+        async fn is_complete(&self) -> Result<bool> {
+            self.0.is_complete().await
+        }
+
+        async fn finalize(self) -> Result<CompletedKeyExchange> {
+            self.0.finalize().await
+        }
+    }
+
+    #[ockam_macros::test]
+    async fn peer_without_frame_versions_gets_legacy_frames(ctx: &mut Context) -> Result<()> {
+        let vault = Vault::create();
+        let captured = Arc::new(Mutex::new(Vec::new()));
+        ctx.start_worker("tap", Tap(captured.clone())).await?;
+
+        let new_key_exchanger = XXNewKeyExchanger::new(vault.async_try_clone().await?);
+        SecureChannel::create_listener_extended(
+            ctx,
+            "secure_channel_listener".to_string(),
+            new_key_exchanger.async_try_clone().await?,
+            vault.async_try_clone().await?,
+        )
+        .await?;
+        let initiator = SecureChannel::create_extended(
+            ctx,
+            Route::new().append("tap").append("secure_channel_listener"),
+            None,
+            PreFrameVersions(new_key_exchanger.initiator().await?),
+            vault,
+        )
+        .await?;
+        let route = Route::new().append(initiator.address()).append("app");
+
+        // A legacy frame can't carry associated data
+        ctx.send_with_local_info(
+            route.clone(),
+            "Hello, tenant".to_string(),
+            vec![SecureChannelAssociatedData::new(b"tenant-1".to_vec()).to_local_info()],
+        )
+        .await?;
+        assert!(ctx.receive_timeout::<String>(1).await.is_err());
+
+        let test_msg = "Hello, channel".to_string();
+        ctx.send(route, test_msg.clone()).await?;
+        assert_eq!(ctx.receive::<String>().await?, test_msg);
+
+        let payload = captured.lock().unwrap().last().unwrap().clone();
+        let frame = Vec::<u8>::decode(&payload)?;
+        assert_eq!(Frame::decode(&frame)?.version, LEGACY_FRAME_VERSION);
+
+        ctx.stop().await
+    }
+
+    /// Records the messages passing through it, like someone watching the
+    /// wire between both ends of a channel
+    struct Tap(Arc<Mutex<Vec<Vec<u8>>>>);
+
+    #[async_trait]
+    impl Worker for Tap {
+        type Message = Any;
+        type Context = Context;
+
+        async fn handle_message(&mut self, ctx: &mut Context, msg: Routed<Any>) -> Result<()> {
+            let mut local_message = msg.into_local_message();
+            let transport_message = local_message.transport_mut();
+            self.0
+                .lock()
+                .unwrap()
+                .push(transport_message.payload.clone());
+            transport_message.onward_route.step()?;
+            transport_message
+                .return_route
+                .modify()
+                .prepend(ctx.address());
+            ctx.forward(local_message).await
+        }
+    }
+
+    #[ockam_macros::test]
+    async fn responder_rekeys_the_channel(ctx: &mut Context) -> Result<()> {
+        let vault = Vault::create();
+        let recovery_secret = vault
+            .secret_generate(SecretAttributes::new(
+                SecretType::X25519,
+                SecretPersistence::Ephemeral,
+                CURVE25519_SECRET_LENGTH_U32,
+            ))
+            .await?;
+        let key_escrow = KeyEscrow::new(vault.secret_public_key_get(&recovery_secret).await?)?;
+
+        let captured = Arc::new(Mutex::new(Vec::new()));
+        ctx.start_worker("tap", Tap(captured.clone())).await?;
+
+        // Only the responder is configured to rekey, the initiator follows
+        let new_key_exchanger = XXNewKeyExchanger::new(vault.async_try_clone().await?);
+        let listener =
+            SecureChannelListener::new(new_key_exchanger.async_try_clone().await?, vault.clone())
+                .with_key_escrow(key_escrow.clone())
+                .with_rekey_after(3);
+        ctx.start_worker("secure_channel_listener", listener)
+            .await?;
+        let initiator = SecureChannel::create_extended(
+            ctx,
+            Route::new().append("tap").append("secure_channel_listener"),
+            None,
+            new_key_exchanger.initiator().await?,
+            vault.async_try_clone().await?,
+        )
+        .await?;
+
+        for i in 0..10 {
+            let ping = format!("ping {}", i);
+            ctx.send(
+                Route::new().append(initiator.address()).append("app"),
+                ping.clone(),
+            )
+            .await?;
+            let msg = ctx.receive::<String>().await?.take();
+            let return_route = msg.return_route();
+            assert_eq!(msg.body(), ping);
+
+            let pong = format!("pong {}", i);
+            ctx.send(return_route, pong.clone()).await?;
+            assert_eq!(ctx.receive::<String>().await?.take().body(), pong);
+        }
+
+        // The keys agreed at the handshake read the first messages, not the
+        // last ones
+        let keys = key_escrow.take_records().await?[0]
+            .recover(&vault, &recovery_secret)
+            .await?;
+        let captured = captured.lock().unwrap().clone();
+        let mut readable = 0;
+        for payload in &captured {
+            if keys.decrypt(&vault, payload).await.is_ok() {
+                readable += 1;
+            }
+        }
+        assert!(readable > 0);
+        assert!(keys
+            .decrypt(&vault, captured.last().unwrap())
+            .await
+            .is_err());
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn cached_return_route_survives_rekeys(ctx: &mut Context) -> Result<()> {
+        let vault = Vault::create();
+
+        let new_key_exchanger = XXNewKeyExchanger::new(vault.async_try_clone().await?);
+        let listener =
+            SecureChannelListener::new(new_key_exchanger.async_try_clone().await?, vault.clone())
+                .with_rekey_after(3);
+        ctx.start_worker("secure_channel_listener", listener)
+            .await?;
+        let initiator = SecureChannel::create_extended(
+            ctx,
+            Route::new().append("secure_channel_listener"),
+            None,
+            new_key_exchanger.initiator().await?,
+            vault,
+        )
+        .await?;
+        let route = Route::new().append(initiator.address()).append("app");
+
+        ctx.send(route.clone(), "ping 0".to_string()).await?;
+        let cached_route = ctx.receive::<String>().await?.take().return_route();
+
+        // Enough messages for the keys to be rotated several times
+        for i in 1..10 {
+            ctx.send(route.clone(), format!("ping {}", i)).await?;
+            assert_eq!(
+                ctx.receive::<String>().await?.take().body(),
+                format!("ping {}", i)
+            );
+        }
+
+        ctx.send(cached_route, "pong".to_string()).await?;
+        assert_eq!(ctx.receive::<String>().await?.take().body(), "pong");
+
+        ctx.stop().await
+    }
+
+    /// Delivers the messages passing through it like a lossy datagram
+    /// transport: after the key exchange, the messages are swapped by pairs
+    /// and two in a row are lost
+    #[derive(Default)]
+    struct Shuffler {
+        seen: usize,
+        held: Option<LocalMessage>,
+    }
+
+    impl Shuffler {
+        const KEY_EXCHANGE_MESSAGES: usize = 3;
+        const LOST: [usize; 2] = [7, 8];
+    }
+
+    #[async_trait]
+    impl Worker for Shuffler {
+        type Message = Any;
+        type Context = Context;
+
+        async fn handle_message(&mut self, ctx: &mut Context, msg: Routed<Any>) -> Result<()> {
+            let mut local_message = msg.into_local_message();
+            let transport_message = local_message.transport_mut();
+            transport_message.onward_route.step()?;
+            transport_message
+                .return_route
+                .modify()
+                .prepend(ctx.address());
+
+            self.seen += 1;
+            if self.seen <= Self::KEY_EXCHANGE_MESSAGES {
+                return ctx.forward(local_message).await;
+            }
+
+            let index = self.seen - Self::KEY_EXCHANGE_MESSAGES - 1;
+            if index % 2 == 1 {
+                if !Self::LOST.contains(&index) {
+                    self.held = Some(local_message);
+                }
+                return Ok(());
+            }
+            if !Self::LOST.contains(&index) {
+                ctx.forward(local_message).await?;
+            }
+            if let Some(held) = self.held.take() {
+                ctx.forward(held).await?;
+            }
+            Ok(())
+        }
+    }
+
+    #[ockam_macros::test]
+    async fn rekeys_survive_lost_and_reordered_messages(ctx: &mut Context) -> Result<()> {
+        let vault = Vault::create();
+        ctx.start_worker("shuffler", Shuffler::default()).await?;
+
+        let new_key_exchanger = XXNewKeyExchanger::new(vault.async_try_clone().await?);
+        SecureChannel::create_listener_extended(
+            ctx,
+            "secure_channel_listener".to_string(),
+            new_key_exchanger.async_try_clone().await?,
+            vault.async_try_clone().await?,
+        )
+        .await?;
+        let initiator = SecureChannel::create_extended_with_rekey_after(
+            ctx,
+            Route::new()
+                .append("shuffler")
+                .append("secure_channel_listener"),
+            None,
+            new_key_exchanger.initiator().await?,
+            vault,
+            Duration::from_secs(120),
+            2,
+        )
+        .await?;
+
+        // The key is rotated every 2 messages: some messages are overtaken
+        // by the next epoch, and epoch 4 is skipped altogether
+        let sent = 13;
+        for i in 0..sent {
+            ctx.send(
+                Route::new().append(initiator.address()).append("app"),
+                format!("message {}", i),
+            )
+            .await?;
+        }
+
+        let mut received = Vec::new();
+        while let Ok(msg) = ctx.receive_timeout::<String>(1).await {
+            received.push(msg.take().body());
+        }
+        received.sort();
+        let mut expected: Vec<String> = (0..sent)
+            .filter(|i| !Shuffler::LOST.contains(i))
+            .map(|i| format!("message {}", i))
+            .collect();
+        expected.sort();
+        assert_eq!(received, expected);
+
+        ctx.stop().await
+    }
 }
@@ -1,5 +1,5 @@
 use crate::SecureChannelError;
-use ockam_core::compat::string::String;
+use ockam_core::compat::{string::String, vec::Vec};
 use ockam_core::{Decodable, Encodable, LocalInfo, LocalMessage, Result};
 use serde::{Deserialize, Serialize};
 
@@ -61,3 +61,49 @@ impl SecureChannelLocalInfo {
         Self { key_exchange }
     }
 }
+
+/// Associated data LocalInfo unique Identifier
+pub const SECURE_CHANNEL_ASSOCIATED_DATA_IDENTIFIER: &str =
+    "SECURE_CHANNEL_ASSOCIATED_DATA_IDENTIFIER";
+
+/// Associated data bound to a single SecureChannel message.
+///
+/// Attach it to a message sent to the channel encryptor to have it authenticated, but not
+/// encrypted, along with the payload. The decryptor marks the decrypted message with it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SecureChannelAssociatedData {
+    data: Vec<u8>,
+}
+
+impl SecureChannelAssociatedData {
+    /// Constructor
+    pub fn new(data: impl Into<Vec<u8>>) -> Self {
+        Self { data: data.into() }
+    }
+
+    /// Associated data bytes
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Create Ockam Routing LocalInfo object using the associated data
+    pub fn to_local_info(&self) -> LocalInfo {
+        LocalInfo::new(
+            SECURE_CHANNEL_ASSOCIATED_DATA_IDENTIFIER.into(),
+            self.data.clone(),
+        )
+    }
+
+    /// Find associated data in a list of LocalInfo
+    pub fn find(local_info: &[LocalInfo]) -> Option<Self> {
+        local_info
+            .iter()
+            .find(|x| x.type_identifier() == SECURE_CHANNEL_ASSOCIATED_DATA_IDENTIFIER)
+            .map(|x| Self::new(x.data()))
+    }
+
+    /// Find associated data in a LocalMessage
+    pub fn find_info(local_msg: &LocalMessage) -> Option<Self> {
+        Self::find(local_msg.local_info())
+    }
+}
@@ -0,0 +1,105 @@
+//! Rotation of the keys of an established secure channel
+//!
+//! Each direction of a channel is rotated by its encryptor, once it sent,
+//! or the decryptor of its end received, as many messages under the
+//! current key as it was configured with. Every frame carries the epoch
+//! of its key, so no rekey message is exchanged: the decryptor at the
+//! other end derives the next keys when it sees a later epoch, and has its
+//! own encryptor follow so both directions are rotated together.
+//!
+//! Frames may be lost or reordered, as over UDP. The decryptor keeps the
+//! key of the previous epoch for frames overtaken by a rotation, and
+//! catches up with at most [`MAX_SKIPPED_EPOCHS`] epochs whose frames were
+//! all lost. Keys are only rotated when both ends speak
+//! [`REKEY_FRAME_VERSION`](crate::REKEY_FRAME_VERSION).
+//!
+//! The next key is derived from the current one as with the `REKEY`
+//! function of the Noise protocol framework. Keys escrowed with a
+//! [`KeyEscrow`](crate::KeyEscrow) don't decrypt messages sent after a rekey.
+
+use crate::SecureChannelEncryptor;
+use crate::SecureChannelVault;
+use ockam_core::compat::boxed::Box;
+use ockam_core::vault::{
+    KeyId, SecretAttributes, SecretPersistence, SecretType, AES256_SECRET_LENGTH_U32,
+    AES256_SECRET_LENGTH_USIZE,
+};
+use ockam_core::{async_trait, AccessControl, Address, LocalMessage, Message, Result};
+use ockam_node::access_control::LocalOriginOnly;
+use serde::{Deserialize, Serialize};
+
+/// Epochs a decryptor skips at most to decrypt a frame
+pub(crate) const MAX_SKIPPED_EPOCHS: u32 = 16;
+
+/// Rekey steps the decryptor of an end asks its encryptor for
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Message)]
+pub(crate) enum Rekey {
+    /// Rotate the key of this direction
+    Initiate,
+    /// The other end rotated its direction up to this epoch, rotate this
+    /// direction up to it too
+    Follow(u32),
+}
+
+/// Admits only the messages sent by the decryptor of this end of the
+/// channel, so no one else can rotate the keys of its encryptor
+#[derive(Debug)]
+pub(crate) struct DecryptorOnly {
+    decryptor: Address,
+}
+
+impl DecryptorOnly {
+    pub(crate) fn new(decryptor: Address) -> Self {
+        Self { decryptor }
+    }
+}
+
+#[async_trait]
+impl AccessControl for DecryptorOnly {
+    async fn is_authorized(&self, local_msg: &LocalMessage) -> Result<bool> {
+        let sender = local_msg.transport().return_route.next().ok();
+        Ok(LocalOriginOnly.is_authorized(local_msg).await? && sender == Some(&self.decryptor))
+    }
+}
+
+/// Derive the key following `key`
+///
+/// The next key is the beginning of the encryption of zeros under `key`,
+/// with the largest nonce, which is never used for messages.
+pub(crate) async fn next_key<V: SecureChannelVault>(vault: &V, key: &KeyId) -> Result<KeyId> {
+    let (_, nonce) = SecureChannelEncryptor::<V>::convert_nonce_from_u64(u64::MAX);
+    let zeros = [0u8; AES256_SECRET_LENGTH_USIZE];
+    let cipher_text = vault.aead_aes_gcm_encrypt(key, &zeros, &nonce, &[]).await?;
+
+    let attributes = SecretAttributes::new(
+        SecretType::Aes,
+        SecretPersistence::Ephemeral,
+        AES256_SECRET_LENGTH_U32,
+    );
+    let next = vault
+        .secret_import(&cipher_text[..AES256_SECRET_LENGTH_USIZE], attributes)
+        .await?;
+    Ok(next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ockam_core::compat::vec::Vec;
+    use ockam_core::{route, TransportMessage};
+    use ockam_node::Context;
+
+    #[ockam_macros::test]
+    async fn only_the_decryptor_is_admitted(ctx: &mut Context) -> Result<()> {
+        let access_control = DecryptorOnly::new("decryptor".into());
+        let from = |sender: &str| {
+            let msg = TransportMessage::v1(route!["encryptor"], route![sender], Vec::new());
+            LocalMessage::new(msg, Vec::new())
+        };
+
+        assert!(access_control.is_authorized(&from("decryptor")).await?);
+        assert!(!access_control.is_authorized(&from("app")).await?);
+
+        ctx.stop().await
+    }
+}
@@ -1,7 +1,8 @@
 use crate::{
-    KeyExchangeCompleted, SecureChannelDecryptor, SecureChannelKeyExchanger, SecureChannelListener,
-    SecureChannelNewKeyExchanger, SecureChannelVault,
+    KeyEscrow, KeyExchangeOutcome, SecureChannelDecryptor, SecureChannelKeyExchanger,
+    SecureChannelListener, SecureChannelNewKeyExchanger, SecureChannelVault,
 };
+use core::time::Duration;
 use ockam_core::compat::{rand::random, vec::Vec};
 use ockam_core::{Address, Result, Route};
 use ockam_node::Context;
@@ -69,6 +70,30 @@ impl SecureChannel {
         Ok(())
     }
 
+    /// Create and start channel listener with given address, whose channels reject
+    /// messages not carrying `expected_associated_data`.
+    ///
+    /// Senders attach associated data with [`crate::SecureChannelAssociatedData`].
+    pub async fn create_listener_with_associated_data<
+        A: Into<Address>,
+        N: SecureChannelNewKeyExchanger,
+        V: SecureChannelVault,
+    >(
+        ctx: &Context,
+        address: A,
+        new_key_exchanger: N,
+        vault: V,
+        expected_associated_data: Vec<u8>,
+    ) -> Result<()> {
+        let address = address.into();
+        let channel_listener = SecureChannelListener::new(new_key_exchanger, vault)
+            .with_expected_associated_data(expected_associated_data);
+        info!("Starting SecureChannel listener at {}", &address);
+        ctx.start_worker(address, channel_listener).await?;
+
+        Ok(())
+    }
+
     /// Create initiator channel with given route to a remote channel listener using noise xx and software vault.
     #[cfg(all(feature = "software_vault", feature = "noise_xx"))]
     pub async fn create<V: SecureChannelVault>(
@@ -96,6 +121,101 @@ impl SecureChannel {
         custom_payload: Option<Vec<u8>>,
         key_exchanger: impl SecureChannelKeyExchanger,
         vault: impl SecureChannelVault,
+    ) -> Result<SecureChannelInfo> {
+        Self::create_extended_with_timeout(
+            ctx,
+            route,
+            custom_payload,
+            key_exchanger,
+            vault,
+            Duration::from_secs(120),
+        )
+        .await
+    }
+
+    /// Create initiator channel with given route to a remote channel listener,
+    /// giving up after `timeout` if the key exchange didn't complete.
+    ///
+    /// The initiator's worker is stopped when giving up.
+    pub async fn create_extended_with_timeout(
+        ctx: &Context,
+        route: impl Into<Route>,
+        custom_payload: Option<Vec<u8>>,
+        key_exchanger: impl SecureChannelKeyExchanger,
+        vault: impl SecureChannelVault,
+        timeout: Duration,
+    ) -> Result<SecureChannelInfo> {
+        Self::create_initiator(
+            ctx,
+            route,
+            custom_payload,
+            key_exchanger,
+            vault,
+            timeout,
+            None,
+            None,
+        )
+        .await
+    }
+
+    /// Create initiator channel with given route to a remote channel listener,
+    /// escrowing the keys of the channel with `key_escrow`, see [`KeyEscrow`].
+    pub async fn create_extended_with_key_escrow(
+        ctx: &Context,
+        route: impl Into<Route>,
+        custom_payload: Option<Vec<u8>>,
+        key_exchanger: impl SecureChannelKeyExchanger,
+        vault: impl SecureChannelVault,
+        timeout: Duration,
+        key_escrow: KeyEscrow,
+    ) -> Result<SecureChannelInfo> {
+        Self::create_initiator(
+            ctx,
+            route,
+            custom_payload,
+            key_exchanger,
+            vault,
+            timeout,
+            Some(key_escrow),
+            None,
+        )
+        .await
+    }
+
+    /// Create initiator channel with given route to a remote channel listener,
+    /// rotating the keys of the channel once either direction carried
+    /// `rekey_after` messages under them.
+    pub async fn create_extended_with_rekey_after(
+        ctx: &Context,
+        route: impl Into<Route>,
+        custom_payload: Option<Vec<u8>>,
+        key_exchanger: impl SecureChannelKeyExchanger,
+        vault: impl SecureChannelVault,
+        timeout: Duration,
+        rekey_after: u64,
+    ) -> Result<SecureChannelInfo> {
+        Self::create_initiator(
+            ctx,
+            route,
+            custom_payload,
+            key_exchanger,
+            vault,
+            timeout,
+            None,
+            Some(rekey_after),
+        )
+        .await
+    }
+
+    async fn create_initiator(
+        ctx: &Context,
+        route: impl Into<Route>,
+        custom_payload: Option<Vec<u8>>,
+        key_exchanger: impl SecureChannelKeyExchanger,
+        vault: impl SecureChannelVault,
+        timeout: Duration,
+        key_escrow: Option<KeyEscrow>,
+        rekey_after: Option<u64>,
     ) -> Result<SecureChannelInfo> {
         let address_remote: Address = random();
 
@@ -107,7 +227,7 @@ impl SecureChannel {
         let route = route.into();
 
         let callback_address: Address = random();
-        let decryptor = SecureChannelDecryptor::new_initiator(
+        let mut decryptor = SecureChannelDecryptor::new_initiator(
             key_exchanger,
             Some(callback_address.clone()),
             route,
@@ -115,15 +235,40 @@ impl SecureChannel {
             vault.async_try_clone().await?,
         )
         .await?;
+        if let Some(key_escrow) = key_escrow {
+            decryptor = decryptor.with_key_escrow(key_escrow);
+        }
+        if let Some(rekey_after) = rekey_after {
+            decryptor = decryptor.with_rekey_after(rekey_after);
+        }
 
         let mut child_ctx = ctx.new_detached(callback_address).await?;
         ctx.start_worker(address_remote.clone(), decryptor).await?;
 
-        let resp = child_ctx
-            .receive_timeout::<KeyExchangeCompleted>(120)
-            .await?
-            .take()
-            .body();
+        let resp = match child_ctx
+            .receive_duration_timeout::<KeyExchangeOutcome>(timeout)
+            .await
+        {
+            Ok(resp) => match resp.take().body() {
+                KeyExchangeOutcome::Completed(resp) => resp,
+                KeyExchangeOutcome::Failed(e) => {
+                    debug!(
+                        "SecureChannel initiator at remote: {} failed",
+                        &address_remote
+                    );
+                    let _ = ctx.stop_worker(address_remote).await;
+                    return Err(e);
+                }
+            },
+            Err(e) => {
+                debug!(
+                    "SecureChannel initiator at remote: {} timed out",
+                    &address_remote
+                );
+                let _ = ctx.stop_worker(address_remote).await;
+                return Err(e);
+            }
+        };
 
         let info = SecureChannelInfo {
             worker_address: resp.address().clone(),
@@ -1,18 +1,36 @@
 use crate::{
-    ChannelKeys, CreateResponderChannelMessage, KeyExchangeCompleted, Role, SecureChannelEncryptor,
+    frame_version_offer, is_vault_failure, negotiate_frame_version, next_key, ChannelKeys,
+    CreateResponderChannelMessage, DecryptorOnly, Frame, KeyEscrow, KeyExchangeCompleted,
+    KeyExchangeOutcome, Rekey, Role, SecureChannelAssociatedData, SecureChannelEncryptor,
     SecureChannelError, SecureChannelKeyExchanger, SecureChannelLocalInfo, SecureChannelVault,
+    MAX_SKIPPED_EPOCHS, REKEY_FRAME_VERSION,
 };
-use ockam_core::compat::{boxed::Box, string::String, vec::Vec};
+use ockam_core::compat::{boxed::Box, string::String, sync::Arc, vec::Vec};
+use ockam_core::vault::KeyId;
 use ockam_core::{async_trait, route};
 use ockam_core::{
-    Address, Any, Decodable, LocalMessage, Result, Route, Routed, TransportMessage, Worker,
+    Address, AllowAll, Any, Decodable, Error, LocalMessage, Mailbox, Mailboxes, MessageId, Result,
+    Route, Routed, TransportMessage, Worker,
 };
-use ockam_node::Context;
-use tracing::{debug, info};
+use ockam_node::{Context, WorkerBuilder};
+use tracing::field::{self, display};
+use tracing::{debug, debug_span, info, warn, Instrument};
 
 struct DecryptorReadyState {
     keys: ChannelKeys,
+    /// Version of the frames both ends agreed on
+    frame_version: u8,
+    /// How many times the key was rotated
+    epoch: u32,
+    /// Key of the previous epoch, for frames overtaken by a rotation
+    previous_key: Option<KeyId>,
     encryptor_address: Address,
+    /// Address the encryptor of this end takes [`Rekey`] steps at
+    encryptor_control_address: Address,
+    /// Messages decrypted under the current key
+    decrypted: u64,
+    /// The encryptor was asked to rotate its key during this epoch
+    rekey_requested: bool,
 }
 
 /// Secure Channel Decryptor
@@ -26,6 +44,15 @@ pub struct SecureChannelDecryptor<V: SecureChannelVault, K: SecureChannelKeyExch
     custom_payload: Option<Vec<u8>>,
     vault: V,
     key_exchange_name: String,
+    /// Payload of the first key exchange message of the other end
+    their_frame_version_offer: Option<Vec<u8>>,
+    /// Associated data every decrypted message must carry, if set
+    expected_associated_data: Option<Vec<u8>>,
+    /// Escrows the keys once the key exchange completes, if set
+    key_escrow: Option<KeyEscrow>,
+    /// Rotate the keys once either direction carried this many messages
+    /// under them, if set
+    rekey_after: Option<u64>,
 }
 
 impl<V: SecureChannelVault, K: SecureChannelKeyExchanger> SecureChannelDecryptor<V, K> {
@@ -47,6 +74,10 @@ impl<V: SecureChannelVault, K: SecureChannelKeyExchanger> SecureChannelDecryptor
             vault,
             key_exchange_name,
             state: None,
+            their_frame_version_offer: None,
+            expected_associated_data: None,
+            key_escrow: None,
+            rekey_after: None,
         })
     }
 
@@ -67,16 +98,34 @@ impl<V: SecureChannelVault, K: SecureChannelKeyExchanger> SecureChannelDecryptor
             vault,
             key_exchange_name,
             state: None,
+            their_frame_version_offer: None,
+            expected_associated_data: None,
+            key_escrow: None,
+            rekey_after: None,
         })
     }
 
-    /// Restore 12-byte nonce needed for AES GCM from 8 byte that we use for noise
-    fn convert_nonce_from_small(b: &[u8]) -> Result<[u8; 12]> {
-        let bytes: [u8; 8] = b.try_into().map_err(|_| SecureChannelError::InvalidNonce)?;
+    /// Reject messages whose associated data differs from `expected_associated_data`
+    pub fn with_expected_associated_data(mut self, expected_associated_data: Vec<u8>) -> Self {
+        self.expected_associated_data = Some(expected_associated_data);
+        self
+    }
 
-        let nonce = u64::from_be_bytes(bytes);
+    /// Escrow the keys of the channel with `key_escrow`, once they are agreed
+    pub fn with_key_escrow(mut self, key_escrow: KeyEscrow) -> Self {
+        self.key_escrow = Some(key_escrow);
+        self
+    }
 
-        Ok(SecureChannelEncryptor::<V>::convert_nonce_from_u64(nonce).1)
+    /// Rotate the keys of the channel once this end sent, or received,
+    /// `rekey_after` messages under them
+    ///
+    /// The other end follows the rekeys of this end whatever it was
+    /// configured with. The keys are kept when the other end doesn't
+    /// support rekeys.
+    pub fn with_rekey_after(mut self, rekey_after: u64) -> Self {
+        self.rekey_after = Some(rekey_after);
+        self
     }
 
     async fn send_key_exchange_payload(
@@ -107,26 +156,33 @@ impl<V: SecureChannelVault, K: SecureChannelKeyExchanger> SecureChannelDecryptor
 
         let state = self
             .state
-            .as_mut()
+            .as_ref()
             .ok_or(SecureChannelError::InvalidInternalState)?;
 
+        let ttl = msg.local_message().ttl();
         let transport_message = msg.into_transport_message();
         let payload = transport_message.payload;
         let payload = Vec::<u8>::decode(&payload)?;
 
-        let payload = {
-            if payload.len() < 8 {
-                return Err(SecureChannelError::InvalidNonce.into());
-            }
+        let frame = Frame::decode(&payload)?;
+        if frame.version != state.frame_version {
+            return Err(SecureChannelError::UnsupportedFrameVersion.into());
+        }
+        let associated_data = frame.associated_data;
 
-            let nonce = Self::convert_nonce_from_small(&payload.as_slice()[..8])?;
+        let payload = self.decrypt_frame(ctx, &frame).await?;
+        let mut transport_message = TransportMessage::decode(&payload)?;
 
-            self.vault
-                .aead_aes_gcm_decrypt(&state.keys.key, &payload[8..], &nonce, &[])
-                .await?
-        };
+        let state = self
+            .state
+            .as_mut()
+            .ok_or(SecureChannelError::InvalidInternalState)?;
 
-        let mut transport_message = TransportMessage::decode(&payload)?;
+        if let Some(expected_associated_data) = &self.expected_associated_data {
+            if associated_data != expected_associated_data.as_slice() {
+                return Err(SecureChannelError::AssociatedDataMismatch.into());
+            }
+        }
 
         transport_message
             .return_route
@@ -135,11 +191,125 @@ impl<V: SecureChannelVault, K: SecureChannelKeyExchanger> SecureChannelDecryptor
 
         let local_info = SecureChannelLocalInfo::new(self.key_exchange_name.clone());
 
-        let local_msg = LocalMessage::new(transport_message, vec![local_info.to_local_info()?]);
+        let mut local_info = vec![local_info.to_local_info()?];
+        if !associated_data.is_empty() {
+            local_info.push(SecureChannelAssociatedData::new(associated_data).to_local_info());
+        }
+
+        let local_msg = LocalMessage::new(transport_message, local_info).with_ttl(ttl);
+
+        state.decrypted += 1;
+        if !state.rekey_requested
+            && self
+                .rekey_after
+                .map_or(false, |rekey_after| state.decrypted >= rekey_after)
+        {
+            state.rekey_requested = true;
+            ctx.send(state.encryptor_control_address.clone(), Rekey::Initiate)
+                .await?;
+        }
 
         ctx.forward(local_msg).await
     }
 
+    /// Decrypt `frame` under the key of its epoch
+    ///
+    /// A later epoch means the other end rotated its key: the keys up to
+    /// it are derived, and kept once they decrypt the frame, and the
+    /// encryptor of this end is told to follow.
+    async fn decrypt_frame(
+        &mut self,
+        ctx: &<Self as Worker>::Context,
+        frame: &Frame<'_>,
+    ) -> Result<Vec<u8>> {
+        let state = self
+            .state
+            .as_mut()
+            .ok_or(SecureChannelError::InvalidInternalState)?;
+
+        let (_, nonce) = SecureChannelEncryptor::<V>::convert_nonce_from_u64(frame.nonce);
+        let associated_data = Frame::aead_associated_data(frame.version, frame.associated_data);
+
+        if frame.epoch <= state.epoch {
+            let key = if frame.epoch == state.epoch {
+                &state.keys.key
+            } else {
+                match &state.previous_key {
+                    Some(key) if frame.epoch + 1 == state.epoch => key,
+                    _ => return Err(SecureChannelError::InvalidKeyEpoch.into()),
+                }
+            };
+            return self
+                .vault
+                .aead_aes_gcm_decrypt(key, frame.cipher_text, &nonce, &associated_data)
+                .await;
+        }
+        if frame.epoch - state.epoch > MAX_SKIPPED_EPOCHS {
+            return Err(SecureChannelError::InvalidKeyEpoch.into());
+        }
+
+        // The derived keys are only kept once they decrypt the frame
+        let vault = &self.vault;
+        let mut derived: Vec<KeyId> = Vec::new();
+        let decrypted = async {
+            for _ in state.epoch..frame.epoch {
+                let key = derived.last().unwrap_or(&state.keys.key);
+                derived.push(next_key(vault, key).await?);
+            }
+            let key = derived
+                .last()
+                .ok_or(SecureChannelError::InvalidInternalState)?;
+            vault
+                .aead_aes_gcm_decrypt(key, frame.cipher_text, &nonce, &associated_data)
+                .await
+        }
+        .await;
+        let plain_text = match decrypted {
+            Ok(plain_text) => plain_text,
+            Err(err) => {
+                for key in derived {
+                    self.vault.secret_destroy(key).await?;
+                }
+                return Err(err);
+            }
+        };
+
+        // Keep the key of the epoch before the new one, and drop the others
+        let current = derived
+            .pop()
+            .ok_or(SecureChannelError::InvalidInternalState)?;
+        let mut stale: Vec<KeyId> = state.previous_key.take().into_iter().collect();
+        let replaced = core::mem::replace(&mut state.keys.key, current);
+        let previous = match derived.pop() {
+            Some(previous) => {
+                stale.push(replaced);
+                previous
+            }
+            None => replaced,
+        };
+        stale.append(&mut derived);
+        state.previous_key = Some(previous);
+        for key in stale {
+            self.vault.secret_destroy(key).await?;
+        }
+
+        state.epoch = frame.epoch;
+        state.decrypted = 0;
+        state.rekey_requested = false;
+        info!(
+            "SecureChannel decryptor {} rotated its key to epoch {}",
+            ctx.address(),
+            state.epoch
+        );
+
+        ctx.send(
+            state.encryptor_control_address.clone(),
+            Rekey::Follow(state.epoch),
+        )
+        .await?;
+        Ok(plain_text)
+    }
+
     async fn handle_key_exchange(
         &mut self,
         ctx: &mut <Self as Worker>::Context,
@@ -161,10 +331,19 @@ impl<V: SecureChannelVault, K: SecureChannelKeyExchanger> SecureChannelDecryptor
         // Update route to a remote
         self.remote_route = reply;
 
-        let _ = key_exchanger.handle_response(payload.as_slice()).await?;
+        let their_payload = key_exchanger.handle_response(payload.as_slice()).await?;
+        if self.their_frame_version_offer.is_none() {
+            self.their_frame_version_offer = Some(their_payload);
+        }
 
         if !key_exchanger.is_complete().await? {
-            let payload = key_exchanger.generate_request(&[]).await?;
+            // The responder offers its frame versions in its first message,
+            // the initiator did in the message starting the key exchange
+            let payload = match self.role {
+                Role::Initiator => Vec::new(),
+                Role::Responder => frame_version_offer(),
+            };
+            let payload = key_exchanger.generate_request(&payload).await?;
             let is_now_complete = key_exchanger.is_complete().await?;
             self.send_key_exchange_payload(ctx, payload, false).await?;
 
@@ -179,17 +358,55 @@ impl<V: SecureChannelVault, K: SecureChannelKeyExchanger> SecureChannelDecryptor
             .ok_or(SecureChannelError::InvalidInternalState)?;
 
         let keys = key_exchanger.finalize().await?;
+        let frame_version = negotiate_frame_version(
+            self.their_frame_version_offer
+                .as_deref()
+                .unwrap_or_default(),
+        );
+        if self.rekey_after.is_some() && frame_version < REKEY_FRAME_VERSION {
+            warn!(
+                "SecureChannel {} at {} keeps its keys, the other end doesn't support rekeys",
+                self.role.role_str(),
+                ctx.address()
+            );
+            self.rekey_after = None;
+        }
+
+        if let Some(key_escrow) = &self.key_escrow {
+            key_escrow
+                .escrow(
+                    &self.vault,
+                    &ctx.address(),
+                    keys.encrypt_key(),
+                    keys.decrypt_key(),
+                )
+                .await?;
+        }
 
         let address_local = Address::random_local();
+        let control_address = Address::random_local();
         let encryptor = SecureChannelEncryptor::new(
             ChannelKeys {
                 key: keys.encrypt_key().clone(),
                 nonce: 0,
             },
+            frame_version,
             self.remote_route.clone(),
             self.vault.async_try_clone().await?,
+            control_address.clone(),
+            self.rekey_after,
+        );
+        // Only the decryptor of this end may rotate the keys of the encryptor
+        let mailboxes = Mailboxes::new(
+            Mailbox::new(address_local.clone(), Arc::new(AllowAll)),
+            vec![Mailbox::new(
+                control_address.clone(),
+                Arc::new(DecryptorOnly::new(ctx.address())),
+            )],
         );
-        ctx.start_worker(address_local.clone(), encryptor).await?;
+        WorkerBuilder::with_mailboxes(mailboxes, encryptor)
+            .start(ctx)
+            .await?;
 
         info!(
             "Started SecureChannel {} at local: {}, remote: {}",
@@ -202,7 +419,10 @@ impl<V: SecureChannelVault, K: SecureChannelKeyExchanger> SecureChannelDecryptor
         if let Some(r) = self.key_exchange_completed_callback_route.take() {
             ctx.send(
                 r,
-                KeyExchangeCompleted::new(address_local.clone(), *keys.h()),
+                KeyExchangeOutcome::Completed(KeyExchangeCompleted::new(
+                    address_local.clone(),
+                    *keys.h(),
+                )),
             )
             .await?;
         }
@@ -212,11 +432,42 @@ impl<V: SecureChannelVault, K: SecureChannelKeyExchanger> SecureChannelDecryptor
                 key: keys.decrypt_key().clone(),
                 nonce: 0,
             },
+            frame_version,
+            epoch: 0,
+            previous_key: None,
             encryptor_address: address_local,
+            encryptor_control_address: control_address,
+            decrypted: 0,
+            rekey_requested: false,
         });
 
         Ok(())
     }
+
+    /// Abort the key exchange if `err` is a vault failure: the callback is
+    /// told why, and a responder stops since the key exchange can't go on.
+    /// An initiator is stopped by [`SecureChannel`](crate::SecureChannel)
+    /// which waits for it
+    async fn abort_key_exchange(&mut self, ctx: &Context, err: Error) -> Result<()> {
+        if !is_vault_failure(&err) {
+            return Err(err);
+        }
+
+        warn!(
+            "SecureChannel {} at {} aborted the key exchange: {}",
+            self.role.role_str(),
+            ctx.address(),
+            err
+        );
+        self.key_exchanger = None;
+        if let Some(r) = self.key_exchange_completed_callback_route.take() {
+            ctx.send(r, KeyExchangeOutcome::Failed(err)).await?;
+        }
+        match self.role {
+            Role::Initiator => Ok(()),
+            Role::Responder => ctx.stop_worker(ctx.address()).await,
+        }
+    }
 }
 
 #[async_trait]
@@ -227,7 +478,10 @@ impl<V: SecureChannelVault, K: SecureChannelKeyExchanger> Worker for SecureChann
     async fn initialize(&mut self, ctx: &mut Self::Context) -> Result<()> {
         if let Role::Initiator = &self.role {
             if let Some(key_exchanger) = &mut self.key_exchanger {
-                let payload = key_exchanger.generate_request(&[]).await?;
+                let payload = match key_exchanger.generate_request(&frame_version_offer()).await {
+                    Ok(payload) => payload,
+                    Err(err) => return self.abort_key_exchange(ctx, err).await,
+                };
 
                 self.send_key_exchange_payload(ctx, payload, true).await?;
             } else {
@@ -243,14 +497,30 @@ impl<V: SecureChannelVault, K: SecureChannelKeyExchanger> Worker for SecureChann
         ctx: &mut Self::Context,
         msg: Routed<Self::Message>,
     ) -> Result<()> {
-        if self.state.is_some() {
-            self.handle_decrypt(ctx, msg).await?;
-        } else if self.key_exchanger.is_some() {
-            self.handle_key_exchange(ctx, msg).await?;
-        } else {
-            return Err(SecureChannelError::InvalidInternalState.into());
+        let span = debug_span!(
+            "secure_channel_decryptor",
+            channel = %ctx.address(),
+            peer = %self.remote_route,
+            message_id = field::Empty
+        );
+        if !span.is_disabled() {
+            span.record("message_id", &display(MessageId::of(msg.payload())));
         }
 
-        Ok(())
+        async {
+            if self.state.is_some() {
+                self.handle_decrypt(ctx, msg).await?;
+            } else if self.key_exchanger.is_some() {
+                if let Err(err) = self.handle_key_exchange(ctx, msg).await {
+                    return self.abort_key_exchange(ctx, err).await;
+                }
+            } else {
+                return Err(SecureChannelError::InvalidInternalState.into());
+            }
+
+            Ok(())
+        }
+        .instrument(span)
+        .await
     }
 }
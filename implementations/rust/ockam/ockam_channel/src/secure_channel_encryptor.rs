@@ -1,22 +1,51 @@
-use crate::{ChannelKeys, SecureChannelError, SecureChannelVault};
+use crate::{
+    next_key, ChannelKeys, Frame, Rekey, SecureChannelAssociatedData, SecureChannelError,
+    SecureChannelVault, REKEY_FRAME_VERSION,
+};
 use ockam_core::async_trait;
 use ockam_core::compat::{boxed::Box, vec::Vec};
-use ockam_core::{Any, Encodable, Result, Route, Routed, TransportMessage, Worker};
+use ockam_core::{
+    Address, Any, Decodable, Encodable, LocalMessage, MessageId, Result, Route, Routed,
+    TransportMessage, Worker,
+};
 use ockam_node::Context;
-use tracing::debug;
+use tracing::field::{self, display};
+use tracing::{debug, debug_span, info, Instrument, Span};
 
 pub(crate) struct SecureChannelEncryptor<V: SecureChannelVault> {
     keys: ChannelKeys,
+    /// Version of the frames both ends agreed on
+    frame_version: u8,
+    /// How many times the key was rotated
+    epoch: u32,
     remote_route: Route,
     vault: V,
+    /// Address the decryptor of this end sends [`Rekey`] steps to
+    control_address: Address,
+    /// Rotate the key once this many messages were encrypted under it
+    rekey_after: Option<u64>,
+    /// Messages encrypted under the current key
+    encrypted: u64,
 }
 
 impl<V: SecureChannelVault> SecureChannelEncryptor<V> {
-    pub(crate) fn new(keys: ChannelKeys, remote_route: Route, vault: V) -> Self {
+    pub(crate) fn new(
+        keys: ChannelKeys,
+        frame_version: u8,
+        remote_route: Route,
+        vault: V,
+        control_address: Address,
+        rekey_after: Option<u64>,
+    ) -> Self {
         Self {
             keys,
+            frame_version,
+            epoch: 0,
             remote_route,
             vault,
+            control_address,
+            rekey_after,
+            encrypted: 0,
         }
     }
 
@@ -41,12 +70,36 @@ impl<V: SecureChannelVault> SecureChannelEncryptor<V> {
 
         let reply = msg.return_route();
         let mut onward_route = msg.onward_route();
+        let associated_data = SecureChannelAssociatedData::find_info(msg.local_message())
+            .map(|x| x.data().to_vec())
+            .unwrap_or_default();
+        let ttl = msg.local_message().ttl();
         let transport_message = msg.into_transport_message();
         let payload = transport_message.payload;
 
         let _ = onward_route.step();
 
         let msg = TransportMessage::v1(onward_route, reply, payload.to_vec());
+        self.encrypt_and_send(ctx, msg, &associated_data, ttl)
+            .await?;
+
+        self.encrypted += 1;
+        if self
+            .rekey_after
+            .map_or(false, |rekey_after| self.encrypted >= rekey_after)
+        {
+            self.rekey(ctx).await?;
+        }
+        Ok(())
+    }
+
+    async fn encrypt_and_send(
+        &mut self,
+        ctx: &mut <Self as Worker>::Context,
+        msg: TransportMessage,
+        associated_data: &[u8],
+        ttl: u8,
+    ) -> Result<()> {
         let payload = msg.encode()?;
 
         let payload = {
@@ -58,21 +111,79 @@ impl<V: SecureChannelVault> SecureChannelEncryptor<V> {
 
             self.keys.nonce += 1;
 
-            let (small_nonce, nonce) = Self::convert_nonce_from_u64(nonce);
+            let (_, aead_nonce) = Self::convert_nonce_from_u64(nonce);
 
-            let mut cipher_text = self
+            let cipher_text = self
                 .vault
-                .aead_aes_gcm_encrypt(&self.keys.key, payload.as_slice(), &nonce, &[])
+                .aead_aes_gcm_encrypt(
+                    &self.keys.key,
+                    payload.as_slice(),
+                    &aead_nonce,
+                    &Frame::aead_associated_data(self.frame_version, associated_data),
+                )
                 .await?;
 
-            let mut res = Vec::new();
-            res.extend_from_slice(&small_nonce);
-            res.append(&mut cipher_text);
-
-            res
+            // Associated data is sent in clear, authenticated by the AEAD tag
+            Frame {
+                version: self.frame_version,
+                epoch: self.epoch,
+                nonce,
+                associated_data,
+                cipher_text: &cipher_text,
+            }
+            .encode()?
         };
 
-        ctx.send(self.remote_route.clone(), payload).await
+        let payload = payload.encode()?;
+
+        // The id of the message the transport workers and the decryptor
+        // at the other end see
+        let span = Span::current();
+        if !span.is_disabled() {
+            span.record("message_id", &display(MessageId::of(&payload)));
+        }
+
+        // Carry the TTL over so routing loops through the channel are still detected
+        let msg = TransportMessage::v1(self.remote_route.clone(), ctx.address(), payload);
+        ctx.forward(LocalMessage::new(msg, Vec::new()).with_ttl(ttl))
+            .await
+    }
+
+    /// Switch to the next key, which the other end derives once it sees
+    /// the next epoch
+    async fn rekey(&mut self, ctx: &<Self as Worker>::Context) -> Result<()> {
+        if self.frame_version < REKEY_FRAME_VERSION {
+            return Err(SecureChannelError::RekeyNotNegotiated.into());
+        }
+
+        let next = next_key(&self.vault, &self.keys.key).await?;
+        let previous = core::mem::replace(&mut self.keys.key, next);
+        self.vault.secret_destroy(previous).await?;
+        self.epoch += 1;
+        self.encrypted = 0;
+        info!(
+            "SecureChannel encryptor {} rotated its key to epoch {}",
+            ctx.address(),
+            self.epoch
+        );
+        Ok(())
+    }
+
+    /// Handle a rekey step asked for by the decryptor of this end
+    async fn handle_control(
+        &mut self,
+        ctx: &mut <Self as Worker>::Context,
+        msg: Routed<<Self as Worker>::Message>,
+    ) -> Result<()> {
+        match Rekey::decode(msg.payload())? {
+            Rekey::Initiate => self.rekey(ctx).await,
+            Rekey::Follow(epoch) => {
+                while self.epoch < epoch {
+                    self.rekey(ctx).await?;
+                }
+                Ok(())
+            }
+        }
     }
 }
 
@@ -86,6 +197,16 @@ impl<V: SecureChannelVault> Worker for SecureChannelEncryptor<V> {
         ctx: &mut Self::Context,
         msg: Routed<Self::Message>,
     ) -> Result<()> {
-        self.handle_encrypt(ctx, msg).await
+        let span = debug_span!(
+            "secure_channel_encryptor",
+            channel = %ctx.address(),
+            peer = %self.remote_route,
+            message_id = field::Empty
+        );
+        if msg.msg_addr() == self.control_address {
+            self.handle_control(ctx, msg).instrument(span).await
+        } else {
+            self.handle_encrypt(ctx, msg).instrument(span).await
+        }
     }
 }
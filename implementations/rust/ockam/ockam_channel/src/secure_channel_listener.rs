@@ -1,4 +1,4 @@
-use crate::{SecureChannelDecryptor, SecureChannelNewKeyExchanger, SecureChannelVault};
+use crate::{KeyEscrow, SecureChannelDecryptor, SecureChannelNewKeyExchanger, SecureChannelVault};
 use ockam_core::async_trait;
 use ockam_core::compat::rand::random;
 use ockam_core::compat::{boxed::Box, vec::Vec};
@@ -14,6 +14,9 @@ use tracing::debug;
 pub struct SecureChannelListener<V: SecureChannelVault, N: SecureChannelNewKeyExchanger> {
     new_key_exchanger: N,
     vault: V,
+    expected_associated_data: Option<Vec<u8>>,
+    key_escrow: Option<KeyEscrow>,
+    rekey_after: Option<u64>,
 }
 
 impl<V: SecureChannelVault, N: SecureChannelNewKeyExchanger> SecureChannelListener<V, N> {
@@ -22,8 +25,31 @@ impl<V: SecureChannelVault, N: SecureChannelNewKeyExchanger> SecureChannelListen
         Self {
             new_key_exchanger,
             vault,
+            expected_associated_data: None,
+            key_escrow: None,
+            rekey_after: None,
         }
     }
+
+    /// Make responder channels reject messages whose associated data
+    /// differs from `expected_associated_data`
+    pub fn with_expected_associated_data(mut self, expected_associated_data: Vec<u8>) -> Self {
+        self.expected_associated_data = Some(expected_associated_data);
+        self
+    }
+
+    /// Make responder channels escrow their keys with `key_escrow`
+    pub fn with_key_escrow(mut self, key_escrow: KeyEscrow) -> Self {
+        self.key_escrow = Some(key_escrow);
+        self
+    }
+
+    /// Make responder channels rotate their keys after `rekey_after`
+    /// messages, see [`SecureChannelDecryptor::with_rekey_after`]
+    pub fn with_rekey_after(mut self, rekey_after: u64) -> Self {
+        self.rekey_after = Some(rekey_after);
+        self
+    }
 }
 
 /// SecureChannelListener message wrapper.
@@ -78,7 +104,17 @@ impl<V: SecureChannelVault, N: SecureChannelNewKeyExchanger> Worker
 
         let key_exchanger = self.new_key_exchanger.responder().await?;
         let vault = self.vault.async_try_clone().await?;
-        let decryptor = SecureChannelDecryptor::new_responder(key_exchanger, None, vault).await?;
+        let mut decryptor =
+            SecureChannelDecryptor::new_responder(key_exchanger, None, vault).await?;
+        if let Some(expected_associated_data) = &self.expected_associated_data {
+            decryptor = decryptor.with_expected_associated_data(expected_associated_data.clone());
+        }
+        if let Some(key_escrow) = &self.key_escrow {
+            decryptor = decryptor.with_key_escrow(key_escrow.clone());
+        }
+        if let Some(rekey_after) = self.rekey_after {
+            decryptor = decryptor.with_rekey_after(rekey_after);
+        }
 
         ctx.start_worker(vec![address_remote.clone()], decryptor)
             .await?;
@@ -1,5 +1,5 @@
 use crate::help;
-use crate::util::embedded_node;
+use crate::util::{embedded_node, parse_route};
 use anyhow::{anyhow, Result};
 use clap::builder::NonEmptyStringValueParser;
 use clap::{Args, Subcommand};
@@ -21,6 +21,7 @@ pub enum AuthenticatedSubcommand {
     /// Get attribute value.
     Get {
         /// Address to connect to.
+        #[arg(value_parser = parse_route)]
         addr: MultiAddr,
 
         /// Subject identifier
@@ -34,6 +35,7 @@ pub enum AuthenticatedSubcommand {
     /// Delete attribute
     Del {
         /// Address to connect to.
+        #[arg(value_parser = parse_route)]
         addr: MultiAddr,
 
         /// Subject identifier
@@ -11,8 +11,7 @@ pub struct GetCommand {
 impl GetCommand {
     pub fn run(self, options: CommandGlobalOpts) {
         if let Err(e) = run_impl(options, self) {
-            eprintln!("{}", e);
-            std::process::exit(e.code());
+            e.exit();
         }
     }
 }
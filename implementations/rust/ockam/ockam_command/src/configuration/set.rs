@@ -15,8 +15,7 @@ pub struct SetCommand {
 impl SetCommand {
     pub fn run(self, options: CommandGlobalOpts) {
         if let Err(e) = run_impl(options, self) {
-            eprintln!("{}", e);
-            std::process::exit(e.code());
+            e.exit();
         }
     }
 }
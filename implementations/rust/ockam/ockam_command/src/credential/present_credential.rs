@@ -5,7 +5,7 @@ use ockam_multiaddr::MultiAddr;
 
 use crate::node::NodeOpts;
 use crate::util::api::{self};
-use crate::util::{node_rpc, Rpc};
+use crate::util::{node_rpc, parse_route, Rpc};
 use crate::CommandGlobalOpts;
 
 #[derive(Clone, Debug, Args)]
@@ -13,7 +13,7 @@ pub struct PresentCredentialCommand {
     #[command(flatten)]
     pub node_opts: NodeOpts,
 
-    #[arg(long, display_order = 900, id = "ROUTE")]
+    #[arg(long, display_order = 900, id = "ROUTE", value_parser = parse_route)]
     pub to: MultiAddr,
 
     #[arg(short, long)]
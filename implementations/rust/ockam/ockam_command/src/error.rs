@@ -1,10 +1,16 @@
 use std::fmt::{Debug, Display, Formatter};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::Serialize;
 
 use crate::util::ConfigError;
 use crate::{exitcode, ExitCode};
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// Whether errors are reported as JSON, set from `--output json`
+static JSON_ERRORS: AtomicBool = AtomicBool::new(false);
+
 pub struct Error {
     code: ExitCode,
     inner: anyhow::Error,
@@ -19,6 +25,52 @@ impl Error {
     pub fn code(&self) -> ExitCode {
         self.code
     }
+
+    /// Report errors on stderr as JSON objects instead of human readable text
+    pub fn use_json_output(json: bool) {
+        JSON_ERRORS.store(json, Ordering::Relaxed);
+    }
+
+    /// The machine-readable form of this error
+    ///
+    /// `{"error": {"message": "..", "causes": [..]}, "code": n}`
+    pub fn to_json(&self) -> String {
+        let report = ErrorReport {
+            error: ErrorDetails {
+                message: self.inner.to_string(),
+                causes: self.inner.chain().skip(1).map(|e| e.to_string()).collect(),
+            },
+            code: self.code,
+        };
+        serde_json::to_string(&report).expect("Failed to serialize error")
+    }
+
+    /// Print the error to stderr, as JSON when running with `--output json`
+    pub fn print(&self) {
+        if JSON_ERRORS.load(Ordering::Relaxed) {
+            eprintln!("{}", self.to_json());
+        } else {
+            eprintln!("{self:?}");
+        }
+    }
+
+    /// Print the error and exit the process with its exit code
+    pub fn exit(&self) -> ! {
+        self.print();
+        std::process::exit(self.code)
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorReport {
+    error: ErrorDetails,
+    code: ExitCode,
+}
+
+#[derive(Serialize)]
+struct ErrorDetails {
+    message: String,
+    causes: Vec<String>,
 }
 
 impl Debug for Error {
@@ -53,7 +105,11 @@ impl From<ockam::Error> for Error {
 
 impl From<anyhow::Error> for Error {
     fn from(e: anyhow::Error) -> Self {
-        Error::new(exitcode::SOFTWARE, e)
+        // Keep the exit code of an `Error` which went through anyhow
+        match e.downcast::<Error>() {
+            Ok(e) => e,
+            Err(e) => Error::new(exitcode::SOFTWARE, e),
+        }
     }
 }
 
@@ -80,3 +136,28 @@ impl From<std::net::AddrParseError> for Error {
         Error::new(exitcode::SOFTWARE, e.into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    #[test]
+    fn test_to_json() {
+        let err = Error::new(
+            exitcode::IOERR,
+            anyhow!("file not found").context("failed to load config"),
+        );
+        let json: serde_json::Value = serde_json::from_str(&err.to_json()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "error": {
+                    "message": "failed to load config",
+                    "causes": ["file not found"],
+                },
+                "code": exitcode::IOERR,
+            })
+        );
+    }
+}
@@ -12,7 +12,7 @@ use ockam_multiaddr::{proto::Node, MultiAddr, Protocol};
 
 use crate::forwarder::HELP_DETAIL;
 use crate::util::output::Output;
-use crate::util::{extract_address_value, node_rpc, RpcBuilder};
+use crate::util::{extract_address_value, node_rpc, parse_route, RpcBuilder};
 use crate::Result;
 use crate::{help, CommandGlobalOpts};
 
@@ -32,7 +32,7 @@ pub struct CreateCommand {
     to: String,
 
     /// Route to the node at which to create the forwarder (optional)
-    #[arg(long, id = "ROUTE", display_order = 900)]
+    #[arg(long, id = "ROUTE", display_order = 900, value_parser = parse_route)]
     at: MultiAddr,
 
     /// Authorized identity for secure channel connection (optional)
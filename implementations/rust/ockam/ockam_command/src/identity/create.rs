@@ -4,6 +4,7 @@ use crate::util::{node_rpc, Rpc};
 use crate::CommandGlobalOpts;
 use clap::Args;
 use ockam::Context;
+use ockam_api::nodes::models::identity::CreateIdentityRequest;
 use ockam_core::api::Request;
 
 #[derive(Clone, Debug, Args)]
@@ -11,6 +12,11 @@ use ockam_core::api::Request;
 pub struct CreateCommand {
     #[command(flatten)]
     node_opts: NodeOpts,
+
+    /// Name of a vault created with `vault create --name`, which becomes
+    /// the node's vault
+    #[arg(long, value_name = "NAME")]
+    vault: Option<String>,
 }
 
 impl CreateCommand {
@@ -24,7 +30,11 @@ async fn run_impl(
     (options, cmd): (CommandGlobalOpts, CreateCommand),
 ) -> crate::Result<()> {
     let mut rpc = Rpc::background(&ctx, &options, &cmd.node_opts.api_node)?;
-    let request = Request::post("/node/identity");
+    let mut body = CreateIdentityRequest::new();
+    if let Some(vault) = cmd.vault {
+        body = body.with_vault(vault);
+    }
+    let request = Request::post("/node/identity").body(body);
     rpc.request(request).await?;
     rpc.parse_response()?;
 
@@ -0,0 +1,119 @@
+use super::DEFAULT_IDENTITY;
+use crate::util::{exitcode, node_rpc};
+use crate::{help, CommandGlobalOpts, OckamConfig};
+use anyhow::anyhow;
+use clap::Args;
+use nix::unistd::Pid;
+use ockam::identity::change_history::IdentityChangeHistory;
+use ockam::identity::IdentityStateConst;
+use ockam::Context;
+use ockam_core::vault::{AsymmetricVault, SecretVault};
+use ockam_vault::storage::FileStorage;
+use ockam_vault::Vault;
+use std::sync::Arc;
+
+/// Delete an Identity and its secrets from the vault
+#[derive(Clone, Debug, Args)]
+#[command(arg_required_else_help = true, hide = help::hide())]
+pub struct DeleteCommand {
+    /// Name of the identity, only `default` can be deleted for now
+    name: String,
+
+    /// Delete the identity even if running nodes use it
+    #[arg(long, short)]
+    force: bool,
+}
+
+impl DeleteCommand {
+    pub fn run(self, options: CommandGlobalOpts) {
+        node_rpc(run_impl, (options, self))
+    }
+}
+
+async fn run_impl(
+    _ctx: Context,
+    (opts, cmd): (CommandGlobalOpts, DeleteCommand),
+) -> crate::Result<()> {
+    let cfg = &opts.config;
+    let identity = match cfg.get_default_identity() {
+        Some(identity) if cmd.name == DEFAULT_IDENTITY => identity,
+        _ => {
+            return Err(crate::Error::new(
+                exitcode::NOINPUT,
+                anyhow!("Identity '{}' was not found", cmd.name),
+            ))
+        }
+    };
+
+    let nodes = running_nodes_using(cfg, &identity);
+    if !nodes.is_empty() && !cmd.force {
+        return Err(crate::Error::new(
+            exitcode::UNAVAILABLE,
+            anyhow!(
+                "Identity '{}' is used by the running nodes {}, stop them or use --force",
+                cmd.name,
+                nodes.join(", ")
+            ),
+        ));
+    }
+
+    // Destroy the identity's current keys, other secrets of the vault are kept
+    let history = IdentityChangeHistory::import(&identity)?;
+    let mut destroyed = 0;
+    let vault_path = cfg.get_default_vault_path();
+    let identifier = match &vault_path {
+        Some(vault_path) => {
+            let storage = FileStorage::create(vault_path.clone()).await?;
+            let vault = Vault::new(Some(Arc::new(storage)));
+            for label in [
+                IdentityStateConst::ROOT_LABEL,
+                IdentityStateConst::SIGNING_LABEL,
+                IdentityStateConst::KEY_AGREEMENT_LABEL,
+            ] {
+                let public_key = match history.get_public_key(label) {
+                    Ok(public_key) => public_key,
+                    Err(_) => continue,
+                };
+                let key_id = vault.compute_key_id_for_public_key(&public_key).await?;
+                if vault.secret_destroy(key_id).await.is_ok() {
+                    destroyed += 1;
+                }
+            }
+            Some(history.compute_identity_id(&vault).await?)
+        }
+        None => None,
+    };
+
+    cfg.set_default_identity(None);
+    cfg.persist_config_updates()?;
+
+    match identifier {
+        Some(identifier) => println!("Deleted identity '{}' ({})", cmd.name, identifier),
+        None => println!("Deleted identity '{}'", cmd.name),
+    }
+    if let Some(vault_path) = vault_path {
+        println!(
+            "Removed {} secret(s) from the vault at {}",
+            destroyed,
+            vault_path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Names of the running nodes which were created with `identity`
+fn running_nodes_using(cfg: &OckamConfig, identity: &[u8]) -> Vec<String> {
+    let nodes: Vec<_> = cfg.inner().nodes.values().cloned().collect();
+    nodes
+        .into_iter()
+        .filter(|node| match node.pid() {
+            Some(pid) => nix::sys::signal::kill(Pid::from_raw(pid), None).is_ok(),
+            None => false,
+        })
+        .filter(|node| match cfg.node(node.name()) {
+            Ok(node_cfg) => node_cfg.state().read().identity.as_deref() == Some(identity),
+            Err(_) => false,
+        })
+        .map(|node| node.name().to_string())
+        .collect()
+}
@@ -0,0 +1,50 @@
+use super::DEFAULT_IDENTITY;
+use crate::util::exitcode;
+use crate::{help, CommandGlobalOpts};
+use anyhow::anyhow;
+use clap::Args;
+use std::path::PathBuf;
+
+/// Export the change history of an Identity, to be imported with `identity import`
+#[derive(Clone, Debug, Args)]
+#[command(arg_required_else_help = true, hide = help::hide())]
+pub struct ExportCommand {
+    /// Name of the identity, `default` or an imported identity
+    name: String,
+
+    /// Path of the file to write the hex encoded change history to
+    file: PathBuf,
+}
+
+impl ExportCommand {
+    pub fn run(self, options: CommandGlobalOpts) {
+        if let Err(e) = run_impl(options, self) {
+            e.exit();
+        }
+    }
+}
+
+fn run_impl(opts: CommandGlobalOpts, cmd: ExportCommand) -> crate::Result<()> {
+    let cfg = &opts.config;
+    let history = if cmd.name == DEFAULT_IDENTITY {
+        cfg.get_default_identity()
+    } else {
+        cfg.get_imported_identity(&cmd.name)
+    };
+    let history = history.ok_or_else(|| {
+        crate::Error::new(
+            exitcode::NOINPUT,
+            anyhow!("Identity '{}' was not found", cmd.name),
+        )
+    })?;
+
+    // Only the public change history is exported, never the secret keys
+    std::fs::write(&cmd.file, hex::encode(history)).map_err(|e| {
+        crate::Error::new(
+            exitcode::IOERR,
+            anyhow!("Failed to write {:?}: {}", cmd.file, e),
+        )
+    })?;
+    println!("Exported identity '{}' to {}", cmd.name, cmd.file.display());
+    Ok(())
+}
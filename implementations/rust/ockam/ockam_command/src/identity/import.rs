@@ -0,0 +1,100 @@
+use super::DEFAULT_IDENTITY;
+use crate::util::{exitcode, node_rpc};
+use crate::{help, CommandGlobalOpts};
+use anyhow::anyhow;
+use clap::Args;
+use ockam::identity::PublicIdentity;
+use ockam::Context;
+use ockam_vault::Vault;
+use std::path::PathBuf;
+
+/// Import the change history of an Identity, as written by `identity export`
+///
+/// The imported identity has no secret keys: it can only be verified, for
+/// instance to trust its identifier in secure channel trust policies.
+#[derive(Clone, Debug, Args)]
+#[command(arg_required_else_help = true, hide = help::hide())]
+pub struct ImportCommand {
+    /// Name to give to the imported identity
+    name: String,
+
+    /// Path of the file holding the hex encoded change history
+    file: PathBuf,
+}
+
+impl ImportCommand {
+    pub fn run(self, options: CommandGlobalOpts) {
+        node_rpc(run_impl, (options, self))
+    }
+}
+
+async fn run_impl(
+    _ctx: Context,
+    (opts, cmd): (CommandGlobalOpts, ImportCommand),
+) -> crate::Result<()> {
+    let cfg = &opts.config;
+    if cmd.name == DEFAULT_IDENTITY || cfg.get_imported_identity(&cmd.name).is_some() {
+        return Err(crate::Error::new(
+            exitcode::CANTCREAT,
+            anyhow!("Identity '{}' already exists", cmd.name),
+        ));
+    }
+
+    let s = std::fs::read_to_string(&cmd.file).map_err(|e| {
+        crate::Error::new(
+            exitcode::IOERR,
+            anyhow!("Failed to read {:?}: {}", cmd.file, e),
+        )
+    })?;
+    let identity = verify_change_history(s.trim()).await.map_err(|e| {
+        crate::Error::new(
+            exitcode::DATAERR,
+            anyhow!("Invalid identity file {:?}: {:#}", cmd.file, e),
+        )
+    })?;
+
+    cfg.set_imported_identity(&cmd.name, identity.export()?);
+    cfg.persist_config_updates()?;
+
+    // The identifier alone goes to stdout, to be used in trust policies
+    println!("{}", identity.identifier());
+    eprintln!("Imported identity '{}', it can only be verified", cmd.name);
+    Ok(())
+}
+
+/// Decode a hex encoded change history and verify each of its changes
+async fn verify_change_history(hex_history: &str) -> anyhow::Result<PublicIdentity> {
+    let history = hex::decode(hex_history)?;
+    Ok(PublicIdentity::import(&history, &Vault::default()).await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::embedded_node;
+    use ockam::identity::Identity;
+
+    #[test]
+    fn only_valid_change_histories_are_imported() {
+        embedded_node(
+            |ctx, _: ()| async move {
+                let vault = Vault::create();
+                let identity = Identity::create(&ctx, &vault).await?;
+                let exported = hex::encode(identity.export().await?);
+
+                let imported = verify_change_history(&exported).await.unwrap();
+                assert_eq!(imported.identifier(), identity.identifier());
+
+                // A change history whose signature doesn't match is rejected
+                let mut tampered = identity.export().await?;
+                let last = tampered.len() - 1;
+                tampered[last] ^= 0xff;
+                assert!(verify_change_history(&hex::encode(tampered)).await.is_err());
+                assert!(verify_change_history("not hex").await.is_err());
+                Ok(())
+            },
+            (),
+        )
+        .unwrap();
+    }
+}
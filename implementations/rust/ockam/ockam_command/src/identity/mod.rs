@@ -1,12 +1,23 @@
 mod create;
+mod delete;
+mod export;
+mod import;
+mod rotate;
 mod show;
 
 pub(crate) use create::CreateCommand;
+pub(crate) use delete::DeleteCommand;
+pub(crate) use export::ExportCommand;
+pub(crate) use import::ImportCommand;
+pub(crate) use rotate::RotateCommand;
 pub(crate) use show::ShowCommand;
 
 use crate::CommandGlobalOpts;
 use clap::{Args, Subcommand};
 
+/// Name of the identity shared by nodes created without `--no-shared-identity`
+const DEFAULT_IDENTITY: &str = "default";
+
 /// Manage Identities
 #[derive(Clone, Debug, Args)]
 pub struct IdentityCommand {
@@ -20,6 +31,14 @@ pub enum IdentitySubcommand {
     Create(CreateCommand),
     /// Print short existing identity, `--full` for long identity
     Show(ShowCommand),
+    /// Delete Identity
+    Delete(DeleteCommand),
+    /// Rotate the root key of an Identity
+    Rotate(RotateCommand),
+    /// Export the change history of an Identity to a file
+    Export(ExportCommand),
+    /// Import the change history of an Identity from a file
+    Import(ImportCommand),
 }
 
 impl IdentityCommand {
@@ -27,6 +46,10 @@ impl IdentityCommand {
         match self.subcommand {
             IdentitySubcommand::Create(c) => c.run(options),
             IdentitySubcommand::Show(c) => c.run(options),
+            IdentitySubcommand::Delete(c) => c.run(options),
+            IdentitySubcommand::Rotate(c) => c.run(options),
+            IdentitySubcommand::Export(c) => c.run(options),
+            IdentitySubcommand::Import(c) => c.run(options),
         }
     }
 }
@@ -0,0 +1,134 @@
+use super::DEFAULT_IDENTITY;
+use crate::util::{exitcode, node_rpc};
+use crate::{help, CommandGlobalOpts, OckamConfig};
+use anyhow::anyhow;
+use clap::Args;
+use ockam::identity::Identity;
+use ockam::Context;
+use ockam_api::nodes::models::vault::VaultBackend;
+use ockam_vault::storage::FileStorage;
+use ockam_vault::Vault;
+use std::sync::Arc;
+
+/// Rotate the root key of an Identity
+#[derive(Clone, Debug, Args)]
+#[command(arg_required_else_help = true, hide = help::hide())]
+pub struct RotateCommand {
+    /// Name of the identity, only `default` can be rotated for now
+    name: String,
+}
+
+impl RotateCommand {
+    pub fn run(self, options: CommandGlobalOpts) {
+        node_rpc(run_impl, (options, self))
+    }
+}
+
+async fn run_impl(
+    ctx: Context,
+    (opts, cmd): (CommandGlobalOpts, RotateCommand),
+) -> crate::Result<()> {
+    let cfg = &opts.config;
+    let (identity, vault_path) = match (cfg.get_default_identity(), cfg.get_default_vault_path()) {
+        (Some(identity), Some(vault_path)) if cmd.name == DEFAULT_IDENTITY => {
+            (identity, vault_path)
+        }
+        _ => {
+            return Err(crate::Error::new(
+                exitcode::NOINPUT,
+                anyhow!("Identity '{}' was not found", cmd.name),
+            ))
+        }
+    };
+
+    // The CLI only reaches keys kept in its own software vault
+    if let Some((node, backend)) = hardware_vault_using(cfg, &identity) {
+        return Err(crate::Error::new(
+            exitcode::UNAVAILABLE,
+            anyhow!(
+                "Identity '{}' is kept in the {} vault of node {}, its keys can't be rotated from the CLI",
+                cmd.name,
+                backend,
+                node
+            ),
+        ));
+    }
+
+    let storage = FileStorage::create(vault_path).await?;
+    let vault = Vault::new(Some(Arc::new(storage)));
+    let (rotated, changes) = rotate_root_key(&ctx, &vault, &identity).await?;
+
+    cfg.set_default_identity(Some(rotated));
+    cfg.persist_config_updates()?;
+
+    println!(
+        "Rotated the root key of identity '{}', its history has {} changes",
+        cmd.name, changes
+    );
+    eprintln!(
+        "Peers which know this identity must fetch and verify its updated history, \
+         and nodes created before the rotation keep using the previous key"
+    );
+    Ok(())
+}
+
+/// Append the rotation of the root key to the exported `identity`, returning
+/// the updated history and its number of changes
+async fn rotate_root_key(
+    ctx: &Context,
+    vault: &Vault,
+    identity: &[u8],
+) -> crate::Result<(Vec<u8>, usize)> {
+    let identity = Identity::import(ctx, identity, vault).await?;
+    identity.rotate_root_key().await?;
+    let changes = identity.to_public().await?.changes_count();
+    Ok((identity.export().await?, changes))
+}
+
+/// A node created with `identity` whose vault isn't a software vault
+fn hardware_vault_using(cfg: &OckamConfig, identity: &[u8]) -> Option<(String, VaultBackend)> {
+    let nodes: Vec<_> = cfg.inner().nodes.keys().cloned().collect();
+    nodes.into_iter().find_map(|node| {
+        let node_cfg = cfg.node(&node).ok()?;
+        let state = node_cfg.state().read();
+        match &state.vault_backend {
+            Some(backend)
+                if *backend != VaultBackend::Software
+                    && state.identity.as_deref() == Some(identity) =>
+            {
+                Some((node, backend.clone()))
+            }
+            _ => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::embedded_node;
+    use ockam::identity::PublicIdentity;
+
+    #[test]
+    fn rotation_appends_one_verified_change() {
+        embedded_node(
+            |ctx, _: ()| async move {
+                let vault = Vault::create();
+                let identity = Identity::create(&ctx, &vault).await?;
+                let before = identity.to_public().await?.changes_count();
+
+                let (rotated, changes) =
+                    rotate_root_key(&ctx, &vault, &identity.export().await?).await?;
+                assert_eq!(changes, before + 1);
+
+                // The rotated history is verified when it is imported
+                let public = PublicIdentity::import(&rotated, &vault).await?;
+                assert_eq!(public.changes_count(), before + 1);
+                assert_eq!(public.identifier(), identity.identifier());
+                Ok(())
+            },
+            (),
+        )
+        .unwrap();
+    }
+}
@@ -15,9 +15,11 @@ mod message;
 mod node;
 mod project;
 mod reset;
+mod run;
 mod secure_channel;
 mod service;
 mod space;
+mod status;
 mod subscription;
 mod tcp;
 mod terminal;
@@ -39,15 +41,18 @@ use message::MessageCommand;
 use node::NodeCommand;
 use project::ProjectCommand;
 use reset::ResetCommand;
+use run::RunCommand;
 use secure_channel::{listener::SecureChannelListenerCommand, SecureChannelCommand};
 use service::ServiceCommand;
 use space::SpaceCommand;
+use status::StatusCommand;
 use std::path::PathBuf;
+use std::time::Duration;
 use tcp::{
     connection::TcpConnectionCommand, inlet::TcpInletCommand, listener::TcpListenerCommand,
     outlet::TcpOutletCommand,
 };
-use util::{exitcode, exitcode::ExitCode, setup_logging, OckamConfig};
+use util::{exitcode, exitcode::ExitCode, parse_duration, setup_logging, OckamConfig};
 use vault::VaultCommand;
 use version::Version;
 
@@ -178,7 +183,8 @@ pub struct GlobalArgs {
     #[arg(global = true, long, short, conflicts_with("verbose"))]
     quiet: bool,
 
-    /// Increase verbosity of trace messages
+    /// Increase verbosity of trace messages: -v for info, -vv for debug and -vvv
+    /// for trace of the ockam crates, -vvvv for trace of all dependencies too
     #[arg(
         global = true,
         long,
@@ -207,6 +213,21 @@ pub struct GlobalArgs {
     #[arg(global = true, long, hide = true)]
     test_argument_parser: bool,
 
+    /// Write trace messages to this file instead of stdout, rotating it
+    /// once it grows too large
+    #[arg(global = true, long, hide = true)]
+    log_file: Option<PathBuf>,
+
+    /// How long to wait for the response of a node to a request, e.g. `500ms` or `1m`
+    #[arg(
+        global = true,
+        long,
+        value_name = "DURATION",
+        default_value = "30s",
+        value_parser = parse_duration
+    )]
+    timeout: Duration,
+
     #[command(flatten)]
     export: ExportCommandArgs,
 }
@@ -264,6 +285,8 @@ pub enum OckamSubcommand {
     Project(ProjectCommand),
     #[command(display_order = 803)]
     Reset(ResetCommand),
+    #[command(display_order = 804)]
+    Status(StatusCommand),
 
     #[command(display_order = 811)]
     Node(NodeCommand),
@@ -285,6 +308,8 @@ pub enum OckamSubcommand {
     Forwarder(ForwarderCommand),
     #[command(display_order = 820)]
     Message(MessageCommand),
+    #[command(display_order = 821)]
+    Run(RunCommand),
 
     #[command(display_order = 900)]
     Completion(CompletionCommand),
@@ -304,13 +329,23 @@ pub fn run() {
         .collect::<Vec<_>>();
     let args = input.clone();
     let command: OckamCommand = OckamCommand::parse_from(input);
+    Error::use_json_output(command.global_args.output_format == OutputFormat::Json);
 
     if !command.global_args.test_argument_parser {
         check_if_an_upgrade_is_available();
     }
 
     if !command.global_args.quiet {
-        setup_logging(command.global_args.verbose, command.global_args.no_color);
+        let log_level = match &command.subcommand {
+            OckamSubcommand::Node(c) => c.log_level(),
+            _ => None,
+        };
+        setup_logging(
+            command.global_args.verbose,
+            log_level,
+            command.global_args.no_color,
+            command.global_args.log_file.as_deref(),
+        );
         tracing::debug!("{}", Version::short());
         tracing::debug!("Parsed {:?}", &command);
     }
@@ -361,6 +396,8 @@ impl OckamCommand {
             OckamSubcommand::Credential(c) => c.run(options),
             OckamSubcommand::Subscription(c) => c.run(options),
             OckamSubcommand::Reset(c) => c.run(options),
+            OckamSubcommand::Run(c) => c.run(options),
+            OckamSubcommand::Status(c) => c.run(options),
             OckamSubcommand::Admin(c) => c.run(options),
         }
     }
@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use anyhow::Context as _;
 use clap::Args;
 
@@ -10,7 +12,7 @@ use ockam_multiaddr::MultiAddr;
 
 use crate::node::util::{delete_embedded_node, start_embedded_node};
 use crate::util::api::CloudOpts;
-use crate::util::{extract_address_value, node_rpc, RpcBuilder};
+use crate::util::{extract_address_value, node_rpc, parse_route, RpcBuilder};
 use crate::Result;
 use crate::{help, message::HELP_DETAIL, CommandGlobalOpts};
 
@@ -22,15 +24,28 @@ pub struct SendCommand {
     #[arg(short, long, value_name = "NODE")]
     from: Option<String>,
 
-    /// The route to send the message to
-    #[arg(short, long, value_name = "ROUTE")]
+    /// The route to send the message to, e.g. `/node/n1/service/echoer`,
+    /// `udp://127.0.0.1:4000/service/echoer`, or
+    /// `tcp://127.0.0.1:4000/secure/service/echoer` to go through a secure
+    /// channel
+    #[arg(short, long, value_name = "ROUTE", value_parser = parse_route)]
     pub to: MultiAddr,
 
-    /// Override Default Timeout
-    #[arg(long, value_name = "TIMEOUT")]
-    pub timeout: Option<u64>,
+    /// The message to send
+    #[arg(required_unless_present_any = ["file", "hex"])]
+    pub message: Option<String>,
+
+    /// Send the content of this file instead of a message
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["message", "hex"])]
+    file: Option<PathBuf>,
+
+    /// Send these hex encoded bytes instead of a message
+    #[arg(long, value_name = "HEX", conflicts_with = "message", value_parser = parse_hex)]
+    hex: Option<Vec<u8>>,
 
-    pub message: String,
+    /// Write the reply to this file instead of printing it
+    #[arg(long, value_name = "PATH")]
+    output: Option<PathBuf>,
 
     #[command(flatten)]
     cloud_opts: CloudOpts,
@@ -40,10 +55,29 @@ impl SendCommand {
     pub fn run(self, options: CommandGlobalOpts) {
         node_rpc(rpc, (options, self))
     }
+
+    /// The bytes to send, from the message, the file or the hex input
+    fn payload(&self) -> Result<Vec<u8>> {
+        if let Some(path) = &self.file {
+            let payload = std::fs::read(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            return Ok(payload);
+        }
+        if let Some(hex) = &self.hex {
+            return Ok(hex.clone());
+        }
+        Ok(self.message.clone().unwrap_or_default().into_bytes())
+    }
+}
+
+fn parse_hex(s: &str) -> std::result::Result<Vec<u8>, String> {
+    hex::decode(s).map_err(|_| "the message must be hex encoded".to_string())
 }
 
 async fn rpc(mut ctx: Context, (opts, cmd): (CommandGlobalOpts, SendCommand)) -> Result<()> {
     async fn go(ctx: &mut Context, opts: &CommandGlobalOpts, cmd: SendCommand) -> Result<()> {
+        let payload = cmd.payload()?;
+
         // Process `--to` Multiaddr
         let (to, meta) = clean_multiaddr(&cmd.to, &opts.config.lookup())
             .context("Argument '--to' is invalid")?;
@@ -75,12 +109,18 @@ async fn rpc(mut ctx: Context, (opts, cmd): (CommandGlobalOpts, SendCommand)) ->
         let mut rpc = RpcBuilder::new(ctx, opts, &api_node)
             .tcp(tcp.as_ref())?
             .build();
-        rpc.request(req(&to, &cmd.message)).await?;
+        rpc.request(req(&to, &payload)).await?;
         let res = rpc.parse_response::<Vec<u8>>()?;
-        println!(
-            "{}",
-            String::from_utf8(res).context("Received content is not a valid utf8 string")?
-        );
+        match &cmd.output {
+            Some(path) => std::fs::write(path, res)
+                .with_context(|| format!("Failed to write the reply to {}", path.display()))?,
+            None => println!(
+                "{}",
+                String::from_utf8(res).context(
+                    "Received content is not a valid utf8 string, use --output to save it to a file"
+                )?
+            ),
+        }
 
         // only delete node in case 'from' is empty and embedded node was started before
         if cmd.from.is_none() {
@@ -92,6 +132,6 @@ async fn rpc(mut ctx: Context, (opts, cmd): (CommandGlobalOpts, SendCommand)) ->
     go(&mut ctx, &opts, cmd).await
 }
 
-pub(crate) fn req<'a>(to: &'a MultiAddr, message: &'a str) -> RequestBuilder<'a, SendMessage<'a>> {
-    Request::post("v0/message").body(SendMessage::new(to, message.as_bytes()))
+pub(crate) fn req<'a>(to: &'a MultiAddr, message: &'a [u8]) -> RequestBuilder<'a, SendMessage<'a>> {
+    Request::post("v0/message").body(SendMessage::new(to, message))
 }
@@ -1,42 +1,84 @@
-use clap::Args;
+use clap::{Args, ValueEnum};
 use rand::prelude::random;
+use serde::{Deserialize, Serialize};
 
 use anyhow::{anyhow, Context as _, Result};
 use std::{
-    net::{IpAddr, SocketAddr},
+    net::{IpAddr, SocketAddr, ToSocketAddrs, UdpSocket},
     path::{Path, PathBuf},
     str::FromStr,
+    time::Duration,
 };
 
 use crate::node::util::run::CommandsRunner;
 use crate::node::util::{
-    add_project_authority, create_default_identity_if_needed, get_identity_override,
+    add_project_authority, create_default_identity_if_needed, delete_node, get_identity_from_vault,
+    get_identity_override,
 };
 use crate::project::ProjectInfo;
 use crate::secure_channel::listener::create as secure_channel_listener;
-use crate::service::config::Config;
+use crate::service::config::{Config, ServiceConfigs, ServiceSpec};
 use crate::service::start;
-use crate::util::{bind_to_port_check, embedded_node_that_is_not_stopped, exitcode};
+use crate::util::dog::RestartPolicy;
+use crate::util::{
+    api, bind_to_port_check, dog, embedded_node_that_is_not_stopped, env_file, exitcode, json_file,
+    metrics::NodeMetrics, parse_duration, parse_log_level, probe::NodeProbe, RpcBuilder,
+};
 use crate::{
     help,
     node::show::print_query_status,
     node::HELP_DETAIL,
     project,
-    util::{connect_to, embedded_node, find_available_port, startup},
-    CommandGlobalOpts,
+    util::{
+        connect_to, embedded_node, find_available_port,
+        startup::{self, SpawnNodeOptions, PRE_SHARED_KEY_ENV},
+    },
+    CommandGlobalOpts, OckamConfig,
 };
+use ockam::identity::authenticated_storage::quota::StorageQuota;
+use ockam::identity::MIN_PRE_SHARED_KEY_LENGTH;
 use ockam::{Address, AsyncTryClone, TCP};
-use ockam::{Context, TcpTransport};
+use ockam::{Context, ResourceLimits, TcpTransport};
 use ockam_api::{
+    clean_multiaddr,
+    nodes::models::secure_channel::{CreateSecureChannelResponse, CredentialExchangeMode},
     nodes::models::transport::{TransportMode, TransportType},
     nodes::{
         service::{
             NodeManagerGeneralOptions, NodeManagerProjectsOptions, NodeManagerTransportOptions,
         },
-        NodeManager, NodeManagerWorker, NODEMANAGER_ADDR,
+        CredentialChecks, CredentialOutagePolicy, NodeManager, NodeManagerWorker, NODEMANAGER_ADDR,
     },
 };
 use ockam_core::LOCAL;
+use ockam_multiaddr::MultiAddr;
+#[cfg(unix)]
+use ockam_transport_uds::UdsTransport;
+use tracing::{error, info};
+
+/// How long a node with a credential grace period waits for the project
+/// authority before using its cached credential
+const AUTHORITY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a bootstrap peer has to accept a secure channel
+const BOOTSTRAP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Value of `--credential-checks`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CredentialChecksArg {
+    On,
+    Off,
+}
+
+impl From<CredentialChecksArg> for CredentialChecks {
+    fn from(arg: CredentialChecksArg) -> Self {
+        match arg {
+            CredentialChecksArg::On => CredentialChecks::On,
+            CredentialChecksArg::Off => CredentialChecks::Off,
+        }
+    }
+}
 
 /// Create Nodes
 #[derive(Clone, Debug, Args)]
@@ -50,7 +92,10 @@ pub struct CreateCommand {
     #[arg(display_order = 900, long, short)]
     pub foreground: bool,
 
-    /// TCP listener address
+    /// TCP listener address, e.g. `127.0.0.1:4000` or `localhost:4000`
+    ///
+    /// A hostname is resolved when the node is created, and the first of
+    /// its addresses which belongs to a local interface is bound.
     #[arg(
         display_order = 900,
         long,
@@ -60,13 +105,41 @@ pub struct CreateCommand {
     )]
     pub tcp_listener_address: String,
 
+    /// Externally reachable address of the TCP listener
+    ///
+    /// Use this when binding a wildcard address such as `0.0.0.0:4000`
+    /// so that peers and the node status report a concrete address.
+    #[arg(display_order = 900, long, id = "ADVERTISED_ADDRESS")]
+    pub advertised_address: Option<String>,
+
     /// Skip creation of default Vault and Identity
     #[arg(long, short, hide = true)]
     pub skip_defaults: bool,
 
-    /// Skip credential checks
+    /// Whether credentials are exchanged on the secure channels the node creates
+    ///
+    /// With `on`, the node presents its project credential when it creates
+    /// a secure channel, and closes the channel if the peer's credential
+    /// wasn't issued by the project's authority. It requires `--project`.
+    /// With `off`, no credential is exchanged, so the peer's credential is
+    /// never checked.
+    #[arg(
+        display_order = 900,
+        long,
+        value_enum,
+        default_value_t = CredentialChecksArg::Off
+    )]
+    pub credential_checks: CredentialChecksArg,
+
+    /// Keep using a credential issued less than this long ago, e.g. `10m`,
+    /// while the project authority is unreachable. Requires `--credential-checks on`
+    #[arg(long, hide = true, value_name = "DURATION", value_parser = parse_duration)]
+    pub credential_grace_period: Option<Duration>,
+
+    /// Get a credential from the project authority as the node starts, and
+    /// present it on every secure channel until it nearly expires
     #[arg(long, hide = true)]
-    pub enable_credential_checks: bool,
+    pub prefetch_credential: bool,
 
     /// Don't share default identity with this node
     #[arg(long, hide = true)]
@@ -76,22 +149,265 @@ pub struct CreateCommand {
     #[arg(display_order = 900, long, hide = true)]
     pub child_process: bool,
 
-    /// JSON config to setup a foreground node
+    /// JSON config to setup a node
     ///
-    /// This argument is currently ignored on background nodes.  Node
-    /// configuration is run asynchronously and may take several
+    /// Node configuration is run asynchronously and may take several
     /// seconds to complete.
     #[arg(long, hide = true)]
     pub launch_config: Option<PathBuf>,
 
+    /// Service to start with the node, as `<name>` or `<name>=<address>`,
+    /// e.g. `identity` or `secure-channel-listener=listener`. Can be given
+    /// several times. Services are one of `vault`, `identity`,
+    /// `secure-channel-listener` and `verifier`.
+    #[arg(
+        display_order = 900,
+        long,
+        value_name = "SERVICE",
+        conflicts_with_all = ["launch_config", "no_api"]
+    )]
+    pub service: Vec<ServiceSpec>,
+
+    /// File of KEY=VALUE lines to add to the node's environment
+    ///
+    /// Variables are set before the services of the launch config
+    /// are started, which can reference them as `${KEY}`.
+    #[arg(long, hide = true, value_name = "PATH")]
+    pub env_file: Option<PathBuf>,
+
     #[arg(long, hide = true)]
     pub no_watchdog: bool,
 
+    /// Check every given number of seconds that the node process is
+    /// running, and restart it if it died. Restarts are logged to the
+    /// `watchdog.log` file of the node.
+    #[arg(
+        display_order = 900,
+        long,
+        value_name = "SECONDS",
+        conflicts_with_all = ["foreground", "no_watchdog"],
+        value_parser = parse_watchdog_interval
+    )]
+    pub watchdog_interval: Option<Duration>,
+
+    /// Which exits of the node the watchdog restarts it after. A node
+    /// stopping on its own, e.g. with `--exit-on-idle`, didn't fail.
+    #[arg(
+        display_order = 900,
+        long,
+        value_enum,
+        default_value_t = RestartPolicy::Always,
+        requires = "watchdog_interval"
+    )]
+    pub restart_policy: RestartPolicy,
+
+    /// Stop watching the node after the watchdog restarted it that many
+    /// times. Crash loops are also slowed down by waiting longer and longer
+    /// between restarts.
+    #[arg(
+        display_order = 900,
+        long,
+        value_name = "COUNT",
+        requires = "watchdog_interval"
+    )]
+    pub max_restarts: Option<u32>,
+
     #[arg(long, hide = true)]
     pub project: Option<PathBuf>,
 
     #[arg(long, hide = true)]
     pub config: Option<PathBuf>,
+
+    /// Don't start the node manager API.
+    ///
+    /// The node only creates its transports and routes messages.
+    /// Commands that query or configure the node will fail.
+    #[arg(display_order = 900, long, conflicts_with_all = ["launch_config", "config"])]
+    pub no_api: bool,
+
+    /// Stop a foreground node once it has had no open connection nor
+    /// traffic for the given duration, e.g. `30s` or `5m`.
+    #[arg(
+        display_order = 900,
+        long,
+        requires = "foreground",
+        value_name = "DURATION",
+        value_parser = parse_duration
+    )]
+    pub exit_on_idle: Option<Duration>,
+
+    /// Replace an existing node of the same name, stopping and deleting it.
+    /// Without it, creating a node whose name is taken fails.
+    #[arg(display_order = 900, long)]
+    pub overwrite: bool,
+
+    /// Require this hex encoded key, of at least 16 bytes, on the node's
+    /// default secure channel listener. Channels created with
+    /// `secure-channel create --pre-shared-key` prove the key, so nodes
+    /// created with the same key can talk to each other without any other
+    /// trust setup. The key is kept in the node's vault.
+    #[arg(
+        display_order = 900,
+        long,
+        value_name = "HEX",
+        value_parser = parse_pre_shared_key
+    )]
+    pub pre_shared_key: Option<PreSharedKey>,
+
+    /// Route to a secure channel listener to connect to once the node is
+    /// up, e.g. `/node/n1/service/api`. Can be given several times.
+    /// Peers which can't be reached are logged and skipped.
+    #[arg(
+        display_order = 900,
+        long,
+        value_name = "ROUTE",
+        conflicts_with = "no_api"
+    )]
+    pub bootstrap_peer: Vec<MultiAddr>,
+
+    /// Stop the node if any of its bootstrap peers can't be reached
+    #[arg(display_order = 900, long, requires = "bootstrap_peer")]
+    pub require_bootstrap: bool,
+
+    /// Serve liveness and readiness probes over HTTP on this address,
+    /// e.g. `0.0.0.0:8080`. `GET /livez` succeeds while the node runs,
+    /// `GET /readyz` once its transports and services are up, until the
+    /// node starts shutting down.
+    #[arg(display_order = 900, long, value_name = "SOCKET_ADDRESS")]
+    pub probe_address: Option<SocketAddr>,
+
+    /// Serve Prometheus metrics of the node's TCP transport over HTTP on
+    /// this address, e.g. `0.0.0.0:9090`, at `GET /metrics`. Metrics
+    /// aren't served by default.
+    #[arg(display_order = 900, long, value_name = "SOCKET_ADDRESS")]
+    pub metrics_address: Option<SocketAddr>,
+
+    /// Stop the node if the metrics can't be served on `--metrics-address`,
+    /// rather than only logging the error
+    #[arg(display_order = 900, long, requires = "metrics_address")]
+    pub require_metrics: bool,
+
+    /// Reject new workers, and thus new secure channels, once the node
+    /// runs this many workers. Running workers are left alone.
+    #[arg(display_order = 900, long, value_name = "COUNT")]
+    pub max_workers: Option<usize>,
+
+    /// Reject new workers while the node process uses more than this many
+    /// bytes of resident memory. The memory is only measured on Linux.
+    #[arg(display_order = 900, long, value_name = "BYTES")]
+    pub memory_hint: Option<usize>,
+
+    /// Reject the writes which would take the node's authenticated storage
+    /// past this many entries
+    #[arg(
+        display_order = 900,
+        long,
+        value_name = "COUNT",
+        conflicts_with = "no_api"
+    )]
+    pub storage_max_entries: Option<usize>,
+
+    /// Reject the writes which would take the node's authenticated storage
+    /// past this many bytes, counting the id, key and value of every entry
+    #[arg(
+        display_order = 900,
+        long,
+        value_name = "BYTES",
+        conflicts_with = "no_api"
+    )]
+    pub storage_max_bytes: Option<usize>,
+
+    /// Also listen for connections from nodes of the same host on a Unix
+    /// domain socket at this path. A socket file left by a node which
+    /// didn't shut down is replaced, and the file is removed when the node
+    /// stops.
+    #[arg(display_order = 900, long, value_name = "PATH")]
+    pub uds_listener_path: Option<PathBuf>,
+
+    /// Forbid the node from changing its identity, e.g. rotating its keys.
+    /// The node can still create and accept secure channels. The node
+    /// keeps this mode across restarts.
+    #[arg(display_order = 900, long)]
+    pub readonly_identity: bool,
+
+    /// Create the node's identity from a key already in a vault, given as
+    /// `<vault>:<key-id>` where `<vault>` is the path of a vault file. No
+    /// key is generated and the vault file is only read, the node gets a
+    /// copy of it. The key must be an Ed25519 secret key.
+    #[arg(
+        display_order = 900,
+        long,
+        value_name = "VAULT:KEY_ID",
+        value_parser = parse_vault_key,
+        conflicts_with = "no_shared_identity"
+    )]
+    pub identity_from_vault: Option<VaultKey>,
+
+    /// Which trace messages the node logs, as a `RUST_LOG`-style filter,
+    /// e.g. `info,ockam_transport_udp=trace`. Overrides `--verbose`.
+    #[arg(
+        display_order = 900,
+        long,
+        value_name = "FILTER",
+        value_parser = parse_log_level
+    )]
+    pub log_level: Option<String>,
+}
+
+/// Key material given to `--pre-shared-key`, never printed
+#[derive(Clone)]
+pub struct PreSharedKey(Vec<u8>);
+
+impl std::fmt::Debug for PreSharedKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PreSharedKey(..)")
+    }
+}
+
+/// Key of a vault file given to `--identity-from-vault`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VaultKey {
+    pub vault_path: PathBuf,
+    pub key_id: String,
+}
+
+impl std::fmt::Display for VaultKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.vault_path.display(), self.key_id)
+    }
+}
+
+impl CreateCommand {
+    /// Options of the node process to spawn, `launch_config` being the
+    /// validated path of `--launch-config`
+    fn spawn_options(&self, launch_config: Option<PathBuf>) -> SpawnNodeOptions {
+        SpawnNodeOptions {
+            skip_defaults: self.skip_defaults,
+            no_shared_identity: self.no_shared_identity,
+            credential_checks: self.credential_checks,
+            credential_grace_period: self.credential_grace_period,
+            prefetch_credential: self.prefetch_credential,
+            advertised_address: self.advertised_address.clone(),
+            project: self.project.clone(),
+            launch_config,
+            services: self.service.clone(),
+            no_api: self.no_api,
+            pre_shared_key: self.pre_shared_key.as_ref().map(|key| hex::encode(&key.0)),
+            bootstrap_peers: self.bootstrap_peer.clone(),
+            require_bootstrap: self.require_bootstrap,
+            probe_address: self.probe_address,
+            metrics_address: self.metrics_address,
+            require_metrics: self.require_metrics,
+            max_workers: self.max_workers,
+            memory_hint: self.memory_hint,
+            storage_max_entries: self.storage_max_entries,
+            storage_max_bytes: self.storage_max_bytes,
+            uds_listener_path: self.uds_listener_path.clone(),
+            readonly_identity: self.readonly_identity,
+            identity_from_vault: self.identity_from_vault.as_ref().map(|key| key.to_string()),
+            log_level: self.log_level.clone(),
+        }
+    }
 }
 
 impl Default for CreateCommand {
@@ -100,14 +416,39 @@ impl Default for CreateCommand {
             node_name: hex::encode(&random::<[u8; 4]>()),
             foreground: false,
             tcp_listener_address: "127.0.0.1:0".to_string(),
+            advertised_address: None,
             skip_defaults: false,
-            enable_credential_checks: false,
+            credential_checks: CredentialChecksArg::Off,
+            credential_grace_period: None,
+            prefetch_credential: false,
             no_shared_identity: false,
             child_process: false,
             launch_config: None,
+            service: Vec::new(),
+            env_file: None,
             no_watchdog: false,
+            watchdog_interval: None,
+            restart_policy: RestartPolicy::Always,
+            max_restarts: None,
             project: None,
             config: None,
+            no_api: false,
+            exit_on_idle: None,
+            overwrite: false,
+            pre_shared_key: None,
+            bootstrap_peer: Vec::new(),
+            require_bootstrap: false,
+            probe_address: None,
+            metrics_address: None,
+            require_metrics: false,
+            max_workers: None,
+            memory_hint: None,
+            storage_max_entries: None,
+            storage_max_bytes: None,
+            uds_listener_path: None,
+            readonly_identity: false,
+            identity_from_vault: None,
+            log_level: None,
         }
     }
 }
@@ -115,29 +456,203 @@ impl Default for CreateCommand {
 impl CreateCommand {
     pub fn run(self, opts: CommandGlobalOpts) {
         if let Err(e) = run_impl(opts, self) {
-            eprintln!("{}", e);
-            std::process::exit(e.code());
+            e.exit();
         }
     }
 
+    /// Resources the node process may use
+    fn resource_limits(&self) -> ResourceLimits {
+        let mut limits = ResourceLimits::new();
+        if let Some(max_workers) = self.max_workers {
+            limits = limits.max_workers(max_workers);
+        }
+        if let Some(memory_hint) = self.memory_hint {
+            limits = limits.memory_hint(memory_hint);
+        }
+        limits
+    }
+
+    /// Limits of the node's authenticated storage, `None` when unlimited
+    fn storage_quota(&self) -> Option<StorageQuota> {
+        if self.storage_max_entries.is_none() && self.storage_max_bytes.is_none() {
+            return None;
+        }
+        let mut quota = StorageQuota::new();
+        if let Some(max_entries) = self.storage_max_entries {
+            quota = quota.with_max_entries(max_entries);
+        }
+        if let Some(max_bytes) = self.storage_max_bytes {
+            quota = quota.with_max_bytes(max_bytes);
+        }
+        Some(quota)
+    }
+
     fn overwrite_addr(&self) -> Result<Self> {
         let cmd = self.clone();
         let addr: SocketAddr = if &cmd.tcp_listener_address == "127.0.0.1:0" {
             let port = find_available_port().context("failed to acquire available port")?;
             SocketAddr::new(IpAddr::from_str("127.0.0.1")?, port)
         } else {
-            cmd.tcp_listener_address.parse()?
+            resolve_listener_address(&cmd.tcp_listener_address)?
         };
+        if let Some(advertised) = &cmd.advertised_address {
+            let advertised: SocketAddr = advertised
+                .parse()
+                .context("advertised address must be a socket address")?;
+            if advertised.ip().is_unspecified() {
+                return Err(anyhow!(
+                    "advertised address {advertised} must not be a wildcard address"
+                ));
+            }
+        }
         Ok(Self {
             tcp_listener_address: addr.to_string(),
             ..cmd
         })
     }
+
+    /// The address peers should use to reach this node's TCP listener
+    fn reachable_address(&self) -> String {
+        self.advertised_address
+            .clone()
+            .unwrap_or_else(|| self.tcp_listener_address.clone())
+    }
+
+    /// Fail closed unless a credential grace period was given
+    fn credential_outage_policy(&self) -> CredentialOutagePolicy {
+        match self.credential_grace_period {
+            Some(grace_period) => CredentialOutagePolicy::GracePeriod {
+                grace_period,
+                authority_timeout: AUTHORITY_TIMEOUT,
+            },
+            None => CredentialOutagePolicy::FailClosed,
+        }
+    }
+}
+
+/// Parse a listener address, resolving a hostname to the first of its
+/// addresses which belongs to a local interface
+fn resolve_listener_address(address: &str) -> Result<SocketAddr> {
+    if let Ok(addr) = address.parse() {
+        return Ok(addr);
+    }
+    let resolved: Vec<SocketAddr> = address
+        .to_socket_addrs()
+        .with_context(|| format!("failed to resolve TCP listener address {address}"))?
+        .collect();
+    // Only the addresses of local interfaces can be bound
+    let mut addr = resolved
+        .iter()
+        .find(|addr| UdpSocket::bind((addr.ip(), 0)).is_ok())
+        .copied()
+        .ok_or_else(|| {
+            anyhow!("TCP listener address {address} doesn't resolve to a local interface")
+        })?;
+    if addr.port() == 0 {
+        let port = find_available_port().context("failed to acquire available port")?;
+        addr.set_port(port);
+    }
+    Ok(addr)
+}
+
+/// Parse a watchdog interval in seconds, between 1 second and 1 hour
+fn parse_watchdog_interval(s: &str) -> std::result::Result<Duration, String> {
+    let secs: u64 = s
+        .parse()
+        .map_err(|_| format!("invalid number of seconds: {s}"))?;
+    if !(1..=3600).contains(&secs) {
+        return Err("the watchdog interval must be between 1 and 3600 seconds".to_string());
+    }
+    Ok(Duration::from_secs(secs))
+}
+
+/// Parse a hex encoded pre-shared key of at least 16 bytes
+fn parse_pre_shared_key(s: &str) -> std::result::Result<PreSharedKey, String> {
+    let key = hex::decode(s).map_err(|_| "the pre-shared key must be hex encoded".to_string())?;
+    if key.len() < MIN_PRE_SHARED_KEY_LENGTH {
+        return Err(format!(
+            "the pre-shared key must be at least {MIN_PRE_SHARED_KEY_LENGTH} bytes long"
+        ));
+    }
+    Ok(PreSharedKey(key))
+}
+
+/// Parse `<vault>:<key-id>`, the path of an existing vault file and the
+/// id of one of its keys
+fn parse_vault_key(s: &str) -> std::result::Result<VaultKey, String> {
+    let (vault_path, key_id) = s
+        .rsplit_once(':')
+        .filter(|(path, key_id)| !path.is_empty() && !key_id.is_empty())
+        .ok_or_else(|| "expected <vault>:<key-id>".to_string())?;
+    // Absolute, so that a background node finds it too
+    let vault_path = Path::new(vault_path)
+        .canonicalize()
+        .map_err(|_| format!("vault {vault_path} not found"))?;
+    Ok(VaultKey {
+        vault_path,
+        key_id: key_id.to_string(),
+    })
+}
+
+/// The pre-shared key of the node, passed to a background node through
+/// the environment so that it doesn't show up in its command line
+fn pre_shared_key(cmd: &CreateCommand) -> Result<Option<PreSharedKey>> {
+    if let Some(key) = &cmd.pre_shared_key {
+        return Ok(Some(key.clone()));
+    }
+    if !cmd.child_process {
+        return Ok(None);
+    }
+    match std::env::var(PRE_SHARED_KEY_ENV) {
+        Ok(key) => parse_pre_shared_key(&key).map(Some).map_err(|e| anyhow!(e)),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Record the advertised address of a node so routes to it resolve
+/// to an externally reachable address rather than the bind address
+fn set_advertised_address(cfg: &OckamConfig, cmd: &CreateCommand) -> Result<()> {
+    if let Some(advertised) = &cmd.advertised_address {
+        let advertised: SocketAddr = advertised.parse()?;
+        cfg.set_node_advertised_address(&cmd.node_name, advertised)?;
+        cfg.set_node_alias(cmd.node_name.clone(), advertised.into());
+    }
+    Ok(())
 }
 
 fn run_impl(opts: CommandGlobalOpts, cmd: CreateCommand) -> crate::Result<()> {
     let verbose = opts.global_args.verbose;
     let cfg = &opts.config;
+
+    if cmd.credential_grace_period.is_some() && cmd.credential_checks == CredentialChecksArg::Off {
+        return Err(crate::Error::new(
+            exitcode::USAGE,
+            anyhow!("--credential-grace-period requires --credential-checks on"),
+        ));
+    }
+
+    // Check the name before anything else so that a refusal leaves the
+    // existing node and the config untouched. Child processes run nodes
+    // which were just added to the config, so they are expected to exist.
+    if !cmd.child_process && cfg.get_node(&cmd.node_name).is_ok() {
+        if !cmd.overwrite {
+            return Err(crate::Error::new(
+                exitcode::CANTCREAT,
+                anyhow!(
+                    "Node {} already exists, use --overwrite to replace it",
+                    cmd.node_name
+                ),
+            ));
+        }
+        delete_node(&opts, &cmd.node_name, false);
+        cfg.persist_config_updates()?;
+    }
+
+    // A background node inherits the environment set here
+    if let Some(path) = &cmd.env_file {
+        env_file::apply(path).map_err(|e| crate::Error::new(exitcode::CONFIG, e))?;
+    }
+
     if cmd.foreground {
         let cmd = cmd.overwrite_addr()?;
         let addr = SocketAddr::from_str(&cmd.tcp_listener_address)?;
@@ -148,9 +663,16 @@ fn run_impl(opts: CommandGlobalOpts, cmd: CreateCommand) -> crate::Result<()> {
         if cfg.get_node_dir(&cmd.node_name).is_err() {
             println!("Creating node directory...");
             cfg.create_node(&cmd.node_name, addr, verbose)?;
+            set_advertised_address(cfg, &cmd)?;
+            cfg.set_node_no_api(&cmd.node_name, cmd.no_api)?;
             cfg.persist_config_updates()?;
         }
-        embedded_node_that_is_not_stopped(run_foreground_node, (opts.clone(), cmd, addr))?;
+        // Let a watchdog tell a crash from the node stopping on its own
+        let node_dir = cfg.get_node_dir(&cmd.node_name)?;
+        dog::forget_exit(&node_dir);
+        let limits = cmd.resource_limits();
+        embedded_node_that_is_not_stopped(limits, run_foreground_node, (opts.clone(), cmd, addr))?;
+        dog::record_clean_exit(&node_dir);
     } else {
         if cmd.child_process {
             return Err(crate::Error::new(
@@ -159,12 +681,19 @@ fn run_impl(opts: CommandGlobalOpts, cmd: CreateCommand) -> crate::Result<()> {
             ));
         }
 
+        // Validate the config up front so a bad one doesn't leave a half-configured node
+        if let Some(config_path) = &cmd.config {
+            CommandsRunner::validate(config_path).map_err(|e| {
+                crate::Error::new(exitcode::CONFIG, e.context("Invalid node config"))
+            })?;
+        }
+
         let cmd = cmd.overwrite_addr()?;
         let addr = SocketAddr::from_str(&cmd.tcp_listener_address)?;
         embedded_node(spawn_background_node, (opts.clone(), cmd.clone(), addr))?;
         connect_to(
             addr.port(),
-            (cfg.clone(), cmd.node_name.clone(), true),
+            (cfg.clone(), cmd.node_name.clone(), true, false),
             print_query_status,
         );
         if let Some(config_path) = &cmd.config {
@@ -189,7 +718,9 @@ async fn run_foreground_node(
         create_default_identity_if_needed(&ctx, cfg).await?;
     }
 
-    let identity_override = if cmd.skip_defaults || cmd.no_shared_identity {
+    let identity_override = if let Some(key) = &cmd.identity_from_vault {
+        Some(get_identity_from_vault(&ctx, key).await?)
+    } else if cmd.skip_defaults || cmd.no_shared_identity {
         None
     } else {
         Some(get_identity_override(&ctx, cfg).await?)
@@ -198,7 +729,7 @@ async fn run_foreground_node(
     let project_id = match &cmd.project {
         Some(path) => {
             let s = tokio::fs::read_to_string(path).await?;
-            let p: ProjectInfo = serde_json::from_str(&s)?;
+            let p: ProjectInfo = json_file::parse(path, &s)?;
             let project_id = p.id.to_string();
             project::config::set_project(cfg, &(&p).into()).await?;
             add_project_authority(p, &cmd.node_name, cfg).await?;
@@ -207,30 +738,98 @@ async fn run_foreground_node(
         None => None,
     };
 
+    let probe = NodeProbe::default();
+    if let Some(probe_address) = cmd.probe_address {
+        probe.serve(probe_address).await?;
+    }
+
     let tcp = TcpTransport::create(&ctx).await?;
-    let bind = cmd.tcp_listener_address;
+    let bind = cmd.tcp_listener_address.clone();
     tcp.listen(&bind).await?;
 
+    if let Some(metrics_address) = cmd.metrics_address {
+        serve_metrics(&tcp, metrics_address, cmd.require_metrics).await?;
+    }
+
+    #[cfg(unix)]
+    let uds = match &cmd.uds_listener_path {
+        Some(path) => Some((listen_uds(&ctx, path).await?, path.clone())),
+        None => None,
+    };
+    #[cfg(not(unix))]
+    if cmd.uds_listener_path.is_some() {
+        return Err(crate::Error::new(
+            exitcode::USAGE,
+            anyhow!("Unix domain sockets are only supported on unix"),
+        ));
+    }
+
+    if let Some(idle_timeout) = cmd.exit_on_idle {
+        stop_when_idle(&ctx, &tcp, idle_timeout).await?;
+    }
+
+    // A pure data-plane node only needs its transports
+    if cmd.no_api {
+        info!(
+            "Node {} started without the node manager API",
+            cmd.node_name
+        );
+        probe.set_ready();
+        return Ok(());
+    }
+
     let node_dir = cfg.get_node_dir(&cmd.node_name)?;
     let projects = cfg.inner().lookup().projects().collect();
+    let authenticated_storage = NodeManager::node_dir_authenticated_storage(&node_dir).await?;
+    let general_options = NodeManagerGeneralOptions::new(
+        cmd.node_name.clone(),
+        node_dir,
+        cmd.skip_defaults || cmd.launch_config.is_some() || !cmd.service.is_empty(),
+        cmd.credential_checks.into(),
+        identity_override,
+        Some(authenticated_storage),
+    )
+    .with_credential_outage_policy(cmd.credential_outage_policy());
+    let general_options = match pre_shared_key(&cmd)? {
+        Some(PreSharedKey(key)) => general_options.with_pre_shared_key(key),
+        None => general_options,
+    };
+    let general_options = if cmd.readonly_identity {
+        general_options.with_read_only_identity()
+    } else {
+        general_options
+    };
+    let general_options = if cmd.prefetch_credential {
+        general_options.with_credential_prefetch()
+    } else {
+        general_options
+    };
+    let general_options = match cmd.storage_quota() {
+        Some(quota) => general_options.with_storage_quota(quota),
+        None => general_options,
+    };
+    let transport_options = NodeManagerTransportOptions::new(
+        (
+            TransportType::Tcp,
+            TransportMode::Listen,
+            cmd.reachable_address(),
+        ),
+        tcp.async_try_clone().await?,
+    );
+    #[cfg(unix)]
+    let transport_options = match uds {
+        Some((uds, path)) => transport_options.with_uds_transport(uds, path),
+        None => transport_options,
+    };
     let node_man = NodeManager::create(
         &ctx,
-        NodeManagerGeneralOptions::new(
-            cmd.node_name.clone(),
-            node_dir,
-            cmd.skip_defaults || cmd.launch_config.is_some(),
-            cmd.enable_credential_checks,
-            identity_override,
-        ),
+        general_options,
         NodeManagerProjectsOptions::new(
             Some(&cfg.authorities(&cmd.node_name)?.snapshot()),
             project_id,
             projects,
         ),
-        NodeManagerTransportOptions::new(
-            (TransportType::Tcp, TransportMode::Listen, bind),
-            tcp.async_try_clone().await?,
-        ),
+        transport_options,
     )
     .await?;
     let node_manager_worker = NodeManagerWorker::new(node_man);
@@ -238,33 +837,147 @@ async fn run_foreground_node(
     ctx.start_worker(NODEMANAGER_ADDR, node_manager_worker)
         .await?;
 
-    if let Some(path) = cmd.launch_config {
+    connect_bootstrap_peers(&ctx, &tcp, &opts, &cmd).await?;
+
+    let services = match &cmd.launch_config {
+        Some(path) => Config::read(path)?.startup_services,
+        None if !cmd.service.is_empty() => Some(ServiceConfigs::from_specs(&cmd.service)?),
+        None => None,
+    };
+    if let Some(services) = services {
         let node_opts = super::NodeOpts {
             api_node: cmd.node_name,
         };
-        start_services(&ctx, &tcp, &path, addr, node_opts, &opts).await?
+        start_services(&ctx, &tcp, services, addr, node_opts, &opts).await?
+    }
+
+    probe.set_ready();
+    Ok(())
+}
+
+/// Create a secure channel from the node to each of its bootstrap peers
+///
+/// Failures are only logged, unless the node requires all its bootstrap
+/// peers, in which case the node doesn't start.
+async fn connect_bootstrap_peers(
+    ctx: &Context,
+    tcp: &TcpTransport,
+    opts: &CommandGlobalOpts,
+    cmd: &CreateCommand,
+) -> crate::Result<()> {
+    let mut failures = 0;
+    for peer in &cmd.bootstrap_peer {
+        match connect_bootstrap_peer(ctx, tcp, opts, cmd, peer).await {
+            Ok(channel) => info!(
+                "Connected to bootstrap peer {} through secure channel {}",
+                peer, channel
+            ),
+            Err(e) => {
+                error!("Failed to connect to bootstrap peer {}: {:#}", peer, e);
+                failures += 1;
+            }
+        }
+    }
+    if failures > 0 && cmd.require_bootstrap {
+        return Err(crate::Error::new(
+            exitcode::UNAVAILABLE,
+            anyhow!(
+                "{} of {} bootstrap peers could not be reached",
+                failures,
+                cmd.bootstrap_peer.len()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Ask the node manager for a secure channel to `peer`, returning its address
+async fn connect_bootstrap_peer(
+    ctx: &Context,
+    tcp: &TcpTransport,
+    opts: &CommandGlobalOpts,
+    cmd: &CreateCommand,
+    peer: &MultiAddr,
+) -> Result<String> {
+    let (to, _) = clean_multiaddr(peer, &opts.config.lookup())
+        .ok_or_else(|| anyhow!("could not convert {} into a route", peer))?;
+    let credential_exchange_mode = if cmd.credential_checks == CredentialChecksArg::On {
+        CredentialExchangeMode::Mutual
+    } else {
+        CredentialExchangeMode::None
+    };
+    let mut rpc = RpcBuilder::new(ctx, opts, &cmd.node_name).tcp(tcp)?.build();
+    rpc.request_with_timeout(
+        api::create_secure_channel(&to, None, credential_exchange_mode, None, false),
+        BOOTSTRAP_TIMEOUT,
+    )
+    .await?;
+    let response = rpc.parse_response::<CreateSecureChannelResponse>()?;
+    Ok(response.addr.to_string())
+}
+
+/// Serve the metrics of the node's TCP transport on `addr`
+///
+/// A failure to bind is only logged, unless the node requires its
+/// metrics, in which case the node doesn't start.
+async fn serve_metrics(
+    tcp: &TcpTransport,
+    addr: SocketAddr,
+    require_metrics: bool,
+) -> crate::Result<()> {
+    match NodeMetrics::new(tcp.metrics()).serve(addr).await {
+        Ok(_) => Ok(()),
+        Err(e) if require_metrics => Err(crate::Error::new(exitcode::UNAVAILABLE, e)),
+        Err(e) => {
+            error!("Node metrics are not served: {:#}", e);
+            Ok(())
+        }
     }
+}
+
+/// Listen for connections on a Unix domain socket at `path`
+#[cfg(unix)]
+async fn listen_uds(ctx: &Context, path: &Path) -> crate::Result<UdsTransport> {
+    let uds = UdsTransport::create(ctx).await?;
+    uds.listen(path).await.map_err(|e| {
+        crate::Error::new(
+            exitcode::CANTCREAT,
+            anyhow!("Failed to listen on {}: {}", path.display(), e),
+        )
+    })?;
+    Ok(uds)
+}
 
+/// Stop the node once its TCP transport has had no open connection
+/// nor traffic for `idle_timeout`
+async fn stop_when_idle(ctx: &Context, tcp: &TcpTransport, idle_timeout: Duration) -> Result<()> {
+    let mut ctx = ctx.async_try_clone().await?;
+    let metrics = tcp.metrics();
+    let check_interval =
+        (idle_timeout / 4).clamp(Duration::from_millis(100), Duration::from_secs(5));
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(check_interval).await;
+            if metrics.active_connections() == 0 && metrics.idle_for() >= idle_timeout {
+                info!("Node was idle for {:?}, stopping", idle_timeout);
+                if let Err(e) = ctx.stop().await {
+                    error!("Failed to stop idle node: {}", e);
+                }
+                break;
+            }
+        }
+    });
     Ok(())
 }
 
 async fn start_services(
     ctx: &Context,
     tcp: &TcpTransport,
-    cfg: &Path,
+    config: ServiceConfigs,
     addr: SocketAddr,
     node_opts: super::NodeOpts,
     opts: &CommandGlobalOpts,
 ) -> Result<()> {
-    let config = {
-        let c = Config::read(cfg)?;
-        if let Some(sc) = c.startup_services {
-            sc
-        } else {
-            return Ok(());
-        }
-    };
-
     let addr = Address::from((TCP, addr.to_string()));
     tcp.connect(addr.address()).await?;
 
@@ -288,7 +1001,7 @@ async fn start_services(
             let ids = cfg.authorized_identifiers;
             let rte = addr.clone().into();
             println!("starting secure-channel listener ...");
-            secure_channel_listener::create_listener(ctx, adr, ids, rte).await?;
+            secure_channel_listener::create_listener(ctx, opts, adr, ids, rte).await?;
         }
     }
     if let Some(cfg) = config.verifier {
@@ -332,26 +1045,234 @@ async fn spawn_background_node(
         ));
     }
 
+    // The key isn't persisted, a restarted node wouldn't have it
+    if cmd.watchdog_interval.is_some() && cmd.pre_shared_key.is_some() {
+        return Err(crate::Error::new(
+            exitcode::USAGE,
+            anyhow!(
+                "A watched node can't be given --pre-shared-key, set {PRE_SHARED_KEY_ENV} instead"
+            ),
+        ));
+    }
+
+    // Report an invalid launch config now rather than in the node's logs
+    let launch_config = match &cmd.launch_config {
+        Some(path) => {
+            Config::read(path).map_err(|e| crate::Error::new(exitcode::CONFIG, e))?;
+            Some(path.canonicalize()?)
+        }
+        None => None,
+    };
+    ServiceConfigs::from_specs(&cmd.service).map_err(|e| crate::Error::new(exitcode::USAGE, e))?;
+    if let Some(key) = &cmd.identity_from_vault {
+        get_identity_from_vault(&ctx, key)
+            .await
+            .map_err(|e| crate::Error::new(exitcode::USAGE, e))?;
+    }
+
     // First we create a new node in the configuration so that
     // we can ask it for the correct log path, as well as
     // making sure the watchdog can do its job later on.
     cfg.create_node(&cmd.node_name, addr, verbose)?;
+    set_advertised_address(cfg, &cmd)?;
+    cfg.set_node_no_api(&cmd.node_name, cmd.no_api)?;
     cfg.persist_config_updates()?;
 
     create_default_identity_if_needed(&ctx, cfg).await?;
 
     // Construct the arguments list and re-execute the ockam
     // CLI in foreground mode to start the newly created node
+    let options = cmd.spawn_options(launch_config);
+    options.save(&cfg.get_node_dir(&cmd.node_name)?)?;
     startup::spawn_node(
         &opts.config,
         verbose,
-        cmd.skip_defaults,
-        cmd.no_shared_identity,
-        cmd.enable_credential_checks,
         &cmd.node_name,
         &cmd.tcp_listener_address,
-        cmd.project.as_deref(),
+        &options,
     )?;
 
+    if let Some(interval) = cmd.watchdog_interval {
+        dog::spawn(
+            cfg,
+            &cmd.node_name,
+            interval,
+            cmd.restart_policy,
+            cmd.max_restarts,
+        )?;
+    }
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcard_listener_reports_advertised_address() {
+        let cmd = CreateCommand {
+            tcp_listener_address: "0.0.0.0:4000".to_string(),
+            advertised_address: Some("192.0.2.10:4000".to_string()),
+            ..Default::default()
+        };
+        let cmd = cmd.overwrite_addr().unwrap();
+        assert_eq!(cmd.tcp_listener_address, "0.0.0.0:4000");
+        assert_eq!(cmd.reachable_address(), "192.0.2.10:4000");
+
+        let cmd = CreateCommand {
+            tcp_listener_address: "127.0.0.1:4000".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(cmd.reachable_address(), "127.0.0.1:4000");
+    }
+
+    #[test]
+    fn resource_limits_follow_the_options() {
+        assert_eq!(
+            CreateCommand::default().resource_limits(),
+            ResourceLimits::new()
+        );
+        let cmd = CreateCommand {
+            max_workers: Some(100),
+            memory_hint: Some(64 * 1024 * 1024),
+            ..Default::default()
+        };
+        assert_eq!(
+            cmd.resource_limits(),
+            ResourceLimits::new()
+                .max_workers(100)
+                .memory_hint(64 * 1024 * 1024)
+        );
+    }
+
+    #[test]
+    fn storage_quota_follows_the_options() {
+        assert_eq!(CreateCommand::default().storage_quota(), None);
+        let cmd = CreateCommand {
+            storage_max_entries: Some(1000),
+            ..Default::default()
+        };
+        assert_eq!(
+            cmd.storage_quota(),
+            Some(StorageQuota::new().with_max_entries(1000))
+        );
+    }
+
+    #[test]
+    fn watchdog_interval_must_be_sane() {
+        assert_eq!(
+            parse_watchdog_interval("5").unwrap(),
+            Duration::from_secs(5)
+        );
+        assert!(parse_watchdog_interval("0").is_err());
+        assert!(parse_watchdog_interval("3601").is_err());
+        assert!(parse_watchdog_interval("-1").is_err());
+        assert!(parse_watchdog_interval("5s").is_err());
+    }
+
+    #[test]
+    fn listener_address_can_be_a_hostname() {
+        let addr = resolve_listener_address("localhost:4000").unwrap();
+        assert!(addr.ip().is_loopback());
+        assert_eq!(addr.port(), 4000);
+
+        // A port is picked, as for the default address
+        assert_ne!(resolve_listener_address("localhost:0").unwrap().port(), 0);
+
+        assert!(resolve_listener_address("unknown.invalid:4000").is_err());
+        assert!(resolve_listener_address("localhost").is_err());
+    }
+
+    #[test]
+    fn advertised_address_must_be_concrete() {
+        let cmd = CreateCommand {
+            tcp_listener_address: "0.0.0.0:4000".to_string(),
+            advertised_address: Some("0.0.0.0:4000".to_string()),
+            ..Default::default()
+        };
+        assert!(cmd.overwrite_addr().is_err());
+    }
+
+    #[ockam::test]
+    async fn node_identity_from_an_existing_vault_key(ctx: &mut Context) -> ockam::Result<()> {
+        use ockam::identity::Identity;
+        use ockam_core::vault::{
+            SecretAttributes, SecretPersistence, SecretType, SecretVault,
+            CURVE25519_SECRET_LENGTH_U32,
+        };
+        use ockam_vault::{storage::FileStorage, Vault};
+        use std::sync::Arc;
+
+        // A software vault provisioned with a key beforehand
+        let dir = tempfile::tempdir().unwrap();
+        let vault_path = dir.path().join("vault.json");
+        let secret = [0x2a; 32];
+        let key_id = {
+            let storage = FileStorage::create(vault_path.clone()).await?;
+            let vault = Vault::new(Some(Arc::new(storage)));
+            let attributes = SecretAttributes::new(
+                SecretType::Ed25519,
+                SecretPersistence::Persistent,
+                CURVE25519_SECRET_LENGTH_U32,
+            );
+            vault.secret_import(&secret, attributes).await?
+        };
+        let provisioned = std::fs::read(&vault_path).unwrap();
+
+        let key = parse_vault_key(&format!("{}:{}", vault_path.display(), key_id)).unwrap();
+        let identity_override = get_identity_from_vault(ctx, &key).await.unwrap();
+
+        // The node's identity is the one of the key, and no key was added
+        let expected = Identity::create_with_key(ctx, &Vault::create(), &secret).await?;
+        let identity = Identity::import(ctx, &identity_override.identity, &Vault::create()).await?;
+        assert_eq!(identity.identifier(), expected.identifier());
+        assert_eq!(
+            identity_override.vault_path,
+            vault_path.canonicalize().unwrap()
+        );
+        assert_eq!(std::fs::read(&vault_path).unwrap(), provisioned);
+
+        // Unknown keys and vaults are rejected
+        let unknown = parse_vault_key(&format!("{}:unknown", vault_path.display())).unwrap();
+        assert!(get_identity_from_vault(ctx, &unknown).await.is_err());
+        assert!(parse_vault_key("/does/not/exist.json:key").is_err());
+        assert!(parse_vault_key(&vault_path.display().to_string()).is_err());
+
+        ctx.stop().await
+    }
+
+    #[test]
+    fn spawn_options_are_restored_from_the_node_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(SpawnNodeOptions::load(dir.path()).unwrap(), None);
+
+        let cmd = CreateCommand {
+            credential_checks: CredentialChecksArg::On,
+            credential_grace_period: Some(Duration::from_secs(30)),
+            service: vec!["verifier=my_verifier".parse().unwrap()],
+            pre_shared_key: Some(parse_pre_shared_key(&"2a".repeat(32)).unwrap()),
+            bootstrap_peer: vec!["/dnsaddr/localhost/tcp/4000".parse().unwrap()],
+            probe_address: Some("127.0.0.1:9001".parse().unwrap()),
+            metrics_address: Some("127.0.0.1:9002".parse().unwrap()),
+            max_workers: Some(64),
+            storage_max_entries: Some(1000),
+            uds_listener_path: Some(PathBuf::from("/tmp/node.sock")),
+            log_level: Some("debug".to_string()),
+            ..Default::default()
+        };
+        let options = cmd.spawn_options(None);
+        options.save(dir.path()).unwrap();
+
+        // Everything but the pre-shared key is restored
+        let restored = SpawnNodeOptions::load(dir.path()).unwrap().unwrap();
+        assert_eq!(restored.pre_shared_key, None);
+        assert_eq!(
+            restored,
+            SpawnNodeOptions {
+                pre_shared_key: None,
+                ..options
+            }
+        );
+    }
+}
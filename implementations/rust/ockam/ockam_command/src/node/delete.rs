@@ -22,8 +22,7 @@ pub struct DeleteCommand {
 impl DeleteCommand {
     pub fn run(self, options: CommandGlobalOpts) {
         if let Err(e) = run_impl(options, self) {
-            eprintln!("{}", e);
-            std::process::exit(e.code());
+            e.exit();
         }
     }
 }
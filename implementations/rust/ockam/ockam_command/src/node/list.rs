@@ -1,5 +1,6 @@
 use crate::util::{connect_to, exitcode, verify_pids};
-use crate::{help, node::show::print_query_status, node::HELP_DETAIL, CommandGlobalOpts};
+use crate::{help, node::show::print_query_status, node::HELP_DETAIL, CommandGlobalOpts, Error};
+use anyhow::anyhow;
 use clap::Args;
 
 /// List Nodes
@@ -14,8 +15,11 @@ impl ListCommand {
             let inner = cfg.inner();
 
             if inner.nodes.is_empty() {
-                println!("No nodes registered on this system!");
-                std::process::exit(exitcode::IOERR);
+                Error::new(
+                    exitcode::IOERR,
+                    anyhow!("No nodes registered on this system!"),
+                )
+                .exit();
             }
 
             // Before printing node state we have to verify it.  This
@@ -32,7 +36,7 @@ impl ListCommand {
         cfg.inner().nodes.iter().for_each(|(node_name, node_cfg)| {
             connect_to(
                 node_cfg.port(),
-                (cfg.clone(), node_name.clone(), false),
+                (cfg.clone(), node_name.clone(), false, false),
                 print_query_status,
             )
         });
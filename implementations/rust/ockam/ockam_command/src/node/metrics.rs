@@ -0,0 +1,42 @@
+use clap::Args;
+
+use ockam::Context;
+use ockam_api::nodes::models::base::MetricsStatus;
+
+use crate::node::HELP_DETAIL;
+use crate::util::{api, node_rpc, Rpc};
+use crate::{help, CommandGlobalOpts};
+
+/// Show the traffic metrics of a node
+#[derive(Clone, Debug, Args)]
+#[command(after_long_help = help::template(HELP_DETAIL))]
+pub struct MetricsCommand {
+    /// Name of the node.
+    #[arg(default_value = "default")]
+    node_name: String,
+
+    /// Reset the counters to zero, showing their values before the reset.
+    /// The open connections are not reset.
+    #[arg(long)]
+    reset: bool,
+}
+
+impl MetricsCommand {
+    pub fn run(self, options: CommandGlobalOpts) {
+        node_rpc(run_impl, (options, self));
+    }
+}
+
+async fn run_impl(
+    ctx: Context,
+    (opts, cmd): (CommandGlobalOpts, MetricsCommand),
+) -> crate::Result<()> {
+    let mut rpc = Rpc::background(&ctx, &opts, &cmd.node_name)?;
+    if cmd.reset {
+        rpc.request(api::reset_metrics()).await?;
+    } else {
+        rpc.request(api::query_metrics()).await?;
+    }
+    rpc.parse_and_print_response::<MetricsStatus>()?;
+    Ok(())
+}
@@ -1,23 +1,31 @@
 use clap::{Args, Subcommand};
 
-pub(crate) use create::CreateCommand;
+pub(crate) use create::{CreateCommand, CredentialChecksArg};
 use delete::DeleteCommand;
 use list::ListCommand;
+use metrics::MetricsCommand;
 use run::RunCommand;
 use show::ShowCommand;
 use start::StartCommand;
 use stop::StopCommand;
+use watchdog::WatchdogCommand;
+use workers::WorkersCommand;
+
+pub(crate) use start::respawn_node;
 
 use crate::{help, CommandGlobalOpts};
 
 mod create;
 mod delete;
 mod list;
+mod metrics;
 mod run;
 mod show;
 mod start;
 mod stop;
 pub mod util;
+mod watchdog;
+mod workers;
 
 const HELP_DETAIL: &str = "\
 About:
@@ -102,6 +110,13 @@ Examples:
     # Show information about a specific node
     $ ockam node show n1
 
+    # List the workers running on a node, and the cluster they belong to
+    $ ockam node workers n1
+
+    # Show the traffic metrics of a node, then reset its counters
+    $ ockam node metrics n1
+    $ ockam node metrics n1 --reset
+
     # List all created nodes
     $ ockam node list
 
@@ -144,6 +159,12 @@ pub enum NodeSubcommand {
     Start(StartCommand),
     #[command(display_order = 800)]
     Stop(StopCommand),
+    #[command(display_order = 800)]
+    Workers(WorkersCommand),
+    #[command(display_order = 800)]
+    Metrics(MetricsCommand),
+    #[command(hide = true)]
+    Watchdog(WatchdogCommand),
 }
 
 impl NodeCommand {
@@ -156,6 +177,17 @@ impl NodeCommand {
             NodeSubcommand::Show(c) => c.run(options),
             NodeSubcommand::Start(c) => c.run(options),
             NodeSubcommand::Stop(c) => c.run(options),
+            NodeSubcommand::Workers(c) => c.run(options),
+            NodeSubcommand::Metrics(c) => c.run(options),
+            NodeSubcommand::Watchdog(c) => c.run(options),
+        }
+    }
+
+    /// The log filter given to `node create --log-level`
+    pub fn log_level(&self) -> Option<&str> {
+        match &self.subcommand {
+            NodeSubcommand::Create(c) => c.log_level.as_deref(),
+            _ => None,
         }
     }
 }
@@ -18,8 +18,7 @@ impl RunCommand {
     pub fn run(self, options: CommandGlobalOpts) {
         if let Err(e) = self.run_impl(options) {
             error!(%e);
-            eprintln!("{e:?}");
-            std::process::exit(e.code());
+            e.exit();
         }
     }
 
@@ -1,11 +1,12 @@
 use crate::util::{api, connect_to, exitcode, OckamConfig};
-use crate::{help, node::HELP_DETAIL, CommandGlobalOpts};
-use anyhow::Context;
+use crate::{help, node::HELP_DETAIL, CommandGlobalOpts, Error};
+use anyhow::{anyhow, Context};
 use clap::Args;
 use colorful::Colorful;
 use minicbor::Decoder;
 use ockam_api::config::cli::NodeConfigOld;
 use ockam_api::nodes::models::portal::{InletList, OutletList};
+use ockam_api::nodes::models::secure_channel::SecureChannelListenerList;
 use ockam_api::nodes::models::services::ServiceList;
 use ockam_api::nodes::models::transport::TransportList;
 use ockam_api::nodes::NODEMANAGER_ADDR;
@@ -14,11 +15,14 @@ use ockam_core::api::{Response, Status};
 use ockam_core::{Result, Route};
 use ockam_multiaddr::proto::{DnsAddr, Node, Tcp};
 use ockam_multiaddr::MultiAddr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::time::Duration;
+use tokio::net::TcpStream;
 
 const IS_NODE_UP_ATTEMPTS: usize = 10;
 const IS_NODE_UP_SLEEP_MILLIS: u64 = 250;
 const SEND_RECEIVE_TIMEOUT_SECS: u64 = 1;
+const LISTENER_CHECK_TIMEOUT_SECS: u64 = 1;
 
 /// Show Nodes
 #[derive(Clone, Debug, Args)]
@@ -27,6 +31,11 @@ pub struct ShowCommand {
     /// Name of the node.
     #[arg(default_value = "default")]
     node_name: String,
+
+    /// Check that each TCP listener of the node accepts connections from
+    /// this host
+    #[arg(long)]
+    check: bool,
 }
 
 impl ShowCommand {
@@ -35,13 +44,18 @@ impl ShowCommand {
         let port = match cfg.inner().nodes.get(&self.node_name) {
             Some(cfg) => cfg.port(),
             None => {
-                eprintln!("No such node available.  Run `ockam node list` to list available nodes");
-                std::process::exit(exitcode::IOERR);
+                Error::new(
+                    exitcode::IOERR,
+                    anyhow!(
+                        "No such node available.  Run `ockam node list` to list available nodes"
+                    ),
+                )
+                .exit();
             }
         };
         connect_to(
             port,
-            (cfg.clone(), self.node_name, false),
+            (cfg.clone(), self.node_name, false, self.check),
             print_query_status,
         );
     }
@@ -58,7 +72,8 @@ fn print_node_info(
     default_id: &str,
     services: Option<&ServiceList>,
     tcp_listeners: Option<&TransportList>,
-    secure_channel_listeners: Option<&Vec<String>>,
+    listener_checks: Option<&[bool]>,
+    secure_channel_listeners: Option<&SecureChannelListenerList>,
     inlets_outlets: Option<(&InletList, &OutletList)>,
 ) {
     println!();
@@ -89,21 +104,25 @@ fn print_node_info(
 
     if let Some(list) = tcp_listeners {
         println!("  Transports:");
-        for e in &list.list {
+        for (i, e) in list.list.iter().enumerate() {
             println!("    Transport:");
             println!("      Type: {}", e.tt);
             println!("      Mode: {}", e.tm);
             println!("      Address: {}", e.payload);
+            if let Some(reachable) = listener_checks.and_then(|checks| checks.get(i)) {
+                println!("      Reachable: {}", if *reachable { "yes" } else { "no" });
+            }
         }
     }
 
     if let Some(list) = secure_channel_listeners {
         println!("  Secure Channel Listeners:");
-        for e in list {
+        for e in &list.list {
             println!("    Listener:");
-            if let Some(ma) = addr_to_multiaddr(e) {
+            if let Some(ma) = addr_to_multiaddr(e.addr.as_ref()) {
                 println!("      Address: {}", ma);
             }
+            println!("      Trust Policy: {}", e.trust_policy);
         }
     }
 
@@ -143,14 +162,21 @@ fn print_node_info(
 
 pub async fn print_query_status(
     mut ctx: ockam::Context,
-    (cfg, node_name, wait_until_ready): (OckamConfig, String, bool),
+    (cfg, node_name, wait_until_ready, check_listeners): (OckamConfig, String, bool, bool),
     mut base_route: Route,
 ) -> anyhow::Result<()> {
     let route = base_route.modify().append(NODEMANAGER_ADDR).into();
     let node_cfg = cfg.get_node(&node_name)?;
 
-    if !is_node_up(&mut ctx, &route, wait_until_ready).await? {
-        print_node_info(&node_cfg, &node_name, "DOWN", "N/A", None, None, None, None);
+    if node_cfg.no_api() {
+        // There is no API to query the node's state from
+        print_node_info(
+            &node_cfg, &node_name, "NO API", "N/A", None, None, None, None, None,
+        );
+    } else if !is_node_up(&mut ctx, &route, wait_until_ready).await? {
+        print_node_info(
+            &node_cfg, &node_name, "DOWN", "N/A", None, None, None, None, None,
+        );
     } else {
         // Get short id for the node
         let resp: Vec<u8> = ctx
@@ -190,19 +216,29 @@ pub async fn print_query_status(
             .await
             .context("Failed to get list of tcp listeners from node")?;
         let tcp_listeners = api::parse_tcp_list(&resp)?;
+        let listener_checks = match check_listeners {
+            true => {
+                let mut checks = Vec::with_capacity(tcp_listeners.list.len());
+                for e in &tcp_listeners.list {
+                    checks.push(is_listener_reachable(&e.payload).await);
+                }
+                Some(checks)
+            }
+            false => None,
+        };
 
         // Get list of Secure Channel Listeners
         let resp: Vec<u8> = ctx
             .send_and_receive_with_timeout(
                 route.clone(),
-                api::list_secure_channel_listener().to_vec()?,
+                api::list_secure_channel_listener_status().to_vec()?,
                 SEND_RECEIVE_TIMEOUT_SECS,
             )
             .await
             .context("Failed to get list of secure channel listeners from node")?;
         let mut dec = Decoder::new(&resp);
         let _ = dec.decode::<Response>()?;
-        let secure_channel_listeners = dec.decode::<Vec<String>>()?;
+        let secure_channel_listeners = dec.decode::<SecureChannelListenerList>()?;
 
         // Get list of inlets
         let resp: Vec<u8> = ctx
@@ -233,6 +269,7 @@ pub async fn print_query_status(
             &default_id,
             Some(&services),
             Some(&tcp_listeners),
+            listener_checks.as_deref(),
             Some(&secure_channel_listeners),
             Some((&inlets, &outlets)),
         );
@@ -282,3 +319,47 @@ async fn is_node_up(
 
     Ok(false)
 }
+
+/// Connect to a TCP listener bound to `bind_addr` to check whether it
+/// accepts connections from this host
+///
+/// A listener bound to every interface is dialed on the loopback one.
+async fn is_listener_reachable(bind_addr: &str) -> bool {
+    let mut addr = match bind_addr.parse::<SocketAddr>() {
+        Ok(addr) => addr,
+        Err(_) => return false,
+    };
+    if addr.ip().is_unspecified() {
+        addr.set_ip(match addr.ip() {
+            IpAddr::V4(_) => Ipv4Addr::LOCALHOST.into(),
+            IpAddr::V6(_) => Ipv6Addr::LOCALHOST.into(),
+        });
+    }
+    let timeout = Duration::from_secs(LISTENER_CHECK_TIMEOUT_SECS);
+    matches!(
+        tokio::time::timeout(timeout, TcpStream::connect(addr)).await,
+        Ok(Ok(_))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_listener_reachability() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let reachable = listener.local_addr().unwrap();
+        assert!(is_listener_reachable(&reachable.to_string()).await);
+
+        let unspecified = format!("0.0.0.0:{}", reachable.port());
+        assert!(is_listener_reachable(&unspecified).await);
+
+        // Nothing accepts connections once the listener is closed, as if
+        // a firewall rejected them
+        drop(listener);
+        assert!(!is_listener_reachable(&reachable.to_string()).await);
+        assert!(!is_listener_reachable("not an address").await);
+    }
+}
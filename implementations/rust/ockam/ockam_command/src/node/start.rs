@@ -2,6 +2,7 @@ use anyhow::{anyhow, Context as _};
 use clap::Args;
 use nix::unistd::Pid;
 use rand::prelude::random;
+use tracing::warn;
 
 use ockam::Context;
 
@@ -11,8 +12,11 @@ use crate::util::{connect_to, embedded_node};
 use crate::{
     help,
     node::HELP_DETAIL,
-    util::{exitcode, startup::spawn_node},
-    CommandGlobalOpts,
+    util::{
+        exitcode,
+        startup::{spawn_node, SpawnNodeOptions},
+    },
+    CommandGlobalOpts, OckamConfig,
 };
 
 /// Start Nodes
@@ -27,8 +31,7 @@ pub struct StartCommand {
 impl StartCommand {
     pub fn run(self, opts: CommandGlobalOpts) {
         if let Err(e) = run_impl(opts, self) {
-            eprintln!("{}", e);
-            std::process::exit(e.code());
+            e.exit();
         }
     }
 }
@@ -57,7 +60,7 @@ fn run_impl(opts: CommandGlobalOpts, cmd: StartCommand) -> crate::Result<()> {
     embedded_node(restart_background_node, (opts.clone(), cmd.clone()))?;
     connect_to(
         cfg_node.port(),
-        (cfg.clone(), cmd.node_name.clone(), true),
+        (cfg.clone(), cmd.node_name.clone(), true, false),
         print_query_status,
     );
     if let Ok(cfg) = cfg.node(&cmd.node_name) {
@@ -73,19 +76,47 @@ async fn restart_background_node(
     (opts, cmd): (CommandGlobalOpts, StartCommand),
 ) -> crate::Result<()> {
     let cfg = &opts.config;
-    let cfg_node = cfg.get_node(&cmd.node_name)?;
+    let options = match SpawnNodeOptions::load(&cfg.get_node_dir(&cmd.node_name)?)? {
+        Some(options) => options,
+        // The node was created before its options were persisted
+        None => {
+            let cfg_node = cfg.get_node(&cmd.node_name)?;
+            warn!(
+                "Node '{}' has no persisted options, starting it with the default ones",
+                cmd.node_name
+            );
+            SpawnNodeOptions {
+                advertised_address: cfg_node.advertised_address().map(|a| a.to_string()),
+                no_api: cfg_node.no_api(),
+                ..Default::default()
+            }
+        }
+    };
+    respawn_node(cfg, &cmd.node_name, &options)
+}
+
+/// Start the process of an existing node again, with the options it was
+/// created with
+pub(crate) fn respawn_node(
+    cfg: &OckamConfig,
+    node_name: &str,
+    options: &SpawnNodeOptions,
+) -> crate::Result<()> {
+    let cfg_node = cfg.get_node(node_name)?;
+    let options = SpawnNodeOptions {
+        // The node already exists
+        skip_defaults: true,
+        ..options.clone()
+    };
 
     // Construct the arguments list and re-execute the ockam
-    // CLI in foreground mode to start the newly created node
+    // CLI in foreground mode to start the node again
     spawn_node(
-        &opts.config,
-        cfg_node.verbose(),           // Previously user-chosen verbosity level
-        true,                         // skip-defaults because the node already exists
-        false,                        // Default value. TODO: implement persistence of this option
-        false,                        // Default value. TODO: implement persistence of this option
-        cfg_node.name(),              // The selected node name
-        &cfg_node.addr().to_string(), // The selected node api address
-        None,                         // No project information available
+        cfg,
+        cfg_node.verbose(),
+        cfg_node.name(),
+        &cfg_node.addr().to_string(),
+        &options,
     )?;
 
     Ok(())
@@ -1,9 +1,10 @@
 use crate::{
     help,
     node::HELP_DETAIL,
-    util::{exitcode, startup},
-    CommandGlobalOpts,
+    util::{dog, exitcode, startup},
+    CommandGlobalOpts, Error,
 };
+use anyhow::anyhow;
 use clap::Args;
 use rand::prelude::random;
 
@@ -24,31 +25,45 @@ impl StopCommand {
         let cfg = options.config;
         match cfg.get_node_pid(&self.node_name) {
             Ok(Some(pid)) => {
+                // Otherwise the watchdog would restart the node
+                dog::stop(&cfg, &self.node_name);
+
                 if let Err(e) = startup::stop(pid, self.force) {
-                    eprintln!("{e:?}");
-                    std::process::exit(exitcode::OSERR);
+                    Error::new(exitcode::OSERR, e).exit();
                 } else {
                     // Clear pid in config, so StartCommand does not have to rely on
                     // `kill 0 pid` to detect if a node is running.
                     if let Err(e) = cfg.set_node_pid(&self.node_name, None) {
-                        eprintln!("Failed to update pid for node {}: {}", &self.node_name, e);
-                        std::process::exit(exitcode::IOERR);
+                        Error::new(
+                            exitcode::IOERR,
+                            anyhow!("Failed to update pid for node {}: {}", &self.node_name, e),
+                        )
+                        .exit();
                     }
 
                     // Save the config update
                     if let Err(e) = cfg.persist_config_updates() {
-                        eprintln!("Failed to update configuration: {}", e);
-                        std::process::exit(exitcode::IOERR);
+                        Error::new(
+                            exitcode::IOERR,
+                            anyhow!("Failed to update configuration: {}", e),
+                        )
+                        .exit();
                     }
                 }
             }
             Ok(_) => {
-                eprintln!("Node {} is not running!", &self.node_name);
-                std::process::exit(exitcode::IOERR);
+                Error::new(
+                    exitcode::IOERR,
+                    anyhow!("Node {} is not running!", &self.node_name),
+                )
+                .exit();
             }
             Err(_) => {
-                eprintln!("Node {} does not exist!", &self.node_name);
-                std::process::exit(exitcode::IOERR);
+                Error::new(
+                    exitcode::IOERR,
+                    anyhow!("Node {} does not exist!", &self.node_name),
+                )
+                .exit();
             }
         };
     }
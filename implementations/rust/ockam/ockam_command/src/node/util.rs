@@ -1,3 +1,4 @@
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use anyhow::{anyhow, Context as _, Result};
@@ -17,10 +18,14 @@ use ockam_multiaddr::MultiAddr;
 use ockam_vault::storage::FileStorage;
 use ockam_vault::Vault;
 
+use crate::node::create::VaultKey;
 use crate::node::CreateCommand;
 use crate::project::ProjectInfo;
 use crate::{project, OckamConfig};
-use crate::{util::startup, CommandGlobalOpts};
+use crate::{
+    util::{dog, json_file, startup},
+    CommandGlobalOpts,
+};
 
 pub async fn start_embedded_node(ctx: &Context, cfg: &OckamConfig) -> Result<String> {
     let cmd = CreateCommand::default();
@@ -42,7 +47,7 @@ pub async fn start_embedded_node(ctx: &Context, cfg: &OckamConfig) -> Result<Str
     let project_id = match &cmd.project {
         Some(path) => {
             let s = tokio::fs::read_to_string(path).await?;
-            let p: ProjectInfo = serde_json::from_str(&s)?;
+            let p: ProjectInfo = json_file::parse(path, &s)?;
             let project_id = p.id.to_string();
             project::config::set_project(cfg, &(&p).into()).await?;
             add_project_authority(p, &cmd.node_name, cfg).await?;
@@ -56,14 +61,16 @@ pub async fn start_embedded_node(ctx: &Context, cfg: &OckamConfig) -> Result<Str
     tcp.listen(&bind).await?;
     let node_dir = cfg.get_node_dir_raw(&cmd.node_name)?;
     let projects = cfg.inner().lookup().projects().collect();
+    let authenticated_storage = NodeManager::node_dir_authenticated_storage(&node_dir).await?;
     let node_man = NodeManager::create(
         ctx,
         NodeManagerGeneralOptions::new(
             cmd.node_name.clone(),
             node_dir,
             cmd.skip_defaults || cmd.launch_config.is_some(),
-            cmd.enable_credential_checks,
+            cmd.credential_checks.into(),
             identity_override,
+            Some(authenticated_storage),
         ),
         NodeManagerProjectsOptions::new(
             Some(&cfg.authorities(&cmd.node_name)?.snapshot()),
@@ -135,6 +142,30 @@ pub(super) async fn get_identity_override(
     Ok(IdentityOverride {
         identity: default_identity,
         vault_path: default_vault_path,
+        shared_vault: false,
+    })
+}
+
+/// Identity of a node whose root key is in the vault file of `key`
+///
+/// The node uses the vault file in place rather than a copy of it, so
+/// that the key isn't duplicated.
+pub(super) async fn get_identity_from_vault(
+    ctx: &Context,
+    key: &VaultKey,
+) -> Result<IdentityOverride> {
+    let storage = FileStorage::create(key.vault_path.clone()).await?;
+    let vault = Vault::new(Some(Arc::new(storage)));
+
+    let identity = Identity::create_with_existing_key(ctx, &vault, &key.key_id)
+        .await
+        .map_err(|e| anyhow!("Key {} can't be the identity of a node: {}", key, e))?;
+
+    Ok(IdentityOverride {
+        identity: identity.export().await?,
+        // The node keeps the path, which mustn't depend on where it runs
+        vault_path: std::fs::canonicalize(&key.vault_path)?,
+        shared_vault: true,
     })
 }
 
@@ -169,7 +200,9 @@ pub async fn delete_embedded_node(cfg: &OckamConfig, name: &str) {
     }
 }
 
-pub fn delete_all_nodes(opts: CommandGlobalOpts, force: bool) -> anyhow::Result<()> {
+/// Delete all nodes. When forced, also delete all local state and return
+/// the directories that were removed.
+pub fn delete_all_nodes(opts: CommandGlobalOpts, force: bool) -> anyhow::Result<Vec<PathBuf>> {
     // Try to delete all nodes found in the config file + their associated processes
     let nn: Vec<String> = {
         let inner = &opts.config.inner();
@@ -197,9 +230,10 @@ pub fn delete_all_nodes(opts: CommandGlobalOpts, force: bool) -> anyhow::Result<
     }
 
     // If force is enabled
+    let mut removed = vec![];
     if force {
         // delete the config and nodes directories
-        opts.config.remove()?;
+        removed = opts.config.remove()?;
         // and all dangling/orphan ockam processes
         if let Ok(cpid) = get_current_pid() {
             let s = System::new_all();
@@ -213,7 +247,7 @@ pub fn delete_all_nodes(opts: CommandGlobalOpts, force: bool) -> anyhow::Result<
         eprintln!("Failed to update config file. You might need to run the command with --force to delete all config directories");
         return Err(e);
     }
-    Ok(())
+    Ok(removed)
 }
 
 pub fn delete_node(opts: &CommandGlobalOpts, node_name: &str, sigkill: bool) {
@@ -228,6 +262,7 @@ pub fn delete_node(opts: &CommandGlobalOpts, node_name: &str, sigkill: bool) {
 
 fn delete_node_pid(opts: &CommandGlobalOpts, node_name: &str, sigkill: bool) -> anyhow::Result<()> {
     trace!(%node_name, "Deleting node pid");
+    dog::stop(&opts.config, node_name);
     // Stop the process PID if it has one assigned in the config file
     if let Some(pid) = opts.config.get_node_pid(node_name)? {
         startup::stop(pid, sigkill)?;
@@ -260,8 +295,8 @@ fn delete_node_config(opts: &CommandGlobalOpts, node_name: &str) {
 }
 
 pub mod run {
+    use std::collections::BTreeMap;
     use std::env::current_exe;
-    #[cfg(test)]
     use std::fmt::{Display, Formatter};
     use std::io::Write;
     use std::iter::Peekable;
@@ -276,6 +311,7 @@ pub mod run {
 
     use ockam_multiaddr::proto::Node;
 
+    use crate::service::config::expand_vars;
     use crate::OckamCommand;
 
     use super::*;
@@ -286,6 +322,35 @@ pub mod run {
         pub commands: Commands,
     }
 
+    /// Commands run in order by `ockam run`
+    ///
+    /// `${NAME}` in the commands is replaced by the `NAME` environment
+    /// variable, or else by the default value given in `variables`.
+    #[derive(serde::Deserialize, Debug)]
+    pub struct Script {
+        #[serde(default)]
+        pub variables: BTreeMap<String, String>,
+        pub commands: Vec<Command>,
+    }
+
+    impl Script {
+        /// Read the script at `path`, looking variables up with `env`
+        /// before falling back to the script's defaults
+        pub fn read(path: &Path, env: impl Fn(&str) -> Option<String>) -> Result<Self> {
+            let mut json: serde_json::Value = json_file::read(path)?;
+            let defaults: BTreeMap<String, String> = match json.get("variables") {
+                Some(variables) => json_file::from_value(path, variables.clone())?,
+                None => BTreeMap::new(),
+            };
+            let lookup = |name: &str| env(name).or_else(|| defaults.get(name).cloned());
+            if let Some(commands) = json.get_mut("commands") {
+                expand_vars(commands, &lookup)
+                    .with_context(|| anyhow!("invalid script {:?}", path))?;
+            }
+            json_file::from_value(path, json)
+        }
+    }
+
     #[derive(clap::ValueEnum, Clone, Debug)]
     pub enum CommandSection {
         OnNodeInit,
@@ -298,7 +363,6 @@ pub mod run {
         }
     }
 
-    #[cfg(test)]
     impl Display for CommandSection {
         fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
             let s = match self {
@@ -313,8 +377,7 @@ pub mod run {
         pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
             let path = path.as_ref();
             let commands = if path.exists() {
-                let s = std::fs::read_to_string(path)?;
-                serde_json::from_str(&s)?
+                json_file::read(path)?
             } else {
                 Commands::default()
             };
@@ -379,6 +442,67 @@ pub mod run {
             Ok(())
         }
 
+        /// Check a config file before any node is created.
+        ///
+        /// The file must exist and be valid JSON, and every command must parse
+        /// as an `ockam` command whose referenced files and addresses are valid.
+        pub fn validate<P: AsRef<Path>>(path: P) -> Result<()> {
+            let path = path.as_ref();
+            let commands: Commands = json_file::read(path)?;
+
+            if let Some(run) = commands.run {
+                let cmd = Command::from(run);
+                CommandsRunner::validate_command(&cmd).with_context(|| {
+                    format!(
+                        "Invalid `run` section in {}: `{}`",
+                        path.display(),
+                        cmd.args().join(" ")
+                    )
+                })?;
+            }
+            for (section, cmds) in [
+                (CommandSection::OnNodeInit, &commands.on_node_init),
+                (CommandSection::OnNodeStartup, &commands.on_node_startup),
+            ] {
+                for (i, cmd) in cmds.iter().enumerate() {
+                    CommandsRunner::validate_command(cmd).with_context(|| {
+                        format!(
+                            "Invalid command #{} in `{section}` section of {}: `{}`",
+                            i + 1,
+                            path.display(),
+                            cmd.args().join(" ")
+                        )
+                    })?;
+                }
+            }
+            Ok(())
+        }
+
+        /// Parse a single command and check the resources it references
+        fn validate_command(cmd: &Command) -> Result<()> {
+            // Arguments whose value is a path that must exist when the command runs
+            const FILE_ARGS: [&str; 3] = ["--project", "--config", "--launch-config"];
+
+            let args = cmd.args();
+            OckamCommand::try_parse_from(
+                std::iter::once("ockam").chain(args.iter().map(|a| &**a)),
+            )?;
+
+            let mut it = args.iter();
+            while let Some(arg) = it.next() {
+                if arg.starts_with("/node/") {
+                    MultiAddr::from_str(arg).with_context(|| format!("Invalid address `{arg}`"))?;
+                } else if FILE_ARGS.contains(&arg.as_str()) {
+                    if let Some(file) = it.next() {
+                        if !Path::new(file).exists() {
+                            return Err(anyhow!("File `{file}` referenced by `{arg}` not found"));
+                        }
+                    }
+                }
+            }
+            Ok(())
+        }
+
         /// Create a node given the arguments from the "run" section
         pub fn run<P: AsRef<Path>>(path: P) -> Result<()> {
             let cr = Self::new(path)?;
@@ -400,7 +524,7 @@ pub mod run {
             let mut it = cmds.iter().peekable();
             // Node was just created, prompt user before executing the first command
             CommandsRunner::wait_for_prompt(it.peek())?;
-            CommandsRunner::go(&cr.exe, it)
+            CommandsRunner::go(&cr.exe, it, true)
         }
 
         /// Run "on_node_startup" commands section
@@ -408,15 +532,43 @@ pub mod run {
             let cr = Self::new(path)?;
             let cmds = cr.commands.on_node_startup;
             let it = cmds.iter().peekable();
-            CommandsRunner::go(&cr.exe, it)
+            CommandsRunner::go(&cr.exe, it, true)
+        }
+
+        /// Run the commands of a script, see [`Script`]
+        ///
+        /// Every command is checked before the first one runs, and the
+        /// script stops at the first command which fails.
+        pub fn run_script<P: AsRef<Path>>(path: P) -> Result<()> {
+            let path = path.as_ref();
+            let script = Script::read(path, |name| std::env::var(name).ok())?;
+            for (i, cmd) in script.commands.iter().enumerate() {
+                CommandsRunner::validate_command(cmd).with_context(|| {
+                    format!(
+                        "Invalid step #{} of {}: `{}`",
+                        i + 1,
+                        path.display(),
+                        cmd.args().join(" ")
+                    )
+                })?;
+            }
+            let exe = current_exe().unwrap_or_else(|_| "ockam".into());
+            CommandsRunner::go(&exe, script.commands.iter().peekable(), false)
         }
 
         /// Execute the list of commands
-        fn go(exe: &PathBuf, mut it: Peekable<Iter<Command>>) -> Result<()> {
+        ///
+        /// When `interactive`, nodes referenced by the commands are created
+        /// on demand and the user is prompted after a node is created.
+        fn go(exe: &PathBuf, mut it: Peekable<Iter<Command>>, interactive: bool) -> Result<()> {
             let mut prev_output: Option<Vec<u8>> = None;
             let mut stdin = Stdio::null();
+            let mut step = 0;
             while let Some(cmd) = it.next() {
-                CommandsRunner::command_preprocessing(exe, cmd)?;
+                step += 1;
+                if interactive {
+                    CommandsRunner::command_preprocessing(exe, cmd)?;
+                }
                 let args = cmd.args();
                 trace!("Running command `{:?}`", &args);
                 println!("\nRunning command '{}'", &args.join(" "));
@@ -447,7 +599,11 @@ pub mod run {
 
                 // Stop processing any further commands if the current command failed
                 if !output.status.success() {
-                    return Err(anyhow!("Command returned non-zero exit code"));
+                    return Err(anyhow!(
+                        "Step #{step} `{}` failed with {}",
+                        args.join(" "),
+                        output.status
+                    ));
                 }
 
                 // Save output for next command
@@ -459,8 +615,7 @@ pub mod run {
                 }
 
                 // If command was `node create`, then prompt the user before continuing to the next command.
-                let args = cmd.args();
-                if args.len() >= 2 && &args[0] == "node" && &args[1] == "create" {
+                if interactive && args.len() >= 2 && &args[0] == "node" && &args[1] == "create" {
                     CommandsRunner::wait_for_prompt(it.peek())?;
                 }
 
@@ -714,6 +869,90 @@ pub mod run {
             assert(contents);
         }
 
+        #[test]
+        fn read_script_with_variables() {
+            let contents = r#"{
+                "variables": {
+                    "OCKAM_TEST_SCRIPT_NODE": "n1",
+                    "OCKAM_TEST_SCRIPT_PEER": "n2"
+                },
+                "commands": [
+                    "node create ${OCKAM_TEST_SCRIPT_NODE}",
+                    {
+                        "command": "secure-channel create",
+                        "args": "--from /node/${OCKAM_TEST_SCRIPT_NODE} --to /node/${OCKAM_TEST_SCRIPT_PEER}/service/api"
+                    }
+                ]
+            }"#;
+            let dir = tempdir().expect("Failed to create temp dir");
+            let file_path = dir.path().join("script.json");
+            std::fs::write(&file_path, contents).expect("Failed to write contents to file");
+
+            // The environment takes precedence over the script's defaults
+            let env = |name: &str| (name == "OCKAM_TEST_SCRIPT_PEER").then(|| "blue".to_string());
+            let script = Script::read(&file_path, env).expect("Failed to read script");
+            assert_eq!(script.commands.len(), 2);
+            assert_eq!(script.commands[0].args(), vec!["node", "create", "n1"]);
+            assert_eq!(
+                script.commands[1].args(),
+                vec![
+                    "secure-channel",
+                    "create",
+                    "--from",
+                    "/node/n1",
+                    "--to",
+                    "/node/blue/service/api"
+                ]
+            );
+
+            // Undefined variables are reported
+            let contents = r#"{"commands": ["node create ${OCKAM_TEST_SCRIPT_UNSET}"]}"#;
+            std::fs::write(&file_path, contents).expect("Failed to write contents to file");
+            assert!(Script::read(&file_path, env).is_err());
+        }
+
+        #[test]
+        fn validate() {
+            let dir = tempdir().expect("Failed to create temp dir");
+            let file_path = dir.path().join("cmds.json");
+
+            // Missing file
+            assert!(CommandsRunner::validate(&file_path).is_err());
+
+            // Syntax error is reported with its location
+            let contents = "{\n  \"on_node_init\": [\n    \"node list\",\n  ]\n}";
+            std::fs::write(&file_path, contents).expect("Failed to write contents to file");
+            let err = CommandsRunner::validate(&file_path).unwrap_err();
+            assert!(format!("{err:#}").contains("line 4"));
+
+            // Unknown subcommand is reported with its section and position
+            let contents = r#"{
+                "on_node_init": ["node list"],
+                "on_node_startup": ["node list", "node frobnicate blue"]
+            }"#;
+            std::fs::write(&file_path, contents).expect("Failed to write contents to file");
+            let err = CommandsRunner::validate(&file_path).unwrap_err();
+            let err = format!("{err:#}");
+            assert!(err.contains("#2 in `node-startup` section"));
+            assert!(err.contains("node frobnicate blue"));
+
+            // Referenced files must exist
+            let contents = r#"{
+                "run": { "name": "blue", "args": "--project missing-project.json" }
+            }"#;
+            std::fs::write(&file_path, contents).expect("Failed to write contents to file");
+            let err = CommandsRunner::validate(&file_path).unwrap_err();
+            assert!(format!("{err:#}").contains("missing-project.json"));
+
+            // A valid config passes
+            let contents = r#"{
+                "run": { "name": "blue" },
+                "on_node_init": ["node list", { "command": "node show blue" }]
+            }"#;
+            std::fs::write(&file_path, contents).expect("Failed to write contents to file");
+            CommandsRunner::validate(&file_path).expect("Config should be valid");
+        }
+
         #[test]
         fn cleanup_export_args() {
             let dir = tempdir().expect("Failed to create temp dir");
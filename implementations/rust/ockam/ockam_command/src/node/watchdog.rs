@@ -0,0 +1,40 @@
+use clap::Args;
+use std::time::Duration;
+
+use crate::util::dog::{RestartPolicy, Watchdog};
+use crate::CommandGlobalOpts;
+
+/// Restart a node whenever its process dies
+///
+/// Spawned by `ockam node create --watchdog-interval`.
+#[derive(Clone, Debug, Args)]
+pub struct WatchdogCommand {
+    /// Name of the node.
+    node_name: String,
+
+    /// Seconds between two checks of the node process
+    #[arg(long, value_name = "SECONDS")]
+    interval: u64,
+
+    /// Which exits of the node to restart it after
+    #[arg(long, value_enum, default_value_t = RestartPolicy::Always)]
+    restart_policy: RestartPolicy,
+
+    /// Stop watching the node after restarting it that many times
+    #[arg(long, value_name = "COUNT")]
+    max_restarts: Option<u32>,
+}
+
+impl WatchdogCommand {
+    pub fn run(self, _options: CommandGlobalOpts) {
+        let watchdog = Watchdog {
+            node_name: self.node_name,
+            interval: Duration::from_secs(self.interval),
+            restart_policy: self.restart_policy,
+            max_restarts: self.max_restarts,
+        };
+        if let Err(e) = watchdog.run() {
+            e.exit();
+        }
+    }
+}
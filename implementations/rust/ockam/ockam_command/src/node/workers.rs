@@ -0,0 +1,33 @@
+use clap::Args;
+
+use ockam::Context;
+use ockam_api::nodes::models::base::WorkerList;
+
+use crate::node::HELP_DETAIL;
+use crate::util::{api, node_rpc, Rpc};
+use crate::{help, CommandGlobalOpts};
+
+/// List the workers running on a node
+#[derive(Clone, Debug, Args)]
+#[command(after_long_help = help::template(HELP_DETAIL))]
+pub struct WorkersCommand {
+    /// Name of the node.
+    #[arg(default_value = "default")]
+    node_name: String,
+}
+
+impl WorkersCommand {
+    pub fn run(self, options: CommandGlobalOpts) {
+        node_rpc(run_impl, (options, self));
+    }
+}
+
+async fn run_impl(
+    ctx: Context,
+    (opts, cmd): (CommandGlobalOpts, WorkersCommand),
+) -> crate::Result<()> {
+    let mut rpc = Rpc::background(&ctx, &opts, &cmd.node_name)?;
+    rpc.request(api::list_workers()).await?;
+    rpc.parse_and_print_response::<WorkerList>()?;
+    Ok(())
+}
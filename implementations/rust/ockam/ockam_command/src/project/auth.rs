@@ -15,7 +15,7 @@ use crate::enroll::{Auth0Provider, Auth0Service};
 use crate::node::util::{delete_embedded_node, start_embedded_node};
 use crate::project::ProjectInfo;
 use crate::util::api::{self, CloudOpts};
-use crate::util::{node_rpc, RpcBuilder};
+use crate::util::{json_file, node_rpc, RpcBuilder};
 use crate::{help, CommandGlobalOpts};
 use std::path::PathBuf;
 
@@ -49,8 +49,8 @@ async fn run_impl(
     //  - The api's okta enroll is not used, remove it
 
     // Read (okta and authority) project parameters from project.json
-    let s = tokio::fs::read_to_string(cmd.project).await?;
-    let p: ProjectInfo = serde_json::from_str(&s)?;
+    let s = tokio::fs::read_to_string(&cmd.project).await?;
+    let p: ProjectInfo = json_file::parse(&cmd.project, &s)?;
 
     // Get auth0 token
     let okta_config: OktaAuth0 = p.okta_config.context("Okta addon not configured")?.into();
@@ -111,6 +111,8 @@ async fn secure_channel(
         // Some(allowed),
         None, //Do this means all are ok?
         CredentialExchangeMode::None,
+        None,
+        false,
     ))
     .await?;
     let res = rpc.parse_response::<CreateSecureChannelResponse>()?;
@@ -15,7 +15,7 @@ use tracing::debug;
 use crate::node::util::{delete_embedded_node, start_embedded_node};
 use crate::node::NodeOpts;
 use crate::util::api::{self, CloudOpts};
-use crate::util::{node_rpc, RpcBuilder};
+use crate::util::{node_rpc, parse_route, RpcBuilder};
 use crate::{help, CommandGlobalOpts, Result};
 
 /// An authorised enroller can add members to a project.
@@ -32,7 +32,7 @@ pub struct EnrollCommand {
     #[arg(long, short)]
     member: IdentityIdentifier,
 
-    #[arg(long, short)]
+    #[arg(long, short, value_parser = parse_route)]
     to: MultiAddr,
 }
 
@@ -95,6 +95,8 @@ impl Runner {
             &addr,
             Some(allowed),
             CredentialExchangeMode::None,
+            None,
+            false,
         ))
         .await?;
         let res = rpc.parse_response::<CreateSecureChannelResponse>()?;
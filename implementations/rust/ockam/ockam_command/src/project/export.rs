@@ -0,0 +1,40 @@
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+use clap::Args;
+
+use crate::project::util::config;
+use crate::util::exitcode;
+use crate::CommandGlobalOpts;
+
+/// Export a project definition, to be imported with `project import`
+#[derive(Clone, Debug, Args)]
+pub struct ExportCommand {
+    /// Name of the project.
+    pub name: String,
+
+    /// Path of the project JSON file to write.
+    pub file: PathBuf,
+}
+
+impl ExportCommand {
+    pub fn run(self, options: CommandGlobalOpts) {
+        if let Err(e) = run_impl(options, self) {
+            e.exit();
+        }
+    }
+}
+
+fn run_impl(opts: CommandGlobalOpts, cmd: ExportCommand) -> crate::Result<()> {
+    let info = config::get_project_info(&opts.config, &cmd.name)
+        .map_err(|e| crate::Error::new(exitcode::NOINPUT, e))?;
+    let json = serde_json::to_string_pretty(&info)?;
+    std::fs::write(&cmd.file, json).map_err(|e| {
+        crate::Error::new(
+            exitcode::IOERR,
+            anyhow!("Failed to write {:?}: {}", cmd.file, e),
+        )
+    })?;
+    println!("Exported project '{}' to {}", cmd.name, cmd.file.display());
+    Ok(())
+}
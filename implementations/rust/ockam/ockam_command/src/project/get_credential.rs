@@ -8,7 +8,7 @@ use ockam_multiaddr::MultiAddr;
 
 use crate::node::NodeOpts;
 use crate::util::api::CloudOpts;
-use crate::util::{node_rpc, RpcBuilder};
+use crate::util::{node_rpc, parse_route, RpcBuilder};
 use crate::{stop_node, CommandGlobalOpts, Result};
 
 #[derive(Clone, Debug, Args)]
@@ -20,7 +20,7 @@ pub struct GetCredentialCommand {
     #[command(flatten)]
     node_opts: NodeOpts,
 
-    #[arg(long, short)]
+    #[arg(long, short, value_parser = parse_route)]
     to: MultiAddr,
 }
 
@@ -0,0 +1,55 @@
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+use clap::Args;
+
+use ockam::Context;
+
+use crate::project::util::config;
+use crate::project::ProjectInfo;
+use crate::util::{exitcode, node_rpc};
+use crate::CommandGlobalOpts;
+
+/// Import a project definition, as written by `project export`
+#[derive(Clone, Debug, Args)]
+pub struct ImportCommand {
+    /// Path of the project JSON file.
+    pub file: PathBuf,
+}
+
+impl ImportCommand {
+    pub fn run(self, options: CommandGlobalOpts) {
+        node_rpc(run_impl, (options, self));
+    }
+}
+
+async fn run_impl(
+    _ctx: Context,
+    (opts, cmd): (CommandGlobalOpts, ImportCommand),
+) -> crate::Result<()> {
+    let s = std::fs::read_to_string(&cmd.file).map_err(|e| {
+        crate::Error::new(
+            exitcode::IOERR,
+            anyhow!("Failed to read {:?}: {}", cmd.file, e),
+        )
+    })?;
+    let info: ProjectInfo = serde_json::from_str(&s).map_err(|e| {
+        crate::Error::new(
+            exitcode::DATAERR,
+            anyhow!("Invalid project file {:?}: {}", cmd.file, e),
+        )
+    })?;
+
+    // Checks the access routes and the authority identity
+    config::set_project(&opts.config, &(&info).into())
+        .await
+        .map_err(|e| {
+            crate::Error::new(
+                exitcode::DATAERR,
+                anyhow!("Invalid project '{}': {:#}", info.name, e),
+            )
+        })?;
+
+    println!("Imported project '{}'", info.name);
+    Ok(())
+}
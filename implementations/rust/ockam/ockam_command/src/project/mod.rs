@@ -5,6 +5,8 @@ mod create;
 mod delete;
 mod delete_enroller;
 mod enroll;
+mod export;
+mod import;
 mod info;
 mod list;
 mod list_enrollers;
@@ -23,6 +25,8 @@ pub use create::CreateCommand;
 pub use delete::DeleteCommand;
 pub use delete_enroller::DeleteEnrollerCommand;
 pub use enroll::EnrollCommand;
+pub use export::ExportCommand;
+pub use import::ImportCommand;
 pub use info::InfoCommand;
 pub use list::ListCommand;
 pub use list_enrollers::ListEnrollersCommand;
@@ -46,6 +50,8 @@ pub enum ProjectSubcommand {
     List(ListCommand),
     Show(ShowCommand),
     Info(InfoCommand),
+    Import(ImportCommand),
+    Export(ExportCommand),
     AddEnroller(AddEnrollerCommand),
     ListEnrollers(ListEnrollersCommand),
     DeleteEnroller(DeleteEnrollerCommand),
@@ -66,6 +72,8 @@ impl ProjectCommand {
             ProjectSubcommand::DeleteEnroller(c) => c.run(options),
             ProjectSubcommand::Enroll(c) => c.run(options),
             ProjectSubcommand::Info(c) => c.run(options),
+            ProjectSubcommand::Import(c) => c.run(options),
+            ProjectSubcommand::Export(c) => c.run(options),
             ProjectSubcommand::Addon(c) => c.run(options),
             ProjectSubcommand::Authenticate(c) => c.run(options),
         }
@@ -112,6 +112,8 @@ async fn create_secure_channel_to_project(
         project_access_route,
         Some(authorized_identifier),
         credential_exchange_mode,
+        None,
+        false,
     ))
     .await?;
     let sc = rpc.parse_response::<CreateSecureChannelResponse>()?;
@@ -227,9 +229,10 @@ pub async fn check_project_readiness<'a>(
 }
 
 pub mod config {
+    use crate::project::ProjectInfo;
     use crate::util::output::Output;
     use ockam::Context;
-    use ockam_api::cloud::project::OktaAuth0;
+    use ockam_api::cloud::project::{OktaAuth0, OktaConfig};
     use ockam_api::config::lookup::ProjectAuthority;
     use tracing::trace;
 
@@ -259,6 +262,7 @@ pub mod config {
         let okta = project.okta_config.as_ref().map(|o| OktaAuth0 {
             tenant_url: o.tenant_url.to_string(),
             client_id: o.client_id.to_string(),
+            certificate: o.certificate.to_string(),
         });
         config.set_project_alias(
             project.name.to_string(),
@@ -314,6 +318,35 @@ pub mod config {
         inner.lookup.get_project(name).map(|s| s.id.clone())
     }
 
+    /// Rebuild the definition of a project stored by [`set_project`]
+    pub fn get_project_info(config: &OckamConfig, name: &str) -> Result<ProjectInfo<'static>> {
+        let inner = config.read();
+        let project = inner
+            .lookup
+            .get_project(name)
+            .context(format!("Project '{}' does not exist", name))?;
+        let access_route = project
+            .node_route
+            .as_ref()
+            .context(format!("Project '{}' has no access route", name))?;
+        let authority = project.authority.as_ref();
+        Ok(ProjectInfo {
+            id: project.id.clone().into(),
+            name: name.to_string().into(),
+            identity: project.identity_id.clone(),
+            access_route: access_route.to_string().into(),
+            authority_access_route: authority.map(|a| a.address().to_string().into()),
+            authority_identity: authority.map(|a| hex::encode(a.identity()).into()),
+            okta_config: project.okta.as_ref().map(|o| {
+                OktaConfig::new(
+                    o.tenant_url.clone(),
+                    o.certificate.clone(),
+                    o.client_id.clone(),
+                )
+            }),
+        })
+    }
+
     pub async fn refresh_projects(
         ctx: &Context,
         opts: &CommandGlobalOpts,
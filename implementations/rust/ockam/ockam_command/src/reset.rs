@@ -4,24 +4,41 @@ use clap::Args;
 use std::io::{self, BufReader, Read, Write};
 
 /// Full Ockam Reset
+///
+/// Stops and deletes all nodes, and removes all vaults, identities and
+/// configuration stored in the local state directory.
 #[derive(Clone, Debug, Args)]
 pub struct ResetCommand {
+    /// Skip the confirmation prompt
     #[arg(display_order = 901, long, short)]
     yes: bool,
 }
 
 impl ResetCommand {
     pub fn run(self, options: CommandGlobalOpts) {
-        let _ = run_impl(options, self);
+        if let Err(e) = run_impl(options, self) {
+            e.exit();
+        }
     }
 }
 
 fn run_impl(opts: CommandGlobalOpts, cmd: ResetCommand) -> crate::Result<()> {
-    if cmd.yes || get_user_confirmation() {
-        if let Err(e) = delete_all_nodes(opts, true) {
-            eprintln!("{}", e);
-            std::process::exit(crate::util::exitcode::IOERR);
-        }
+    if !cmd.yes && !get_user_confirmation() {
+        println!("Reset cancelled");
+        return Ok(());
+    }
+    let nodes: Vec<String> = opts.config.inner().nodes.keys().cloned().collect();
+    let removed = delete_all_nodes(opts, true)
+        .map_err(|e| crate::Error::new(crate::util::exitcode::IOERR, e))?;
+    if nodes.is_empty() && removed.is_empty() {
+        println!("Nothing to remove");
+        return Ok(());
+    }
+    for node in nodes {
+        println!("Deleted node '{}'", node);
+    }
+    for dir in removed {
+        println!("Removed {}", dir.display());
     }
     Ok(())
 }
@@ -42,3 +59,31 @@ fn get_user_confirmation() -> bool {
         .map(|c| (c == 'y' || c == 'Y'))
         .unwrap_or(false)
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::util::OckamConfig;
+    use directories::ProjectDirs;
+    use std::fs;
+
+    #[test]
+    fn reset_removes_local_state() {
+        let state_dir = tempfile::tempdir().unwrap();
+        let state_path = state_dir.path().join("ockam");
+        let directories = ProjectDirs::from_path(state_path.clone()).unwrap();
+
+        let cfg = OckamConfig::load_from(directories).unwrap();
+        cfg.create_node("n1", "127.0.0.1:0".parse().unwrap(), 0)
+            .unwrap();
+        cfg.persist_config_updates().unwrap();
+        fs::write(state_path.join("default_vault.json"), "{}").unwrap();
+        assert!(state_path.join("node-n1").exists());
+
+        let removed = cfg.clone().remove().unwrap();
+        assert_eq!(removed, vec![state_path.clone()]);
+        assert!(!state_path.exists());
+
+        // A missing state directory is not an error
+        assert!(cfg.remove().unwrap().is_empty());
+    }
+}
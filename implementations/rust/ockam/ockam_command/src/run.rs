@@ -0,0 +1,51 @@
+use anyhow::Context;
+use clap::Args;
+use std::path::PathBuf;
+use tracing::error;
+
+use crate::{help, CommandGlobalOpts};
+
+const HELP_DETAIL: &str = "\
+About:
+    Run the commands listed in a JSON script, in order, to set up several nodes
+    reproducibly. `${NAME}` in a command is replaced by the `NAME` environment
+    variable, or else by the default value given in `variables`. The script
+    stops at the first command which fails.
+
+```sh
+    $ cat script.json
+    {
+      \"variables\": { \"NODE\": \"n1\" },
+      \"commands\": [
+        \"node create ${NODE}\",
+        \"node create n2\",
+        \"secure-channel create --from /node/${NODE} --to /node/n2/service/api\"
+      ]
+    }
+
+    $ ockam run script.json
+```
+";
+
+/// Run the commands of a script
+#[derive(Clone, Debug, Args)]
+#[command(arg_required_else_help = true, after_long_help = help::template(HELP_DETAIL))]
+pub struct RunCommand {
+    /// Path of the script
+    pub script: PathBuf,
+}
+
+impl RunCommand {
+    pub fn run(self, options: CommandGlobalOpts) {
+        if let Err(e) = self.run_impl(options) {
+            error!(%e);
+            e.exit();
+        }
+    }
+
+    fn run_impl(self, _opts: CommandGlobalOpts) -> crate::Result<()> {
+        crate::node::util::run::CommandsRunner::run_script(&self.script)
+            .with_context(|| format!("Failed to run script {}", self.script.display()))?;
+        Ok(())
+    }
+}
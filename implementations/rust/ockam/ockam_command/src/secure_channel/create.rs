@@ -1,16 +1,16 @@
 use crate::{
     help,
-    util::{api, exitcode, extract_address_value, node_rpc},
-    CommandGlobalOpts, OutputFormat, Result,
+    util::{api, exitcode, extract_address_value, node_rpc, parse_route},
+    CommandGlobalOpts, Error, OutputFormat, Result,
 };
 
-use anyhow::Context as _;
+use anyhow::{anyhow, Context as _};
 use atty::Stream;
 use clap::Args;
-use colorful::Colorful;
+use cli_table::{print_stdout, Cell, Style, Table};
 use serde_json::json;
 
-use crate::secure_channel::HELP_DETAIL;
+use crate::secure_channel::{TrustPolicyArg, HELP_DETAIL};
 use crate::util::api::CloudOpts;
 use crate::util::RpcBuilder;
 use ockam::{identity::IdentityIdentifier, route, Context, TcpTransport};
@@ -29,14 +29,35 @@ pub struct CreateCommand {
     #[arg(value_name = "NODE", long, display_order = 800)]
     pub from: String,
 
-    /// Route to a secure channel listener (required)
-    #[arg(value_name = "ROUTE", long, display_order = 800)]
+    /// Route to a secure channel listener (required), as a multiaddr or
+    /// starting with `tcp://<host>:<port>` or `udp://<host>:<port>`
+    #[arg(value_name = "ROUTE", long, display_order = 800, value_parser = parse_route)]
     pub to: MultiAddr,
 
     /// Identifiers authorized to be presented by the listener
     #[arg(value_name = "IDENTIFIER", long, short, display_order = 801)]
     pub authorized: Option<Vec<IdentityIdentifier>>,
 
+    /// Which listeners to trust
+    ///
+    /// `identifiers` only trusts the identifiers given with `--authorized`,
+    /// `credential` requires the listener to present a valid credential and
+    /// `everyone` skips the credential exchange. By default the channel is
+    /// restricted to `--authorized` when given, and credentials are exchanged
+    /// when the node checks them.
+    #[arg(long, value_enum, value_name = "POLICY", display_order = 801)]
+    pub trust_policy: Option<TrustPolicyArg>,
+
+    /// Label shown with the channel in `secure-channel list` and in the
+    /// node's logs, e.g. "billing". Labels need not be unique
+    #[arg(long, value_name = "LABEL", display_order = 802)]
+    pub label: Option<String>,
+
+    /// Prove the pre-shared key the node was created with, required by
+    /// the default listener of nodes created with the same key
+    #[arg(long, display_order = 802)]
+    pub pre_shared_key: bool,
+
     /// Orchestrator address to resolve projects present in the `at` argument
     #[command(flatten)]
     cloud_opts: CloudOpts,
@@ -79,6 +100,20 @@ impl CreateCommand {
         extract_address_value(&self.from).unwrap_or_else(|_| "".to_string())
     }
 
+    /// How credentials are exchanged for the `--trust-policy`
+    fn credential_exchange_mode(&self) -> Result<CredentialExchangeMode> {
+        let mode = match self.trust_policy {
+            None => CredentialExchangeMode::Mutual,
+            Some(policy) => match policy.check(self.authorized.is_some())? {
+                TrustPolicyArg::Everyone | TrustPolicyArg::Identifiers => {
+                    CredentialExchangeMode::None
+                }
+                TrustPolicyArg::Credential => CredentialExchangeMode::Mutual,
+            },
+        };
+        Ok(mode)
+    }
+
     fn print_output(
         &self,
         parsed_from: &String,
@@ -91,70 +126,66 @@ impl CreateCommand {
             Some(multiaddr) => {
                 // if stdout is not interactive/tty write the secure channel address to it
                 // in case some other program is trying to read it as piped input
-                if !atty::is(Stream::Stdout)
+                if (!atty::is(Stream::Stdout)
                 // or if the `--pipe` flag is set
-                || options.global_args.export.pipe()
+                || options.global_args.export.pipe())
+                    && options.global_args.output_format == OutputFormat::Plain
                 {
                     println!("{}", multiaddr)
                 }
 
                 // if output format is json, write json to stdout.
                 if options.global_args.output_format == OutputFormat::Json {
-                    let json = json!([{ "address": multiaddr.to_string() }]);
+                    let json = json!([{
+                        "address": multiaddr.to_string(),
+                        "from": format!("/node/{}", parsed_from),
+                        "to": self.to.to_string(),
+                        "label": self.label,
+                    }]);
                     println!("{}", json);
                 }
 
-                // if stderr is interactive/tty and we haven't been asked to be quiet
-                // and output format is plain then write a plain info to stderr.
-                if atty::is(Stream::Stderr)
+                // if stdout is interactive/tty and we haven't been asked to be quiet
+                // and output format is plain then write a table to stdout.
+                if atty::is(Stream::Stdout)
+                    && !options.global_args.export.pipe()
                     && !options.global_args.quiet
                     && options.global_args.output_format == OutputFormat::Plain
                 {
-                    if options.global_args.no_color {
-                        eprintln!("\n  Created Secure Channel:");
-                        eprintln!("  • From: /node/{}", parsed_from);
-                        eprintln!("  •   To: {} ({})", &self.to, &parsed_to);
-                        eprintln!("  •   At: {}", multiaddr);
-                    } else {
-                        eprintln!("\n  Created Secure Channel:");
-
-                        // From:
-                        eprint!("{}", "  • From: ".light_magenta());
-                        eprintln!("{}", format!("/node/{}", parsed_from).light_yellow());
-
-                        // To:
-                        eprint!("{}", "  •   To: ".light_magenta());
-                        let t = format!("{} ({})", &self.to, &parsed_to);
-                        eprintln!("{}", t.light_yellow());
-
-                        // At:
-                        eprint!("{}", "  •   At: ".light_magenta());
-                        eprintln!("{}", multiaddr.to_string().light_yellow());
+                    let table = vec![[
+                        multiaddr.to_string().cell(),
+                        format!("/node/{}", parsed_from).cell(),
+                        format!("{} ({})", &self.to, &parsed_to).cell(),
+                    ]]
+                    .table()
+                    .title([
+                        "Address".cell().bold(true),
+                        "From".cell().bold(true),
+                        "To".cell().bold(true),
+                    ]);
+                    if let Err(e) = print_stdout(table) {
+                        Error::new(exitcode::IOERR, anyhow!("Failed to print table: {}", e)).exit();
                     }
                 }
             }
             None => {
-                // if stderr is interactive/tty and we haven't been asked to be quiet
-                // and output format is plain then write a plain info to stderr.
-                if atty::is(Stream::Stderr)
-                    && !options.global_args.quiet
-                    && options.global_args.output_format == OutputFormat::Plain
-                {
-                    eprintln!(
-                        "Could not convert returned secure channel address {} into a multiaddr",
-                        route
-                    );
-                }
-
                 // return the exitcode::PROTOCOL since if things are going as expected
                 // a route in the response should be convertable to multiaddr.
-                std::process::exit(exitcode::PROTOCOL);
+                Error::new(
+                    exitcode::PROTOCOL,
+                    anyhow!(
+                        "Could not convert returned secure channel address {} into a multiaddr",
+                        route
+                    ),
+                )
+                .exit();
             }
         };
     }
 }
 
 async fn rpc(ctx: Context, (opts, cmd): (CommandGlobalOpts, CreateCommand)) -> Result<()> {
+    let credential_exchange_mode = cmd.credential_exchange_mode()?;
     let tcp = TcpTransport::create(&ctx).await?;
 
     let config = &opts.config.lookup();
@@ -167,8 +198,13 @@ async fn rpc(ctx: Context, (opts, cmd): (CommandGlobalOpts, CreateCommand)) -> R
 
     // Delegate the request to create a secure channel to the from node.
     let mut rpc = RpcBuilder::new(&ctx, &opts, from).tcp(&tcp)?.build();
-    let request =
-        api::create_secure_channel(to, authorized_identifiers, CredentialExchangeMode::Mutual);
+    let request = api::create_secure_channel(
+        to,
+        authorized_identifiers,
+        credential_exchange_mode,
+        cmd.label.clone(),
+        cmd.pre_shared_key,
+    );
 
     rpc.request(request).await?;
     let response = rpc.parse_response::<CreateSecureChannelResponse>()?;
@@ -177,3 +213,58 @@ async fn rpc(ctx: Context, (opts, cmd): (CommandGlobalOpts, CreateCommand)) -> R
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "P6474cfdbf547240b6d716bff89c976810859bc3f47be8ea620df12a392ea6cb7";
+
+    fn command(args: &[&str]) -> CreateCommand {
+        #[derive(clap::Parser)]
+        struct Cli {
+            #[command(flatten)]
+            cmd: CreateCommand,
+        }
+        let args = ["create", "--from", "n1", "--to", "/node/n2/service/api"]
+            .iter()
+            .chain(args);
+        <Cli as clap::Parser>::parse_from(args).cmd
+    }
+
+    #[test]
+    fn credential_exchange_mode_for_trust_policy() {
+        let mode = command(&[]).credential_exchange_mode().unwrap();
+        assert!(matches!(mode, CredentialExchangeMode::Mutual));
+
+        let mode = command(&["--authorized", ID])
+            .credential_exchange_mode()
+            .unwrap();
+        assert!(matches!(mode, CredentialExchangeMode::Mutual));
+
+        let mode = command(&["--trust-policy", "everyone"])
+            .credential_exchange_mode()
+            .unwrap();
+        assert!(matches!(mode, CredentialExchangeMode::None));
+
+        let mode = command(&["--trust-policy", "identifiers", "--authorized", ID])
+            .credential_exchange_mode()
+            .unwrap();
+        assert!(matches!(mode, CredentialExchangeMode::None));
+
+        let mode = command(&["--trust-policy", "credential"])
+            .credential_exchange_mode()
+            .unwrap();
+        assert!(matches!(mode, CredentialExchangeMode::Mutual));
+    }
+
+    #[test]
+    fn trust_policy_rejects_mismatched_identifiers() {
+        assert!(command(&["--trust-policy", "identifiers"])
+            .credential_exchange_mode()
+            .is_err());
+        assert!(command(&["--trust-policy", "everyone", "--authorized", ID])
+            .credential_exchange_mode()
+            .is_err());
+    }
+}
@@ -2,8 +2,9 @@ use crate::secure_channel::HELP_DETAIL;
 use crate::{
     help,
     util::{api, exitcode, extract_address_value, node_rpc, Rpc},
-    CommandGlobalOpts, OutputFormat, Result,
+    CommandGlobalOpts, Error, OutputFormat, Result,
 };
+use anyhow::anyhow;
 use std::str::FromStr;
 
 use atty::Stream;
@@ -86,38 +87,29 @@ impl DeleteCommand {
                         }
                     }
                     None => {
-                        // if stderr is interactive/tty and we haven't been asked to be quiet
-                        // and output format is plain then write a plain info to stderr.
-                        if atty::is(Stream::Stderr)
-                            && !options.global_args.quiet
-                            && options.global_args.output_format == OutputFormat::Plain
-                        {
-                            eprintln!(
-                                "Could not convert returned secure channel route {} into a multiaddr",
-                                route
-                            );
-                        }
-
                         // return the exitcode::PROTOCOL since if things are going as expected
                         // a route in the response should be convertable to multiaddr.
-                        std::process::exit(exitcode::PROTOCOL);
+                        Error::new(
+                            exitcode::PROTOCOL,
+                            anyhow!(
+                                "Could not convert returned secure channel route {} into a multiaddr",
+                                route
+                            ),
+                        )
+                        .exit();
                     }
                 }
             }
             None => {
-                // if stderr is interactive/tty and we haven't been asked to be quiet
-                // and output format is plain then write a plain info to stderr.
-                if atty::is(Stream::Stderr)
-                    && !options.global_args.quiet
-                    && options.global_args.output_format == OutputFormat::Plain
-                {
-                    eprintln!(
+                Error::new(
+                    exitcode::NOINPUT,
+                    anyhow!(
                         "Could not find secure channel with address {} at node {}",
-                        address, &self.at
-                    );
-                }
-
-                println!("channel with address {} not found", address)
+                        address,
+                        &self.at
+                    ),
+                )
+                .exit();
             }
         }
     }
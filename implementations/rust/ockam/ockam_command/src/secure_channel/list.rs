@@ -1,3 +1,4 @@
+use anyhow::anyhow;
 use atty::Stream;
 use clap::Args;
 use colorful::Colorful;
@@ -14,7 +15,7 @@ use crate::util::RpcBuilder;
 use crate::{
     exitcode, help,
     util::{api, node_rpc},
-    CommandGlobalOpts, OutputFormat,
+    CommandGlobalOpts, Error, OutputFormat,
 };
 
 /// List Secure Channels
@@ -55,6 +56,8 @@ impl ListCommand {
                 channel_multiaddr.to_string()
             };
 
+            let negotiation = negotiation(&show_response);
+
             let to = {
                 let show_route = show_response
                     .route
@@ -84,18 +87,30 @@ impl ListCommand {
 
             // if output format is json, write json to stdout.
             if options.global_args.output_format == OutputFormat::Json {
-                let json = json!([{ "address": at }]);
+                let json = json!([{
+                    "address": at,
+                    "cipher_suite": show_response.cipher_suite,
+                    "pre_shared_key": show_response.pre_shared_key,
+                    "handshake_padding": show_response.handshake_padding,
+                    "sequence_numbers": show_response.sequence_numbers,
+                    "byte_budget": show_response.byte_budget,
+                    "label": show_response.label,
+                }]);
                 println!("{}", json);
             }
 
             // if stderr is interactive/tty and we haven't been asked to be quiet
             // and output format is plain then write a plain info to stderr.
             if has_plain_stderr(options) {
-                println!("\n    Secure Channel:");
+                match &show_response.label {
+                    Some(label) => println!("\n    Secure Channel: {}", label),
+                    None => println!("\n    Secure Channel:"),
+                }
                 if options.global_args.no_color {
                     eprintln!("      • From: /node/{}", from);
                     eprintln!("      •   To: {}", to);
                     eprintln!("      •   At: {}", at);
+                    eprintln!("      • With: {}", negotiation);
                 } else {
                     // From:
                     eprint!("{}", "      • From: ".light_magenta());
@@ -108,6 +123,10 @@ impl ListCommand {
                     // At:
                     eprint!("{}", "      •   At: ".light_magenta());
                     eprintln!("{}", at.light_yellow());
+
+                    // With:
+                    eprint!("{}", "      • With: ".light_magenta());
+                    eprintln!("{}", negotiation.light_yellow());
                 }
             }
         }
@@ -115,6 +134,35 @@ impl ListCommand {
     }
 }
 
+/// Describe the parameters a channel was negotiated with
+fn negotiation(show_response: &ShowSecureChannelResponse) -> String {
+    let cipher_suite = match &show_response.cipher_suite {
+        Some(cipher_suite) => cipher_suite.to_string(),
+        None => return "unknown".to_string(),
+    };
+    let mut parameters = vec![cipher_suite];
+    if show_response.pre_shared_key == Some(true) {
+        parameters.push("pre-shared key".to_string());
+    }
+    if let Some(padding) = show_response.handshake_padding {
+        parameters.push(format!("handshake padded to {} bytes", padding));
+    }
+    if show_response.sequence_numbers == Some(true) {
+        parameters.push("sequence numbers".to_string());
+    }
+    if let Some(byte_budget) = show_response.byte_budget {
+        parameters.push(format!("{} bytes budget", byte_budget));
+    }
+    // Nodes which don't report it don't compress
+    match show_response.compression.as_ref().map(|c| c.to_string()) {
+        Some(compression) if compression != "none" => {
+            parameters.push(format!("{} compression", compression))
+        }
+        _ => parameters.push("no compression".to_string()),
+    }
+    parameters.join(", ")
+}
+
 #[inline]
 fn has_plain_stderr(options: &CommandGlobalOpts) -> bool {
     atty::is(Stream::Stderr)
@@ -153,13 +201,7 @@ async fn rpc(
     let responses = results?;
 
     if let Err(e) = command.print_output(&options, channel_identifiers, responses) {
-        if atty::is(Stream::Stderr)
-            && !options.global_args.quiet
-            && options.global_args.output_format == OutputFormat::Plain
-        {
-            eprintln!("{}", e);
-        }
-        std::process::exit(exitcode::PROTOCOL)
+        Error::new(exitcode::PROTOCOL, anyhow!(e)).exit();
     }
 
     Ok(())
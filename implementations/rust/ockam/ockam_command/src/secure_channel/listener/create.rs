@@ -8,9 +8,9 @@ use ockam_api::nodes::NODEMANAGER_ADDR;
 use ockam_core::api::{Request, Status};
 use ockam_core::{Address, Route};
 
-use crate::secure_channel::HELP_DETAIL;
-use crate::util::{api, exitcode, extract_address_value, node_rpc, Rpc};
-use crate::{help, CommandGlobalOpts};
+use crate::secure_channel::{TrustPolicyArg, HELP_DETAIL};
+use crate::util::{api, exitcode, extract_address_value, node_rpc, send_and_receive, Rpc};
+use crate::{help, CommandGlobalOpts, Error};
 
 /// Create Secure Channel Listeners
 #[derive(Clone, Debug, Args)]
@@ -25,6 +25,17 @@ pub struct CreateCommand {
     /// Authorized Identifiers of secure channel initiators
     #[arg(short, long, value_name = "IDENTIFIERS")]
     authorized_identifiers: Option<Vec<IdentityIdentifier>>,
+
+    /// Which secure channel initiators to trust
+    ///
+    /// Defaults to `identifiers` when authorized identifiers are given
+    /// and to `everyone` otherwise.
+    #[arg(long, value_enum, value_name = "POLICY")]
+    trust_policy: Option<TrustPolicyArg>,
+
+    /// Require the pre-shared key the node was created with
+    #[arg(long)]
+    pre_shared_key: bool,
 }
 
 #[derive(Clone, Debug, Args)]
@@ -38,6 +49,15 @@ impl CreateCommand {
     pub fn run(self, opts: CommandGlobalOpts) {
         node_rpc(rpc, (opts, self));
     }
+
+    /// Check that the trust policy and authorized identifiers agree
+    fn trust_policy(&self) -> crate::Result<TrustPolicyArg> {
+        match (self.trust_policy, &self.authorized_identifiers) {
+            (None, Some(_)) => Ok(TrustPolicyArg::Identifiers),
+            (None, None) => Ok(TrustPolicyArg::Everyone),
+            (Some(policy), ids) => policy.check(ids.is_some()),
+        }
+    }
 }
 
 async fn rpc(ctx: Context, (opts, cmd): (CommandGlobalOpts, CreateCommand)) -> crate::Result<()> {
@@ -48,11 +68,18 @@ async fn run_impl(
     ctx: &Context,
     (opts, cmd): (CommandGlobalOpts, CreateCommand),
 ) -> crate::Result<()> {
+    let check_credential = cmd.trust_policy()? == TrustPolicyArg::Credential;
     let node = extract_address_value(&cmd.node_opts.at)?;
     let mut rpc = Rpc::background(ctx, &opts, &node)?;
-    let req = Request::post("/node/secure_channel_listener").body(
-        CreateSecureChannelListenerRequest::new(&cmd.address, cmd.authorized_identifiers),
+    let mut body = CreateSecureChannelListenerRequest::new(
+        &cmd.address,
+        cmd.authorized_identifiers,
+        check_credential,
     );
+    if cmd.pre_shared_key {
+        body = body.with_pre_shared_key();
+    }
+    let req = Request::post("/node/secure_channel_listener").body(body);
     rpc.request(req).await?;
     match rpc.is_ok() {
         Ok(_) => {
@@ -68,16 +95,18 @@ async fn run_impl(
 
 pub async fn create_listener(
     ctx: &Context,
+    opts: &CommandGlobalOpts,
     addr: Address,
     authorized_identifiers: Option<Vec<IdentityIdentifier>>,
     mut base_route: Route,
 ) -> anyhow::Result<()> {
-    let resp: Vec<u8> = ctx
-        .send_and_receive(
-            base_route.modify().append(NODEMANAGER_ADDR),
-            api::create_secure_channel_listener(&addr, authorized_identifiers)?,
-        )
-        .await?;
+    let resp: Vec<u8> = send_and_receive(
+        ctx,
+        opts,
+        base_route.modify().append(NODEMANAGER_ADDR).into(),
+        api::create_secure_channel_listener(&addr, authorized_identifiers, false)?,
+    )
+    .await?;
 
     let response = api::parse_create_secure_channel_listener_response(&resp)?;
 
@@ -87,8 +116,70 @@ pub async fn create_listener(
             Ok(())
         }
         _ => {
-            eprintln!("An error occurred while creating secure channel listener",);
-            std::process::exit(exitcode::CANTCREAT)
+            Error::new(
+                exitcode::CANTCREAT,
+                anyhow!("An error occurred while creating secure channel listener"),
+            )
+            .exit();
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "P6474cfdbf547240b6d716bff89c976810859bc3f47be8ea620df12a392ea6cb7";
+
+    fn command(args: &[&str]) -> CreateCommand {
+        #[derive(clap::Parser)]
+        struct Cli {
+            #[command(flatten)]
+            cmd: CreateCommand,
+        }
+        let args = ["create", "listener"].iter().chain(args);
+        <Cli as clap::Parser>::parse_from(args).cmd
+    }
+
+    #[test]
+    fn trust_policy_defaults() {
+        let policy = command(&[]).trust_policy().unwrap();
+        assert_eq!(policy, TrustPolicyArg::Everyone);
+
+        let policy = command(&["--authorized-identifiers", ID])
+            .trust_policy()
+            .unwrap();
+        assert_eq!(policy, TrustPolicyArg::Identifiers);
+    }
+
+    #[test]
+    fn trust_policy_variants() {
+        let policy = command(&["--trust-policy", "everyone"])
+            .trust_policy()
+            .unwrap();
+        assert_eq!(policy, TrustPolicyArg::Everyone);
+
+        let policy = command(&["--trust-policy", "identifiers", "-a", ID])
+            .trust_policy()
+            .unwrap();
+        assert_eq!(policy, TrustPolicyArg::Identifiers);
+
+        let policy = command(&["--trust-policy", "credential"])
+            .trust_policy()
+            .unwrap();
+        assert_eq!(policy, TrustPolicyArg::Credential);
+    }
+
+    #[test]
+    fn trust_policy_rejects_mismatched_identifiers() {
+        assert!(command(&["--trust-policy", "identifiers"])
+            .trust_policy()
+            .is_err());
+        assert!(command(&["--trust-policy", "everyone", "-a", ID])
+            .trust_policy()
+            .is_err());
+        assert!(command(&["--trust-policy", "credential", "-a", ID])
+            .trust_policy()
+            .is_err());
+    }
+}
@@ -1,12 +1,16 @@
+use anyhow::anyhow;
 use clap::Args;
+use cli_table::{print_stdout, Cell, Style, Table};
+use serde_json::json;
 
 use ockam::Context;
+use ockam_api::nodes::models::secure_channel::SecureChannelListenerList;
 
 use crate::node::NodeOpts;
 use crate::secure_channel::HELP_DETAIL;
 use crate::util::api;
-use crate::util::{node_rpc, Rpc};
-use crate::{help, CommandGlobalOpts};
+use crate::util::{exitcode, node_rpc, Rpc};
+use crate::{help, CommandGlobalOpts, OutputFormat};
 
 /// List Secure Channel Listeners
 #[derive(Args, Clone, Debug)]
@@ -33,16 +37,39 @@ async fn run_impl(
     cmd: ListCommand,
 ) -> crate::Result<()> {
     let mut rpc = Rpc::background(ctx, &opts, &cmd.node_opts.api_node)?;
-    rpc.request(api::list_secure_channel_listener()).await?;
-    let res = rpc.parse_response::<Vec<String>>()?;
-
-    println!(
-        "Secure channel listeners for node `{}`:",
-        &cmd.node_opts.api_node
-    );
-    for addr in res {
-        println!("  {}", addr);
+    rpc.request(api::list_secure_channel_listener_status())
+        .await?;
+    let SecureChannelListenerList { list, .. } =
+        rpc.parse_response::<SecureChannelListenerList>()?;
+
+    if opts.global_args.output_format == OutputFormat::Json {
+        let json: Vec<_> = list
+            .iter()
+            .map(|listener| {
+                json!({
+                    "address": listener.addr,
+                    "trust_policy": listener.trust_policy,
+                })
+            })
+            .collect();
+        println!("{}", serde_json::Value::Array(json));
+        return Ok(());
     }
 
+    let table = list
+        .iter()
+        .map(|listener| vec![listener.addr.cell(), listener.trust_policy.cell()])
+        .table()
+        .title(vec![
+            "Address".cell().bold(true),
+            "Trust Policy".cell().bold(true),
+        ]);
+    print_stdout(table).map_err(|e| {
+        crate::Error::new(
+            exitcode::IOERR,
+            anyhow!("failed to print secure channel listeners: {}", e),
+        )
+    })?;
+
     Ok(())
 }
@@ -1,8 +1,10 @@
 pub mod create;
 pub mod list;
+pub mod update;
 
 pub(crate) use create::CreateCommand;
 pub(crate) use list::ListCommand;
+pub(crate) use update::UpdateCommand;
 
 use crate::secure_channel::HELP_DETAIL;
 use crate::{help, CommandGlobalOpts};
@@ -26,6 +28,8 @@ pub enum SecureChannelListenerSubcommand {
     Create(CreateCommand),
     #[command(display_order = 800)]
     List(ListCommand),
+    #[command(display_order = 800)]
+    Update(UpdateCommand),
 }
 
 impl SecureChannelListenerCommand {
@@ -33,6 +37,7 @@ impl SecureChannelListenerCommand {
         match self.subcommand {
             SecureChannelListenerSubcommand::Create(c) => c.run(options),
             SecureChannelListenerSubcommand::List(c) => c.run(options),
+            SecureChannelListenerSubcommand::Update(c) => c.run(options),
         }
     }
 }
@@ -0,0 +1,66 @@
+use anyhow::anyhow;
+use clap::Args;
+
+use ockam::identity::IdentityIdentifier;
+use ockam::Context;
+use ockam_api::nodes::models::secure_channel::UpdateSecureChannelListenerRequest;
+use ockam_core::api::Request;
+use ockam_core::Address;
+
+use super::create::SecureChannelListenerNodeOpts;
+use crate::secure_channel::HELP_DETAIL;
+use crate::util::{exitcode, extract_address_value, node_rpc, Rpc};
+use crate::{help, CommandGlobalOpts};
+
+/// Change the Identifiers trusted by a Secure Channel Listener
+///
+/// Channels which the listener already established are kept.
+#[derive(Clone, Debug, Args)]
+#[command(arg_required_else_help = true, after_long_help = help::template(HELP_DETAIL))]
+pub struct UpdateCommand {
+    #[command(flatten)]
+    node_opts: SecureChannelListenerNodeOpts,
+
+    /// Address of the listener
+    address: Address,
+
+    /// Identifiers of secure channel initiators to trust from now on
+    #[arg(long, value_name = "IDENTIFIERS")]
+    add: Vec<IdentityIdentifier>,
+
+    /// Identifiers of secure channel initiators to stop trusting
+    #[arg(long, value_name = "IDENTIFIERS")]
+    remove: Vec<IdentityIdentifier>,
+}
+
+impl UpdateCommand {
+    pub fn run(self, opts: CommandGlobalOpts) {
+        node_rpc(rpc, (opts, self));
+    }
+}
+
+async fn rpc(ctx: Context, (opts, cmd): (CommandGlobalOpts, UpdateCommand)) -> crate::Result<()> {
+    if cmd.add.is_empty() && cmd.remove.is_empty() {
+        return Err(crate::Error::new(
+            exitcode::USAGE,
+            anyhow!("Give the identifiers to trust with --add, or to stop trusting with --remove"),
+        ));
+    }
+
+    let node = extract_address_value(&cmd.node_opts.at)?;
+    let mut rpc = Rpc::background(&ctx, &opts, &node)?;
+    let req = Request::put("/node/secure_channel_listener").body(
+        UpdateSecureChannelListenerRequest::new(&cmd.address, cmd.add, cmd.remove),
+    );
+    rpc.request(req).await?;
+    match rpc.is_ok() {
+        Ok(_) => {
+            println!("/service/{} updated", cmd.address.address());
+            Ok(())
+        }
+        Err(e) => Err(crate::Error::new(
+            exitcode::UNAVAILABLE,
+            anyhow!("An error occurred while updating secure channel listener").context(e),
+        )),
+    }
+}
@@ -10,8 +10,10 @@ pub use delete::DeleteCommand;
 pub use list::ListCommand;
 pub use show::ShowCommand;
 
+use crate::util::exitcode;
 use crate::{help, CommandGlobalOpts};
-use clap::{Args, Subcommand};
+use anyhow::anyhow;
+use clap::{Args, Subcommand, ValueEnum};
 
 const HELP_DETAIL: &str = "\
 About:
@@ -134,8 +136,55 @@ About:
     $ ockam secure-channel create --from /node/n1 --to /node/n2/service/test
     /service/09738b73c54b81d48531f659aaa22533
 ```
+
+
+    Trust Policies
+    ------
+
+    Both ends of a channel can restrict who they trust with `--trust-policy`:
+    `everyone`, `identifiers` (the identifiers given with `--authorized`, or
+    `--authorized-identifiers` for listeners) or `credential`.
+
+```sh
+    # Only accept n2 if it is the expected identity, and print the channel
+    # address as json to use it in a script
+    $ ockam secure-channel create --from /node/n1 --to /node/n2/service/api \\
+        --trust-policy identifiers --authorized P6474cfdbf547240b6d716bff89c97681... \\
+        --output json
+    [{\"address\":\"/service/09738b73c54b81d48531f659aaa22533\",\"from\":\"/node/n1\",\"to\":\"/node/n2/service/api\"}]
+```
 ";
 
+/// Which end of a secure channel to trust
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum TrustPolicyArg {
+    /// Trust every identity
+    Everyone,
+    /// Trust the identities given as authorized identifiers
+    Identifiers,
+    /// Trust identities which presented a valid credential
+    Credential,
+}
+
+impl TrustPolicyArg {
+    /// Check that an explicit trust policy agrees with the authorized identifiers
+    pub(crate) fn check(self, has_identifiers: bool) -> crate::Result<Self> {
+        match (self, has_identifiers) {
+            (TrustPolicyArg::Identifiers, false) => Err(crate::error::Error::new(
+                exitcode::USAGE,
+                anyhow!("The `identifiers` trust policy requires authorized identifiers"),
+            )),
+            (TrustPolicyArg::Everyone | TrustPolicyArg::Credential, true) => {
+                Err(crate::error::Error::new(
+                    exitcode::USAGE,
+                    anyhow!("Authorized identifiers require the `identifiers` trust policy"),
+                ))
+            }
+            (policy, _) => Ok(policy),
+        }
+    }
+}
+
 /// Manage Secure Channels.
 #[derive(Clone, Debug, Args)]
 #[command(
@@ -1,8 +1,11 @@
+use crate::util::json_file;
 use anyhow::{anyhow, Context, Result};
 use ockam::identity::IdentityIdentifier;
 use ockam_api::DefaultAddress;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VaultConfig {
@@ -70,14 +73,217 @@ pub struct Config {
     pub(crate) startup_services: Option<ServiceConfigs>,
 }
 
+impl ServiceConfigs {
+    /// Services given inline to `node create --service`
+    pub(crate) fn from_specs(specs: &[ServiceSpec]) -> Result<Self> {
+        let mut configs = ServiceConfigs {
+            vault: None,
+            identity: None,
+            secure_channel_listener: None,
+            verifier: None,
+            authenticator: None,
+        };
+        for spec in specs {
+            let duplicate = match spec.kind {
+                ServiceKind::Vault => configs
+                    .vault
+                    .replace(VaultConfig {
+                        address: spec.address_or(vault_default_addr),
+                        disabled: false,
+                    })
+                    .is_some(),
+                ServiceKind::Identity => configs
+                    .identity
+                    .replace(IdentityConfig {
+                        address: spec.address_or(identity_default_addr),
+                        disabled: false,
+                    })
+                    .is_some(),
+                ServiceKind::SecureChannelListener => configs
+                    .secure_channel_listener
+                    .replace(SecureChannelListenerConfig {
+                        address: spec.address_or(sec_listener_default_addr),
+                        authorized_identifiers: None,
+                        disabled: false,
+                    })
+                    .is_some(),
+                ServiceKind::Verifier => configs
+                    .verifier
+                    .replace(VerifierConfig {
+                        address: spec.address_or(verifier_default_addr),
+                        disabled: false,
+                    })
+                    .is_some(),
+            };
+            if duplicate {
+                return Err(anyhow!("service '{}' is given more than once", spec.kind));
+            }
+        }
+        Ok(configs)
+    }
+}
+
+/// Kinds of services which can be started with `node create --service`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceKind {
+    Vault,
+    Identity,
+    SecureChannelListener,
+    Verifier,
+}
+
+impl ServiceKind {
+    const ALL: [ServiceKind; 4] = [
+        ServiceKind::Vault,
+        ServiceKind::Identity,
+        ServiceKind::SecureChannelListener,
+        ServiceKind::Verifier,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            ServiceKind::Vault => "vault",
+            ServiceKind::Identity => "identity",
+            ServiceKind::SecureChannelListener => "secure-channel-listener",
+            ServiceKind::Verifier => "verifier",
+        }
+    }
+}
+
+impl fmt::Display for ServiceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A service given as `<name>` or `<name>=<address>` to `node create --service`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceSpec {
+    kind: ServiceKind,
+    address: Option<String>,
+}
+
+impl ServiceSpec {
+    fn address_or(&self, default: fn() -> String) -> String {
+        self.address.clone().unwrap_or_else(default)
+    }
+}
+
+impl FromStr for ServiceSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (name, address) = match s.split_once('=') {
+            Some((name, address)) => (name, Some(address)),
+            None => (s, None),
+        };
+        let kind = match ServiceKind::ALL.into_iter().find(|k| k.name() == name) {
+            Some(kind) => kind,
+            None if name == "authenticator" => {
+                return Err(
+                    "the authenticator service can only be started from a launch config"
+                        .to_string(),
+                )
+            }
+            None => {
+                let names: Vec<_> = ServiceKind::ALL.iter().map(|k| k.name()).collect();
+                return Err(format!(
+                    "unknown service '{name}', expected one of: {}",
+                    names.join(", ")
+                ));
+            }
+        };
+        if let Some(address) = address {
+            if address.is_empty() || address.contains(|c: char| c.is_whitespace() || c == '/') {
+                return Err(format!("invalid address '{address}' for service '{kind}'"));
+            }
+        }
+        Ok(ServiceSpec {
+            kind,
+            address: address.map(str::to_string),
+        })
+    }
+}
+
+impl fmt::Display for ServiceSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.address {
+            Some(address) => write!(f, "{}={}", self.kind, address),
+            None => write!(f, "{}", self.kind),
+        }
+    }
+}
+
+impl Serialize for ServiceSpec {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> std::result::Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ServiceSpec {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> std::result::Result<Self, D::Error> {
+        let s = String::deserialize(d)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 impl Config {
+    /// Read the config at `path`, replacing `${NAME}` with the `NAME`
+    /// environment variable
     pub(crate) fn read<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let s = std::fs::read_to_string(path.as_ref())
-            .with_context(|| anyhow!("failed to read {:?}", path.as_ref()))?;
-        serde_json::from_str(&s).with_context(|| anyhow!("invalid config {:?}", path.as_ref()))
+        Self::read_with_vars(path, &|name| std::env::var(name).ok())
+    }
+
+    /// Read the config at `path`, replacing `${NAME}` with the value
+    /// `lookup` gives for `NAME`
+    pub(crate) fn read_with_vars<P: AsRef<Path>>(
+        path: P,
+        lookup: &dyn Fn(&str) -> Option<String>,
+    ) -> Result<Self> {
+        let path = path.as_ref();
+        let mut json: serde_json::Value = json_file::read(path)?;
+        expand_vars(&mut json, lookup).with_context(|| anyhow!("invalid config {:?}", path))?;
+        json_file::from_value(path, json)
     }
 }
 
+/// Replace `${NAME}` in string values with the value `lookup` gives for `NAME`
+pub(crate) fn expand_vars(
+    json: &mut serde_json::Value,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<()> {
+    match json {
+        serde_json::Value::String(s) => {
+            let mut expanded = String::new();
+            let mut rest = s.as_str();
+            while let Some(start) = rest.find("${") {
+                let end = rest[start..]
+                    .find('}')
+                    .ok_or_else(|| anyhow!("unterminated variable in '{s}'"))?;
+                let name = &rest[start + 2..start + end];
+                let val = lookup(name).ok_or_else(|| anyhow!("variable '{name}' is not set"))?;
+                expanded.push_str(&rest[..start]);
+                expanded.push_str(&val);
+                rest = &rest[start + end + 1..];
+            }
+            expanded.push_str(rest);
+            *s = expanded;
+        }
+        serde_json::Value::Array(values) => {
+            for value in values {
+                expand_vars(value, lookup)?;
+            }
+        }
+        serde_json::Value::Object(values) => {
+            for value in values.values_mut() {
+                expand_vars(value, lookup)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
 fn vault_default_addr() -> String {
     DefaultAddress::VAULT_SERVICE.to_string()
 }
@@ -97,3 +303,96 @@ fn verifier_default_addr() -> String {
 fn authenticator_default_addr() -> String {
     DefaultAddress::AUTHENTICATOR.to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::env_file;
+    use std::io::Write;
+
+    #[test]
+    fn service_config_reads_env_file_variables() {
+        let mut env = tempfile::NamedTempFile::new().unwrap();
+        writeln!(env, "OCKAM_TEST_VERIFIER_ADDR=env_verifier").unwrap();
+        let vars = env_file::read(env.path()).unwrap();
+        let lookup = |name: &str| {
+            vars.iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, val)| val.clone())
+        };
+
+        let mut config = tempfile::NamedTempFile::new().unwrap();
+        let json =
+            r#"{"startup_services": {"verifier": {"address": "${OCKAM_TEST_VERIFIER_ADDR}"}}}"#;
+        write!(config, "{json}").unwrap();
+        let config = Config::read_with_vars(config.path(), &lookup).unwrap();
+        let verifier = config.startup_services.unwrap().verifier.unwrap();
+        assert_eq!(verifier.address, "env_verifier");
+    }
+
+    #[test]
+    fn service_config_rejects_unset_variables() {
+        let mut config = tempfile::NamedTempFile::new().unwrap();
+        let json = r#"{"startup_services": {"vault": {"address": "${OCKAM_TEST_UNSET_VAR}"}}}"#;
+        write!(config, "{json}").unwrap();
+        let err = Config::read_with_vars(config.path(), &|_| None).unwrap_err();
+        assert!(format!("{err:#}").contains("OCKAM_TEST_UNSET_VAR"));
+    }
+
+    #[test]
+    fn service_config_errors_name_the_offending_field() {
+        let mut config = tempfile::NamedTempFile::new().unwrap();
+        let json = r#"{"startup_services": {"verifier": {"address": 42}}}"#;
+        write!(config, "{json}").unwrap();
+        let err = format!("{:#}", Config::read(config.path()).unwrap_err());
+        assert!(err.contains("startup_services.verifier.address"), "{err}");
+        assert!(err.contains("invalid type"), "{err}");
+
+        let mut config = tempfile::NamedTempFile::new().unwrap();
+        let json = r#"{"startup_services": {"vault": {"disabled": "yes"}}}"#;
+        write!(config, "{json}").unwrap();
+        let err = format!("{:#}", Config::read(config.path()).unwrap_err());
+        assert!(err.contains("startup_services.vault.disabled"), "{err}");
+    }
+
+    #[test]
+    fn service_config_ignores_unknown_fields() {
+        // Fields added by later versions don't break older ones
+        let mut config = tempfile::NamedTempFile::new().unwrap();
+        let json = r#"{"startup_services": {"vault": {"address": "v", "new_field": 1}}}"#;
+        write!(config, "{json}").unwrap();
+        let config = Config::read(config.path()).unwrap();
+        assert_eq!(config.startup_services.unwrap().vault.unwrap().address, "v");
+    }
+
+    #[test]
+    fn inline_service_specs() {
+        let specs: Vec<ServiceSpec> = ["identity", "secure-channel-listener=inline_listener"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+        assert_eq!(
+            specs[1].to_string(),
+            "secure-channel-listener=inline_listener"
+        );
+
+        let configs = ServiceConfigs::from_specs(&specs).unwrap();
+        assert_eq!(configs.identity.unwrap().address, identity_default_addr());
+        assert_eq!(
+            configs.secure_channel_listener.unwrap().address,
+            "inline_listener"
+        );
+        assert!(configs.vault.is_none());
+
+        assert!("identities".parse::<ServiceSpec>().is_err());
+        assert!("authenticator".parse::<ServiceSpec>().is_err());
+        assert!("verifier=".parse::<ServiceSpec>().is_err());
+        assert!("verifier=a/b".parse::<ServiceSpec>().is_err());
+
+        let duplicates: Vec<ServiceSpec> = ["vault", "vault=other_vault"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+        assert!(ServiceConfigs::from_specs(&duplicates).is_err());
+    }
+}
@@ -0,0 +1,149 @@
+use crate::util::output::Output;
+use crate::util::{api, node_rpc, RpcBuilder};
+use crate::{CommandGlobalOpts, OutputFormat};
+use anyhow::Context as _;
+use clap::Args;
+use cli_table::{Cell, Style, Table};
+use ockam::{Context, TcpTransport};
+use ockam_api::nodes::models::identity::ShortIdentityResponse;
+use serde::Serialize;
+use std::time::Duration;
+use tracing::debug;
+
+/// How long a node has to answer before it is reported as down
+const NODE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Show the state of every local node
+///
+/// Each node is queried through its node manager API. Nodes which don't
+/// answer are reported as down.
+#[derive(Clone, Debug, Args)]
+pub struct StatusCommand {}
+
+impl StatusCommand {
+    pub fn run(self, options: CommandGlobalOpts) {
+        node_rpc(run_impl, options);
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+enum NodeState {
+    Up,
+    Down,
+    NoApi,
+}
+
+#[derive(Debug, Serialize)]
+struct NodeStatus {
+    name: String,
+    state: NodeState,
+    port: u16,
+    identity: Option<String>,
+    secure_channels: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+struct FleetStatus {
+    nodes: Vec<NodeStatus>,
+}
+
+async fn run_impl(ctx: Context, opts: CommandGlobalOpts) -> crate::Result<()> {
+    let tcp = TcpTransport::create(&ctx).await?;
+    let nodes: Vec<(String, u16, bool)> = opts
+        .config
+        .inner()
+        .nodes
+        .iter()
+        .map(|(name, cfg)| (name.clone(), cfg.port(), cfg.no_api()))
+        .collect();
+
+    let mut fleet = FleetStatus { nodes: Vec::new() };
+    for (name, port, no_api) in nodes {
+        let mut status = NodeStatus {
+            name,
+            state: NodeState::NoApi,
+            port,
+            identity: None,
+            secure_channels: None,
+        };
+        if !no_api {
+            status.state = match query_node(&ctx, &opts, &tcp, &mut status).await {
+                Ok(()) => NodeState::Up,
+                Err(e) => {
+                    debug!(node = %status.name, %e, "node is unreachable");
+                    NodeState::Down
+                }
+            };
+        }
+        fleet.nodes.push(status);
+    }
+
+    let o = match opts.global_args.output_format {
+        OutputFormat::Plain => fleet.output()?,
+        OutputFormat::Json => {
+            serde_json::to_string_pretty(&fleet).context("Failed to serialize node statuses")?
+        }
+    };
+    println!("{}", o);
+    Ok(())
+}
+
+/// Fill in the identity and secure channels of a node which answers
+async fn query_node(
+    ctx: &Context,
+    opts: &CommandGlobalOpts,
+    tcp: &TcpTransport,
+    status: &mut NodeStatus,
+) -> anyhow::Result<()> {
+    let mut rpc = RpcBuilder::new(ctx, opts, &status.name).tcp(tcp)?.build();
+    rpc.request_with_timeout(api::short_identity(), NODE_TIMEOUT)
+        .await?;
+    let identity = rpc.parse_response::<ShortIdentityResponse>()?;
+    status.identity = Some(identity.identity_id.to_string());
+
+    let mut rpc = RpcBuilder::new(ctx, opts, &status.name).tcp(tcp)?.build();
+    rpc.request_with_timeout(api::list_secure_channels(), NODE_TIMEOUT)
+        .await?;
+    let channels = rpc.parse_response::<Vec<String>>()?;
+    status.secure_channels = Some(channels.len());
+    Ok(())
+}
+
+impl Output for FleetStatus {
+    fn output(&self) -> anyhow::Result<String> {
+        if self.nodes.is_empty() {
+            return Ok("No nodes registered on this system".to_string());
+        }
+        let mut rows = vec![];
+        for node in &self.nodes {
+            rows.push([
+                node.name.as_str().cell(),
+                match node.state {
+                    NodeState::Up => "UP",
+                    NodeState::Down => "DOWN (unreachable)",
+                    NodeState::NoApi => "NO API",
+                }
+                .cell(),
+                node.port.cell(),
+                node.identity.as_deref().unwrap_or("-").cell(),
+                node.secure_channels
+                    .map(|n| n.to_string())
+                    .unwrap_or_else(|| "-".to_string())
+                    .cell(),
+            ]);
+        }
+        let table = rows
+            .table()
+            .title([
+                "Node".cell().bold(true),
+                "Status".cell().bold(true),
+                "Port".cell().bold(true),
+                "Identity".cell().bold(true),
+                "Secure Channels".cell().bold(true),
+            ])
+            .display()?
+            .to_string();
+        Ok(table)
+    }
+}
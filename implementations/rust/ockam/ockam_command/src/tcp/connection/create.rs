@@ -1,10 +1,11 @@
 use crate::{
-    util::{api, connect_to, exitcode, extract_address_value},
-    CommandGlobalOpts, OutputFormat,
+    util::{api, connect_to, exitcode, extract_address_value, send_and_receive},
+    CommandGlobalOpts, Error, OutputFormat,
 };
+use anyhow::anyhow;
 use clap::Args;
 use colorful::Colorful;
-use ockam::{Context, Route, TCP};
+use ockam::{route, Context, Route, TCP};
 use ockam_api::{
     nodes::{models::transport::TransportStatus, NODEMANAGER_ADDR},
     route_to_multiaddr,
@@ -34,6 +35,12 @@ pub struct CreateCommand {
     /// The address to connect to (required)
     #[arg(id = "to", short, long, value_name = "ADDRESS")]
     pub address: String,
+
+    /// Connect to the Unix domain socket at the address, which the node
+    /// must have been created with `--uds-listener-path` to do. Routes
+    /// through the connection start at the `/service` it prints
+    #[arg(long)]
+    pub uds: bool,
 }
 
 impl CreateCommand {
@@ -51,69 +58,91 @@ pub async fn create_connection(
     (cmd, opts): (CreateCommand, CommandGlobalOpts),
     mut base_route: Route,
 ) -> anyhow::Result<()> {
-    let resp: Vec<u8> = match ctx
-        .send_and_receive(
-            base_route.modify().append(NODEMANAGER_ADDR),
-            api::create_tcp_connection(&cmd)?,
-        )
-        .await
+    let resp: Vec<u8> = match send_and_receive(
+        &ctx,
+        &opts,
+        base_route.modify().append(NODEMANAGER_ADDR).into(),
+        api::create_tcp_connection(&cmd)?,
+    )
+    .await
     {
         Ok(sr_msg) => sr_msg,
-        Err(e) => {
-            eprintln!("Wasn't able to send or receive `Message`: {}", e);
-            std::process::exit(exitcode::IOERR);
-        }
+        Err(e) => e.exit(),
     };
 
-    let (response, TransportStatus { payload, .. }) = api::parse_transport_status(&resp)?;
+    let (
+        response,
+        TransportStatus {
+            payload,
+            tid,
+            worker_addr,
+            ..
+        },
+    ) = api::parse_transport_status(&resp)?;
 
     match response.status() {
         Some(Status::Ok) => {
-            let r: Route = base_route
-                .modify()
-                .pop_back()
-                .append_t(TCP, payload.to_string())
-                .into();
+            let r: Route = match worker_addr {
+                // Unix domain socket paths have no multiaddr form
+                Some(worker_addr) => route![worker_addr.to_string()],
+                None => base_route
+                    .modify()
+                    .pop_back()
+                    .append_t(TCP, payload.to_string())
+                    .into(),
+            };
             let multiaddr = match route_to_multiaddr(&r) {
                 Some(addr) => addr,
                 None => {
-                    eprintln!("Couldn't convert given address into `MultiAddr`");
-                    std::process::exit(exitcode::SOFTWARE);
+                    Error::new(
+                        exitcode::SOFTWARE,
+                        anyhow!("Couldn't convert given address into `MultiAddr`"),
+                    )
+                    .exit();
                 }
             };
 
             let from = cmd.node_opts.from;
-            let to = cmd.address.parse::<SocketAddrV4>().unwrap();
+            // Hostnames have no ip4 multiaddr form
+            let to = match cmd.address.parse::<SocketAddrV4>() {
+                Ok(to) => format!("{} (/ip4/{}/tcp/{})", to, to.ip(), to.port()),
+                Err(_) => cmd.address.clone(),
+            };
 
             // if output format is json, write json to stdout.
             match opts.global_args.output_format {
                 OutputFormat::Plain => {
+                    // The transport ID is what `tcp-connection delete` expects
+                    println!("{}", tid);
                     if opts.global_args.no_color {
                         eprintln!("\n  Created TCP Connection:");
+                        eprintln!("  •   ID: {}", tid);
                         eprintln!("  • From: /node/{}", from);
-                        eprintln!("  •   To: {} (/ip4/{}/tcp/{})", to, to.ip(), to.port());
+                        eprintln!("  •   To: {}", to);
                     } else {
                         eprintln!("\n  Created TCP Connection:");
+                        eprintln!("{}", format!("  •   ID: {}", tid).light_magenta());
                         eprintln!("{}", format!("  • From: /node/{}", from).light_magenta());
-                        eprintln!(
-                            "{}",
-                            format!("  •   To: {} (/ip4/{}/tcp/{})", to, to.ip(), to.port())
-                                .light_magenta()
-                        );
+                        eprintln!("{}", format!("  •   To: {}", to).light_magenta());
                     }
                 }
                 OutputFormat::Json => {
-                    let json = json!([{"route": multiaddr.to_string() }]);
-                    eprintln!("{}", json);
+                    let json = json!([{"tid": tid, "route": multiaddr.to_string() }]);
+                    println!("{}", json);
                 }
             }
         }
         _ => {
-            eprintln!(
-                "An error occurred while creating the tcp connection: {}",
-                payload
-            );
-            std::process::exit(exitcode::CANTCREAT);
+            Error::new(
+                exitcode::UNAVAILABLE,
+                anyhow!(
+                    "Failed to connect to {} from /node/{}: {}",
+                    cmd.address,
+                    cmd.node_opts.from,
+                    payload
+                ),
+            )
+            .exit();
         }
     }
     Ok(())
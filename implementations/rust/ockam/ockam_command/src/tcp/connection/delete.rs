@@ -1,3 +1,4 @@
+use anyhow::anyhow;
 use clap::Args;
 use ockam::{Context, Route};
 use ockam_api::nodes::NODEMANAGER_ADDR;
@@ -6,8 +7,8 @@ use ockam_core::api::{Response, Status};
 use crate::util::extract_address_value;
 use crate::{
     node::NodeOpts,
-    util::{api, connect_to, exitcode},
-    CommandGlobalOpts,
+    util::{api, connect_to, exitcode, send_and_receive},
+    CommandGlobalOpts, Error,
 };
 
 #[derive(Clone, Debug, Args)]
@@ -29,39 +30,38 @@ impl DeleteCommand {
         let node =
             extract_address_value(&self.node_opts.api_node).unwrap_or_else(|_| "".to_string());
         let port = cfg.get_node_port(&node).unwrap();
-        connect_to(port, self, delete_connection);
+        connect_to(port, (self, options.clone()), delete_connection);
     }
 }
 
 pub async fn delete_connection(
     ctx: Context,
-    cmd: DeleteCommand,
+    (cmd, opts): (DeleteCommand, CommandGlobalOpts),
     mut base_route: Route,
 ) -> anyhow::Result<()> {
-    let resp: Vec<u8> = match ctx
-        .send_and_receive(
-            base_route.modify().append(NODEMANAGER_ADDR),
-            api::delete_tcp_connection(&cmd)?,
-        )
-        .await
+    let resp: Vec<u8> = match send_and_receive(
+        &ctx,
+        &opts,
+        base_route.modify().append(NODEMANAGER_ADDR).into(),
+        api::delete_tcp_connection(&cmd)?,
+    )
+    .await
     {
         Ok(sr_msg) => sr_msg,
-        Err(e) => {
-            eprintln!("Wasn't able to send or receive `Message`: {}", e);
-            std::process::exit(exitcode::IOERR);
-        }
+        Err(e) => e.exit(),
     };
     let r: Response = api::parse_response(&resp)?;
 
     match r.status() {
         Some(Status::Ok) => println!("Tcp connection `{}` successfully delete", cmd.id),
-        _ => {
-            eprintln!("Failed to delete tcp connection");
-            if !cmd.force {
-                eprintln!("You may have to provide --force to delete the API transport");
-                std::process::exit(exitcode::UNAVAILABLE);
-            }
+        _ if !cmd.force => {
+            Error::new(
+                exitcode::UNAVAILABLE,
+                anyhow!("Failed to delete tcp connection\nYou may have to provide --force to delete the API transport"),
+            )
+            .exit();
         }
+        _ => eprintln!("Failed to delete tcp connection"),
     }
     Ok(())
 }
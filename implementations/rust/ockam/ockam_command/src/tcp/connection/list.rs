@@ -1,13 +1,15 @@
 use crate::node::NodeOpts;
-use crate::util::{api, connect_to, exitcode, extract_address_value};
-use crate::CommandGlobalOpts;
+use crate::util::{api, connect_to, exitcode, extract_address_value, send_and_receive};
+use crate::{CommandGlobalOpts, Error, OutputFormat};
+use anyhow::anyhow;
 use clap::Args;
 use cli_table::{print_stdout, Cell, Style, Table};
 use ockam::{Context, Route};
 use ockam_api::nodes::{
-    models::transport::{TransportList, TransportStatus},
+    models::transport::{TransportChannel, TransportList, TransportStatus},
     NODEMANAGER_ADDR,
 };
+use serde_json::json;
 
 #[derive(Args, Clone, Debug)]
 pub struct ListCommand {
@@ -22,27 +24,35 @@ impl ListCommand {
             extract_address_value(&self.node_opts.api_node).unwrap_or_else(|_| "".to_string());
         let port = cfg.get_node_port(&node).unwrap();
 
-        connect_to(port, (), list_connections);
+        connect_to(port, options, list_connections);
     }
 }
 
-pub async fn list_connections(ctx: Context, _: (), mut base_route: Route) -> anyhow::Result<()> {
-    let resp: Vec<u8> = match ctx
-        .send_and_receive(
-            base_route.modify().append(NODEMANAGER_ADDR),
-            api::list_tcp_connections()?,
-        )
-        .await
+pub async fn list_connections(
+    ctx: Context,
+    options: CommandGlobalOpts,
+    mut base_route: Route,
+) -> anyhow::Result<()> {
+    let resp: Vec<u8> = match send_and_receive(
+        &ctx,
+        &options,
+        base_route.modify().append(NODEMANAGER_ADDR).into(),
+        api::list_tcp_connections()?,
+    )
+    .await
     {
         Ok(sr_msg) => sr_msg,
-        Err(e) => {
-            eprintln!("Wasn't able to send or receive `Message`: {}", e);
-            std::process::exit(exitcode::IOERR);
-        }
+        Err(e) => e.exit(),
     };
 
     let TransportList { list, .. } = api::parse_tcp_list(&resp)?;
 
+    if options.global_args.output_format == OutputFormat::Json {
+        let json: Vec<_> = list.iter().map(status_json).collect();
+        println!("{}", serde_json::Value::Array(json));
+        return Ok(());
+    }
+
     let table = list
         .iter()
         .fold(
@@ -53,9 +63,22 @@ pub async fn list_connections(ctx: Context, _: (), mut base_route: Route) -> any
                  tm,
                  payload,
                  tid,
+                 bytes_in,
+                 bytes_out,
+                 connected_at,
+                 secure_channels,
                  ..
              }| {
-                let row = vec![tid.cell(), tt.cell(), tm.cell(), payload.cell()];
+                let row = vec![
+                    tid.cell(),
+                    tt.cell(),
+                    tm.cell(),
+                    payload.cell(),
+                    optional_cell(bytes_in),
+                    optional_cell(bytes_out),
+                    optional_cell(connected_at),
+                    channels_cell(secure_channels),
+                ];
                 acc.push(row);
                 acc
             },
@@ -66,12 +89,67 @@ pub async fn list_connections(ctx: Context, _: (), mut base_route: Route) -> any
             "Transport Type".cell().bold(true),
             "Mode".cell().bold(true),
             "Address bind".cell().bold(true),
+            "Bytes in".cell().bold(true),
+            "Bytes out".cell().bold(true),
+            "Connected at".cell().bold(true),
+            "Secure channels".cell().bold(true),
         ]);
 
     if let Err(e) = print_stdout(table) {
-        eprintln!("failed to print node status: {}", e);
-        std::process::exit(exitcode::IOERR);
+        Error::new(
+            exitcode::IOERR,
+            anyhow!("failed to print node status: {}", e),
+        )
+        .exit();
     }
 
     Ok(())
 }
+
+/// JSON form of a connection, as printed with `--output json`
+pub(super) fn status_json(status: &TransportStatus) -> serde_json::Value {
+    json!({
+        "tid": status.tid,
+        "type": status.tt.to_string(),
+        "mode": status.tm.to_string(),
+        "address": status.payload,
+        "bytes_in": status.bytes_in,
+        "bytes_out": status.bytes_out,
+        "connected_at": status.connected_at,
+        "secure_channels": status.secure_channels.as_ref().map(|channels| {
+            channels
+                .iter()
+                .map(|channel| {
+                    json!({
+                        "address": channel.address,
+                        "authorized_identifiers": channel.authorized_identifiers,
+                    })
+                })
+                .collect::<Vec<_>>()
+        }),
+    })
+}
+
+/// One line per channel: its address, and the identifiers it trusts if any
+pub(super) fn channels_cell(channels: &Option<Vec<TransportChannel>>) -> cli_table::CellStruct {
+    let channels = match channels {
+        Some(channels) if !channels.is_empty() => channels,
+        _ => return "-".cell(),
+    };
+    channels
+        .iter()
+        .map(|channel| match &channel.authorized_identifiers {
+            Some(ids) => format!("{} ({})", channel.address, ids.join(", ")),
+            None => channel.address.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        .cell()
+}
+
+pub(super) fn optional_cell(value: &Option<u64>) -> cli_table::CellStruct {
+    match value {
+        Some(v) => v.cell(),
+        None => "-".cell(),
+    }
+}
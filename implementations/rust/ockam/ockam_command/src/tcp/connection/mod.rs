@@ -1,10 +1,12 @@
 mod create;
 mod delete;
 mod list;
+mod show;
 
 pub(crate) use create::CreateCommand;
 pub(crate) use delete::DeleteCommand;
 pub(crate) use list::ListCommand;
+pub(crate) use show::ShowCommand;
 
 use crate::CommandGlobalOpts;
 use clap::{Args, Subcommand};
@@ -21,6 +23,7 @@ pub enum TcpConnectionSubCommand {
     Create(CreateCommand),
     Delete(DeleteCommand),
     List(ListCommand),
+    Show(ShowCommand),
 }
 
 impl TcpConnectionCommand {
@@ -29,6 +32,7 @@ impl TcpConnectionCommand {
             TcpConnectionSubCommand::Create(c) => c.run(options),
             TcpConnectionSubCommand::Delete(c) => c.run(options),
             TcpConnectionSubCommand::List(c) => c.run(options),
+            TcpConnectionSubCommand::Show(c) => c.run(options),
         }
     }
 }
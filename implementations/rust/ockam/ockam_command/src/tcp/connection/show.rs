@@ -0,0 +1,121 @@
+use crate::node::NodeOpts;
+use crate::util::{api, connect_to, exitcode, extract_address_value, send_and_receive};
+use crate::{CommandGlobalOpts, Error, OutputFormat};
+use anyhow::anyhow;
+use clap::Args;
+use cli_table::{print_stdout, Cell, Style, Table};
+use minicbor::Decoder;
+use ockam::{Context, Route};
+use ockam_api::nodes::{models::transport::TransportStatus, NODEMANAGER_ADDR};
+use ockam_core::api::{Response, Status};
+
+use super::list::{channels_cell, optional_cell, status_json};
+
+/// Show the details of a TCP connection
+#[derive(Clone, Debug, Args)]
+pub struct ShowCommand {
+    #[command(flatten)]
+    node_opts: NodeOpts,
+
+    /// Tcp Connection ID
+    pub id: String,
+}
+
+impl ShowCommand {
+    pub fn run(self, options: CommandGlobalOpts) {
+        let cfg = &options.config;
+        let node =
+            extract_address_value(&self.node_opts.api_node).unwrap_or_else(|_| "".to_string());
+        let port = cfg.get_node_port(&node).unwrap();
+
+        connect_to(port, (self, options.clone()), show_connection);
+    }
+}
+
+pub async fn show_connection(
+    ctx: Context,
+    (cmd, opts): (ShowCommand, CommandGlobalOpts),
+    mut base_route: Route,
+) -> anyhow::Result<()> {
+    let resp: Vec<u8> = match send_and_receive(
+        &ctx,
+        &opts,
+        base_route.modify().append(NODEMANAGER_ADDR).into(),
+        api::show_tcp_connection(&cmd.id)?,
+    )
+    .await
+    {
+        Ok(sr_msg) => sr_msg,
+        Err(e) => e.exit(),
+    };
+
+    let mut dec = Decoder::new(&resp);
+    let response = dec.decode::<Response>()?;
+    match response.status() {
+        Some(Status::Ok) => {}
+        // Unknown connections are answered without a body
+        Some(Status::NotFound) => {
+            Error::new(
+                exitcode::NOINPUT,
+                anyhow!("TCP connection `{}` not found", cmd.id),
+            )
+            .exit();
+        }
+        status => {
+            Error::new(
+                exitcode::UNAVAILABLE,
+                anyhow!(
+                    "Failed to show TCP connection `{}`, status: {:?}",
+                    cmd.id,
+                    status
+                ),
+            )
+            .exit();
+        }
+    }
+    let status = dec.decode::<TransportStatus>()?;
+
+    if opts.global_args.output_format == OutputFormat::Json {
+        println!("{}", status_json(&status));
+        return Ok(());
+    }
+
+    let TransportStatus {
+        tt,
+        tm,
+        payload,
+        tid,
+        bytes_in,
+        bytes_out,
+        connected_at,
+        secure_channels,
+        ..
+    } = &status;
+    let table = vec![
+        vec!["Transport ID".cell().bold(true), tid.cell()],
+        vec!["Transport Type".cell().bold(true), tt.cell()],
+        vec!["Mode".cell().bold(true), tm.cell()],
+        vec!["Peer address".cell().bold(true), payload.cell()],
+        vec!["Bytes in".cell().bold(true), optional_cell(bytes_in)],
+        vec!["Bytes out".cell().bold(true), optional_cell(bytes_out)],
+        vec![
+            "Connected at".cell().bold(true),
+            optional_cell(connected_at),
+        ],
+        vec![
+            "Secure channels".cell().bold(true),
+            channels_cell(secure_channels),
+        ],
+    ]
+    .table();
+
+    if let Err(e) = print_stdout(table) {
+        Error::new(
+            exitcode::IOERR,
+            anyhow!("failed to print tcp connection: {}", e),
+        )
+        .exit();
+    }
+
+    Ok(())
+}
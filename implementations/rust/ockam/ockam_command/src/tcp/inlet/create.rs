@@ -1,6 +1,8 @@
-use crate::util::{bind_to_port_check, exitcode, extract_address_value, node_rpc, RpcBuilder};
+use crate::util::{
+    bind_to_port_check, exitcode, extract_address_value, node_rpc, parse_route, RpcBuilder,
+};
 use crate::Result;
-use crate::{help, CommandGlobalOpts};
+use crate::{help, CommandGlobalOpts, Error};
 use anyhow::anyhow;
 use clap::Args;
 use ockam::identity::IdentityIdentifier;
@@ -47,7 +49,7 @@ pub struct CreateCommand {
     from: SocketAddr,
 
     /// Route to a tcp outlet.
-    #[arg(long, display_order = 900, id = "ROUTE")]
+    #[arg(long, display_order = 900, id = "ROUTE", value_parser = parse_route)]
     to: MultiAddr,
 
     /// Authorized identity for secure channel connection (optional)
@@ -83,8 +85,11 @@ impl CreateCommand {
 
         // Check if the port is used by some other services or process
         if !bind_to_port_check(&self.from) {
-            eprintln!("Another process is listening on the provided port!");
-            std::process::exit(exitcode::IOERR);
+            Error::new(
+                exitcode::IOERR,
+                anyhow!("Another process is listening on the provided port!"),
+            )
+            .exit();
         }
 
         node_rpc(rpc, (options, self));
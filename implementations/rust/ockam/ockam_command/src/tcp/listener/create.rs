@@ -1,8 +1,9 @@
-use crate::util::{bind_to_port_check, extract_address_value};
+use crate::util::{bind_to_port_check, extract_address_value, send_and_receive};
 use crate::{
     util::{api, connect_to, exitcode},
-    CommandGlobalOpts,
+    CommandGlobalOpts, Error,
 };
+use anyhow::anyhow;
 use clap::Args;
 use ockam::{Context, Route, TCP};
 use ockam_api::{
@@ -37,38 +38,38 @@ impl CreateCommand {
         let input_addr = match std::net::SocketAddr::from_str(&self.address) {
             Ok(value) => value,
             _ => {
-                eprintln!("Invalid Input Address");
-                std::process::exit(exitcode::IOERR);
+                Error::new(exitcode::IOERR, anyhow!("Invalid Input Address")).exit();
             }
         };
 
         // Check if the port is used by some other services or process
         if !bind_to_port_check(&input_addr) {
-            eprintln!("Another process is listening on the provided port!");
-            std::process::exit(exitcode::IOERR);
+            Error::new(
+                exitcode::IOERR,
+                anyhow!("Another process is listening on the provided port!"),
+            )
+            .exit();
         }
 
-        connect_to(port, self, create_listener);
+        connect_to(port, (self, options.clone()), create_listener);
     }
 }
 
 pub async fn create_listener(
     ctx: Context,
-    cmd: CreateCommand,
+    (cmd, opts): (CreateCommand, CommandGlobalOpts),
     mut base_route: Route,
 ) -> anyhow::Result<()> {
-    let resp: Vec<u8> = match ctx
-        .send_and_receive(
-            base_route.modify().append(NODEMANAGER_ADDR),
-            api::create_tcp_listener(&cmd)?,
-        )
-        .await
+    let resp: Vec<u8> = match send_and_receive(
+        &ctx,
+        &opts,
+        base_route.modify().append(NODEMANAGER_ADDR).into(),
+        api::create_tcp_listener(&cmd)?,
+    )
+    .await
     {
         Ok(sr_msg) => sr_msg,
-        Err(e) => {
-            eprintln!("Wasn't able to send or receive `Message`: {}", e);
-            std::process::exit(exitcode::IOERR);
-        }
+        Err(e) => e.exit(),
     };
 
     let (response, TransportStatus { payload, .. }) = api::parse_transport_status(&resp)?;
@@ -83,8 +84,11 @@ pub async fn create_listener(
             let multiaddr = match route_to_multiaddr(&r) {
                 Some(addr) => addr,
                 None => {
-                    eprintln!("Couldn't convert given address into `MultiAddr`");
-                    std::process::exit(exitcode::SOFTWARE);
+                    Error::new(
+                        exitcode::SOFTWARE,
+                        anyhow!("Couldn't convert given address into `MultiAddr`"),
+                    )
+                    .exit();
                 }
             };
 
@@ -94,11 +98,14 @@ pub async fn create_listener(
             )
         }
         _ => {
-            eprintln!(
-                "An error occurred while creating the tcp listener: {}",
-                payload
-            );
-            std::process::exit(exitcode::CANTCREAT);
+            Error::new(
+                exitcode::CANTCREAT,
+                anyhow!(
+                    "An error occurred while creating the tcp listener: {}",
+                    payload
+                ),
+            )
+            .exit();
         }
     }
     Ok(())
@@ -35,6 +35,21 @@ pub(crate) fn query_status() -> Result<Vec<u8>> {
     Ok(buf)
 }
 
+/// Construct a request to list the workers of a node
+pub(crate) fn list_workers() -> RequestBuilder<'static, ()> {
+    Request::get("/node/workers")
+}
+
+/// Construct a request to query the metrics of a node
+pub(crate) fn query_metrics() -> RequestBuilder<'static, ()> {
+    Request::get("/node/metrics")
+}
+
+/// Construct a request to reset the metrics counters of a node
+pub(crate) fn reset_metrics() -> RequestBuilder<'static, ()> {
+    Request::post("/node/metrics/actions/reset")
+}
+
 /// Construct a request to query node tcp connections
 pub(crate) fn list_tcp_connections() -> Result<Vec<u8>> {
     let mut buf = vec![];
@@ -43,6 +58,14 @@ pub(crate) fn list_tcp_connections() -> Result<Vec<u8>> {
     Ok(buf)
 }
 
+/// Construct a request to show the details of a node tcp connection
+pub(crate) fn show_tcp_connection(tid: &str) -> Result<Vec<u8>> {
+    let mut buf = vec![];
+    let builder = Request::get(format!("/node/tcp/connection/{tid}"));
+    builder.encode(&mut buf)?;
+    Ok(buf)
+}
+
 /// Construct a request to query node tcp listeners
 pub(crate) fn list_tcp_listeners() -> RequestBuilder<'static, ()> {
     Request::get("/node/tcp/listener")
@@ -57,8 +80,12 @@ pub(crate) fn create_tcp_connection(
         cmd.address.clone(),
     );
 
-    let payload =
-        models::transport::CreateTransport::new(models::transport::TransportType::Tcp, tt, addr);
+    let transport_type = if cmd.uds {
+        models::transport::TransportType::Uds
+    } else {
+        models::transport::TransportType::Tcp
+    };
+    let payload = models::transport::CreateTransport::new(transport_type, tt, addr);
     let mut buf = vec![];
     Request::post("/node/tcp/connection")
         .body(payload)
@@ -124,12 +151,23 @@ pub(crate) fn create_secure_channel(
     addr: &MultiAddr,
     authorized_identifiers: Option<Vec<IdentityIdentifier>>,
     credential_exchange_mode: CredentialExchangeMode,
+    label: Option<String>,
+    pre_shared_key: bool,
 ) -> RequestBuilder<'static, models::secure_channel::CreateSecureChannelRequest<'static>> {
     let payload = models::secure_channel::CreateSecureChannelRequest::new(
         addr,
         authorized_identifiers,
         credential_exchange_mode,
     );
+    let payload = match label {
+        Some(label) => payload.with_label(label),
+        None => payload,
+    };
+    let payload = if pre_shared_key {
+        payload.with_pre_shared_key()
+    } else {
+        payload
+    };
     Request::post("/node/secure_channel").body(payload)
 }
 
@@ -151,10 +189,12 @@ pub(crate) fn show_secure_channel(
 pub(crate) fn create_secure_channel_listener(
     addr: &Address,
     authorized_identifiers: Option<Vec<IdentityIdentifier>>,
+    check_credential: bool,
 ) -> Result<Vec<u8>> {
     let payload = models::secure_channel::CreateSecureChannelListenerRequest::new(
         addr,
         authorized_identifiers,
+        check_credential,
     );
 
     let mut buf = vec![];
@@ -164,9 +204,9 @@ pub(crate) fn create_secure_channel_listener(
     Ok(buf)
 }
 
-/// Construct a request to list Secure Channel Listeners
-pub(crate) fn list_secure_channel_listener() -> RequestBuilder<'static, ()> {
-    Request::get("/node/secure_channel_listener")
+/// Construct a request to list Secure Channel Listeners with their trust policy
+pub(crate) fn list_secure_channel_listener_status() -> RequestBuilder<'static, ()> {
+    Request::get("/node/secure_channel_listener/status")
 }
 
 /// Construct a request to start a Vault Service
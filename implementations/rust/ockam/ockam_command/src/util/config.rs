@@ -3,6 +3,7 @@
 use std::{fs::create_dir_all, net::SocketAddr, ops::Deref, path::PathBuf, sync::RwLockReadGuard};
 
 use anyhow::{Context, Result};
+use directories::ProjectDirs;
 use slug::slugify;
 use tracing::{error, trace};
 
@@ -45,7 +46,12 @@ pub enum ConfigError {
 
 impl OckamConfig {
     pub fn load() -> Result<OckamConfig> {
-        let directories = cli::OckamConfig::directories();
+        Self::load_from(cli::OckamConfig::directories())
+    }
+
+    /// Load the config stored in `directories` rather than in the
+    /// default location
+    pub fn load_from(directories: ProjectDirs) -> Result<OckamConfig> {
         let config_dir = directories.config_dir();
         let inner = Config::<cli::OckamConfig>::load(config_dir, "config")?;
         inner.write().directories = Some(directories);
@@ -62,35 +68,24 @@ impl OckamConfig {
         NodeConfig::new(&node_dir)
     }
 
-    pub fn remove(self) -> Result<()> {
+    /// Delete the config and nodes directories, returning the ones that existed
+    pub fn remove(self) -> Result<Vec<PathBuf>> {
         let inner = self.inner.write();
-        // Try to delete the config directory. If the directory is not found,
-        // we continue. Otherwise, we return the error.
-        let config_dir = inner
-            .directories
-            .as_ref()
-            .context("configuration is in an invalid state")?
-            .config_dir();
-        if let Err(e) = std::fs::remove_dir_all(config_dir) {
-            match e.kind() {
-                std::io::ErrorKind::NotFound => {}
-                _ => return Err(e.into()),
-            }
-        };
-        // Try to delete the nodes directory. If the directory is not found,
-        // we continue. Otherwise, we return the error.
-        let nodes_dir = inner
+        let directories = inner
             .directories
             .as_ref()
-            .context("configuration is in an invalid state")?
-            .data_local_dir();
-        if let Err(e) = std::fs::remove_dir_all(nodes_dir) {
-            match e.kind() {
-                std::io::ErrorKind::NotFound => {}
-                _ => return Err(e.into()),
+            .context("configuration is in an invalid state")?;
+        let mut removed = vec![];
+        for dir in [directories.config_dir(), directories.data_local_dir()] {
+            // If the directory is not found (e.g. it was deleted manually or
+            // both directories are the same), we continue. Otherwise, we return the error.
+            match std::fs::remove_dir_all(dir) {
+                Ok(()) => removed.push(dir.to_path_buf()),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
             }
-        };
-        Ok(())
+        }
+        Ok(removed)
     }
 
     pub fn get_default_vault_path(&self) -> Option<PathBuf> {
@@ -101,6 +96,11 @@ impl OckamConfig {
         self.inner.read().default_identity.clone()
     }
 
+    /// Get the change history of an identity imported with `identity import`
+    pub fn get_imported_identity(&self, name: &str) -> Option<Vec<u8>> {
+        self.inner.read().imported_identities.get(name).cloned()
+    }
+
     /// Get the node state directory
     pub fn get_node_dir(&self, name: &str) -> Result<PathBuf> {
         let inner = self.inner.read();
@@ -208,6 +208,14 @@ impl OckamConfig {
         self.inner.write().default_identity = default_identity;
     }
 
+    /// Add the change history of an identity, which can only be verified
+    pub fn set_imported_identity(&self, name: &str, change_history: Vec<u8>) {
+        self.inner
+            .write()
+            .imported_identities
+            .insert(name.to_string(), change_history);
+    }
+
     /// Add a new node to the configuration for future lookup
     pub fn create_node(&self, name: &str, bind: SocketAddr, verbose: u8) -> Result<()> {
         let mut inner = self.inner.write();
@@ -276,6 +284,30 @@ impl OckamConfig {
         Ok(())
     }
 
+    /// Record whether an existing node runs without the node manager API
+    pub fn set_node_no_api(&self, name: &str, no_api: bool) -> Result<()> {
+        let mut inner = self.inner.write();
+
+        if !inner.nodes.contains_key(name) {
+            return Err(ConfigError::NotFound(name.to_string()).into());
+        }
+
+        inner.nodes.get_mut(name).unwrap().no_api = no_api;
+        Ok(())
+    }
+
+    /// Record the externally reachable address of an existing node
+    pub fn set_node_advertised_address(&self, name: &str, advertised: SocketAddr) -> Result<()> {
+        let mut inner = self.inner.write();
+
+        if !inner.nodes.contains_key(name) {
+            return Err(ConfigError::NotFound(name.to_string()).into());
+        }
+
+        inner.nodes.get_mut(name).unwrap().advertised_address = Some(advertised);
+        Ok(())
+    }
+
     pub fn set_node_alias(&self, alias: String, addr: InternetAddress) {
         let mut inner = self.inner.write();
         inner.lookup.set_node(&alias, addr);
@@ -352,3 +384,27 @@ impl AuthoritiesConfig {
         self.inner.read().clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn advertised_address_is_persisted() {
+        let state_dir = tempfile::tempdir().unwrap();
+        let state_path = state_dir.path().join("ockam");
+        let advertised: SocketAddr = "192.0.2.10:4000".parse().unwrap();
+
+        let cfg =
+            OckamConfig::load_from(ProjectDirs::from_path(state_path.clone()).unwrap()).unwrap();
+        cfg.create_node("n1", "0.0.0.0:4000".parse().unwrap(), 0)
+            .unwrap();
+        cfg.set_node_advertised_address("n1", advertised).unwrap();
+        cfg.persist_config_updates().unwrap();
+
+        // A restarted node reads the config again
+        let cfg = OckamConfig::load_from(ProjectDirs::from_path(state_path).unwrap()).unwrap();
+        let node = cfg.get_node("n1").unwrap();
+        assert_eq!(node.advertised_address(), Some(advertised));
+    }
+}
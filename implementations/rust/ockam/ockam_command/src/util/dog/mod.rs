@@ -3,42 +3,123 @@
 mod service;
 pub use service::Watchdog;
 
-use super::OckamConfig;
-use clap::Args;
-use std::path::PathBuf;
-use tokio::sync::mpsc::{channel, Receiver, Sender};
+use crate::util::OckamConfig;
+use anyhow::Context;
+use clap::ValueEnum;
+use std::env::current_exe;
+use std::fs::OpenOptions;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+use tracing::debug;
 
-pub fn socket_path(opts: CommandGlobalOpts, node_name: &str) -> PathBuf {
-    let cfg = &opts.config;
-    let node_dir = cfg
-        .get_node_dir(node_name)
-        .expect("this shouldn't happen, is there a race condition? (there always is)");
-    node_dir.join("_watchdog.socket")
+/// Which exits of a node its watchdog restarts it after
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum RestartPolicy {
+    /// Restart the node whenever its process is gone
+    #[default]
+    Always,
+    /// Restart the node unless it stopped on its own, e.g. when idle
+    OnFailure,
+    /// Only log that the node is gone
+    Never,
 }
 
-#[derive(Clone, Debug, Args)]
-pub struct WatchdogCommand {
-    node_name: String,
+/// Path of the file holding the PID of a node's watchdog
+pub fn pid_path(node_dir: &Path) -> PathBuf {
+    node_dir.join("watchdog.pid")
 }
 
-impl WatchdogCommand {
-    pub fn run(opts: CommandGlobalOpts, cmd: WatchdogCommand) {
-        let cfg = &opts.config;
-        let socket_path = socket_path(cfg, &cmd.node_opts.api_node);
+/// Path of the file a node creates when it stops on its own
+fn clean_exit_path(node_dir: &Path) -> PathBuf {
+    node_dir.join("clean_exit")
+}
 
-        Watchdog { socket_path }.run()
+/// Record that the node stopped on its own rather than crashed
+pub fn record_clean_exit(node_dir: &Path) {
+    let _ = std::fs::write(clean_exit_path(node_dir), "");
+}
 
-        // let stream = listener.incoming().next();
-    }
+/// Forget the previous exit of a node which is starting
+pub fn forget_exit(node_dir: &Path) {
+    let _ = std::fs::remove_file(clean_exit_path(node_dir));
 }
 
-// enum Instruction {
-//     /// Restart this node
-//     Restart,
-//     /// Stop the node and prevent it from restarting
-//     StopAndHalt,
-// }
+/// Whether the last process of a node stopped on its own
+pub fn exited_cleanly(node_dir: &Path) -> bool {
+    clean_exit_path(node_dir).exists()
+}
+
+/// Spawn a watchdog process checking every `interval` that the node is
+/// still running, and restarting it otherwise as `restart_policy` allows,
+/// at most `max_restarts` times
+///
+/// The watchdog logs its restarts to `watchdog.log` in the node directory.
+pub fn spawn(
+    cfg: &OckamConfig,
+    node_name: &str,
+    interval: Duration,
+    restart_policy: RestartPolicy,
+    max_restarts: Option<u32>,
+) -> crate::Result<()> {
+    let ockam_exe = current_exe().unwrap_or_else(|_| "ockam".into());
+    let node_dir = cfg.get_node_dir(node_name)?;
+    let (_, elog) = cfg.node_log_paths(node_name).unwrap();
+    let stderr_log_file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(elog)
+        .context("failed to open stderr log path")?;
 
-// struct NodeSide(Receiver<Instruction>);
+    let log_path = node_dir.join("watchdog.log");
+    let mut args = vec![
+        "-vv".to_string(),
+        "--no-color".to_string(),
+        "--log-file".to_string(),
+        log_path
+            .to_str()
+            .unwrap_or_else(|| panic!("unsupported path {log_path:?}"))
+            .to_string(),
+        "node".to_string(),
+        "watchdog".to_string(),
+        "--interval".to_string(),
+        interval.as_secs().to_string(),
+        "--restart-policy".to_string(),
+        restart_policy
+            .to_possible_value()
+            .expect("no skipped variants")
+            .get_name()
+            .to_string(),
+    ];
+    if let Some(max_restarts) = max_restarts {
+        args.push("--max-restarts".to_string());
+        args.push(max_restarts.to_string());
+    }
+    args.push(node_name.to_string());
 
-// struct UserSide(Sender<Instruction>);
+    let child = Command::new(ockam_exe)
+        .args(args)
+        .stdout(stderr_log_file.try_clone()?)
+        .stderr(stderr_log_file)
+        .spawn()?;
+    debug!(%node_name, pid = child.id(), "Spawned node watchdog");
+
+    Ok(())
+}
+
+/// Stop the watchdog of a node, so that it doesn't restart it
+///
+/// Nodes without a watchdog are left alone.
+pub fn stop(cfg: &OckamConfig, node_name: &str) {
+    let path = match cfg.get_node_dir_raw(node_name) {
+        Ok(node_dir) => pid_path(&node_dir),
+        Err(_) => return,
+    };
+    if let Some(pid) = std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|pid| pid.trim().parse().ok())
+    {
+        let _ = super::startup::stop(pid, false);
+    }
+    let _ = std::fs::remove_file(path);
+}
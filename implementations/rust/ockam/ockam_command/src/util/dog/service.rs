@@ -1,32 +1,214 @@
 //! Woof
 
-use std::{io::Read, os::unix::net::UnixStream, path::PathBuf, time::Duration};
-use nix::unistd::mkfifo;
+use super::RestartPolicy;
+use crate::node::respawn_node;
+use crate::util::startup::SpawnNodeOptions;
+use crate::util::OckamConfig;
+use nix::sys::signal;
+use nix::unistd::Pid;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+/// How long a restarted node must run for its next crash not to count as
+/// part of a crash loop
+const STABLE_RUN: Duration = Duration::from_secs(60);
+
+/// Longest wait before restarting a node which keeps crashing
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
 
 /// A node watchdog restarts the node when it crashes
 ///
-/// Watchdogs get restarted by the user-facing CLI
+/// Watchdogs are spawned by `ockam node create --watchdog-interval`, and
+/// stopped by `ockam node stop` and `ockam node delete`. A watchdog stops
+/// once its restart policy doesn't allow restarting the node anymore.
 pub struct Watchdog {
-    pub socket_path: PathBuf,
+    pub node_name: String,
+    pub interval: Duration,
+    pub restart_policy: RestartPolicy,
+    pub max_restarts: Option<u32>,
 }
 
 impl Watchdog {
-    pub fn run(self) {
-        match UnixStream::connect(self.socket_path) {
-            Ok(stream) => {
-                loop {
-                    let mut buf = vec![0; 64];
-                    match stream.peek(&mut buf) {
-                        Ok(_) => std::thread::sleep(Duration::from_millis(200)),
-                        Err(_) => break,
-                    }
+    pub fn run(self) -> crate::Result<()> {
+        let node_dir = OckamConfig::load()?.get_node_dir(&self.node_name)?;
+        let pid_path = super::pid_path(&node_dir);
+        // Restarting the node with other options than the ones it was
+        // created with would silently change what it does
+        let options = match SpawnNodeOptions::load(&node_dir)? {
+            Some(options) => options,
+            None => {
+                error!(
+                    node = %self.node_name,
+                    "Node has no persisted options, it can't be restarted, not watching it"
+                );
+                return Ok(());
+            }
+        };
+        std::fs::write(&pid_path, std::process::id().to_string())?;
+        info!(
+            node = %self.node_name,
+            interval = ?self.interval,
+            restart_policy = ?self.restart_policy,
+            max_restarts = ?self.max_restarts,
+            "Watching node"
+        );
+
+        let mut restarts = Restarts::new(self.restart_policy, self.max_restarts, self.interval);
+        loop {
+            std::thread::sleep(self.interval);
+
+            // Reload the config to see the changes made by other commands
+            let cfg = OckamConfig::load()?;
+            let pid = match cfg.get_node_pid(&self.node_name) {
+                Ok(pid) => pid,
+                Err(_) => {
+                    info!(node = %self.node_name, "Node was deleted, stopping watchdog");
+                    return Ok(());
                 }
+            };
+            if pid.map(is_running).unwrap_or(false) {
+                continue;
+            }
 
-                // ???
+            let clean_exit = super::exited_cleanly(&node_dir);
+            let backoff = match restarts.next(clean_exit, Instant::now()) {
+                Some(backoff) => backoff,
+                None => {
+                    warn!(
+                        node = %self.node_name,
+                        ?pid,
+                        clean_exit,
+                        restarts = restarts.count(),
+                        "Node process is gone, not restarting it anymore, stopping watchdog"
+                    );
+                    let _ = std::fs::remove_file(&pid_path);
+                    return Ok(());
+                }
+            };
+            if !backoff.is_zero() {
+                warn!(node = %self.node_name, ?backoff, "Node keeps crashing, backing off");
+                std::thread::sleep(backoff);
             }
-            Err(_) => {
-                eprintln!("failed to connect to node socket.  is it running?")
+
+            warn!(
+                node = %self.node_name,
+                ?pid,
+                restarts = restarts.count(),
+                "Node process died, restarting it"
+            );
+            if let Err(e) = respawn_node(&cfg, &self.node_name, &options) {
+                error!(node = %self.node_name, "Failed to restart node: {:?}", e);
             }
         }
     }
 }
+
+/// Decides whether, and when, a node whose process is gone is restarted
+struct Restarts {
+    policy: RestartPolicy,
+    max_restarts: Option<u32>,
+    interval: Duration,
+    count: u32,
+    /// Restarts of the current crash loop
+    consecutive: u32,
+    last_restart: Option<Instant>,
+}
+
+impl Restarts {
+    fn new(policy: RestartPolicy, max_restarts: Option<u32>, interval: Duration) -> Self {
+        Self {
+            policy,
+            max_restarts,
+            interval,
+            count: 0,
+            consecutive: 0,
+            last_restart: None,
+        }
+    }
+
+    /// Number of restarts so far
+    fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Account for a restart of the node, which exited at `now`, returning
+    /// how long to wait before restarting it, or `None` if it mustn't be
+    ///
+    /// A node which crashes again within [`STABLE_RUN`] of its restart is
+    /// in a crash loop: the wait doubles with every restart of the loop,
+    /// from the watchdog interval up to [`MAX_BACKOFF`].
+    fn next(&mut self, clean_exit: bool, now: Instant) -> Option<Duration> {
+        let allowed = match self.policy {
+            RestartPolicy::Always => true,
+            RestartPolicy::OnFailure => !clean_exit,
+            RestartPolicy::Never => false,
+        };
+        if !allowed || self.max_restarts.map_or(false, |max| self.count >= max) {
+            return None;
+        }
+
+        let crash_loop = self
+            .last_restart
+            .map_or(false, |last| now.duration_since(last) < STABLE_RUN);
+        self.consecutive = if crash_loop { self.consecutive + 1 } else { 0 };
+        self.count += 1;
+        self.last_restart = Some(now);
+
+        if self.consecutive == 0 {
+            return Some(Duration::ZERO);
+        }
+        let factor = 1u32 << (self.consecutive - 1).min(16);
+        Some(self.interval.saturating_mul(factor).min(MAX_BACKOFF))
+    }
+}
+
+fn is_running(pid: i32) -> bool {
+    signal::kill(Pid::from_raw(pid), None).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERVAL: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn on_failure_stops_restarting_after_the_cap() {
+        let mut restarts = Restarts::new(RestartPolicy::OnFailure, Some(2), INTERVAL);
+        let now = Instant::now();
+        assert!(restarts.next(false, now).is_some());
+        assert!(restarts.next(false, now + STABLE_RUN * 2).is_some());
+        assert_eq!(restarts.next(false, now + STABLE_RUN * 4), None);
+        assert_eq!(restarts.count(), 2);
+    }
+
+    #[test]
+    fn on_failure_does_not_restart_a_clean_exit() {
+        let mut restarts = Restarts::new(RestartPolicy::OnFailure, None, INTERVAL);
+        assert_eq!(restarts.next(true, Instant::now()), None);
+
+        let mut restarts = Restarts::new(RestartPolicy::Always, None, INTERVAL);
+        assert!(restarts.next(true, Instant::now()).is_some());
+
+        let mut restarts = Restarts::new(RestartPolicy::Never, None, INTERVAL);
+        assert_eq!(restarts.next(false, Instant::now()), None);
+    }
+
+    #[test]
+    fn crash_loop_backs_off() {
+        let mut restarts = Restarts::new(RestartPolicy::Always, None, INTERVAL);
+        let start = Instant::now();
+        let waits: Vec<_> = (0..12)
+            .map(|i| restarts.next(false, start + INTERVAL * i).unwrap())
+            .collect();
+        assert_eq!(waits[0], Duration::ZERO);
+        assert_eq!(waits[1], INTERVAL);
+        assert_eq!(waits[2], INTERVAL * 2);
+        assert_eq!(waits[3], INTERVAL * 4);
+        assert_eq!(waits[11], MAX_BACKOFF);
+
+        // A node which ran long enough is restarted right away
+        let later = start + INTERVAL * 11 + STABLE_RUN;
+        assert_eq!(restarts.next(false, later), Some(Duration::ZERO));
+    }
+}
@@ -0,0 +1,86 @@
+//! `KEY=VALUE` files used to set the environment of a node process
+
+use anyhow::{anyhow, Context, Result};
+use std::path::Path;
+
+/// Read the variables defined in `path`
+///
+/// Blank lines and lines starting with `#` are skipped, values may be
+/// wrapped in single or double quotes.
+pub fn read(path: &Path) -> Result<Vec<(String, String)>> {
+    let s = std::fs::read_to_string(path)
+        .with_context(|| anyhow!("failed to read env file {:?}", path))?;
+    parse(&s).map_err(|e| anyhow!("invalid env file {:?}: {e}", path))
+}
+
+/// Read the variables defined in `path` and set them in the current process
+pub fn apply(path: &Path) -> Result<()> {
+    for (key, val) in read(path)? {
+        std::env::set_var(key, val);
+    }
+    Ok(())
+}
+
+fn parse(s: &str) -> Result<Vec<(String, String)>> {
+    let mut vars = Vec::new();
+    for (i, line) in s.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, val) = line
+            .split_once('=')
+            .ok_or_else(|| anyhow!("line {}: expected KEY=VALUE, got '{line}'", i + 1))?;
+        let key = key.trim();
+        if !is_valid_key(key) {
+            return Err(anyhow!("line {}: invalid variable name '{key}'", i + 1));
+        }
+        vars.push((key.to_string(), unquote(val.trim()).to_string()));
+    }
+    Ok(vars)
+}
+
+fn is_valid_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn unquote(val: &str) -> &str {
+    for quote in ['"', '\''] {
+        if val.len() >= 2 && val.starts_with(quote) && val.ends_with(quote) {
+            return &val[1..val.len() - 1];
+        }
+    }
+    val
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_key_value_pairs() {
+        let vars = parse("# endpoints\nHOST=127.0.0.1\n\nNAME = \"my node\"\nEMPTY=\n").unwrap();
+        assert_eq!(
+            vars,
+            vec![
+                ("HOST".to_string(), "127.0.0.1".to_string()),
+                ("NAME".to_string(), "my node".to_string()),
+                ("EMPTY".to_string(), "".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        let err = parse("HOST=127.0.0.1\nnot a variable\n").unwrap_err();
+        assert!(err.to_string().starts_with("line 2:"));
+
+        let err = parse("1HOST=127.0.0.1").unwrap_err();
+        assert!(err.to_string().contains("invalid variable name"));
+    }
+}
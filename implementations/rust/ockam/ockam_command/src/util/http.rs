@@ -0,0 +1,188 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context as _, Result};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Semaphore;
+use tracing::{debug, info, warn};
+
+/// Longest request line answered, longer ones get a `414 URI Too Long`
+const MAX_REQUEST_LINE: u64 = 1024;
+
+/// How long a client has to send its request line
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Connections answered at the same time, further ones wait to be accepted
+const MAX_CONNECTIONS: usize = 32;
+
+/// Answer of a [`serve`]d endpoint
+pub struct Response {
+    pub status: &'static str,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    /// A plain text response whose body is its status
+    pub fn status(status: &'static str) -> Self {
+        Self {
+            status,
+            content_type: "text/plain",
+            body: status.to_string(),
+        }
+    }
+}
+
+/// Serve `handler` over HTTP on `addr`, returning the address it listens on
+///
+/// This is a minimal server for the probe and metrics endpoints of a node:
+/// only the request line is read, it has to arrive within a few seconds and
+/// fit in [`MAX_REQUEST_LINE`] bytes, and every connection is answered once
+/// then closed. `handler` is given the method and path of the request.
+pub async fn serve<H>(addr: SocketAddr, name: &'static str, handler: H) -> Result<SocketAddr>
+where
+    H: Fn(&str, &str) -> Response + Send + Sync + 'static,
+{
+    serve_with_timeout(addr, name, REQUEST_TIMEOUT, handler).await
+}
+
+async fn serve_with_timeout<H>(
+    addr: SocketAddr,
+    name: &'static str,
+    timeout: Duration,
+    handler: H,
+) -> Result<SocketAddr>
+where
+    H: Fn(&str, &str) -> Response + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("failed to bind the {name} to {addr}"))?;
+    let local_addr = listener.local_addr()?;
+    info!("Serving the {} on {}", name, local_addr);
+
+    let handler = Arc::new(handler);
+    let connections = Arc::new(Semaphore::new(MAX_CONNECTIONS));
+    tokio::spawn(async move {
+        loop {
+            let permit = match connections.clone().acquire_owned().await {
+                Ok(permit) => permit,
+                Err(_) => return,
+            };
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    let handler = handler.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = respond(stream, timeout, &*handler).await {
+                            debug!("Failed to answer a {} request: {}", name, e);
+                        }
+                        drop(permit);
+                    });
+                }
+                Err(e) => warn!("Failed to accept a {} connection: {}", name, e),
+            }
+        }
+    });
+
+    Ok(local_addr)
+}
+
+async fn respond<H>(mut stream: TcpStream, timeout: Duration, handler: &H) -> Result<()>
+where
+    H: Fn(&str, &str) -> Response,
+{
+    let mut request_line = Vec::new();
+    let read = {
+        let mut reader = BufReader::new((&mut stream).take(MAX_REQUEST_LINE));
+        tokio::time::timeout(timeout, reader.read_until(b'\n', &mut request_line)).await
+    };
+
+    let response = match read {
+        Err(_) => Response::status("408 Request Timeout"),
+        Ok(read) => {
+            if read? == 0 {
+                // The client left without asking anything
+                return Ok(());
+            }
+            if request_line.last() != Some(&b'\n') {
+                Response::status("414 URI Too Long")
+            } else {
+                let request_line = String::from_utf8_lossy(&request_line);
+                let mut parts = request_line.split_whitespace();
+                match (parts.next(), parts.next()) {
+                    (Some(method), Some(path)) => handler(method, path),
+                    _ => Response::status("400 Bad Request"),
+                }
+            }
+        }
+    };
+
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.content_type,
+        response.body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(response.body.as_bytes()).await?;
+    stream.shutdown().await?;
+
+    // Closing the connection while headers are still unread would reset it
+    // and may lose the response, so they are drained for a while first
+    let mut rest = (&mut stream).take(MAX_REQUEST_LINE * 8);
+    let _ = tokio::time::timeout(timeout, tokio::io::copy(&mut rest, &mut tokio::io::sink())).await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn status_line(addr: SocketAddr, request: &[u8]) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(request).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response.lines().next().unwrap().to_string()
+    }
+
+    fn ok(_: &str, _: &str) -> Response {
+        Response::status("200 OK")
+    }
+
+    #[tokio::test]
+    async fn oversized_and_slow_requests_are_refused() {
+        let addr = serve_with_timeout(
+            "127.0.0.1:0".parse().unwrap(),
+            "test endpoint",
+            Duration::from_millis(100),
+            ok,
+        )
+        .await
+        .unwrap();
+
+        // The whole line is sent so that the server doesn't close the
+        // connection with unread data, which would reset it
+        let long_path = "a".repeat(MAX_REQUEST_LINE as usize - "GET /".len());
+        let request = format!("GET /{long_path}");
+        assert_eq!(
+            status_line(addr, request.as_bytes()).await,
+            "HTTP/1.1 414 URI Too Long"
+        );
+
+        // A client which never finishes its request line is answered and
+        // closed once the timeout elapses
+        assert_eq!(
+            status_line(addr, b"GET /livez").await,
+            "HTTP/1.1 408 Request Timeout"
+        );
+
+        assert_eq!(
+            status_line(addr, b"GET /livez HTTP/1.1\r\n\r\n").await,
+            "HTTP/1.1 200 OK"
+        );
+        assert_eq!(status_line(addr, b"\r\n").await, "HTTP/1.1 400 Bad Request");
+    }
+}
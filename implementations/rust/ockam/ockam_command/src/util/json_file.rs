@@ -0,0 +1,81 @@
+//! JSON files given to the command, such as project and launch config files
+//!
+//! Errors name the offending field, as a dotted path from the root of the
+//! document, and the line and column at which parsing stopped.
+
+use anyhow::{anyhow, Context, Result};
+use serde::de::{Deserialize, DeserializeOwned};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// Read `path` into a `T`, without loading the whole file in memory
+pub fn read<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let file = File::open(path).with_context(|| anyhow!("failed to read {:?}", path))?;
+    let mut de = serde_json::Deserializer::from_reader(BufReader::new(file));
+    let value = serde_path_to_error::deserialize(&mut de).map_err(|e| located(path, e))?;
+    // Reject trailing characters after the document
+    de.end().map_err(|e| anyhow!("invalid {:?}: {e}", path))?;
+    Ok(value)
+}
+
+/// Parse the contents `s` of `path` into a `T` that may borrow from it
+pub fn parse<'de, T: Deserialize<'de>>(path: &Path, s: &'de str) -> Result<T> {
+    let mut de = serde_json::Deserializer::from_str(s);
+    let value = serde_path_to_error::deserialize(&mut de).map_err(|e| located(path, e))?;
+    de.end().map_err(|e| anyhow!("invalid {:?}: {e}", path))?;
+    Ok(value)
+}
+
+/// Convert a document already read from `path` into a `T`
+///
+/// Line numbers are lost once a document is a [`serde_json::Value`], only
+/// the offending field is reported.
+pub fn from_value<T: DeserializeOwned>(path: &Path, json: serde_json::Value) -> Result<T> {
+    serde_path_to_error::deserialize(json)
+        .map_err(|e| anyhow!("invalid {:?}: field `{}`: {}", path, e.path(), e.inner()))
+}
+
+fn located(path: &Path, e: serde_path_to_error::Error<serde_json::Error>) -> anyhow::Error {
+    // The serde_json error already ends with its line and column
+    anyhow!("invalid {:?}: field `{}`: {}", path, e.path(), e.inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[derive(Debug, serde::Deserialize)]
+    struct Service {
+        #[allow(dead_code)]
+        address: String,
+    }
+
+    #[derive(Debug, serde::Deserialize)]
+    struct Services {
+        #[allow(dead_code)]
+        services: Vec<Service>,
+    }
+
+    #[test]
+    fn read_reports_field_and_line() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            file,
+            "{{\n  \"services\": [\n    {{\"address\": \"a\"}},\n    {{\"address\": 1}}\n  ]\n}}"
+        )
+        .unwrap();
+        let err = read::<Services>(file.path()).unwrap_err().to_string();
+        assert!(err.contains("services[1].address"), "{err}");
+        assert!(err.contains("invalid type"), "{err}");
+        assert!(err.contains("line 4"), "{err}");
+    }
+
+    #[test]
+    fn read_rejects_trailing_characters() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(file, r#"{{"services": []}} }}"#).unwrap();
+        assert!(read::<Services>(file.path()).is_err());
+    }
+}
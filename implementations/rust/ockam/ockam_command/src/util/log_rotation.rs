@@ -0,0 +1,248 @@
+//! Size-based rotation of the log files written by background nodes
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::{env, fmt};
+
+/// Compression applied to rotated log segments
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogCompression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl LogCompression {
+    fn extension(&self) -> &'static str {
+        match self {
+            LogCompression::None => "",
+            LogCompression::Gzip => ".gz",
+            LogCompression::Zstd => ".zst",
+        }
+    }
+}
+
+impl std::str::FromStr for LogCompression {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(LogCompression::None),
+            "gzip" => Ok(LogCompression::Gzip),
+            "zstd" => Ok(LogCompression::Zstd),
+            _ => Err(anyhow::anyhow!("unknown log compression '{s}'")),
+        }
+    }
+}
+
+/// When to rotate a log file and how many rotated segments to keep
+#[derive(Clone, Copy, Debug)]
+pub struct LogRotation {
+    pub max_size: u64,
+    pub max_files: usize,
+    pub compression: LogCompression,
+}
+
+impl Default for LogRotation {
+    fn default() -> Self {
+        Self {
+            max_size: 100 * 1024 * 1024,
+            max_files: 5,
+            compression: LogCompression::None,
+        }
+    }
+}
+
+impl LogRotation {
+    /// Defaults, overridden by the `OCKAM_LOG_MAX_SIZE` (bytes),
+    /// `OCKAM_LOG_MAX_FILES` and `OCKAM_LOG_COMPRESSION` (`none`,
+    /// `gzip` or `zstd`) environment variables
+    pub fn from_env() -> Self {
+        let mut rotation = Self::default();
+        if let Some(max_size) = parse_env("OCKAM_LOG_MAX_SIZE") {
+            rotation.max_size = max_size;
+        }
+        if let Some(max_files) = parse_env("OCKAM_LOG_MAX_FILES") {
+            rotation.max_files = max_files;
+        }
+        if let Some(compression) = parse_env("OCKAM_LOG_COMPRESSION") {
+            rotation.compression = compression;
+        }
+        rotation
+    }
+}
+
+fn parse_env<T: std::str::FromStr>(var: &str) -> Option<T>
+where
+    T::Err: fmt::Display,
+{
+    let value = env::var(var).ok()?;
+    match value.parse() {
+        Ok(value) => Some(value),
+        Err(e) => {
+            eprintln!("Ignoring invalid {var} '{value}': {e}");
+            None
+        }
+    }
+}
+
+/// A log file which is moved to `<path>.1` once it reaches the
+/// maximum size, shifting older segments to `<path>.2` and so on
+pub struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    rotation: LogRotation,
+}
+
+impl RotatingFile {
+    pub fn open(path: impl Into<PathBuf>, rotation: LogRotation) -> io::Result<Self> {
+        let path = path.into();
+        let file = open_append(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            file,
+            size,
+            rotation,
+        })
+    }
+
+    fn segment_path(&self, index: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{index}{}", self.rotation.compression.extension()));
+        name.into()
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        if self.rotation.max_files == 0 {
+            // Nothing is kept, start over
+            self.file = File::create(&self.path)?;
+            self.size = 0;
+            return Ok(());
+        }
+
+        let _ = fs::remove_file(self.segment_path(self.rotation.max_files));
+        for index in (1..self.rotation.max_files).rev() {
+            let from = self.segment_path(index);
+            if from.exists() {
+                fs::rename(from, self.segment_path(index + 1))?;
+            }
+        }
+
+        let first = self.segment_path(1);
+        match self.rotation.compression {
+            LogCompression::None => fs::rename(&self.path, first)?,
+            compression => {
+                compress(&self.path, &first, compression)?;
+                fs::remove_file(&self.path)?;
+            }
+        }
+
+        self.file = open_append(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.rotation.max_size {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn compress(from: &Path, to: &Path, compression: LogCompression) -> io::Result<()> {
+    let mut input = File::open(from)?;
+    let mut output = File::create(to)?;
+    match compression {
+        LogCompression::None => {
+            io::copy(&mut input, &mut output)?;
+        }
+        LogCompression::Gzip => {
+            let mut encoder = flate2::write::GzEncoder::new(output, flate2::Compression::default());
+            io::copy(&mut input, &mut encoder)?;
+            encoder.finish()?;
+        }
+        LogCompression::Zstd => {
+            let mut encoder = zstd::stream::write::Encoder::new(output, 0)?;
+            io::copy(&mut input, &mut encoder)?;
+            encoder.finish()?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn write_lines(file: &mut RotatingFile, count: usize) {
+        for i in 0..count {
+            // Like the tracing formatter, write each line at once
+            file.write_all(format!("log line {i:04}\n").as_bytes())
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn rotates_and_compresses_segments() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("n1.log");
+        let rotation = LogRotation {
+            max_size: 100,
+            max_files: 2,
+            compression: LogCompression::Gzip,
+        };
+        let mut file = RotatingFile::open(&path, rotation).unwrap();
+
+        // 14 bytes per line, 7 lines per segment
+        write_lines(&mut file, 8);
+        let segment = dir.path().join("n1.log.1.gz");
+        assert!(segment.exists());
+
+        let mut decoder = flate2::read::GzDecoder::new(File::open(&segment).unwrap());
+        let mut rotated = String::new();
+        decoder.read_to_string(&mut rotated).unwrap();
+        assert!(rotated.starts_with("log line 0000\n"));
+        assert!(rotated.ends_with("log line 0006\n"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "log line 0007\n");
+
+        // Only `max_files` segments are kept
+        write_lines(&mut file, 30);
+        assert!(dir.path().join("n1.log.2.gz").exists());
+        assert!(!dir.path().join("n1.log.3.gz").exists());
+    }
+
+    #[test]
+    fn rotates_without_compression() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("n1.log");
+        let rotation = LogRotation {
+            max_size: 100,
+            max_files: 1,
+            compression: LogCompression::None,
+        };
+        let mut file = RotatingFile::open(&path, rotation).unwrap();
+        write_lines(&mut file, 8);
+
+        let rotated = fs::read_to_string(dir.path().join("n1.log.1")).unwrap();
+        assert_eq!(rotated.lines().count(), 7);
+    }
+}
@@ -0,0 +1,140 @@
+use std::fmt::Write as _;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+use anyhow::Result;
+use ockam::tcp::{TcpConnectionMetrics, TcpTransportMetrics};
+
+use crate::util::http::{self, Response};
+
+/// Metrics of a node's TCP transport, served over HTTP in the Prometheus
+/// text format
+///
+/// `GET /metrics` returns the number of open connections, the time since
+/// the transport last sent or received a message, the messages and bytes
+/// counted by `ockam node metrics`, and the traffic of each open
+/// connection, labelled by peer. Heartbeats are never counted. Resetting
+/// the node's counters resets the `_total` series of the transport, which
+/// Prometheus handles as any counter reset.
+#[derive(Clone)]
+pub struct NodeMetrics {
+    tcp: Arc<TcpTransportMetrics>,
+}
+
+impl NodeMetrics {
+    pub fn new(tcp: Arc<TcpTransportMetrics>) -> Self {
+        Self { tcp }
+    }
+
+    /// Serve the metrics on `addr`, returning the address it listens on
+    pub async fn serve(&self, addr: SocketAddr) -> Result<SocketAddr> {
+        let metrics = self.clone();
+        http::serve(addr, "node metrics", move |method, path| {
+            metrics.respond(method, path)
+        })
+        .await
+    }
+
+    /// Current metrics, in the Prometheus text format
+    fn render(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "# HELP ockam_tcp_active_connections Open TCP connections, inbound and outbound"
+        );
+        let _ = writeln!(out, "# TYPE ockam_tcp_active_connections gauge");
+        let _ = writeln!(
+            out,
+            "ockam_tcp_active_connections {}",
+            self.tcp.active_connections()
+        );
+        let _ = writeln!(
+            out,
+            "# HELP ockam_tcp_idle_seconds Time since a message was last sent or received"
+        );
+        let _ = writeln!(out, "# TYPE ockam_tcp_idle_seconds gauge");
+        let _ = writeln!(
+            out,
+            "ockam_tcp_idle_seconds {}",
+            self.tcp.idle_for().as_secs_f64()
+        );
+
+        let counters = self.tcp.counters();
+        let totals = [
+            (
+                "ockam_tcp_messages_sent_total",
+                "Messages sent on every connection",
+                counters.messages_sent,
+            ),
+            (
+                "ockam_tcp_messages_received_total",
+                "Messages received on every connection",
+                counters.messages_received,
+            ),
+            (
+                "ockam_tcp_sent_bytes_total",
+                "Bytes of the messages sent on every connection",
+                counters.bytes_sent,
+            ),
+            (
+                "ockam_tcp_received_bytes_total",
+                "Bytes of the messages received on every connection",
+                counters.bytes_received,
+            ),
+        ];
+        for (name, help, value) in totals {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} counter");
+            let _ = writeln!(out, "{name} {value}");
+        }
+
+        let connections = self.tcp.connections();
+        type Value = fn(&TcpConnectionMetrics) -> f64;
+        let series: [(&str, &str, &str, Value); 3] = [
+            (
+                "ockam_tcp_connection_received_bytes_total",
+                "counter",
+                "Bytes received on an open connection",
+                |c| c.bytes_in as f64,
+            ),
+            (
+                "ockam_tcp_connection_sent_bytes_total",
+                "counter",
+                "Bytes sent on an open connection",
+                |c| c.bytes_out as f64,
+            ),
+            (
+                "ockam_tcp_connection_start_time_seconds",
+                "gauge",
+                "When an open connection was established, since the Unix epoch",
+                |c| {
+                    c.connected_at
+                        .duration_since(UNIX_EPOCH)
+                        .map(|d| d.as_secs_f64())
+                        .unwrap_or_default()
+                },
+            ),
+        ];
+        for (name, kind, help, value) in series {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} {kind}");
+            for (peer, connection) in &connections {
+                let _ = writeln!(out, "{name}{{peer=\"{peer}\"}} {}", value(connection));
+            }
+        }
+        out
+    }
+
+    fn respond(&self, method: &str, path: &str) -> Response {
+        match (method, path) {
+            ("GET", "/metrics") => Response {
+                status: "200 OK",
+                content_type: "text/plain; version=0.0.4",
+                body: self.render(),
+            },
+            ("GET", _) => Response::status("404 Not Found"),
+            _ => Response::status("405 Method Not Allowed"),
+        }
+    }
+}
@@ -4,6 +4,7 @@ use std::{
     net::{SocketAddr, TcpListener},
     path::Path,
     str::FromStr,
+    sync::Mutex,
 };
 
 use anyhow::{anyhow, Context as _, Result};
@@ -11,27 +12,38 @@ use crossbeam_channel::{bounded, Sender};
 use minicbor::{data::Type, Decode, Decoder, Encode};
 use tracing::{debug, error, trace};
 use tracing_subscriber::prelude::*;
-use tracing_subscriber::{filter::LevelFilter, fmt, EnvFilter};
+use tracing_subscriber::{fmt, EnvFilter};
 
 pub use addon::AddonCommand;
 pub use config::*;
-use ockam::{route, Address, Context, NodeBuilder, Route, TcpTransport, TCP};
+use ockam::{route, Address, Context, NodeBuilder, ResourceLimits, Route, TcpTransport, TCP};
 use ockam_api::config::cli::NodeConfigOld;
 use ockam_api::nodes::NODEMANAGER_ADDR;
 use ockam_core::api::{RequestBuilder, Response, Status};
 use ockam_multiaddr::{proto, MultiAddr, Protocol};
+pub use route::*;
 
 use crate::node::util::start_embedded_node;
+use crate::util::log_rotation::{LogRotation, RotatingFile};
 use crate::util::output::Output;
-use crate::{CommandGlobalOpts, OutputFormat};
+use crate::{CommandGlobalOpts, Error, OutputFormat};
 
 pub mod api;
+pub mod dog;
+pub mod env_file;
 pub mod exitcode;
+pub mod http;
+pub mod json_file;
+pub mod log_rotation;
+pub mod metrics;
+pub mod probe;
+pub mod signal;
 pub mod startup;
 
 mod addon;
 mod config;
 pub(crate) mod output;
+mod route;
 
 pub const DEFAULT_CONTROLLER_ADDRESS: &str = "/dnsaddr/orchestrator.ockam.io/tcp/6252/service/api";
 
@@ -143,11 +155,7 @@ impl<'a> Rpc<'a> {
         T: Encode<()>,
     {
         let route = self.route_impl(self.ctx).await?;
-        self.buf = self
-            .ctx
-            .send_and_receive(route.clone(), req.to_vec()?)
-            .await
-            .context("Failed to receive response from node")?;
+        self.buf = send_and_receive(self.ctx, self.opts, route, req.to_vec()?).await?;
         Ok(())
     }
 
@@ -176,6 +184,12 @@ impl<'a> Rpc<'a> {
         let route = match self.mode {
             RpcMode::Embedded => self.to.clone(),
             RpcMode::Background { ref cfg, ref tcp } => {
+                if cfg.no_api() && self.to.next()?.address() == NODEMANAGER_ADDR {
+                    return Err(anyhow!(
+                        "Node '{}' was created with `--no-api` and can't serve this command",
+                        self.node_name
+                    ));
+                }
                 let addr = Address::from((TCP, format!("localhost:{}", cfg.port())));
                 let addr_str = addr.address();
                 match tcp {
@@ -312,7 +326,6 @@ pub async fn stop_node(mut ctx: Context) -> Result<()> {
 /// and a user function to run.  It uses `embedded_node` internally,
 /// while also configuring a TcpTransport and connecting to another
 /// node.
-///
 pub fn connect_to<A, F, Fut>(port: u16, a: A, lambda: F)
 where
     A: Send + Sync + 'static,
@@ -324,21 +337,26 @@ where
             let tcp = match TcpTransport::create(&ctx).await {
                 Ok(tcp) => tcp,
                 Err(e) => {
-                    eprintln!("Failed to create TcpTransport. {e}");
                     error!(%e);
-                    std::process::exit(exitcode::CANTCREAT);
+                    Error::new(
+                        exitcode::CANTCREAT,
+                        anyhow!("Failed to create TcpTransport. {e}"),
+                    )
+                    .exit();
                 }
             };
             if let Err(e) = tcp.connect(format!("localhost:{}", port)).await {
-                eprintln!("Failed to connect to node. {e}");
                 error!(%e);
-                std::process::exit(exitcode::IOERR);
+                Error::new(exitcode::IOERR, anyhow!("Failed to connect to node. {e}")).exit();
             }
             let route = route![(TCP, format!("localhost:{}", port))];
             if let Err(e) = lambda(ctx, a, route).await {
-                eprintln!("Encountered an error in command handler code. {e}");
                 error!(%e);
-                std::process::exit(exitcode::IOERR);
+                Error::new(
+                    exitcode::IOERR,
+                    anyhow!("Encountered an error in command handler code. {e}"),
+                )
+                .exit();
             }
             Ok(())
         },
@@ -349,6 +367,41 @@ where
     }
 }
 
+/// Send a request to `route` and wait for the response, for at most the
+/// `--timeout` of the command
+pub async fn send_and_receive(
+    ctx: &Context,
+    opts: &CommandGlobalOpts,
+    route: Route,
+    req: Vec<u8>,
+) -> crate::Result<Vec<u8>> {
+    let timeout = opts.global_args.timeout;
+    let mut child_ctx = ctx.new_detached(Address::random_local()).await?;
+    let res = tokio::time::timeout(timeout, async {
+        child_ctx.send(route.clone(), req).await?;
+        child_ctx
+            .receive_duration_timeout::<Vec<u8>>(timeout)
+            .await
+            .map(|msg| msg.take().body())
+    })
+    .await;
+    match res {
+        Ok(Ok(resp)) => Ok(resp),
+        Ok(Err(e)) => Err(Error::new(
+            exitcode::IOERR,
+            anyhow::Error::from(e).context("Failed to receive response from node"),
+        )),
+        Err(_) => Err(Error::new(
+            exitcode::TEMPFAIL,
+            anyhow!(
+                "No response from {} within {:?}, use --timeout to wait longer",
+                route,
+                timeout
+            ),
+        )),
+    }
+}
+
 pub fn node_rpc<A, F, Fut>(f: F, a: A)
 where
     A: Send + Sync + 'static,
@@ -360,16 +413,14 @@ where
             let res = f(ctx, a).await;
             if let Err(e) = res {
                 error!(%e);
-                eprintln!("{e:?}");
-                std::process::exit(e.code());
+                e.exit();
             }
             Ok(())
         },
         a,
     );
     if let Err(e) = res {
-        eprintln!("Ockam node failed: {e}");
-        std::process::exit(exitcode::SOFTWARE);
+        Error::new(exitcode::SOFTWARE, anyhow!("Ockam node failed: {e}")).exit();
     }
 }
 
@@ -386,13 +437,13 @@ where
             .new_detached(Address::random_local())
             .await
             .expect("Embedded node child ctx can't be created");
+        stop_on_signal(&ctx).await;
         let r = f(child_ctx, a).await;
         stop_node(ctx).await.unwrap();
         match r {
             Err(e) => {
                 error!(%e);
-                eprintln!("{e:?}");
-                std::process::exit(e.code());
+                e.exit();
             }
             Ok(v) => v,
         }
@@ -400,24 +451,31 @@ where
     Ok(r)
 }
 
-pub fn embedded_node_that_is_not_stopped<A, F, Fut, T>(f: F, a: A) -> crate::Result<T>
+pub fn embedded_node_that_is_not_stopped<A, F, Fut, T>(
+    limits: ResourceLimits,
+    f: F,
+    a: A,
+) -> crate::Result<T>
 where
     A: Send + Sync + 'static,
     F: FnOnce(Context, A) -> Fut + Send + Sync + 'static,
     Fut: core::future::Future<Output = crate::Result<T>> + Send + 'static,
     T: Send + 'static,
 {
-    let (ctx, mut executor) = NodeBuilder::without_access_control().no_logging().build();
+    let (ctx, mut executor) = NodeBuilder::without_access_control()
+        .no_logging()
+        .with_resource_limits(limits)
+        .build();
     let r = executor.execute(async move {
         let child_ctx = ctx
             .new_detached(Address::random_local())
             .await
             .expect("Embedded node child ctx can't be created");
+        stop_on_signal(&ctx).await;
         match f(child_ctx, a).await {
             Err(e) => {
                 error!(%e);
-                eprintln!("{e:?}");
-                std::process::exit(e.code());
+                e.exit();
             }
             Ok(v) => v,
         }
@@ -425,6 +483,14 @@ where
     Ok(r)
 }
 
+/// Stop the embedded node of `ctx` cleanly on SIGINT or SIGTERM
+async fn stop_on_signal(ctx: &Context) {
+    match ctx.new_detached(Address::random_local()).await {
+        Ok(signal_ctx) => signal::stop_on_signal(signal_ctx),
+        Err(e) => error!("Failed to set up signal handling: {}", e),
+    }
+}
+
 pub fn find_available_port() -> Result<u16> {
     let listener = TcpListener::bind("127.0.0.1:0").context("Unable to bind to an open port")?;
     let address = listener
@@ -433,43 +499,103 @@ pub fn find_available_port() -> Result<u16> {
     Ok(address.port())
 }
 
-pub fn setup_logging(verbose: u8, no_color: bool) {
+/// The `RUST_LOG`-style filter for a number of `-v` flags, `None` when not verbose
+///
+/// * `-v`: info for the ockam crates, warnings for everything else
+/// * `-vv`: debug for the ockam crates, warnings for everything else
+/// * `-vvv`: trace for the ockam crates, info for everything else
+/// * `-vvvv` and more: trace for everything
+pub fn log_filter(verbose: u8) -> Option<String> {
     let ockam_crates = [
         "ockam",
-        "ockam_node",
-        "ockam_core",
+        "ockam_abac",
+        "ockam_api",
+        "ockam_channel",
         "ockam_command",
+        "ockam_core",
         "ockam_identity",
-        "ockam_channel",
+        "ockam_multiaddr",
+        "ockam_node",
         "ockam_transport_tcp",
+        "ockam_transport_udp",
         "ockam_vault",
         "ockam_vault_sync_core",
     ];
+    let (default, ockam) = match verbose {
+        0 => return None,
+        1 => ("warn", "info"),
+        2 => ("warn", "debug"),
+        3 => ("info", "trace"),
+        _ => return Some("trace".to_string()),
+    };
+    let directives = ockam_crates.map(|c| format!("{c}={ockam}"));
+    Some(format!("{default},{}", directives.join(",")))
+}
+
+/// Check a `RUST_LOG`-style filter given to `--log-level`, e.g.
+/// `info,ockam_transport_udp=trace`
+pub fn parse_log_level(input: &str) -> Result<String> {
+    EnvFilter::builder()
+        .parse(input)
+        .map_err(|e| anyhow!("Invalid log level '{input}': {e}"))?;
+    Ok(input.to_string())
+}
+
+/// The filter of the trace messages to log, `None` when nothing is logged
+///
+/// `log_level` overrides `verbose`. If neither is set, the log level is
+/// read from the OCKAM_LOG env variable.
+fn env_filter(verbose: u8, log_level: Option<&str>) -> Option<EnvFilter> {
     let builder = EnvFilter::builder();
-    // If `verbose` is not set, try to read the log level from the OCKAM_LOG env variable.
-    // If both `verbose` and OCKAM_LOG are not set, logging will not be enabled.
-    // Otherwise, use `verbose` to define the log level.
-    let filter = match verbose {
-        0 => match env::var("OCKAM_LOG") {
-            Ok(s) if !s.is_empty() => builder.with_env_var("OCKAM_LOG").from_env_lossy(),
-            _ => return,
+    match log_level
+        .map(str::to_string)
+        .or_else(|| log_filter(verbose))
+    {
+        Some(directives) => Some(builder.parse_lossy(directives)),
+        None => match env::var("OCKAM_LOG") {
+            Ok(s) if !s.is_empty() => Some(builder.with_env_var("OCKAM_LOG").from_env_lossy()),
+            _ => None,
+        },
+    }
+}
+
+/// Log to stdout, or to `log_file` rotated as configured by [`LogRotation::from_env`]
+pub fn setup_logging(
+    verbose: u8,
+    log_level: Option<&str>,
+    no_color: bool,
+    log_file: Option<&Path>,
+) {
+    let filter = match env_filter(verbose, log_level) {
+        Some(filter) => filter,
+        None => return,
+    };
+    let result = match log_file {
+        Some(path) => match RotatingFile::open(path, LogRotation::from_env()) {
+            Ok(file) => {
+                let fmt = fmt::Layer::default()
+                    .with_ansi(false)
+                    .with_writer(Mutex::new(file));
+                tracing_subscriber::registry()
+                    .with(filter)
+                    .with(tracing_error::ErrorLayer::default())
+                    .with(fmt)
+                    .try_init()
+            }
+            Err(e) => {
+                eprintln!("Failed to open log file {}: {}", path.display(), e);
+                return;
+            }
         },
-        1 => builder
-            .with_default_directive(LevelFilter::INFO.into())
-            .parse_lossy(ockam_crates.map(|c| format!("{c}=info")).join(",")),
-        2 => builder
-            .with_default_directive(LevelFilter::DEBUG.into())
-            .parse_lossy(ockam_crates.map(|c| format!("{c}=debug")).join(",")),
-        _ => builder
-            .with_default_directive(LevelFilter::TRACE.into())
-            .parse_lossy(ockam_crates.map(|c| format!("{c}=trace")).join(",")),
+        None => {
+            let fmt = fmt::Layer::default().with_ansi(!no_color);
+            tracing_subscriber::registry()
+                .with(filter)
+                .with(tracing_error::ErrorLayer::default())
+                .with(fmt)
+                .try_init()
+        }
     };
-    let fmt = fmt::Layer::default().with_ansi(!no_color);
-    let result = tracing_subscriber::registry()
-        .with(filter)
-        .with(tracing_error::ErrorLayer::default())
-        .with(fmt)
-        .try_init();
     if result.is_err() {
         eprintln!("Failed to initialise tracing logging.");
     }
@@ -530,6 +656,33 @@ pub fn comma_separated<T: AsRef<str>>(data: &[T]) -> String {
     data.iter().map(AsRef::as_ref).intersperse(", ").collect()
 }
 
+/// Parse a duration such as `500ms`, `30s`, `5m` or `1h`; a bare number is in seconds
+pub fn parse_duration(input: &str) -> Result<Duration> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (value, unit) = input.split_at(split);
+    let value: u64 = value
+        .parse()
+        .map_err(|_| anyhow!("Invalid duration: {input}"))?;
+    let secs_per_unit = match unit {
+        "ms" => return Ok(Duration::from_millis(value)),
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        _ => {
+            return Err(anyhow!(
+                "Invalid duration unit '{unit}', expected ms, s, m or h"
+            ))
+        }
+    };
+    value
+        .checked_mul(secs_per_unit)
+        .map(Duration::from_secs)
+        .ok_or_else(|| anyhow!("Invalid duration: {input}"))
+}
+
 pub fn bind_to_port_check(address: &SocketAddr) -> bool {
     let port = address.port();
     let ip = address.ip();
@@ -547,15 +700,21 @@ pub fn verify_pids(cfg: &OckamConfig, nodes: Vec<String>) {
 
         if node_cfg.pid() != verified_pid {
             if let Err(e) = cfg.set_node_pid(&node_name, verified_pid) {
-                eprintln!("Failed to update pid for node {}: {}", node_name, e);
-                std::process::exit(exitcode::IOERR);
+                Error::new(
+                    exitcode::IOERR,
+                    anyhow!("Failed to update pid for node {}: {}", node_name, e),
+                )
+                .exit();
             }
         }
     }
 
     if cfg.persist_config_updates().is_err() {
-        eprintln!("Failed to update PID information in config!");
-        std::process::exit(exitcode::IOERR);
+        Error::new(
+            exitcode::IOERR,
+            anyhow!("Failed to update PID information in config!"),
+        )
+        .exit();
     }
 }
 
@@ -590,6 +749,52 @@ pub async fn query_pid(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_log_filter() {
+        assert_eq!(log_filter(0), None);
+
+        let info = log_filter(1).unwrap();
+        assert!(info.starts_with("warn,"));
+        assert!(info.contains(",ockam=info,"));
+        assert!(info.contains(",ockam_node=info,"));
+
+        let debug = log_filter(2).unwrap();
+        assert!(debug.starts_with("warn,"));
+        assert!(debug.contains(",ockam_api=debug,"));
+        assert!(!debug.contains("=info"));
+
+        let trace = log_filter(3).unwrap();
+        assert!(trace.starts_with("info,"));
+        assert!(trace.contains(",ockam_transport_tcp=trace,"));
+
+        assert_eq!(log_filter(4).unwrap(), "trace");
+        assert_eq!(log_filter(u8::MAX).unwrap(), "trace");
+
+        // Every level parses as a valid filter
+        for verbose in 1..=4 {
+            assert!(EnvFilter::builder()
+                .parse(log_filter(verbose).unwrap())
+                .is_ok());
+        }
+    }
+
+    #[test]
+    fn test_log_level_overrides_verbosity() {
+        let filter = env_filter(2, Some("info,ockam_transport_udp=trace")).unwrap();
+        let directives = filter.to_string();
+        assert!(directives.contains("ockam_transport_udp=trace"));
+        assert!(directives.contains("info"));
+        assert!(!directives.contains("ockam_api=debug"));
+
+        assert!(env_filter(2, None)
+            .unwrap()
+            .to_string()
+            .contains("ockam_api=debug"));
+
+        assert!(parse_log_level("info,ockam_transport_udp=trace").is_ok());
+        assert!(parse_log_level("ockam_transport_udp=loud").is_err());
+    }
+
     #[test]
     fn test_extract_address_value() {
         let test_cases = vec![
@@ -615,4 +820,29 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_parse_duration() {
+        let test_cases = vec![
+            ("", Err(())),
+            ("s", Err(())),
+            ("10", Ok(Duration::from_secs(10))),
+            ("500ms", Ok(Duration::from_millis(500))),
+            ("30s", Ok(Duration::from_secs(30))),
+            ("5m", Ok(Duration::from_secs(300))),
+            ("1h", Ok(Duration::from_secs(3600))),
+            ("1d", Err(())),
+            ("-1s", Err(())),
+            ("18446744073709551615s", Ok(Duration::from_secs(u64::MAX))),
+            ("18446744073709551615m", Err(())),
+            ("5124095576030432h", Err(())),
+        ];
+        for (input, expected) in test_cases {
+            if let Ok(duration) = expected {
+                assert_eq!(parse_duration(input).unwrap(), duration);
+            } else {
+                assert!(parse_duration(input).is_err());
+            }
+        }
+    }
 }
@@ -8,6 +8,7 @@ use crate::project::ProjectInfo;
 use crate::util::comma_separated;
 use colorful::Colorful;
 use ockam_api::cloud::space::Space;
+use ockam_api::nodes::models::base::{MetricsStatus, WorkerList};
 use ockam_api::nodes::models::secure_channel::{
     CreateSecureChannelResponse, ShowSecureChannelResponse,
 };
@@ -168,6 +169,60 @@ impl Output for Vec<Project<'_>> {
     }
 }
 
+impl Output for WorkerList<'_> {
+    fn output(&self) -> anyhow::Result<String> {
+        if self.list.is_empty() {
+            return Ok("No workers found".to_string());
+        }
+        let mut rows = vec![];
+        for w in &self.list {
+            let address = w.addresses.first().map(|a| a.as_ref()).unwrap_or("-");
+            let aliases = w.addresses.get(1..).unwrap_or_default();
+            rows.push([
+                address.cell(),
+                comma_separated(aliases).cell(),
+                w.cluster.as_deref().unwrap_or("-").cell(),
+                if w.processor { "Processor" } else { "Worker" }.cell(),
+            ]);
+        }
+        let table = rows
+            .table()
+            .title([
+                "Address".cell().bold(true),
+                "Aliases".cell().bold(true),
+                "Cluster".cell().bold(true),
+                "Type".cell().bold(true),
+            ])
+            .display()?
+            .to_string();
+        Ok(table)
+    }
+}
+
+impl Output for MetricsStatus {
+    fn output(&self) -> anyhow::Result<String> {
+        let rows = [
+            [
+                "TCP active connections".cell(),
+                self.tcp_active_connections.cell(),
+            ],
+            ["TCP messages sent".cell(), self.tcp_messages_sent.cell()],
+            [
+                "TCP messages received".cell(),
+                self.tcp_messages_received.cell(),
+            ],
+            ["TCP bytes sent".cell(), self.tcp_bytes_sent.cell()],
+            ["TCP bytes received".cell(), self.tcp_bytes_received.cell()],
+        ];
+        let table = rows
+            .table()
+            .title(["Metric".cell().bold(true), "Value".cell().bold(true)])
+            .display()?
+            .to_string();
+        Ok(table)
+    }
+}
+
 impl Output for CreateSecureChannelResponse<'_> {
     fn output(&self) -> anyhow::Result<String> {
         let addr = route_to_multiaddr(&route![self.addr.to_string()])
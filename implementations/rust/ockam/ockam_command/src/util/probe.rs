@@ -0,0 +1,112 @@
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use crate::util::http::{self, Response};
+use crate::util::signal;
+
+const STARTING: u8 = 0;
+const READY: u8 = 1;
+const DRAINING: u8 = 2;
+
+/// Liveness and readiness of a node, served over HTTP for orchestrators
+/// such as Kubernetes
+///
+/// `GET /livez` succeeds as long as the node process answers. `GET /readyz`
+/// only succeeds once the node has marked itself ready, i.e. once its
+/// transports and services are up, and fails again while the node is
+/// draining after a SIGINT or SIGTERM. The probe doesn't go through the
+/// node manager, so it keeps answering while the node is busy or stopping.
+#[derive(Clone, Default)]
+pub struct NodeProbe {
+    state: Arc<AtomicU8>,
+}
+
+impl NodeProbe {
+    /// Serve the probe on `addr`, returning the address it listens on
+    pub async fn serve(&self, addr: SocketAddr) -> Result<SocketAddr> {
+        let probe = self.clone();
+        let local_addr = http::serve(addr, "node probes", move |method, path| {
+            probe.respond(method, path)
+        })
+        .await?;
+
+        let probe = self.clone();
+        tokio::spawn(async move {
+            if signal::wait_for_signal().await.is_ok() {
+                probe.set_draining();
+            }
+        });
+
+        Ok(local_addr)
+    }
+
+    /// Report the node as ready to receive traffic
+    pub fn set_ready(&self) {
+        // A draining node doesn't become ready again
+        let _ = self
+            .state
+            .compare_exchange(STARTING, READY, Ordering::SeqCst, Ordering::SeqCst);
+    }
+
+    /// Report the node as shutting down
+    pub fn set_draining(&self) {
+        self.state.store(DRAINING, Ordering::SeqCst);
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.state.load(Ordering::SeqCst) == READY
+    }
+
+    fn respond(&self, method: &str, path: &str) -> Response {
+        Response::status(match (method, path) {
+            ("GET", "/livez") => "200 OK",
+            ("GET", "/readyz") if self.is_ready() => "200 OK",
+            ("GET", "/readyz") => "503 Service Unavailable",
+            ("GET", _) => "404 Not Found",
+            _ => "405 Method Not Allowed",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    async fn get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!("GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response.lines().next().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn probe_reports_readiness() {
+        let probe = NodeProbe::default();
+        let addr = probe.serve("127.0.0.1:0".parse().unwrap()).await.unwrap();
+
+        assert_eq!(get(addr, "/livez").await, "HTTP/1.1 200 OK");
+        assert_eq!(
+            get(addr, "/readyz").await,
+            "HTTP/1.1 503 Service Unavailable"
+        );
+
+        probe.set_ready();
+        assert_eq!(get(addr, "/readyz").await, "HTTP/1.1 200 OK");
+        assert_eq!(get(addr, "/metrics").await, "HTTP/1.1 404 Not Found");
+
+        probe.set_draining();
+        probe.set_ready();
+        assert_eq!(
+            get(addr, "/readyz").await,
+            "HTTP/1.1 503 Service Unavailable"
+        );
+        assert_eq!(get(addr, "/livez").await, "HTTP/1.1 200 OK");
+    }
+}
@@ -0,0 +1,210 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use ockam_api::DefaultAddress;
+use ockam_multiaddr::proto::{DnsAddr, Ip4, Ip6, Node, Project, Secure, Service, Space, Tcp, Udp};
+use ockam_multiaddr::{MultiAddr, Protocol};
+
+/// Parse a route given on the command line
+///
+/// Besides multiaddrs, a route can start with a `tcp://` or `udp://` host
+/// and port, followed by the rest of the route, e.g.
+/// `udp://127.0.0.1:4000/service/echoer`. Several of them can be chained,
+/// e.g. `tcp://relay:4000/service/hop/udp://10.0.0.2:5000`
+///
+/// A `/secure` hop without a name goes through the default secure channel
+/// listener of the node before it, e.g. `tcp://127.0.0.1:4000/secure/service/echoer`
+/// is `/ip4/127.0.0.1/tcp/4000/secure/api/service/echoer`. The node sending
+/// to such a route creates a secure channel to each `/secure` hop.
+pub fn parse_route(input: &str) -> Result<MultiAddr> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err(anyhow!("Invalid route: the route is empty"));
+    }
+
+    if !input.contains('/') {
+        return Err(anyhow!(
+            "Invalid route {input}: expected a multiaddr, e.g. /service/{input}"
+        ));
+    }
+
+    let mut ma = MultiAddr::default();
+
+    for segment in segments(input) {
+        let path = match segment.split_once("://") {
+            Some((scheme, rest)) => {
+                let (host_port, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+                push_transport(&mut ma, input, scheme, host_port)?;
+                path
+            }
+            None => segment,
+        };
+        let path = expand_secure(path);
+        if !path.is_empty() {
+            let path =
+                MultiAddr::from_str(&path).map_err(|e| anyhow!("Invalid route {input}: {e}"))?;
+            ma.try_extend(&path)?;
+        }
+    }
+    Ok(ma)
+}
+
+/// Split `input` before every `<scheme>://`
+fn segments(input: &str) -> Vec<&str> {
+    let mut starts: Vec<usize> = input
+        .match_indices("://")
+        .map(|(i, _)| input[..i].rfind('/').map_or(0, |slash| slash + 1))
+        .collect();
+    if starts.first() != Some(&0) {
+        starts.insert(0, 0);
+    }
+    starts.push(input.len());
+    starts.windows(2).map(|w| &input[w[0]..w[1]]).collect()
+}
+
+/// Push the address and transport protocols of `<scheme>://<host>:<port>`
+fn push_transport(ma: &mut MultiAddr, input: &str, scheme: &str, host_port: &str) -> Result<()> {
+    let (host, port) = host_port
+        .rsplit_once(':')
+        .ok_or_else(|| anyhow!("Invalid route {input}: expected {scheme}://<host>:<port>"))?;
+    let port: u16 = port
+        .parse()
+        .map_err(|_| anyhow!("Invalid route {input}: invalid port '{port}'"))?;
+    let host = host
+        .strip_prefix('[')
+        .and_then(|h| h.strip_suffix(']'))
+        .unwrap_or(host);
+
+    if let Ok(ip) = Ipv4Addr::from_str(host) {
+        ma.push_back(Ip4::new(ip))?;
+    } else if let Ok(ip) = Ipv6Addr::from_str(host) {
+        ma.push_back(Ip6::new(ip))?;
+    } else {
+        ma.push_back(DnsAddr::new(host))?;
+    }
+    match scheme {
+        "tcp" => ma.push_back(Tcp::new(port))?,
+        "udp" => ma.push_back(Udp::new(port))?,
+        _ => {
+            return Err(anyhow!(
+                "Invalid route {input}: unknown transport '{scheme}', expected tcp or udp"
+            ))
+        }
+    }
+    Ok(())
+}
+
+/// Name the `/secure` hops of a multiaddr path which have none after the
+/// default secure channel listener
+fn expand_secure(path: &str) -> String {
+    const PREFIXES: [&str; 10] = [
+        Ip4::PREFIX,
+        Ip6::PREFIX,
+        DnsAddr::PREFIX,
+        Tcp::PREFIX,
+        Udp::PREFIX,
+        Service::PREFIX,
+        Node::PREFIX,
+        Project::PREFIX,
+        Space::PREFIX,
+        Secure::PREFIX,
+    ];
+    let parts: Vec<&str> = path.split('/').filter(|p| !p.is_empty()).collect();
+    let mut expanded = String::new();
+    let mut i = 0;
+    while i < parts.len() {
+        expanded.push('/');
+        expanded.push_str(parts[i]);
+        let named = parts
+            .get(i + 1)
+            .map_or(false, |next| !PREFIXES.contains(next));
+        if parts[i] == Secure::PREFIX && !named {
+            expanded.push('/');
+            expanded.push_str(DefaultAddress::SECURE_CHANNEL_LISTENER);
+            i += 1;
+        } else {
+            if let Some(value) = parts.get(i + 1) {
+                expanded.push('/');
+                expanded.push_str(value);
+            }
+            i += 2;
+        }
+    }
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ockam::TCP;
+
+    #[test]
+    fn test_parse_route_with_scheme() {
+        use ockam_api::multiaddr_to_route;
+        use ockam_core::TransportType;
+
+        let ma = parse_route("udp://127.0.0.1:4000/service/echoer").unwrap();
+        assert_eq!(ma.to_string(), "/ip4/127.0.0.1/udp/4000/service/echoer");
+        let route = multiaddr_to_route(&ma).unwrap();
+        let first = route.next().unwrap();
+        assert_eq!(first.transport_type(), TransportType::new(2));
+        assert_eq!(first.to_string(), "2#127.0.0.1:4000");
+
+        let ma = parse_route("tcp://localhost:4000").unwrap();
+        assert_eq!(ma.to_string(), "/dnsaddr/localhost/tcp/4000");
+        let route = multiaddr_to_route(&ma).unwrap();
+        assert_eq!(route.next().unwrap().transport_type(), TCP);
+
+        let ma = parse_route("udp://[::1]:4000").unwrap();
+        assert_eq!(ma.to_string(), "/ip6/::1/udp/4000");
+
+        // Routes without a scheme are plain multiaddrs
+        let ma = parse_route("/node/n1/service/echoer").unwrap();
+        assert_eq!(ma.to_string(), "/node/n1/service/echoer");
+
+        assert!(parse_route("quic://127.0.0.1:4000").is_err());
+        assert!(parse_route("udp://127.0.0.1").is_err());
+        assert!(parse_route("udp://127.0.0.1:70000").is_err());
+    }
+
+    #[test]
+    fn test_parse_route_without_slash() {
+        assert!(parse_route("echoer").is_err());
+        assert!(parse_route("  ").is_err());
+    }
+
+    #[test]
+    fn test_parse_route_mixed_schemes() {
+        let ma = parse_route("tcp://10.0.0.1:4000/service/hop/udp://10.0.0.2:5000/service/echoer")
+            .unwrap();
+        assert_eq!(
+            ma.to_string(),
+            "/ip4/10.0.0.1/tcp/4000/service/hop/ip4/10.0.0.2/udp/5000/service/echoer"
+        );
+
+        let ma = parse_route("/node/n1/service/hop/tcp://relay:4000").unwrap();
+        assert_eq!(
+            ma.to_string(),
+            "/node/n1/service/hop/dnsaddr/relay/tcp/4000"
+        );
+
+        assert!(parse_route("tcp://10.0.0.1:4000/service/hop/quic://10.0.0.2:5000").is_err());
+    }
+
+    #[test]
+    fn test_parse_route_secure_sugar() {
+        let ma = parse_route("tcp://127.0.0.1:4000/secure/service/echoer").unwrap();
+        assert_eq!(
+            ma.to_string(),
+            "/ip4/127.0.0.1/tcp/4000/secure/api/service/echoer"
+        );
+
+        // Named listeners are kept
+        let ma = parse_route("/node/n1/secure/other/service/hop/secure").unwrap();
+        assert_eq!(
+            ma.to_string(),
+            "/node/n1/secure/other/service/hop/secure/api"
+        );
+    }
+}
@@ -0,0 +1,58 @@
+use std::time::Duration;
+
+use nix::sys::signal::Signal;
+use ockam::Context;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::time::timeout;
+use tracing::{error, info, warn};
+
+use crate::util::exitcode::{self, ExitCode};
+
+/// How long the node has to stop its workers before the process exits anyway
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Stop the node of `ctx` then exit once the process receives SIGINT or
+/// SIGTERM
+///
+/// Stopping the node shuts its workers and processors down, which closes
+/// their connections and listeners. The process exits with the usual
+/// `128 + signal number` code, or `SOFTWARE` if the node didn't stop in
+/// time.
+pub fn stop_on_signal(mut ctx: Context) {
+    tokio::spawn(async move {
+        let signal = match wait_for_signal().await {
+            Ok(signal) => signal,
+            Err(e) => {
+                warn!("Failed to listen for signals: {}", e);
+                return;
+            }
+        };
+        info!("Received {}, stopping the node", signal);
+
+        let code: ExitCode = match timeout(SHUTDOWN_TIMEOUT, ctx.stop()).await {
+            Ok(Ok(())) => {
+                info!("Node stopped");
+                128 + signal as ExitCode
+            }
+            Ok(Err(e)) => {
+                error!("Failed to stop the node: {}", e);
+                exitcode::SOFTWARE
+            }
+            Err(_) => {
+                error!("The node didn't stop within {:?}", SHUTDOWN_TIMEOUT);
+                exitcode::SOFTWARE
+            }
+        };
+        std::process::exit(code);
+    });
+}
+
+/// Wait for SIGINT or SIGTERM
+pub(crate) async fn wait_for_signal() -> std::io::Result<Signal> {
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    let mut terminate = signal(SignalKind::terminate())?;
+    Ok(tokio::select! {
+        _ = interrupt.recv() => Signal::SIGINT,
+        _ = terminate.recv() => Signal::SIGTERM,
+    })
+}
@@ -3,18 +3,24 @@
 #![allow(unused)]
 
 use crate::exitcode;
+use crate::node::CredentialChecksArg;
+use crate::service::config::ServiceSpec;
 use crate::util::OckamConfig;
 use anyhow::Context;
 use nix::sys::signal::{self, Signal};
 use nix::unistd::Pid;
+use ockam_multiaddr::MultiAddr;
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 use std::io::Stdout;
 use std::process::Stdio;
 use std::{
     env::current_exe,
     fs::OpenOptions,
+    net::SocketAddr,
     path::{Path, PathBuf},
     process::Command,
+    time::Duration,
 };
 
 /// Stop a node without deleting its state directory
@@ -31,21 +37,140 @@ pub fn stop(pid: i32, sigkill: bool) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Environment variable through which a background node receives its pre-shared key
+pub const PRE_SHARED_KEY_ENV: &str = "OCKAM_PRE_SHARED_KEY";
+
+/// Options a node process is spawned with, built from the arguments of
+/// `ockam node create`
+///
+/// They are persisted in the node directory so that `ockam node start`
+/// and the watchdog restart the node the way it was created.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpawnNodeOptions {
+    pub skip_defaults: bool,
+    pub no_shared_identity: bool,
+    pub credential_checks: CredentialChecksArg,
+    pub credential_grace_period: Option<Duration>,
+    pub prefetch_credential: bool,
+    pub advertised_address: Option<String>,
+    pub project: Option<PathBuf>,
+    pub launch_config: Option<PathBuf>,
+    pub services: Vec<ServiceSpec>,
+    pub no_api: bool,
+    /// Given to the node through [`PRE_SHARED_KEY_ENV`], never persisted
+    #[serde(skip)]
+    pub pre_shared_key: Option<String>,
+    pub bootstrap_peers: Vec<MultiAddr>,
+    pub require_bootstrap: bool,
+    pub probe_address: Option<SocketAddr>,
+    pub metrics_address: Option<SocketAddr>,
+    pub require_metrics: bool,
+    pub max_workers: Option<usize>,
+    pub memory_hint: Option<usize>,
+    pub storage_max_entries: Option<usize>,
+    pub storage_max_bytes: Option<usize>,
+    pub uds_listener_path: Option<PathBuf>,
+    pub readonly_identity: bool,
+    pub identity_from_vault: Option<String>,
+    pub log_level: Option<String>,
+}
+
+impl Default for SpawnNodeOptions {
+    fn default() -> Self {
+        Self {
+            skip_defaults: false,
+            no_shared_identity: false,
+            credential_checks: CredentialChecksArg::Off,
+            credential_grace_period: None,
+            prefetch_credential: false,
+            advertised_address: None,
+            project: None,
+            launch_config: None,
+            services: vec![],
+            no_api: false,
+            pre_shared_key: None,
+            bootstrap_peers: vec![],
+            require_bootstrap: false,
+            probe_address: None,
+            metrics_address: None,
+            require_metrics: false,
+            max_workers: None,
+            memory_hint: None,
+            storage_max_entries: None,
+            storage_max_bytes: None,
+            uds_listener_path: None,
+            readonly_identity: false,
+            identity_from_vault: None,
+            log_level: None,
+        }
+    }
+}
+
+impl SpawnNodeOptions {
+    fn path(node_dir: &Path) -> PathBuf {
+        node_dir.join("spawn_options.json")
+    }
+
+    /// Persist the options in the node directory
+    pub fn save(&self, node_dir: &Path) -> anyhow::Result<()> {
+        let path = Self::path(node_dir);
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(&path, json).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// The options persisted in the node directory, if any
+    pub fn load(node_dir: &Path) -> anyhow::Result<Option<Self>> {
+        let path = Self::path(node_dir);
+        let json = match std::fs::read_to_string(&path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        let options = serde_json::from_str(&json)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        Ok(Some(options))
+    }
+}
+
 /// A utility function to spawn a new node into foreground mode
 ///
 /// This function is used by `ockam node create` as well as `ockam
 /// node start`, which attempts to re-use an existing node config
-#[allow(clippy::too_many_arguments)]
 pub fn spawn_node(
     cfg: &OckamConfig,
     verbose: u8,
-    skip_defaults: bool,
-    no_shared_identity: bool,
-    enable_credential_checks: bool,
     name: &str,
     address: &str,
-    project: Option<&Path>,
+    options: &SpawnNodeOptions,
 ) -> crate::Result<()> {
+    let SpawnNodeOptions {
+        skip_defaults,
+        no_shared_identity,
+        credential_checks,
+        credential_grace_period,
+        prefetch_credential,
+        advertised_address,
+        project,
+        launch_config,
+        services,
+        no_api,
+        pre_shared_key,
+        bootstrap_peers,
+        require_bootstrap,
+        probe_address,
+        metrics_address,
+        require_metrics,
+        max_workers,
+        memory_hint,
+        storage_max_entries,
+        storage_max_bytes,
+        uds_listener_path,
+        readonly_identity,
+        identity_from_vault,
+        log_level,
+    } = options;
+
     // On systems with non-obvious path setups (or during
     // development) re-executing the current binary is a more
     // deterministic way of starting a node.
@@ -53,24 +178,24 @@ pub fn spawn_node(
 
     let (mlog, elog) = cfg.node_log_paths(name).unwrap();
 
-    let main_log_file = OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(mlog)
-        .context("failed to open log path")?;
-
     let stderr_log_file = OpenOptions::new()
         .create(true)
         .append(true)
         .open(elog)
         .context("failed to open stderr log path")?;
 
+    // The node writes and rotates its main log itself, anything it
+    // prints ends up next to its errors
     let mut args = vec![
         match verbose {
             0 => "-vv".to_string(),
             v => format!("-{}", "v".repeat(v as usize)),
         },
         "--no-color".to_string(),
+        "--log-file".to_string(),
+        mlog.to_str()
+            .unwrap_or_else(|| panic!("unsupported path {mlog:?}"))
+            .to_string(),
         "node".to_string(),
         "create".to_string(),
         "--tcp-listener-address".to_string(),
@@ -79,6 +204,11 @@ pub fn spawn_node(
         "--child-process".to_string(),
     ];
 
+    if let Some(advertised) = advertised_address {
+        args.push("--advertised-address".to_string());
+        args.push(advertised.to_string());
+    }
+
     if let Some(path) = project {
         args.push("--project".to_string());
         let p = path
@@ -87,25 +217,124 @@ pub fn spawn_node(
         args.push(p.to_string())
     }
 
-    if skip_defaults {
+    if let Some(path) = launch_config {
+        args.push("--launch-config".to_string());
+        let p = path
+            .to_str()
+            .unwrap_or_else(|| panic!("unsupported path {path:?}"));
+        args.push(p.to_string())
+    }
+
+    for service in services {
+        args.push("--service".to_string());
+        args.push(service.to_string());
+    }
+
+    if *skip_defaults {
         args.push("--skip-defaults".to_string());
     }
 
-    if no_shared_identity {
+    if *no_shared_identity {
         args.push("--no-shared-identity".to_string());
     }
 
-    if enable_credential_checks {
-        args.push("--enable-credential-checks".to_string());
+    if *credential_checks == CredentialChecksArg::On {
+        args.push("--credential-checks".to_string());
+        args.push("on".to_string());
+    }
+
+    if let Some(grace_period) = credential_grace_period {
+        args.push("--credential-grace-period".to_string());
+        args.push(format!("{}ms", grace_period.as_millis()));
+    }
+
+    if *prefetch_credential {
+        args.push("--prefetch-credential".to_string());
+    }
+
+    if *no_api {
+        args.push("--no-api".to_string());
+    }
+
+    for peer in bootstrap_peers {
+        args.push("--bootstrap-peer".to_string());
+        args.push(peer.to_string());
+    }
+
+    if *require_bootstrap {
+        args.push("--require-bootstrap".to_string());
+    }
+
+    if let Some(probe_address) = probe_address {
+        args.push("--probe-address".to_string());
+        args.push(probe_address.to_string());
+    }
+
+    if let Some(metrics_address) = metrics_address {
+        args.push("--metrics-address".to_string());
+        args.push(metrics_address.to_string());
+    }
+
+    if *require_metrics {
+        args.push("--require-metrics".to_string());
+    }
+
+    if let Some(max_workers) = max_workers {
+        args.push("--max-workers".to_string());
+        args.push(max_workers.to_string());
+    }
+
+    if let Some(memory_hint) = memory_hint {
+        args.push("--memory-hint".to_string());
+        args.push(memory_hint.to_string());
+    }
+
+    if let Some(max_entries) = storage_max_entries {
+        args.push("--storage-max-entries".to_string());
+        args.push(max_entries.to_string());
+    }
+
+    if let Some(max_bytes) = storage_max_bytes {
+        args.push("--storage-max-bytes".to_string());
+        args.push(max_bytes.to_string());
+    }
+
+    if let Some(path) = uds_listener_path {
+        args.push("--uds-listener-path".to_string());
+        let p = path
+            .to_str()
+            .unwrap_or_else(|| panic!("unsupported path {path:?}"));
+        args.push(p.to_string())
+    }
+
+    if *readonly_identity {
+        args.push("--readonly-identity".to_string());
+    }
+
+    if let Some(key) = identity_from_vault {
+        args.push("--identity-from-vault".to_string());
+        args.push(key.to_string());
+    }
+
+    if let Some(log_level) = log_level {
+        args.push("--log-level".to_string());
+        args.push(log_level.to_string());
     }
 
     args.push(name.to_owned());
 
-    let child = Command::new(ockam_exe)
+    let mut command = Command::new(ockam_exe);
+    command
         .args(args)
-        .stdout(main_log_file)
-        .stderr(stderr_log_file)
-        .spawn()?;
+        .stdout(stderr_log_file.try_clone()?)
+        .stderr(stderr_log_file);
+
+    // Keep the key out of the child's command line
+    if let Some(key) = pre_shared_key {
+        command.env(PRE_SHARED_KEY_ENV, key);
+    }
+
+    let child = command.spawn()?;
 
     // Update the pid in the config (should we remove this?)
     cfg.set_node_pid(name, child.id() as i32)?;
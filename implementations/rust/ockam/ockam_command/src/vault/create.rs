@@ -1,11 +1,23 @@
 use crate::node::NodeOpts;
-use crate::util::{node_rpc, Rpc};
+use crate::util::{exitcode, node_rpc, Rpc};
 use crate::CommandGlobalOpts;
-use clap::Args;
+use anyhow::anyhow;
+use clap::{Args, ValueEnum};
 use ockam::Context;
-use ockam_api::nodes::models::vault::CreateVaultRequest;
+use ockam_api::nodes::models::vault::{CreateVaultRequest, VaultBackend};
 use ockam_core::api::Request;
 
+/// Where the vault keeps its secrets
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum VaultType {
+    /// A file on the node's machine
+    Software,
+    /// A hardware security module, through its PKCS#11 library
+    Pkcs11,
+    /// The AWS Key Management Service
+    AwsKms,
+}
+
 /// Create vaults
 #[derive(Clone, Debug, Args)]
 pub struct CreateCommand {
@@ -15,24 +27,139 @@ pub struct CreateCommand {
     /// Path to the Vault storage file
     #[arg(short, long)]
     pub path: Option<String>,
+
+    /// Record the vault under this name, for `identity create --vault`,
+    /// rather than making it the node's vault
+    #[arg(long)]
+    pub name: Option<String>,
+
+    /// Where the vault keeps its secrets
+    #[arg(long = "type", value_enum, default_value_t = VaultType::Software)]
+    pub vault_type: VaultType,
+
+    /// Path to the PKCS#11 library of the HSM, for pkcs11 vaults
+    #[arg(long, value_name = "PATH")]
+    pub pkcs11_module: Option<String>,
+
+    /// HSM slot holding the keys, for pkcs11 vaults
+    #[arg(long, value_name = "SLOT")]
+    pub pkcs11_slot: Option<u64>,
+
+    /// AWS region of the keys, for aws-kms vaults
+    #[arg(long, value_name = "REGION")]
+    pub aws_region: Option<String>,
 }
 
 impl CreateCommand {
     pub fn run(self, options: CommandGlobalOpts) {
         node_rpc(run_impl, (options, self))
     }
+
+    /// Build the vault backend, checking that only its parameters were given
+    fn backend(&self) -> crate::Result<VaultBackend> {
+        let usage = |msg: &str| crate::Error::new(exitcode::USAGE, anyhow!("{}", msg));
+        let has_pkcs11 = self.pkcs11_module.is_some() || self.pkcs11_slot.is_some();
+        let has_aws = self.aws_region.is_some();
+
+        match self.vault_type {
+            VaultType::Software => {
+                if has_pkcs11 || has_aws {
+                    return Err(usage("Software vaults don't take HSM or KMS parameters"));
+                }
+                Ok(VaultBackend::Software)
+            }
+            VaultType::Pkcs11 => {
+                if self.path.is_some() || has_aws {
+                    return Err(usage("pkcs11 vaults only take --pkcs11-* parameters"));
+                }
+                match (&self.pkcs11_module, self.pkcs11_slot) {
+                    (Some(module), Some(slot)) => Ok(VaultBackend::Pkcs11 {
+                        module: module.clone(),
+                        slot,
+                    }),
+                    _ => Err(usage(
+                        "pkcs11 vaults require --pkcs11-module and --pkcs11-slot",
+                    )),
+                }
+            }
+            VaultType::AwsKms => {
+                if self.path.is_some() || has_pkcs11 {
+                    return Err(usage("aws-kms vaults only take --aws-* parameters"));
+                }
+                match &self.aws_region {
+                    Some(region) => Ok(VaultBackend::AwsKms {
+                        region: region.clone(),
+                    }),
+                    None => Err(usage("aws-kms vaults require --aws-region")),
+                }
+            }
+        }
+    }
 }
 
 async fn run_impl(
     ctx: Context,
     (options, cmd): (CommandGlobalOpts, CreateCommand),
 ) -> crate::Result<()> {
+    let backend = cmd.backend()?;
     let mut rpc = Rpc::background(&ctx, &options, &cmd.node_opts.api_node)?;
-    let request = Request::post("/node/vault").body(CreateVaultRequest::new(cmd.path));
+    let mut body = CreateVaultRequest::new(cmd.path).with_backend(backend.clone());
+    if let Some(name) = cmd.name {
+        body = body.with_name(name);
+    }
+    let request = Request::post("/node/vault").body(body);
 
     rpc.request(request).await?;
     rpc.parse_response()?;
 
-    println!("Vault created!");
+    println!("Vault created! ({})", backend);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        create: CreateCommand,
+    }
+
+    fn backend(args: &[&str]) -> crate::Result<VaultBackend> {
+        let args = std::iter::once("create").chain(args.iter().copied());
+        Cli::try_parse_from(args).unwrap().create.backend()
+    }
+
+    #[test]
+    fn backend_parameters_are_validated() {
+        assert_eq!(backend(&[]).unwrap(), VaultBackend::Software);
+        assert!(backend(&["--aws-region", "eu-west-1"]).is_err());
+
+        assert_eq!(
+            backend(&[
+                "--type",
+                "pkcs11",
+                "--pkcs11-module",
+                "/usr/lib/softhsm/libsofthsm2.so",
+                "--pkcs11-slot",
+                "0"
+            ])
+            .unwrap(),
+            VaultBackend::Pkcs11 {
+                module: "/usr/lib/softhsm/libsofthsm2.so".to_string(),
+                slot: 0
+            }
+        );
+        assert!(backend(&["--type", "pkcs11", "--pkcs11-slot", "0"]).is_err());
+
+        assert_eq!(
+            backend(&["--type", "aws-kms", "--aws-region", "eu-west-1"]).unwrap(),
+            VaultBackend::AwsKms {
+                region: "eu-west-1".to_string()
+            }
+        );
+        assert!(backend(&["--type", "aws-kms", "--path", "vault.json"]).is_err());
+    }
+}
@@ -1,6 +1,8 @@
 mod create;
+mod rotate;
 
 pub(crate) use create::CreateCommand;
+pub(crate) use rotate::RotateCommand;
 
 use crate::help;
 use crate::CommandGlobalOpts;
@@ -16,12 +18,14 @@ pub struct VaultCommand {
 #[derive(Clone, Debug, Subcommand)]
 pub enum VaultSubcommand {
     Create(CreateCommand),
+    Rotate(RotateCommand),
 }
 
 impl VaultCommand {
     pub fn run(self, options: CommandGlobalOpts) {
         match self.subcommand {
             VaultSubcommand::Create(c) => c.run(options),
+            VaultSubcommand::Rotate(c) => c.run(options),
         }
     }
 }
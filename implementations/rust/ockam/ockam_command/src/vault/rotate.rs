@@ -0,0 +1,38 @@
+use crate::node::NodeOpts;
+use crate::util::{node_rpc, Rpc};
+use crate::CommandGlobalOpts;
+use clap::Args;
+use ockam::Context;
+use ockam_core::api::Request;
+
+/// Encrypt the node's storage with a new key
+///
+/// The values are re-encrypted in the background, and stay readable
+/// meanwhile. A node stopped before the rotation completed resumes it when
+/// it starts again. The previous key is then forgotten.
+#[derive(Clone, Debug, Args)]
+pub struct RotateCommand {
+    #[command(flatten)]
+    node_opts: NodeOpts,
+}
+
+impl RotateCommand {
+    pub fn run(self, options: CommandGlobalOpts) {
+        node_rpc(run_impl, (options, self))
+    }
+}
+
+async fn run_impl(
+    ctx: Context,
+    (options, cmd): (CommandGlobalOpts, RotateCommand),
+) -> crate::Result<()> {
+    let mut rpc = Rpc::background(&ctx, &options, &cmd.node_opts.api_node)?;
+    rpc.request(Request::post("/node/vault/rotate")).await?;
+    rpc.parse_response()?;
+
+    println!(
+        "Rotating the storage key of node {} in the background",
+        cmd.node_opts.api_node
+    );
+    Ok(())
+}
@@ -11,6 +11,75 @@ fn valid_arguments() -> Result<(), Box<dyn std::error::Error>> {
         .arg("node-name");
     cmd.assert().success();
 
+    // create node without the API success
+    let mut cmd = Command::cargo_bin("ockam")?;
+    cmd.arg("--test-argument-parser")
+        .arg("node")
+        .arg("create")
+        .arg("node-name")
+        .arg("--no-api");
+    cmd.assert().success();
+
+    // a node without the API can't run a launch config
+    let mut cmd = Command::cargo_bin("ockam")?;
+    cmd.arg("--test-argument-parser")
+        .arg("node")
+        .arg("create")
+        .arg("node-name")
+        .arg("--no-api")
+        .arg("--launch-config")
+        .arg("config.json");
+    cmd.assert().failure();
+
+    // foreground node exiting when idle success
+    let mut cmd = Command::cargo_bin("ockam")?;
+    cmd.arg("--test-argument-parser")
+        .arg("node")
+        .arg("create")
+        .arg("node-name")
+        .arg("--foreground")
+        .arg("--exit-on-idle")
+        .arg("30s");
+    cmd.assert().success();
+
+    // only foreground nodes can exit when idle
+    let mut cmd = Command::cargo_bin("ockam")?;
+    cmd.arg("--test-argument-parser")
+        .arg("node")
+        .arg("create")
+        .arg("node-name")
+        .arg("--exit-on-idle")
+        .arg("30s");
+    cmd.assert().failure();
+
+    // idle duration must have a known unit
+    let mut cmd = Command::cargo_bin("ockam")?;
+    cmd.arg("--test-argument-parser")
+        .arg("node")
+        .arg("create")
+        .arg("node-name")
+        .arg("--foreground")
+        .arg("--exit-on-idle")
+        .arg("30d");
+    cmd.assert().failure();
+
+    // list node workers success
+    let mut cmd = Command::cargo_bin("ockam")?;
+    cmd.arg("--test-argument-parser")
+        .arg("node")
+        .arg("workers")
+        .arg("node-name");
+    cmd.assert().success();
+
+    // node metrics success
+    let mut cmd = Command::cargo_bin("ockam")?;
+    cmd.arg("--test-argument-parser")
+        .arg("node")
+        .arg("metrics")
+        .arg("node-name")
+        .arg("--reset");
+    cmd.assert().success();
+
     // delete node success
     let mut cmd = Command::cargo_bin("ockam")?;
     cmd.arg("--test-argument-parser")
@@ -30,5 +30,16 @@ fn valid_arguments() -> Result<(), Box<dyn std::error::Error>> {
         .arg("project-id");
     cmd.assert().success();
 
+    let mut cmd = Command::cargo_bin("ockam")?;
+    cmd.args(&prefix_args).arg("import").arg("project.json");
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("ockam")?;
+    cmd.args(&prefix_args)
+        .arg("export")
+        .arg("project-name")
+        .arg("project.json");
+    cmd.assert().success();
+
     Ok(())
 }
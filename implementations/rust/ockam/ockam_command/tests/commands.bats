@@ -84,6 +84,92 @@ teardown() {
   assert_output --regexp '^P'
 }
 
+@test "delete the default identity" {
+  # In use by a running node
+  $OCKAM node create n1
+  run $OCKAM identity delete default
+  assert_failure 69
+  assert_output --partial "n1"
+
+  # Unused once the node is stopped
+  $OCKAM node stop n1
+  run $OCKAM identity delete default
+  assert_success
+  assert_output --partial "Deleted identity 'default'"
+  assert_output --partial "secret(s) from the vault"
+
+  run $OCKAM identity delete default
+  assert_failure 66
+}
+
+@test "rotate the default identity" {
+  $OCKAM node create n1
+  run $OCKAM identity rotate default
+  assert_success
+  assert_output --partial "its history has 2 changes"
+
+  run $OCKAM identity rotate default
+  assert_success
+  assert_output --partial "its history has 3 changes"
+
+  run $OCKAM identity rotate other
+  assert_failure 66
+}
+
+@test "export an identity and trust it from another configuration" {
+  $OCKAM node create n1
+  n1_id=$($OCKAM identity show --node n1)
+  run $OCKAM identity export default "$BATS_TMPDIR/n1.identity"
+  assert_success
+
+  # The other configuration gets a copy of the identity which can only be verified
+  peer_config="$BATS_TMPDIR/peer_config"
+  rm -rf "$peer_config"
+  run --separate-stderr env OCKAM_PROJECT_PATH="$peer_config" $OCKAM identity import n1 "$BATS_TMPDIR/n1.identity"
+  assert_success
+  assert_output "$n1_id"
+  run env OCKAM_PROJECT_PATH="$peer_config" $OCKAM identity import n1 "$BATS_TMPDIR/n1.identity"
+  assert_failure 73
+  run env OCKAM_PROJECT_PATH="$peer_config" $OCKAM identity export n1 "$BATS_TMPDIR/n1.reexported"
+  assert_success
+  run cmp "$BATS_TMPDIR/n1.identity" "$BATS_TMPDIR/n1.reexported"
+  assert_success
+
+  # Its identifier is trusted by a listener of the other configuration
+  port=$(shuf -i 10000-30000 -n 1)
+  OCKAM_PROJECT_PATH="$peer_config" $OCKAM node create m1 --tcp-listener-address "127.0.0.1:$port"
+  run env OCKAM_PROJECT_PATH="$peer_config" $OCKAM secure-channel-listener create "ids_listener" --at /node/m1 \
+    --trust-policy identifiers --authorized-identifiers "$n1_id"
+  assert_success
+  output=$($OCKAM secure-channel create --from /node/n1 --to "/ip4/127.0.0.1/tcp/$port/service/ids_listener" | \
+    $OCKAM message send hello --from /node/n1 --to -/service/uppercase)
+  OCKAM_PROJECT_PATH="$peer_config" $OCKAM node delete --all
+  assert [ "$output" == "HELLO" ]
+
+  # A corrupted change history is rejected
+  echo "0123abcd" > "$BATS_TMPDIR/invalid.identity"
+  run env OCKAM_PROJECT_PATH="$peer_config" $OCKAM identity import invalid "$BATS_TMPDIR/invalid.identity"
+  assert_failure 65
+}
+
+@test "show the status of every local node" {
+  $OCKAM node create n1
+  $OCKAM node create n2
+  $OCKAM secure-channel create --from /node/n1 --to /node/n2/service/api
+  $OCKAM node stop n2
+
+  run $OCKAM status --output json
+  assert_success
+  assert_equal "$(echo "$output" | jq -r '.nodes[] | select(.name == "n1") | .state')" "UP"
+  assert_equal "$(echo "$output" | jq -r '.nodes[] | select(.name == "n1") | .secure_channels')" "1"
+  assert_equal "$(echo "$output" | jq -r '.nodes[] | select(.name == "n2") | .state')" "DOWN"
+  assert_equal "$(echo "$output" | jq -r '.nodes[] | select(.name == "n2") | .identity')" "null"
+
+  run $OCKAM status
+  assert_success
+  assert_output --partial "DOWN (unreachable)"
+}
+
 @test "create a node with a name and do show on it" {
   run $OCKAM node create n1
   assert_success
@@ -95,6 +181,209 @@ teardown() {
   assert_output --partial "/service/uppercase"
 }
 
+@test "check that the listeners of a node are reachable" {
+  port=$(shuf -i 10000-30000 -n 1)
+  run $OCKAM node create n1 --tcp-listener-address "127.0.0.1:$port"
+  assert_success
+
+  run $OCKAM node show n1
+  assert_success
+  refute_output --partial "Reachable:"
+
+  run $OCKAM node show n1 --check
+  assert_success
+  assert_output --partial "127.0.0.1:$port"
+  assert_output --partial "Reachable: yes"
+  refute_output --partial "Reachable: no"
+}
+
+@test "create a node with a per-crate log level" {
+  run $OCKAM node create n1 --log-level "ockam_transport_udp=loud"
+  assert_failure
+
+  run $OCKAM node create n1 --log-level "info,ockam_transport_udp=trace"
+  assert_success
+  run $OCKAM node show n1
+  assert_success
+}
+
+@test "create a node listening on a hostname" {
+  port=$(shuf -i 10000-30000 -n 1)
+  run $OCKAM node create n1 --tcp-listener-address "localhost:$port"
+  assert_success
+
+  run $OCKAM node show n1
+  assert_success
+  assert_output --partial "/tcp/$port"
+
+  run --separate-stderr $OCKAM message send hello --to "/ip4/127.0.0.1/tcp/$port/service/uppercase"
+  assert_success
+  assert_output "HELLO"
+
+  run $OCKAM node create n2 --tcp-listener-address "unknown.invalid:4000"
+  assert_failure
+}
+
+@test "send a message through a secure channel with the route sugar" {
+  port=$(shuf -i 10000-30000 -n 1)
+  run $OCKAM node create n1 --tcp-listener-address "127.0.0.1:$port"
+  assert_success
+
+  run --separate-stderr $OCKAM message send hello --to "tcp://127.0.0.1:$port/secure/service/uppercase"
+  assert_success
+  assert_output "HELLO"
+
+  run --separate-stderr $OCKAM message send hello --to "quic://127.0.0.1:$port/service/uppercase"
+  assert_failure
+
+  run --separate-stderr $OCKAM message send hello --to uppercase
+  assert_failure
+}
+
+@test "refuse to create a node whose name is taken" {
+  run $OCKAM node create n1 --tcp-listener-address 127.0.0.1:6001
+  assert_success
+
+  run $OCKAM node create n1
+  assert_failure 73
+  assert_output --partial "Node n1 already exists"
+
+  # The existing node is left as it was
+  run $OCKAM node show n1
+  assert_success
+  assert_output --partial "/tcp/6001"
+
+  run $OCKAM node create n1 --overwrite --tcp-listener-address 127.0.0.1:6002
+  assert_success
+  run $OCKAM node show n1
+  assert_success
+  assert_output --partial "/tcp/6002"
+}
+
+@test "restart a node killed while a watchdog watches it" {
+  run $OCKAM node create n1 --watchdog-interval 0
+  assert_failure
+
+  run $OCKAM node create n1 --watchdog-interval 1
+  assert_success
+  pid=$(pgrep -f -- "--child-process.* n1$")
+  kill -9 "$pid"
+
+  # The watchdog spawns a new node process
+  new_pid=""
+  for _ in $(seq 1 20); do
+    new_pid=$(pgrep -f -- "--child-process.* n1$" || true)
+    if [ -n "$new_pid" ] && [ "$new_pid" != "$pid" ]; then
+      break
+    fi
+    sleep 0.5
+  done
+  assert [ -n "$new_pid" ]
+  assert [ "$new_pid" != "$pid" ]
+
+  run $OCKAM node show n1
+  assert_success
+  assert_output --partial "/service/api"
+
+  # Stopping the node stops its watchdog too
+  $OCKAM node stop n1
+  sleep 2
+  run pgrep -f -- "--child-process.* n1$"
+  assert_failure
+}
+
+@test "stop restarting a crashing node after the maximum number of restarts" {
+  run $OCKAM node create n1 --restart-policy on-failure
+  assert_failure
+
+  run $OCKAM node create n1 --watchdog-interval 1 --restart-policy on-failure --max-restarts 1
+  assert_success
+  pid=$(pgrep -f -- "--child-process.* n1$")
+  kill -9 "$pid"
+
+  # The watchdog restarts the node once
+  new_pid=""
+  for _ in $(seq 1 20); do
+    new_pid=$(pgrep -f -- "--child-process.* n1$" || true)
+    if [ -n "$new_pid" ] && [ "$new_pid" != "$pid" ]; then
+      break
+    fi
+    sleep 0.5
+  done
+  assert [ -n "$new_pid" ]
+  assert [ "$new_pid" != "$pid" ]
+
+  # Then gives up, and exits without restarting the node
+  kill -9 "$new_pid"
+  for _ in $(seq 1 20); do
+    if ! pgrep -f -- "watchdog --interval.* n1$" >/dev/null; then
+      break
+    fi
+    sleep 0.5
+  done
+  run pgrep -f -- "watchdog --interval.* n1$"
+  assert_failure
+  run pgrep -f -- "--child-process.* n1$"
+  assert_failure
+}
+
+@test "create a secure channel and list the workers of the node" {
+  $OCKAM node create n1
+  $OCKAM node create n2
+
+  output=$($OCKAM secure-channel create --from /node/n1 --to /node/n2/service/api)
+  encryptor=${output#/service/}
+
+  run $OCKAM node workers n1
+  assert_success
+  assert_output --partial "$encryptor"
+
+  run $OCKAM node workers n1 --output json
+  assert_success
+  assert_output --partial "\"0#$encryptor\""
+}
+
+@test "report errors as json with --output json" {
+  run $OCKAM node show unknown
+  assert_failure 74
+  assert_output --partial "No such node available"
+
+  run --separate-stderr $OCKAM node show unknown --output json
+  assert_failure 74
+  assert_equal "$(echo "$stderr" | jq -r '.code')" "74"
+  assert_equal "$(echo "$stderr" | jq -r '.error.message')" \
+    "No such node available.  Run \`ockam node list\` to list available nodes"
+}
+
+@test "show and reset the metrics of a node" {
+  $OCKAM node create n1
+  $OCKAM node create n2
+  $OCKAM message send hello --from /node/n1 --to /node/n2/service/uppercase
+
+  run $OCKAM node metrics n1
+  assert_success
+  assert_output --partial "TCP messages sent"
+
+  # The requests of the commands go through n1's transport too, so only
+  # compare the counters with each other
+  run $OCKAM node metrics n1 --reset --output json
+  assert_success
+  before_reset=$(echo "$output" | jq -r '.tcp_messages_sent')
+  [ "$before_reset" -gt 0 ]
+
+  run $OCKAM node metrics n1 --output json
+  assert_success
+  after_reset=$(echo "$output" | jq -r '.tcp_messages_sent')
+  [ "$after_reset" -lt "$before_reset" ]
+
+  $OCKAM message send hello --from /node/n1 --to /node/n2/service/uppercase
+  run $OCKAM node metrics n1 --output json
+  assert_success
+  later=$(echo "$output" | jq -r '.tcp_messages_sent')
+  # The message to n2 and the replies to both commands
+  [ $((later - after_reset)) -ge 2 ]
+}
+
 @test "create a node with a name and send it a message" {
   $OCKAM node create n1
   run --separate-stderr $OCKAM message send "hello" --to /node/n1/service/uppercase
@@ -103,6 +392,81 @@ teardown() {
   assert_output "HELLO"
 }
 
+@test "send a binary file and hex encoded bytes to a node" {
+  $OCKAM node create n1
+  printf '\x00\xff\xfe\x80ockam\x01' >payload.bin
+
+  run --separate-stderr $OCKAM message send --file payload.bin --to /node/n1/service/echo --output reply.bin
+  assert_success
+  run cmp payload.bin reply.bin
+  assert_success
+
+  run --separate-stderr $OCKAM message send --hex 68656c6c6f --to /node/n1/service/echo
+  assert_success
+  assert_output "hello"
+
+  # Only one input can be given
+  run --separate-stderr $OCKAM message send hello --hex 68656c6c6f --to /node/n1/service/echo
+  assert_failure
+  run --separate-stderr $OCKAM message send --file payload.bin --hex 00 --to /node/n1/service/echo
+  assert_failure
+}
+
+@test "create a node without the API" {
+  run $OCKAM node create n1 --no-api
+  assert_success
+  assert_output --partial "NO API"
+
+  # The node manager isn't listening
+  run --separate-stderr $OCKAM message send "hello" --to /node/n1/service/_internal.nodemanager --timeout 2
+  assert_failure
+
+  # Commands that need the API report it
+  run $OCKAM tcp-listener list --node n1
+  assert_failure
+  assert_output --partial "--no-api"
+}
+
+@test "time out waiting for a node which doesn't respond" {
+  # The node manager of n1 doesn't exist, so the request is never answered
+  $OCKAM node create n1 --no-api
+
+  run timeout 20 $OCKAM tcp-connection list --node n1 --timeout 2s
+  assert_failure 75
+  assert_output --partial "No response from"
+  assert_output --partial "use --timeout to wait longer"
+}
+
+@test "create a foreground node that exits when idle" {
+  run timeout 30 $OCKAM node create n1 --foreground --exit-on-idle 2s
+  assert_success
+}
+
+@test "stop a foreground node cleanly on SIGINT" {
+  $OCKAM node create n1 --foreground -vv --tcp-listener-address 127.0.0.1:6003 >"$BATS_TMPDIR/n1.log" 2>&1 &
+  pid=$!
+  for _ in $(seq 1 20); do
+    if $OCKAM node show n1 >/dev/null 2>&1; then
+      break
+    fi
+    sleep 0.5
+  done
+  run $OCKAM node show n1
+  assert_success
+
+  kill -INT "$pid"
+  code=0
+  wait "$pid" || code=$?
+  assert_equal "$code" 130
+  run cat "$BATS_TMPDIR/n1.log"
+  assert_output --partial "Received SIGINT, stopping the node"
+  assert_output --partial "Node stopped"
+
+  # The listener was closed
+  run bash -c "echo > /dev/tcp/127.0.0.1/6003"
+  assert_failure
+}
+
 @test "create two nodes and send message from one to the other" {
   $OCKAM node create n1
   $OCKAM node create n2
@@ -123,6 +487,229 @@ teardown() {
   assert_output "HELLO"
 }
 
+@test "import and export a project" {
+  run $OCKAM node create n1
+  assert_success
+  authority_id=$($OCKAM identity show -n n1)
+  authority_identity=$($OCKAM identity show -n n1 --full)
+
+  cat > "$BATS_TMPDIR/project.json" <<EOF
+{
+  "id": "c5ad3e40-0f73-4f3b-8ed7-20f4a6e0e3f1",
+  "name": "imported",
+  "identity": "$authority_id",
+  "access_route": "/dnsaddr/127.0.0.1/tcp/4000/service/api",
+  "authority_access_route": "/dnsaddr/127.0.0.1/tcp/4001/service/authority",
+  "authority_identity": "$authority_identity",
+  "okta_config": null
+}
+EOF
+  run $OCKAM project import "$BATS_TMPDIR/project.json"
+  assert_success
+
+  run $OCKAM project export imported "$BATS_TMPDIR/exported.json"
+  assert_success
+  run cat "$BATS_TMPDIR/exported.json"
+  assert_output --partial "$authority_identity"
+  assert_output --partial "/dnsaddr/127.0.0.1/tcp/4001/service/authority"
+
+  # The exported file imports back to the same project
+  run $OCKAM project import "$BATS_TMPDIR/exported.json"
+  assert_success
+  run $OCKAM project export imported "$BATS_TMPDIR/reexported.json"
+  assert_success
+  run diff "$BATS_TMPDIR/exported.json" "$BATS_TMPDIR/reexported.json"
+  assert_success
+}
+
+@test "import an invalid project" {
+  echo '{"id": "c5ad3e40", "name": "invalid", "access_route": "/dnsaddr/127.0.0.1/tcp/4000/service/api"}' > "$BATS_TMPDIR/project.json"
+  run $OCKAM project import "$BATS_TMPDIR/project.json"
+  assert_failure
+}
+
+@test "create a background node with a launch config" {
+  echo '{"startup_services": {"vault": {"address": "launched_vault"}, "verifier": {"address": "launched_verifier"}}}' > "$BATS_TMPDIR/launch_config.json"
+  run $OCKAM node create n1 --launch-config "$BATS_TMPDIR/launch_config.json"
+  assert_success
+
+  # Services are started asynchronously
+  for i in {1..20}; do
+    if $OCKAM node show n1 | grep -q "/service/launched_verifier"; then
+      break
+    fi
+    sleep 0.5
+  done
+  run $OCKAM node show n1
+  assert_success
+  assert_output --partial "/service/launched_vault"
+  assert_output --partial "/service/launched_verifier"
+}
+
+@test "create a node with an inline service" {
+  run $OCKAM node create n1 --service identity=inline_identity
+  assert_success
+
+  for i in {1..20}; do
+    if $OCKAM node show n1 | grep -q "/service/inline_identity"; then
+      break
+    fi
+    sleep 0.5
+  done
+  run $OCKAM node show n1
+  assert_success
+  assert_output --partial "/service/inline_identity"
+}
+
+@test "create a node with health probes" {
+  port=$(shuf -i 10000-30000 -n 1)
+  run $OCKAM node create n1 --probe-address 127.0.0.1:$port --service verifier
+  assert_success
+
+  # The probe is served as soon as the node process is up
+  for i in {1..20}; do
+    if curl --fail --silent 127.0.0.1:$port/livez; then
+      break
+    fi
+    sleep 0.5
+  done
+  run curl --fail --silent 127.0.0.1:$port/livez
+  assert_success
+
+  # The node is ready once its services are started
+  for i in {1..20}; do
+    if curl --fail --silent 127.0.0.1:$port/readyz; then
+      break
+    fi
+    sleep 0.5
+  done
+  run curl --fail --silent 127.0.0.1:$port/readyz
+  assert_success
+  assert_output "200 OK"
+
+  run $OCKAM node stop n1
+  assert_success
+  for i in {1..20}; do
+    if ! curl --fail --silent 127.0.0.1:$port/livez; then
+      break
+    fi
+    sleep 0.5
+  done
+  run curl --fail --silent 127.0.0.1:$port/livez
+  assert_failure
+}
+
+@test "create a node serving metrics" {
+  port=$(shuf -i 10000-30000 -n 1)
+  run $OCKAM node create n1 --metrics-address 127.0.0.1:$port
+  assert_success
+
+  for i in {1..20}; do
+    if curl --fail --silent 127.0.0.1:$port/metrics; then
+      break
+    fi
+    sleep 0.5
+  done
+  run curl --fail --silent 127.0.0.1:$port/metrics
+  assert_success
+  assert_output --partial "# TYPE ockam_tcp_active_connections gauge"
+  assert_output --partial "ockam_tcp_idle_seconds"
+  assert_output --partial "# TYPE ockam_tcp_messages_sent_total counter"
+  assert_output --partial "ockam_tcp_received_bytes_total"
+
+  run $OCKAM node stop n1
+  assert_success
+  for i in {1..20}; do
+    if ! curl --fail --silent 127.0.0.1:$port/metrics; then
+      break
+    fi
+    sleep 0.5
+  done
+  run curl --fail --silent 127.0.0.1:$port/metrics
+  assert_failure
+}
+
+@test "fail to create a node requiring metrics on a taken address" {
+  port=$(shuf -i 10000-30000 -n 1)
+  run $OCKAM node create n1 --metrics-address 127.0.0.1:$port
+  assert_success
+  for i in {1..20}; do
+    if curl --fail --silent 127.0.0.1:$port/metrics; then
+      break
+    fi
+    sleep 0.5
+  done
+
+  # n1 already serves its metrics on the port, so n2 can't start
+  run timeout 30 $OCKAM node create n2 --foreground --metrics-address 127.0.0.1:$port --require-metrics
+  assert_failure 69
+}
+
+@test "create a node listening on a unix domain socket" {
+  socket="$BATS_TMPDIR/n1.sock"
+  run $OCKAM node create n1 --uds-listener-path "$socket"
+  assert_success
+
+  for i in {1..20}; do
+    if [ -S "$socket" ]; then
+      break
+    fi
+    sleep 0.5
+  done
+  run test -S "$socket"
+  assert_success
+
+  # Another node connects to the socket, and sends n1 a message over it
+  run $OCKAM node create n2 --uds-listener-path "$BATS_TMPDIR/n2.sock"
+  assert_success
+  run $OCKAM tcp-connection create --from n2 --to "$socket" --uds --output json
+  assert_success
+  connection=$(echo "$output" | jq -r '.[0].route')
+  run $OCKAM message send hello --from /node/n2 --to "$connection/service/uppercase"
+  assert_success
+  assert_output "HELLO"
+
+  run $OCKAM node delete n1
+  assert_success
+  sleep 1
+  run test -e "$socket"
+  assert_failure
+}
+
+@test "create a node with an invalid inline service" {
+  run $OCKAM node create n1 --service identities
+  assert_failure
+  assert_output --partial "unknown service 'identities'"
+
+  run $OCKAM node create n1 --service vault --service vault=other_vault
+  assert_failure
+  assert_output --partial "given more than once"
+}
+
+@test "create a background node with an env file referenced by its launch config" {
+  echo 'VERIFIER_ADDR=env_verifier' > "$BATS_TMPDIR/node.env"
+  echo '{"startup_services": {"verifier": {"address": "${VERIFIER_ADDR}"}}}' > "$BATS_TMPDIR/launch_config.json"
+  run $OCKAM node create n1 --env-file "$BATS_TMPDIR/node.env" --launch-config "$BATS_TMPDIR/launch_config.json"
+  assert_success
+
+  for i in {1..20}; do
+    if $OCKAM node show n1 | grep -q "/service/env_verifier"; then
+      break
+    fi
+    sleep 0.5
+  done
+  run $OCKAM node show n1
+  assert_success
+  assert_output --partial "/service/env_verifier"
+}
+
+@test "create a node with a malformed env file" {
+  echo 'not a variable' > "$BATS_TMPDIR/node.env"
+  run $OCKAM node create n1 --env-file "$BATS_TMPDIR/node.env"
+  assert_failure
+  assert_output --partial "line 1: expected KEY=VALUE"
+}
+
 @test "create node with a startup command, stop it and restart it" {
   echo '{"on_node_startup": ["secure-channel create --from /node/n1 --to /node/n2/service/api"]}' > "$BATS_TMPDIR/configuration.json"
   $OCKAM node create n2
@@ -136,6 +723,87 @@ teardown() {
   assert_output --partial "/service/"
 }
 
+@test "run a script creating nodes and a secure channel" {
+  cat > "$BATS_TMPDIR/script.json" <<'EOF'
+{
+  "variables": { "PEER": "n2" },
+  "commands": [
+    "node create n1",
+    "node create ${PEER}",
+    "secure-channel create --from /node/n1 --to /node/${PEER}/service/api"
+  ]
+}
+EOF
+  run $OCKAM run "$BATS_TMPDIR/script.json"
+  assert_success
+
+  run $OCKAM node show n2
+  assert_success
+  run $OCKAM secure-channel list --at n1
+  assert_success
+  assert_output --partial "/service/"
+
+  # The failing step is reported and the script stops there
+  echo '{"commands": ["node show unknown", "node create n3"]}' > "$BATS_TMPDIR/script.json"
+  run $OCKAM run "$BATS_TMPDIR/script.json"
+  assert_failure
+  assert_output --partial 'Step #1 `node show unknown` failed'
+  run $OCKAM node show n3
+  assert_failure
+}
+
+@test "list the secure channels running over a tcp connection" {
+  port=$(shuf -i 10000-30000 -n 1)
+  $OCKAM node create n1
+  $OCKAM node create n2 --tcp-listener-address "127.0.0.1:$port"
+
+  $OCKAM tcp-connection create --from n1 --to "127.0.0.1:$port"
+  channel=$($OCKAM secure-channel create --from /node/n1 --to "/ip4/127.0.0.1/tcp/$port/service/api")
+
+  run --separate-stderr $OCKAM tcp-connection list --node n1 --output json
+  assert_success
+  assert_equal "$(echo "$output" | jq -r '.[0].address')" "127.0.0.1:$port"
+  assert_equal "/service/$(echo "$output" | jq -r '.[0].secure_channels[0].address')" "$channel"
+}
+
+@test "create a tcp connection and list it" {
+  port=$(shuf -i 10000-30000 -n 1)
+  $OCKAM node create n1
+  $OCKAM node create n2 --tcp-listener-address "127.0.0.1:$port"
+
+  tid=$($OCKAM tcp-connection create --from n1 --to "127.0.0.1:$port")
+  run $OCKAM tcp-connection list --node n1
+  assert_success
+  assert_output --partial "$tid"
+  assert_output --partial "127.0.0.1:$port"
+
+  # Dial failures are reported
+  run $OCKAM tcp-connection create --from n1 --to "127.0.0.1:1"
+  assert_failure 69
+}
+
+@test "show the details of a tcp connection" {
+  port=$(shuf -i 10000-30000 -n 1)
+  $OCKAM node create n1
+  $OCKAM node create n2 --tcp-listener-address "127.0.0.1:$port"
+
+  tid=$($OCKAM tcp-connection create --from n1 --to "127.0.0.1:$port")
+  run --separate-stderr $OCKAM tcp-connection show --node n1 "$tid" --output json
+  assert_success
+  assert_equal "$(echo "$output" | jq -r '.tid')" "$tid"
+  assert_equal "$(echo "$output" | jq -r '.address')" "127.0.0.1:$port"
+  assert [ "$(echo "$output" | jq -r '.bytes_in')" != "null" ]
+  assert [ "$(echo "$output" | jq -r '.bytes_out')" != "null" ]
+  assert [ "$(echo "$output" | jq -r '.connected_at')" != "null" ]
+
+  run $OCKAM tcp-connection show --node n1 "$tid"
+  assert_success
+  assert_output --partial "Peer address"
+
+  run $OCKAM tcp-connection show --node n1 unknown
+  assert_failure 66
+}
+
 @test "create a secure channel between two nodes and send message through it" {
   $OCKAM node create n1
   $OCKAM node create n2
@@ -147,6 +815,65 @@ teardown() {
   assert_output "HELLO"
 }
 
+@test "create a secure channel with a trust policy and send message through its address" {
+  $OCKAM node create n1
+  $OCKAM node create n2
+
+  run --separate-stderr $OCKAM secure-channel create --from /node/n1 --to /node/n2/service/api \
+    --trust-policy everyone --output json
+  assert_success
+  assert_equal "$(echo "$output" | jq -r '.[0].from')" "/node/n1"
+  address=$(echo "$output" | jq -r '.[0].address')
+
+  run --separate-stderr $OCKAM message send hello --from /node/n1 --to "$address/service/uppercase"
+  assert_success
+  assert_output "HELLO"
+
+  run $OCKAM secure-channel create --from /node/n1 --to /node/n2/service/api --trust-policy identifiers
+  assert_failure 64
+}
+
+@test "list the negotiated parameters of secure channels" {
+  $OCKAM node create n1
+  $OCKAM node create n2
+
+  $OCKAM secure-channel create --from /node/n1 --to /node/n2/service/api
+  run --separate-stderr $OCKAM secure-channel list --at n1 --output json
+  assert_success
+  assert_output --partial '"cipher_suite":"Noise_XX_25519_AESGCM_SHA256"'
+  assert_output --partial '"pre_shared_key":false'
+}
+
+@test "list a labeled secure channel" {
+  $OCKAM node create n1
+  $OCKAM node create n2
+
+  $OCKAM secure-channel create --from /node/n1 --to /node/n2/service/api --label billing
+  run --separate-stderr $OCKAM secure-channel list --at n1 --output json
+  assert_success
+  assert_output --partial '"label":"billing"'
+}
+
+@test "delete a secure channel by address" {
+  $OCKAM node create n1
+  $OCKAM node create n2
+
+  address=$($OCKAM secure-channel create --from /node/n1 --to /node/n2/service/api)
+  run $OCKAM secure-channel list --at n1
+  assert_output --partial "$address"
+
+  run $OCKAM secure-channel delete --at n1 "$address"
+  assert_success
+  run $OCKAM secure-channel list --at n1
+  refute_output --partial "$address"
+
+  run $OCKAM message send hello --from /node/n1 --to "$address/service/uppercase"
+  assert_failure
+
+  run $OCKAM secure-channel delete --at n1 "$address"
+  assert_failure 66
+}
+
 @test "create a secure channel between two nodes and send message through it - in a pipeline" {
   $OCKAM node create n1
   $OCKAM node create n2
@@ -177,6 +904,159 @@ teardown() {
   assert [ "$output" == "HELLO" ]
 }
 
+@test "create secure channel listeners with each trust policy" {
+  $OCKAM node create n1
+  $OCKAM node create n2
+  n1_id=$($OCKAM identity show --node n1)
+
+  run $OCKAM secure-channel-listener create "everyone_listener" --at /node/n2 --trust-policy everyone
+  assert_success
+  run $OCKAM secure-channel-listener create "ids_listener" --at /node/n2 --trust-policy identifiers --authorized-identifiers "$n1_id"
+  assert_success
+  run $OCKAM secure-channel-listener create "credential_listener" --at /node/n2 --trust-policy credential
+  assert_success
+
+  output=$($OCKAM secure-channel create --from /node/n1 --to /node/n2/service/ids_listener | \
+    $OCKAM message send hello --from /node/n1 --to -/service/uppercase)
+  assert [ "$output" == "HELLO" ]
+
+  # Identifiers only go with the identifiers policy
+  run $OCKAM secure-channel-listener create "bad_listener" --at /node/n2 --trust-policy credential --authorized-identifiers "$n1_id"
+  assert_failure 64
+  run $OCKAM secure-channel-listener create "bad_listener" --at /node/n2 --trust-policy identifiers
+  assert_failure 64
+}
+
+@test "show the trust policies of secure channel listeners" {
+  $OCKAM node create n1
+  $OCKAM node create n2
+  n1_id=$($OCKAM identity show --node n1)
+
+  run $OCKAM secure-channel-listener create "ids_listener" --at /node/n2 --authorized-identifiers "$n1_id"
+  assert_success
+
+  run $OCKAM secure-channel-listener list --node n2
+  assert_success
+  assert_output --partial "trusts identifiers [$n1_id]"
+
+  run $OCKAM node show n2
+  assert_success
+  assert_output --partial "Trust Policy: trusts identifiers [$n1_id]"
+  assert_output --partial "Trust Policy: trusts everyone"
+}
+
+@test "list the secure channel listeners of a node" {
+  $OCKAM node create n1
+  $OCKAM node create n2
+  n1_id=$($OCKAM identity show --node n1)
+
+  run $OCKAM secure-channel-listener create "everyone_listener" --at /node/n2 --trust-policy everyone
+  assert_success
+  run $OCKAM secure-channel-listener create "ids_listener" --at /node/n2 --authorized-identifiers "$n1_id"
+  assert_success
+
+  run $OCKAM secure-channel-listener list --node n2
+  assert_success
+  assert_output --partial "everyone_listener"
+  assert_output --partial "ids_listener"
+
+  run $OCKAM secure-channel-listener list --node n2 --output json
+  assert_success
+  assert_equal "$(echo "$output" | jq -r '.[] | select(.address == "0#everyone_listener") | .trust_policy')" "trusts everyone"
+  assert_equal "$(echo "$output" | jq -r '.[] | select(.address == "0#ids_listener") | .trust_policy')" "trusts identifiers [$n1_id]"
+}
+
+@test "update the identifiers trusted by a secure channel listener" {
+  $OCKAM node create n1 --no-shared-identity
+  $OCKAM node create n2 --no-shared-identity
+  $OCKAM node create n3 --no-shared-identity
+  n1_id=$($OCKAM identity show --node n1)
+  n3_id=$($OCKAM identity show --node n3)
+
+  run $OCKAM secure-channel-listener create "ids_listener" --at /node/n2 --authorized-identifiers "$n3_id"
+  assert_success
+  run $OCKAM secure-channel create --from /node/n1 --to /node/n2/service/ids_listener
+  assert_failure
+
+  run $OCKAM secure-channel-listener update "ids_listener" --at /node/n2 --add "$n1_id" --remove "$n3_id"
+  assert_success
+  run $OCKAM secure-channel-listener list --node n2
+  assert_output --partial "trusts identifiers [$n1_id]"
+
+  output=$($OCKAM secure-channel create --from /node/n1 --to /node/n2/service/ids_listener | \
+    $OCKAM message send hello --from /node/n1 --to -/service/uppercase)
+  assert [ "$output" == "HELLO" ]
+}
+
+@test "create secure channels between nodes bootstrapped with a pre-shared key" {
+  psk="000102030405060708090a0b0c0d0e0f"
+  $OCKAM node create n1 --pre-shared-key "$psk"
+  $OCKAM node create n2 --pre-shared-key "$psk"
+  $OCKAM node create n3 --pre-shared-key "ffffffffffffffffffffffffffffffff"
+
+  output=$($OCKAM secure-channel create --from /node/n1 --to /node/n2/service/api --pre-shared-key | \
+    $OCKAM message send hello --from /node/n1 --to -/service/uppercase)
+  assert [ "$output" == "HELLO" ]
+
+  # The default listener requires the key
+  run $OCKAM secure-channel create --from /node/n3 --to /node/n2/service/api --pre-shared-key
+  assert_failure
+  run $OCKAM secure-channel create --from /node/n1 --to /node/n2/service/api
+  assert_failure
+
+  # The key is only proven to the listeners which require it
+  $OCKAM node create n4
+  output=$($OCKAM secure-channel create --from /node/n1 --to /node/n4/service/api | \
+    $OCKAM message send hello --from /node/n1 --to -/service/uppercase)
+  assert [ "$output" == "HELLO" ]
+  run $OCKAM secure-channel create --from /node/n4 --to /node/n1/service/api --pre-shared-key
+  assert_failure
+
+  # Other listeners require it when asked to
+  run $OCKAM secure-channel-listener create psk --at /node/n2 --pre-shared-key
+  assert_success
+  output=$($OCKAM secure-channel create --from /node/n1 --to /node/n2/service/psk --pre-shared-key | \
+    $OCKAM message send hello --from /node/n1 --to -/service/uppercase)
+  assert [ "$output" == "HELLO" ]
+
+  run $OCKAM node create n5 --pre-shared-key "0001"
+  assert_failure
+}
+
+@test "create secure channels from a node with a read-only identity" {
+  $OCKAM node create n1 --readonly-identity
+  $OCKAM node create n2
+
+  output=$($OCKAM secure-channel create --from /node/n1 --to /node/n2/service/api | \
+    $OCKAM message send hello --from /node/n1 --to -/service/uppercase)
+  assert [ "$output" == "HELLO" ]
+
+  # The node keeps its identity across restarts
+  $OCKAM node stop n1
+  $OCKAM node start n1
+  sleep 1
+  output=$($OCKAM secure-channel create --from /node/n1 --to /node/n2/service/api | \
+    $OCKAM message send hello --from /node/n1 --to -/service/uppercase)
+  assert [ "$output" == "HELLO" ]
+}
+
+@test "create a node with bootstrap peers" {
+  $OCKAM node create n1
+  $OCKAM node create n2 --bootstrap-peer /node/n1/service/api --bootstrap-peer /ip4/127.0.0.1/tcp/1/service/api
+  sleep 2
+
+  # The reachable peer is connected, the unreachable one doesn't stop the node
+  run $OCKAM status --output json
+  assert_success
+  assert_equal "$(echo "$output" | jq -r '.nodes[] | select(.name == "n2") | .state')" "UP"
+  assert_equal "$(echo "$output" | jq -r '.nodes[] | select(.name == "n2") | .secure_channels')" "1"
+
+  # Unless every bootstrap peer is required
+  run $OCKAM node create n3 --foreground --require-bootstrap \
+    --bootstrap-peer /node/n1/service/api --bootstrap-peer /ip4/127.0.0.1/tcp/1/service/api
+  assert_failure
+}
+
 @test "create a forwarder and send message through it" {
   $OCKAM node create n1
   $OCKAM node create n2
@@ -224,6 +1104,57 @@ teardown() {
   assert_success
 }
 
+@test "create a vault with a backend, then an identity" {
+  $OCKAM node create n1 --skip-defaults
+
+  run $OCKAM vault create --node n1 --type pkcs11 --pkcs11-slot 0
+  assert_failure
+  assert_output --partial "require --pkcs11-module and --pkcs11-slot"
+
+  # No HSM support in this build, unless it emulates them
+  if [ -z "${HSM_STUB_TESTS}" ]; then
+    run $OCKAM vault create --node n1 --type pkcs11 --pkcs11-module /tmp/libhsm.so --pkcs11-slot 0
+    assert_failure
+  fi
+
+  run $OCKAM vault create --node n1 --type software --name v1
+  assert_success
+  assert_output --partial "(software)"
+
+  run $OCKAM identity create --node n1 --vault v2
+  assert_failure
+
+  run $OCKAM identity create --node n1 --vault v1
+  assert_success
+}
+
+@test "create an identity with a stub HSM vault" {
+  skip_if_hsm_stub_tests_not_enabled
+  $OCKAM node create n1 --skip-defaults
+
+  run $OCKAM vault create --node n1 --type pkcs11 --pkcs11-module /tmp/libhsm.so --pkcs11-slot 0 --name hsm
+  assert_success
+  assert_output --partial "(pkcs11)"
+
+  run $OCKAM identity create --node n1 --vault hsm
+  assert_success
+
+  run $OCKAM identity show --node n1
+  assert_success
+}
+
+@test "rotate the storage key of a node" {
+  $OCKAM node create n1
+
+  run $OCKAM vault rotate --node n1
+  assert_success
+  assert_output --partial "in the background"
+
+  # The storage is still readable with the new key
+  run $OCKAM node show n1
+  assert_success
+}
+
 @test "create a node and start services" {
   $OCKAM node create n1
 
@@ -362,7 +1293,7 @@ teardown() {
   assert_success
   green_identifer=$($OCKAM identity show -n green)
 
-  run $OCKAM node create blue --project /tmp/project.json --enable-credential-checks --no-shared-identity
+  run $OCKAM node create blue --project /tmp/project.json --credential-checks on --no-shared-identity
   assert_success
   blue_identifer=$($OCKAM identity show -n blue)
 
@@ -396,7 +1327,7 @@ teardown() {
   assert_success
   green_identifer=$($OCKAM identity show -n green)
 
-  run $OCKAM node create blue --project /tmp/project.json --enable-credential-checks --no-shared-identity
+  run $OCKAM node create blue --project /tmp/project.json --credential-checks on --no-shared-identity
   assert_success
   blue_identifer=$($OCKAM identity show -n blue)
 
@@ -424,11 +1355,11 @@ teardown() {
 
   $OCKAM project info --name default --output json  > /tmp/project.json
 
-  run $OCKAM node create green --project /tmp/project.json --enable-credential-checks --no-shared-identity
+  run $OCKAM node create green --project /tmp/project.json --credential-checks on --no-shared-identity
   assert_success
   green_identifer=$($OCKAM identity show -n green)
 
-  run $OCKAM node create blue --project /tmp/project.json --enable-credential-checks --no-shared-identity
+  run $OCKAM node create blue --project /tmp/project.json --credential-checks on --no-shared-identity
   assert_success
   blue_identifer=$($OCKAM identity show -n blue)
 
@@ -456,11 +1387,11 @@ teardown() {
 
   $OCKAM project info --name default --output json  > /tmp/project.json
 
-  run $OCKAM node create green --project /tmp/project.json --enable-credential-checks --no-shared-identity
+  run $OCKAM node create green --project /tmp/project.json --credential-checks on --no-shared-identity
   assert_success
   green_identifer=$($OCKAM identity show -n green)
 
-  run $OCKAM node create blue --project /tmp/project.json --enable-credential-checks --no-shared-identity
+  run $OCKAM node create blue --project /tmp/project.json --credential-checks on --no-shared-identity
   assert_success
   blue_identifer=$($OCKAM identity show -n blue)
 
@@ -497,7 +1428,7 @@ teardown() {
 
   $OCKAM project info --name "${project_name}" --output json  > "/tmp/${project_name}_project.json"
 
-  run $OCKAM node create green --project "/tmp/${project_name}_project.json" --enable-credential-checks --no-shared-identity
+  run $OCKAM node create green --project "/tmp/${project_name}_project.json" --credential-checks on --no-shared-identity
   assert_success
   green_identifer=$($OCKAM identity show -n green)
 
@@ -549,6 +1480,13 @@ function skip_if_orchestrator_tests_not_enabled() {
   fi
 }
 
+function skip_if_hsm_stub_tests_not_enabled() {
+  # Set when ockam is built with the hsm-stub feature
+  if [ -z "${HSM_STUB_TESTS}" ]; then
+    skip "HSM_STUB_TESTS are not enabled"
+  fi
+}
+
 function skip_if_long_tests_not_enabled() {
   if [ -z "${LONG_TESTS}" ]; then
     skip "LONG_TESTS are not enabled"
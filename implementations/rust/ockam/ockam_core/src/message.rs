@@ -127,6 +127,77 @@ impl Decodable for NeutralMessage {
 
 impl Message for NeutralMessage {}
 
+/// A message made of a typed header and an opaque binary attachment.
+///
+/// The attachment is appended to the encoded header as is, so it isn't
+/// encoded a second time. The encoded message is the length of the
+/// encoded header as a big-endian `u32`, the encoded header, then the
+/// attachment.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MessageWithAttachment<H> {
+    header: H,
+    attachment: Vec<u8>,
+}
+
+impl<H> MessageWithAttachment<H> {
+    /// Create a message from its header and attachment
+    pub fn new(header: H, attachment: Vec<u8>) -> Self {
+        Self { header, attachment }
+    }
+
+    /// Return a reference to the header
+    pub fn header(&self) -> &H {
+        &self.header
+    }
+
+    /// Return a reference to the attachment
+    pub fn attachment(&self) -> &[u8] {
+        &self.attachment
+    }
+
+    /// Consume the message and return its header and attachment
+    pub fn into_parts(self) -> (H, Vec<u8>) {
+        (self.header, self.attachment)
+    }
+}
+
+impl<H: Encodable> Encodable for MessageWithAttachment<H> {
+    fn encode(&self) -> Result<Encoded> {
+        let header = self.header.encode()?;
+        let header_len = u32::try_from(header.len())
+            .map_err(|_| Error::new(Origin::Core, Kind::Invalid, "message header is too long"))?;
+
+        let mut encoded = Vec::with_capacity(4 + header.len() + self.attachment.len());
+        encoded.extend_from_slice(&header_len.to_be_bytes());
+        encoded.extend_from_slice(&header);
+        encoded.extend_from_slice(&self.attachment);
+        Ok(encoded)
+    }
+}
+
+impl<H: Decodable> Decodable for MessageWithAttachment<H> {
+    fn decode(encoded: &[u8]) -> Result<Self> {
+        let invalid = || Error::new(Origin::Core, Kind::Invalid, "truncated message header");
+        if encoded.len() < 4 {
+            return Err(invalid());
+        }
+        let (header_len, rest) = encoded.split_at(4);
+        let header_len: [u8; 4] = header_len.try_into().map_err(|_| invalid())?;
+        let header_len = u32::from_be_bytes(header_len) as usize;
+        if rest.len() < header_len {
+            return Err(invalid());
+        }
+        let (header, attachment) = rest.split_at(header_len);
+
+        Ok(Self {
+            header: H::decode(header)?,
+            attachment: attachment.to_vec(),
+        })
+    }
+}
+
+impl<H: Message> Message for MessageWithAttachment<H> {}
+
 impl From<serde_bare::error::Error> for Error {
     fn from(e: serde_bare::error::Error) -> Self {
         Error::new(Origin::Core, Kind::Io, e)
@@ -364,15 +364,40 @@ impl Address {
     /// let local_worker: Address = Address::random(LOCAL);
     /// ```
     pub fn random(tt: TransportType) -> Self {
+        #[cfg(feature = "std")]
+        if let Some(address) = seeded::next() {
+            return Self { tt, ..address };
+        }
         Self { tt, ..random() }
     }
 
     /// Generate a random address with transport type [`LOCAL`].
     pub fn random_local() -> Self {
-        Self {
-            tt: LOCAL,
-            ..random()
-        }
+        Self::random(LOCAL)
+    }
+
+    /// Make [`Address::random`] and [`Address::random_local`] return the
+    /// same sequence of addresses for the same `seed` on the current
+    /// thread, until the returned guard is dropped
+    ///
+    /// This is meant for tests and debugging only, as the addresses are
+    /// predictable. Workers running on other threads, such as those of a
+    /// multi-threaded runtime, keep getting random addresses.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use ockam_core::Address;
+    /// let first = {
+    ///     let _seeded = Address::seed_random(42);
+    ///     Address::random_local()
+    /// };
+    /// let _seeded = Address::seed_random(42);
+    /// assert_eq!(first, Address::random_local());
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn seed_random(seed: u64) -> SeededAddresses {
+        seeded::seed(seed)
     }
 
     /// Get transport type of this address.
@@ -389,6 +414,31 @@ impl Address {
     pub fn is_local(&self) -> bool {
         self.tt == LOCAL
     }
+
+    /// Human-friendly form of this address, for logs
+    ///
+    /// Local addresses are shown by name and addresses of well-known
+    /// transports as `tcp://127.0.0.1:4000`. Unlike the [`Display`] form,
+    /// it can't be parsed back into an address.
+    pub fn pretty(&self) -> PrettyAddress<'_> {
+        PrettyAddress(self)
+    }
+}
+
+/// Human-friendly form of an [`Address`], see [`Address::pretty`]
+pub struct PrettyAddress<'a>(&'a Address);
+
+impl Display for PrettyAddress<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let address = self.0;
+        if address.is_local() {
+            return write!(f, "{}", address.address());
+        }
+        match address.tt.name() {
+            Some(name) => write!(f, "{}://{}", name, address.address()),
+            None => write!(f, "{}", address),
+        }
+    }
 }
 
 impl core::str::FromStr for Address {
@@ -524,6 +574,50 @@ impl From<Address> for String {
     }
 }
 
+#[cfg(feature = "std")]
+pub use seeded::SeededAddresses;
+
+#[cfg(feature = "std")]
+mod seeded {
+    use super::Address;
+    use core::cell::RefCell;
+    use core::marker::PhantomData;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    std::thread_local! {
+        static SEEDED_RNG: RefCell<Option<StdRng>> = RefCell::new(None);
+    }
+
+    /// Guard returned by [`Address::seed_random`], addresses are random
+    /// again once it is dropped
+    #[must_use = "addresses are random again once the guard is dropped"]
+    pub struct SeededAddresses {
+        previous: Option<StdRng>,
+        // The generator is only installed on the current thread
+        _not_send: PhantomData<*const ()>,
+    }
+
+    impl Drop for SeededAddresses {
+        fn drop(&mut self) {
+            let previous = self.previous.take();
+            SEEDED_RNG.with(|rng| *rng.borrow_mut() = previous);
+        }
+    }
+
+    pub(super) fn seed(seed: u64) -> SeededAddresses {
+        let previous = SEEDED_RNG.with(|rng| rng.replace(Some(StdRng::seed_from_u64(seed))));
+        SeededAddresses {
+            previous,
+            _not_send: PhantomData,
+        }
+    }
+
+    pub(super) fn next() -> Option<Address> {
+        SEEDED_RNG.with(|rng| rng.borrow_mut().as_mut().map(|rng| rng.gen()))
+    }
+}
+
 impl Distribution<Address> for Standard {
     fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Address {
         let address: [u8; 16] = rng.gen();
@@ -549,6 +643,20 @@ impl TransportType {
     pub fn is_local(self) -> bool {
         self == LOCAL
     }
+
+    /// Name of the transport type, if it's one of the transports
+    /// implemented by the Ockam crates
+    pub fn name(self) -> Option<&'static str> {
+        match self.0 {
+            0 => Some("local"),
+            1 => Some("tcp"),
+            2 => Some("udp"),
+            3 => Some("ws"),
+            4 => Some("ble"),
+            16 => Some("stream"),
+            _ => None,
+        }
+    }
 }
 
 impl Display for TransportType {
@@ -587,6 +695,21 @@ fn parse_addr_with_type() {
     );
 }
 
+#[test]
+fn pretty_addresses() {
+    assert_eq!(Address::from_string("app").pretty().to_string(), "app");
+    assert_eq!(
+        Address::from_string("1#127.0.0.1:4000")
+            .pretty()
+            .to_string(),
+        "tcp://127.0.0.1:4000"
+    );
+    assert_eq!(
+        Address::from_string("42#peer").pretty().to_string(),
+        "42#peer"
+    );
+}
+
 #[test]
 #[should_panic(expected = "Failed to parse address type:")]
 fn parse_addr_invalid() {
@@ -598,3 +721,40 @@ fn parse_addr_invalid() {
 fn parse_addr_invalid_multiple_separators() {
     let _ = Address::from_string("1#invalid#");
 }
+
+#[test]
+fn seeded_addresses_are_reproducible() {
+    let first: Vec<Address> = {
+        let _seeded = Address::seed_random(7);
+        (0..3).map(|_| Address::random_local()).collect()
+    };
+    let second: Vec<Address> = {
+        let _seeded = Address::seed_random(7);
+        (0..3).map(|_| Address::random_local()).collect()
+    };
+    assert_eq!(first, second);
+
+    let other_seed = {
+        let _seeded = Address::seed_random(8);
+        Address::random_local()
+    };
+    assert_ne!(first[0], other_seed);
+
+    // Random again once the guard is dropped
+    assert_ne!(first[0], Address::random_local());
+}
+
+#[test]
+fn seeded_addresses_nest() {
+    let _outer = Address::seed_random(1);
+    let outer_first = Address::random_local();
+    {
+        let _inner = Address::seed_random(1);
+        assert_eq!(outer_first, Address::random_local());
+    }
+    let outer_second = Address::random_local();
+
+    let _again = Address::seed_random(1);
+    let _ = Address::random_local();
+    assert_eq!(outer_second, Address::random_local());
+}
@@ -5,3 +5,6 @@ pub use transport_message::*;
 
 mod local_message;
 pub use local_message::*;
+
+mod message_id;
+pub use message_id::*;
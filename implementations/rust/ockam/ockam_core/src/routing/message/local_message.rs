@@ -1,6 +1,42 @@
 use crate::{compat::string::String, compat::vec::Vec, Message, TransportMessage};
 use serde::{Deserialize, Serialize};
 
+/// Default number of times a [`LocalMessage`] may be forwarded before
+/// it is dropped.
+pub const DEFAULT_TTL: u8 = 64;
+
+/// How urgently a [`LocalMessage`] should be handled.
+///
+/// A worker takes the queued messages of the highest priority first,
+/// and messages of the same priority in the order they arrived. Use
+/// [`MessagePriority::High`] for small control messages, such as pings,
+/// which must not wait behind a backlog of bulk data.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, Ord, PartialOrd, Eq, PartialEq)]
+pub enum MessagePriority {
+    /// Handled once no other message is queued
+    Low,
+    /// The priority of most messages
+    Normal,
+    /// Handled before any other queued message
+    High,
+}
+
+impl MessagePriority {
+    /// Number of priority classes
+    pub const COUNT: usize = 3;
+
+    /// Index of the priority class, from 0 for the lowest
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl Default for MessagePriority {
+    fn default() -> Self {
+        MessagePriority::Normal
+    }
+}
+
 /// Contains metadata that will only be routed locally within the
 /// local Ockam Node.
 #[derive(Serialize, Deserialize, Debug, Clone, Hash, Ord, PartialOrd, Eq, PartialEq, Message)]
@@ -53,6 +89,15 @@ impl LocalInfo {
 /// Casual users of Ockam should never have to interact with this type
 /// directly.
 ///
+/// The TTL of a message is the number of times it may still be
+/// forwarded, which stops misconfigured routes from looping forever.
+/// It's only carried to another node by transports speaking a wire
+/// format which has room for it, and starts over from [`DEFAULT_TTL`]
+/// otherwise.
+///
+/// The [`MessagePriority`] of a message only applies within the node,
+/// a message received from another node has the normal priority.
+///
 /// # Examples
 ///
 /// See `ockam_transport_tcp::workers::receiver::TcpRecvProcessor` for a usage example.
@@ -61,6 +106,8 @@ impl LocalInfo {
 pub struct LocalMessage {
     transport_message: TransportMessage,
     local_info: Vec<LocalInfo>,
+    ttl: u8,
+    priority: MessagePriority,
 }
 
 impl LocalMessage {
@@ -84,6 +131,15 @@ impl LocalMessage {
     pub fn dissolve(self) -> (TransportMessage, Vec<LocalInfo>) {
         (self.transport_message, self.local_info)
     }
+    /// Return the number of times the message may still be forwarded.
+    pub fn ttl(&self) -> u8 {
+        self.ttl
+    }
+    /// Return how urgently the message should be handled by the worker
+    /// it is delivered to.
+    pub fn priority(&self) -> MessagePriority {
+        self.priority
+    }
 }
 
 impl LocalMessage {
@@ -112,6 +168,36 @@ impl LocalMessage {
         LocalMessage {
             transport_message,
             local_info,
+            ttl: DEFAULT_TTL,
+            priority: MessagePriority::Normal,
+        }
+    }
+
+    /// Set how urgently the message should be handled by the worker it
+    /// is delivered to.
+    pub fn with_priority(mut self, priority: MessagePriority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Set the number of times the message may still be forwarded,
+    /// e.g. to the TTL of the message it was unwrapped from.
+    pub fn with_ttl(mut self, ttl: u8) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Consume one hop of the message's TTL.
+    ///
+    /// Returns `false` if the TTL was already exhausted, in which case
+    /// the message must not be forwarded any further.
+    pub fn decrement_ttl(&mut self) -> bool {
+        match self.ttl.checked_sub(1) {
+            Some(ttl) => {
+                self.ttl = ttl;
+                true
+            }
+            None => false,
         }
     }
 }
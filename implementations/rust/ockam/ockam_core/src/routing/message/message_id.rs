@@ -0,0 +1,44 @@
+use core::fmt::{self, Display, Formatter};
+
+/// Identifier of the payload of a message, to correlate the traces of
+/// the workers a message goes through.
+///
+/// The id is a hash of the payload, so it is the same for every worker
+/// passing the payload on unchanged: the encryptor of a secure channel,
+/// the transport workers on both ends, and the decryptor at the other
+/// end of the channel all see the same encrypted payload. It isn't
+/// unique: two messages with the same payload have the same id.
+#[derive(Clone, Copy, Debug, Hash, Ord, PartialOrd, Eq, PartialEq)]
+pub struct MessageId(u64);
+
+impl MessageId {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+    /// Id of a message with this payload
+    pub fn of(payload: &[u8]) -> Self {
+        // FNV-1a, which is fast on small payloads and needs no dependency
+        let hash = payload.iter().fold(Self::FNV_OFFSET_BASIS, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(Self::FNV_PRIME)
+        });
+        Self(hash)
+    }
+}
+
+impl Display for MessageId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_id_is_a_hash_of_the_payload() {
+        assert_eq!(MessageId::of(b"hello"), MessageId::of(b"hello"));
+        assert_ne!(MessageId::of(b"hello"), MessageId::of(b"hellp"));
+        assert_eq!(MessageId::of(&[]).to_string(), "cbf29ce484222325");
+    }
+}
@@ -1,7 +1,19 @@
-use crate::{compat::vec::Vec, Message, Route};
+use crate::errcode::{Kind, Origin};
+use crate::{
+    compat::{format, vec::Vec},
+    Error, Message, Result, Route, DEFAULT_TTL,
+};
 use core::fmt::{self, Display, Formatter};
 use serde::{Deserialize, Serialize};
 
+/// Oldest wire format of [`TransportMessage`], which doesn't carry the
+/// TTL of the message.
+pub const MIN_TRANSPORT_VERSION: u8 = 1;
+
+/// Latest wire format of [`TransportMessage`], carrying the TTL of the
+/// message after its fields.
+pub const LATEST_TRANSPORT_VERSION: u8 = 2;
+
 /// A generic transport message type.
 ///
 /// This type is exposed in `ockam_core` (and the root `ockam` crate) in
@@ -31,7 +43,7 @@ pub struct TransportMessage {
 }
 
 impl TransportMessage {
-    /// Create a new v1 transport message with empty return route.
+    /// Create a new v1 transport message.
     pub fn v1(
         onward_route: impl Into<Route>,
         return_route: impl Into<Route>,
@@ -46,6 +58,112 @@ impl TransportMessage {
     }
 }
 
+impl TransportMessage {
+    /// Encode the message in the wire format of `version`, which
+    /// replaces the message's own version, with the remaining `ttl` of
+    /// its [`LocalMessage`](crate::LocalMessage).
+    ///
+    /// Version 1 drops the TTL.
+    pub fn encode_versioned(&self, version: u8, ttl: u8) -> Result<Vec<u8>> {
+        let encoded = match version {
+            1 => serde_bare::to_vec(&TransportMessageV1Ref {
+                version,
+                onward_route: &self.onward_route,
+                return_route: &self.return_route,
+                payload: &self.payload,
+            }),
+            2 => serde_bare::to_vec(&TransportMessageV2Ref {
+                version,
+                onward_route: &self.onward_route,
+                return_route: &self.return_route,
+                payload: &self.payload,
+                ttl,
+            }),
+            _ => return Err(unsupported_version(version)),
+        };
+        Ok(encoded?)
+    }
+
+    /// Decode a message in the wire format given by its first byte,
+    /// along with its remaining TTL.
+    ///
+    /// A version 1 message gets [`DEFAULT_TTL`]. A version this crate
+    /// doesn't speak is reported with [`Kind::Unsupported`].
+    pub fn decode_versioned(data: &[u8]) -> Result<(Self, u8)> {
+        let version = *data
+            .first()
+            .ok_or_else(|| Error::new(Origin::Core, Kind::Protocol, "empty message"))?;
+        match version {
+            1 => {
+                let msg: TransportMessageV1 = serde_bare::from_slice(data)?;
+                let msg = Self {
+                    version,
+                    onward_route: msg.onward_route,
+                    return_route: msg.return_route,
+                    payload: msg.payload,
+                };
+                Ok((msg, DEFAULT_TTL))
+            }
+            2 => {
+                let msg: TransportMessageV2 = serde_bare::from_slice(data)?;
+                let ttl = msg.ttl;
+                let msg = Self {
+                    version,
+                    onward_route: msg.onward_route,
+                    return_route: msg.return_route,
+                    payload: msg.payload,
+                };
+                Ok((msg, ttl))
+            }
+            _ => Err(unsupported_version(version)),
+        }
+    }
+}
+
+fn unsupported_version(version: u8) -> Error {
+    Error::new(
+        Origin::Core,
+        Kind::Unsupported,
+        format!("unsupported transport message version {}", version),
+    )
+}
+
+/// Layout of a version 1 [`TransportMessage`]
+#[derive(Deserialize)]
+struct TransportMessageV1 {
+    _version: u8,
+    onward_route: Route,
+    return_route: Route,
+    payload: Vec<u8>,
+}
+
+#[derive(Serialize)]
+struct TransportMessageV1Ref<'a> {
+    version: u8,
+    onward_route: &'a Route,
+    return_route: &'a Route,
+    payload: &'a [u8],
+}
+
+/// Layout of a version 2 [`TransportMessage`]
+#[derive(Deserialize)]
+struct TransportMessageV2 {
+    _version: u8,
+    onward_route: Route,
+    return_route: Route,
+    payload: Vec<u8>,
+    ttl: u8,
+}
+
+#[derive(Serialize)]
+struct TransportMessageV2Ref<'a> {
+    version: u8,
+    onward_route: &'a Route,
+    return_route: &'a Route,
+    payload: &'a [u8],
+    ttl: u8,
+}
+
 impl Display for TransportMessage {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         write!(
@@ -55,3 +173,36 @@ impl Display for TransportMessage {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{route, Encodable};
+
+    #[test]
+    fn versioned_encoding() {
+        let msg = TransportMessage::v1(route!["a", "b"], route!["c"], vec![1, 2, 3]);
+
+        let v2 = msg.encode_versioned(2, 7).unwrap();
+        let (decoded, ttl) = TransportMessage::decode_versioned(&v2).unwrap();
+        assert_eq!(ttl, 7);
+        assert_eq!(decoded.version, 2);
+        assert_eq!(decoded.payload, msg.payload);
+
+        // Version 1 has no room for the TTL, and is the layout of the struct
+        let v1 = msg.encode_versioned(1, 7).unwrap();
+        assert!(v1.len() < v2.len());
+        assert_eq!(v1, msg.encode().unwrap());
+        let (decoded, ttl) = TransportMessage::decode_versioned(&v1).unwrap();
+        assert_eq!(decoded.onward_route, msg.onward_route);
+        assert_eq!(decoded.return_route, msg.return_route);
+        assert_eq!(decoded.payload, msg.payload);
+        assert_eq!(ttl, DEFAULT_TTL);
+
+        let err = TransportMessage::decode_versioned(&[3, 0, 0]).unwrap_err();
+        assert_eq!(err.code().kind, Kind::Unsupported);
+        let err = TransportMessage::decode_versioned(&[]).unwrap_err();
+        assert_eq!(err.code().kind, Kind::Protocol);
+        assert!(msg.encode_versioned(3, 7).is_err());
+    }
+}
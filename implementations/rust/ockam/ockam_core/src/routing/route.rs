@@ -196,6 +196,37 @@ impl Route {
     }
 }
 
+impl Route {
+    /// Human-friendly form of this route, for logs
+    ///
+    /// ```
+    /// # use ockam_core::route;
+    /// let route = route!["1#127.0.0.1:4000", "secure_channel", "service"];
+    /// assert_eq!(
+    ///     route.pretty().to_string(),
+    ///     "tcp://127.0.0.1:4000 -> secure_channel -> service"
+    /// );
+    /// ```
+    pub fn pretty(&self) -> PrettyRoute<'_> {
+        PrettyRoute(self)
+    }
+}
+
+/// Human-friendly form of a [`Route`], see [`Route::pretty`]
+pub struct PrettyRoute<'a>(&'a Route);
+
+impl Display for PrettyRoute<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, address) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " -> ")?;
+            }
+            write!(f, "{}", address.pretty())?;
+        }
+        Ok(())
+    }
+}
+
 impl Display for Route {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
@@ -472,4 +503,21 @@ mod tests {
         r1.modify().prepend_route(r2);
         assert_eq!(r1, route!["1", "2", "3", "a", "b", "c"]);
     }
+
+    #[test]
+    fn test_route_pretty() {
+        let route = route![
+            "1#127.0.0.1:4000",
+            "0#secure_channel",
+            "2#10.0.0.1:5000",
+            "9#relay",
+            "uppercase"
+        ];
+        assert_eq!(
+            route.pretty().to_string(),
+            "tcp://127.0.0.1:4000 -> secure_channel -> udp://10.0.0.1:5000 -> 9#relay -> uppercase"
+        );
+        // The parseable form is unchanged
+        assert_eq!(Route::parse(route.to_string()), Some(route));
+    }
 }
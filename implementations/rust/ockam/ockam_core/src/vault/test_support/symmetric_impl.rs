@@ -31,4 +31,21 @@ pub async fn encryption(vault: &mut (impl SymmetricVault + SecretVault)) {
         .aead_aes_gcm_decrypt(ctx, ciphertext.as_slice(), nonce.as_ref(), aad.as_ref())
         .await;
     assert!(res.is_err());
+
+    // An empty plaintext is still authenticated
+    let mut ciphertext = vault
+        .aead_aes_gcm_encrypt(ctx, &[], nonce.as_ref(), aad.as_ref())
+        .await
+        .unwrap();
+    assert!(!ciphertext.is_empty());
+    let plaintext = vault
+        .aead_aes_gcm_decrypt(ctx, ciphertext.as_slice(), nonce.as_ref(), aad.as_ref())
+        .await
+        .unwrap();
+    assert!(plaintext.is_empty());
+    ciphertext[0] ^= 0xb4;
+    let res = vault
+        .aead_aes_gcm_decrypt(ctx, ciphertext.as_slice(), nonce.as_ref(), aad.as_ref())
+        .await;
+    assert!(res.is_err());
 }
@@ -25,6 +25,14 @@ pub trait Worker: Send + 'static {
     }
 
     /// Override shutdown behaviour.
+    ///
+    /// Called exactly once when the worker stops, whether it was stopped
+    /// explicitly or because the node shuts down, and even if
+    /// [`Self::initialize`] failed. Use it to release resources
+    /// asynchronously, e.g. to close sockets. No message is handled once it
+    /// is called. The node only waits for it for a limited time, and an
+    /// error or a panic is logged without preventing the worker from
+    /// stopping.
     async fn shutdown(&mut self, _context: &mut Self::Context) -> Result<()> {
         Ok(())
     }
@@ -1,5 +1,12 @@
+use crate::IdentityError;
+use core::sync::atomic::{AtomicUsize, Ordering};
 use ockam_core::async_trait;
-use ockam_core::compat::{boxed::Box, string::String, vec::Vec};
+use ockam_core::compat::{
+    boxed::Box,
+    string::{String, ToString},
+    sync::{Arc, RwLock},
+    vec::Vec,
+};
 use ockam_core::{AsyncTryClone, Result};
 
 /// Storage for Authenticated data
@@ -13,7 +20,392 @@ pub trait AuthenticatedStorage: AsyncTryClone + Send + Sync + 'static {
 
     /// Delete entry
     async fn del(&self, id: &str, key: &str) -> Result<()>;
+
+    /// Subscribe to entry changes.
+    ///
+    /// The listener is notified after every successful `set` or `del`.
+    /// Storages that can't notify about changes return an error.
+    fn subscribe(&self, _listener: Arc<dyn AuthenticatedStorageListener>) -> Result<()> {
+        Err(IdentityError::StorageSubscriptionNotSupported.into())
+    }
+
+    /// Watch an entry: the returned [`AuthenticatedStorageWatch`] yields
+    /// the new value of the entry whenever it changes, and `None` when it
+    /// is deleted.
+    ///
+    /// Storages that can't notify about changes return an error.
+    #[cfg(feature = "std")]
+    async fn watch_key(&self, id: &str, key: &str) -> Result<AuthenticatedStorageWatch<Self>> {
+        AuthenticatedStorageWatch::new(self, id, key).await
+    }
+
+    /// Start a transaction, to be applied with [`AuthenticatedStorage::commit`]
+    fn begin(&self) -> AuthenticatedStorageTransaction {
+        AuthenticatedStorageTransaction::new()
+    }
+
+    /// Apply all writes of a transaction at once.
+    ///
+    /// Either every write succeeds, or the storage is left unchanged.
+    /// Listeners are notified of each write once the transaction is applied.
+    /// Storages that can't write atomically return an error.
+    async fn commit(&self, _transaction: AuthenticatedStorageTransaction) -> Result<()> {
+        Err(IdentityError::StorageTransactionNotSupported.into())
+    }
+
+    /// Operation counters and current size of the storage
+    ///
+    /// Storages that don't keep metrics return an error.
+    async fn metrics(&self) -> Result<AuthenticatedStorageMetrics> {
+        Err(IdentityError::StorageMetricsNotSupported.into())
+    }
+
+    /// All entries of the storage, as `(id, key, value)`
+    ///
+    /// Storages that can't enumerate their entries return an error.
+    async fn entries(&self) -> Result<Vec<(String, String, Vec<u8>)>> {
+        Err(IdentityError::StorageExportNotSupported.into())
+    }
+}
+
+/// An [`AuthenticatedStorage`] of any type, shared by its clones
+///
+/// Lets a storage picked at runtime, e.g. by the embedder of a node, be
+/// used wherever a storage of a single type is expected.
+#[derive(Clone)]
+pub struct SharedAuthenticatedStorage(Arc<dyn DynAuthenticatedStorage>);
+
+impl SharedAuthenticatedStorage {
+    pub fn new(storage: impl AuthenticatedStorage) -> Self {
+        Self(Arc::new(storage))
+    }
+}
+
+#[async_trait]
+impl AuthenticatedStorage for SharedAuthenticatedStorage {
+    async fn get(&self, id: &str, key: &str) -> Result<Option<Vec<u8>>> {
+        self.0.get(id, key).await
+    }
+
+    async fn set(&self, id: &str, key: String, val: Vec<u8>) -> Result<()> {
+        self.0.set(id, key, val).await
+    }
+
+    async fn del(&self, id: &str, key: &str) -> Result<()> {
+        self.0.del(id, key).await
+    }
+
+    fn subscribe(&self, listener: Arc<dyn AuthenticatedStorageListener>) -> Result<()> {
+        self.0.subscribe(listener)
+    }
+
+    async fn commit(&self, transaction: AuthenticatedStorageTransaction) -> Result<()> {
+        self.0.commit(transaction).await
+    }
+
+    async fn metrics(&self) -> Result<AuthenticatedStorageMetrics> {
+        self.0.metrics().await
+    }
+
+    async fn entries(&self) -> Result<Vec<(String, String, Vec<u8>)>> {
+        self.0.entries().await
+    }
+}
+
+/// The methods of [`AuthenticatedStorage`] which can be called on a trait
+/// object, [`AsyncTryClone`] requiring `Sized`
+#[async_trait]
+trait DynAuthenticatedStorage: Send + Sync + 'static {
+    async fn get(&self, id: &str, key: &str) -> Result<Option<Vec<u8>>>;
+    async fn set(&self, id: &str, key: String, val: Vec<u8>) -> Result<()>;
+    async fn del(&self, id: &str, key: &str) -> Result<()>;
+    fn subscribe(&self, listener: Arc<dyn AuthenticatedStorageListener>) -> Result<()>;
+    async fn commit(&self, transaction: AuthenticatedStorageTransaction) -> Result<()>;
+    async fn metrics(&self) -> Result<AuthenticatedStorageMetrics>;
+    async fn entries(&self) -> Result<Vec<(String, String, Vec<u8>)>>;
+}
+
+#[async_trait]
+impl<S: AuthenticatedStorage> DynAuthenticatedStorage for S {
+    async fn get(&self, id: &str, key: &str) -> Result<Option<Vec<u8>>> {
+        AuthenticatedStorage::get(self, id, key).await
+    }
+
+    async fn set(&self, id: &str, key: String, val: Vec<u8>) -> Result<()> {
+        AuthenticatedStorage::set(self, id, key, val).await
+    }
+
+    async fn del(&self, id: &str, key: &str) -> Result<()> {
+        AuthenticatedStorage::del(self, id, key).await
+    }
+
+    fn subscribe(&self, listener: Arc<dyn AuthenticatedStorageListener>) -> Result<()> {
+        AuthenticatedStorage::subscribe(self, listener)
+    }
+
+    async fn commit(&self, transaction: AuthenticatedStorageTransaction) -> Result<()> {
+        AuthenticatedStorage::commit(self, transaction).await
+    }
+
+    async fn metrics(&self) -> Result<AuthenticatedStorageMetrics> {
+        AuthenticatedStorage::metrics(self).await
+    }
+
+    async fn entries(&self) -> Result<Vec<(String, String, Vec<u8>)>> {
+        AuthenticatedStorage::entries(self).await
+    }
+}
+
+/// Write of an [`AuthenticatedStorageTransaction`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AuthenticatedStorageWrite {
+    /// Create or update an entry
+    Set {
+        /// Id the entry belongs to
+        id: String,
+        /// Key of the entry
+        key: String,
+        /// New value of the entry
+        val: Vec<u8>,
+    },
+    /// Delete an entry
+    Del {
+        /// Id the entry belongs to
+        id: String,
+        /// Key of the entry
+        key: String,
+    },
+}
+
+impl AuthenticatedStorageWrite {
+    /// The event listeners are notified with once the write is applied
+    pub fn event(&self) -> AuthenticatedStorageEvent {
+        match self {
+            Self::Set { id, key, .. } => AuthenticatedStorageEvent::Set {
+                id: id.clone(),
+                key: key.clone(),
+            },
+            Self::Del { id, key } => AuthenticatedStorageEvent::Deleted {
+                id: id.clone(),
+                key: key.clone(),
+            },
+        }
+    }
+}
+
+/// Writes applied all-or-nothing by [`AuthenticatedStorage::commit`]
+///
+/// Nothing is written before the transaction is committed: dropping it,
+/// or calling [`rollback`](Self::rollback), discards all its writes.
+#[derive(Clone, Debug, Default)]
+pub struct AuthenticatedStorageTransaction {
+    writes: Vec<AuthenticatedStorageWrite>,
+}
+
+impl AuthenticatedStorageTransaction {
+    /// Constructor
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Set entry when the transaction is committed
+    pub fn set(&mut self, id: &str, key: String, val: Vec<u8>) -> &mut Self {
+        self.writes.push(AuthenticatedStorageWrite::Set {
+            id: id.to_string(),
+            key,
+            val,
+        });
+        self
+    }
+
+    /// Delete entry when the transaction is committed
+    pub fn del(&mut self, id: &str, key: &str) -> &mut Self {
+        self.writes.push(AuthenticatedStorageWrite::Del {
+            id: id.to_string(),
+            key: key.to_string(),
+        });
+        self
+    }
+
+    /// Discard all writes
+    pub fn rollback(self) {}
+
+    /// Writes in the order they were made
+    pub fn writes(&self) -> &[AuthenticatedStorageWrite] {
+        &self.writes
+    }
+
+    /// Whether the transaction has no write
+    pub fn is_empty(&self) -> bool {
+        self.writes.is_empty()
+    }
+
+    /// Take the writes, to apply them
+    pub fn into_writes(self) -> Vec<AuthenticatedStorageWrite> {
+        self.writes
+    }
+}
+
+/// Change made to an [`AuthenticatedStorage`] entry
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AuthenticatedStorageEvent {
+    /// Entry was created or updated
+    Set {
+        /// Id the entry belongs to
+        id: String,
+        /// Key of the entry
+        key: String,
+    },
+    /// Entry was deleted
+    Deleted {
+        /// Id the entry belonged to
+        id: String,
+        /// Key of the entry
+        key: String,
+    },
+}
+
+impl AuthenticatedStorageEvent {
+    /// Id the changed entry belongs to
+    pub fn id(&self) -> &str {
+        match self {
+            Self::Set { id, .. } | Self::Deleted { id, .. } => id,
+        }
+    }
+
+    /// Key of the changed entry
+    pub fn key(&self) -> &str {
+        match self {
+            Self::Set { key, .. } | Self::Deleted { key, .. } => key,
+        }
+    }
+}
+
+/// Receives [`AuthenticatedStorageEvent`]s, see [`AuthenticatedStorage::subscribe`]
+pub trait AuthenticatedStorageListener: Send + Sync + 'static {
+    /// Called after an entry changed. Returning `false` unsubscribes the listener
+    fn on_event(&self, event: &AuthenticatedStorageEvent) -> bool;
+}
+
+/// Set of listeners subscribed to an [`AuthenticatedStorage`], to be
+/// shared between clones of a storage implementation
+#[derive(Clone, Default)]
+pub struct AuthenticatedStorageListeners {
+    listeners: Arc<RwLock<Vec<Arc<dyn AuthenticatedStorageListener>>>>,
+}
+
+impl AuthenticatedStorageListeners {
+    /// Add a listener
+    pub fn subscribe(&self, listener: Arc<dyn AuthenticatedStorageListener>) {
+        self.listeners.write().unwrap().push(listener);
+    }
+
+    /// Notify all listeners, dropping the ones that unsubscribed
+    pub fn notify(&self, event: AuthenticatedStorageEvent) {
+        self.listeners
+            .write()
+            .unwrap()
+            .retain(|listener| listener.on_event(&event));
+    }
+}
+
+/// Operation counters of an [`AuthenticatedStorage`], see
+/// [`AuthenticatedStorage::metrics`]
+///
+/// Writes of committed transactions are counted as `set`s and `del`s.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AuthenticatedStorageMetrics {
+    /// Number of `get` calls
+    pub gets: usize,
+    /// Number of `get` calls which found an entry
+    pub hits: usize,
+    /// Number of `get` calls which found no entry
+    pub misses: usize,
+    /// Number of entries set
+    pub sets: usize,
+    /// Number of entries deleted
+    pub dels: usize,
+    /// Number of entries currently stored
+    pub entries: usize,
+}
+
+/// Counters of an [`AuthenticatedStorage`], to be shared between clones
+/// of a storage implementation
+#[derive(Clone, Default)]
+pub struct AuthenticatedStorageCounters {
+    counters: Arc<[AtomicUsize; 4]>,
+}
+
+impl AuthenticatedStorageCounters {
+    const HITS: usize = 0;
+    const MISSES: usize = 1;
+    const SETS: usize = 2;
+    const DELS: usize = 3;
+
+    fn increment(&self, counter: usize) {
+        self.counters[counter].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a `get`, and whether it found an entry
+    pub fn record_get(&self, hit: bool) {
+        self.increment(if hit { Self::HITS } else { Self::MISSES });
+    }
+
+    /// Count an entry set
+    pub fn record_set(&self) {
+        self.increment(Self::SETS);
+    }
+
+    /// Count an entry deleted
+    pub fn record_del(&self) {
+        self.increment(Self::DELS);
+    }
+
+    /// Count the write of a committed transaction
+    pub fn record_event(&self, event: &AuthenticatedStorageEvent) {
+        match event {
+            AuthenticatedStorageEvent::Set { .. } => self.record_set(),
+            AuthenticatedStorageEvent::Deleted { .. } => self.record_del(),
+        }
+    }
+
+    /// The current counters, for a storage of `entries` entries
+    pub fn metrics(&self, entries: usize) -> AuthenticatedStorageMetrics {
+        let load = |counter: usize| self.counters[counter].load(Ordering::Relaxed);
+        let (hits, misses) = (load(Self::HITS), load(Self::MISSES));
+        AuthenticatedStorageMetrics {
+            gets: hits + misses,
+            hits,
+            misses,
+            sets: load(Self::SETS),
+            dels: load(Self::DELS),
+            entries,
+        }
+    }
 }
 
 /// In-memory impl
 pub mod mem;
+
+/// Fixed-capacity in-memory impl
+pub mod fixed;
+
+/// Writable impl layered over a read-only base
+pub mod overlay;
+
+/// Signed snapshots of a storage
+pub mod snapshot;
+
+/// Impl isolating the keys of several users of a storage
+pub mod namespaced;
+
+/// Impl limiting the size of another storage
+pub mod quota;
+
+mod codec;
+pub use codec::*;
+
+#[cfg(feature = "std")]
+mod watch;
+#[cfg(feature = "std")]
+pub use watch::*;
@@ -0,0 +1,164 @@
+use super::{
+    AuthenticatedStorage, AuthenticatedStorageListener, AuthenticatedStorageMetrics,
+    AuthenticatedStorageTransaction, AuthenticatedStorageWrite,
+};
+use ockam_core::async_trait;
+use ockam_core::compat::{boxed::Box, string::String, sync::Arc, vec::Vec};
+use ockam_core::Result;
+
+/// Transformation of the values of a [`CodecStorage`], e.g. compression
+pub trait ValueCodec: Send + Sync + 'static {
+    /// Transform a value before it is written
+    fn encode(&self, val: Vec<u8>) -> Result<Vec<u8>>;
+
+    /// Reverse [`ValueCodec::encode`] on a value which was read
+    fn decode(&self, val: Vec<u8>) -> Result<Vec<u8>>;
+}
+
+/// Pipeline of two codecs: values are encoded by `A` then by `B`, and
+/// decoded by `B` then by `A`
+impl<A: ValueCodec, B: ValueCodec> ValueCodec for (A, B) {
+    fn encode(&self, val: Vec<u8>) -> Result<Vec<u8>> {
+        self.1.encode(self.0.encode(val)?)
+    }
+
+    fn decode(&self, val: Vec<u8>) -> Result<Vec<u8>> {
+        self.0.decode(self.1.decode(val)?)
+    }
+}
+
+/// Storage applying a [`ValueCodec`] to the values of another storage
+///
+/// Values are encoded before they are written to `storage` and decoded
+/// when read back, ids and keys are stored as is. Since a `CodecStorage`
+/// is itself a storage, it can be layered with other storage wrappers.
+#[derive(Clone)]
+pub struct CodecStorage<S: AuthenticatedStorage + Clone, C: ValueCodec> {
+    storage: S,
+    codec: Arc<C>,
+}
+
+impl<S: AuthenticatedStorage + Clone, C: ValueCodec> CodecStorage<S, C> {
+    /// Constructor
+    pub fn new(storage: S, codec: C) -> Self {
+        Self {
+            storage,
+            codec: Arc::new(codec),
+        }
+    }
+
+    /// The storage holding the encoded values
+    pub fn inner(&self) -> &S {
+        &self.storage
+    }
+}
+
+#[async_trait]
+impl<S: AuthenticatedStorage + Clone, C: ValueCodec> AuthenticatedStorage for CodecStorage<S, C> {
+    async fn get(&self, id: &str, key: &str) -> Result<Option<Vec<u8>>> {
+        match self.storage.get(id, key).await? {
+            Some(val) => Ok(Some(self.codec.decode(val)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn set(&self, id: &str, key: String, val: Vec<u8>) -> Result<()> {
+        self.storage.set(id, key, self.codec.encode(val)?).await
+    }
+
+    async fn del(&self, id: &str, key: &str) -> Result<()> {
+        self.storage.del(id, key).await
+    }
+
+    /// Events only carry ids and keys, so they are the ones of `storage`
+    fn subscribe(&self, listener: Arc<dyn AuthenticatedStorageListener>) -> Result<()> {
+        self.storage.subscribe(listener)
+    }
+
+    async fn commit(&self, transaction: AuthenticatedStorageTransaction) -> Result<()> {
+        let mut encoded = self.storage.begin();
+        for write in transaction.into_writes() {
+            match write {
+                AuthenticatedStorageWrite::Set { id, key, val } => {
+                    encoded.set(&id, key, self.codec.encode(val)?)
+                }
+                AuthenticatedStorageWrite::Del { id, key } => encoded.del(&id, &key),
+            };
+        }
+        self.storage.commit(encoded).await
+    }
+
+    async fn metrics(&self) -> Result<AuthenticatedStorageMetrics> {
+        self.storage.metrics().await
+    }
+
+    async fn entries(&self) -> Result<Vec<(String, String, Vec<u8>)>> {
+        self.storage
+            .entries()
+            .await?
+            .into_iter()
+            .map(|(id, key, val)| Ok((id, key, self.codec.decode(val)?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::authenticated_storage::mem::InMemoryStorage;
+    use ockam_core::compat::string::ToString;
+    use ockam_node::Context;
+
+    /// Reverses the bytes of the values
+    struct Reverse;
+
+    impl ValueCodec for Reverse {
+        fn encode(&self, mut val: Vec<u8>) -> Result<Vec<u8>> {
+            val.reverse();
+            Ok(val)
+        }
+
+        fn decode(&self, val: Vec<u8>) -> Result<Vec<u8>> {
+            self.encode(val)
+        }
+    }
+
+    /// Appends a marker to the values
+    struct Marker;
+
+    impl ValueCodec for Marker {
+        fn encode(&self, mut val: Vec<u8>) -> Result<Vec<u8>> {
+            val.push(b'!');
+            Ok(val)
+        }
+
+        fn decode(&self, mut val: Vec<u8>) -> Result<Vec<u8>> {
+            val.pop();
+            Ok(val)
+        }
+    }
+
+    #[ockam_macros::test]
+    async fn test_codecs_are_applied_in_order(ctx: &mut Context) -> Result<()> {
+        let inner = InMemoryStorage::new();
+        let storage = CodecStorage::new(inner.clone(), (Reverse, Marker));
+
+        storage
+            .set("alice", "role".to_string(), b"admin".to_vec())
+            .await?;
+        assert_eq!(inner.get("alice", "role").await?, Some(b"nimda!".to_vec()));
+        assert_eq!(storage.get("alice", "role").await?, Some(b"admin".to_vec()));
+
+        let mut transaction = storage.begin();
+        transaction
+            .set("bob", "role".to_string(), b"reader".to_vec())
+            .del("alice", "role");
+        storage.commit(transaction).await?;
+        assert_eq!(
+            storage.entries().await?,
+            vec![("bob".to_string(), "role".to_string(), b"reader".to_vec())]
+        );
+
+        ctx.stop().await
+    }
+}
@@ -0,0 +1,282 @@
+use super::{
+    AuthenticatedStorage, AuthenticatedStorageCounters, AuthenticatedStorageEvent,
+    AuthenticatedStorageListener, AuthenticatedStorageListeners, AuthenticatedStorageMetrics,
+    AuthenticatedStorageTransaction, AuthenticatedStorageWrite,
+};
+use crate::IdentityError;
+use ockam_core::async_trait;
+use ockam_core::compat::{
+    boxed::Box,
+    string::{String, ToString},
+    sync::{Arc, RwLock},
+    vec::Vec,
+};
+use ockam_core::Result;
+
+#[derive(Clone)]
+struct Entry<const K: usize, const V: usize> {
+    id: heapless::String<K>,
+    key: heapless::String<K>,
+    val: heapless::Vec<u8, V>,
+}
+
+impl<const K: usize, const V: usize> Entry<K, V> {
+    fn new(id: &str, key: &str, val: &[u8]) -> Result<Self> {
+        Ok(Self {
+            id: bounded_str(id)?,
+            key: bounded_str(key)?,
+            val: bounded_val(val)?,
+        })
+    }
+
+    fn is(&self, id: &str, key: &str) -> bool {
+        self.id.as_str() == id && self.key.as_str() == key
+    }
+}
+
+fn bounded_str<const K: usize>(s: &str) -> Result<heapless::String<K>> {
+    let mut bounded = heapless::String::new();
+    bounded
+        .push_str(s)
+        .map_err(|_| IdentityError::StorageEntryTooLarge)?;
+    Ok(bounded)
+}
+
+fn bounded_val<const V: usize>(val: &[u8]) -> Result<heapless::Vec<u8, V>> {
+    heapless::Vec::from_slice(val).map_err(|_| IdentityError::StorageEntryTooLarge.into())
+}
+
+/// Non-persistent table stored in RAM, holding at most `N` entries whose
+/// ids and keys are at most `K` bytes long and values at most `V` bytes
+/// long
+///
+/// Unlike [`InMemoryStorage`](super::mem::InMemoryStorage) the table
+/// doesn't grow nor allocate: `set` fails once `N` entries are stored, or
+/// when the entry doesn't fit in `K` and `V`.
+#[derive(Clone)]
+pub struct FixedStorage<const N: usize, const K: usize, const V: usize> {
+    entries: Arc<RwLock<heapless::Vec<Entry<K, V>, N>>>,
+    listeners: AuthenticatedStorageListeners,
+    counters: AuthenticatedStorageCounters,
+}
+
+impl<const N: usize, const K: usize, const V: usize> Default for FixedStorage<N, K, V> {
+    fn default() -> Self {
+        Self {
+            entries: Arc::new(RwLock::new(heapless::Vec::new())),
+            listeners: Default::default(),
+            counters: Default::default(),
+        }
+    }
+}
+
+impl<const N: usize, const K: usize, const V: usize> FixedStorage<N, K, V> {
+    /// Constructor
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Maximum number of entries
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Number of stored entries
+    pub fn len(&self) -> usize {
+        self.entries.read().unwrap().len()
+    }
+
+    /// Whether no entry is stored
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[async_trait]
+impl<const N: usize, const K: usize, const V: usize> AuthenticatedStorage
+    for FixedStorage<N, K, V>
+{
+    async fn get(&self, id: &str, key: &str) -> Result<Option<Vec<u8>>> {
+        let entries = self.entries.read().unwrap();
+        let val = entries
+            .iter()
+            .find(|e| e.is(id, key))
+            .map(|e| e.val.to_vec());
+        self.counters.record_get(val.is_some());
+        Ok(val)
+    }
+
+    async fn set(&self, id: &str, key: String, val: Vec<u8>) -> Result<()> {
+        let event = AuthenticatedStorageEvent::Set {
+            id: id.to_string(),
+            key: key.clone(),
+        };
+        set_entry(&mut self.entries.write().unwrap(), id, &key, &val)?;
+        self.counters.record_set();
+        self.listeners.notify(event);
+        Ok(())
+    }
+
+    async fn del(&self, id: &str, key: &str) -> Result<()> {
+        del_entry(&mut self.entries.write().unwrap(), id, key);
+        self.counters.record_del();
+        self.listeners.notify(AuthenticatedStorageEvent::Deleted {
+            id: id.to_string(),
+            key: key.to_string(),
+        });
+        Ok(())
+    }
+
+    fn subscribe(&self, listener: Arc<dyn AuthenticatedStorageListener>) -> Result<()> {
+        self.listeners.subscribe(listener);
+        Ok(())
+    }
+
+    async fn commit(&self, transaction: AuthenticatedStorageTransaction) -> Result<()> {
+        let writes = transaction.into_writes();
+        let events: Vec<_> = writes.iter().map(|w| w.event()).collect();
+        {
+            // Apply the writes to a copy, which replaces the table only if
+            // all of them fit
+            let mut entries = self.entries.write().unwrap();
+            let mut updated = entries.clone();
+            for write in writes {
+                match write {
+                    AuthenticatedStorageWrite::Set { id, key, val } => {
+                        set_entry(&mut updated, &id, &key, &val)?
+                    }
+                    AuthenticatedStorageWrite::Del { id, key } => {
+                        del_entry(&mut updated, &id, &key)
+                    }
+                }
+            }
+            *entries = updated;
+        }
+        for event in events {
+            self.counters.record_event(&event);
+            self.listeners.notify(event);
+        }
+        Ok(())
+    }
+
+    async fn metrics(&self) -> Result<AuthenticatedStorageMetrics> {
+        Ok(self.counters.metrics(self.len()))
+    }
+
+    async fn entries(&self) -> Result<Vec<(String, String, Vec<u8>)>> {
+        let entries = self.entries.read().unwrap();
+        Ok(entries
+            .iter()
+            .map(|e| (e.id.to_string(), e.key.to_string(), e.val.to_vec()))
+            .collect())
+    }
+}
+
+fn set_entry<const N: usize, const K: usize, const V: usize>(
+    entries: &mut heapless::Vec<Entry<K, V>, N>,
+    id: &str,
+    key: &str,
+    val: &[u8],
+) -> Result<()> {
+    match entries.iter_mut().find(|e| e.is(id, key)) {
+        Some(entry) => entry.val = bounded_val(val)?,
+        None => {
+            if entries.push(Entry::new(id, key, val)?).is_err() {
+                return Err(IdentityError::StorageCapacityExceeded.into());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn del_entry<const N: usize, const K: usize, const V: usize>(
+    entries: &mut heapless::Vec<Entry<K, V>, N>,
+    id: &str,
+    key: &str,
+) {
+    if let Some(index) = entries.iter().position(|e| e.is(id, key)) {
+        entries.swap_remove(index);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ockam_core::compat::future::poll_once;
+
+    // Runs without a node, as on a device without std
+    #[test]
+    fn test_fixed_storage_overflow() -> Result<()> {
+        poll_once(async {
+            let storage = FixedStorage::<4, 8, 4>::new();
+            for i in 0..4 {
+                storage.set("alice", format!("key{}", i), vec![i]).await?;
+            }
+            assert_eq!(storage.len(), storage.capacity());
+
+            // Updating an existing entry doesn't need room
+            storage.set("alice", "key0".to_string(), vec![42]).await?;
+            assert_eq!(storage.get("alice", "key0").await?, Some(vec![42]));
+
+            let res = storage.set("bob", "key0".to_string(), vec![0]).await;
+            assert!(res.is_err());
+            assert_eq!(storage.get("bob", "key0").await?, None);
+
+            // Deleting an entry makes room again
+            storage.del("alice", "key1").await?;
+            storage.set("bob", "key0".to_string(), vec![0]).await?;
+            assert_eq!(storage.get("bob", "key0").await?, Some(vec![0]));
+
+            // Ids, keys and values beyond their bounds are rejected, even as
+            // updates
+            storage.del("bob", "key0").await?;
+            assert!(storage
+                .set("a too long id", "key0".to_string(), vec![0])
+                .await
+                .is_err());
+            assert!(storage
+                .set("bob", "a too long key".to_string(), vec![0])
+                .await
+                .is_err());
+            assert!(storage
+                .set("alice", "key0".to_string(), vec![0; 5])
+                .await
+                .is_err());
+            assert_eq!(storage.get("alice", "key0").await?, Some(vec![42]));
+            assert_eq!(storage.len(), 3);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_fixed_storage_transaction_overflow() -> Result<()> {
+        poll_once(async {
+            let storage = FixedStorage::<2, 8, 4>::new();
+            storage.set("alice", "key0".to_string(), vec![0]).await?;
+
+            // The second new entry doesn't fit, so the first one isn't written
+            let mut transaction = storage.begin();
+            transaction
+                .set("alice", "key0".to_string(), vec![42])
+                .set("alice", "key1".to_string(), vec![1])
+                .set("alice", "key2".to_string(), vec![2]);
+            assert!(storage.commit(transaction).await.is_err());
+            assert_eq!(storage.len(), 1);
+            assert_eq!(storage.get("alice", "key0").await?, Some(vec![0]));
+            assert_eq!(storage.get("alice", "key1").await?, None);
+
+            // Deletions in the same transaction make room
+            let mut transaction = storage.begin();
+            transaction
+                .del("alice", "key0")
+                .set("alice", "key1".to_string(), vec![1])
+                .set("alice", "key2".to_string(), vec![2]);
+            storage.commit(transaction).await?;
+            assert_eq!(storage.get("alice", "key0").await?, None);
+            assert_eq!(storage.get("alice", "key2").await?, Some(vec![2]));
+
+            Ok(())
+        })
+    }
+}
@@ -1,4 +1,8 @@
-use super::AuthenticatedStorage;
+use super::{
+    AuthenticatedStorage, AuthenticatedStorageCounters, AuthenticatedStorageEvent,
+    AuthenticatedStorageListener, AuthenticatedStorageListeners, AuthenticatedStorageMetrics,
+    AuthenticatedStorageTransaction, AuthenticatedStorageWrite,
+};
 use ockam_core::async_trait;
 use ockam_core::compat::{
     boxed::Box,
@@ -15,6 +19,8 @@ type Attributes = BTreeMap<String, Vec<u8>>;
 #[derive(Clone, Default)]
 pub struct InMemoryStorage {
     map: Arc<RwLock<BTreeMap<String, Attributes>>>,
+    listeners: AuthenticatedStorageListeners,
+    counters: AuthenticatedStorageCounters,
 }
 
 impl InMemoryStorage {
@@ -28,33 +34,198 @@ impl InMemoryStorage {
 impl AuthenticatedStorage for InMemoryStorage {
     async fn get(&self, id: &str, key: &str) -> Result<Option<Vec<u8>>> {
         let m = self.map.read().unwrap();
-        if let Some(a) = m.get(id) {
-            return Ok(a.get(key).cloned());
-        }
-        Ok(None)
+        let val = m.get(id).and_then(|a| a.get(key).cloned());
+        self.counters.record_get(val.is_some());
+        Ok(val)
     }
 
     async fn set(&self, id: &str, key: String, val: Vec<u8>) -> Result<()> {
-        let mut m = self.map.write().unwrap();
-        match m.get_mut(id) {
-            Some(a) => {
-                a.insert(key, val);
-            }
-            None => {
-                m.insert(id.to_string(), BTreeMap::from([(key, val)]));
-            }
-        }
+        let event = AuthenticatedStorageEvent::Set {
+            id: id.to_string(),
+            key: key.clone(),
+        };
+        set_entry(&mut self.map.write().unwrap(), id, key, val);
+        self.counters.record_set();
+        self.listeners.notify(event);
         Ok(())
     }
 
     async fn del(&self, id: &str, key: &str) -> Result<()> {
-        let mut m = self.map.write().unwrap();
-        if let Some(a) = m.get_mut(id) {
-            a.remove(key);
-            if a.is_empty() {
-                m.remove(id);
+        del_entry(&mut self.map.write().unwrap(), id, key);
+        self.counters.record_del();
+        self.listeners.notify(AuthenticatedStorageEvent::Deleted {
+            id: id.to_string(),
+            key: key.to_string(),
+        });
+        Ok(())
+    }
+
+    fn subscribe(&self, listener: Arc<dyn AuthenticatedStorageListener>) -> Result<()> {
+        self.listeners.subscribe(listener);
+        Ok(())
+    }
+
+    async fn commit(&self, transaction: AuthenticatedStorageTransaction) -> Result<()> {
+        let writes = transaction.into_writes();
+        let events: Vec<_> = writes.iter().map(|w| w.event()).collect();
+        {
+            // Readers see either none or all of the writes
+            let mut m = self.map.write().unwrap();
+            for write in writes {
+                match write {
+                    AuthenticatedStorageWrite::Set { id, key, val } => {
+                        set_entry(&mut m, &id, key, val)
+                    }
+                    AuthenticatedStorageWrite::Del { id, key } => del_entry(&mut m, &id, &key),
+                }
             }
         }
+        for event in events {
+            self.counters.record_event(&event);
+            self.listeners.notify(event);
+        }
         Ok(())
     }
+
+    async fn metrics(&self) -> Result<AuthenticatedStorageMetrics> {
+        let entries = self.map.read().unwrap().values().map(|a| a.len()).sum();
+        Ok(self.counters.metrics(entries))
+    }
+
+    async fn entries(&self) -> Result<Vec<(String, String, Vec<u8>)>> {
+        let m = self.map.read().unwrap();
+        Ok(m.iter()
+            .flat_map(|(id, a)| {
+                a.iter()
+                    .map(move |(key, val)| (id.clone(), key.clone(), val.clone()))
+            })
+            .collect())
+    }
+}
+
+fn set_entry(m: &mut BTreeMap<String, Attributes>, id: &str, key: String, val: Vec<u8>) {
+    match m.get_mut(id) {
+        Some(a) => {
+            a.insert(key, val);
+        }
+        None => {
+            m.insert(id.to_string(), BTreeMap::from([(key, val)]));
+        }
+    }
+}
+
+fn del_entry(m: &mut BTreeMap<String, Attributes>, id: &str, key: &str) {
+    if let Some(a) = m.get_mut(id) {
+        a.remove(key);
+        if a.is_empty() {
+            m.remove(id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::IdentityError;
+    use ockam_node::Context;
+
+    /// Installs attributes for `id`, failing after the first ones were written
+    async fn install_attributes(storage: &InMemoryStorage, id: &str) -> Result<()> {
+        let mut transaction = storage.begin();
+        transaction
+            .set(id, "role".to_string(), b"admin".to_vec())
+            .del(id, "expires");
+        if id == "bob" {
+            return Err(IdentityError::InvalidCredentialFormat.into());
+        }
+        transaction.set(id, "project".to_string(), b"p1".to_vec());
+        storage.commit(transaction).await
+    }
+
+    #[ockam_macros::test]
+    async fn test_transaction(ctx: &mut Context) -> Result<()> {
+        let storage = InMemoryStorage::new();
+        for id in ["alice", "bob"] {
+            storage.set(id, "expires".to_string(), vec![0]).await?;
+        }
+
+        install_attributes(&storage, "alice").await?;
+        assert_eq!(storage.get("alice", "role").await?, Some(b"admin".to_vec()));
+        assert_eq!(storage.get("alice", "project").await?, Some(b"p1".to_vec()));
+        assert_eq!(storage.get("alice", "expires").await?, None);
+
+        // The failed transaction leaves storage unchanged
+        assert!(install_attributes(&storage, "bob").await.is_err());
+        assert_eq!(storage.get("bob", "role").await?, None);
+        assert_eq!(storage.get("bob", "project").await?, None);
+        assert_eq!(storage.get("bob", "expires").await?, Some(vec![0]));
+
+        // So does a rolled back one
+        let mut transaction = storage.begin();
+        transaction.set("bob", "role".to_string(), b"admin".to_vec());
+        transaction.rollback();
+        assert_eq!(storage.get("bob", "role").await?, None);
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn test_metrics(ctx: &mut Context) -> Result<()> {
+        let storage = InMemoryStorage::new();
+        storage.set("alice", "role".to_string(), vec![1]).await?;
+        storage.set("alice", "project".to_string(), vec![2]).await?;
+        storage.set("bob", "role".to_string(), vec![3]).await?;
+        storage.get("alice", "role").await?;
+        storage.get("bob", "project").await?;
+        storage.del("bob", "role").await?;
+
+        let mut transaction = storage.begin();
+        transaction
+            .set("carol", "role".to_string(), vec![4])
+            .del("alice", "project");
+        storage.commit(transaction).await?;
+
+        // Clones share their counters
+        let metrics = storage.clone().metrics().await?;
+        assert_eq!(
+            metrics,
+            AuthenticatedStorageMetrics {
+                gets: 2,
+                hits: 1,
+                misses: 1,
+                sets: 4,
+                dels: 2,
+                entries: 2,
+            }
+        );
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn test_watch_key(ctx: &mut Context) -> Result<()> {
+        let storage = InMemoryStorage::new();
+        storage.set("alice", "flag".to_string(), vec![0]).await?;
+        let mut watch = storage.watch_key("alice", "flag").await?;
+
+        storage.set("alice", "flag".to_string(), vec![1]).await?;
+        assert_eq!(watch.next().await?, Some(vec![1]));
+
+        // Other entries and unchanged values aren't yielded
+        storage.set("alice", "role".to_string(), vec![9]).await?;
+        storage.set("bob", "flag".to_string(), vec![9]).await?;
+        storage.set("alice", "flag".to_string(), vec![1]).await?;
+        storage.set("alice", "flag".to_string(), vec![2]).await?;
+        assert_eq!(watch.next().await?, Some(vec![2]));
+
+        storage.del("alice", "flag").await?;
+        assert_eq!(watch.next().await?, None);
+
+        let mut transaction = storage.begin();
+        transaction.set("alice", "flag".to_string(), vec![3]);
+        storage.commit(transaction).await?;
+        assert_eq!(watch.next().await?, Some(vec![3]));
+
+        ctx.stop().await
+    }
 }
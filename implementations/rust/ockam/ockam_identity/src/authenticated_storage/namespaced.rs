@@ -0,0 +1,158 @@
+use super::{
+    AuthenticatedStorage, AuthenticatedStorageEvent, AuthenticatedStorageListener,
+    AuthenticatedStorageMetrics, AuthenticatedStorageTransaction, AuthenticatedStorageWrite,
+};
+use ockam_core::async_trait;
+use ockam_core::compat::{
+    boxed::Box,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+use ockam_core::Result;
+
+/// Storage prefixing the keys of another storage with a namespace
+///
+/// Subsystems sharing a storage through different namespaces, e.g. the
+/// credentials and an application, can use the same keys without
+/// overwriting each other's entries. Ids are stored as is, and keys are
+/// stored as `<namespace>/<key>`.
+#[derive(Clone)]
+pub struct NamespacedStorage<S: AuthenticatedStorage + Clone> {
+    storage: S,
+    prefix: Arc<str>,
+}
+
+impl<S: AuthenticatedStorage + Clone> NamespacedStorage<S> {
+    /// Constructor
+    pub fn new(storage: S, namespace: &str) -> Self {
+        Self {
+            storage,
+            prefix: format!("{}/", namespace).into(),
+        }
+    }
+
+    /// The shared storage
+    pub fn inner(&self) -> &S {
+        &self.storage
+    }
+
+    fn key(&self, key: &str) -> String {
+        format!("{}{}", self.prefix, key)
+    }
+}
+
+#[async_trait]
+impl<S: AuthenticatedStorage + Clone> AuthenticatedStorage for NamespacedStorage<S> {
+    async fn get(&self, id: &str, key: &str) -> Result<Option<Vec<u8>>> {
+        self.storage.get(id, &self.key(key)).await
+    }
+
+    async fn set(&self, id: &str, key: String, val: Vec<u8>) -> Result<()> {
+        self.storage.set(id, self.key(&key), val).await
+    }
+
+    async fn del(&self, id: &str, key: &str) -> Result<()> {
+        self.storage.del(id, &self.key(key)).await
+    }
+
+    /// The listener is only notified of the changes to the namespace
+    fn subscribe(&self, listener: Arc<dyn AuthenticatedStorageListener>) -> Result<()> {
+        self.storage.subscribe(Arc::new(NamespacedListener {
+            prefix: Arc::clone(&self.prefix),
+            listener,
+        }))
+    }
+
+    async fn commit(&self, transaction: AuthenticatedStorageTransaction) -> Result<()> {
+        let mut namespaced = self.storage.begin();
+        for write in transaction.into_writes() {
+            match write {
+                AuthenticatedStorageWrite::Set { id, key, val } => {
+                    namespaced.set(&id, self.key(&key), val)
+                }
+                AuthenticatedStorageWrite::Del { id, key } => namespaced.del(&id, &self.key(&key)),
+            };
+        }
+        self.storage.commit(namespaced).await
+    }
+
+    /// Metrics are the ones of the whole shared storage
+    async fn metrics(&self) -> Result<AuthenticatedStorageMetrics> {
+        self.storage.metrics().await
+    }
+
+    async fn entries(&self) -> Result<Vec<(String, String, Vec<u8>)>> {
+        Ok(self
+            .storage
+            .entries()
+            .await?
+            .into_iter()
+            .filter_map(|(id, key, val)| {
+                let key = key.strip_prefix(&*self.prefix)?.to_string();
+                Some((id, key, val))
+            })
+            .collect())
+    }
+}
+
+/// Forwards the events of a namespace, without its prefix
+struct NamespacedListener {
+    prefix: Arc<str>,
+    listener: Arc<dyn AuthenticatedStorageListener>,
+}
+
+impl AuthenticatedStorageListener for NamespacedListener {
+    fn on_event(&self, event: &AuthenticatedStorageEvent) -> bool {
+        let key = match event.key().strip_prefix(&*self.prefix) {
+            Some(key) => key.to_string(),
+            None => return true,
+        };
+        let id = event.id().to_string();
+        let event = match event {
+            AuthenticatedStorageEvent::Set { .. } => AuthenticatedStorageEvent::Set { id, key },
+            AuthenticatedStorageEvent::Deleted { .. } => {
+                AuthenticatedStorageEvent::Deleted { id, key }
+            }
+        };
+        self.listener.on_event(&event)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::authenticated_storage::mem::InMemoryStorage;
+    use ockam_node::Context;
+
+    #[ockam_macros::test]
+    async fn test_namespaces_isolate_identical_keys(ctx: &mut Context) -> Result<()> {
+        let shared = InMemoryStorage::new();
+        let credentials = NamespacedStorage::new(shared.clone(), "credentials");
+        let app = NamespacedStorage::new(shared.clone(), "app");
+
+        credentials
+            .set("alice", "role".to_string(), b"admin".to_vec())
+            .await?;
+        app.set("alice", "role".to_string(), b"player".to_vec())
+            .await?;
+        assert_eq!(
+            credentials.get("alice", "role").await?,
+            Some(b"admin".to_vec())
+        );
+        assert_eq!(app.get("alice", "role").await?, Some(b"player".to_vec()));
+
+        app.del("alice", "role").await?;
+        assert_eq!(app.get("alice", "role").await?, None);
+        assert_eq!(
+            credentials.entries().await?,
+            vec![("alice".to_string(), "role".to_string(), b"admin".to_vec())]
+        );
+        assert_eq!(
+            shared.get("alice", "credentials/role").await?,
+            Some(b"admin".to_vec())
+        );
+
+        ctx.stop().await
+    }
+}
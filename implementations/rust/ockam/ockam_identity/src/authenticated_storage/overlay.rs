@@ -0,0 +1,212 @@
+use super::{
+    AuthenticatedStorage, AuthenticatedStorageEvent, AuthenticatedStorageListener,
+    AuthenticatedStorageListeners, AuthenticatedStorageTransaction, AuthenticatedStorageWrite,
+};
+use crate::IdentityError;
+use ockam_core::async_trait;
+use ockam_core::compat::{
+    boxed::Box,
+    collections::BTreeMap,
+    string::{String, ToString},
+    sync::Arc,
+    vec,
+    vec::Vec,
+};
+use ockam_core::Result;
+
+/// Tag of an overlay entry hiding the base entry of the same key
+const TOMBSTONE: u8 = 0;
+/// Tag of an overlay entry holding a value
+const VALUE: u8 = 1;
+
+/// Writable storage layered over a read-only base
+///
+/// Reads fall through to `base` for the entries the overlay doesn't
+/// have. Writes only go to the overlay, shadowing the entries of `base`,
+/// and deletes leave a tombstone in the overlay which hides the entry of
+/// `base`, so `base` itself is never modified.
+///
+/// Overlay entries are tagged to tell values from tombstones, so the
+/// overlay storage should not be used directly.
+#[derive(Clone)]
+pub struct OverlayStorage<B: AuthenticatedStorage + Clone, O: AuthenticatedStorage + Clone> {
+    base: B,
+    overlay: O,
+    listeners: AuthenticatedStorageListeners,
+}
+
+impl<B: AuthenticatedStorage + Clone, O: AuthenticatedStorage + Clone> OverlayStorage<B, O> {
+    /// Constructor
+    pub fn new(base: B, overlay: O) -> Self {
+        Self {
+            base,
+            overlay,
+            listeners: Default::default(),
+        }
+    }
+
+    /// The read-only base storage
+    pub fn base(&self) -> &B {
+        &self.base
+    }
+}
+
+#[async_trait]
+impl<B: AuthenticatedStorage + Clone, O: AuthenticatedStorage + Clone> AuthenticatedStorage
+    for OverlayStorage<B, O>
+{
+    async fn get(&self, id: &str, key: &str) -> Result<Option<Vec<u8>>> {
+        match self.overlay.get(id, key).await? {
+            Some(entry) => match entry.split_first() {
+                Some((&VALUE, val)) => Ok(Some(val.to_vec())),
+                Some((&TOMBSTONE, [])) => Ok(None),
+                _ => Err(IdentityError::StorageOverlayEntryInvalid.into()),
+            },
+            None => self.base.get(id, key).await,
+        }
+    }
+
+    async fn set(&self, id: &str, key: String, val: Vec<u8>) -> Result<()> {
+        let event = AuthenticatedStorageEvent::Set {
+            id: id.to_string(),
+            key: key.clone(),
+        };
+        self.overlay.set(id, key, tag_value(val)).await?;
+        self.listeners.notify(event);
+        Ok(())
+    }
+
+    async fn del(&self, id: &str, key: &str) -> Result<()> {
+        self.overlay
+            .set(id, key.to_string(), vec![TOMBSTONE])
+            .await?;
+        self.listeners.notify(AuthenticatedStorageEvent::Deleted {
+            id: id.to_string(),
+            key: key.to_string(),
+        });
+        Ok(())
+    }
+
+    fn subscribe(&self, listener: Arc<dyn AuthenticatedStorageListener>) -> Result<()> {
+        self.listeners.subscribe(listener);
+        Ok(())
+    }
+
+    /// Commit the writes to the overlay, which must support transactions
+    async fn commit(&self, transaction: AuthenticatedStorageTransaction) -> Result<()> {
+        let writes = transaction.into_writes();
+        let events: Vec<_> = writes.iter().map(|w| w.event()).collect();
+
+        let mut overlay_transaction = self.overlay.begin();
+        for write in writes {
+            match write {
+                AuthenticatedStorageWrite::Set { id, key, val } => {
+                    overlay_transaction.set(&id, key, tag_value(val))
+                }
+                AuthenticatedStorageWrite::Del { id, key } => {
+                    overlay_transaction.set(&id, key, vec![TOMBSTONE])
+                }
+            };
+        }
+        self.overlay.commit(overlay_transaction).await?;
+
+        for event in events {
+            self.listeners.notify(event);
+        }
+        Ok(())
+    }
+
+    /// Entries of the base, as shadowed or hidden by the overlay
+    async fn entries(&self) -> Result<Vec<(String, String, Vec<u8>)>> {
+        let mut entries: BTreeMap<_, _> = self
+            .base
+            .entries()
+            .await?
+            .into_iter()
+            .map(|(id, key, val)| ((id, key), val))
+            .collect();
+        for (id, key, entry) in self.overlay.entries().await? {
+            match entry.split_first() {
+                Some((&VALUE, val)) => {
+                    entries.insert((id, key), val.to_vec());
+                }
+                Some((&TOMBSTONE, [])) => {
+                    entries.remove(&(id, key));
+                }
+                _ => return Err(IdentityError::StorageOverlayEntryInvalid.into()),
+            }
+        }
+        Ok(entries
+            .into_iter()
+            .map(|((id, key), val)| (id, key, val))
+            .collect())
+    }
+}
+
+fn tag_value(mut val: Vec<u8>) -> Vec<u8> {
+    val.insert(0, VALUE);
+    val
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::authenticated_storage::mem::InMemoryStorage;
+    use ockam_node::Context;
+
+    #[ockam_macros::test]
+    async fn test_overlay_shadows_and_tombstones_base(ctx: &mut Context) -> Result<()> {
+        let base = InMemoryStorage::new();
+        base.set("alice", "role".to_string(), b"reader".to_vec())
+            .await?;
+        base.set("alice", "team".to_string(), b"blue".to_vec())
+            .await?;
+
+        let storage = OverlayStorage::new(base.clone(), InMemoryStorage::new());
+
+        // Reads fall through to the base
+        assert_eq!(
+            storage.get("alice", "role").await?,
+            Some(b"reader".to_vec())
+        );
+
+        // Writes shadow the base, which is left untouched
+        storage
+            .set("alice", "role".to_string(), b"admin".to_vec())
+            .await?;
+        assert_eq!(storage.get("alice", "role").await?, Some(b"admin".to_vec()));
+        assert_eq!(base.get("alice", "role").await?, Some(b"reader".to_vec()));
+
+        // Deletes hide the base entry with a tombstone
+        storage.del("alice", "team").await?;
+        assert_eq!(storage.get("alice", "team").await?, None);
+        assert_eq!(base.get("alice", "team").await?, Some(b"blue".to_vec()));
+
+        // A tombstone can be overwritten
+        storage
+            .set("alice", "team".to_string(), b"red".to_vec())
+            .await?;
+        assert_eq!(storage.get("alice", "team").await?, Some(b"red".to_vec()));
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn test_overlay_transaction(ctx: &mut Context) -> Result<()> {
+        let base = InMemoryStorage::new();
+        base.set("alice", "role".to_string(), b"reader".to_vec())
+            .await?;
+        let storage = OverlayStorage::new(base, InMemoryStorage::new());
+
+        let mut transaction = storage.begin();
+        transaction
+            .del("alice", "role")
+            .set("bob", "role".to_string(), b"admin".to_vec());
+        storage.commit(transaction).await?;
+
+        assert_eq!(storage.get("alice", "role").await?, None);
+        assert_eq!(storage.get("bob", "role").await?, Some(b"admin".to_vec()));
+
+        ctx.stop().await
+    }
+}
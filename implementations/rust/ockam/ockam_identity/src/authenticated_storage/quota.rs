@@ -0,0 +1,339 @@
+use super::{
+    AuthenticatedStorage, AuthenticatedStorageListener, AuthenticatedStorageMetrics,
+    AuthenticatedStorageTransaction, AuthenticatedStorageWrite,
+};
+use crate::IdentityError;
+use core::future::Future;
+use ockam_core::async_trait;
+use ockam_core::compat::{
+    boxed::Box,
+    collections::BTreeMap,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+use ockam_core::Result;
+use ockam_node::compat::asynchronous::Mutex;
+
+/// Limits of a [`QuotaStorage`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StorageQuota {
+    max_entries: Option<usize>,
+    max_bytes: Option<usize>,
+}
+
+impl StorageQuota {
+    /// No limit, until set with the `with_*` methods
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Store at most `max_entries` entries
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = Some(max_entries);
+        self
+    }
+
+    /// Store at most `max_bytes` bytes, counting the id, the key and the
+    /// value of every entry
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Maximum number of entries
+    pub fn max_entries(&self) -> Option<usize> {
+        self.max_entries
+    }
+
+    /// Maximum number of bytes
+    pub fn max_bytes(&self) -> Option<usize> {
+        self.max_bytes
+    }
+
+    /// Check a storage of `entries` entries taking `bytes` bytes is within the quota
+    fn check(&self, entries: usize, bytes: usize) -> Result<()> {
+        let exceeded = self.max_entries.map_or(false, |max| entries > max)
+            || self.max_bytes.map_or(false, |max| bytes > max);
+        if exceeded {
+            return Err(IdentityError::StorageQuotaExceeded.into());
+        }
+        Ok(())
+    }
+}
+
+/// Sizes of the entries of a storage, kept up to date as it is written
+struct Usage {
+    sizes: BTreeMap<(String, String), usize>,
+    bytes: usize,
+}
+
+impl Usage {
+    fn new(entries: Vec<(String, String, Vec<u8>)>) -> Self {
+        let mut usage = Self {
+            sizes: BTreeMap::new(),
+            bytes: 0,
+        };
+        for (id, key, val) in entries {
+            let size = entry_size(&id, &key, &val);
+            usage.apply(id, key, Some(size));
+        }
+        usage
+    }
+
+    /// Number of entries and bytes once `writes` are applied, writes
+    /// being the size of an entry or `None` for a deletion
+    fn after(&self, writes: &BTreeMap<(String, String), Option<usize>>) -> (usize, usize) {
+        let (mut entries, mut bytes) = (self.sizes.len(), self.bytes);
+        for (entry, size) in writes {
+            if let Some(previous) = self.sizes.get(entry) {
+                entries -= 1;
+                bytes -= previous;
+            }
+            if let Some(size) = size {
+                entries += 1;
+                bytes += size;
+            }
+        }
+        (entries, bytes)
+    }
+
+    fn apply(&mut self, id: String, key: String, size: Option<usize>) {
+        let previous = match size {
+            Some(size) => {
+                self.bytes += size;
+                self.sizes.insert((id, key), size)
+            }
+            None => self.sizes.remove(&(id, key)),
+        };
+        if let Some(previous) = previous {
+            self.bytes -= previous;
+        }
+    }
+}
+
+/// Storage rejecting the writes which would take another storage past a
+/// [`StorageQuota`]
+///
+/// Rejected writes fail with [`IdentityError::StorageQuotaExceeded`] and
+/// leave the storage unchanged. Updating or deleting entries is allowed as
+/// long as the storage ends up within its quota. The size of the storage
+/// is read from its entries on the first write, so the storage must
+/// support [`AuthenticatedStorage::entries`], then kept up to date by the
+/// writes. Writes made to the inner storage directly aren't accounted for.
+#[derive(Clone)]
+pub struct QuotaStorage<S: AuthenticatedStorage + Clone> {
+    storage: S,
+    quota: StorageQuota,
+    /// `None` until the first write
+    usage: Arc<Mutex<Option<Usage>>>,
+}
+
+impl<S: AuthenticatedStorage + Clone> QuotaStorage<S> {
+    /// Constructor
+    pub fn new(storage: S, quota: StorageQuota) -> Self {
+        Self {
+            storage,
+            quota,
+            usage: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// The limited storage
+    pub fn inner(&self) -> &S {
+        &self.storage
+    }
+
+    /// The limits of the storage
+    pub fn quota(&self) -> &StorageQuota {
+        &self.quota
+    }
+
+    /// The usage of the storage, read from its entries the first time
+    async fn loaded<'a>(&self, usage: &'a mut Option<Usage>) -> Result<&'a mut Usage> {
+        if usage.is_none() {
+            *usage = Some(Usage::new(self.storage.entries().await?));
+        }
+        Ok(usage.as_mut().unwrap())
+    }
+
+    /// Apply `writes` to the usage once they are written, if they keep the
+    /// storage within its quota
+    async fn write_within_quota<F>(
+        &self,
+        writes: BTreeMap<(String, String), Option<usize>>,
+        write: F,
+    ) -> Result<()>
+    where
+        F: Future<Output = Result<()>>,
+    {
+        // The lock is held during the write so that concurrent writes
+        // can't take the storage past its quota together
+        let mut usage = self.usage.lock().await;
+        let usage = self.loaded(&mut usage).await?;
+        let (entries, bytes) = usage.after(&writes);
+        let grows = entries > usage.sizes.len() || bytes > usage.bytes;
+        if grows {
+            self.quota.check(entries, bytes)?;
+        }
+        write.await?;
+        for ((id, key), size) in writes {
+            usage.apply(id, key, size);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<S: AuthenticatedStorage + Clone> AuthenticatedStorage for QuotaStorage<S> {
+    async fn get(&self, id: &str, key: &str) -> Result<Option<Vec<u8>>> {
+        self.storage.get(id, key).await
+    }
+
+    async fn set(&self, id: &str, key: String, val: Vec<u8>) -> Result<()> {
+        let mut writes = BTreeMap::new();
+        writes.insert(
+            (id.to_string(), key.clone()),
+            Some(entry_size(id, &key, &val)),
+        );
+        self.write_within_quota(writes, self.storage.set(id, key, val))
+            .await
+    }
+
+    async fn del(&self, id: &str, key: &str) -> Result<()> {
+        let mut writes = BTreeMap::new();
+        writes.insert((id.to_string(), key.to_string()), None);
+        self.write_within_quota(writes, self.storage.del(id, key))
+            .await
+    }
+
+    fn subscribe(&self, listener: Arc<dyn AuthenticatedStorageListener>) -> Result<()> {
+        self.storage.subscribe(listener)
+    }
+
+    async fn commit(&self, transaction: AuthenticatedStorageTransaction) -> Result<()> {
+        let mut writes = BTreeMap::new();
+        for write in transaction.writes() {
+            match write {
+                AuthenticatedStorageWrite::Set { id, key, val } => {
+                    writes.insert((id.clone(), key.clone()), Some(entry_size(id, key, val)));
+                }
+                AuthenticatedStorageWrite::Del { id, key } => {
+                    writes.insert((id.clone(), key.clone()), None);
+                }
+            }
+        }
+        self.write_within_quota(writes, self.storage.commit(transaction))
+            .await
+    }
+
+    async fn metrics(&self) -> Result<AuthenticatedStorageMetrics> {
+        self.storage.metrics().await
+    }
+
+    async fn entries(&self) -> Result<Vec<(String, String, Vec<u8>)>> {
+        self.storage.entries().await
+    }
+}
+
+fn entry_size(id: &str, key: &str, val: &[u8]) -> usize {
+    id.len() + key.len() + val.len()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::authenticated_storage::mem::InMemoryStorage;
+    use ockam_node::Context;
+
+    #[ockam_macros::test]
+    async fn test_set_past_quota_is_rejected(ctx: &mut Context) -> Result<()> {
+        let storage = QuotaStorage::new(
+            InMemoryStorage::new(),
+            StorageQuota::new().with_max_entries(3),
+        );
+        for i in 0..3 {
+            storage.set("alice", format!("key{}", i), vec![i]).await?;
+        }
+
+        let err = storage
+            .set("bob", "key0".to_string(), vec![0])
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.code().kind,
+            ockam_core::errcode::Kind::ResourceExhausted
+        );
+
+        // The stored entries are intact
+        assert_eq!(storage.get("bob", "key0").await?, None);
+        for i in 0..3 {
+            assert_eq!(
+                storage.get("alice", &format!("key{}", i)).await?,
+                Some(vec![i])
+            );
+        }
+
+        // Updating an entry doesn't need room, deleting one makes room
+        storage.set("alice", "key0".to_string(), vec![42]).await?;
+        storage.del("alice", "key1").await?;
+        storage.set("bob", "key0".to_string(), vec![0]).await?;
+        assert_eq!(storage.get("bob", "key0").await?, Some(vec![0]));
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn test_byte_quota(ctx: &mut Context) -> Result<()> {
+        // Room for "alice" + "key" + 8 bytes
+        let storage = QuotaStorage::new(
+            InMemoryStorage::new(),
+            StorageQuota::new().with_max_bytes(16),
+        );
+        storage.set("alice", "key".to_string(), vec![0; 8]).await?;
+
+        // Growing the value past the quota is rejected
+        assert!(storage
+            .set("alice", "key".to_string(), vec![0; 9])
+            .await
+            .is_err());
+        assert_eq!(storage.get("alice", "key").await?, Some(vec![0; 8]));
+
+        // So is a transaction whose result doesn't fit, even though it
+        // deletes entries
+        let mut transaction = storage.begin();
+        transaction
+            .del("alice", "key")
+            .set("bob", "key".to_string(), vec![0; 16]);
+        assert!(storage.commit(transaction).await.is_err());
+        assert_eq!(storage.get("alice", "key").await?, Some(vec![0; 8]));
+        assert_eq!(storage.get("bob", "key").await?, None);
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn test_existing_entries_count_towards_the_quota(ctx: &mut Context) -> Result<()> {
+        let inner = InMemoryStorage::new();
+        inner.set("alice", "key0".to_string(), vec![0]).await?;
+        inner.set("alice", "key1".to_string(), vec![1]).await?;
+
+        let storage = QuotaStorage::new(inner, StorageQuota::new().with_max_entries(3));
+        storage.set("alice", "key2".to_string(), vec![2]).await?;
+        assert!(storage
+            .set("alice", "key3".to_string(), vec![3])
+            .await
+            .is_err());
+
+        // The running size follows deletions
+        storage.del("alice", "key0").await?;
+        storage.del("alice", "key0").await?;
+        storage.set("alice", "key3".to_string(), vec![3]).await?;
+        assert!(storage
+            .set("alice", "key4".to_string(), vec![4])
+            .await
+            .is_err());
+
+        ctx.stop().await
+    }
+}
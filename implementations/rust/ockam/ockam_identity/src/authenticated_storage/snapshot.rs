@@ -0,0 +1,118 @@
+use super::AuthenticatedStorage;
+use crate::{Identity, IdentityError, IdentityVault, PublicIdentity};
+use ockam_core::compat::{string::String, vec::Vec};
+use ockam_core::vault::Signature;
+use ockam_core::Result;
+use serde::{Deserialize, Serialize};
+
+/// Entries of a storage, as `(id, key, value)`
+type Entries = Vec<(String, String, Vec<u8>)>;
+
+/// Serialized entries of a storage and the signature over them
+#[derive(Serialize, Deserialize)]
+struct StorageSnapshot {
+    entries: Vec<u8>,
+    signature: Signature,
+}
+
+impl<V: IdentityVault> Identity<V> {
+    /// Export all entries of `storage` to a snapshot signed by this identity
+    ///
+    /// Use [`Identity::import_storage`] to load the snapshot.
+    pub async fn export_storage(&self, storage: &impl AuthenticatedStorage) -> Result<Vec<u8>> {
+        let entries: Entries = storage.entries().await?;
+        let entries = serde_bare::to_vec(&entries).map_err(|_| IdentityError::BareError)?;
+        let signature = self
+            .create_signature(&entries, Some(self.signing_key_label().await))
+            .await?;
+
+        serde_bare::to_vec(&StorageSnapshot { entries, signature })
+            .map_err(|_| IdentityError::BareError.into())
+    }
+
+    /// Load a snapshot created by [`Identity::export_storage`] into `storage`
+    ///
+    /// The snapshot must be signed by `signer`. Its entries are written
+    /// in a single transaction, and only once the signature is verified,
+    /// so `storage` is left unchanged if the snapshot was tampered with.
+    pub async fn import_storage(
+        &self,
+        storage: &impl AuthenticatedStorage,
+        snapshot: &[u8],
+        signer: &PublicIdentity,
+    ) -> Result<()> {
+        let snapshot: StorageSnapshot =
+            serde_bare::from_slice(snapshot).map_err(|_| IdentityError::InvalidStorageSnapshot)?;
+        if !signer
+            .verify_signature(
+                &snapshot.signature,
+                &snapshot.entries,
+                Some(signer.signing_key_label()),
+                &self.vault,
+            )
+            .await?
+        {
+            return Err(IdentityError::InvalidStorageSnapshot.into());
+        }
+        let entries: Entries = serde_bare::from_slice(&snapshot.entries)
+            .map_err(|_| IdentityError::InvalidStorageSnapshot)?;
+
+        let mut transaction = storage.begin();
+        for (id, key, val) in entries {
+            transaction.set(&id, key, val);
+        }
+        storage.commit(transaction).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::authenticated_storage::mem::InMemoryStorage;
+    use ockam_core::compat::string::ToString;
+    use ockam_node::Context;
+    use ockam_vault::Vault;
+
+    #[ockam_macros::test]
+    async fn test_tampered_snapshot_is_rejected(ctx: &mut Context) -> Result<()> {
+        let vault = Vault::create();
+        let alice = Identity::create(ctx, &vault).await?;
+        let bob = Identity::create(ctx, &vault).await?;
+        let alice_public = alice.to_public().await?;
+
+        let storage = InMemoryStorage::new();
+        storage
+            .set("bob", "role".to_string(), b"admin".to_vec())
+            .await?;
+        storage
+            .set("carol", "role".to_string(), b"reader".to_vec())
+            .await?;
+        let snapshot = alice.export_storage(&storage).await?;
+
+        // Every altered byte invalidates the snapshot
+        for i in 0..snapshot.len() {
+            let mut tampered = snapshot.clone();
+            tampered[i] ^= 0x01;
+            let imported = InMemoryStorage::new();
+            assert!(bob
+                .import_storage(&imported, &tampered, &alice_public)
+                .await
+                .is_err());
+            assert!(imported.entries().await?.is_empty());
+        }
+
+        // Snapshots signed by someone else are rejected
+        let bob_public = bob.to_public().await?;
+        assert!(bob
+            .import_storage(&InMemoryStorage::new(), &snapshot, &bob_public)
+            .await
+            .is_err());
+
+        let imported = InMemoryStorage::new();
+        bob.import_storage(&imported, &snapshot, &alice_public)
+            .await?;
+        assert_eq!(imported.entries().await?, storage.entries().await?);
+
+        ctx.stop().await
+    }
+}
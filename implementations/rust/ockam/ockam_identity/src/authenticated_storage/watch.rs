@@ -0,0 +1,74 @@
+use super::{AuthenticatedStorage, AuthenticatedStorageEvent, AuthenticatedStorageListener};
+use crate::IdentityError;
+use ockam_core::compat::{
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+use ockam_core::{AsyncTryClone, Result};
+use ockam_node::tokio::sync::mpsc::{self, error::TrySendError};
+
+/// Values of an [`AuthenticatedStorage`] entry, see
+/// [`AuthenticatedStorage::watch_key`]
+///
+/// Changes made faster than they are read are coalesced: the watch yields
+/// the latest value of the entry, and never the same value twice in a row.
+pub struct AuthenticatedStorageWatch<S> {
+    storage: S,
+    id: String,
+    key: String,
+    last: Option<Vec<u8>>,
+    changes: mpsc::Receiver<()>,
+}
+
+impl<S: AuthenticatedStorage> AuthenticatedStorageWatch<S> {
+    pub(crate) async fn new(storage: &S, id: &str, key: &str) -> Result<Self> {
+        // Capacity 1: a pending notification covers any later change
+        let (sender, changes) = mpsc::channel(1);
+        storage.subscribe(Arc::new(KeyListener {
+            id: id.to_string(),
+            key: key.to_string(),
+            sender,
+        }))?;
+        let storage = storage.async_try_clone().await?;
+        let last = storage.get(id, key).await?;
+        Ok(Self {
+            storage,
+            id: id.to_string(),
+            key: key.to_string(),
+            last,
+            changes,
+        })
+    }
+
+    /// Wait for the entry to change, and return its new value, `None` if
+    /// it was deleted
+    pub async fn next(&mut self) -> Result<Option<Vec<u8>>> {
+        loop {
+            if self.changes.recv().await.is_none() {
+                return Err(IdentityError::StorageWatchClosed.into());
+            }
+            let val = self.storage.get(&self.id, &self.key).await?;
+            if val != self.last {
+                self.last = val.clone();
+                return Ok(val);
+            }
+        }
+    }
+}
+
+/// Notifies an [`AuthenticatedStorageWatch`] of the changes of its entry
+struct KeyListener {
+    id: String,
+    key: String,
+    sender: mpsc::Sender<()>,
+}
+
+impl AuthenticatedStorageListener for KeyListener {
+    fn on_event(&self, event: &AuthenticatedStorageEvent) -> bool {
+        if event.id() != self.id || event.key() != self.key {
+            return !self.sender.is_closed();
+        }
+        !matches!(self.sender.try_send(()), Err(TrySendError::Closed(_)))
+    }
+}
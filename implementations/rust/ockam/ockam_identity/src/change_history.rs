@@ -173,6 +173,15 @@ impl IdentityChangeHistory {
         last_key_change.change().public_key()
     }
 
+    /// Signing key is used for proofs if present, root key otherwise
+    pub(crate) fn signing_key_label(existing_changes: &[IdentitySignedChange]) -> &'static str {
+        if Self::find_last_key_change(existing_changes, IdentityStateConst::SIGNING_LABEL).is_ok() {
+            IdentityStateConst::SIGNING_LABEL
+        } else {
+            IdentityStateConst::ROOT_LABEL
+        }
+    }
+
     pub(crate) fn get_current_root_public_key(
         existing_changes: &[IdentitySignedChange],
     ) -> Result<PublicKey> {
@@ -1,55 +1,194 @@
+mod byte_budget;
+pub(crate) use byte_budget::*;
+mod channel_limit;
+pub(crate) use channel_limit::*;
 mod encryptor;
 pub(crate) use encryptor::*;
 mod decryptor;
 pub(crate) use decryptor::*;
+mod established_hook;
+pub use established_hook::*;
+mod handshake_limit;
+pub use handshake_limit::DEFAULT_MAX_CONCURRENT_HANDSHAKES;
+pub(crate) use handshake_limit::*;
+mod handshake_timeout;
+pub use handshake_timeout::*;
+mod keepalive;
+#[cfg(feature = "std")]
+pub(crate) use keepalive::ChannelKeepaliveWorker;
+pub use keepalive::SecureChannelKeepalive;
 mod listener;
 pub(crate) use listener::*;
 mod messages;
 pub(crate) use messages::*;
+mod negotiation;
+pub(crate) use negotiation::SecureChannelNegotiations;
+pub use negotiation::{ChannelCompression, SecureChannelNegotiation};
+mod options;
+pub use options::*;
+mod pool;
+pub(crate) use pool::*;
+mod sequence;
+pub use sequence::*;
+mod stable_channel;
+pub use stable_channel::*;
 mod trust_policy;
 pub use trust_policy::*;
+#[cfg(feature = "std")]
+mod trust_policy_watcher;
+#[cfg(feature = "std")]
+pub(crate) use trust_policy_watcher::*;
 pub mod access_control;
 mod local_info;
 pub use local_info::*;
+#[cfg(feature = "std")]
+pub use ockam_channel::FileKeyEscrowStorage;
+pub use ockam_channel::{
+    EscrowedChannelKeys, InMemoryKeyEscrowStorage, KeyEscrow, KeyEscrowStorage,
+    RecoveredChannelKeys, DEFAULT_KEY_ESCROW_CAPACITY,
+};
 
 use crate::authenticated_storage::AuthenticatedStorage;
-use crate::{Identity, IdentityVault};
+#[cfg(feature = "std")]
+use crate::IdentityIdentifier;
+use crate::{Identity, IdentityError, IdentityVault};
 use core::time::Duration;
+#[cfg(feature = "std")]
+use ockam_core::compat::rand::random;
 use ockam_core::compat::sync::Arc;
+use ockam_core::route;
+use ockam_core::vault::{KeyId, SecretAttributes, SecretPersistence, SecretType};
 use ockam_core::{Address, AsyncTryClone, Result, Route};
+use ockam_vault::SecretVault;
+use tracing::warn;
+
+/// Pre-shared keys shorter than this, in bytes, are rejected
+pub const MIN_PRE_SHARED_KEY_LENGTH: usize = 16;
+
+/// How long [`Identity::stop_secure_channel`] waits for the workers of a
+/// channel to stop once the close notice is sent
+const CHANNEL_STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often [`Identity::stop_secure_channel`] checks whether the workers
+/// of a channel are stopped
+const CHANNEL_STOP_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Import a key shared out of band with peers into `vault`, to be used
+/// with [`SecureChannelListenerOptions::with_pre_shared_key`] and
+/// [`SecureChannelOptions::with_pre_shared_key`] by the identities of
+/// that vault
+///
+/// The key must be at least [`MIN_PRE_SHARED_KEY_LENGTH`] bytes long. It is
+/// kept by the vault and only referred to by the returned key id.
+pub async fn import_pre_shared_key(vault: &impl SecretVault, key: &[u8]) -> Result<KeyId> {
+    if key.len() < MIN_PRE_SHARED_KEY_LENGTH {
+        return Err(IdentityError::InvalidPreSharedKey.into());
+    }
+    let attributes = SecretAttributes::new(
+        SecretType::Buffer,
+        SecretPersistence::Persistent,
+        key.len() as u32,
+    );
+    vault.secret_import(key, attributes).await
+}
 
 impl<V: IdentityVault> Identity<V> {
+    /// Check that the route is non-empty, well-formed and that its first hop
+    /// can be resolved, before any channel worker is started
+    async fn validate_secure_channel_route(&self, route: &Route) -> Result<()> {
+        let first_hop = route
+            .next()
+            .map_err(|_| IdentityError::EmptySecureChannelRoute)?;
+
+        if route.iter().any(|address| address.address().is_empty()) {
+            return Err(IdentityError::InvalidSecureChannelRoute.into());
+        }
+
+        // Non-local hops are resolved by their transport's router
+        if first_hop.is_local() && !self.ctx.is_address_resolvable(first_hop).await? {
+            return Err(IdentityError::UnknownSecureChannelRouteHop.into());
+        }
+
+        Ok(())
+    }
+
+    /// Create a secure channel listener, returning the address it accepts
+    /// handshakes at
     pub async fn create_secure_channel_listener(
         &self,
         address: impl Into<Address>,
         trust_policy: impl TrustPolicy,
         storage: &impl AuthenticatedStorage,
-    ) -> Result<()> {
+    ) -> Result<Address> {
+        self.create_secure_channel_listener_with_options(
+            address,
+            trust_policy,
+            storage,
+            SecureChannelListenerOptions::new(),
+        )
+        .await
+    }
+
+    /// Create a secure channel listener with `options`, returning the
+    /// address it accepts handshakes at
+    pub async fn create_secure_channel_listener_with_options(
+        &self,
+        address: impl Into<Address>,
+        trust_policy: impl TrustPolicy,
+        storage: &impl AuthenticatedStorage,
+        options: SecureChannelListenerOptions,
+    ) -> Result<Address> {
+        let address = address.into();
+        if options.handshake.key_escrow.is_some() {
+            warn!(
+                "SecureChannel listener {} escrows the keys of its channels",
+                address
+            );
+        }
         let identity_clone = self.async_try_clone().await?;
         let storage_clone = storage.async_try_clone().await?;
-        let listener = IdentityChannelListener::new(trust_policy, identity_clone, storage_clone);
-        self.ctx.start_worker(address.into(), listener).await?;
-        Ok(())
+        let listener =
+            IdentityChannelListener::new(trust_policy, identity_clone, storage_clone, options);
+        self.ctx.start_worker(address.clone(), listener).await?;
+        Ok(address)
     }
 
+    /// Create a secure channel to the listener at the end of `route`
+    ///
+    /// Messages sent from one address through the channel are delivered in
+    /// the order they were sent, including when the channel is tunneled
+    /// through other secure channels: every hop is a single worker handling
+    /// its mailbox in order. Messages from different senders may interleave.
+    ///
+    /// With [`enable_secure_channel_pool`](Self::enable_secure_channel_pool),
+    /// a healthy channel previously created to the same route is returned
+    /// instead of a new one.
     pub async fn create_secure_channel(
         &self,
         route: impl Into<Route>,
         trust_policy: impl TrustPolicy,
         storage: &impl AuthenticatedStorage,
     ) -> Result<Address> {
-        let identity_clone = self.async_try_clone().await?;
-        let storage_clone = storage.async_try_clone().await?;
+        let route = route.into();
+        let pool_key = self.secure_channel_pool_key(&route, &trust_policy);
+        if let Some(pool_key) = &pool_key {
+            if let Some(channel) = self.pooled_secure_channel(pool_key, &trust_policy).await? {
+                return Ok(channel);
+            }
+        }
 
-        DecryptorWorker::create_initiator(
-            &self.ctx,
-            route.into(),
-            identity_clone,
-            storage_clone,
-            Arc::new(trust_policy),
-            Duration::from_secs(120),
-        )
-        .await
+        let channel = self
+            .create_secure_channel_with_options(
+                route,
+                trust_policy,
+                storage,
+                SecureChannelOptions::new(),
+            )
+            .await?;
+        if let Some(pool_key) = pool_key {
+            self.pool_secure_channel(pool_key, &channel);
+        }
+        Ok(channel)
     }
 
     pub async fn create_secure_channel_extended(
@@ -59,22 +198,164 @@ impl<V: IdentityVault> Identity<V> {
         storage: &impl AuthenticatedStorage,
         timeout: Duration,
     ) -> Result<Address> {
+        self.create_secure_channel_with_options(
+            route,
+            trust_policy,
+            storage,
+            SecureChannelOptions::new().with_timeout(timeout),
+        )
+        .await
+    }
+
+    /// Create a secure channel with `options` to the listener at the end
+    /// of `route`
+    ///
+    /// Channels created with options are never pooled.
+    pub async fn create_secure_channel_with_options(
+        &self,
+        route: impl Into<Route>,
+        trust_policy: impl TrustPolicy,
+        storage: &impl AuthenticatedStorage,
+        options: SecureChannelOptions,
+    ) -> Result<Address> {
+        let route = route.into();
+        self.validate_secure_channel_route(&route).await?;
+        if options.handshake.key_escrow.is_some() {
+            warn!("SecureChannel over {} escrows its keys", route.pretty());
+        }
+
         let identity_clone = self.async_try_clone().await?;
         let storage_clone = storage.async_try_clone().await?;
 
         DecryptorWorker::create_initiator(
             &self.ctx,
-            route.into(),
+            route,
             identity_clone,
             storage_clone,
             Arc::new(trust_policy),
-            timeout,
+            options.timeout,
+            options.handshake,
         )
         .await
     }
 
+    /// Create a secure channel from this identity to itself, returning the
+    /// address of its initiating end
+    ///
+    /// Meant for tests which need a channel without a second identity: a
+    /// listener is started at a random address, trusting only this
+    /// identity, and the channel is created to it. The listener is stopped
+    /// once the channel is created. Messages sent through the channel are
+    /// decrypted by the listener's end, and replies come back through the
+    /// initiating end.
+    pub async fn create_secure_channel_to_self(
+        &self,
+        storage: &impl AuthenticatedStorage,
+    ) -> Result<Address> {
+        let trust_policy = TrustIdentifierPolicy::new(self.identifier().clone());
+        let listener = self
+            .create_secure_channel_listener(Address::random_local(), trust_policy.clone(), storage)
+            .await?;
+        let channel = self
+            .create_secure_channel(route![listener.clone()], trust_policy, storage)
+            .await;
+        // The listener's end of the channel keeps running without it
+        self.ctx.stop_worker(listener).await?;
+        channel
+    }
+
+    /// Import a key shared out of band with the peers of this identity
+    /// into its vault, see [`import_pre_shared_key`]
+    pub async fn import_pre_shared_key(&self, key: &[u8]) -> Result<KeyId> {
+        import_pre_shared_key(&self.vault, key).await
+    }
+
+    /// Create a secure channel whose address stays the same when it
+    /// reconnects, see [`StableSecureChannel`]
+    pub async fn create_stable_secure_channel<S: AuthenticatedStorage>(
+        &self,
+        route: impl Into<Route>,
+        trust_policy: impl TrustPolicy,
+        storage: &S,
+    ) -> Result<StableSecureChannel<V, S>> {
+        let route = route.into();
+        self.validate_secure_channel_route(&route).await?;
+        StableSecureChannel::create(self, route, Arc::new(trust_policy), storage).await
+    }
+
+    /// Close a secure channel, given the address returned when it was
+    /// created
+    ///
+    /// The other end is notified through the channel so it stops its own
+    /// workers, and this returns once both workers of this end are
+    /// stopped. The notice is best effort: if it is lost, the other end
+    /// lingers until it is closed on its own.
     pub async fn stop_secure_channel(&self, channel: &Address) -> Result<()> {
-        self.ctx.stop_worker(channel.clone()).await
+        self.channel_pool.forget(channel);
+        self.ctx
+            .send(route![channel.clone(), CHANNEL_CLOSE_ADDRESS], ())
+            .await?;
+
+        // The encryptor stops the decryptor, then itself, once the notice
+        // is sent
+        let mut waited = Duration::ZERO;
+        while self.ctx.is_address_resolvable(channel).await? {
+            if waited >= CHANNEL_STOP_TIMEOUT {
+                warn!(%channel, "Secure channel didn't close in time, stopping its encryptor");
+                return self.ctx.stop_worker(channel.clone()).await;
+            }
+            self.ctx.sleep(CHANNEL_STOP_POLL_INTERVAL).await;
+            waited += CHANNEL_STOP_POLL_INTERVAL;
+        }
+        Ok(())
+    }
+
+    /// Check that the other end of a secure channel is alive, returning
+    /// the round trip time
+    ///
+    /// The ping is echoed by the other end of the channel itself, without
+    /// involving its workers. Fails with
+    /// [`IdentityError::SecureChannelClosed`] if the channel was stopped on
+    /// this end, and with [`IdentityError::SecureChannelPingTimeout`] if no
+    /// echo came back within `timeout`, because the other end was stopped,
+    /// is unreachable or is too slow. Send-only channels never answer.
+    #[cfg(feature = "std")]
+    pub async fn ping_channel(
+        &self,
+        channel: impl Into<Address>,
+        timeout: Duration,
+    ) -> Result<Duration> {
+        let start = std::time::Instant::now();
+        self.echo_channel_ping(channel.into(), timeout).await?;
+        Ok(start.elapsed())
+    }
+
+    /// Ping a channel, returning the identity at its other end, which
+    /// marked the echo
+    #[cfg(feature = "std")]
+    async fn echo_channel_ping(
+        &self,
+        channel: Address,
+        timeout: Duration,
+    ) -> Result<IdentityIdentifier> {
+        let mut ctx = self.ctx.new_detached(Address::random_local()).await?;
+        let nonce: u64 = random();
+        ctx.send_with_priority(
+            route![channel, CHANNEL_PING_ADDRESS],
+            ChannelPing(nonce),
+            ockam_core::MessagePriority::High,
+        )
+        .await
+        .map_err(|_| IdentityError::SecureChannelClosed)?;
+        let echo = ctx
+            .receive_duration_timeout::<ChannelPing>(timeout)
+            .await
+            .map_err(|_| IdentityError::SecureChannelPingTimeout)?;
+        if echo.body().0 != nonce {
+            return Err(IdentityError::InvalidSecureChannelInternalState.into());
+        }
+        let local_info = IdentitySecureChannelLocalInfo::find_info(echo.local_message())?;
+        Ok(local_info.their_identity_id().clone())
     }
 }
 
@@ -83,13 +364,21 @@ mod test {
     use super::*;
     use crate::access_control::IdentityAccessControlBuilder;
     use crate::authenticated_storage::mem::InMemoryStorage;
-    use crate::Identity;
-    use core::sync::atomic::{AtomicU8, Ordering};
+    use crate::{Identity, IdentityIdentifier, IdentityStateConst};
+    use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
     use core::time::Duration;
+    use ockam_core::compat::rand::{prelude::SeedableRng, rngs::StdRng};
     use ockam_core::compat::sync::Arc;
-    use ockam_core::{route, Any, Result, Routed, Worker};
+    use ockam_core::errcode::Kind;
+    use ockam_core::vault::{
+        KeyId, SecretAttributes, SecretPersistence, SecretType, SecretVault,
+        CURVE25519_SECRET_LENGTH_U32,
+    };
+    use ockam_core::{
+        route, Address, Any, Decodable, Encodable, MessageWithAttachment, Result, Routed, Worker,
+    };
     use ockam_node::{Context, WorkerBuilder};
-    use ockam_vault::Vault;
+    use ockam_vault::{InMemoryAuditSink, Vault, VaultOperation};
     use tokio::time::sleep;
 
     #[ockam_macros::test]
@@ -140,210 +429,2442 @@ mod test {
     }
 
     #[ockam_macros::test]
-    async fn test_tunneled_secure_channel_works(ctx: &mut Context) -> Result<()> {
+    async fn test_channel_to_self(ctx: &mut Context) -> Result<()> {
         let vault = Vault::create();
+        let storage = InMemoryStorage::new();
+        let alice = Identity::create(ctx, &vault).await?;
 
-        let alice_storage = InMemoryStorage::new();
-        let bob_storage = InMemoryStorage::new();
+        let workers = ctx.list_workers().await?;
+        let channel = alice.create_secure_channel_to_self(&storage).await?;
 
-        let alice = Identity::create(ctx, &vault).await?;
-        let bob = Identity::create(ctx, &vault).await?;
+        ctx.send(
+            route![channel.clone(), ctx.address()],
+            "Hello, me!".to_string(),
+        )
+        .await?;
+        let msg = ctx.receive::<String>().await?.take();
 
-        let alice_trust_policy = TrustIdentifierPolicy::new(bob.identifier().clone());
-        let bob_trust_policy = TrustIdentifierPolicy::new(alice.identifier().clone());
+        let local_info = IdentitySecureChannelLocalInfo::find_info(msg.local_message())?;
+        assert_eq!(local_info.their_identity_id(), alice.identifier());
+        assert_eq!("Hello, me!", msg.body());
 
-        bob.create_secure_channel_listener("bob_listener", bob_trust_policy.clone(), &bob_storage)
+        // The reply comes back through the initiating end
+        ctx.send(msg.return_route(), "Hello again!".to_string())
             .await?;
+        let msg = ctx.receive::<String>().await?.take();
 
-        let alice_channel = alice
-            .create_secure_channel(
-                route!["bob_listener"],
-                alice_trust_policy.clone(),
-                &alice_storage,
-            )
-            .await?;
+        let local_info = IdentitySecureChannelLocalInfo::find_info(msg.local_message())?;
+        assert_eq!(local_info.their_identity_id(), alice.identifier());
+        assert_eq!(msg.return_route().next()?, &channel);
+        assert_eq!("Hello again!", msg.body());
+
+        // Once the channel is closed, the listener isn't left running
+        alice.stop_secure_channel(&channel).await?;
+        let mut leftover = vec![];
+        for _ in 0..50 {
+            leftover = ctx.list_workers().await?;
+            leftover.retain(|address| !workers.contains(address));
+            if leftover.is_empty() {
+                break;
+            }
+            sleep(Duration::from_millis(100)).await;
+        }
+        assert!(leftover.is_empty(), "still running: {:?}", leftover);
 
-        bob.create_secure_channel_listener("bob_another_listener", bob_trust_policy, &bob_storage)
-            .await?;
+        ctx.stop().await
+    }
 
-        let alice_another_channel = alice
-            .create_secure_channel(
-                route![alice_channel, "bob_another_listener"],
-                alice_trust_policy,
-                &alice_storage,
-            )
+    #[ockam_macros::test]
+    async fn test_channel_listener_returns_its_address(ctx: &mut Context) -> Result<()> {
+        let vault = Vault::create();
+        let storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &vault).await?;
+        let bob = Identity::create(ctx, &vault).await?;
+
+        let listener = bob
+            .create_secure_channel_listener(Address::random_local(), TrustEveryonePolicy, &storage)
+            .await?;
+        let alice_channel = alice
+            .create_secure_channel(route![listener], TrustEveryonePolicy, &storage)
             .await?;
 
         ctx.send(
-            route![alice_another_channel, ctx.address()],
+            route![alice_channel, ctx.address()],
             "Hello, Bob!".to_string(),
         )
         .await?;
         let msg = ctx.receive::<String>().await?.take();
-        let return_route = msg.return_route();
         assert_eq!("Hello, Bob!", msg.body());
 
-        ctx.send(return_route, "Hello, Alice!".to_string()).await?;
-        assert_eq!(
-            "Hello, Alice!",
-            ctx.receive::<String>().await?.take().body()
-        );
-
         ctx.stop().await
     }
 
+    #[derive(Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize, ockam_core::Message)]
+    struct FileHeader {
+        name: String,
+        offset: u64,
+    }
+
     #[ockam_macros::test]
-    async fn test_double_tunneled_secure_channel_works(ctx: &mut Context) -> Result<()> {
+    async fn test_channel_message_with_attachment(ctx: &mut Context) -> Result<()> {
         let vault = Vault::create();
-
-        let alice_storage = InMemoryStorage::new();
-        let bob_storage = InMemoryStorage::new();
+        let storage = InMemoryStorage::new();
 
         let alice = Identity::create(ctx, &vault).await?;
         let bob = Identity::create(ctx, &vault).await?;
 
-        let alice_trust_policy = TrustIdentifierPolicy::new(bob.identifier().clone());
-        let bob_trust_policy = TrustIdentifierPolicy::new(alice.identifier().clone());
-
-        bob.create_secure_channel_listener("bob_listener", bob_trust_policy.clone(), &bob_storage)
+        bob.create_secure_channel_listener("bob_listener", TrustEveryonePolicy, &storage)
             .await?;
-
         let alice_channel = alice
-            .create_secure_channel(
-                route!["bob_listener"],
-                alice_trust_policy.clone(),
-                &alice_storage,
-            )
-            .await?;
-
-        bob.create_secure_channel_listener(
-            "bob_another_listener",
-            bob_trust_policy.clone(),
-            &bob_storage,
-        )
-        .await?;
-
-        let alice_another_channel = alice
-            .create_secure_channel(
-                route![alice_channel, "bob_another_listener"],
-                alice_trust_policy.clone(),
-                &alice_storage,
-            )
-            .await?;
-
-        bob.create_secure_channel_listener(
-            "bob_yet_another_listener",
-            bob_trust_policy,
-            &bob_storage,
-        )
-        .await?;
-
-        let alice_yet_another_channel = alice
-            .create_secure_channel(
-                route![alice_another_channel, "bob_yet_another_listener"],
-                alice_trust_policy,
-                &alice_storage,
-            )
+            .create_secure_channel(route!["bob_listener"], TrustEveryonePolicy, &storage)
             .await?;
 
+        let header = FileHeader {
+            name: "image.png".to_string(),
+            offset: 4096,
+        };
+        let attachment: Vec<u8> = (0..=255).collect();
         ctx.send(
-            route![alice_yet_another_channel, ctx.address()],
-            "Hello, Bob!".to_string(),
+            route![alice_channel, ctx.address()],
+            MessageWithAttachment::new(header, attachment.clone()),
         )
         .await?;
-        let msg = ctx.receive::<String>().await?.take();
-        let return_route = msg.return_route();
-        assert_eq!("Hello, Bob!", msg.body());
 
-        ctx.send(return_route, "Hello, Alice!".to_string()).await?;
+        let msg = ctx
+            .receive::<MessageWithAttachment<FileHeader>>()
+            .await?
+            .take()
+            .body();
+        let (header, received) = msg.into_parts();
         assert_eq!(
-            "Hello, Alice!",
-            ctx.receive::<String>().await?.take().body()
+            header,
+            FileHeader {
+                name: "image.png".to_string(),
+                offset: 4096,
+            }
         );
+        assert_eq!(received, attachment);
 
         ctx.stop().await
     }
 
     #[ockam_macros::test]
-    async fn test_many_times_tunneled_secure_channel_works(ctx: &mut Context) -> Result<()> {
+    async fn test_cached_return_route_survives_reconnect(ctx: &mut Context) -> Result<()> {
         let vault = Vault::create();
-
-        let alice_storage = InMemoryStorage::new();
-        let bob_storage = InMemoryStorage::new();
+        let storage = InMemoryStorage::new();
 
         let alice = Identity::create(ctx, &vault).await?;
         let bob = Identity::create(ctx, &vault).await?;
 
-        let alice_trust_policy = TrustIdentifierPolicy::new(bob.identifier().clone());
-        let bob_trust_policy = TrustIdentifierPolicy::new(alice.identifier().clone());
-
-        let n = rand::random::<u8>() % 5 + 4;
-        let mut channels = vec![];
-        for i in 0..n {
-            bob.create_secure_channel_listener(
-                i.to_string(),
-                bob_trust_policy.clone(),
-                &bob_storage,
-            )
+        bob.create_secure_channel_listener("bob_listener", TrustEveryonePolicy, &storage)
             .await?;
-            let channel_route = if i > 0 {
-                route![channels.pop().unwrap(), i.to_string()]
-            } else {
-                route![i.to_string()]
-            };
-            let alice_channel = alice
-                .create_secure_channel(channel_route, alice_trust_policy.clone(), &alice_storage)
-                .await?;
-            channels.push(alice_channel);
-        }
+        let mut bob_app = ctx.new_detached(Address::random_local()).await?;
 
+        let channel = alice
+            .create_stable_secure_channel(route!["bob_listener"], TrustEveryonePolicy, &storage)
+            .await?;
         ctx.send(
-            route![channels.pop().unwrap(), ctx.address()],
+            route![channel.address().clone(), bob_app.address()],
             "Hello, Bob!".to_string(),
         )
         .await?;
-        let msg = ctx.receive::<String>().await?.take();
-        let return_route = msg.return_route();
-        assert_eq!("Hello, Bob!", msg.body());
+        let msg = bob_app.receive::<String>().await?.take();
+        bob_app
+            .send(msg.return_route(), "Hello, Alice!".to_string())
+            .await?;
+        let reply = ctx.receive::<String>().await?.take();
+        assert_eq!("Hello, Alice!", reply.body());
+        let cached_route = reply.return_route();
+        assert_eq!(cached_route.next()?, channel.address());
 
-        ctx.send(return_route, "Hello, Alice!".to_string()).await?;
-        assert_eq!(
-            "Hello, Alice!",
-            ctx.receive::<String>().await?.take().body()
-        );
+        let previous = channel.channel();
+        channel.reconnect().await?;
+        assert_ne!(previous, channel.channel());
+
+        ctx.send(cached_route, "Hello again, Bob!".to_string())
+            .await?;
+        let msg = bob_app.receive::<String>().await?.take();
+        assert_eq!("Hello again, Bob!", msg.body());
+        bob_app
+            .send(msg.return_route(), "Hello again, Alice!".to_string())
+            .await?;
+        let reply = ctx.receive::<String>().await?.take();
+        assert_eq!("Hello again, Alice!", reply.body());
 
         ctx.stop().await
     }
 
-    struct Receiver {
-        received_count: Arc<AtomicU8>,
-    }
+    #[ockam_macros::test]
+    async fn test_dropped_stable_secure_channel_is_stopped(ctx: &mut Context) -> Result<()> {
+        let vault = Vault::create();
+        let storage = InMemoryStorage::new();
 
-    #[ockam_core::async_trait]
-    impl Worker for Receiver {
-        type Message = Any;
-        type Context = Context;
+        let alice = Identity::create(ctx, &vault).await?;
+        let bob = Identity::create(ctx, &vault).await?;
 
-        async fn handle_message(
-            &mut self,
-            _context: &mut Self::Context,
-            _msg: Routed<Self::Message>,
-        ) -> Result<()> {
-            self.received_count.fetch_add(1, Ordering::Relaxed);
+        bob.create_secure_channel_listener("bob_listener", TrustEveryonePolicy, &storage)
+            .await?;
 
-            Ok(())
-        }
+        let channel = alice
+            .create_stable_secure_channel(route!["bob_listener"], TrustEveryonePolicy, &storage)
+            .await?;
+        let address = channel.address().clone();
+        let encryptor = channel.channel();
+        drop(channel);
+
+        wait_until_stopped(ctx, &address).await?;
+        wait_until_stopped(ctx, &encryptor).await?;
+
+        ctx.stop().await
     }
 
-    #[allow(non_snake_case)]
     #[ockam_macros::test]
-    async fn access_control__known_participant__should_pass_messages(
-        ctx: &mut Context,
-    ) -> Result<()> {
-        let received_count = Arc::new(AtomicU8::new(0));
-        let receiver = Receiver {
-            received_count: received_count.clone(),
-        };
+    async fn test_channel_delivery_address(ctx: &mut Context) -> Result<()> {
+        let alice_vault = Vault::create();
+        let bob_vault = Vault::create();
+
+        let alice_storage = InMemoryStorage::new();
+        let bob_storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &alice_vault).await?;
+        let bob = Identity::create(ctx, &bob_vault).await?;
+
+        let mut pipeline = ctx.new_detached("bob_pipeline").await?;
+        bob.create_secure_channel_listener_with_options(
+            "bob_listener",
+            TrustEveryonePolicy,
+            &bob_storage,
+            SecureChannelListenerOptions::new().with_delivery_address("bob_pipeline"),
+        )
+        .await?;
+
+        let alice_channel = alice
+            .create_secure_channel(route!["bob_listener"], TrustEveryonePolicy, &alice_storage)
+            .await?;
+
+        // Nothing runs at "bob_app", the message goes to the pipeline anyway
+        ctx.send(route![alice_channel, "bob_app"], "Hello, Bob!".to_string())
+            .await?;
+
+        let msg = pipeline.receive::<String>().await?.take();
+
+        let local_info = IdentitySecureChannelLocalInfo::find_info(msg.local_message())?;
+        assert_eq!(local_info.their_identity_id(), alice.identifier());
+        assert!(msg
+            .onward_route()
+            .iter()
+            .any(|a| a == &Address::from_string("bob_app")));
+
+        let return_route = msg.return_route();
+        assert_eq!("Hello, Bob!", msg.body());
+
+        pipeline
+            .send(return_route, "Hello, Alice!".to_string())
+            .await?;
+
+        let msg = ctx.receive::<String>().await?.take();
+        assert_eq!("Hello, Alice!", msg.body());
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn test_channel_initiator_only_auth(ctx: &mut Context) -> Result<()> {
+        let alice_vault = Vault::create();
+        let bob_vault = Vault::create();
+        let carol_vault = Vault::create();
+
+        let storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &alice_vault).await?;
+        let bob = Identity::create(ctx, &bob_vault).await?;
+        let carol = Identity::create(ctx, &carol_vault).await?;
+
+        // Bob only accepts Alice, and presents no identity to his initiators
+        let bob_trust_policy = MutualAuthTrustPolicy::initiator_only(TrustIdentifierPolicy::new(
+            alice.identifier().clone(),
+        ));
+        bob.create_secure_channel_listener_with_options(
+            "bob_listener",
+            bob_trust_policy,
+            &storage,
+            SecureChannelListenerOptions::new().anonymous(),
+        )
+        .await?;
+
+        // Alice doesn't know Bob's identifier
+        let alice_trust_policy = MutualAuthTrustPolicy::initiator_only(TrustIdentifierPolicy::new(
+            carol.identifier().clone(),
+        ));
+        let alice_channel = alice
+            .create_secure_channel(route!["bob_listener"], alice_trust_policy, &storage)
+            .await?;
+
+        ctx.send(
+            route![alice_channel, ctx.address()],
+            "Hello, Bob!".to_string(),
+        )
+        .await?;
+        let msg = ctx.receive::<String>().await?.take();
+        let local_info = IdentitySecureChannelLocalInfo::find_info(msg.local_message())?;
+        assert_eq!(local_info.their_identity_id(), alice.identifier());
+        assert_eq!("Hello, Bob!", msg.body());
+
+        // Carol is not authenticated by Bob
+        let res = carol
+            .create_secure_channel_extended(
+                route!["bob_listener"],
+                TrustEveryonePolicy,
+                &storage,
+                Duration::from_secs(1),
+            )
+            .await;
+        assert!(res.is_err());
+
+        // Requiring mutual authentication, Alice rejects Bob who presents
+        // no identity, and so does a policy which doesn't accept anonymous
+        // responders explicitly
+        let alice_trust_policy =
+            MutualAuthTrustPolicy::mutual(TrustIdentifierPolicy::new(carol.identifier().clone()));
+        let res = alice
+            .create_secure_channel_extended(
+                route!["bob_listener"],
+                alice_trust_policy,
+                &storage,
+                Duration::from_secs(1),
+            )
+            .await;
+        assert!(res.is_err());
+        let res = alice
+            .create_secure_channel_extended(
+                route!["bob_listener"],
+                TrustEveryonePolicy,
+                &storage,
+                Duration::from_secs(1),
+            )
+            .await;
+        assert!(res.is_err());
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn test_channel_responder_only_auth(ctx: &mut Context) -> Result<()> {
+        let alice_vault = Vault::create();
+        let bob_vault = Vault::create();
+        let carol_vault = Vault::create();
+
+        let storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &alice_vault).await?;
+        let bob = Identity::create(ctx, &bob_vault).await?;
+        let carol = Identity::create(ctx, &carol_vault).await?;
+
+        // Bob accepts initiators which present no identity
+        let bob_trust_policy = MutualAuthTrustPolicy::responder_only(TrustIdentifierPolicy::new(
+            carol.identifier().clone(),
+        ));
+        bob.create_secure_channel_listener("bob_listener", bob_trust_policy, &storage)
+            .await?;
+
+        // Alice stays anonymous and only accepts Bob
+        let alice_trust_policy = MutualAuthTrustPolicy::responder_only(TrustIdentifierPolicy::new(
+            bob.identifier().clone(),
+        ));
+        let alice_channel = alice
+            .create_secure_channel_with_options(
+                route!["bob_listener"],
+                alice_trust_policy,
+                &storage,
+                SecureChannelOptions::new().anonymous(),
+            )
+            .await?;
+
+        ctx.send(
+            route![alice_channel, ctx.address()],
+            "Hello, Bob!".to_string(),
+        )
+        .await?;
+        let msg = ctx.receive::<String>().await?.take();
+        let local_info = IdentitySecureChannelLocalInfo::find_info(msg.local_message())?;
+        assert!(local_info.their_identity_id().is_anonymous());
+        assert_ne!(local_info.their_identity_id(), alice.identifier());
+        assert_eq!("Hello, Bob!", msg.body());
+
+        // Bob can reply to Alice
+        ctx.send(msg.return_route(), "Hello, Alice!".to_string())
+            .await?;
+        let msg = ctx.receive::<String>().await?.take();
+        assert_eq!("Hello, Alice!", msg.body());
+        let local_info = IdentitySecureChannelLocalInfo::find_info(msg.local_message())?;
+        assert_eq!(local_info.their_identity_id(), bob.identifier());
+
+        // Alice still authenticates Bob
+        let alice_trust_policy = MutualAuthTrustPolicy::responder_only(TrustIdentifierPolicy::new(
+            carol.identifier().clone(),
+        ));
+        let res = alice
+            .create_secure_channel_with_options(
+                route!["bob_listener"],
+                alice_trust_policy,
+                &storage,
+                SecureChannelOptions::new()
+                    .anonymous()
+                    .with_timeout(Duration::from_secs(1)),
+            )
+            .await;
+        assert!(res.is_err());
+
+        // Listeners requiring mutual authentication, or which don't accept
+        // anonymous initiators explicitly, reject Alice
+        let carol_trust_policy = MutualAuthTrustPolicy::mutual(TrustEveryonePolicy);
+        carol
+            .create_secure_channel_listener("carol_mutual_listener", carol_trust_policy, &storage)
+            .await?;
+        carol
+            .create_secure_channel_listener("carol_listener", TrustEveryonePolicy, &storage)
+            .await?;
+        for listener in ["carol_mutual_listener", "carol_listener"] {
+            let res = alice
+                .create_secure_channel_with_options(
+                    route![listener],
+                    TrustEveryonePolicy,
+                    &storage,
+                    SecureChannelOptions::new()
+                        .anonymous()
+                        .with_timeout(Duration::from_secs(1)),
+                )
+                .await;
+            assert!(res.is_err());
+        }
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn test_file_allowlist_trust_policy(ctx: &mut Context) -> Result<()> {
+        let vault = Vault::create();
+        let storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &vault).await?;
+        let bob = Identity::create(ctx, &vault).await?;
+
+        let path = std::env::temp_dir().join(format!("allowlist-{}", bob.identifier()));
+        std::fs::write(&path, "# Peers trusted by Bob\n").unwrap();
+        let bob_trust_policy = FileAllowlistTrustPolicy::new(&path)?;
+        bob.create_secure_channel_listener("bob_listener", bob_trust_policy, &storage)
+            .await?;
+
+        let res = alice
+            .create_secure_channel_extended(
+                route!["bob_listener"],
+                TrustEveryonePolicy,
+                &storage,
+                Duration::from_secs(1),
+            )
+            .await;
+        assert!(res.is_err());
+
+        // The listener reads the modified file without being restarted
+        std::fs::write(
+            &path,
+            format!("# Peers trusted by Bob\n{}\n", alice.identifier()),
+        )
+        .unwrap();
+        let alice_channel = alice
+            .create_secure_channel(route!["bob_listener"], TrustEveryonePolicy, &storage)
+            .await?;
+        ctx.send(
+            route![alice_channel, ctx.address()],
+            "Hello, Bob!".to_string(),
+        )
+        .await?;
+        assert_eq!("Hello, Bob!", ctx.receive::<String>().await?.take().body());
+
+        std::fs::remove_file(&path).unwrap();
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn test_channel_with_handshake_padding(ctx: &mut Context) -> Result<()> {
+        let alice_vault = Vault::create();
+        let bob_vault = Vault::create();
+
+        let alice_storage = InMemoryStorage::new();
+        let bob_storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &alice_vault).await?;
+        let bob = Identity::create(ctx, &bob_vault).await?;
+
+        bob.create_secure_channel_listener_with_options(
+            "bob_listener",
+            TrustEveryonePolicy,
+            &bob_storage,
+            SecureChannelListenerOptions::new().with_handshake_padding(1024),
+        )
+        .await?;
+        bob.create_secure_channel_listener("bob_plain_listener", TrustEveryonePolicy, &bob_storage)
+            .await?;
+
+        // Both sides pad
+        let alice_channel = alice
+            .create_secure_channel_with_options(
+                route!["bob_listener"],
+                TrustEveryonePolicy,
+                &alice_storage,
+                SecureChannelOptions::new().with_handshake_padding(1024),
+            )
+            .await?;
+        ctx.send(
+            route![alice_channel, ctx.address()],
+            "Hello, Bob!".to_string(),
+        )
+        .await?;
+        let msg = ctx.receive::<String>().await?.take();
+        assert_eq!("Hello, Bob!", msg.body());
+        ctx.send(msg.return_route(), "Hello, Alice!".to_string())
+            .await?;
+        assert_eq!(
+            "Hello, Alice!",
+            ctx.receive::<String>().await?.take().body()
+        );
+
+        // Alice follows the padding of the listener
+        let alice_channel = alice
+            .create_secure_channel(route!["bob_listener"], TrustEveryonePolicy, &alice_storage)
+            .await?;
+        ctx.send(
+            route![alice_channel, ctx.address()],
+            "Hello again, Bob!".to_string(),
+        )
+        .await?;
+        assert_eq!(
+            "Hello again, Bob!",
+            ctx.receive::<String>().await?.take().body()
+        );
+
+        // A listener which doesn't pad is rejected
+        let res = tokio::time::timeout(
+            Duration::from_secs(2),
+            alice.create_secure_channel_with_options(
+                route!["bob_plain_listener"],
+                TrustEveryonePolicy,
+                &alice_storage,
+                SecureChannelOptions::new().with_handshake_padding(1024),
+            ),
+        )
+        .await;
+        assert!(!matches!(res, Ok(Ok(_))));
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn test_channel_with_pre_shared_key(ctx: &mut Context) -> Result<()> {
+        let alice_vault = Vault::create();
+        let bob_vault = Vault::create();
+        let mallory_vault = Vault::create();
+
+        let alice_storage = InMemoryStorage::new();
+        let bob_storage = InMemoryStorage::new();
+        let mallory_storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &alice_vault).await?;
+        let bob = Identity::create(ctx, &bob_vault).await?;
+        let mallory = Identity::create(ctx, &mallory_vault).await?;
+
+        assert!(alice.import_pre_shared_key(&[7; 8]).await.is_err());
+        let alice_psk = alice.import_pre_shared_key(&[7; 32]).await?;
+        let bob_psk = bob.import_pre_shared_key(&[7; 32]).await?;
+        let mallory_psk = mallory.import_pre_shared_key(&[8; 32]).await?;
+
+        bob.create_secure_channel_listener_with_options(
+            "bob_listener",
+            TrustEveryonePolicy,
+            &bob_storage,
+            SecureChannelListenerOptions::new().with_pre_shared_key(bob_psk.clone()),
+        )
+        .await?;
+        bob.create_secure_channel_listener("bob_plain_listener", TrustEveryonePolicy, &bob_storage)
+            .await?;
+
+        let alice_channel = alice
+            .create_secure_channel_with_options(
+                route!["bob_listener"],
+                TrustEveryonePolicy,
+                &alice_storage,
+                SecureChannelOptions::new()
+                    .with_pre_shared_key(alice_psk.clone())
+                    .with_timeout(Duration::from_secs(5)),
+            )
+            .await?;
+        ctx.send(
+            route![alice_channel, ctx.address()],
+            "Hello, Bob!".to_string(),
+        )
+        .await?;
+        let msg = ctx.receive::<String>().await?.take();
+        assert_eq!("Hello, Bob!", msg.body());
+
+        // A wrong key, no key, or a listener without the key are rejected
+        let res = mallory
+            .create_secure_channel_with_options(
+                route!["bob_listener"],
+                TrustEveryonePolicy,
+                &mallory_storage,
+                SecureChannelOptions::new()
+                    .with_pre_shared_key(mallory_psk.clone())
+                    .with_timeout(Duration::from_secs(2)),
+            )
+            .await;
+        assert!(res.is_err());
+        let res = mallory
+            .create_secure_channel_extended(
+                route!["bob_listener"],
+                TrustEveryonePolicy,
+                &mallory_storage,
+                Duration::from_secs(2),
+            )
+            .await;
+        assert!(res.is_err());
+        let res = alice
+            .create_secure_channel_with_options(
+                route!["bob_plain_listener"],
+                TrustEveryonePolicy,
+                &alice_storage,
+                SecureChannelOptions::new()
+                    .with_pre_shared_key(alice_psk.clone())
+                    .with_timeout(Duration::from_secs(2)),
+            )
+            .await;
+        assert!(res.is_err());
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn test_channel_negotiation(ctx: &mut Context) -> Result<()> {
+        let alice_vault = Vault::create();
+        let bob_vault = Vault::create();
+
+        let alice_storage = InMemoryStorage::new();
+        let bob_storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &alice_vault).await?;
+        let bob = Identity::create(ctx, &bob_vault).await?;
+
+        let alice_psk = alice.import_pre_shared_key(&[7; 32]).await?;
+        let bob_psk = bob.import_pre_shared_key(&[7; 32]).await?;
+
+        bob.create_secure_channel_listener_with_options(
+            "bob_psk_listener",
+            TrustEveryonePolicy,
+            &bob_storage,
+            SecureChannelListenerOptions::new().with_pre_shared_key(bob_psk.clone()),
+        )
+        .await?;
+        bob.create_secure_channel_listener_with_options(
+            "bob_padded_listener",
+            TrustEveryonePolicy,
+            &bob_storage,
+            SecureChannelListenerOptions::new().with_handshake_padding(1024),
+        )
+        .await?;
+
+        let alice_channel = alice
+            .create_secure_channel_with_options(
+                route!["bob_psk_listener"],
+                TrustEveryonePolicy,
+                &alice_storage,
+                SecureChannelOptions::new()
+                    .with_pre_shared_key(alice_psk.clone())
+                    .with_timeout(Duration::from_secs(5)),
+            )
+            .await?;
+        let (alice_side, bob_side) = negotiations(ctx, &alice, &bob, &alice_channel).await?;
+        assert_eq!(alice_side.role(), SecureChannelRole::Initiator);
+        assert_eq!(bob_side.role(), SecureChannelRole::Responder);
+        for negotiation in [&alice_side, &bob_side] {
+            assert_eq!(
+                negotiation.cipher_suite(),
+                CipherSuite::NoiseXx25519AesGcmSha256
+            );
+            assert_eq!(negotiation.compression(), ChannelCompression::None);
+            assert!(negotiation.pre_shared_key());
+            assert_eq!(negotiation.handshake_padding(), None);
+            assert!(!negotiation.sequence_numbers());
+        }
+
+        // The initiator pads to the listener's bucket size
+        let padded_channel = alice
+            .create_secure_channel(
+                route!["bob_padded_listener"],
+                TrustEveryonePolicy,
+                &alice_storage,
+            )
+            .await?;
+        let (alice_side, bob_side) = negotiations(ctx, &alice, &bob, &padded_channel).await?;
+        for negotiation in [&alice_side, &bob_side] {
+            assert!(!negotiation.pre_shared_key());
+            assert_eq!(negotiation.handshake_padding(), Some(1024));
+        }
+
+        // Stopped channels are forgotten
+        alice.stop_secure_channel(&alice_channel).await?;
+        sleep(Duration::from_millis(250)).await;
+        assert!(alice.secure_channel_negotiation(&alice_channel).is_none());
+
+        ctx.stop().await
+    }
+
+    /// Negotiation of both ends of a channel from Alice to Bob
+    async fn negotiations(
+        ctx: &mut Context,
+        alice: &Identity<Vault>,
+        bob: &Identity<Vault>,
+        alice_channel: &Address,
+    ) -> Result<(SecureChannelNegotiation, SecureChannelNegotiation)> {
+        ctx.send(
+            route![alice_channel.clone(), ctx.address()],
+            "Hello".to_string(),
+        )
+        .await?;
+        let msg = ctx.receive::<String>().await?.take();
+        let local_info = IdentitySecureChannelLocalInfo::find_info(msg.local_message())?;
+        Ok((
+            alice.secure_channel_negotiation(alice_channel).unwrap(),
+            bob.secure_channel_negotiation(local_info.channel_address())
+                .unwrap(),
+        ))
+    }
+
+    #[test]
+    fn test_handshake_padding_is_uniform() -> Result<()> {
+        let messages = [
+            IdentityChannelMessage::Request {
+                identity: vec![1; 10],
+                signature: vec![2; 64],
+            },
+            IdentityChannelMessage::RequestAcceptsSendOnly {
+                identity: vec![1; 300],
+                signature: vec![2; 64],
+            },
+            IdentityChannelMessage::Response {
+                identity: vec![1; 700],
+                signature: vec![2; 64],
+            },
+            IdentityChannelMessage::ResponseSendOnly {
+                identity: vec![1; 1000],
+                signature: vec![2; 64],
+            },
+        ];
+        for message in &messages {
+            let padded = message.padded(1024)?.encode()?;
+            assert_eq!(padded.len(), 1024);
+            let (unpadded, bucket_size) = IdentityChannelMessage::decode_padded(&padded)?;
+            assert_eq!(unpadded.encode()?, message.encode()?);
+            assert_eq!(bucket_size, Some(1024));
+        }
+
+        // Larger messages take several buckets
+        let message = IdentityChannelMessage::Request {
+            identity: vec![1; 1500],
+            signature: vec![2; 64],
+        };
+        assert_eq!(message.padded(1024)?.encode()?.len(), 2048);
+
+        // The bucket size is capped
+        let padded = message.padded(usize::MAX)?.encode()?;
+        assert_eq!(padded.len(), MAX_HANDSHAKE_BUCKET_SIZE);
+
+        // A message claiming a bucket size it isn't padded to is rejected,
+        // as is one claiming a bucket size larger than the cap
+        for bucket_size in [1024, MAX_HANDSHAKE_BUCKET_SIZE as u32 * 2] {
+            let lying = IdentityChannelMessage::Padded {
+                bucket_size,
+                message: message.encode()?,
+                padding: Vec::new(),
+            };
+            assert!(IdentityChannelMessage::decode_padded(&lying.encode()?).is_err());
+        }
+
+        Ok(())
+    }
+
+    #[ockam_macros::test]
+    async fn test_channel_with_purpose_keys(ctx: &mut Context) -> Result<()> {
+        let alice_vault = Vault::create();
+        let bob_vault = Vault::create();
+
+        let alice_storage = InMemoryStorage::new();
+        let bob_storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &alice_vault).await?;
+        let bob = Identity::create(ctx, &bob_vault).await?;
+
+        alice.create_purpose_keys().await?;
+        bob.create_purpose_keys().await?;
+
+        for identity in [&alice, &bob] {
+            let public = identity.to_public().await?;
+            assert_eq!(
+                public.signing_key_label(),
+                IdentityStateConst::SIGNING_LABEL
+            );
+            assert_eq!(
+                public
+                    .get_public_key(IdentityStateConst::SIGNING_LABEL)?
+                    .stype(),
+                SecretType::Ed25519
+            );
+            assert_eq!(
+                public
+                    .get_public_key(IdentityStateConst::KEY_AGREEMENT_LABEL)?
+                    .stype(),
+                SecretType::X25519
+            );
+        }
+
+        let alice_trust_policy = TrustIdentifierPolicy::new(bob.identifier().clone());
+        let bob_trust_policy = TrustIdentifierPolicy::new(alice.identifier().clone());
+
+        bob.create_secure_channel_listener("bob_listener", bob_trust_policy, &bob_storage)
+            .await?;
+
+        let alice_channel = alice
+            .create_secure_channel(route!["bob_listener"], alice_trust_policy, &alice_storage)
+            .await?;
+
+        ctx.send(
+            route![alice_channel, ctx.address()],
+            "Hello, Bob!".to_string(),
+        )
+        .await?;
+
+        let msg = ctx.receive::<String>().await?.take();
+
+        let local_info = IdentitySecureChannelLocalInfo::find_info(msg.local_message())?;
+        assert_eq!(local_info.their_identity_id(), alice.identifier());
+
+        let return_route = msg.return_route();
+        assert_eq!("Hello, Bob!", msg.body());
+
+        ctx.send(return_route, "Hello, Alice!".to_string()).await?;
+
+        let msg = ctx.receive::<String>().await?.take();
+
+        let local_info = IdentitySecureChannelLocalInfo::find_info(msg.local_message())?;
+        assert_eq!(local_info.their_identity_id(), bob.identifier());
+        assert_eq!("Hello, Alice!", msg.body());
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn test_channel_key_usage_is_audited(ctx: &mut Context) -> Result<()> {
+        let audit_sink = InMemoryAuditSink::new();
+        let alice_vault = Vault::create().with_audit_sink(Arc::new(audit_sink.clone()));
+        let bob_vault = Vault::create();
+
+        let alice_storage = InMemoryStorage::new();
+        let bob_storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &alice_vault).await?;
+        let bob = Identity::create(ctx, &bob_vault).await?;
+        alice.create_purpose_keys().await?;
+
+        let alice_signing_key = alice
+            .get_secret_key(IdentityStateConst::SIGNING_LABEL)
+            .await?;
+        let alice_key_agreement_key = alice.get_key_agreement_secret_key().await?.unwrap();
+
+        bob.create_secure_channel_listener("bob_listener", TrustEveryonePolicy, &bob_storage)
+            .await?;
+
+        let logged_before_channel = audit_sink.entries().len();
+        alice
+            .create_secure_channel("bob_listener", TrustEveryonePolicy, &alice_storage)
+            .await?;
+
+        // Noise XX initiator: ee and es while reading message 2, se while writing
+        // message 3, then the identity proof signed with the signing key
+        let entries = audit_sink.entries().split_off(logged_before_channel);
+        let operations: Vec<_> = entries.iter().map(|e| e.operation()).collect();
+        assert_eq!(
+            operations,
+            vec![
+                VaultOperation::EcDiffieHellman,
+                VaultOperation::EcDiffieHellman,
+                VaultOperation::EcDiffieHellman,
+                VaultOperation::Sign,
+            ]
+        );
+        assert_eq!(entries[2].key_id(), &alice_key_agreement_key);
+        assert_eq!(entries[3].key_id(), &alice_signing_key);
+
+        ctx.stop().await
+    }
+
+    /// Key ids Alice's vault agrees keys with while creating a channel to
+    /// Bob, both vaults drawing their randomness from generators seeded
+    /// with `seed`
+    async fn seeded_handshake_key_ids(
+        ctx: &Context,
+        seed: u64,
+        listener: &str,
+    ) -> Result<Vec<KeyId>> {
+        let audit_sink = InMemoryAuditSink::new();
+        let alice_vault = Vault::create()
+            .with_rng(StdRng::seed_from_u64(seed))
+            .with_audit_sink(Arc::new(audit_sink.clone()));
+        let bob_vault = Vault::create().with_rng(StdRng::seed_from_u64(seed + 1));
+        let storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &alice_vault).await?;
+        let bob = Identity::create(ctx, &bob_vault).await?;
+        bob.create_secure_channel_listener(listener, TrustEveryonePolicy, &storage)
+            .await?;
+        alice
+            .create_secure_channel(listener, TrustEveryonePolicy, &storage)
+            .await?;
+
+        Ok(audit_sink
+            .entries()
+            .iter()
+            .filter(|e| e.operation() == VaultOperation::EcDiffieHellman)
+            .map(|e| e.key_id().clone())
+            .collect())
+    }
+
+    #[ockam_macros::test]
+    async fn test_seeded_vault_gives_reproducible_handshake_keys(ctx: &mut Context) -> Result<()> {
+        let first = seeded_handshake_key_ids(ctx, 7, "first_listener").await?;
+        let second = seeded_handshake_key_ids(ctx, 7, "second_listener").await?;
+        assert_eq!(first.len(), 3);
+        assert_eq!(first, second);
+
+        let other = seeded_handshake_key_ids(ctx, 8, "other_listener").await?;
+        assert_ne!(first[0], other[0]);
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn test_channel_with_invalid_route(ctx: &mut Context) -> Result<()> {
+        let vault = Vault::create();
+        let storage = InMemoryStorage::new();
+        let alice = Identity::create(ctx, &vault).await?;
+
+        let err = alice
+            .create_secure_channel(route![], TrustEveryonePolicy, &storage)
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "EmptySecureChannelRoute");
+
+        let err = alice
+            .create_secure_channel(route!["nobody_listens_here"], TrustEveryonePolicy, &storage)
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "UnknownSecureChannelRouteHop");
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn test_channel_limit_per_identity(ctx: &mut Context) -> Result<()> {
+        let vault = Vault::create();
+
+        let alice_storage = InMemoryStorage::new();
+        let bob_storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &vault).await?;
+        let bob = Identity::create(ctx, &vault).await?;
+
+        bob.create_secure_channel_listener_with_options(
+            "bob_listener",
+            TrustEveryonePolicy,
+            &bob_storage,
+            SecureChannelListenerOptions::new().with_channel_limit(2),
+        )
+        .await?;
+
+        // Up to the limit, channels work
+        let mut bob_channels = vec![];
+        for i in 0..2 {
+            let alice_channel = alice
+                .create_secure_channel("bob_listener", TrustEveryonePolicy, &alice_storage)
+                .await?;
+            ctx.send(route![alice_channel, ctx.address()], i.to_string())
+                .await?;
+            let msg = ctx.receive::<String>().await?.take();
+            bob_channels.push(msg.return_route().next()?.clone());
+            assert_eq!(i.to_string(), msg.body());
+        }
+
+        // Beyond the limit, the handshake is rejected by Bob
+        let alice_channel = alice
+            .create_secure_channel("bob_listener", TrustEveryonePolicy, &alice_storage)
+            .await?;
+        ctx.send(route![alice_channel, ctx.address()], "rejected".to_string())
+            .await?;
+        assert!(ctx.receive_timeout::<String>(1).await.is_err());
+
+        // Closing one channel frees a slot
+        bob.stop_secure_channel(&bob_channels[0]).await?;
+        sleep(Duration::from_millis(100)).await;
+
+        let alice_channel = alice
+            .create_secure_channel("bob_listener", TrustEveryonePolicy, &alice_storage)
+            .await?;
+        ctx.send(route![alice_channel, ctx.address()], "accepted".to_string())
+            .await?;
+        assert_eq!("accepted", ctx.receive::<String>().await?.take().body());
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn test_ping_channel(ctx: &mut Context) -> Result<()> {
+        let vault = Vault::create();
+        let storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &vault).await?;
+        let bob = Identity::create(ctx, &vault).await?;
+
+        bob.create_secure_channel_listener("bob_listener", TrustEveryonePolicy, &storage)
+            .await?;
+        let alice_channel = alice
+            .create_secure_channel("bob_listener", TrustEveryonePolicy, &storage)
+            .await?;
+
+        // A live channel answers, without the ping reaching any worker
+        let rtt = alice
+            .ping_channel(alice_channel.clone(), Duration::from_secs(5))
+            .await?;
+        assert!(rtt < Duration::from_secs(5));
+        assert!(ctx.receive_timeout::<Any>(1).await.is_err());
+
+        // Both ends can ping each other
+        ctx.send(
+            route![alice_channel.clone(), ctx.address()],
+            "hello".to_string(),
+        )
+        .await?;
+        let msg = ctx.receive::<String>().await?.take();
+        let bob_channel = msg.return_route().next()?.clone();
+        bob.ping_channel(bob_channel.clone(), Duration::from_secs(5))
+            .await?;
+
+        // A peer gone without closing the channel doesn't answer
+        ctx.stop_worker(bob_channel).await?;
+        let err = alice
+            .ping_channel(alice_channel.clone(), Duration::from_millis(500))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code().kind, Kind::Timeout);
+
+        // A channel stopped on our end is reported right away
+        alice.stop_secure_channel(&alice_channel).await?;
+        let err = alice
+            .ping_channel(alice_channel, Duration::from_secs(5))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code().kind, Kind::NotFound);
+
+        ctx.stop().await
+    }
+
+    /// Records the address of the responder decryptor the hook is called by
+    struct DecryptorAddressHook {
+        decryptor: Arc<ockam_core::compat::sync::Mutex<Option<Address>>>,
+    }
+
+    #[ockam_core::async_trait]
+    impl SecureChannelEstablishedHook for DecryptorAddressHook {
+        async fn on_established(
+            &self,
+            ctx: &Context,
+            _their_identity_id: &IdentityIdentifier,
+            _channel: &Address,
+        ) -> Result<()> {
+            *self.decryptor.lock().unwrap() = Some(ctx.address());
+            Ok(())
+        }
+    }
+
+    #[ockam_macros::test]
+    async fn test_channel_keepalive(ctx: &mut Context) -> Result<()> {
+        let vault = Vault::create();
+        let storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &vault).await?;
+        let bob = Identity::create(ctx, &vault).await?;
+
+        let decryptor = Arc::new(ockam_core::compat::sync::Mutex::new(None));
+        let hook = DecryptorAddressHook {
+            decryptor: decryptor.clone(),
+        };
+        bob.create_secure_channel_listener_with_options(
+            "bob_listener",
+            TrustEveryonePolicy,
+            &storage,
+            SecureChannelListenerOptions::new().with_established_hook(hook),
+        )
+        .await?;
+        let keepalive =
+            SecureChannelKeepalive::new(Duration::from_millis(100), Duration::from_millis(500));
+        let alice_channel = alice
+            .create_secure_channel_with_options(
+                "bob_listener",
+                TrustEveryonePolicy,
+                &storage,
+                SecureChannelOptions::new().with_keepalive(keepalive),
+            )
+            .await?;
+
+        // Heartbeats are echoed while the other end is alive
+        sleep(Duration::from_secs(1)).await;
+        assert!(ctx.is_address_resolvable(&alice_channel).await?);
+        ctx.send(
+            route![alice_channel.clone(), ctx.address()],
+            "hello".to_string(),
+        )
+        .await?;
+        assert_eq!(ctx.receive::<String>().await?.take().body(), "hello");
+
+        // The transport is fine, but nothing echoes heartbeats anymore
+        let bob_decryptor = decryptor.lock().unwrap().take().unwrap();
+        ctx.stop_worker(bob_decryptor).await?;
+        sleep(Duration::from_millis(300)).await;
+        assert!(ctx.is_address_resolvable(&alice_channel).await?);
+        wait_until_stopped(ctx, &alice_channel).await?;
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn test_channel_listener_keepalive(ctx: &mut Context) -> Result<()> {
+        let vault = Vault::create();
+        let storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &vault).await?;
+        let bob = Identity::create(ctx, &vault).await?;
+
+        let keepalive =
+            SecureChannelKeepalive::new(Duration::from_millis(100), Duration::from_millis(500));
+        bob.create_secure_channel_listener_with_options(
+            "bob_listener",
+            TrustEveryonePolicy,
+            &storage,
+            SecureChannelListenerOptions::new().with_keepalive(keepalive),
+        )
+        .await?;
+        let alice_channel = alice
+            .create_secure_channel("bob_listener", TrustEveryonePolicy, &storage)
+            .await?;
+        ctx.send(
+            route![alice_channel.clone(), ctx.address()],
+            "hello".to_string(),
+        )
+        .await?;
+        let msg = ctx.receive::<String>().await?.take();
+        let bob_channel = msg.return_route().next()?.clone();
+
+        // Alice echoes the heartbeats of the listener without a keepalive
+        sleep(Duration::from_secs(1)).await;
+        assert!(ctx.is_address_resolvable(&bob_channel).await?);
+
+        // Alice can't echo them anymore once her end is gone
+        ctx.stop_worker(alice_channel).await?;
+        wait_until_stopped(ctx, &bob_channel).await?;
+
+        ctx.stop().await
+    }
+
+    /// Wait for the worker at `address` to stop, for at most 5 seconds
+    async fn wait_until_stopped(ctx: &Context, address: &Address) -> Result<()> {
+        for _ in 0..50 {
+            if !ctx.is_address_resolvable(address).await? {
+                return Ok(());
+            }
+            sleep(Duration::from_millis(100)).await;
+        }
+        panic!("{} is still running", address)
+    }
+
+    #[ockam_macros::test]
+    async fn test_stop_secure_channel_closes_both_ends(ctx: &mut Context) -> Result<()> {
+        let vault = Vault::create();
+        let storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &vault).await?;
+        let bob = Identity::create(ctx, &vault).await?;
+
+        bob.create_secure_channel_listener("bob_listener", TrustEveryonePolicy, &storage)
+            .await?;
+        let alice_channel = alice
+            .create_secure_channel("bob_listener", TrustEveryonePolicy, &storage)
+            .await?;
+
+        ctx.send(
+            route![alice_channel.clone(), ctx.address()],
+            "hello".to_string(),
+        )
+        .await?;
+        let msg = ctx.receive::<String>().await?.take();
+        let bob_channel = msg.return_route().next()?.clone();
+
+        alice.stop_secure_channel(&alice_channel).await?;
+        // This end is stopped once the call returns, the other one soon after
+        assert!(!ctx.is_address_resolvable(&alice_channel).await?);
+        wait_until_stopped(ctx, &bob_channel).await?;
+
+        for (identity, channel) in [(&alice, alice_channel), (&bob, bob_channel)] {
+            let err = identity
+                .ping_channel(channel, Duration::from_secs(5))
+                .await
+                .unwrap_err();
+            assert_eq!(err.code().kind, Kind::NotFound);
+        }
+
+        ctx.stop().await
+    }
+
+    /// Forwards messages to the next hop, dropping one when told to
+    struct LossyHop {
+        drop_next: Arc<AtomicBool>,
+    }
+
+    #[ockam_core::async_trait]
+    impl Worker for LossyHop {
+        type Message = Any;
+        type Context = Context;
+
+        async fn handle_message(&mut self, ctx: &mut Context, msg: Routed<Any>) -> Result<()> {
+            if self.drop_next.swap(false, Ordering::SeqCst) {
+                return Ok(());
+            }
+            let mut local_msg = msg.into_local_message();
+            let transport = local_msg.transport_mut();
+            transport.onward_route.step()?;
+            transport.return_route.modify().prepend(ctx.address());
+            ctx.forward(local_msg).await
+        }
+    }
+
+    /// Forwards messages to the next hop, or waits while stalled as a
+    /// transport whose socket doesn't drain
+    struct StallingHop {
+        stalled: Arc<AtomicBool>,
+    }
+
+    #[ockam_core::async_trait]
+    impl Worker for StallingHop {
+        type Message = Any;
+        type Context = Context;
+
+        async fn handle_message(&mut self, ctx: &mut Context, msg: Routed<Any>) -> Result<()> {
+            while self.stalled.load(Ordering::SeqCst) {
+                sleep(Duration::from_millis(10)).await;
+            }
+            let mut local_msg = msg.into_local_message();
+            let transport = local_msg.transport_mut();
+            transport.onward_route.step()?;
+            transport.return_route.modify().prepend(ctx.address());
+            ctx.forward(local_msg).await
+        }
+    }
+
+    #[ockam_macros::test]
+    async fn test_stalled_transport_blocks_the_sender(ctx: &mut Context) -> Result<()> {
+        let vault = Vault::create();
+        let storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &vault).await?;
+        let bob = Identity::create(ctx, &vault).await?;
+
+        let stalled = Arc::new(AtomicBool::new(false));
+        ctx.start_worker(
+            "stalling",
+            StallingHop {
+                stalled: stalled.clone(),
+            },
+        )
+        .await?;
+
+        bob.create_secure_channel_listener("bob_listener", TrustEveryonePolicy, &storage)
+            .await?;
+        let alice_channel = alice
+            .create_secure_channel(
+                route!["stalling", "bob_listener"],
+                TrustEveryonePolicy,
+                &storage,
+            )
+            .await?;
+
+        // Sending blocks once the few messages each hop queues are taken,
+        // rather than the encryptors sealing and queuing everything
+        stalled.store(true, Ordering::SeqCst);
+        let mut sent = 0;
+        while sent < 1000 {
+            let send = ctx.send(
+                route![alice_channel.clone(), ctx.address()],
+                sent.to_string(),
+            );
+            if tokio::time::timeout(Duration::from_millis(500), send)
+                .await
+                .is_err()
+            {
+                break;
+            }
+            sent += 1;
+        }
+        assert!(sent < 200, "{} messages were queued", sent);
+
+        // Once the transport drains, the queued messages are delivered in order
+        stalled.store(false, Ordering::SeqCst);
+        for i in 0..sent {
+            assert_eq!(i.to_string(), ctx.receive::<String>().await?.take().body());
+        }
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn test_sequence_numbers_report_gaps(ctx: &mut Context) -> Result<()> {
+        let vault = Vault::create();
+        let storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &vault).await?;
+        let bob = Identity::create(ctx, &vault).await?;
+
+        let drop_next = Arc::new(AtomicBool::new(false));
+        ctx.start_worker(
+            "lossy",
+            LossyHop {
+                drop_next: drop_next.clone(),
+            },
+        )
+        .await?;
+
+        let bob_gaps = ChannelGapCounter::new();
+        bob.create_secure_channel_listener_with_options(
+            "bob_listener",
+            TrustEveryonePolicy,
+            &storage,
+            SecureChannelListenerOptions::new().with_sequence_numbers(bob_gaps.clone()),
+        )
+        .await?;
+        let alice_gaps = ChannelGapCounter::new();
+        let alice_channel = alice
+            .create_secure_channel_with_options(
+                route!["lossy", "bob_listener"],
+                TrustEveryonePolicy,
+                &storage,
+                SecureChannelOptions::new().with_sequence_numbers(alice_gaps.clone()),
+            )
+            .await?;
+
+        for (i, dropped) in [(1, false), (2, true), (3, false)] {
+            drop_next.store(dropped, Ordering::SeqCst);
+            ctx.send(route![alice_channel.clone(), ctx.address()], i.to_string())
+                .await?;
+            if !dropped {
+                assert_eq!(i.to_string(), ctx.receive::<String>().await?.take().body());
+            }
+        }
+        assert_eq!(bob_gaps.gaps(), 1);
+        assert_eq!(bob_gaps.lost_messages(), 1);
+
+        // Replies are numbered too, and none of them was lost
+        ctx.send(route![alice_channel, ctx.address()], "4".to_string())
+            .await?;
+        let msg = ctx.receive::<String>().await?.take();
+        let bob_channel = msg.return_route().next()?.clone();
+        ctx.send(route![bob_channel, ctx.address()], "reply".to_string())
+            .await?;
+        assert_eq!("reply", ctx.receive::<String>().await?.take().body());
+        assert_eq!(alice_gaps.gaps(), 0);
+        assert_eq!(bob_gaps.gaps(), 1);
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn test_channel_byte_budget(ctx: &mut Context) -> Result<()> {
+        let vault = Vault::create();
+        let storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &vault).await?;
+        let bob = Identity::create(ctx, &vault).await?;
+
+        bob.create_secure_channel_listener("bob_listener", TrustEveryonePolicy, &storage)
+            .await?;
+        let alice_channel = alice
+            .create_secure_channel_with_options(
+                "bob_listener",
+                TrustEveryonePolicy,
+                &storage,
+                SecureChannelOptions::new().with_byte_budget(50),
+            )
+            .await?;
+
+        // Each message takes 21 bytes of the budget, two of them fit
+        let payload = "x".repeat(20);
+        let mut bob_channel = None;
+        for _ in 0..2 {
+            ctx.send(
+                route![alice_channel.clone(), ctx.address()],
+                payload.clone(),
+            )
+            .await?;
+            let msg = ctx.receive::<String>().await?.take();
+            bob_channel = msg.return_route().next().ok().cloned();
+            assert_eq!(payload, msg.body());
+        }
+
+        // The third one is dropped and the channel is closed
+        ctx.send(
+            route![alice_channel.clone(), ctx.address()],
+            payload.clone(),
+        )
+        .await?;
+        assert!(ctx.receive_timeout::<String>(1).await.is_err());
+
+        let err = alice
+            .ping_channel(alice_channel, Duration::from_secs(5))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code().kind, Kind::NotFound);
+
+        // The other end was told, and closed its side too
+        wait_until_stopped(ctx, &bob_channel.unwrap()).await?;
+
+        ctx.stop().await
+    }
+
+    /// Forwards messages to the next hop, keeping a copy of their payload
+    /// as an eavesdropper on the transport would
+    struct CapturingHop {
+        captured: Arc<ockam_core::compat::sync::Mutex<Vec<Vec<u8>>>>,
+    }
+
+    #[ockam_core::async_trait]
+    impl Worker for CapturingHop {
+        type Message = Any;
+        type Context = Context;
+
+        async fn handle_message(&mut self, ctx: &mut Context, msg: Routed<Any>) -> Result<()> {
+            self.captured.lock().unwrap().push(msg.payload().to_vec());
+            let mut local_msg = msg.into_local_message();
+            let transport = local_msg.transport_mut();
+            transport.onward_route.step()?;
+            transport.return_route.modify().prepend(ctx.address());
+            ctx.forward(local_msg).await
+        }
+    }
+
+    #[ockam_macros::test]
+    async fn test_channel_key_escrow(ctx: &mut Context) -> Result<()> {
+        let alice_vault = Vault::create();
+        let bob_vault = Vault::create();
+        let recovery_vault = Vault::create();
+        let storage = InMemoryStorage::new();
+
+        let attributes = SecretAttributes::new(
+            SecretType::X25519,
+            SecretPersistence::Persistent,
+            CURVE25519_SECRET_LENGTH_U32,
+        );
+        let recovery_secret = recovery_vault.secret_generate(attributes).await?;
+        let other_secret = recovery_vault.secret_generate(attributes).await?;
+        let key_escrow = KeyEscrow::new(
+            recovery_vault
+                .secret_public_key_get(&recovery_secret)
+                .await?,
+        )?;
+
+        let alice = Identity::create(ctx, &alice_vault).await?;
+        let bob = Identity::create(ctx, &bob_vault).await?;
+
+        let captured = Arc::new(ockam_core::compat::sync::Mutex::new(Vec::new()));
+        ctx.start_worker(
+            "capturing",
+            CapturingHop {
+                captured: captured.clone(),
+            },
+        )
+        .await?;
+
+        bob.create_secure_channel_listener_with_options(
+            "bob_listener",
+            TrustEveryonePolicy,
+            &storage,
+            SecureChannelListenerOptions::new().with_key_escrow(key_escrow.clone()),
+        )
+        .await?;
+        let alice_channel = alice
+            .create_secure_channel(
+                route!["capturing", "bob_listener"],
+                TrustEveryonePolicy,
+                &storage,
+            )
+            .await?;
+
+        // The channel works as usual, in both directions
+        ctx.send(
+            route![alice_channel.clone(), ctx.address()],
+            "Hello, Bob!".to_string(),
+        )
+        .await?;
+        let msg = ctx.receive::<String>().await?.take();
+        assert_eq!("Hello, Bob!", msg.body());
+        let bob_channel = msg.return_route().next()?.clone();
+        ctx.send(
+            route![bob_channel, ctx.address()],
+            "Hello, Alice!".to_string(),
+        )
+        .await?;
+        assert_eq!(
+            "Hello, Alice!",
+            ctx.receive::<String>().await?.take().body()
+        );
+
+        // The recovery key decrypts what was captured, both ways, once the
+        // records are exported to its holder
+        let records = EscrowedChannelKeys::import(&key_escrow.export().await?)?;
+        assert_eq!(records.len(), 1);
+        assert!(key_escrow.take_records().await?.is_empty());
+        let keys = records[0]
+            .recover(&recovery_vault, &recovery_secret)
+            .await?;
+        let captured = captured.lock().unwrap().clone();
+        let mut decrypted = Vec::new();
+        for payload in captured {
+            // The key exchange isn't encrypted, the identity handshake is
+            if let Ok(msg) = keys.decrypt(&recovery_vault, &payload).await {
+                if msg.onward_route.recipient() == ctx.address() {
+                    decrypted.push(String::decode(&msg.payload)?);
+                }
+            }
+        }
+        assert_eq!(decrypted, vec!["Hello, Bob!", "Hello, Alice!"]);
+
+        // Without the recovery key the keys stay sealed
+        assert!(records[0]
+            .recover(&recovery_vault, &other_secret)
+            .await
+            .is_err());
+
+        // Channels of other listeners don't escrow their keys
+        bob.create_secure_channel_listener("bob_plain_listener", TrustEveryonePolicy, &storage)
+            .await?;
+        alice
+            .create_secure_channel("bob_plain_listener", TrustEveryonePolicy, &storage)
+            .await?;
+        assert!(key_escrow.take_records().await?.is_empty());
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn test_channel_key_escrow_storage(ctx: &mut Context) -> Result<()> {
+        let vault = Vault::create();
+        let storage = InMemoryStorage::new();
+        let attributes = SecretAttributes::new(
+            SecretType::X25519,
+            SecretPersistence::Persistent,
+            CURVE25519_SECRET_LENGTH_U32,
+        );
+        let recovery_secret = vault.secret_generate(attributes).await?;
+        let recovery_key = vault.secret_public_key_get(&recovery_secret).await?;
+
+        let alice = Identity::create(ctx, &vault).await?;
+        let bob = Identity::create(ctx, &vault).await?;
+
+        // Records are appended to the file, which a new storage reopens
+        let path = std::env::temp_dir().join(format!("ockam_key_escrow_{}", random::<u64>()));
+        let key_escrow = KeyEscrow::with_storage(
+            recovery_key.clone(),
+            FileKeyEscrowStorage::open(&path, 1).await?,
+        )?;
+        bob.create_secure_channel_listener_with_options(
+            "bob_listener",
+            TrustEveryonePolicy,
+            &storage,
+            SecureChannelListenerOptions::new().with_key_escrow(key_escrow.clone()),
+        )
+        .await?;
+        alice
+            .create_secure_channel_extended(
+                "bob_listener",
+                TrustEveryonePolicy,
+                &storage,
+                Duration::from_secs(5),
+            )
+            .await?;
+
+        // Once the storage is full, channels aren't established
+        assert!(alice
+            .create_secure_channel_extended(
+                "bob_listener",
+                TrustEveryonePolicy,
+                &storage,
+                Duration::from_secs(5),
+            )
+            .await
+            .is_err());
+
+        let reopened =
+            KeyEscrow::with_storage(recovery_key, FileKeyEscrowStorage::open(&path, 1).await?)?;
+        let records = reopened.take_records().await?;
+        assert_eq!(records.len(), 1);
+        records[0].recover(&vault, &recovery_secret).await?;
+
+        // Taking the records frees the storage
+        alice
+            .create_secure_channel_extended(
+                "bob_listener",
+                TrustEveryonePolicy,
+                &storage,
+                Duration::from_secs(5),
+            )
+            .await?;
+        assert_eq!(key_escrow.take_records().await?.len(), 1);
+
+        std::fs::remove_file(path).unwrap();
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn test_tunneled_secure_channel_works(ctx: &mut Context) -> Result<()> {
+        let vault = Vault::create();
+
+        let alice_storage = InMemoryStorage::new();
+        let bob_storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &vault).await?;
+        let bob = Identity::create(ctx, &vault).await?;
+
+        let alice_trust_policy = TrustIdentifierPolicy::new(bob.identifier().clone());
+        let bob_trust_policy = TrustIdentifierPolicy::new(alice.identifier().clone());
+
+        bob.create_secure_channel_listener("bob_listener", bob_trust_policy.clone(), &bob_storage)
+            .await?;
+
+        let alice_channel = alice
+            .create_secure_channel(
+                route!["bob_listener"],
+                alice_trust_policy.clone(),
+                &alice_storage,
+            )
+            .await?;
+
+        bob.create_secure_channel_listener("bob_another_listener", bob_trust_policy, &bob_storage)
+            .await?;
+
+        let alice_another_channel = alice
+            .create_secure_channel(
+                route![alice_channel, "bob_another_listener"],
+                alice_trust_policy,
+                &alice_storage,
+            )
+            .await?;
+
+        ctx.send(
+            route![alice_another_channel, ctx.address()],
+            "Hello, Bob!".to_string(),
+        )
+        .await?;
+        let msg = ctx.receive::<String>().await?.take();
+        let return_route = msg.return_route();
+        assert_eq!("Hello, Bob!", msg.body());
+
+        ctx.send(return_route, "Hello, Alice!".to_string()).await?;
+        assert_eq!(
+            "Hello, Alice!",
+            ctx.receive::<String>().await?.take().body()
+        );
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn test_double_tunneled_secure_channel_works(ctx: &mut Context) -> Result<()> {
+        let vault = Vault::create();
+
+        let alice_storage = InMemoryStorage::new();
+        let bob_storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &vault).await?;
+        let bob = Identity::create(ctx, &vault).await?;
+
+        let alice_trust_policy = TrustIdentifierPolicy::new(bob.identifier().clone());
+        let bob_trust_policy = TrustIdentifierPolicy::new(alice.identifier().clone());
+
+        bob.create_secure_channel_listener("bob_listener", bob_trust_policy.clone(), &bob_storage)
+            .await?;
+
+        let alice_channel = alice
+            .create_secure_channel(
+                route!["bob_listener"],
+                alice_trust_policy.clone(),
+                &alice_storage,
+            )
+            .await?;
+
+        bob.create_secure_channel_listener(
+            "bob_another_listener",
+            bob_trust_policy.clone(),
+            &bob_storage,
+        )
+        .await?;
+
+        let alice_another_channel = alice
+            .create_secure_channel(
+                route![alice_channel, "bob_another_listener"],
+                alice_trust_policy.clone(),
+                &alice_storage,
+            )
+            .await?;
+
+        bob.create_secure_channel_listener(
+            "bob_yet_another_listener",
+            bob_trust_policy,
+            &bob_storage,
+        )
+        .await?;
+
+        let alice_yet_another_channel = alice
+            .create_secure_channel(
+                route![alice_another_channel, "bob_yet_another_listener"],
+                alice_trust_policy,
+                &alice_storage,
+            )
+            .await?;
+
+        ctx.send(
+            route![alice_yet_another_channel, ctx.address()],
+            "Hello, Bob!".to_string(),
+        )
+        .await?;
+        let msg = ctx.receive::<String>().await?.take();
+        let return_route = msg.return_route();
+        assert_eq!("Hello, Bob!", msg.body());
+
+        ctx.send(return_route, "Hello, Alice!".to_string()).await?;
+        assert_eq!(
+            "Hello, Alice!",
+            ctx.receive::<String>().await?.take().body()
+        );
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn test_double_tunneled_secure_channel_preserves_order(ctx: &mut Context) -> Result<()> {
+        let vault = Vault::create();
+        let storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &vault).await?;
+        let bob = Identity::create(ctx, &vault).await?;
+
+        let mut channel_route = route![];
+        for listener in [
+            "bob_listener",
+            "bob_another_listener",
+            "bob_yet_another_listener",
+        ] {
+            bob.create_secure_channel_listener(listener, TrustEveryonePolicy, &storage)
+                .await?;
+            let channel = alice
+                .create_secure_channel(
+                    channel_route.modify().append(listener),
+                    TrustEveryonePolicy,
+                    &storage,
+                )
+                .await?;
+            channel_route = route![channel];
+        }
+
+        // Several senders push numbered messages concurrently through the
+        // same channel, each of them must see its messages arrive in order
+        let senders = 4;
+        let count = 250;
+        let mut receiver = ctx.new_detached("ordered_receiver").await?;
+        for sender in 0..senders {
+            let sender_ctx = ctx
+                .new_detached(format!("ordered_sender_{}", sender))
+                .await?;
+            let route: Route = channel_route
+                .clone()
+                .modify()
+                .append("ordered_receiver")
+                .into();
+            tokio::spawn(async move {
+                for i in 0..count {
+                    sender_ctx
+                        .send(route.clone(), format!("{}:{}", sender, i))
+                        .await
+                        .unwrap();
+                }
+            });
+        }
+
+        let mut next = vec![0; senders];
+        for _ in 0..senders * count {
+            let msg = receiver.receive::<String>().await?.take().body();
+            let (sender, i) = msg.split_once(':').unwrap();
+            let sender: usize = sender.parse().unwrap();
+            assert_eq!(i.parse::<usize>().unwrap(), next[sender]);
+            next[sender] += 1;
+        }
+        assert!(next.iter().all(|&n| n == count));
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn test_many_times_tunneled_secure_channel_works(ctx: &mut Context) -> Result<()> {
+        let vault = Vault::create();
+
+        let alice_storage = InMemoryStorage::new();
+        let bob_storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &vault).await?;
+        let bob = Identity::create(ctx, &vault).await?;
+
+        let alice_trust_policy = TrustIdentifierPolicy::new(bob.identifier().clone());
+        let bob_trust_policy = TrustIdentifierPolicy::new(alice.identifier().clone());
+
+        let n = rand::random::<u8>() % 5 + 4;
+        let mut channels = vec![];
+        for i in 0..n {
+            bob.create_secure_channel_listener(
+                i.to_string(),
+                bob_trust_policy.clone(),
+                &bob_storage,
+            )
+            .await?;
+            let channel_route = if i > 0 {
+                route![channels.pop().unwrap(), i.to_string()]
+            } else {
+                route![i.to_string()]
+            };
+            let alice_channel = alice
+                .create_secure_channel(channel_route, alice_trust_policy.clone(), &alice_storage)
+                .await?;
+            channels.push(alice_channel);
+        }
+
+        ctx.send(
+            route![channels.pop().unwrap(), ctx.address()],
+            "Hello, Bob!".to_string(),
+        )
+        .await?;
+        let msg = ctx.receive::<String>().await?.take();
+        let return_route = msg.return_route();
+        assert_eq!("Hello, Bob!", msg.body());
+
+        ctx.send(return_route, "Hello, Alice!".to_string()).await?;
+        assert_eq!(
+            "Hello, Alice!",
+            ctx.receive::<String>().await?.take().body()
+        );
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn test_nested_secure_channel_runs_one_handshake_per_layer(
+        ctx: &mut Context,
+    ) -> Result<()> {
+        let vault = Vault::create();
+
+        let alice_storage = InMemoryStorage::new();
+        let bob_storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &vault).await?;
+        let bob = Identity::create(ctx, &vault).await?;
+
+        let layers = 3;
+        for i in 0..layers {
+            bob.create_secure_channel_listener(i.to_string(), TrustEveryonePolicy, &bob_storage)
+                .await?;
+        }
+
+        // Routing through the previous channel runs a new handshake for
+        // every inner layer each time the stack is set up
+        let before = alice.started_handshakes();
+        for _ in 0..2 {
+            let mut outer = alice
+                .create_secure_channel("0", TrustEveryonePolicy, &alice_storage)
+                .await?;
+            for i in 1..layers {
+                outer = alice
+                    .create_secure_channel(
+                        route![outer, i.to_string()],
+                        TrustEveryonePolicy,
+                        &alice_storage,
+                    )
+                    .await?;
+            }
+        }
+        assert_eq!(alice.started_handshakes() - before, 2 * layers);
+
+        // Without the pool, nested channels are set up on every call
+        let outer = alice
+            .create_secure_channel("0", TrustEveryonePolicy, &alice_storage)
+            .await?;
+        let before = alice.started_handshakes();
+        for _ in 0..2 {
+            alice
+                .create_nested_secure_channel(&outer, "1", TrustEveryonePolicy, &alice_storage)
+                .await?;
+        }
+        assert_eq!(alice.started_handshakes() - before, 2);
+
+        // With the pool, nested channels are set up once per outer channel
+        alice.enable_secure_channel_pool();
+        let outer = alice
+            .create_secure_channel("0", TrustEveryonePolicy, &alice_storage)
+            .await?;
+        let before = alice.started_handshakes();
+        let mut innermost = vec![];
+        for _ in 0..2 {
+            let mut channel = outer.clone();
+            for i in 1..layers {
+                channel = alice
+                    .create_nested_secure_channel(
+                        &channel,
+                        i.to_string(),
+                        TrustEveryonePolicy,
+                        &alice_storage,
+                    )
+                    .await?;
+            }
+            innermost.push(channel);
+        }
+        assert_eq!(alice.started_handshakes() - before, layers - 1);
+        assert_eq!(innermost[0], innermost[1]);
+
+        ctx.send(
+            route![innermost[0].clone(), ctx.address()],
+            "Hello, Bob!".to_string(),
+        )
+        .await?;
+        let msg = ctx.receive::<String>().await?.take();
+        assert_eq!("Hello, Bob!", msg.body());
+
+        ctx.stop().await
+    }
+
+    /// Delays the trust decision for one identity to simulate a slow remote check
+    struct SlowTrustPolicy {
+        slow_identity_id: IdentityIdentifier,
+    }
+
+    #[ockam_core::async_trait]
+    impl TrustPolicy for SlowTrustPolicy {
+        async fn check(&self, trust_info: &SecureChannelTrustInfo) -> Result<bool> {
+            if trust_info.their_identity_id() == &self.slow_identity_id {
+                sleep(Duration::from_secs(2)).await;
+            }
+            Ok(true)
+        }
+    }
+
+    #[ockam_macros::test]
+    async fn test_slow_handshake_does_not_block_listener(ctx: &mut Context) -> Result<()> {
+        let vault = Vault::create();
+
+        let alice_storage = InMemoryStorage::new();
+        let bob_storage = InMemoryStorage::new();
+        let carol_storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &vault).await?;
+        let bob = Identity::create(ctx, &vault).await?;
+        let carol = Identity::create(ctx, &vault).await?;
+
+        let bob_trust_policy = SlowTrustPolicy {
+            slow_identity_id: carol.identifier().clone(),
+        };
+        bob.create_secure_channel_listener("bob_listener", bob_trust_policy, &bob_storage)
+            .await?;
+
+        // Carol's handshake is stuck in Bob's trust policy check...
+        let carol_channel = carol
+            .create_secure_channel("bob_listener", TrustEveryonePolicy, &carol_storage)
+            .await?;
+        ctx.send(route![carol_channel, ctx.address()], "carol".to_string())
+            .await?;
+
+        // ...while Alice's handshake, started later, completes right away
+        let alice_channel = alice
+            .create_secure_channel("bob_listener", TrustEveryonePolicy, &alice_storage)
+            .await?;
+        ctx.send(route![alice_channel, ctx.address()], "alice".to_string())
+            .await?;
+
+        assert_eq!("alice", ctx.receive::<String>().await?.take().body());
+        assert_eq!("carol", ctx.receive::<String>().await?.take().body());
+
+        ctx.stop().await
+    }
+
+    /// Trusts identities that have a given attribute in the storage
+    struct AttributeTrustPolicy {
+        storage: InMemoryStorage,
+        key: &'static str,
+    }
+
+    #[ockam_core::async_trait]
+    impl TrustPolicy for AttributeTrustPolicy {
+        async fn check(&self, trust_info: &SecureChannelTrustInfo) -> Result<bool> {
+            let id = trust_info.their_identity_id().to_string();
+            Ok(self.storage.get(&id, self.key).await?.is_some())
+        }
+    }
+
+    #[ockam_macros::test]
+    async fn test_channel_closed_when_attribute_is_deleted(ctx: &mut Context) -> Result<()> {
+        let vault = Vault::create();
+
+        let alice_storage = InMemoryStorage::new();
+        let bob_storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &vault).await?;
+        let bob = Identity::create(ctx, &vault).await?;
+        let bob_id = bob.identifier().to_string();
+
+        alice_storage
+            .set(&bob_id, "role".to_string(), b"member".to_vec())
+            .await?;
+        let alice_trust_policy = AttributeTrustPolicy {
+            storage: alice_storage.clone(),
+            key: "role",
+        };
+
+        bob.create_secure_channel_listener("bob_listener", TrustEveryonePolicy, &bob_storage)
+            .await?;
+        let alice_channel = alice
+            .create_secure_channel("bob_listener", alice_trust_policy, &alice_storage)
+            .await?;
+        ctx.send(
+            route![alice_channel.clone(), ctx.address()],
+            "hello".to_string(),
+        )
+        .await?;
+        assert_eq!("hello", ctx.receive::<String>().await?.take().body());
+
+        // Unrelated changes don't affect the channel
+        alice_storage
+            .set(&bob_id, "other".to_string(), vec![])
+            .await?;
+        sleep(Duration::from_millis(100)).await;
+        assert!(ctx.is_address_resolvable(&alice_channel).await?);
+
+        // Revoking the attribute makes the trust policy fail and closes the channel
+        alice_storage.del(&bob_id, "role").await?;
+        sleep(Duration::from_millis(100)).await;
+        assert!(!ctx.is_address_resolvable(&alice_channel).await?);
+        assert!(ctx
+            .send(route![alice_channel, ctx.address()], "bye".to_string())
+            .await
+            .is_err());
+
+        ctx.stop().await
+    }
+
+    /// Rejects channels negotiating a given cipher suite
+    struct RejectCipherSuite(CipherSuite);
+
+    #[ockam_core::async_trait]
+    impl TrustPolicy for RejectCipherSuite {
+        async fn check(&self, trust_context: &TrustContext) -> Result<bool> {
+            Ok(trust_context.cipher_suite() != Some(self.0))
+        }
+    }
+
+    #[ockam_macros::test]
+    async fn test_trust_policy_rejects_cipher_suite(ctx: &mut Context) -> Result<()> {
+        let vault = Vault::create();
+
+        let alice_storage = InMemoryStorage::new();
+        let bob_storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &vault).await?;
+        let bob = Identity::create(ctx, &vault).await?;
+
+        bob.create_secure_channel_listener("bob_listener", TrustEveryonePolicy, &bob_storage)
+            .await?;
+
+        let res = alice
+            .create_secure_channel(
+                "bob_listener",
+                RejectCipherSuite(CipherSuite::NoiseXx25519AesGcmSha256),
+                &alice_storage,
+            )
+            .await;
+        assert!(res.is_err());
+
+        ctx.stop().await
+    }
+
+    /// Only trusts local channels whose context is complete
+    struct LocalContextPolicy;
+
+    #[ockam_core::async_trait]
+    impl TrustPolicy for LocalContextPolicy {
+        async fn check(&self, trust_context: &TrustContext) -> Result<bool> {
+            Ok(trust_context.cipher_suite().is_some()
+                && trust_context.local_address().is_some()
+                && trust_context.transport() == Some(ockam_core::LOCAL))
+        }
+    }
+
+    #[ockam_macros::test]
+    async fn test_trust_context_is_complete(ctx: &mut Context) -> Result<()> {
+        let vault = Vault::create();
+
+        let alice_storage = InMemoryStorage::new();
+        let bob_storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &vault).await?;
+        let bob = Identity::create(ctx, &vault).await?;
+
+        bob.create_secure_channel_listener("bob_listener", LocalContextPolicy, &bob_storage)
+            .await?;
+        let alice_channel = alice
+            .create_secure_channel("bob_listener", LocalContextPolicy, &alice_storage)
+            .await?;
+
+        ctx.send(route![alice_channel, ctx.address()], "hello".to_string())
+            .await?;
+        assert_eq!("hello", ctx.receive::<String>().await?.take().body());
+
+        ctx.stop().await
+    }
+
+    /// Trusts everyone while the flag is set, without a pool key
+    struct UnkeyedTrustPolicy(Arc<AtomicBool>);
+
+    #[ockam_core::async_trait]
+    impl TrustPolicy for UnkeyedTrustPolicy {
+        async fn check(&self, _trust_info: &SecureChannelTrustInfo) -> Result<bool> {
+            Ok(self.0.load(Ordering::Relaxed))
+        }
+    }
+
+    /// Trusts everyone while the flag is set
+    struct RevocableTrustPolicy(Arc<AtomicBool>);
+
+    #[ockam_core::async_trait]
+    impl TrustPolicy for RevocableTrustPolicy {
+        async fn check(&self, _trust_info: &SecureChannelTrustInfo) -> Result<bool> {
+            Ok(self.0.load(Ordering::Relaxed))
+        }
+
+        fn pool_key(&self) -> Option<String> {
+            Some("revocable".into())
+        }
+    }
+
+    #[ockam_macros::test]
+    async fn test_secure_channel_pool(ctx: &mut Context) -> Result<()> {
+        let vault = Vault::create();
+
+        let alice_storage = InMemoryStorage::new();
+        let bob_storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &vault).await?;
+        let bob = Identity::create(ctx, &vault).await?;
+
+        bob.create_secure_channel_listener("bob_listener", TrustEveryonePolicy, &bob_storage)
+            .await?;
+
+        alice.enable_secure_channel_pool();
+        let channel = alice
+            .create_secure_channel("bob_listener", TrustEveryonePolicy, &alice_storage)
+            .await?;
+        let reused = alice
+            .create_secure_channel("bob_listener", TrustEveryonePolicy, &alice_storage)
+            .await?;
+        assert_eq!(channel, reused);
+
+        // A policy trusting other identities gets its own channel, which
+        // is shared with the policies trusting the same identities
+        let other = alice
+            .create_secure_channel(
+                "bob_listener",
+                TrustIdentifierPolicy::new(bob.identifier().clone()),
+                &alice_storage,
+            )
+            .await?;
+        assert_ne!(channel, other);
+        let reused = alice
+            .create_secure_channel(
+                "bob_listener",
+                TrustIdentifierPolicy::new(bob.identifier().clone()),
+                &alice_storage,
+            )
+            .await?;
+        assert_eq!(other, reused);
+
+        // Channels checked by policies without a pool key are not pooled
+        let unpooled = alice
+            .create_secure_channel(
+                "bob_listener",
+                UnkeyedTrustPolicy(Arc::new(AtomicBool::new(true))),
+                &alice_storage,
+            )
+            .await?;
+        assert!(!alice.pooled_secure_channels().contains(&unpooled));
+
+        // A channel whose identity no longer passes the policy is evicted
+        // and stopped
+        let trusted = Arc::new(AtomicBool::new(true));
+        let revoked = alice
+            .create_secure_channel(
+                "bob_listener",
+                RevocableTrustPolicy(trusted.clone()),
+                &alice_storage,
+            )
+            .await?;
+        trusted.store(false, Ordering::Relaxed);
+        assert!(alice
+            .create_secure_channel(
+                "bob_listener",
+                RevocableTrustPolicy(trusted.clone()),
+                &alice_storage,
+            )
+            .await
+            .is_err());
+        assert!(!alice.pooled_secure_channels().contains(&revoked));
+        let mut polls = 0;
+        while alice.secure_channel_negotiation(&revoked).is_some() {
+            polls += 1;
+            assert!(polls < 100, "the evicted channel wasn't stopped");
+            sleep(Duration::from_millis(20)).await;
+        }
+
+        // A stopped channel is evicted and replaced
+        alice.stop_secure_channel(&channel).await?;
+        let replacement = alice
+            .create_secure_channel("bob_listener", TrustEveryonePolicy, &alice_storage)
+            .await?;
+        assert_ne!(channel, replacement);
+        let pooled = alice.pooled_secure_channels();
+        assert!(pooled.contains(&replacement));
+        assert!(!pooled.contains(&channel));
+
+        alice.disable_secure_channel_pool();
+        let new = alice
+            .create_secure_channel("bob_listener", TrustEveryonePolicy, &alice_storage)
+            .await?;
+        assert_ne!(replacement, new);
+        assert!(alice.pooled_secure_channels().is_empty());
+
+        ctx.stop().await
+    }
+
+    async fn sorted_workers(ctx: &Context) -> Result<Vec<Address>> {
+        let mut workers = ctx.list_workers().await?;
+        workers.sort();
+        Ok(workers)
+    }
+
+    #[ockam_macros::test]
+    async fn test_handshake_timeout_stops_workers(ctx: &mut Context) -> Result<()> {
+        let vault = Vault::create();
+        let alice_storage = InMemoryStorage::new();
+        let alice = Identity::create(ctx, &vault).await?;
+
+        // Nobody answers the handshake
+        let received_count = Arc::new(AtomicU8::new(0));
+        ctx.start_worker(
+            "blackhole",
+            Receiver {
+                received_count: received_count.clone(),
+            },
+        )
+        .await?;
+        let workers = sorted_workers(ctx).await?;
+
+        let res = alice
+            .create_secure_channel_extended(
+                route!["blackhole"],
+                TrustEveryonePolicy,
+                &alice_storage,
+                Duration::from_millis(500),
+            )
+            .await;
+        assert!(res.is_err());
+        assert_eq!(received_count.load(Ordering::Relaxed), 1);
+
+        sleep(Duration::from_secs(1)).await;
+        assert_eq!(workers, sorted_workers(ctx).await?);
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn test_cancelled_handshake_stops_workers(ctx: &mut Context) -> Result<()> {
+        let vault = Vault::create();
+        let alice_storage = InMemoryStorage::new();
+        let alice = Identity::create(ctx, &vault).await?;
+
+        let received_count = Arc::new(AtomicU8::new(0));
+        ctx.start_worker(
+            "blackhole",
+            Receiver {
+                received_count: received_count.clone(),
+            },
+        )
+        .await?;
+        let workers = sorted_workers(ctx).await?;
+
+        // The caller gives up long before the handshake times out
+        let res = tokio::time::timeout(
+            Duration::from_millis(100),
+            alice.create_secure_channel_extended(
+                route!["blackhole"],
+                TrustEveryonePolicy,
+                &alice_storage,
+                Duration::from_secs(1),
+            ),
+        )
+        .await;
+        assert!(res.is_err());
+
+        sleep(Duration::from_secs(2)).await;
+        assert_eq!(workers, sorted_workers(ctx).await?);
+
+        ctx.stop().await
+    }
+
+    /// A link adding `delay` to every message, which stops forwarding
+    /// messages after `forwarded` of them
+    struct SlowLink {
+        delay: Duration,
+        forwarded: usize,
+    }
+
+    #[ockam_core::async_trait]
+    impl Worker for SlowLink {
+        type Message = Any;
+        type Context = Context;
+
+        async fn handle_message(
+            &mut self,
+            ctx: &mut Self::Context,
+            msg: Routed<Self::Message>,
+        ) -> Result<()> {
+            if self.forwarded == 0 {
+                return Ok(());
+            }
+            self.forwarded -= 1;
+
+            let mut local_msg = msg.into_local_message();
+            let transport = local_msg.transport_mut();
+            transport.onward_route.step()?;
+            transport.return_route.modify().prepend(ctx.address());
+            sleep(self.delay).await;
+            ctx.forward(local_msg).await
+        }
+    }
+
+    #[ockam_macros::test]
+    async fn test_adaptive_handshake_timeout(ctx: &mut Context) -> Result<()> {
+        let vault = Vault::create();
+        let storage = InMemoryStorage::new();
+        let alice = Identity::create(ctx, &vault).await?;
+        let bob = Identity::create(ctx, &vault).await?;
+        bob.create_secure_channel_listener("bob_listener", TrustEveryonePolicy, &storage)
+            .await?;
+
+        // The rest of the handshake takes longer than the minimum timeout,
+        // which is scaled to the round trip of 400ms over the slow link
+        let timeout = AdaptiveHandshakeTimeout::new(
+            Duration::from_secs(5),
+            Duration::from_millis(100),
+            Duration::from_secs(10),
+        );
+        ctx.start_worker(
+            "slow_link",
+            SlowLink {
+                delay: Duration::from_millis(200),
+                forwarded: usize::MAX,
+            },
+        )
+        .await?;
+        let channel = alice
+            .create_secure_channel_with_options(
+                route!["slow_link", "bob_listener"],
+                TrustEveryonePolicy,
+                &storage,
+                SecureChannelOptions::new().with_adaptive_timeout(timeout),
+            )
+            .await?;
+        ctx.send(route![channel, ctx.address()], "Hello, Bob!".to_string())
+            .await?;
+        assert_eq!("Hello, Bob!", ctx.receive::<String>().await?.take().body());
+
+        // Bob stops answering after the key exchange over a fast link, so
+        // the handshake fails long before the maximum timeout
+        ctx.start_worker(
+            "failing_link",
+            SlowLink {
+                delay: Duration::ZERO,
+                forwarded: 2,
+            },
+        )
+        .await?;
+        let started = std::time::Instant::now();
+        let res = alice
+            .create_secure_channel_with_options(
+                route!["failing_link", "bob_listener"],
+                TrustEveryonePolicy,
+                &storage,
+                SecureChannelOptions::new().with_adaptive_timeout(timeout),
+            )
+            .await;
+        assert!(res.is_err());
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        ctx.stop().await
+    }
+
+    struct Receiver {
+        received_count: Arc<AtomicU8>,
+    }
+
+    #[ockam_core::async_trait]
+    impl Worker for Receiver {
+        type Message = Any;
+        type Context = Context;
+
+        async fn handle_message(
+            &mut self,
+            _context: &mut Self::Context,
+            _msg: Routed<Self::Message>,
+        ) -> Result<()> {
+            self.received_count.fetch_add(1, Ordering::Relaxed);
+
+            Ok(())
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[ockam_macros::test]
+    async fn access_control__known_participant__should_pass_messages(
+        ctx: &mut Context,
+    ) -> Result<()> {
+        let received_count = Arc::new(AtomicU8::new(0));
+        let receiver = Receiver {
+            received_count: received_count.clone(),
+        };
 
         let vault = Vault::create();
 
@@ -441,4 +2962,418 @@ mod test {
 
         ctx.stop().await
     }
+
+    /// Starts a `Receiver` for every peer, only accepting messages from
+    /// that peer's channel
+    struct PeerReceiverHook {
+        received_count: Arc<AtomicU8>,
+    }
+
+    #[ockam_core::async_trait]
+    impl SecureChannelEstablishedHook for PeerReceiverHook {
+        async fn on_established(
+            &self,
+            ctx: &Context,
+            their_identity_id: &IdentityIdentifier,
+            channel: &Address,
+        ) -> Result<()> {
+            let receiver = Receiver {
+                received_count: self.received_count.clone(),
+            };
+            let access_control = IdentityAccessControlBuilder::new_with_channel(channel.clone());
+            let address = format!("receiver.{}", their_identity_id);
+            WorkerBuilder::with_access_control(access_control, address, receiver)
+                .start(ctx)
+                .await?;
+            Ok(())
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[ockam_macros::test]
+    async fn access_control__established_hook__should_lock_worker_to_peer(
+        ctx: &mut Context,
+    ) -> Result<()> {
+        let received_count = Arc::new(AtomicU8::new(0));
+
+        let vault = Vault::create();
+
+        let alice_storage = InMemoryStorage::new();
+        let bob_storage = InMemoryStorage::new();
+        let carol_storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &vault).await?;
+        let bob = Identity::create(ctx, &vault).await?;
+        let carol = Identity::create(ctx, &vault).await?;
+
+        let hook = PeerReceiverHook {
+            received_count: received_count.clone(),
+        };
+        bob.create_secure_channel_listener_with_options(
+            "listener",
+            TrustEveryonePolicy,
+            &bob_storage,
+            SecureChannelListenerOptions::new().with_established_hook(hook),
+        )
+        .await?;
+
+        let alice_channel = alice
+            .create_secure_channel("listener", TrustEveryonePolicy, &alice_storage)
+            .await?;
+        let carol_channel = carol
+            .create_secure_channel("listener", TrustEveryonePolicy, &carol_storage)
+            .await?;
+
+        let alice_receiver = format!("receiver.{}", alice.identifier());
+        ctx.send(
+            route![alice_channel, alice_receiver.as_str()],
+            "Hello, Bob!".to_string(),
+        )
+        .await?;
+
+        // Carol reaches the worker started for Alice, which rejects her
+        ctx.send(
+            route![carol_channel, alice_receiver.as_str()],
+            "Hello, Bob!".to_string(),
+        )
+        .await?;
+
+        sleep(Duration::from_secs(1)).await;
+
+        assert_eq!(received_count.load(Ordering::Relaxed), 1);
+
+        ctx.stop().await
+    }
+
+    #[allow(non_snake_case)]
+    #[ockam_macros::test]
+    async fn access_control__other_channel__should_not_pass_messages(
+        ctx: &mut Context,
+    ) -> Result<()> {
+        let received_count = Arc::new(AtomicU8::new(0));
+        let receiver = Receiver {
+            received_count: received_count.clone(),
+        };
+
+        let vault = Vault::create();
+
+        let alice_storage = InMemoryStorage::new();
+        let bob_storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &vault).await?;
+        let bob = Identity::create(ctx, &vault).await?;
+
+        bob.create_secure_channel_listener("listener", TrustEveryonePolicy, &bob_storage)
+            .await?;
+
+        let vetted_channel = alice
+            .create_secure_channel("listener", TrustEveryonePolicy, &alice_storage)
+            .await?;
+        let other_channel = alice
+            .create_secure_channel("listener", TrustEveryonePolicy, &alice_storage)
+            .await?;
+
+        // Bob's end of the vetted channel
+        ctx.send(
+            route![vetted_channel.clone(), ctx.address()],
+            "Hi".to_string(),
+        )
+        .await?;
+        let msg = ctx.receive::<String>().await?.take();
+        let bob_channel = msg.return_route().next()?.clone();
+
+        let access_control = IdentityAccessControlBuilder::new_with_channel(bob_channel);
+        WorkerBuilder::with_access_control(access_control, "receiver", receiver)
+            .start(ctx)
+            .await?;
+
+        ctx.send(
+            route![vetted_channel, "receiver"],
+            "Hello, Bob!".to_string(),
+        )
+        .await?;
+        ctx.send(route![other_channel, "receiver"], "Hello, Bob!".to_string())
+            .await?;
+
+        sleep(Duration::from_secs(1)).await;
+
+        assert_eq!(received_count.load(Ordering::Relaxed), 1);
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn test_send_only_channel(ctx: &mut Context) -> Result<()> {
+        let alice_storage = InMemoryStorage::new();
+        let bob_storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &Vault::create()).await?;
+        let bob = Identity::create(ctx, &Vault::create()).await?;
+
+        bob.create_secure_channel_listener_with_options(
+            "bob_listener",
+            TrustEveryonePolicy,
+            &bob_storage,
+            SecureChannelListenerOptions::new().accept_send_only(Duration::from_secs(60)),
+        )
+        .await?;
+
+        let alice_channel = alice
+            .create_secure_channel_with_options(
+                route!["bob_listener"],
+                TrustIdentifierPolicy::new(bob.identifier().clone()),
+                &alice_storage,
+                SecureChannelOptions::new().send_only(),
+            )
+            .await?;
+
+        let mut bob_ctx = ctx.new_detached("bob_receiver").await?;
+        ctx.send(
+            route![alice_channel, "bob_receiver"],
+            "Hello, Bob!".to_string(),
+        )
+        .await?;
+
+        let msg = bob_ctx.receive::<String>().await?.take();
+        let local_info = IdentitySecureChannelLocalInfo::find_info(msg.local_message())?;
+        assert_eq!(local_info.their_identity_id(), alice.identifier());
+
+        // There is no way back to alice
+        let return_route = msg.return_route();
+        assert_eq!(return_route.iter().count(), 0);
+        assert_eq!("Hello, Bob!", msg.body());
+        let res = bob_ctx
+            .send(return_route, "Hello, Alice!".to_string())
+            .await;
+        assert!(res.is_err());
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn test_send_only_channel_rejected_by_listener(ctx: &mut Context) -> Result<()> {
+        let storage = InMemoryStorage::new();
+        let vault = Vault::create();
+
+        let alice = Identity::create(ctx, &vault).await?;
+        let bob = Identity::create(ctx, &vault).await?;
+
+        bob.create_secure_channel_listener("bob_listener", TrustEveryonePolicy, &storage)
+            .await?;
+
+        // Fails as soon as the listener shows it doesn't accept it, well
+        // before the timeout
+        let err = alice
+            .create_secure_channel_with_options(
+                route!["bob_listener"],
+                TrustEveryonePolicy,
+                &storage,
+                SecureChannelOptions::new()
+                    .send_only()
+                    .with_timeout(Duration::from_secs(30)),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.code().kind, Kind::Unsupported);
+
+        // Regular channels are still accepted
+        alice
+            .create_secure_channel(route!["bob_listener"], TrustEveryonePolicy, &storage)
+            .await?;
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn test_idle_send_only_channel_is_closed(ctx: &mut Context) -> Result<()> {
+        let storage = InMemoryStorage::new();
+        let vault = Vault::create();
+
+        let alice = Identity::create(ctx, &vault).await?;
+        let bob = Identity::create(ctx, &vault).await?;
+
+        let decryptor = Arc::new(ockam_core::compat::sync::Mutex::new(None));
+        let hook = DecryptorAddressHook {
+            decryptor: decryptor.clone(),
+        };
+        bob.create_secure_channel_listener_with_options(
+            "bob_listener",
+            TrustEveryonePolicy,
+            &storage,
+            SecureChannelListenerOptions::new()
+                .accept_send_only(Duration::from_millis(500))
+                .with_established_hook(hook),
+        )
+        .await?;
+
+        let alice_channel = alice
+            .create_secure_channel_with_options(
+                route!["bob_listener"],
+                TrustEveryonePolicy,
+                &storage,
+                SecureChannelOptions::new().send_only(),
+            )
+            .await?;
+        let decryptor = decryptor.lock().unwrap().clone().unwrap();
+
+        // Messages keep the channel open past the idle timeout
+        let mut bob_ctx = ctx.new_detached("bob_receiver").await?;
+        for _ in 0..6 {
+            ctx.send(
+                route![alice_channel.clone(), "bob_receiver"],
+                "Hello, Bob!".to_string(),
+            )
+            .await?;
+            bob_ctx.receive::<String>().await?;
+            sleep(Duration::from_millis(200)).await;
+        }
+        assert!(ctx.is_address_resolvable(&decryptor).await?);
+
+        // Then the responder is closed once idle
+        wait_until_stopped(ctx, &decryptor).await?;
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn test_unchanged_history_is_verified_once(ctx: &mut Context) -> Result<()> {
+        let alice_storage = InMemoryStorage::new();
+        let bob_storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &Vault::create()).await?;
+        let bob = Identity::create(ctx, &Vault::create()).await?;
+
+        bob.create_secure_channel_listener("bob_listener", TrustEveryonePolicy, &bob_storage)
+            .await?;
+
+        let mut bob_ctx = ctx.new_detached("bob_receiver").await?;
+        for _ in 0..3 {
+            let alice_channel = alice
+                .create_secure_channel(
+                    route!["bob_listener"],
+                    TrustIdentifierPolicy::new(bob.identifier().clone()),
+                    &alice_storage,
+                )
+                .await?;
+
+            ctx.send(
+                route![alice_channel, "bob_receiver"],
+                "Hello, Bob!".to_string(),
+            )
+            .await?;
+            let msg = bob_ctx.receive::<String>().await?.take();
+            assert_eq!("Hello, Bob!", msg.body());
+        }
+
+        assert_eq!(alice.verified_histories.verifications(), 1);
+        assert_eq!(bob.verified_histories.verifications(), 1);
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn test_concurrent_handshakes_limit(ctx: &mut Context) -> Result<()> {
+        let alice_storage = InMemoryStorage::new();
+        let bob_storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &Vault::create()).await?;
+        let bob = Identity::create(ctx, &Vault::create()).await?;
+
+        bob.create_secure_channel_listener("bob_listener", TrustEveryonePolicy, &bob_storage)
+            .await?;
+
+        // Handshakes keep their slot while alice checks bob
+        let slow_trust_policy = || SlowTrustPolicy {
+            slow_identity_id: bob.identifier().clone(),
+        };
+        alice.set_max_concurrent_handshakes(2);
+        let create = || {
+            alice.create_secure_channel(route!["bob_listener"], slow_trust_policy(), &alice_storage)
+        };
+        let (r1, r2, r3, r4) = tokio::join!(create(), create(), create(), create());
+
+        // Only two handshakes ran, the others were rejected right away
+        let results = [r1, r2, r3, r4];
+        assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 2);
+        for err in results.iter().filter_map(|r| r.as_ref().err()) {
+            assert_eq!(err.code().kind, Kind::ResourceExhausted);
+        }
+
+        // Slots are freed once the handshakes are over
+        assert_eq!(alice.running_handshakes(), 0);
+        alice
+            .create_secure_channel(route!["bob_listener"], slow_trust_policy(), &alice_storage)
+            .await?;
+
+        ctx.stop().await
+    }
+
+    /// Forwards the first message to the next hop and drops the others,
+    /// as a transport going down in the middle of a handshake
+    struct BrokenHop {
+        forwarded: bool,
+    }
+
+    #[ockam_core::async_trait]
+    impl Worker for BrokenHop {
+        type Message = Any;
+        type Context = Context;
+
+        async fn handle_message(&mut self, ctx: &mut Context, msg: Routed<Any>) -> Result<()> {
+            if self.forwarded {
+                return Ok(());
+            }
+            self.forwarded = true;
+            let mut local_msg = msg.into_local_message();
+            let transport = local_msg.transport_mut();
+            transport.onward_route.step()?;
+            transport.return_route.modify().prepend(ctx.address());
+            ctx.forward(local_msg).await
+        }
+    }
+
+    #[ockam_macros::test]
+    async fn test_responder_handshake_timeout_frees_the_slot(ctx: &mut Context) -> Result<()> {
+        let storage = InMemoryStorage::new();
+
+        let alice = Identity::create(ctx, &Vault::create()).await?;
+        let bob = Identity::create(ctx, &Vault::create()).await?;
+
+        bob.set_max_concurrent_handshakes(1);
+        bob.create_secure_channel_listener_with_options(
+            "bob_listener",
+            TrustEveryonePolicy,
+            &storage,
+            SecureChannelListenerOptions::new().with_handshake_timeout(Duration::from_millis(500)),
+        )
+        .await?;
+        ctx.start_worker("broken_hop", BrokenHop { forwarded: false })
+            .await?;
+
+        // Bob never hears back from this initiator
+        assert!(alice
+            .create_secure_channel_with_options(
+                route!["broken_hop", "bob_listener"],
+                TrustEveryonePolicy,
+                &storage,
+                SecureChannelOptions::new().with_timeout(Duration::from_millis(200)),
+            )
+            .await
+            .is_err());
+        assert_eq!(bob.running_handshakes(), 1);
+
+        // Until his side of the handshake times out
+        for _ in 0..50 {
+            if bob.running_handshakes() == 0 {
+                break;
+            }
+            sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(bob.running_handshakes(), 0);
+        alice
+            .create_secure_channel("bob_listener", TrustEveryonePolicy, &storage)
+            .await?;
+
+        ctx.stop().await
+    }
 }
@@ -2,7 +2,7 @@ use crate::{IdentityIdentifier, IdentitySecureChannelLocalInfo};
 use ockam_core::access_control::AccessControl;
 use ockam_core::compat::vec::Vec;
 use ockam_core::{async_trait, compat::boxed::Box};
-use ockam_core::{LocalMessage, Result};
+use ockam_core::{Address, LocalMessage, Result};
 
 pub struct IdentityAccessControlBuilder;
 
@@ -20,6 +20,11 @@ impl IdentityAccessControlBuilder {
     pub fn new_with_any_id() -> IdentityAnyIdAccessControl {
         IdentityAnyIdAccessControl
     }
+
+    /// Only allow messages delivered by the secure channel at `channel_address`
+    pub fn new_with_channel(channel_address: impl Into<Address>) -> ChannelAddressAccessControl {
+        ChannelAddressAccessControl::new(channel_address)
+    }
 }
 
 #[derive(Debug)]
@@ -60,3 +65,29 @@ impl AccessControl for IdentityIdAccessControl {
         }
     }
 }
+
+/// Allows messages which arrived via one secure channel, given by the
+/// local address of its encryptor
+#[derive(Clone, Debug)]
+pub struct ChannelAddressAccessControl {
+    channel_address: Address,
+}
+
+impl ChannelAddressAccessControl {
+    pub fn new(channel_address: impl Into<Address>) -> Self {
+        Self {
+            channel_address: channel_address.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl AccessControl for ChannelAddressAccessControl {
+    async fn is_authorized(&self, local_msg: &LocalMessage) -> Result<bool> {
+        if let Ok(info) = IdentitySecureChannelLocalInfo::find_info(local_msg) {
+            Ok(info.channel_address() == &self.channel_address)
+        } else {
+            Ok(false)
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use crate::{IdentityIdentifier, CHANNEL_CLOSE_ADDRESS};
+use ockam_core::compat::{sync::Arc, sync::Mutex, vec::Vec};
+use ockam_core::{route, Address};
+use ockam_node::Context;
+use tracing::warn;
+
+/// Bytes used so far, and whether the channel is being closed
+#[derive(Default)]
+struct BudgetUsage {
+    used_bytes: u64,
+    closing: bool,
+}
+
+/// Number of payload bytes a channel may carry, shared by its encryptor and
+/// decryptor so sent and received bytes count against the same budget
+#[derive(Clone)]
+pub(crate) struct ChannelByteBudget {
+    max_bytes: u64,
+    usage: Arc<Mutex<BudgetUsage>>,
+    their_identity_id: IdentityIdentifier,
+    /// Sends the close notice to the other end, a send-only responder has none
+    encryptor_address: Option<Address>,
+    channel_addresses: Vec<Address>,
+}
+
+impl ChannelByteBudget {
+    pub fn new(
+        max_bytes: u64,
+        their_identity_id: IdentityIdentifier,
+        encryptor_address: Option<Address>,
+        channel_addresses: Vec<Address>,
+    ) -> Self {
+        Self {
+            max_bytes,
+            usage: Arc::new(Mutex::new(BudgetUsage::default())),
+            their_identity_id,
+            encryptor_address,
+            channel_addresses,
+        }
+    }
+
+    /// Account for a message of `len` bytes, returns `false` if it doesn't
+    /// fit in what is left of the budget
+    pub fn try_consume(&self, len: usize) -> bool {
+        let mut usage = self.usage.lock().unwrap();
+        match usage.used_bytes.checked_add(len as u64) {
+            Some(total) if total <= self.max_bytes => {
+                usage.used_bytes = total;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Close the channel, telling the other end so it closes its side too
+    ///
+    /// Only the first call closes the channel, the messages refused while
+    /// the close notice is on its way don't send other notices.
+    pub async fn close(&self, ctx: &Context) {
+        {
+            let mut usage = self.usage.lock().unwrap();
+            if usage.closing {
+                return;
+            }
+            usage.closing = true;
+        }
+        warn!(
+            "Closing SecureChannel with {}: byte budget of {} exceeded",
+            self.their_identity_id, self.max_bytes
+        );
+
+        // The encryptor sends the notice, which isn't counted against the
+        // budget, then stops both workers
+        if let Some(encryptor_address) = &self.encryptor_address {
+            let notice = route![encryptor_address.clone(), CHANNEL_CLOSE_ADDRESS];
+            if ctx.send(notice, ()).await.is_ok() {
+                return;
+            }
+        }
+        for address in &self.channel_addresses {
+            let _ = ctx.stop_worker(address.clone()).await;
+        }
+    }
+}
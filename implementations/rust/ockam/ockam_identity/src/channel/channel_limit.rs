@@ -0,0 +1,43 @@
+use crate::IdentityIdentifier;
+use ockam_core::compat::{
+    collections::BTreeMap,
+    sync::{Arc, RwLock},
+};
+
+/// Caps the number of simultaneous channels a listener accepts per peer identity
+#[derive(Clone)]
+pub(crate) struct IdentityChannelLimit {
+    max_channels: usize,
+    open_channels: Arc<RwLock<BTreeMap<IdentityIdentifier, usize>>>,
+}
+
+impl IdentityChannelLimit {
+    pub fn new(max_channels: usize) -> Self {
+        Self {
+            max_channels,
+            open_channels: Default::default(),
+        }
+    }
+
+    /// Reserve a channel slot for the given identity, returns `false` if the cap is reached
+    pub fn try_acquire(&self, their_identity_id: &IdentityIdentifier) -> bool {
+        let mut open_channels = self.open_channels.write().unwrap();
+        let count = open_channels.entry(their_identity_id.clone()).or_insert(0);
+        if *count >= self.max_channels {
+            return false;
+        }
+        *count += 1;
+        true
+    }
+
+    /// Free a channel slot previously reserved with [`IdentityChannelLimit::try_acquire`]
+    pub fn release(&self, their_identity_id: &IdentityIdentifier) {
+        let mut open_channels = self.open_channels.write().unwrap();
+        if let Some(count) = open_channels.get_mut(their_identity_id) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                open_channels.remove(their_identity_id);
+            }
+        }
+    }
+}
@@ -1,32 +1,102 @@
 use crate::authenticated_storage::AuthenticatedStorage;
+use crate::channel::trust_policy::check_trust_info;
 use crate::{
-    EncryptorWorker, Identity, IdentityChannelMessage, IdentityError, IdentityIdentifier,
-    IdentitySecureChannelLocalInfo, IdentityVault, PublicIdentity, SecureChannelTrustInfo,
-    TrustPolicy,
+    AdaptiveHandshakeTimeout, ChannelByteBudget, ChannelGapCounter, CipherSuite, EncryptorWorker,
+    HandshakeSlot, Identity, IdentityChannelLimit, IdentityChannelMessage, IdentityError,
+    IdentityIdentifier, IdentitySecureChannelLocalInfo, IdentityVault,
+    SecureChannelEstablishedHook, SecureChannelKeepalive, SecureChannelNegotiation,
+    SecureChannelRole, SecureChannelTrustInfo, SequencedPayload, TrustPolicy,
+    CHANNEL_CLOSE_ADDRESS, CHANNEL_PING_ADDRESS, CHANNEL_SEQUENCE_ADDRESS,
+    DEFAULT_HANDSHAKE_TIMEOUT,
 };
+#[cfg(feature = "std")]
+use crate::{ChannelKeepaliveWorker, TrustPolicyWatcher};
 use core::future::Future;
 use core::pin::Pin;
 use core::time::Duration;
 use ockam_channel::{
-    CreateResponderChannelMessage, KeyExchangeCompleted, SecureChannel, SecureChannelDecryptor,
-    SecureChannelInfo,
+    is_vault_failure, CreateResponderChannelMessage, KeyEscrow, KeyExchangeOutcome, SecureChannel,
+    SecureChannelDecryptor, SecureChannelInfo,
 };
 use ockam_core::async_trait;
 use ockam_core::compat::rand::random;
 use ockam_core::compat::{boxed::Box, sync::Arc, vec::Vec};
-use ockam_core::vault::Signature;
+use ockam_core::errcode::Origin;
+use ockam_core::vault::{KeyId, SecretAttributes, SecretPersistence, SecretType, Signature};
 use ockam_core::{
-    route, Address, Any, Decodable, Encodable, LocalMessage, Message, Result, Route, Routed,
-    TransportMessage, Worker,
+    route, Address, Any, Decodable, Encodable, Error, LocalMessage, Message, MessageId,
+    MessagePriority, Result, Route, Routed, TransportMessage, Worker,
 };
 use ockam_key_exchange_core::NewKeyExchanger;
 use ockam_key_exchange_xx::XXNewKeyExchanger;
-use ockam_node::Context;
+use ockam_node::{Context, DelayedEvent};
 use serde::{Deserialize, Serialize};
-use tracing::{debug, info, warn};
+use tracing::field::{self, display};
+use tracing::{debug, debug_span, info, warn, Instrument};
 
+/// Progress of an initiator's handshake, sent to the callback address
 #[derive(Serialize, Deserialize, Message)]
-pub(crate) struct AuthenticationConfirmation(pub Address);
+pub(crate) enum HandshakeProgress {
+    /// The key exchange is done, see [`HandshakeOptions::adaptive_timeout`]
+    KeyExchangeDone,
+    /// The channel is established, its encryptor is at this address
+    Established(Address),
+    /// The handshake was aborted
+    Failed(Error),
+}
+
+/// Error of a handshake aborted by the failure of a vault operation
+fn handshake_vault_failure(err: &Error) -> Error {
+    Error::new(
+        Origin::Identity,
+        err.code().kind,
+        format!(
+            "SecureChannel handshake aborted, a vault operation failed: {}",
+            err
+        ),
+    )
+}
+
+/// How a SecureChannel handshake is run, and where the channel delivers
+/// decrypted messages
+#[derive(Clone, Default)]
+pub(crate) struct HandshakeOptions {
+    /// Messages only flow from the initiator to the responder. Only used
+    /// by initiators, which tell the responder during the handshake
+    pub(crate) send_only: bool,
+    /// Bucket size handshake messages are padded to
+    pub(crate) padding: Option<usize>,
+    /// Key both sides must prove they know during the handshake
+    pub(crate) pre_shared_key: Option<KeyId>,
+    /// Called by a responder once the channel is established
+    pub(crate) established_hook: Option<Arc<dyn SecureChannelEstablishedHook>>,
+    /// Local address decrypted messages are delivered to, ahead of their
+    /// onward route
+    pub(crate) delivery_address: Option<Address>,
+    /// Payload bytes the channel may carry, sent and received, before it
+    /// is closed
+    pub(crate) byte_budget: Option<u64>,
+    /// Number the messages sent, and count the messages lost on their way
+    /// from the other end
+    pub(crate) sequence_gaps: Option<ChannelGapCounter>,
+    /// Time out the handshake after its first round trip based on how long
+    /// that round trip took. Only used by initiators
+    pub(crate) adaptive_timeout: Option<AdaptiveHandshakeTimeout>,
+    /// Close the channel once the other end stops echoing heartbeats.
+    /// Unused by send-only channels, whose other end can't echo them
+    pub(crate) keepalive: Option<SecureChannelKeepalive>,
+    /// Escrow the keys of the channel once they are agreed
+    pub(crate) key_escrow: Option<KeyEscrow>,
+    /// Give up on the handshake after this long, [`DEFAULT_HANDSHAKE_TIMEOUT`]
+    /// if unset. Only used by responders, initiators are given their
+    /// timeout when created
+    pub(crate) responder_timeout: Option<Duration>,
+    /// Accept send-only channels, closing them once they carried no
+    /// message for this long. Only used by responders
+    pub(crate) send_only_idle_timeout: Option<Duration>,
+    /// Present no identity to the other side
+    pub(crate) anonymous: bool,
+}
 
 trait StartSecureChannelFuture: Future<Output = Result<SecureChannelInfo>> + Send + 'static {}
 
@@ -38,6 +108,16 @@ impl<T> StartSecureChannelFuture for T where
 struct InitiatorStartChannel {
     channel_future: Pin<Box<dyn StartSecureChannelFuture>>, // TODO: Replace with generic
     callback_address: Address,
+    /// Send [`HandshakeProgress::KeyExchangeDone`] to the callback address
+    report_key_exchange: bool,
+}
+
+/// Addresses of a handshake in progress, to abort it
+struct Handshake {
+    /// Underlying channel, once the key exchange is done
+    channel: Option<Address>,
+    /// Where an initiator's caller waits for the handshake
+    callback_address: Option<Address>,
 }
 
 struct ResponderWaitForKex {
@@ -59,6 +139,7 @@ struct Initialized {
     local_secure_channel_address: Address,
     their_identity_id: IdentityIdentifier,
     encryptor_address: Address,
+    byte_budget: Option<ChannelByteBudget>,
 }
 
 enum State {
@@ -69,13 +150,82 @@ enum State {
     Initialized(Initialized),
 }
 
+impl State {
+    /// The handshake in progress, if the channel isn't established yet
+    fn handshake(&self) -> Option<Handshake> {
+        match self {
+            State::InitiatorStartChannel(s) => Some(Handshake {
+                channel: None,
+                callback_address: Some(s.callback_address.clone()),
+            }),
+            State::ResponderWaitForKex(_) => Some(Handshake {
+                channel: None,
+                callback_address: None,
+            }),
+            State::InitiatorSendIdentity(s) => Some(Handshake {
+                channel: Some(s.channel.address()),
+                callback_address: Some(s.callback_address.clone()),
+            }),
+            State::ResponderWaitForIdentity(s) => Some(Handshake {
+                channel: Some(s.local_secure_channel_address.clone()),
+                callback_address: None,
+            }),
+            State::Initialized(_) => None,
+        }
+    }
+}
+
 pub(crate) struct DecryptorWorker<V: IdentityVault, S: AuthenticatedStorage> {
     is_initiator: bool,
+    /// Messages only flow from the initiator to the responder
+    send_only: bool,
+    /// Present no identity to the other side
+    anonymous: bool,
+    /// Bucket size our handshake messages are padded to, which the peer's
+    /// handshake messages must be padded to as well
+    handshake_padding: Option<usize>,
     self_address: Address,
+    /// First hop of the route to the other side
+    remote_address: Option<Address>,
     kex_callback_address: Option<Address>,
     identity: Identity<V>,
     storage: S,
     trust_policy: Arc<dyn TrustPolicy>,
+    channel_limit: Option<IdentityChannelLimit>,
+    /// Resources of a send-only responder, which has no encryptor to hold them
+    channel_slot: Option<(IdentityChannelLimit, IdentityIdentifier)>,
+    trust_policy_watcher: Option<Address>,
+    /// Called by a responder once the channel is established
+    established_hook: Option<Arc<dyn SecureChannelEstablishedHook>>,
+    /// Key both sides must prove they know during the handshake
+    pre_shared_key: Option<KeyId>,
+    /// Local address decrypted messages are delivered to, ahead of their
+    /// onward route
+    delivery_address: Option<Address>,
+    /// Payload bytes the channel may carry, sent and received
+    byte_budget: Option<u64>,
+    /// Counts the messages lost on their way from the other end, if
+    /// messages are numbered on this channel
+    sequence_gaps: Option<ChannelGapCounter>,
+    /// Number expected on the next numbered message
+    next_sequence: u64,
+    /// Heartbeats closing the channel once the other end stops echoing them
+    keepalive: Option<SecureChannelKeepalive>,
+    /// Held until the handshake completes or fails
+    handshake_slot: Option<HandshakeSlot>,
+    /// Event received at its address once a responder's handshake timed
+    /// out, unset once the channel is established
+    handshake_timeout: Option<(Address, DelayedEvent<Vec<u8>>)>,
+    /// Decryptor of the underlying channel of a responder, stopped if the
+    /// handshake times out
+    regular_decryptor: Option<Address>,
+    /// Accept send-only channels, closing them once idle for this long
+    send_only_idle_timeout: Option<Duration>,
+    /// Event received at its address every idle timeout by a send-only
+    /// responder, which has nothing else telling it the initiator is gone
+    idle_check: Option<(Address, DelayedEvent<Vec<u8>>)>,
+    /// Whether a message was received since the last idle check
+    received_since_idle_check: bool,
     state: Option<State>,
 }
 
@@ -87,39 +237,99 @@ impl<V: IdentityVault, S: AuthenticatedStorage> DecryptorWorker<V, S> {
         storage: S,
         trust_policy: Arc<dyn TrustPolicy>,
         timeout: Duration,
+        options: HandshakeOptions,
     ) -> Result<Address> {
+        let handshake_slot = match identity.handshake_limit.try_acquire() {
+            Some(handshake_slot) => handshake_slot,
+            None => {
+                warn!("Not creating SecureChannel: handshake limit reached");
+                return Err(IdentityError::SecureChannelHandshakeLimitReached.into());
+            }
+        };
+
         let child_address = Address::random_local();
         let mut child_ctx = ctx.new_detached(child_address.clone()).await?;
 
         let self_address: Address = random();
 
+        debug!("Creating IdentitySecureChannel over {}", route.pretty());
+        let remote_address = route.next().ok().cloned();
         let vault = identity.vault.async_try_clone().await?;
-        let initiator = XXNewKeyExchanger::new(vault.async_try_clone().await?)
-            .initiator()
-            .await?;
+        let initiator = Self::key_exchanger(&identity).await?.initiator().await?;
         // Create regular secure channel and set self address as first responder
         let custom_payload = self_address.encode()?;
         let temp_ctx = ctx.new_detached(Address::random_local()).await?;
+        let key_escrow = options.key_escrow;
         let channel_future = Box::pin(async move {
-            SecureChannel::create_extended(&temp_ctx, route, Some(custom_payload), initiator, vault)
-                .await
+            match key_escrow {
+                Some(key_escrow) => {
+                    SecureChannel::create_extended_with_key_escrow(
+                        &temp_ctx,
+                        route,
+                        Some(custom_payload),
+                        initiator,
+                        vault,
+                        timeout,
+                        key_escrow,
+                    )
+                    .await
+                }
+                None => {
+                    SecureChannel::create_extended_with_timeout(
+                        &temp_ctx,
+                        route,
+                        Some(custom_payload),
+                        initiator,
+                        vault,
+                        timeout,
+                    )
+                    .await
+                }
+            }
         });
 
         let state = State::InitiatorStartChannel(InitiatorStartChannel {
             channel_future,
             callback_address: child_address,
+            report_key_exchange: options.adaptive_timeout.is_some(),
         });
 
         let worker = DecryptorWorker {
             is_initiator: true,
+            send_only: options.send_only,
+            anonymous: options.anonymous,
+            handshake_padding: options.padding,
             self_address: self_address.clone(),
+            remote_address,
             kex_callback_address: None,
             identity,
             trust_policy,
             storage,
+            channel_limit: None,
+            channel_slot: None,
+            trust_policy_watcher: None,
+            established_hook: None,
+            pre_shared_key: options.pre_shared_key,
+            delivery_address: options.delivery_address,
+            byte_budget: options.byte_budget,
+            sequence_gaps: options.sequence_gaps,
+            next_sequence: 0,
+            keepalive: options.keepalive,
+            handshake_slot: Some(handshake_slot),
+            handshake_timeout: None,
+            regular_decryptor: None,
+            send_only_idle_timeout: None,
+            idle_check: None,
+            received_since_idle_check: false,
             state: Some(state),
         };
 
+        let guard = InitiatorGuard {
+            ctx: Some(ctx.new_detached(Address::random_local()).await?),
+            address: self_address.clone(),
+        };
+        #[cfg(feature = "std")]
+        let started = std::time::Instant::now();
         ctx.start_worker(self_address.clone(), worker).await?;
 
         debug!(
@@ -127,14 +337,58 @@ impl<V: IdentityVault, S: AuthenticatedStorage> DecryptorWorker<V, S> {
             &self_address
         );
 
-        let encryptor_address = child_ctx
-            .receive_timeout::<AuthenticationConfirmation>(timeout.as_secs())
+        #[cfg(feature = "std")]
+        let timeout = match options.adaptive_timeout {
+            Some(adaptive_timeout) => {
+                match Self::receive_progress(&mut child_ctx, timeout).await? {
+                    HandshakeProgress::KeyExchangeDone => {}
+                    _ => return Err(IdentityError::InvalidSecureChannelInternalState.into()),
+                }
+                let round_trip = started.elapsed();
+                let timeout = adaptive_timeout.remaining(round_trip);
+                debug!(
+                    "IdentitySecureChannel Initiator at remote: {} measured a round trip of {:?}, \
+                     timing out the rest of the handshake after {:?}",
+                    &self_address, round_trip, timeout
+                );
+                timeout
+            }
+            None => timeout,
+        };
+
+        let encryptor_address = match Self::receive_progress(&mut child_ctx, timeout).await? {
+            HandshakeProgress::Established(encryptor_address) => encryptor_address,
+            _ => return Err(IdentityError::InvalidSecureChannelInternalState.into()),
+        };
+        guard.disarm();
+
+        Ok(encryptor_address)
+    }
+
+    /// Wait for the next step of an initiator's handshake, failing if the
+    /// handshake was aborted
+    async fn receive_progress(
+        child_ctx: &mut Context,
+        timeout: Duration,
+    ) -> Result<HandshakeProgress> {
+        let progress = child_ctx
+            .receive_duration_timeout::<HandshakeProgress>(timeout)
             .await?
             .take()
-            .body()
-            .0;
+            .body();
+        match progress {
+            HandshakeProgress::Failed(err) => Err(err),
+            progress => Ok(progress),
+        }
+    }
 
-        Ok(encryptor_address)
+    /// Use the identity's key-agreement key as the XX static key if it has one
+    async fn key_exchanger(identity: &Identity<V>) -> Result<XXNewKeyExchanger<V>> {
+        let vault = identity.vault.async_try_clone().await?;
+        Ok(match identity.get_key_agreement_secret_key().await? {
+            Some(key) => XXNewKeyExchanger::new_with_static_key(vault, key),
+            None => XXNewKeyExchanger::new(vault),
+        })
     }
 
     pub(crate) async fn create_responder(
@@ -142,8 +396,21 @@ impl<V: IdentityVault, S: AuthenticatedStorage> DecryptorWorker<V, S> {
         identity: Identity<V>,
         storage: S,
         trust_policy: Arc<dyn TrustPolicy>,
+        channel_limit: Option<IdentityChannelLimit>,
+        options: HandshakeOptions,
         msg: Routed<CreateResponderChannelMessage>,
     ) -> Result<()> {
+        let handshake_slot = match identity.handshake_limit.try_acquire() {
+            Some(handshake_slot) => handshake_slot,
+            None => {
+                warn!(
+                    "Rejecting SecureChannel from {}: handshake limit reached",
+                    msg.return_route()
+                );
+                return Err(IdentityError::SecureChannelHandshakeLimitReached.into());
+            }
+        };
+
         let return_route = msg.return_route();
         let mut onward_route = msg.onward_route();
         let body = msg.body();
@@ -157,23 +424,61 @@ impl<V: IdentityVault, S: AuthenticatedStorage> DecryptorWorker<V, S> {
         let self_address: Address = random();
 
         let vault = identity.vault.async_try_clone().await?;
+        let key_exchanger = Self::key_exchanger(&identity).await?;
         let state = State::ResponderWaitForKex(ResponderWaitForKex {
             first_responder_address,
         });
 
         let kex_callback_address = Address::random_local();
+        let regular_responder_address = Address::random_local();
+        // Frees the slot of an initiator which never completes the handshake
+        let timeout_address = Address::random_local();
+        let mut handshake_timeout =
+            DelayedEvent::create(ctx, timeout_address.clone(), vec![]).await?;
+        handshake_timeout
+            .schedule(
+                options
+                    .responder_timeout
+                    .unwrap_or(DEFAULT_HANDSHAKE_TIMEOUT),
+            )
+            .await?;
         let worker = DecryptorWorker {
             is_initiator: false,
+            // Until the initiator tells otherwise
+            send_only: false,
+            anonymous: options.anonymous,
+            handshake_padding: options.padding,
             self_address: self_address.clone(),
+            remote_address: return_route.next().ok().cloned(),
             identity,
             trust_policy,
             storage,
+            channel_limit,
+            channel_slot: None,
+            trust_policy_watcher: None,
+            established_hook: options.established_hook,
+            pre_shared_key: options.pre_shared_key,
+            delivery_address: options.delivery_address,
+            byte_budget: options.byte_budget,
+            sequence_gaps: options.sequence_gaps,
+            next_sequence: 0,
+            keepalive: options.keepalive,
+            handshake_slot: Some(handshake_slot),
+            handshake_timeout: Some((timeout_address.clone(), handshake_timeout)),
+            regular_decryptor: Some(regular_responder_address.clone()),
+            send_only_idle_timeout: options.send_only_idle_timeout,
+            idle_check: None,
+            received_since_idle_check: false,
             kex_callback_address: Some(kex_callback_address.clone()),
             state: Some(state),
         };
 
         ctx.start_worker(
-            vec![self_address.clone(), kex_callback_address.clone()],
+            vec![
+                self_address.clone(),
+                kex_callback_address.clone(),
+                timeout_address,
+            ],
             worker,
         )
         .await?;
@@ -183,16 +488,15 @@ impl<V: IdentityVault, S: AuthenticatedStorage> DecryptorWorker<V, S> {
             &self_address
         );
 
-        let regular_responder_address = Address::random_local();
-
-        let responder = XXNewKeyExchanger::new(vault.async_try_clone().await?)
-            .responder()
-            .await?;
+        let responder = key_exchanger.responder().await?;
 
         let vault = vault.async_try_clone().await?;
-        let regular_decryptor =
+        let mut regular_decryptor =
             SecureChannelDecryptor::new_responder(responder, Some(kex_callback_address), vault)
                 .await?;
+        if let Some(key_escrow) = options.key_escrow {
+            regular_decryptor = regular_decryptor.with_key_escrow(key_escrow);
+        }
 
         ctx.start_worker(vec![regular_responder_address.clone()], regular_decryptor)
             .await?;
@@ -213,18 +517,29 @@ impl<V: IdentityVault, S: AuthenticatedStorage> DecryptorWorker<V, S> {
         msg: Routed<<Self as Worker>::Message>,
         state: ResponderWaitForKex,
     ) -> Result<()> {
-        let kex_msg = KeyExchangeCompleted::decode(msg.payload())?;
+        let kex_msg = match KeyExchangeOutcome::decode(msg.payload())? {
+            KeyExchangeOutcome::Completed(kex_msg) => kex_msg,
+            KeyExchangeOutcome::Failed(err) => return Err(err),
+        };
 
-        // Prove we posses Identity key
-        let signature = self
-            .identity
-            .create_signature(&kex_msg.auth_hash(), None)
-            .await?;
-        let identity = self.identity.export().await?;
-        let msg = IdentityChannelMessage::Request {
-            identity,
-            signature: signature.as_ref().to_vec(),
+        let accepts_send_only = self.send_only_idle_timeout.is_some();
+        let msg = match self.prove_identity(&kex_msg.auth_hash()).await? {
+            None => IdentityChannelMessage::RequestAnonymous { accepts_send_only },
+            Some((identity, signature)) if accepts_send_only => {
+                IdentityChannelMessage::RequestAcceptsSendOnly {
+                    identity,
+                    signature,
+                }
+            }
+            Some((identity, signature)) => IdentityChannelMessage::Request {
+                identity,
+                signature,
+            },
         };
+        let msg = self
+            .add_pre_shared_key_proof(msg, &kex_msg.auth_hash())
+            .await?;
+        let msg = Self::pad(msg, self.handshake_padding)?;
         ctx.send_from_address(
             route![kex_msg.address().clone(), state.first_responder_address],
             msg,
@@ -254,105 +569,128 @@ impl<V: IdentityVault, S: AuthenticatedStorage> DecryptorWorker<V, S> {
             return Err(IdentityError::UnknownChannelMsgDestination.into());
         }
 
-        let body = IdentityChannelMessage::decode(msg.payload())?;
+        let (body, their_padding) = self.check_padding(msg.payload())?;
+        let body = self
+            .check_pre_shared_key_proof(body, &state.channel.auth_hash())
+            .await?;
 
         // Wait for responder to send us his Identity and Identity Proof.
         // In case of using Noise XX this is m4 message.
-        if let IdentityChannelMessage::Request {
-            identity,
-            signature,
-        } = body
-        {
-            debug!("Received Authentication request");
-
-            let their_identity = PublicIdentity::import(&identity, &self.identity.vault).await?;
-            let their_identity_id = their_identity.identifier();
-
-            // Verify responder posses their Identity key
-            let verified = their_identity
-                .verify_signature(
-                    &Signature::new(signature),
-                    &state.channel.auth_hash(),
-                    None,
-                    &self.identity.vault,
-                )
-                .await?;
-
-            if !verified {
-                return Err(IdentityError::SecureChannelVerificationFailed.into());
+        let (presented, accepts_send_only) = match body {
+            IdentityChannelMessage::Request {
+                identity,
+                signature,
+            } => (Some((identity, signature)), false),
+            IdentityChannelMessage::RequestAcceptsSendOnly {
+                identity,
+                signature,
+            } => (Some((identity, signature)), true),
+            IdentityChannelMessage::RequestAnonymous { accepts_send_only } => {
+                (None, accepts_send_only)
             }
+            _ => return Err(IdentityError::InvalidSecureChannelInternalState.into()),
+        };
 
-            self.identity
-                .update_known_identity(their_identity_id, &their_identity, &self.storage)
-                .await?;
-
-            info!(
-                "Initiator verified SecureChannel from: {}",
-                their_identity_id
-            );
+        debug!("Received Authentication request");
 
-            // Check our TrustPolicy
-            let trust_info = SecureChannelTrustInfo::new(their_identity_id.clone());
-            let trusted = self.trust_policy.check(&trust_info).await?;
-            if !trusted {
-                // TODO: Shutdown? Communicate error?
-                return Err(IdentityError::SecureChannelTrustCheckFailed.into());
-            }
-            info!(
-                "Initiator checked trust policy for SecureChannel from: {}",
-                their_identity_id
+        // Tell the caller now rather than have it wait for the timeout
+        if self.send_only && !accepts_send_only {
+            warn!(
+                "IdentitySecureChannel at {}: the listener doesn't accept send-only channels",
+                self.self_address
             );
+            ctx.send(
+                state.callback_address,
+                HandshakeProgress::Failed(IdentityError::SecureChannelSendOnlyRejected.into()),
+            )
+            .await?;
+            return Err(IdentityError::SecureChannelSendOnlyRejected.into());
+        }
 
-            // Prove we posses our Identity key
-            let identity = self.identity.export().await?;
-            let signature = self
-                .identity
-                .create_signature(&state.channel.auth_hash(), None)
-                .await?;
+        let their_identity_id = &self
+            .authenticate_peer(presented, &state.channel.auth_hash())
+            .await?;
 
-            let auth_msg = IdentityChannelMessage::Response {
+        let send_only = self.send_only;
+        let auth_msg = match self.prove_identity(&state.channel.auth_hash()).await? {
+            None => IdentityChannelMessage::ResponseAnonymous { send_only },
+            Some((identity, signature)) if send_only => IdentityChannelMessage::ResponseSendOnly {
+                identity,
+                signature,
+            },
+            Some((identity, signature)) => IdentityChannelMessage::Response {
                 identity,
-                signature: signature.as_ref().to_vec(),
-            };
+                signature,
+            },
+        };
+        // Pad to the larger of both bucket sizes, so that the responder
+        // accepts it whatever we were configured with
+        let auth_msg = self
+            .add_pre_shared_key_proof(auth_msg, &state.channel.auth_hash())
+            .await?;
+        let auth_msg = Self::pad(auth_msg, self.handshake_padding.max(their_padding))?;
 
-            let remote_identity_secure_channel_address = return_route.recipient();
+        let remote_identity_secure_channel_address = return_route.recipient();
 
-            ctx.send_from_address(return_route, auth_msg, self.self_address.clone())
-                .await?;
-            debug!("Sent Authentication response");
+        ctx.send_from_address(return_route, auth_msg, self.self_address.clone())
+            .await?;
+        debug!("Sent Authentication response");
+
+        let encryptor_address = Address::random_local();
+        let byte_budget = self.channel_byte_budget(their_identity_id, &encryptor_address);
+        self.record_negotiation(
+            &encryptor_address,
+            their_identity_id,
+            self.handshake_padding.max(their_padding),
+        );
 
-            let encryptor_address = Address::random_local();
+        self.state = Some(State::Initialized(Initialized {
+            local_secure_channel_address: state.channel.address(),
+            their_identity_id: their_identity_id.clone(),
+            encryptor_address: encryptor_address.clone(),
+            byte_budget: byte_budget.clone(),
+        }));
 
-            self.state = Some(State::Initialized(Initialized {
-                local_secure_channel_address: state.channel.address(),
-                their_identity_id: their_identity_id.clone(),
-                encryptor_address: encryptor_address.clone(),
-            }));
+        let trust_policy_watcher = self
+            .start_trust_policy_watcher(ctx, their_identity_id, &encryptor_address)
+            .await?;
+        let keepalive = self
+            .start_keepalive(ctx, their_identity_id, &encryptor_address)
+            .await?;
 
-            let encryptor = EncryptorWorker::new(
-                self.is_initiator,
-                remote_identity_secure_channel_address,
-                state.channel.address(),
-            );
+        let encryptor = EncryptorWorker::new(
+            self.is_initiator,
+            remote_identity_secure_channel_address,
+            state.channel.address(),
+            self.self_address.clone(),
+            None,
+            trust_policy_watcher,
+            byte_budget,
+        )
+        .with_keepalive(keepalive);
+        let encryptor = if self.sequence_gaps.is_some() {
+            encryptor.with_sequence_numbers()
+        } else {
+            encryptor
+        };
 
-            ctx.start_worker(encryptor_address.clone(), encryptor)
-                .await?;
+        ctx.start_worker(encryptor_address.clone(), encryptor)
+            .await?;
 
-            info!(
-                "Initialized IdentitySecureChannel Initiator at local: {}, remote: {}",
-                &encryptor_address, &self.self_address
-            );
+        info!(
+            "Initialized IdentitySecureChannel Initiator at local: {}, remote: {}",
+            &encryptor_address, &self.self_address
+        );
 
-            ctx.send(
-                state.callback_address,
-                AuthenticationConfirmation(encryptor_address),
-            )
-            .await?;
+        // Free the slot before the channel is handed over
+        self.handshake_slot = None;
+        ctx.send(
+            state.callback_address,
+            HandshakeProgress::Established(encryptor_address),
+        )
+        .await?;
 
-            Ok(())
-        } else {
-            Err(IdentityError::InvalidSecureChannelInternalState.into())
-        }
+        Ok(())
     }
 
     async fn handle_receive_identity(
@@ -368,85 +706,584 @@ impl<V: IdentityVault, S: AuthenticatedStorage> DecryptorWorker<V, S> {
             return Err(IdentityError::UnknownChannelMsgDestination.into());
         }
 
-        let body = IdentityChannelMessage::decode(msg.payload())?;
+        let (body, their_padding) = self.check_padding(msg.payload())?;
+        let body = self
+            .check_pre_shared_key_proof(body, &state.auth_hash)
+            .await?;
 
         // Wait for responder to send us his Identity and Identity Proof.
         // In case of using Noise XX this is m4 message.
-        if let IdentityChannelMessage::Response {
-            identity,
-            signature,
-        } = body
-        {
-            debug!("Received Authentication response");
-
-            let their_identity = PublicIdentity::import(&identity, &self.identity.vault).await?;
-            let their_identity_id = their_identity.identifier();
-
-            // Verify initiator posses their Identity key
-            let verified = their_identity
-                .verify_signature(
-                    &Signature::new(signature),
-                    &state.auth_hash,
-                    None,
-                    &self.identity.vault,
-                )
-                .await?;
+        let (presented, send_only) = match body {
+            IdentityChannelMessage::Response {
+                identity,
+                signature,
+            } => (Some((identity, signature)), false),
+            IdentityChannelMessage::ResponseSendOnly {
+                identity,
+                signature,
+            } => (Some((identity, signature)), true),
+            IdentityChannelMessage::ResponseAnonymous { send_only } => (None, send_only),
+            _ => return Err(IdentityError::InvalidSecureChannelInternalState.into()),
+        };
+        if send_only && self.send_only_idle_timeout.is_none() {
+            return Err(IdentityError::SecureChannelSendOnlyRejected.into());
+        }
+
+        debug!("Received Authentication response");
 
-            if !verified {
-                return Err(IdentityError::SecureChannelVerificationFailed.into());
+        let their_identity_id = &self.authenticate_peer(presented, &state.auth_hash).await?;
+
+        // Check the listener's per-identity channel cap
+        let channel_limit = match self.channel_limit.take() {
+            Some(channel_limit) => {
+                if !channel_limit.try_acquire(their_identity_id) {
+                    warn!(
+                        "Rejecting SecureChannel from {}: channel limit reached",
+                        their_identity_id
+                    );
+                    ctx.stop_worker(self.self_address.clone()).await?;
+                    return Err(IdentityError::SecureChannelLimitReached.into());
+                }
+                Some((channel_limit, their_identity_id.clone()))
             }
+            None => None,
+        };
 
-            self.identity
-                .update_known_identity(their_identity_id, &their_identity, &self.storage)
+        if send_only {
+            // Nothing is sent back, the channel is identified by this worker
+            let channel = self.self_address.clone();
+            self.call_established_hook(ctx, their_identity_id, &channel, &channel_limit)
+                .await?;
+            self.send_only = true;
+            self.record_negotiation(&channel, their_identity_id, their_padding);
+            self.state = Some(State::Initialized(Initialized {
+                local_secure_channel_address: state.local_secure_channel_address,
+                their_identity_id: their_identity_id.clone(),
+                encryptor_address: self.self_address.clone(),
+                byte_budget: self.channel_byte_budget(their_identity_id, &self.self_address),
+            }));
+            self.channel_slot = channel_limit;
+            self.trust_policy_watcher = self
+                .start_trust_policy_watcher(ctx, their_identity_id, &self.self_address)
                 .await?;
+            // The initiator never tells us it's gone, the handshake timeout
+            // event is reused to check whether the channel is still in use
+            if let (Some(idle_timeout), Some((address, mut event))) =
+                (self.send_only_idle_timeout, self.handshake_timeout.take())
+            {
+                event.schedule(idle_timeout).await?;
+                self.idle_check = Some((address, event));
+            }
 
             info!(
-                "Responder verified SecureChannel from: {}",
-                their_identity_id
+                "Initialized send-only IdentitySecureChannel Responder at remote: {}",
+                &self.self_address
             );
+            return Ok(());
+        }
+
+        let remote_identity_secure_channel_address = return_route.recipient();
+
+        let encryptor_address = Address::random_local();
+
+        self.call_established_hook(ctx, their_identity_id, &encryptor_address, &channel_limit)
+            .await?;
+
+        let byte_budget = self.channel_byte_budget(their_identity_id, &encryptor_address);
+        self.record_negotiation(&encryptor_address, their_identity_id, their_padding);
+
+        self.state = Some(State::Initialized(Initialized {
+            local_secure_channel_address: state.local_secure_channel_address.clone(),
+            their_identity_id: their_identity_id.clone(),
+            encryptor_address: encryptor_address.clone(),
+            byte_budget: byte_budget.clone(),
+        }));
+
+        let trust_policy_watcher = self
+            .start_trust_policy_watcher(ctx, their_identity_id, &encryptor_address)
+            .await?;
+        let keepalive = self
+            .start_keepalive(ctx, their_identity_id, &encryptor_address)
+            .await?;
+
+        let encryptor = EncryptorWorker::new(
+            self.is_initiator,
+            remote_identity_secure_channel_address,
+            state.local_secure_channel_address,
+            self.self_address.clone(),
+            channel_limit,
+            trust_policy_watcher,
+            byte_budget,
+        )
+        .with_keepalive(keepalive);
+        let encryptor = if self.sequence_gaps.is_some() {
+            encryptor.with_sequence_numbers()
+        } else {
+            encryptor
+        };
+
+        ctx.start_worker(encryptor_address.clone(), encryptor)
+            .await?;
+
+        info!(
+            "Initialized IdentitySecureChannel Responder at local: {}, remote: {}",
+            &encryptor_address, &self.self_address
+        );
 
-            // Check our TrustPolicy
-            let trust_info = SecureChannelTrustInfo::new(their_identity_id.clone());
-            let trusted = self.trust_policy.check(&trust_info).await?;
-            if !trusted {
-                // TODO: Shutdown? Communicate error?
-                return Err(IdentityError::SecureChannelTrustCheckFailed.into());
+        Ok(())
+    }
+
+    /// Let the listener's hook set up the channel, rejecting the channel
+    /// if it fails
+    async fn call_established_hook(
+        &self,
+        ctx: &Context,
+        their_identity_id: &IdentityIdentifier,
+        channel: &Address,
+        channel_slot: &Option<(IdentityChannelLimit, IdentityIdentifier)>,
+    ) -> Result<()> {
+        let hook = match &self.established_hook {
+            Some(hook) => hook,
+            None => return Ok(()),
+        };
+        if let Err(err) = hook.on_established(ctx, their_identity_id, channel).await {
+            warn!(
+                "Rejecting SecureChannel from {}: {}",
+                their_identity_id, err
+            );
+            if let Some((channel_limit, their_identity_id)) = channel_slot {
+                channel_limit.release(their_identity_id);
             }
-            info!(
-                "Responder checked trust policy for SecureChannel from: {}",
-                their_identity_id
+            ctx.stop_worker(self.self_address.clone()).await?;
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// Account for a numbered message, reporting a gap if messages before
+    /// it are missing
+    fn record_sequence(&mut self, sequence: u64, state: &Initialized) {
+        if sequence > self.next_sequence {
+            let lost_messages = sequence - self.next_sequence;
+            warn!(
+                "IdentitySecureChannel {} lost {} message(s) from {}",
+                state.encryptor_address, lost_messages, state.their_identity_id
             );
+            if let Some(sequence_gaps) = &self.sequence_gaps {
+                sequence_gaps.record_gap(lost_messages);
+            }
+        }
+        // Late messages, already counted as lost, don't move the numbers back
+        self.next_sequence = self.next_sequence.max(sequence.saturating_add(1));
+    }
 
-            let remote_identity_secure_channel_address = return_route.recipient();
+    /// Our identity and its signature of `auth_hash`, proving we possess
+    /// its key, `None` if we present no identity
+    async fn prove_identity(&self, auth_hash: &[u8; 32]) -> Result<Option<(Vec<u8>, Vec<u8>)>> {
+        if self.anonymous {
+            return Ok(None);
+        }
+        let signing_key_label = self.identity.signing_key_label().await;
+        let signature = self
+            .identity
+            .create_signature(auth_hash, Some(signing_key_label))
+            .await?;
+        let identity = self.identity.export().await?;
+        Ok(Some((identity, signature.as_ref().to_vec())))
+    }
 
-            let encryptor_address = Address::random_local();
+    /// Verify that the other side possesses the key of the identity and
+    /// signature of `auth_hash` it presented, giving it an anonymous
+    /// identifier if it presented none, then check it against our trust
+    /// policy
+    async fn authenticate_peer(
+        &self,
+        presented: Option<(Vec<u8>, Vec<u8>)>,
+        auth_hash: &[u8; 32],
+    ) -> Result<IdentityIdentifier> {
+        let side = if self.is_initiator {
+            "Initiator"
+        } else {
+            "Responder"
+        };
+        let their_identity_id = match presented {
+            Some((identity, signature)) => {
+                let their_identity = self.identity.import_public_identity(&identity).await?;
+                let their_identity_id = their_identity.identifier().clone();
+
+                let verified = their_identity
+                    .verify_signature(
+                        &Signature::new(signature),
+                        auth_hash,
+                        Some(their_identity.signing_key_label()),
+                        &self.identity.vault,
+                    )
+                    .await?;
+                if !verified {
+                    return Err(IdentityError::SecureChannelVerificationFailed.into());
+                }
 
-            self.state = Some(State::Initialized(Initialized {
-                local_secure_channel_address: state.local_secure_channel_address.clone(),
-                their_identity_id: their_identity_id.clone(),
-                encryptor_address: encryptor_address.clone(),
-            }));
+                self.identity
+                    .update_known_identity(&their_identity_id, &their_identity, &self.storage)
+                    .await?;
+                info!(
+                    "{} verified SecureChannel from: {}",
+                    side, their_identity_id
+                );
+                their_identity_id
+            }
+            None => {
+                let their_identity_id = IdentityIdentifier::anonymous(auth_hash);
+                info!(
+                    "{} got no identity for SecureChannel from: {}",
+                    side, their_identity_id
+                );
+                their_identity_id
+            }
+        };
 
-            let encryptor = EncryptorWorker::new(
-                self.is_initiator,
-                remote_identity_secure_channel_address,
-                state.local_secure_channel_address,
-            );
+        // Check our TrustPolicy
+        let trust_info = self.trust_info(&their_identity_id);
+        if !check_trust_info(&self.trust_policy, &trust_info).await? {
+            return Err(IdentityError::SecureChannelTrustCheckFailed.into());
+        }
+        info!(
+            "{} checked trust policy for SecureChannel from: {}",
+            side, their_identity_id
+        );
+        Ok(their_identity_id)
+    }
 
-            ctx.start_worker(encryptor_address.clone(), encryptor)
-                .await?;
+    /// Addresses of the workers making up an established channel
+    fn channel_addresses(&self, encryptor_address: &Address) -> Vec<Address> {
+        let mut channel_addresses = vec![self.self_address.clone()];
+        // A send-only responder has no encryptor
+        if encryptor_address != &self.self_address {
+            channel_addresses.insert(0, encryptor_address.clone());
+        }
+        channel_addresses
+    }
 
-            info!(
-                "Initialized IdentitySecureChannel Responder at local: {}, remote: {}",
-                &encryptor_address, &self.self_address
+    /// Budget shared by the encryptor and this worker once the channel is established
+    fn channel_byte_budget(
+        &self,
+        their_identity_id: &IdentityIdentifier,
+        encryptor_address: &Address,
+    ) -> Option<ChannelByteBudget> {
+        self.byte_budget.map(|max_bytes| {
+            // A send-only responder has no encryptor
+            let has_encryptor = encryptor_address != &self.self_address;
+            ChannelByteBudget::new(
+                max_bytes,
+                their_identity_id.clone(),
+                has_encryptor.then(|| encryptor_address.clone()),
+                self.channel_addresses(encryptor_address),
+            )
+        })
+    }
+
+    /// Watch the storage so that the channel is closed if the trust policy
+    /// stops accepting the peer, e.g. because one of its attributes was revoked
+    #[cfg(feature = "std")]
+    async fn start_trust_policy_watcher(
+        &self,
+        ctx: &Context,
+        their_identity_id: &IdentityIdentifier,
+        encryptor_address: &Address,
+    ) -> Result<Option<Address>> {
+        // Anonymous peers have nothing in the storage to watch
+        if their_identity_id.is_anonymous() {
+            return Ok(None);
+        }
+        TrustPolicyWatcher::create(
+            ctx,
+            &self.storage,
+            self.trust_info(their_identity_id),
+            self.trust_policy.clone(),
+            self.channel_addresses(encryptor_address),
+        )
+        .await
+    }
+
+    #[cfg(not(feature = "std"))]
+    async fn start_trust_policy_watcher(
+        &self,
+        _ctx: &Context,
+        _their_identity_id: &IdentityIdentifier,
+        _encryptor_address: &Address,
+    ) -> Result<Option<Address>> {
+        Ok(None)
+    }
+
+    /// Send heartbeats through the encryptor at `encryptor_address`, which
+    /// is about to be started, if the channel has a keepalive
+    #[cfg(feature = "std")]
+    async fn start_keepalive(
+        &self,
+        ctx: &Context,
+        their_identity_id: &IdentityIdentifier,
+        encryptor_address: &Address,
+    ) -> Result<Option<Address>> {
+        let keepalive = match self.keepalive {
+            Some(keepalive) if !self.send_only => keepalive,
+            _ => return Ok(None),
+        };
+        let address = ChannelKeepaliveWorker::create(
+            ctx,
+            keepalive,
+            their_identity_id.clone(),
+            encryptor_address.clone(),
+            self.channel_addresses(encryptor_address),
+        )
+        .await?;
+        Ok(Some(address))
+    }
+
+    #[cfg(not(feature = "std"))]
+    async fn start_keepalive(
+        &self,
+        _ctx: &Context,
+        _their_identity_id: &IdentityIdentifier,
+        _encryptor_address: &Address,
+    ) -> Result<Option<Address>> {
+        Ok(None)
+    }
+
+    fn pad(
+        msg: IdentityChannelMessage,
+        bucket_size: Option<usize>,
+    ) -> Result<IdentityChannelMessage> {
+        match bucket_size {
+            Some(bucket_size) => msg.padded(bucket_size),
+            None => Ok(msg),
+        }
+    }
+
+    /// Decode a handshake message from the peer, rejecting it if it isn't
+    /// padded to at least our bucket size. Returns the peer's bucket size.
+    fn check_padding(&self, data: &[u8]) -> Result<(IdentityChannelMessage, Option<usize>)> {
+        let (msg, their_padding) = IdentityChannelMessage::decode_padded(data)?;
+        if their_padding < self.handshake_padding {
+            warn!(
+                "Rejecting SecureChannel handshake padded to {:?}, expected {:?}",
+                their_padding, self.handshake_padding
             );
+            return Err(IdentityError::SecureChannelPaddingMismatch.into());
+        }
+        Ok((msg, their_padding))
+    }
 
-            Ok(())
+    /// Prove with a handshake message that we know the pre-shared key, if
+    /// the channel has one
+    async fn add_pre_shared_key_proof(
+        &self,
+        msg: IdentityChannelMessage,
+        auth_hash: &[u8; 32],
+    ) -> Result<IdentityChannelMessage> {
+        let pre_shared_key = match &self.pre_shared_key {
+            Some(pre_shared_key) => pre_shared_key,
+            None => return Ok(msg),
+        };
+        let our_role = if self.is_initiator {
+            SecureChannelRole::Initiator
         } else {
-            Err(IdentityError::InvalidSecureChannelInternalState.into())
+            SecureChannelRole::Responder
+        };
+        Ok(IdentityChannelMessage::WithPreSharedKey {
+            proof: self
+                .pre_shared_key_proof(pre_shared_key, auth_hash, our_role)
+                .await?,
+            message: msg.encode()?,
+        })
+    }
+
+    /// Unwrap a handshake message from the peer, rejecting it unless the
+    /// peer proved it knows our pre-shared key, or neither side has one
+    async fn check_pre_shared_key_proof(
+        &self,
+        msg: IdentityChannelMessage,
+        auth_hash: &[u8; 32],
+    ) -> Result<IdentityChannelMessage> {
+        let their_role = if self.is_initiator {
+            SecureChannelRole::Responder
+        } else {
+            SecureChannelRole::Initiator
+        };
+        match (&self.pre_shared_key, msg) {
+            (Some(pre_shared_key), IdentityChannelMessage::WithPreSharedKey { proof, message }) => {
+                use subtle::ConstantTimeEq;
+                let expected = self
+                    .pre_shared_key_proof(pre_shared_key, auth_hash, their_role)
+                    .await?;
+                if !bool::from(expected.ct_eq(&proof)) {
+                    warn!("Rejecting SecureChannel handshake with a wrong pre-shared key proof");
+                    return Err(IdentityError::SecureChannelPreSharedKeyMismatch.into());
+                }
+                match IdentityChannelMessage::decode(&message)? {
+                    IdentityChannelMessage::WithPreSharedKey { .. }
+                    | IdentityChannelMessage::Padded { .. } => {
+                        Err(IdentityError::InvalidSecureChannelInternalState.into())
+                    }
+                    msg => Ok(msg),
+                }
+            }
+            (None, msg @ IdentityChannelMessage::WithPreSharedKey { .. }) | (Some(_), msg) => {
+                warn!(
+                    "Rejecting SecureChannel handshake: pre-shared key expected: {}, received: {}",
+                    self.pre_shared_key.is_some(),
+                    matches!(msg, IdentityChannelMessage::WithPreSharedKey { .. })
+                );
+                Err(IdentityError::SecureChannelPreSharedKeyMismatch.into())
+            }
+            (None, msg) => Ok(msg),
         }
     }
 
+    /// Derive the proof that the side playing `role` knows the pre-shared
+    /// key, bound to the key exchange of this channel
+    async fn pre_shared_key_proof(
+        &self,
+        pre_shared_key: &KeyId,
+        auth_hash: &[u8; 32],
+        role: SecureChannelRole,
+    ) -> Result<Vec<u8>> {
+        let mut info = b"ockam.secure_channel.pre_shared_key".to_vec();
+        info.push(match role {
+            SecureChannelRole::Initiator => 0,
+            SecureChannelRole::Responder => 1,
+        });
+        info.extend_from_slice(auth_hash);
+
+        let vault = &self.identity.vault;
+        let attributes =
+            SecretAttributes::new(SecretType::Buffer, SecretPersistence::Ephemeral, 32);
+        let proof_key = vault
+            .hkdf_sha256(pre_shared_key, &info, None, vec![attributes])
+            .await?
+            .pop()
+            .ok_or(IdentityError::InvalidSecureChannelInternalState)?;
+        let proof = vault.secret_export(&proof_key).await?.as_ref().to_vec();
+        vault.secret_destroy(proof_key).await?;
+        Ok(proof)
+    }
+
+    /// Record the parameters the channel at `channel` was established
+    /// with, `handshake_padding` being the bucket size both sides agreed on
+    fn record_negotiation(
+        &self,
+        channel: &Address,
+        their_identity_id: &IdentityIdentifier,
+        handshake_padding: Option<usize>,
+    ) {
+        let role = if self.is_initiator {
+            SecureChannelRole::Initiator
+        } else {
+            SecureChannelRole::Responder
+        };
+        let negotiation =
+            SecureChannelNegotiation::new(role, CipherSuite::NoiseXx25519AesGcmSha256)
+                .with_pre_shared_key(self.pre_shared_key.is_some())
+                .with_handshake_padding(handshake_padding)
+                .with_send_only(self.send_only)
+                .with_sequence_numbers(self.sequence_gaps.is_some())
+                .with_byte_budget(self.byte_budget);
+        self.identity.channel_negotiations.insert(
+            channel.clone(),
+            negotiation,
+            self.trust_info(their_identity_id),
+        );
+    }
+
+    /// Trust information about the other side of the channel, which plays
+    /// the role opposite to ours
+    fn trust_info(&self, their_identity_id: &IdentityIdentifier) -> SecureChannelTrustInfo {
+        let their_role = if self.is_initiator {
+            SecureChannelRole::Responder
+        } else {
+            SecureChannelRole::Initiator
+        };
+        let trust_info = SecureChannelTrustInfo::new(their_identity_id.clone())
+            .with_their_role(their_role)
+            // The only suite of the XX key exchanger
+            .with_cipher_suite(CipherSuite::NoiseXx25519AesGcmSha256)
+            .with_local_address(self.self_address.clone());
+        match &self.remote_address {
+            Some(remote_address) => trust_info.with_remote_address(remote_address.clone()),
+            None => trust_info,
+        }
+    }
+
+    /// Abort a handshake after a vault failure: the underlying channel is
+    /// stopped, and so is a responder. An initiator's caller is told why,
+    /// then stops the initiator
+    async fn abort_handshake(
+        &mut self,
+        ctx: &Context,
+        handshake: Handshake,
+        err: &Error,
+    ) -> Result<()> {
+        let err = handshake_vault_failure(err);
+        warn!("IdentitySecureChannel at {}: {}", self.self_address, err);
+        if let Some(channel) = handshake.channel {
+            let _ = ctx.stop_worker(channel).await;
+        }
+        match handshake.callback_address {
+            Some(callback_address) => {
+                ctx.send(callback_address, HandshakeProgress::Failed(err))
+                    .await
+            }
+            None => ctx.stop_worker(self.self_address.clone()).await,
+        }
+    }
+
+    /// Give up on a responder's handshake which took too long, freeing
+    /// its slot. A responder whose handshake failed is stopped as well
+    async fn time_out_handshake(&mut self, ctx: &Context) -> Result<()> {
+        if let Some(State::Initialized(_)) = self.state {
+            return Ok(());
+        }
+        warn!(
+            "IdentitySecureChannel Responder at {}: handshake timed out",
+            self.self_address
+        );
+        self.handshake_slot = None;
+        self.handshake_timeout = None;
+        let channel = self
+            .state
+            .as_ref()
+            .and_then(State::handshake)
+            .and_then(|handshake| handshake.channel);
+        for address in channel.into_iter().chain(self.regular_decryptor.take()) {
+            let _ = ctx.stop_worker(address).await;
+        }
+        ctx.stop_worker(self.self_address.clone()).await
+    }
+
+    /// Close a send-only responder which received no message since the
+    /// last check, otherwise check again after the idle timeout
+    async fn check_idle(&mut self, ctx: &Context) -> Result<()> {
+        if self.received_since_idle_check {
+            self.received_since_idle_check = false;
+            if let (Some(idle_timeout), Some((_, event))) =
+                (self.send_only_idle_timeout, self.idle_check.as_mut())
+            {
+                event.schedule(idle_timeout).await?;
+            }
+            return Ok(());
+        }
+        info!(
+            "Closing idle send-only IdentitySecureChannel Responder at {}",
+            self.self_address
+        );
+        self.idle_check = None;
+        if let Some(State::Initialized(state)) = &self.state {
+            let _ = ctx
+                .stop_worker(state.local_secure_channel_address.clone())
+                .await;
+        }
+        ctx.stop_worker(self.self_address.clone()).await
+    }
+
     // FIXME: Avoid situation where we take state but don't put it back because of an error
     fn take_state(&mut self) -> Result<State> {
         if let Some(s) = self.state.take() {
@@ -472,6 +1309,15 @@ impl<V: IdentityVault, S: AuthenticatedStorage> DecryptorWorker<V, S> {
         );
 
         self.state = Some(State::Initialized(state.clone()));
+        self.received_since_idle_check = true;
+
+        if self.send_only && self.is_initiator {
+            warn!(
+                "Dropping message received on send-only IdentitySecureChannel {}",
+                state.encryptor_address
+            );
+            return Ok(());
+        }
 
         let mut onward_route = msg.onward_route();
         let mut return_route = msg.return_route();
@@ -483,24 +1329,93 @@ impl<V: IdentityVault, S: AuthenticatedStorage> DecryptorWorker<V, S> {
 
         let local_msg = msg.into_local_message();
         let local_info = local_msg.local_info().to_vec();
-        let payload = local_msg.into_transport_message().payload;
+        let ttl = local_msg.ttl();
+        let priority = local_msg.priority();
+        let transport_msg = local_msg.into_transport_message();
+        let mut payload = transport_msg.payload;
+
+        // Numbered messages are unwrapped, reporting the messages lost before them
+        if onward_route.iter().nth(1) == Some(&Address::from_string(CHANNEL_SEQUENCE_ADDRESS)) {
+            let self_address = onward_route.step()?;
+            let _ = onward_route.step()?;
+            onward_route.modify().prepend(self_address);
+            let sequenced = SequencedPayload::decode(&payload)?;
+            self.record_sequence(sequenced.sequence, &state);
+            payload = sequenced.payload;
+        }
+
+        // The close notice goes through even once the budget is spent
+        let is_close = onward_route
+            .iter()
+            .skip(1)
+            .eq([&Address::from_string(CHANNEL_CLOSE_ADDRESS)]);
+        if let Some(byte_budget) = &state.byte_budget {
+            if !is_close && !byte_budget.try_consume(payload.len()) {
+                byte_budget.close(ctx).await;
+                return Err(IdentityError::SecureChannelByteBudgetExceeded.into());
+            }
+        }
 
         // Forward to local workers
         let _ = onward_route.step()?;
 
-        let return_route = return_route
-            .modify()
-            .pop_front()
-            .prepend(state.encryptor_address.clone());
+        // Replies of a send-only channel have nowhere to go
+        let return_route: Route = if self.send_only {
+            Route::new().into()
+        } else {
+            return_route
+                .modify()
+                .pop_front()
+                .prepend(state.encryptor_address.clone())
+                .into()
+        };
+
+        // Pings are echoed back through our encryptor without reaching any worker
+        if onward_route
+            .iter()
+            .eq([&Address::from_string(CHANNEL_PING_ADDRESS)])
+        {
+            if self.send_only {
+                return Ok(());
+            }
+            let echo = TransportMessage::v1(return_route, Route::new(), payload);
+            let echo = LocalMessage::new(echo, Vec::new()).with_priority(MessagePriority::High);
+            if let Err(err) = ctx.forward(echo).await {
+                warn!("{} echoing ping from {}", err, state.encryptor_address);
+            }
+            return Ok(());
+        }
+
+        // The other end closed the channel
+        if is_close {
+            info!(
+                "IdentitySecureChannel {} closed by {}",
+                state.encryptor_address, state.their_identity_id
+            );
+            for address in self.channel_addresses(&state.encryptor_address) {
+                let _ = ctx.stop_worker(address).await;
+            }
+            return Ok(());
+        }
+
+        // The remaining onward route is kept for the worker at the delivery address
+        if let Some(delivery_address) = &self.delivery_address {
+            onward_route.modify().prepend(delivery_address.clone());
+        }
 
         let transport_msg = TransportMessage::v1(onward_route, return_route, payload);
 
         // Mark message LocalInfo with IdentitySecureChannelLocalInfo,
         // replacing any pre-existing entries
-        let local_info =
-            IdentitySecureChannelLocalInfo::mark(local_info, state.their_identity_id.clone())?;
+        let local_info = IdentitySecureChannelLocalInfo::mark(
+            local_info,
+            state.their_identity_id.clone(),
+            state.encryptor_address.clone(),
+        )?;
 
-        let msg = LocalMessage::new(transport_msg, local_info);
+        let msg = LocalMessage::new(transport_msg, local_info)
+            .with_ttl(ttl)
+            .with_priority(priority);
 
         match ctx.forward(msg).await {
             Ok(_) => Ok(()),
@@ -515,16 +1430,65 @@ impl<V: IdentityVault, S: AuthenticatedStorage> DecryptorWorker<V, S> {
     }
 }
 
+/// Stops the initiator's worker when [`DecryptorWorker::create_initiator`]
+/// times out or its future is dropped before the handshake completed
+struct InitiatorGuard {
+    ctx: Option<Context>,
+    address: Address,
+}
+
+impl InitiatorGuard {
+    fn disarm(mut self) {
+        self.ctx = None;
+    }
+}
+
+impl Drop for InitiatorGuard {
+    fn drop(&mut self) {
+        if let Some(ctx) = self.ctx.take() {
+            let address = self.address.clone();
+            // Drop can't await, stopping the worker is left to the runtime
+            let runtime = ctx.runtime().clone();
+            runtime.spawn(async move {
+                debug!("Stopping unfinished IdentitySecureChannel {}", address);
+                let _ = ctx.stop_worker(address).await;
+            });
+        }
+    }
+}
+
 #[async_trait]
 impl<V: IdentityVault, S: AuthenticatedStorage> Worker for DecryptorWorker<V, S> {
     type Message = Any;
     type Context = Context;
 
-    async fn initialize(&mut self, _ctx: &mut Self::Context) -> Result<()> {
+    async fn initialize(&mut self, ctx: &mut Self::Context) -> Result<()> {
         if self.is_initiator {
             match self.take_state()? {
                 State::InitiatorStartChannel(s) => {
-                    let channel = s.channel_future.await?;
+                    // The handshake can't go on without the channel, the
+                    // caller waiting for it stops this worker
+                    let channel = match s.channel_future.await {
+                        Ok(channel) => channel,
+                        Err(err) => {
+                            let err = if is_vault_failure(&err) {
+                                handshake_vault_failure(&err)
+                            } else {
+                                err
+                            };
+                            warn!("IdentitySecureChannel at {}: {}", self.self_address, err);
+                            ctx.send(s.callback_address, HandshakeProgress::Failed(err))
+                                .await?;
+                            return Ok(());
+                        }
+                    };
+                    if s.report_key_exchange {
+                        ctx.send(
+                            s.callback_address.clone(),
+                            HandshakeProgress::KeyExchangeDone,
+                        )
+                        .await?;
+                    }
 
                     self.state = Some(State::InitiatorSendIdentity(InitiatorSendIdentity {
                         channel,
@@ -538,11 +1502,82 @@ impl<V: IdentityVault, S: AuthenticatedStorage> Worker for DecryptorWorker<V, S>
         Ok(())
     }
 
+    async fn shutdown(&mut self, ctx: &mut Self::Context) -> Result<()> {
+        if let Some((channel_limit, their_identity_id)) = self.channel_slot.take() {
+            channel_limit.release(&their_identity_id);
+        }
+        if let Some(trust_policy_watcher) = self.trust_policy_watcher.take() {
+            let _ = ctx.stop_processor(trust_policy_watcher).await;
+        }
+        if let Some(State::Initialized(state)) = &self.state {
+            self.identity
+                .channel_negotiations
+                .remove(&state.encryptor_address);
+        }
+
+        // Stop the underlying channel of a handshake that didn't complete,
+        // an established channel is managed by its encryptor
+        let local_secure_channel_address = match &self.state {
+            Some(State::InitiatorSendIdentity(s)) => s.channel.address(),
+            Some(State::ResponderWaitForIdentity(s)) => s.local_secure_channel_address.clone(),
+            _ => return Ok(()),
+        };
+        let _ = ctx.stop_worker(local_secure_channel_address).await;
+        Ok(())
+    }
+
     async fn handle_message(
         &mut self,
         ctx: &mut Self::Context,
         msg: Routed<Self::Message>,
     ) -> Result<()> {
+        if let Some((timeout_address, _)) = &self.handshake_timeout {
+            if &msg.msg_addr() == timeout_address {
+                return self.time_out_handshake(ctx).await;
+            }
+        }
+        if let Some((idle_check_address, _)) = &self.idle_check {
+            if &msg.msg_addr() == idle_check_address {
+                return self.check_idle(ctx).await;
+            }
+        }
+
+        // Handling the message takes the state, which is needed to abort
+        // the handshake
+        let handshake = self.state.as_ref().and_then(State::handshake);
+        let span = debug_span!(
+            "identity_secure_channel_decryptor",
+            channel = %ctx.address(),
+            peer = field::Empty,
+            message_id = field::Empty
+        );
+        if !span.is_disabled() {
+            if let Some(State::Initialized(state)) = &self.state {
+                span.record("peer", &display(&state.their_identity_id));
+            }
+            span.record("message_id", &display(MessageId::of(msg.payload())));
+        }
+        let result = self.handle_state(ctx, msg).instrument(span).await;
+
+        // The handshake is over once the channel is established, or failed
+        if result.is_err() || matches!(self.state, Some(State::Initialized(_))) {
+            self.handshake_slot = None;
+        }
+        if matches!(self.state, Some(State::Initialized(_))) {
+            self.handshake_timeout = None;
+        }
+
+        match (result, handshake) {
+            (Err(err), Some(handshake)) if is_vault_failure(&err) => {
+                self.abort_handshake(ctx, handshake, &err).await
+            }
+            (result, _) => result,
+        }
+    }
+}
+
+impl<V: IdentityVault, S: AuthenticatedStorage> DecryptorWorker<V, S> {
+    async fn handle_state(&mut self, ctx: &mut Context, msg: Routed<Any>) -> Result<()> {
         let msg_addr = msg.msg_addr();
 
         match self.take_state()? {
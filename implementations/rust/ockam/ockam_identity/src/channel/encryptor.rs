@@ -1,6 +1,11 @@
+use crate::{
+    ChannelByteBudget, IdentityChannelLimit, IdentityError, IdentityIdentifier, SequencedPayload,
+    CHANNEL_CLOSE_ADDRESS, CHANNEL_SEQUENCE_ADDRESS,
+};
+use ockam_channel::SecureChannelAssociatedData;
 use ockam_core::async_trait;
-use ockam_core::compat::{boxed::Box, vec::Vec};
-use ockam_core::{Address, Any, LocalMessage, Result, Routed, TransportMessage, Worker};
+use ockam_core::compat::boxed::Box;
+use ockam_core::{Address, Any, Encodable, LocalMessage, Result, Routed, TransportMessage, Worker};
 use ockam_node::Context;
 use tracing::debug;
 
@@ -8,6 +13,19 @@ pub(crate) struct EncryptorWorker {
     is_initiator: bool,
     remote_identity_secure_channel_address: Address,
     local_secure_channel_address: Address,
+    /// Decryptor of this end of the channel, stopped along with this worker
+    /// when the channel is closed
+    decryptor_address: Address,
+    /// Channel slot held for the peer, freed when this channel is stopped
+    channel_limit: Option<(IdentityChannelLimit, IdentityIdentifier)>,
+    /// Processor closing this channel if the peer stops being trusted
+    trust_policy_watcher: Option<Address>,
+    /// Worker closing this channel if the other end stops echoing heartbeats
+    keepalive: Option<Address>,
+    /// Payload bytes left to the channel, shared with its decryptor
+    byte_budget: Option<ChannelByteBudget>,
+    /// Number of the next message, if messages are numbered
+    next_sequence: Option<u64>,
 }
 
 impl EncryptorWorker {
@@ -15,14 +33,36 @@ impl EncryptorWorker {
         is_initiator: bool,
         remote_identity_secure_channel_address: Address,
         local_secure_channel_address: Address,
+        decryptor_address: Address,
+        channel_limit: Option<(IdentityChannelLimit, IdentityIdentifier)>,
+        trust_policy_watcher: Option<Address>,
+        byte_budget: Option<ChannelByteBudget>,
     ) -> Self {
         Self {
             is_initiator,
             remote_identity_secure_channel_address,
             local_secure_channel_address,
+            decryptor_address,
+            channel_limit,
+            trust_policy_watcher,
+            keepalive: None,
+            byte_budget,
+            next_sequence: None,
         }
     }
 
+    /// Stop the keepalive worker at `keepalive` along with this channel
+    pub fn with_keepalive(mut self, keepalive: Option<Address>) -> Self {
+        self.keepalive = keepalive;
+        self
+    }
+
+    /// Number the messages sent, so the other end detects lost messages
+    pub fn with_sequence_numbers(mut self) -> Self {
+        self.next_sequence = Some(0);
+        self
+    }
+
     async fn handle_encrypt(
         &mut self,
         ctx: &mut <Self as Worker>::Context,
@@ -39,21 +79,60 @@ impl EncryptorWorker {
 
         let mut onward_route = msg.onward_route();
         let return_route = msg.return_route();
-        let payload = msg.payload().to_vec();
+        let mut payload = msg.payload().to_vec();
+        let ttl = msg.local_message().ttl();
+        let priority = msg.local_message().priority();
+        let local_info = SecureChannelAssociatedData::find_info(msg.local_message())
+            .map(|x| vec![x.to_local_info()])
+            .unwrap_or_default();
 
         // Send to the other party using local regular SecureChannel
         let _ = onward_route.step()?;
+        let is_close = onward_route
+            .iter()
+            .eq([&Address::from_string(CHANNEL_CLOSE_ADDRESS)]);
+
+        // The close notice goes through even once the budget is spent
+        if let Some(byte_budget) = &self.byte_budget {
+            if !is_close && !byte_budget.try_consume(payload.len()) {
+                byte_budget.close(ctx).await;
+                return Err(IdentityError::SecureChannelByteBudgetExceeded.into());
+            }
+        }
+        if let Some(next_sequence) = &mut self.next_sequence {
+            onward_route
+                .modify()
+                .prepend(Address::from_string(CHANNEL_SEQUENCE_ADDRESS));
+            payload = SequencedPayload {
+                sequence: *next_sequence,
+                payload,
+            }
+            .encode()?;
+            *next_sequence += 1;
+        }
         let onward_route = onward_route
             .modify()
             .prepend(self.remote_identity_secure_channel_address.clone())
             .prepend(self.local_secure_channel_address.clone());
 
+        // Carry the TTL over so routing loops through the channel are still detected
         let transport_msg = TransportMessage::v1(onward_route, return_route, payload);
 
-        ctx.forward(LocalMessage::new(transport_msg, Vec::new()))
-            .await?;
+        // Associated data is bound to the message by the regular SecureChannel
+        let local_msg = LocalMessage::new(transport_msg, local_info)
+            .with_ttl(ttl)
+            .with_priority(priority);
+        let sent = ctx.forward(local_msg).await;
 
-        Ok(())
+        // The close notice is on its way, the other end stops when it gets
+        // it. This end stops even if the notice couldn't be sent
+        if is_close {
+            debug!("Closing IdentitySecureChannel {}", ctx.address());
+            let _ = ctx.stop_worker(self.decryptor_address.clone()).await;
+            ctx.stop_worker(ctx.address()).await?;
+        }
+
+        sent
     }
 }
 
@@ -62,6 +141,21 @@ impl Worker for EncryptorWorker {
     type Message = Any;
     type Context = Context;
 
+    async fn shutdown(&mut self, ctx: &mut Self::Context) -> Result<()> {
+        if let Some((channel_limit, their_identity_id)) = self.channel_limit.take() {
+            channel_limit.release(&their_identity_id);
+        }
+        if let Some(trust_policy_watcher) = self.trust_policy_watcher.take() {
+            // The watcher may already be gone if it is the one closing the channel
+            let _ = ctx.stop_processor(trust_policy_watcher).await;
+        }
+        if let Some(keepalive) = self.keepalive.take() {
+            // Same for the keepalive
+            let _ = ctx.stop_worker(keepalive).await;
+        }
+        Ok(())
+    }
+
     async fn handle_message(
         &mut self,
         ctx: &mut Self::Context,
@@ -0,0 +1,38 @@
+use crate::IdentityIdentifier;
+use ockam_core::{
+    async_trait,
+    compat::{boxed::Box, sync::Arc},
+    Address, Result,
+};
+use ockam_node::Context;
+
+/// Called by a secure channel listener for every channel it establishes
+///
+/// The hook runs once the other side passed the trust policy, before the
+/// channel carries any message, so that workers only accepting messages
+/// from that peer can be started in one step, e.g. with
+/// [`IdentityAccessControlBuilder::new_with_channel`](crate::access_control::IdentityAccessControlBuilder::new_with_channel).
+/// An error rejects the channel.
+#[async_trait]
+pub trait SecureChannelEstablishedHook: Send + Sync + 'static {
+    /// `channel` is the local address messages to the peer are sent to,
+    /// and which messages from the peer are delivered by
+    async fn on_established(
+        &self,
+        ctx: &Context,
+        their_identity_id: &IdentityIdentifier,
+        channel: &Address,
+    ) -> Result<()>;
+}
+
+#[async_trait]
+impl<T: SecureChannelEstablishedHook + ?Sized> SecureChannelEstablishedHook for Arc<T> {
+    async fn on_established(
+        &self,
+        ctx: &Context,
+        their_identity_id: &IdentityIdentifier,
+        channel: &Address,
+    ) -> Result<()> {
+        T::on_established(&**self, ctx, their_identity_id, channel).await
+    }
+}
@@ -0,0 +1,89 @@
+use crate::{Identity, IdentityVault};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use ockam_core::compat::sync::Arc;
+
+/// Number of handshakes an [`Identity`] runs at once by default
+pub const DEFAULT_MAX_CONCURRENT_HANDSHAKES: usize = 256;
+
+/// Caps the number of handshakes an [`Identity`] and its clones run at
+/// once, as initiator and as responder
+#[derive(Clone)]
+pub(crate) struct HandshakeLimit {
+    max_handshakes: Arc<AtomicUsize>,
+    running_handshakes: Arc<AtomicUsize>,
+    started_handshakes: Arc<AtomicUsize>,
+}
+
+impl Default for HandshakeLimit {
+    fn default() -> Self {
+        Self {
+            max_handshakes: Arc::new(AtomicUsize::new(DEFAULT_MAX_CONCURRENT_HANDSHAKES)),
+            running_handshakes: Default::default(),
+            started_handshakes: Default::default(),
+        }
+    }
+}
+
+impl HandshakeLimit {
+    /// Reserve a handshake slot, returns `None` if the cap is reached
+    pub fn try_acquire(&self) -> Option<HandshakeSlot> {
+        let max_handshakes = self.max_handshakes.load(Ordering::Relaxed);
+        self.running_handshakes
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |running| {
+                if running < max_handshakes {
+                    Some(running + 1)
+                } else {
+                    None
+                }
+            })
+            .ok()
+            .map(|_| {
+                self.started_handshakes.fetch_add(1, Ordering::SeqCst);
+                HandshakeSlot {
+                    running_handshakes: self.running_handshakes.clone(),
+                }
+            })
+    }
+}
+
+/// Slot of a running handshake, freed when dropped
+pub(crate) struct HandshakeSlot {
+    running_handshakes: Arc<AtomicUsize>,
+}
+
+impl Drop for HandshakeSlot {
+    fn drop(&mut self) {
+        self.running_handshakes.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl<V: IdentityVault> Identity<V> {
+    /// Run at most `max_handshakes` secure channel handshakes at once,
+    /// [`DEFAULT_MAX_CONCURRENT_HANDSHAKES`] by default
+    ///
+    /// Handshakes started beyond the cap are rejected: creating a channel
+    /// fails right away, and a listener drops the initiator's request.
+    /// Running handshakes are not affected by a lower cap. The cap is
+    /// shared by the clones of this identity.
+    pub fn set_max_concurrent_handshakes(&self, max_handshakes: usize) {
+        self.handshake_limit
+            .max_handshakes
+            .store(max_handshakes, Ordering::Relaxed);
+    }
+
+    /// Number of secure channel handshakes currently running
+    pub fn running_handshakes(&self) -> usize {
+        self.handshake_limit
+            .running_handshakes
+            .load(Ordering::SeqCst)
+    }
+
+    /// Number of secure channel handshakes started so far, as initiator
+    /// and as responder, by this identity and its clones
+    #[cfg(test)]
+    pub(crate) fn started_handshakes(&self) -> usize {
+        self.handshake_limit
+            .started_handshakes
+            .load(Ordering::SeqCst)
+    }
+}
@@ -0,0 +1,85 @@
+use core::time::Duration;
+
+/// Timeout of a SecureChannel handshake which adapts to the round trip
+/// time to the listener
+///
+/// The key exchange, which is the first round trip of the handshake, must
+/// complete within the first round trip timeout. The rest of the handshake
+/// is then given a multiple of the measured round trip, kept between the
+/// minimum and the maximum timeouts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AdaptiveHandshakeTimeout {
+    first_round_trip: Duration,
+    min: Duration,
+    max: Duration,
+    multiplier: u32,
+}
+
+impl AdaptiveHandshakeTimeout {
+    /// Round trips the rest of the handshake is given by default
+    pub const DEFAULT_MULTIPLIER: u32 = 4;
+
+    /// Give up on the handshake if its first round trip takes longer than
+    /// `first_round_trip`, then time out the rest of it after between
+    /// `min` and `max`
+    pub fn new(first_round_trip: Duration, min: Duration, max: Duration) -> Self {
+        Self {
+            first_round_trip,
+            min,
+            max: max.max(min),
+            multiplier: Self::DEFAULT_MULTIPLIER,
+        }
+    }
+
+    /// Give the rest of the handshake `multiplier` round trips
+    pub fn with_multiplier(mut self, multiplier: u32) -> Self {
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Timeout of the first round trip of the handshake
+    pub fn first_round_trip(&self) -> Duration {
+        self.first_round_trip
+    }
+
+    /// Timeout of the rest of the handshake, once its first round trip
+    /// took `round_trip`
+    pub fn remaining(&self, round_trip: Duration) -> Duration {
+        round_trip
+            .saturating_mul(self.multiplier)
+            .clamp(self.min, self.max)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_remaining_timeout_is_bounded() {
+        let timeout = AdaptiveHandshakeTimeout::new(
+            Duration::from_secs(5),
+            Duration::from_millis(200),
+            Duration::from_secs(60),
+        );
+
+        assert_eq!(
+            timeout.remaining(Duration::from_millis(1)),
+            Duration::from_millis(200)
+        );
+        assert_eq!(
+            timeout.remaining(Duration::from_millis(600)),
+            Duration::from_millis(2400)
+        );
+        assert_eq!(
+            timeout.remaining(Duration::from_secs(30)),
+            Duration::from_secs(60)
+        );
+        assert_eq!(
+            timeout
+                .with_multiplier(10)
+                .remaining(Duration::from_millis(600)),
+            Duration::from_secs(6)
+        );
+    }
+}
@@ -0,0 +1,178 @@
+use core::time::Duration;
+
+/// Heartbeats checking that the other end of a SecureChannel is alive
+///
+/// Every `interval`, a heartbeat is sent through the channel and echoed
+/// by the decryptor of the other end, which authenticates the echo. If no
+/// echo came back for `window`, the channel is closed. Unlike transport
+/// keepalives, this detects that the workers of the other end stopped
+/// while the transport is still up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SecureChannelKeepalive {
+    interval: Duration,
+    window: Duration,
+}
+
+impl SecureChannelKeepalive {
+    /// Send a heartbeat every `interval`, closing the channel when none
+    /// was echoed for `window`, which is at least `interval`
+    pub fn new(interval: Duration, window: Duration) -> Self {
+        Self {
+            interval,
+            window: window.max(interval),
+        }
+    }
+
+    /// Time between two heartbeats
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Time without echo after which the channel is closed
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+}
+
+#[cfg(feature = "std")]
+pub(crate) use worker::ChannelKeepaliveWorker;
+
+#[cfg(feature = "std")]
+mod worker {
+    use super::SecureChannelKeepalive;
+    use crate::{ChannelPing, IdentityIdentifier, CHANNEL_PING_ADDRESS};
+    use ockam_core::compat::{boxed::Box, rand::random, vec, vec::Vec};
+    use ockam_core::{
+        async_trait, route, Address, Any, Decodable, MessagePriority, Result, Routed, Worker,
+    };
+    use ockam_node::{Context, DelayedEvent};
+    use std::time::Instant;
+    use tracing::{debug, warn};
+
+    /// Sends the heartbeats of a channel, and closes the channel once the
+    /// other end stopped echoing them
+    ///
+    /// Heartbeats are sent when `tick_address` receives the event
+    /// scheduled every interval, and their echoes come back to the main
+    /// address of the worker.
+    pub(crate) struct ChannelKeepaliveWorker {
+        keepalive: SecureChannelKeepalive,
+        their_identity_id: IdentityIdentifier,
+        encryptor_address: Address,
+        channel_addresses: Vec<Address>,
+        tick_address: Address,
+        /// Created once the worker is started
+        tick: Option<DelayedEvent<Vec<u8>>>,
+        /// Nonce of the last heartbeat, until it is echoed
+        pending: Option<u64>,
+        last_echo: Instant,
+    }
+
+    impl ChannelKeepaliveWorker {
+        /// Start sending heartbeats through the encryptor at
+        /// `encryptor_address`, once `keepalive`'s interval elapsed
+        pub async fn create(
+            ctx: &Context,
+            keepalive: SecureChannelKeepalive,
+            their_identity_id: IdentityIdentifier,
+            encryptor_address: Address,
+            channel_addresses: Vec<Address>,
+        ) -> Result<Address> {
+            let address = Address::random_local();
+            let tick_address = Address::random_local();
+            let worker = Self {
+                keepalive,
+                their_identity_id,
+                encryptor_address,
+                channel_addresses,
+                tick_address: tick_address.clone(),
+                tick: None,
+                pending: None,
+                last_echo: Instant::now(),
+            };
+            ctx.start_worker(vec![address.clone(), tick_address], worker)
+                .await?;
+            Ok(address)
+        }
+
+        /// Send a heartbeat, unless the channel was already closed on this
+        /// end, returns whether it was sent
+        async fn heartbeat(&mut self, ctx: &Context) -> bool {
+            let nonce: u64 = random();
+            let sent = ctx
+                .send_with_priority(
+                    route![self.encryptor_address.clone(), CHANNEL_PING_ADDRESS],
+                    ChannelPing(nonce),
+                    MessagePriority::High,
+                )
+                .await;
+            if sent.is_err() {
+                return false;
+            }
+            self.pending = Some(nonce);
+            true
+        }
+    }
+
+    #[async_trait]
+    impl Worker for ChannelKeepaliveWorker {
+        type Message = Any;
+        type Context = Context;
+
+        async fn initialize(&mut self, ctx: &mut Context) -> Result<()> {
+            // The channel is still being set up until the first tick
+            let mut tick = DelayedEvent::create(ctx, self.tick_address.clone(), vec![]).await?;
+            tick.schedule(self.keepalive.interval()).await?;
+            self.tick = Some(tick);
+            self.last_echo = Instant::now();
+            Ok(())
+        }
+
+        async fn shutdown(&mut self, _ctx: &mut Context) -> Result<()> {
+            self.tick = None;
+            Ok(())
+        }
+
+        async fn handle_message(&mut self, ctx: &mut Context, msg: Routed<Any>) -> Result<()> {
+            if msg.msg_addr() != self.tick_address {
+                // Echoes of earlier heartbeats may still come in
+                let echo = ChannelPing::decode(msg.payload())?;
+                if self.pending == Some(echo.0) {
+                    self.pending = None;
+                    self.last_echo = Instant::now();
+                }
+                return Ok(());
+            }
+
+            if self.pending.is_some() {
+                debug!(
+                    "No heartbeat echo on SecureChannel {} with {}",
+                    self.encryptor_address, self.their_identity_id
+                );
+            }
+            if self.last_echo.elapsed() >= self.keepalive.window() {
+                warn!(
+                    "Closing SecureChannel {} with {}: no heartbeat echoed for {:?}",
+                    self.encryptor_address,
+                    self.their_identity_id,
+                    self.keepalive.window()
+                );
+                for address in &self.channel_addresses {
+                    let _ = ctx.stop_worker(address.clone()).await;
+                }
+                // The encryptor may already have stopped this worker
+                let _ = ctx.stop_worker(ctx.address()).await;
+                return Ok(());
+            }
+
+            if !self.heartbeat(ctx).await {
+                // The channel was closed on this end
+                return ctx.stop_worker(ctx.address()).await;
+            }
+            if let Some(tick) = &mut self.tick {
+                tick.schedule(self.keepalive.interval()).await?;
+            }
+            Ok(())
+        }
+    }
+}
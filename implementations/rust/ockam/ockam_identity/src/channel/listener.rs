@@ -1,22 +1,38 @@
 use crate::authenticated_storage::AuthenticatedStorage;
-use crate::{DecryptorWorker, Identity, IdentityVault, TrustPolicy};
+use crate::{
+    DecryptorWorker, HandshakeOptions, Identity, IdentityChannelLimit, IdentityVault,
+    SecureChannelListenerOptions, TrustPolicy,
+};
 use ockam_channel::CreateResponderChannelMessage;
 use ockam_core::compat::{boxed::Box, sync::Arc};
 use ockam_core::{AsyncTryClone, Result, Routed, Worker};
 use ockam_node::Context;
 
+/// Accepts incoming secure channel handshakes.
+///
+/// Every handshake is handed off to its own responder [`DecryptorWorker`], so a slow
+/// or failing handshake (e.g. waiting on a remote trust policy) never delays others.
 pub(crate) struct IdentityChannelListener<V: IdentityVault, S: AuthenticatedStorage> {
     trust_policy: Arc<dyn TrustPolicy>,
     identity: Identity<V>,
     storage: S,
+    channel_limit: Option<IdentityChannelLimit>,
+    options: HandshakeOptions,
 }
 
 impl<V: IdentityVault, S: AuthenticatedStorage> IdentityChannelListener<V, S> {
-    pub fn new(trust_policy: impl TrustPolicy, identity: Identity<V>, storage: S) -> Self {
+    pub fn new(
+        trust_policy: impl TrustPolicy,
+        identity: Identity<V>,
+        storage: S,
+        options: SecureChannelListenerOptions,
+    ) -> Self {
         IdentityChannelListener {
             trust_policy: Arc::new(trust_policy),
             identity,
             storage,
+            channel_limit: options.channel_limit.map(IdentityChannelLimit::new),
+            options: options.handshake,
         }
     }
 }
@@ -31,13 +47,19 @@ impl<V: IdentityVault, S: AuthenticatedStorage> Worker for IdentityChannelListen
         ctx: &mut Self::Context,
         msg: Routed<Self::Message>,
     ) -> Result<()> {
-        let trust_policy = Arc::clone(&self.trust_policy);
+        // Later changes of the policy don't apply to this channel
+        let trust_policy = self
+            .trust_policy
+            .snapshot()
+            .unwrap_or_else(|| Arc::clone(&self.trust_policy));
         let identity = self.identity.async_try_clone().await?;
         DecryptorWorker::create_responder(
             ctx,
             identity,
             self.storage.async_try_clone().await?,
             trust_policy,
+            self.channel_limit.clone(),
+            self.options.clone(),
             msg,
         )
         .await
@@ -1,6 +1,6 @@
 use crate::{IdentityError, IdentityIdentifier};
 use ockam_core::compat::vec::Vec;
-use ockam_core::{Decodable, Encodable, LocalInfo, LocalMessage, Result};
+use ockam_core::{Address, Decodable, Encodable, LocalInfo, LocalMessage, Result};
 use serde::{Deserialize, Serialize};
 
 /// Identity SecureChannel LocalInfo unique Identifier
@@ -10,6 +10,7 @@ pub const IDENTITY_SECURE_CHANNEL_IDENTIFIER: &str = "IDENTITY_SECURE_CHANNEL_ID
 #[derive(Serialize, Deserialize)]
 pub struct IdentitySecureChannelLocalInfo {
     their_identity_id: IdentityIdentifier,
+    channel_address: Address,
 }
 
 impl IdentitySecureChannelLocalInfo {
@@ -53,6 +54,11 @@ impl IdentitySecureChannelLocalInfo {
     pub fn their_identity_id(&self) -> &IdentityIdentifier {
         &self.their_identity_id
     }
+
+    /// Local address of the secure channel which delivered the message
+    pub fn channel_address(&self) -> &Address {
+        &self.channel_address
+    }
 }
 
 impl IdentitySecureChannelLocalInfo {
@@ -61,12 +67,19 @@ impl IdentitySecureChannelLocalInfo {
     pub fn mark(
         mut local_info: Vec<LocalInfo>,
         their_identity_id: IdentityIdentifier,
+        channel_address: Address,
     ) -> Result<Vec<LocalInfo>> {
         // strip out any pre-existing IdentitySecureChannLocalInfo
         local_info.retain(|x| x.type_identifier() != IDENTITY_SECURE_CHANNEL_IDENTIFIER);
 
         // mark the vector
-        local_info.push(Self { their_identity_id }.to_local_info()?);
+        local_info.push(
+            Self {
+                their_identity_id,
+                channel_address,
+            }
+            .to_local_info()?,
+        );
 
         Ok(local_info)
     }
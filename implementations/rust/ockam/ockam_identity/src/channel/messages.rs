@@ -1,7 +1,42 @@
+use crate::IdentityError;
 use ockam_core::compat::vec::Vec;
-use ockam_core::Message;
+use ockam_core::{Decodable, Encodable, Message, Result};
 use serde::{Deserialize, Serialize};
 
+/// Address which pings are sent to through a secure channel
+///
+/// Messages for it are echoed back by the decryptor of the other end
+/// instead of being forwarded to its workers.
+pub(crate) const CHANNEL_PING_ADDRESS: &str = "_internal.secure_channel.ping";
+
+/// Address which a close notice is sent to through a secure channel
+///
+/// The encryptor sending it stops its end of the channel once the notice
+/// is on its way, and the decryptor of the other end stops the other end.
+pub(crate) const CHANNEL_CLOSE_ADDRESS: &str = "_internal.secure_channel.close";
+
+/// Address marking a numbered message, whose payload is a
+/// [`SequencedPayload`]
+///
+/// The decryptor of the other end unwraps the payload before handling the
+/// rest of the onward route.
+pub(crate) const CHANNEL_SEQUENCE_ADDRESS: &str = "_internal.secure_channel.sequence";
+
+/// Largest bucket size handshake messages are padded to
+pub(crate) const MAX_HANDSHAKE_BUCKET_SIZE: usize = 64 * 1024;
+
+/// Payload of a numbered message
+#[derive(Serialize, Deserialize, Message)]
+pub(crate) struct SequencedPayload {
+    pub sequence: u64,
+    pub payload: Vec<u8>,
+}
+
+/// A ping, identified by a random nonce
+#[cfg(feature = "std")]
+#[derive(Serialize, Deserialize, Message)]
+pub(crate) struct ChannelPing(pub u64);
+
 #[derive(Serialize, Deserialize, Message)]
 pub(crate) enum IdentityChannelMessage {
     Request {
@@ -13,4 +48,97 @@ pub(crate) enum IdentityChannelMessage {
         signature: Vec<u8>,
     },
     Confirm,
+    /// Same as `Response`, for a channel carrying messages from the
+    /// initiator only. The responder starts no encryptor.
+    ResponseSendOnly {
+        identity: Vec<u8>,
+        signature: Vec<u8>,
+    },
+    /// Another handshake message, padded so that the size of the encoded
+    /// `Padded` message is a multiple of `bucket_size`
+    Padded {
+        bucket_size: u32,
+        message: Vec<u8>,
+        padding: Vec<u8>,
+    },
+    /// Another handshake message, with a proof that the sender knows the
+    /// pre-shared key of the channel
+    WithPreSharedKey {
+        proof: Vec<u8>,
+        message: Vec<u8>,
+    },
+    /// Same as `Request`, from a listener accepting send-only channels,
+    /// which initiators may then answer with `ResponseSendOnly`
+    RequestAcceptsSendOnly {
+        identity: Vec<u8>,
+        signature: Vec<u8>,
+    },
+    /// Same as `Request`, from a listener which presents no identity
+    RequestAnonymous {
+        accepts_send_only: bool,
+    },
+    /// Same as `Response`, from an initiator which presents no identity
+    ResponseAnonymous {
+        send_only: bool,
+    },
+}
+
+impl IdentityChannelMessage {
+    /// Wrap this message into a [`Padded`](Self::Padded) one, the bucket
+    /// size being at most [`MAX_HANDSHAKE_BUCKET_SIZE`]
+    pub(crate) fn padded(&self, bucket_size: usize) -> Result<Self> {
+        let bucket_size = bucket_size.clamp(1, MAX_HANDSHAKE_BUCKET_SIZE);
+        let message = self.encode()?;
+        let padded = |padding_len| IdentityChannelMessage::Padded {
+            bucket_size: bucket_size as u32,
+            message: message.clone(),
+            padding: vec![0; padding_len],
+        };
+        let unpadded_len = padded(0).encode()?.len();
+        let mut target_len = unpadded_len + bucket_size - 1;
+        target_len -= target_len % bucket_size;
+        loop {
+            // The length prefix of the padding grows with it, so a few
+            // padding lengths are tried for each target
+            let max_padding_len = target_len - unpadded_len;
+            for padding_len in max_padding_len.saturating_sub(8)..=max_padding_len {
+                let msg = padded(padding_len);
+                if msg.encode()?.len() == target_len {
+                    return Ok(msg);
+                }
+            }
+            target_len += bucket_size;
+        }
+    }
+
+    /// Decode a handshake message, unwrapping it if it is
+    /// [`Padded`](Self::Padded), and return the bucket size it was padded
+    /// to, if any
+    ///
+    /// A padded message must be as long as a multiple of its bucket size,
+    /// which is at most [`MAX_HANDSHAKE_BUCKET_SIZE`].
+    pub(crate) fn decode_padded(data: &[u8]) -> Result<(Self, Option<usize>)> {
+        match IdentityChannelMessage::decode(data)? {
+            IdentityChannelMessage::Padded {
+                bucket_size,
+                message,
+                ..
+            } => {
+                let bucket_size = bucket_size as usize;
+                if bucket_size == 0
+                    || bucket_size > MAX_HANDSHAKE_BUCKET_SIZE
+                    || data.len() % bucket_size != 0
+                {
+                    return Err(IdentityError::SecureChannelPaddingMismatch.into());
+                }
+                match IdentityChannelMessage::decode(&message)? {
+                    IdentityChannelMessage::Padded { .. } => {
+                        Err(IdentityError::InvalidSecureChannelInternalState.into())
+                    }
+                    msg => Ok((msg, Some(bucket_size))),
+                }
+            }
+            msg => Ok((msg, None)),
+        }
+    }
 }
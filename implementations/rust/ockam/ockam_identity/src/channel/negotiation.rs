@@ -0,0 +1,188 @@
+use crate::{CipherSuite, Identity, IdentityVault, SecureChannelRole, SecureChannelTrustInfo};
+use core::fmt;
+use ockam_core::compat::{collections::BTreeMap, sync::Mutex};
+use ockam_core::Address;
+use serde::{Deserialize, Serialize};
+
+/// Compression of the messages sent through a SecureChannel
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum ChannelCompression {
+    /// Messages are encrypted as they are
+    None,
+}
+
+impl fmt::Display for ChannelCompression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChannelCompression::None => f.write_str("none"),
+        }
+    }
+}
+
+/// Parameters a SecureChannel was established with
+///
+/// The XX key exchange isn't post quantum, so that isn't negotiated.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SecureChannelNegotiation {
+    role: SecureChannelRole,
+    cipher_suite: CipherSuite,
+    compression: ChannelCompression,
+    pre_shared_key: bool,
+    handshake_padding: Option<usize>,
+    send_only: bool,
+    sequence_numbers: bool,
+    byte_budget: Option<u64>,
+}
+
+impl SecureChannelNegotiation {
+    pub(crate) fn new(role: SecureChannelRole, cipher_suite: CipherSuite) -> Self {
+        Self {
+            role,
+            cipher_suite,
+            // Channels don't compress messages yet
+            compression: ChannelCompression::None,
+            pre_shared_key: false,
+            handshake_padding: None,
+            send_only: false,
+            sequence_numbers: false,
+            byte_budget: None,
+        }
+    }
+
+    pub(crate) fn with_pre_shared_key(mut self, pre_shared_key: bool) -> Self {
+        self.pre_shared_key = pre_shared_key;
+        self
+    }
+
+    pub(crate) fn with_handshake_padding(mut self, handshake_padding: Option<usize>) -> Self {
+        self.handshake_padding = handshake_padding;
+        self
+    }
+
+    pub(crate) fn with_send_only(mut self, send_only: bool) -> Self {
+        self.send_only = send_only;
+        self
+    }
+
+    pub(crate) fn with_sequence_numbers(mut self, sequence_numbers: bool) -> Self {
+        self.sequence_numbers = sequence_numbers;
+        self
+    }
+
+    pub(crate) fn with_byte_budget(mut self, byte_budget: Option<u64>) -> Self {
+        self.byte_budget = byte_budget;
+        self
+    }
+}
+
+impl SecureChannelNegotiation {
+    /// Role played by this end of the channel in the handshake
+    pub fn role(&self) -> SecureChannelRole {
+        self.role
+    }
+
+    /// Cipher suite of the key exchange
+    pub fn cipher_suite(&self) -> CipherSuite {
+        self.cipher_suite
+    }
+
+    /// Compression of the messages
+    pub fn compression(&self) -> ChannelCompression {
+        self.compression
+    }
+
+    /// Whether both sides proved they know a pre-shared key
+    pub fn pre_shared_key(&self) -> bool {
+        self.pre_shared_key
+    }
+
+    /// Bucket size the handshake messages were padded to, the larger of
+    /// both sides' sizes
+    pub fn handshake_padding(&self) -> Option<usize> {
+        self.handshake_padding
+    }
+
+    /// Whether messages only flow from the initiator to the responder
+    pub fn send_only(&self) -> bool {
+        self.send_only
+    }
+
+    /// Whether this end numbers the messages it sends
+    pub fn sequence_numbers(&self) -> bool {
+        self.sequence_numbers
+    }
+
+    /// Payload bytes this end may carry before it is closed
+    pub fn byte_budget(&self) -> Option<u64> {
+        self.byte_budget
+    }
+}
+
+/// What is known about an established channel
+struct EstablishedChannel {
+    negotiation: SecureChannelNegotiation,
+    /// What the trust policy was given when the channel was established
+    trust_info: SecureChannelTrustInfo,
+}
+
+/// Negotiations of the established channels of an [`Identity`] and its
+/// clones, by the address of the channel
+pub(crate) struct SecureChannelNegotiations {
+    channels: Mutex<BTreeMap<Address, EstablishedChannel>>,
+}
+
+impl Default for SecureChannelNegotiations {
+    fn default() -> Self {
+        Self {
+            channels: Mutex::new(BTreeMap::new()),
+        }
+    }
+}
+
+impl SecureChannelNegotiations {
+    pub(crate) fn insert(
+        &self,
+        channel: Address,
+        negotiation: SecureChannelNegotiation,
+        trust_info: SecureChannelTrustInfo,
+    ) {
+        self.channels.lock().unwrap().insert(
+            channel,
+            EstablishedChannel {
+                negotiation,
+                trust_info,
+            },
+        );
+    }
+
+    /// What the trust policy was given when `channel` was established,
+    /// `None` once it is stopped
+    pub(crate) fn trust_info(&self, channel: &Address) -> Option<SecureChannelTrustInfo> {
+        let channels = self.channels.lock().unwrap();
+        channels.get(channel).map(|c| c.trust_info.clone())
+    }
+
+    pub(crate) fn remove(&self, channel: &Address) {
+        self.channels.lock().unwrap().remove(channel);
+    }
+}
+
+impl<V: IdentityVault> Identity<V> {
+    /// Parameters an established channel was negotiated with, given the
+    /// address returned when it was created or given to the listener's
+    /// [established hook](crate::SecureChannelEstablishedHook)
+    ///
+    /// Returns `None` once the channel is stopped.
+    pub fn secure_channel_negotiation(
+        &self,
+        channel: &Address,
+    ) -> Option<SecureChannelNegotiation> {
+        self.channel_negotiations
+            .channels
+            .lock()
+            .unwrap()
+            .get(channel)
+            .map(|c| c.negotiation.clone())
+    }
+}
@@ -0,0 +1,278 @@
+#[cfg(feature = "std")]
+use crate::{AdaptiveHandshakeTimeout, SecureChannelKeepalive};
+use crate::{ChannelGapCounter, HandshakeOptions, SecureChannelEstablishedHook};
+use core::time::Duration;
+use ockam_channel::KeyEscrow;
+use ockam_core::compat::sync::Arc;
+use ockam_core::vault::KeyId;
+use ockam_core::Address;
+
+/// How long the handshake of a secure channel may take, unless its
+/// options say otherwise
+pub const DEFAULT_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Options of a secure channel created by
+/// [`Identity::create_secure_channel_with_options`](crate::Identity::create_secure_channel_with_options)
+#[derive(Clone)]
+pub struct SecureChannelOptions {
+    pub(crate) timeout: Duration,
+    pub(crate) handshake: HandshakeOptions,
+}
+
+impl Default for SecureChannelOptions {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_HANDSHAKE_TIMEOUT,
+            handshake: HandshakeOptions::default(),
+        }
+    }
+}
+
+impl SecureChannelOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Give up on the handshake after `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Adapt the handshake timeout to the round trip time to the listener
+    ///
+    /// An unresponsive listener is given up on after the first round trip
+    /// timeout, while the rest of the handshake over a slow link is given
+    /// more time than over a fast one. Replaces the timeout.
+    #[cfg(feature = "std")]
+    pub fn with_adaptive_timeout(mut self, timeout: AdaptiveHandshakeTimeout) -> Self {
+        self.timeout = timeout.first_round_trip();
+        self.handshake.adaptive_timeout = Some(timeout);
+        self
+    }
+
+    /// Only carry messages from this identity
+    ///
+    /// The responder is told during the handshake and keeps no state to
+    /// send messages back: replies to messages received over the channel
+    /// are dropped. Suits devices which only ever report data. The
+    /// handshake fails with
+    /// [`IdentityError::SecureChannelSendOnlyRejected`](crate::IdentityError::SecureChannelSendOnlyRejected)
+    /// as soon as the listener shows it doesn't
+    /// [accept send-only channels](SecureChannelListenerOptions::accept_send_only).
+    pub fn send_only(mut self) -> Self {
+        self.handshake.send_only = true;
+        self
+    }
+
+    /// Present no identity to the listener
+    ///
+    /// The listener identifies this side with an
+    /// [anonymous identifier](crate::IdentityIdentifier::anonymous) unique
+    /// to the channel, and only accepts it if its trust policy accepts
+    /// anonymous initiators, e.g. [`MutualAuthTrustPolicy::responder_only`](crate::MutualAuthTrustPolicy::responder_only).
+    pub fn anonymous(mut self) -> Self {
+        self.handshake.anonymous = true;
+        self
+    }
+
+    /// Pad the handshake messages to a multiple of `bucket_size` bytes
+    ///
+    /// The handshake fails if the listener doesn't pad its messages too.
+    pub fn with_handshake_padding(mut self, bucket_size: usize) -> Self {
+        self.handshake.padding = Some(bucket_size);
+        self
+    }
+
+    /// Prove this side knows `pre_shared_key`, imported with
+    /// [`Identity::import_pre_shared_key`](crate::Identity::import_pre_shared_key)
+    ///
+    /// The handshake fails if the listener doesn't prove it knows the same
+    /// key, and the listener rejects it if this side doesn't.
+    pub fn with_pre_shared_key(mut self, pre_shared_key: KeyId) -> Self {
+        self.handshake.pre_shared_key = Some(pre_shared_key);
+        self
+    }
+
+    /// Deliver the messages decrypted on this side to `address`, whatever
+    /// their onward route
+    pub fn with_delivery_address(mut self, address: impl Into<Address>) -> Self {
+        self.handshake.delivery_address = Some(address.into());
+        self
+    }
+
+    /// Close the channel once it carried `byte_budget` bytes of payload,
+    /// sent and received
+    ///
+    /// A message that doesn't fit in what is left of the budget is
+    /// dropped, and both ends of the channel on this side are stopped with
+    /// [`IdentityError::SecureChannelByteBudgetExceeded`](crate::IdentityError::SecureChannelByteBudgetExceeded).
+    pub fn with_byte_budget(mut self, byte_budget: u64) -> Self {
+        self.handshake.byte_budget = Some(byte_budget);
+        self
+    }
+
+    /// Number the messages sent, and count the messages lost on their way
+    /// from the other end in `gaps`
+    ///
+    /// This tells an idle peer apart from messages being dropped on a lossy
+    /// route. Lost messages are not retransmitted. Each end numbers the
+    /// messages it sends if it was created with sequence numbers, and the
+    /// other end detects gaps in the numbers whether or not it numbers its
+    /// own messages.
+    pub fn with_sequence_numbers(mut self, gaps: ChannelGapCounter) -> Self {
+        self.handshake.sequence_gaps = Some(gaps);
+        self
+    }
+
+    /// Send heartbeats to the other end, and close the channel once the
+    /// other end stops echoing them
+    ///
+    /// Heartbeats are encrypted like any message, and echoed by the
+    /// decryptor at the other end whether or not it has a keepalive. A
+    /// channel whose heartbeats were not echoed for the keepalive's window
+    /// is closed on this side, even though the transport under it may still
+    /// be up. Send-only channels don't send heartbeats.
+    #[cfg(feature = "std")]
+    pub fn with_keepalive(mut self, keepalive: SecureChannelKeepalive) -> Self {
+        self.handshake.keepalive = Some(keepalive);
+        self
+    }
+
+    /// Escrow the keys of the channel with `key_escrow`, see [`KeyEscrow`]
+    ///
+    /// Whoever holds the recovery key of the escrow can decrypt every
+    /// message exchanged on this channel. The other end is not told.
+    pub fn with_key_escrow(mut self, key_escrow: KeyEscrow) -> Self {
+        self.handshake.key_escrow = Some(key_escrow);
+        self
+    }
+}
+
+/// Options of a secure channel listener created by
+/// [`Identity::create_secure_channel_listener_with_options`](crate::Identity::create_secure_channel_listener_with_options)
+#[derive(Clone, Default)]
+pub struct SecureChannelListenerOptions {
+    pub(crate) channel_limit: Option<usize>,
+    pub(crate) handshake: HandshakeOptions,
+}
+
+impl SecureChannelListenerOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accept at most `max_channels_per_identity` simultaneous channels
+    /// from the same peer identity
+    ///
+    /// Handshakes beyond that cap are rejected until one of that peer's
+    /// channels is stopped.
+    pub fn with_channel_limit(mut self, max_channels_per_identity: usize) -> Self {
+        self.channel_limit = Some(max_channels_per_identity);
+        self
+    }
+
+    /// Give up on the handshake of a channel after `timeout`, freeing its
+    /// handshake slot, [`DEFAULT_HANDSHAKE_TIMEOUT`] by default
+    ///
+    /// Initiators which never complete their handshake would otherwise
+    /// keep the slot forever.
+    pub fn with_handshake_timeout(mut self, timeout: Duration) -> Self {
+        self.handshake.responder_timeout = Some(timeout);
+        self
+    }
+
+    /// Accept channels created with [`SecureChannelOptions::send_only`],
+    /// closing them once they carried no message for `idle_timeout`
+    ///
+    /// Send-only channels have no heartbeats nor close notices which would
+    /// tell this side that the initiator went away, hence the timeout. A
+    /// channel is closed between one and two `idle_timeout`s after its
+    /// last message. Initiators which predate send-only channels can't
+    /// complete their handshake with such a listener.
+    pub fn accept_send_only(mut self, idle_timeout: Duration) -> Self {
+        self.handshake.send_only_idle_timeout = Some(idle_timeout);
+        self
+    }
+
+    /// Present no identity to the initiators
+    ///
+    /// Initiators identify this side with an
+    /// [anonymous identifier](crate::IdentityIdentifier::anonymous) unique
+    /// to each channel, and only accept it if their trust policy accepts
+    /// anonymous responders, e.g. [`MutualAuthTrustPolicy::initiator_only`](crate::MutualAuthTrustPolicy::initiator_only).
+    pub fn anonymous(mut self) -> Self {
+        self.handshake.anonymous = true;
+        self
+    }
+
+    /// Call `hook` for every channel established by the listener, with the
+    /// identity of the peer
+    pub fn with_established_hook(mut self, hook: impl SecureChannelEstablishedHook) -> Self {
+        self.handshake.established_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Pad the handshake messages to a multiple of `bucket_size` bytes
+    ///
+    /// Initiators must pad their handshake messages to at least the same
+    /// size, which they do when they see padded messages from the listener,
+    /// so the size of the identities and signatures exchanged is hidden.
+    pub fn with_handshake_padding(mut self, bucket_size: usize) -> Self {
+        self.handshake.padding = Some(bucket_size);
+        self
+    }
+
+    /// Only accept initiators proving they know `pre_shared_key`, and
+    /// prove it knows it too
+    ///
+    /// Both proofs are bound to the key exchange of each channel, so the
+    /// key is never sent. The trust policy still applies to the identity
+    /// of the initiator.
+    pub fn with_pre_shared_key(mut self, pre_shared_key: KeyId) -> Self {
+        self.handshake.pre_shared_key = Some(pre_shared_key);
+        self
+    }
+
+    /// Deliver the decrypted messages of every channel to `address`,
+    /// whatever their onward route
+    ///
+    /// The onward route is kept after `address`, and the return route still
+    /// goes back through the channel, so the worker at `address` can reply
+    /// as usual.
+    pub fn with_delivery_address(mut self, address: impl Into<Address>) -> Self {
+        self.handshake.delivery_address = Some(address.into());
+        self
+    }
+
+    /// Close every channel once it carried `byte_budget` bytes of payload,
+    /// sent and received
+    pub fn with_byte_budget(mut self, byte_budget: u64) -> Self {
+        self.handshake.byte_budget = Some(byte_budget);
+        self
+    }
+
+    /// Number the messages sent on every channel, and count the messages
+    /// lost on their way from initiators in `gaps`
+    pub fn with_sequence_numbers(mut self, gaps: ChannelGapCounter) -> Self {
+        self.handshake.sequence_gaps = Some(gaps);
+        self
+    }
+
+    /// Send heartbeats to the initiators, and close every channel once its
+    /// initiator stops echoing them
+    #[cfg(feature = "std")]
+    pub fn with_keepalive(mut self, keepalive: SecureChannelKeepalive) -> Self {
+        self.handshake.keepalive = Some(keepalive);
+        self
+    }
+
+    /// Escrow the keys of every channel with `key_escrow`, see [`KeyEscrow`]
+    ///
+    /// Whoever holds the recovery key of the escrow can decrypt every
+    /// message exchanged on these channels.
+    pub fn with_key_escrow(mut self, key_escrow: KeyEscrow) -> Self {
+        self.handshake.key_escrow = Some(key_escrow);
+        self
+    }
+}
@@ -0,0 +1,314 @@
+use crate::authenticated_storage::AuthenticatedStorage;
+use crate::channel::trust_policy::check_trust_info;
+use crate::{Identity, IdentityVault, SecureChannelOptions, TrustPolicy};
+use core::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "std")]
+use core::time::Duration;
+use ockam_core::compat::{collections::BTreeMap, string::String, sync::Mutex, vec::Vec};
+use ockam_core::{route, Address, Result, Route};
+
+/// How long a pooled channel has to answer the ping checking its health
+#[cfg(feature = "std")]
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long a pooled channel is reused without pinging it again
+#[cfg(feature = "std")]
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Channels are only shared between calls using the same destination and
+/// trust policies with the same [`TrustPolicy::pool_key`]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct PoolKey {
+    route: Route,
+    trust_policy: String,
+}
+
+impl PoolKey {
+    /// `None` if the trust policy has no pool key
+    fn new(route: &Route, trust_policy: &impl TrustPolicy) -> Option<Self> {
+        Some(Self {
+            route: route.clone(),
+            trust_policy: trust_policy.pool_key()?,
+        })
+    }
+}
+
+/// A pooled channel
+struct PooledChannel {
+    address: Address,
+    /// When the channel last answered a ping
+    #[cfg(feature = "std")]
+    checked_at: std::time::Instant,
+}
+
+impl PooledChannel {
+    fn new(address: Address) -> Self {
+        Self {
+            address,
+            #[cfg(feature = "std")]
+            checked_at: std::time::Instant::now(),
+        }
+    }
+}
+
+type PooledChannels = Mutex<BTreeMap<PoolKey, PooledChannel>>;
+
+/// Secure channels created by an [`Identity`] and its clones, which
+/// [`Identity::create_secure_channel`] returns again rather than running
+/// a new handshake
+pub(crate) struct SecureChannelPool {
+    enabled: AtomicBool,
+    channels: PooledChannels,
+    /// Channels created by [`Identity::create_nested_secure_channel`]
+    nested: PooledChannels,
+}
+
+impl SecureChannelPool {
+    /// Create a disabled pool
+    pub(crate) fn new() -> Self {
+        Self {
+            enabled: AtomicBool::new(false),
+            channels: Mutex::new(BTreeMap::new()),
+            nested: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    fn get(channels: &PooledChannels, key: &PoolKey) -> Option<Address> {
+        let channels = channels.lock().unwrap();
+        channels.get(key).map(|c| c.address.clone())
+    }
+
+    fn insert(channels: &PooledChannels, key: PoolKey, channel: Address) {
+        channels
+            .lock()
+            .unwrap()
+            .insert(key, PooledChannel::new(channel));
+    }
+
+    /// Whether the entry of `key` still holds `channel` and wasn't pinged
+    /// recently
+    #[cfg(feature = "std")]
+    fn needs_ping(channels: &PooledChannels, key: &PoolKey, channel: &Address) -> bool {
+        match channels.lock().unwrap().get(key) {
+            Some(c) if &c.address == channel => c.checked_at.elapsed() >= HEALTH_CHECK_INTERVAL,
+            _ => false,
+        }
+    }
+
+    /// Record that `channel` answered a ping
+    #[cfg(feature = "std")]
+    fn checked(channels: &PooledChannels, key: &PoolKey, channel: &Address) {
+        if let Some(c) = channels.lock().unwrap().get_mut(key) {
+            if &c.address == channel {
+                c.checked_at = std::time::Instant::now();
+            }
+        }
+    }
+
+    /// Remove the entry of `key` if it still holds `channel`
+    fn evict(channels: &PooledChannels, key: &PoolKey, channel: &Address) {
+        let mut channels = channels.lock().unwrap();
+        if channels.get(key).map(|c| &c.address) == Some(channel) {
+            channels.remove(key);
+        }
+    }
+
+    /// Remove `channel` from the pool, once it is stopped
+    pub(crate) fn forget(&self, channel: &Address) {
+        for channels in [&self.channels, &self.nested] {
+            channels
+                .lock()
+                .unwrap()
+                .retain(|_, c| &c.address != channel);
+        }
+    }
+}
+
+impl<V: IdentityVault> Identity<V> {
+    /// Reuse secure channels created by [`create_secure_channel`](Self::create_secure_channel)
+    ///
+    /// Once enabled, creating a channel to a route which already has a
+    /// channel created with a trust policy of the same
+    /// [pool key](TrustPolicy::pool_key) returns that channel if it's
+    /// still healthy: the identity at its other end must still pass the
+    /// trust policy, and the channel must answer a ping if it wasn't
+    /// pinged in the last 30 seconds. Otherwise the channel is stopped,
+    /// and a new one is created and replaces it in the pool.
+    ///
+    /// The pool is shared by the clones of this identity.
+    pub fn enable_secure_channel_pool(&self) {
+        self.channel_pool.enabled.store(true, Ordering::Relaxed);
+    }
+
+    /// Stop reusing secure channels, forgetting the pooled ones, nested
+    /// channels included
+    ///
+    /// The channels are not stopped.
+    pub fn disable_secure_channel_pool(&self) {
+        self.channel_pool.enabled.store(false, Ordering::Relaxed);
+        self.channel_pool.channels.lock().unwrap().clear();
+        self.channel_pool.nested.lock().unwrap().clear();
+    }
+
+    /// Return the addresses of the pooled secure channels
+    pub fn pooled_secure_channels(&self) -> Vec<Address> {
+        let channels = self.channel_pool.channels.lock().unwrap();
+        channels.values().map(|c| c.address.clone()).collect()
+    }
+
+    /// Key of the channels to `route` in the pool, `None` if the pool is
+    /// disabled or `trust_policy` has no pool key
+    pub(crate) fn secure_channel_pool_key(
+        &self,
+        route: &Route,
+        trust_policy: &impl TrustPolicy,
+    ) -> Option<PoolKey> {
+        if !self.channel_pool.enabled.load(Ordering::Relaxed) {
+            return None;
+        }
+        PoolKey::new(route, trust_policy)
+    }
+
+    /// Return the pooled channel of `key`, if there is a healthy one
+    pub(crate) async fn pooled_secure_channel(
+        &self,
+        key: &PoolKey,
+        trust_policy: &impl TrustPolicy,
+    ) -> Result<Option<Address>> {
+        self.healthy_channel_from(&self.channel_pool.channels, key, trust_policy)
+            .await
+    }
+
+    /// Add a channel created for `key` to the pool
+    pub(crate) fn pool_secure_channel(&self, key: PoolKey, channel: &Address) {
+        SecureChannelPool::insert(&self.channel_pool.channels, key, channel.clone());
+    }
+
+    /// Create a secure channel to the listener at `listener`, tunneled
+    /// through the established secure channel `outer_channel`
+    ///
+    /// The inner channel shares the transport of the outer one: its route
+    /// is not resolved again, the outer channel only has to answer a ping.
+    /// With the [pool](Self::enable_secure_channel_pool) enabled, the inner
+    /// handshake runs once per outer channel and listener: later calls
+    /// return the same inner channel while it's healthy and the identity
+    /// at its other end still passes the trust policy.
+    pub async fn create_nested_secure_channel<T: TrustPolicy>(
+        &self,
+        outer_channel: &Address,
+        listener: impl Into<Address>,
+        trust_policy: T,
+        storage: &impl AuthenticatedStorage,
+    ) -> Result<Address> {
+        let route = route![outer_channel.clone(), listener];
+        let key = self.secure_channel_pool_key(&route, &trust_policy);
+        if let Some(key) = &key {
+            if let Some(channel) = self
+                .healthy_channel_from(&self.channel_pool.nested, key, &trust_policy)
+                .await?
+            {
+                return Ok(channel);
+            }
+        }
+
+        let channel = self
+            .create_nested_secure_channel_over(
+                route,
+                trust_policy,
+                storage,
+                SecureChannelOptions::new(),
+            )
+            .await?;
+        if let Some(key) = key {
+            SecureChannelPool::insert(&self.channel_pool.nested, key, channel.clone());
+        }
+        Ok(channel)
+    }
+
+    /// Create a secure channel to the listener at `listener`, tunneled
+    /// through the established secure channel `outer_channel`, with
+    /// `options`
+    ///
+    /// Like [`create_secure_channel_with_options`](Self::create_secure_channel_with_options),
+    /// the channel is never pooled.
+    pub async fn create_nested_secure_channel_with_options(
+        &self,
+        outer_channel: &Address,
+        listener: impl Into<Address>,
+        trust_policy: impl TrustPolicy,
+        storage: &impl AuthenticatedStorage,
+        options: SecureChannelOptions,
+    ) -> Result<Address> {
+        let route = route![outer_channel.clone(), listener];
+        self.create_nested_secure_channel_over(route, trust_policy, storage, options)
+            .await
+    }
+
+    /// Check that the outer channel, first in `route`, is alive, then
+    /// create the inner channel
+    async fn create_nested_secure_channel_over(
+        &self,
+        route: Route,
+        trust_policy: impl TrustPolicy,
+        storage: &impl AuthenticatedStorage,
+        options: SecureChannelOptions,
+    ) -> Result<Address> {
+        #[cfg(feature = "std")]
+        self.echo_channel_ping(route.next()?.clone(), HEALTH_CHECK_TIMEOUT)
+            .await?;
+
+        self.create_secure_channel_with_options(route, trust_policy, storage, options)
+            .await
+    }
+
+    /// Return the channel of `key` from `channels`, if there is a healthy
+    /// one, evicting and stopping it otherwise
+    async fn healthy_channel_from(
+        &self,
+        channels: &PooledChannels,
+        key: &PoolKey,
+        trust_policy: &impl TrustPolicy,
+    ) -> Result<Option<Address>> {
+        let channel = match SecureChannelPool::get(channels, key) {
+            Some(channel) => channel,
+            None => return Ok(None),
+        };
+
+        // Channels are only known while their workers run
+        let trust_info = match self.channel_negotiations.trust_info(&channel) {
+            Some(trust_info) => trust_info,
+            None => {
+                debug!("Evicting stopped secure channel {} from the pool", channel);
+                SecureChannelPool::evict(channels, key, &channel);
+                return Ok(None);
+            }
+        };
+
+        #[cfg(feature = "std")]
+        let alive = if SecureChannelPool::needs_ping(channels, key, &channel) {
+            let answered = self
+                .echo_channel_ping(channel.clone(), HEALTH_CHECK_TIMEOUT)
+                .await
+                .is_ok();
+            if answered {
+                SecureChannelPool::checked(channels, key, &channel);
+            }
+            answered
+        } else {
+            true
+        };
+        #[cfg(not(feature = "std"))]
+        let alive = true;
+
+        if alive && check_trust_info(trust_policy, &trust_info).await? {
+            return Ok(Some(channel));
+        }
+
+        debug!("Evicting secure channel {} from the pool", channel);
+        SecureChannelPool::evict(channels, key, &channel);
+        if let Err(e) = self.stop_secure_channel(&channel).await {
+            debug!("Failed to stop evicted secure channel {}: {}", channel, e);
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,46 @@
+use ockam_core::compat::sync::{Arc, Mutex};
+
+/// Counts the messages lost by secure channels numbering their messages,
+/// see [`SecureChannelOptions::with_sequence_numbers`]
+///
+/// A gap in the numbers of the messages received on a channel is counted
+/// as lost messages, nothing is retransmitted. Messages arriving out of
+/// order are counted as lost. A counter may be shared by several channels,
+/// e.g. by every channel of a listener.
+///
+/// [`SecureChannelOptions::with_sequence_numbers`]: crate::SecureChannelOptions::with_sequence_numbers
+#[derive(Clone)]
+pub struct ChannelGapCounter {
+    /// Gaps and messages lost, counted together
+    counts: Arc<Mutex<(u64, u64)>>,
+}
+
+impl Default for ChannelGapCounter {
+    fn default() -> Self {
+        Self {
+            counts: Arc::new(Mutex::new((0, 0))),
+        }
+    }
+}
+
+impl ChannelGapCounter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of gaps detected so far
+    pub fn gaps(&self) -> u64 {
+        self.counts.lock().unwrap().0
+    }
+
+    /// Number of messages lost so far, over all the gaps
+    pub fn lost_messages(&self) -> u64 {
+        self.counts.lock().unwrap().1
+    }
+
+    pub(crate) fn record_gap(&self, lost_messages: u64) {
+        let mut counts = self.counts.lock().unwrap();
+        counts.0 = counts.0.saturating_add(1);
+        counts.1 = counts.1.saturating_add(lost_messages);
+    }
+}
@@ -0,0 +1,169 @@
+use crate::authenticated_storage::AuthenticatedStorage;
+use crate::{Identity, IdentityVault, SecureChannelOptions, TrustPolicy, CHANNEL_CLOSE_ADDRESS};
+use ockam_core::compat::{
+    boxed::Box,
+    sync::{Arc, RwLock},
+};
+use ockam_core::{async_trait, route, Address, Any, AsyncTryClone, Result, Route, Routed, Worker};
+use ockam_node::Context;
+use tracing::debug;
+
+/// A secure channel whose address stays the same when it reconnects
+///
+/// Messages sent to [`address`](Self::address) go through the current
+/// secure channel, and the return route of the messages received over it
+/// starts with that address rather than with the channel's. Routes cached
+/// by applications, e.g. from the return route of a reply, thus keep
+/// working after [`reconnect`](Self::reconnect) replaced the channel.
+///
+/// Dropping the handle stops the channel, as [`stop`](Self::stop) does.
+pub struct StableSecureChannel<V: IdentityVault, S: AuthenticatedStorage> {
+    address: Address,
+    /// Where the current channel delivers the messages it decrypts
+    inbox: Address,
+    channel: Arc<RwLock<Address>>,
+    identity: Identity<V>,
+    route: Route,
+    trust_policy: Arc<dyn TrustPolicy>,
+    storage: S,
+    /// Stops the channel when the handle is dropped without being stopped
+    drop_ctx: Option<Context>,
+}
+
+impl<V: IdentityVault, S: AuthenticatedStorage> StableSecureChannel<V, S> {
+    pub(crate) async fn create(
+        identity: &Identity<V>,
+        route: Route,
+        trust_policy: Arc<dyn TrustPolicy>,
+        storage: &S,
+    ) -> Result<Self> {
+        let identity = identity.async_try_clone().await?;
+        let storage = storage.async_try_clone().await?;
+        let address = Address::random_local();
+        let inbox = Address::random_local();
+
+        let channel = identity
+            .create_secure_channel_with_options(
+                route.clone(),
+                Arc::clone(&trust_policy),
+                &storage,
+                SecureChannelOptions::new().with_delivery_address(inbox.clone()),
+            )
+            .await?;
+        let channel = Arc::new(RwLock::new(channel));
+
+        let worker = StableChannelWorker {
+            address: address.clone(),
+            inbox: inbox.clone(),
+            channel: Arc::clone(&channel),
+        };
+        identity
+            .ctx
+            .start_worker(vec![address.clone(), inbox.clone()], worker)
+            .await?;
+        let drop_ctx = identity.ctx.new_detached(Address::random_local()).await?;
+
+        Ok(Self {
+            address,
+            inbox,
+            channel,
+            identity,
+            route,
+            trust_policy,
+            storage,
+            drop_ctx: Some(drop_ctx),
+        })
+    }
+
+    /// Address to send messages to the other end of the channel
+    pub fn address(&self) -> &Address {
+        &self.address
+    }
+
+    /// Address of the current secure channel, which changes when the
+    /// channel reconnects
+    pub fn channel(&self) -> Address {
+        self.channel.read().unwrap().clone()
+    }
+
+    /// Replace the secure channel with a new one over the same route
+    ///
+    /// The previous channel is stopped once the new one is established.
+    pub async fn reconnect(&self) -> Result<()> {
+        let channel = self
+            .identity
+            .create_secure_channel_with_options(
+                self.route.clone(),
+                Arc::clone(&self.trust_policy),
+                &self.storage,
+                SecureChannelOptions::new().with_delivery_address(self.inbox.clone()),
+            )
+            .await?;
+        let previous = core::mem::replace(&mut *self.channel.write().unwrap(), channel.clone());
+        debug!(
+            "Stable SecureChannel {} moved from {} to {}",
+            self.address, previous, channel
+        );
+        self.identity.stop_secure_channel(&previous).await
+    }
+
+    /// Stop the secure channel, then stop accepting messages at the
+    /// channel's address
+    pub async fn stop(mut self) -> Result<()> {
+        self.drop_ctx = None;
+        self.identity.stop_secure_channel(&self.channel()).await?;
+        self.identity.ctx.stop_worker(self.address.clone()).await
+    }
+}
+
+impl<V: IdentityVault, S: AuthenticatedStorage> Drop for StableSecureChannel<V, S> {
+    fn drop(&mut self) {
+        if let Some(ctx) = self.drop_ctx.take() {
+            let channel = self.channel();
+            let address = self.address.clone();
+            self.identity.channel_pool.forget(&channel);
+            // Drop can't await, stopping the workers is left to the runtime
+            let runtime = ctx.runtime().clone();
+            runtime.spawn(async move {
+                debug!("Stopping dropped Stable SecureChannel {}", address);
+                let _ = ctx.send(route![channel, CHANNEL_CLOSE_ADDRESS], ()).await;
+                let _ = ctx.stop_worker(address).await;
+            });
+        }
+    }
+}
+
+/// Forwards the messages sent to the stable address to the current
+/// channel, and the messages decrypted by the channel to their onward route
+/// with the stable address at the start of their return route
+struct StableChannelWorker {
+    address: Address,
+    inbox: Address,
+    channel: Arc<RwLock<Address>>,
+}
+
+#[async_trait]
+impl Worker for StableChannelWorker {
+    type Message = Any;
+    type Context = Context;
+
+    async fn handle_message(&mut self, ctx: &mut Context, msg: Routed<Any>) -> Result<()> {
+        let msg_addr = msg.msg_addr();
+        let mut local_msg = msg.into_local_message();
+        let transport = local_msg.transport_mut();
+        transport.onward_route.step()?;
+
+        if msg_addr == self.inbox {
+            transport
+                .return_route
+                .modify()
+                .pop_front()
+                .prepend(self.address.clone());
+        } else {
+            let channel = self.channel.read().unwrap().clone();
+            transport.onward_route.modify().prepend(channel);
+        }
+
+        ctx.forward(local_msg).await
+    }
+}
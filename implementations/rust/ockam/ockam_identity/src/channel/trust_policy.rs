@@ -1,8 +1,9 @@
 use crate::IdentityIdentifier;
+use core::fmt;
 use ockam_core::{
     async_trait,
-    compat::{boxed::Box, sync::Arc},
-    Result,
+    compat::{boxed::Box, string::String, sync::Arc},
+    deny, Address, Result, TransportType,
 };
 use serde::{Deserialize, Serialize};
 
@@ -18,21 +19,127 @@ mod trust_everyone_policy;
 pub use trust_everyone_policy::*;
 mod trust_public_key_policy;
 pub use trust_public_key_policy::*;
+mod trust_credential_policy;
+pub use trust_credential_policy::*;
+mod time_window_trust_policy;
+pub use time_window_trust_policy::*;
+mod reloadable_trust_policy;
+pub use reloadable_trust_policy::*;
+#[cfg(feature = "std")]
+mod file_allowlist_trust_policy;
+#[cfg(feature = "std")]
+pub use file_allowlist_trust_policy::*;
 
+mod mutual_auth_trust_policy;
+pub use mutual_auth_trust_policy::*;
+
+/// Side of the handshake played by a SecureChannel participant
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SecureChannelRole {
+    /// The side which created the channel
+    Initiator,
+    /// The side which accepted the channel on a listener
+    Responder,
+}
+
+/// Cipher suite negotiated by the key exchange of a SecureChannel
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum CipherSuite {
+    /// Noise XX with X25519, AES-256-GCM and SHA-256
+    NoiseXx25519AesGcmSha256,
+}
+
+impl fmt::Display for CipherSuite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CipherSuite::NoiseXx25519AesGcmSha256 => f.write_str("Noise_XX_25519_AESGCM_SHA256"),
+        }
+    }
+}
+
+/// What a [`TrustPolicy`] knows about a SecureChannel being established
+///
+/// Besides the identity of the other side, the context holds the channel
+/// parameters when they are known, so that policies can depend on them.
 #[derive(Clone, Serialize, Deserialize)]
 pub struct SecureChannelTrustInfo {
     their_identity_id: IdentityIdentifier,
+    #[serde(default)]
+    their_role: Option<SecureChannelRole>,
+    #[serde(default)]
+    cipher_suite: Option<CipherSuite>,
+    #[serde(default)]
+    local_address: Option<Address>,
+    #[serde(default)]
+    remote_address: Option<Address>,
 }
 
+/// Another name of [`SecureChannelTrustInfo`], the context given to
+/// [`TrustPolicy::check`]
+pub type TrustContext = SecureChannelTrustInfo;
+
 impl SecureChannelTrustInfo {
     pub fn their_identity_id(&self) -> &IdentityIdentifier {
         &self.their_identity_id
     }
+
+    /// Role of the other side of the channel, if known
+    pub fn their_role(&self) -> Option<SecureChannelRole> {
+        self.their_role
+    }
+
+    /// Cipher suite negotiated for the channel, if known
+    pub fn cipher_suite(&self) -> Option<CipherSuite> {
+        self.cipher_suite
+    }
+
+    /// Address of our end of the channel, which the other side sends to
+    pub fn local_address(&self) -> Option<&Address> {
+        self.local_address.as_ref()
+    }
+
+    /// First hop of the route to the other side, e.g. a TCP connection
+    pub fn remote_address(&self) -> Option<&Address> {
+        self.remote_address.as_ref()
+    }
+
+    /// Transport used to reach the other side, [`LOCAL`](ockam_core::LOCAL)
+    /// if it lives in the same node
+    pub fn transport(&self) -> Option<TransportType> {
+        self.remote_address.as_ref().map(Address::transport_type)
+    }
 }
 
 impl SecureChannelTrustInfo {
     pub fn new(their_identity_id: IdentityIdentifier) -> Self {
-        Self { their_identity_id }
+        Self {
+            their_identity_id,
+            their_role: None,
+            cipher_suite: None,
+            local_address: None,
+            remote_address: None,
+        }
+    }
+
+    pub fn with_their_role(mut self, their_role: SecureChannelRole) -> Self {
+        self.their_role = Some(their_role);
+        self
+    }
+
+    pub fn with_cipher_suite(mut self, cipher_suite: CipherSuite) -> Self {
+        self.cipher_suite = Some(cipher_suite);
+        self
+    }
+
+    pub fn with_local_address(mut self, local_address: Address) -> Self {
+        self.local_address = Some(local_address);
+        self
+    }
+
+    pub fn with_remote_address(mut self, remote_address: Address) -> Self {
+        self.remote_address = Some(remote_address);
+        self
     }
 }
 
@@ -40,6 +147,34 @@ impl SecureChannelTrustInfo {
 pub trait TrustPolicy: Send + Sync + 'static {
     async fn check(&self, trust_info: &SecureChannelTrustInfo) -> Result<bool>;
 
+    /// Whether to trust a peer which presented no identity, the one in
+    /// `trust_info` being [anonymous](IdentityIdentifier::anonymous)
+    ///
+    /// Anonymous peers are rejected unless a policy accepts them
+    /// explicitly, e.g. [`MutualAuthTrustPolicy`] for the side it doesn't
+    /// authenticate.
+    async fn check_anonymous(&self, _trust_info: &SecureChannelTrustInfo) -> Result<bool> {
+        deny()
+    }
+
+    /// The policy which a channel established now is checked against
+    ///
+    /// Policies which can be replaced, e.g. [`ReloadableTrustPolicy`],
+    /// return the one in use, so that replacing it later doesn't affect
+    /// the channel. Other policies return `None`.
+    fn snapshot(&self) -> Option<Arc<dyn TrustPolicy>> {
+        None
+    }
+
+    /// Key equal for the policies which trust the same identities
+    ///
+    /// The [pool](crate::Identity::enable_secure_channel_pool) only shares
+    /// a channel between policies with the same key. Policies which can't
+    /// tell return `None`, and their channels are not pooled.
+    fn pool_key(&self) -> Option<String> {
+        None
+    }
+
     fn and<O: TrustPolicy>(self, other: O) -> AllTrustPolicy<Self, O>
     where
         Self: Sized,
@@ -61,6 +196,18 @@ impl<T: TrustPolicy + ?Sized> TrustPolicy for Box<T> {
     async fn check(&self, trust_info: &SecureChannelTrustInfo) -> Result<bool> {
         T::check(&**self, trust_info).await
     }
+
+    async fn check_anonymous(&self, trust_info: &SecureChannelTrustInfo) -> Result<bool> {
+        T::check_anonymous(&**self, trust_info).await
+    }
+
+    fn snapshot(&self) -> Option<Arc<dyn TrustPolicy>> {
+        T::snapshot(&**self)
+    }
+
+    fn pool_key(&self) -> Option<String> {
+        T::pool_key(&**self)
+    }
 }
 
 #[async_trait]
@@ -68,4 +215,29 @@ impl<T: TrustPolicy + ?Sized> TrustPolicy for Arc<T> {
     async fn check(&self, trust_info: &SecureChannelTrustInfo) -> Result<bool> {
         T::check(&**self, trust_info).await
     }
+
+    async fn check_anonymous(&self, trust_info: &SecureChannelTrustInfo) -> Result<bool> {
+        T::check_anonymous(&**self, trust_info).await
+    }
+
+    fn snapshot(&self) -> Option<Arc<dyn TrustPolicy>> {
+        T::snapshot(&**self)
+    }
+
+    fn pool_key(&self) -> Option<String> {
+        T::pool_key(&**self)
+    }
+}
+
+/// Check `trust_info` with [`TrustPolicy::check_anonymous`] if the peer
+/// presented no identity, with [`TrustPolicy::check`] otherwise
+pub(crate) async fn check_trust_info(
+    trust_policy: &(impl TrustPolicy + ?Sized),
+    trust_info: &SecureChannelTrustInfo,
+) -> Result<bool> {
+    if trust_info.their_identity_id().is_anonymous() {
+        trust_policy.check_anonymous(trust_info).await
+    } else {
+        trust_policy.check(trust_info).await
+    }
 }
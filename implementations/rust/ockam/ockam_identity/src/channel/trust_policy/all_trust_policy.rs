@@ -1,5 +1,8 @@
 use crate::{SecureChannelTrustInfo, TrustPolicy};
-use ockam_core::{async_trait, compat::boxed::Box};
+use ockam_core::{
+    async_trait,
+    compat::{boxed::Box, format, string::String},
+};
 use ockam_core::{AsyncTryClone, Result};
 
 #[derive(AsyncTryClone)]
@@ -21,6 +24,17 @@ impl<F: TrustPolicy, S: TrustPolicy> TrustPolicy for AllTrustPolicy<F, S> {
     async fn check(&self, trust_info: &SecureChannelTrustInfo) -> Result<bool> {
         Ok(self.first.check(trust_info).await? && self.second.check(trust_info).await?)
     }
+
+    async fn check_anonymous(&self, trust_info: &SecureChannelTrustInfo) -> Result<bool> {
+        Ok(self.first.check_anonymous(trust_info).await?
+            && self.second.check_anonymous(trust_info).await?)
+    }
+
+    fn pool_key(&self) -> Option<String> {
+        let first = self.first.pool_key()?;
+        let second = self.second.pool_key()?;
+        Some(format!("all({}, {})", first, second))
+    }
 }
 
 #[cfg(test)]
@@ -1,5 +1,8 @@
 use crate::{SecureChannelTrustInfo, TrustPolicy};
-use ockam_core::{async_trait, compat::boxed::Box};
+use ockam_core::{
+    async_trait,
+    compat::{boxed::Box, format, string::String},
+};
 use ockam_core::{AsyncTryClone, Result};
 
 #[derive(AsyncTryClone)]
@@ -22,6 +25,17 @@ impl<F: TrustPolicy, S: TrustPolicy> TrustPolicy for AnyTrustPolicy<F, S> {
         // TODO: is the short circuit here a side channel?
         Ok(self.first.check(trust_info).await? || self.second.check(trust_info).await?)
     }
+
+    async fn check_anonymous(&self, trust_info: &SecureChannelTrustInfo) -> Result<bool> {
+        Ok(self.first.check_anonymous(trust_info).await?
+            || self.second.check_anonymous(trust_info).await?)
+    }
+
+    fn pool_key(&self) -> Option<String> {
+        let first = self.first.pool_key()?;
+        let second = self.second.pool_key()?;
+        Some(format!("any({}, {})", first, second))
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,179 @@
+use crate::{IdentityIdentifier, SecureChannelTrustInfo, TrustMultiIdentifiersPolicy, TrustPolicy};
+use ockam_core::compat::{boxed::Box, sync::Arc, vec::Vec};
+use ockam_core::errcode::{Kind, Origin};
+use ockam_core::{async_trait, Error, Result};
+use ockam_node::tokio::sync::Mutex;
+use ockam_node::tokio::task;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+struct Allowlist {
+    /// SHA-256 of the contents the identifiers were read from
+    hash: [u8; 32],
+    identifiers: Vec<IdentityIdentifier>,
+}
+
+/// A [`TrustPolicy`] trusting the identifiers listed in a file, which is
+/// read again whenever its contents change
+///
+/// The file holds one identifier per line, blank lines and lines starting
+/// with `#` are ignored. The file is read at every handshake, off the
+/// async runtime, and parsed again when the hash of its contents changed,
+/// so editing the file affects the handshakes which start afterwards,
+/// without restarting the node. When the file can't be read or holds an
+/// invalid identifier, the error is logged and the last valid list stays
+/// in use. Clones share the same list.
+#[derive(Clone)]
+pub struct FileAllowlistTrustPolicy {
+    path: Arc<PathBuf>,
+    allowlist: Arc<Mutex<Allowlist>>,
+}
+
+impl FileAllowlistTrustPolicy {
+    /// Trust the identifiers listed in the file at `path`, which must exist
+    /// and be valid
+    pub fn new(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let contents = std::fs::read(&path).map_err(map_io_err)?;
+        let identifiers = parse_allowlist(&contents)?;
+        Ok(Self {
+            path: Arc::new(path),
+            allowlist: Arc::new(Mutex::new(Allowlist {
+                hash: Sha256::digest(&contents).into(),
+                identifiers,
+            })),
+        })
+    }
+
+    /// Identifiers trusted by the next handshakes
+    pub async fn identifiers(&self) -> Vec<IdentityIdentifier> {
+        let mut allowlist = self.allowlist.lock().await;
+        self.reload_if_modified(&mut allowlist).await;
+        allowlist.identifiers.clone()
+    }
+
+    /// Parse the file again if its contents changed since it was last
+    /// parsed
+    async fn reload_if_modified(&self, allowlist: &mut Allowlist) {
+        let path = self.path.clone();
+        let read = task::spawn_blocking(move || std::fs::read(path.as_ref())).await;
+        let contents = match read
+            .map_err(|e| Error::new(Origin::Identity, Kind::Io, e))
+            .and_then(|r| r.map_err(map_io_err))
+        {
+            Ok(contents) => contents,
+            Err(e) => {
+                warn!(
+                    "Keeping the previous allowlist, {} can't be read: {}",
+                    self.path.display(),
+                    e
+                );
+                return;
+            }
+        };
+
+        let hash: [u8; 32] = Sha256::digest(&contents).into();
+        if allowlist.hash == hash {
+            return;
+        }
+        // An invalid file is only reported once, until it's modified again
+        allowlist.hash = hash;
+        match parse_allowlist(&contents) {
+            Ok(identifiers) => {
+                debug!(
+                    "Reloaded {} identifiers from {}",
+                    identifiers.len(),
+                    self.path.display()
+                );
+                allowlist.identifiers = identifiers;
+            }
+            Err(e) => warn!(
+                "Keeping the previous allowlist, {} is invalid: {}",
+                self.path.display(),
+                e
+            ),
+        }
+    }
+}
+
+#[async_trait]
+impl TrustPolicy for FileAllowlistTrustPolicy {
+    async fn check(&self, trust_info: &SecureChannelTrustInfo) -> Result<bool> {
+        TrustMultiIdentifiersPolicy::new(self.identifiers().await)
+            .check(trust_info)
+            .await
+    }
+}
+
+fn map_io_err(err: std::io::Error) -> Error {
+    Error::new(Origin::Identity, Kind::Io, err)
+}
+
+fn parse_allowlist(contents: &[u8]) -> Result<Vec<IdentityIdentifier>> {
+    let contents = core::str::from_utf8(contents).map_err(|e| {
+        Error::new(
+            Origin::Identity,
+            Kind::Invalid,
+            format!("the allowlist is not UTF-8: {}", e),
+        )
+    })?;
+    contents
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(number, line)| {
+            IdentityIdentifier::try_from(line).map_err(|_| {
+                Error::new(
+                    Origin::Identity,
+                    Kind::Invalid,
+                    format!("invalid identifier on line {}: {}", number, line),
+                )
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+
+    #[tokio::test]
+    async fn test() {
+        let alice = IdentityIdentifier::random();
+        let bob = IdentityIdentifier::random();
+        let path = std::env::temp_dir().join(format!("allowlist-{}", IdentityIdentifier::random()));
+
+        fs::write(&path, format!("# Trusted peers\n\n{}\n", alice)).unwrap();
+        let policy = FileAllowlistTrustPolicy::new(&path).unwrap();
+        assert_eq!(policy.identifiers().await, vec![alice.clone()]);
+        assert!(!policy
+            .check(&SecureChannelTrustInfo::new(bob.clone()))
+            .await
+            .unwrap());
+
+        fs::write(&path, format!("{}\n{}\n", alice, bob)).unwrap();
+        assert!(policy
+            .check(&SecureChannelTrustInfo::new(bob.clone()))
+            .await
+            .unwrap());
+
+        // Contents of the same size written within the same second are
+        // read too
+        let carol = IdentityIdentifier::random();
+        fs::write(&path, format!("{}\n{}\n", alice, carol)).unwrap();
+        assert_eq!(policy.identifiers().await, vec![alice.clone(), carol]);
+        fs::write(&path, format!("{}\n{}\n", alice, bob)).unwrap();
+        assert_eq!(policy.identifiers().await, vec![alice.clone(), bob.clone()]);
+
+        // An invalid file leaves the previous list in use
+        fs::write(&path, format!("{}\nnot an identifier\n", alice)).unwrap();
+        assert_eq!(policy.identifiers().await, vec![alice.clone(), bob.clone()]);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(policy.identifiers().await, vec![alice, bob]);
+
+        assert!(FileAllowlistTrustPolicy::new(&path).is_err());
+    }
+}
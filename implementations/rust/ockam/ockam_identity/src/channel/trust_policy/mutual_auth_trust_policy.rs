@@ -0,0 +1,179 @@
+use crate::{SecureChannelRole, SecureChannelTrustInfo, TrustPolicy};
+use ockam_core::{allow, deny, Result};
+use ockam_core::{async_trait, compat::boxed::Box};
+
+/// Which sides of a SecureChannel must be authenticated
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MutualAuth {
+    /// Only the initiator is verified, the responder may stay anonymous
+    InitiatorOnly,
+    /// Only the responder is verified, the initiator may stay anonymous
+    ResponderOnly,
+    /// Both sides are verified
+    Mutual,
+}
+
+impl MutualAuth {
+    /// Whether the side playing `role` must be authenticated
+    pub fn requires(&self, role: SecureChannelRole) -> bool {
+        matches!(
+            (self, role),
+            (MutualAuth::Mutual, _)
+                | (MutualAuth::InitiatorOnly, SecureChannelRole::Initiator)
+                | (MutualAuth::ResponderOnly, SecureChannelRole::Responder)
+        )
+    }
+}
+
+/// Applies a [`TrustPolicy`] only to the sides of the channel which must
+/// be authenticated
+///
+/// Both ends of a channel are expected to agree on the [`MutualAuth`]
+/// mode: with [`MutualAuth::InitiatorOnly`] a listener checks its
+/// initiators, while initiators accept any responder, including one which
+/// presents no identity, see
+/// [`SecureChannelListenerOptions::anonymous`](crate::SecureChannelListenerOptions::anonymous).
+/// A side which must be authenticated and presents no identity is
+/// rejected. The peer's role comes from
+/// [`SecureChannelTrustInfo::their_role`], the policy is enforced if it's
+/// unknown.
+#[derive(Clone)]
+pub struct MutualAuthTrustPolicy<P: TrustPolicy> {
+    mode: MutualAuth,
+    policy: P,
+}
+
+impl<P: TrustPolicy> MutualAuthTrustPolicy<P> {
+    pub fn new(mode: MutualAuth, policy: P) -> Self {
+        Self { mode, policy }
+    }
+
+    /// Only authenticate initiators with `policy`
+    pub fn initiator_only(policy: P) -> Self {
+        Self::new(MutualAuth::InitiatorOnly, policy)
+    }
+
+    /// Only authenticate responders with `policy`
+    pub fn responder_only(policy: P) -> Self {
+        Self::new(MutualAuth::ResponderOnly, policy)
+    }
+
+    /// Authenticate both sides with `policy`
+    pub fn mutual(policy: P) -> Self {
+        Self::new(MutualAuth::Mutual, policy)
+    }
+
+    pub fn mode(&self) -> MutualAuth {
+        self.mode
+    }
+}
+
+#[async_trait]
+impl<P: TrustPolicy> TrustPolicy for MutualAuthTrustPolicy<P> {
+    async fn check(&self, trust_info: &SecureChannelTrustInfo) -> Result<bool> {
+        match trust_info.their_role() {
+            Some(role) if !self.mode.requires(role) => allow(),
+            _ => self.policy.check(trust_info).await,
+        }
+    }
+
+    async fn check_anonymous(&self, trust_info: &SecureChannelTrustInfo) -> Result<bool> {
+        match trust_info.their_role() {
+            Some(role) if !self.mode.requires(role) => allow(),
+            _ => deny(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::channel::trust_policy::check_trust_info;
+    use crate::{IdentityIdentifier, TrustEveryonePolicy};
+
+    #[derive(Clone)]
+    struct DenyAll;
+
+    #[async_trait]
+    impl TrustPolicy for DenyAll {
+        async fn check(&self, _trust_info: &SecureChannelTrustInfo) -> Result<bool> {
+            Ok(false)
+        }
+    }
+
+    fn trust_info(
+        their_identity_id: IdentityIdentifier,
+        role: Option<SecureChannelRole>,
+    ) -> SecureChannelTrustInfo {
+        let trust_info = SecureChannelTrustInfo::new(their_identity_id);
+        match role {
+            Some(role) => trust_info.with_their_role(role),
+            None => trust_info,
+        }
+    }
+
+    async fn accepts(policy: &impl TrustPolicy, role: Option<SecureChannelRole>) -> bool {
+        let trust_info = trust_info(IdentityIdentifier::random(), role);
+        policy.check(&trust_info).await.unwrap()
+    }
+
+    /// Whether `policy` accepts a peer playing `role` which presented no
+    /// identity, even if it trusts everyone
+    async fn accepts_anonymous(
+        policy: impl Fn(TrustEveryonePolicy) -> MutualAuthTrustPolicy<TrustEveryonePolicy>,
+        role: Option<SecureChannelRole>,
+    ) -> bool {
+        let trust_info = trust_info(IdentityIdentifier::anonymous(&[1; 32]), role);
+        check_trust_info(&policy(TrustEveryonePolicy), &trust_info)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_initiator_only() {
+        let policy = MutualAuthTrustPolicy::initiator_only(DenyAll);
+        assert!(!accepts(&policy, Some(SecureChannelRole::Initiator)).await);
+        assert!(accepts(&policy, Some(SecureChannelRole::Responder)).await);
+        assert!(!accepts(&policy, None).await);
+
+        let policy = MutualAuthTrustPolicy::initiator_only;
+        assert!(!accepts_anonymous(policy, Some(SecureChannelRole::Initiator)).await);
+        assert!(accepts_anonymous(policy, Some(SecureChannelRole::Responder)).await);
+        assert!(!accepts_anonymous(policy, None).await);
+    }
+
+    #[tokio::test]
+    async fn test_responder_only() {
+        let policy = MutualAuthTrustPolicy::responder_only(DenyAll);
+        assert!(accepts(&policy, Some(SecureChannelRole::Initiator)).await);
+        assert!(!accepts(&policy, Some(SecureChannelRole::Responder)).await);
+        assert!(!accepts(&policy, None).await);
+
+        let policy = MutualAuthTrustPolicy::responder_only;
+        assert!(accepts_anonymous(policy, Some(SecureChannelRole::Initiator)).await);
+        assert!(!accepts_anonymous(policy, Some(SecureChannelRole::Responder)).await);
+        assert!(!accepts_anonymous(policy, None).await);
+    }
+
+    #[tokio::test]
+    async fn test_mutual() {
+        let policy = MutualAuthTrustPolicy::mutual(DenyAll);
+        assert!(!accepts(&policy, Some(SecureChannelRole::Initiator)).await);
+        assert!(!accepts(&policy, Some(SecureChannelRole::Responder)).await);
+
+        let policy = MutualAuthTrustPolicy::mutual;
+        assert!(!accepts_anonymous(policy, Some(SecureChannelRole::Initiator)).await);
+        assert!(!accepts_anonymous(policy, Some(SecureChannelRole::Responder)).await);
+    }
+
+    #[tokio::test]
+    async fn test_other_policies_reject_anonymous_peers() {
+        let trust_info = trust_info(
+            IdentityIdentifier::anonymous(&[1; 32]),
+            Some(SecureChannelRole::Initiator),
+        );
+        assert!(!check_trust_info(&TrustEveryonePolicy, &trust_info)
+            .await
+            .unwrap());
+    }
+}
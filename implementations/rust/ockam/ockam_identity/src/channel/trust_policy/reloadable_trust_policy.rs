@@ -0,0 +1,82 @@
+use crate::{SecureChannelTrustInfo, TrustPolicy};
+use ockam_core::compat::{
+    boxed::Box,
+    sync::{Arc, RwLock},
+};
+use ockam_core::{async_trait, Result};
+
+/// A [`TrustPolicy`] which can be replaced while it is in use
+///
+/// Clones share the same policy, so a listener created with a clone checks
+/// the handshakes which start after [`ReloadableTrustPolicy::reload`]
+/// against the new policy. Channels which were already established keep
+/// the policy they were established with.
+#[derive(Clone)]
+pub struct ReloadableTrustPolicy {
+    current: Arc<RwLock<Arc<dyn TrustPolicy>>>,
+}
+
+impl ReloadableTrustPolicy {
+    pub fn new(trust_policy: impl TrustPolicy) -> Self {
+        Self {
+            current: Arc::new(RwLock::new(Arc::new(trust_policy))),
+        }
+    }
+
+    /// Check the next handshakes against `trust_policy`
+    pub fn reload(&self, trust_policy: impl TrustPolicy) {
+        *self.current.write().unwrap() = Arc::new(trust_policy);
+    }
+}
+
+#[async_trait]
+impl TrustPolicy for ReloadableTrustPolicy {
+    async fn check(&self, trust_info: &SecureChannelTrustInfo) -> Result<bool> {
+        let current = Arc::clone(&self.current.read().unwrap());
+        current.check(trust_info).await
+    }
+
+    async fn check_anonymous(&self, trust_info: &SecureChannelTrustInfo) -> Result<bool> {
+        let current = Arc::clone(&self.current.read().unwrap());
+        current.check_anonymous(trust_info).await
+    }
+
+    fn snapshot(&self) -> Option<Arc<dyn TrustPolicy>> {
+        Some(Arc::clone(&self.current.read().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        IdentityIdentifier, ReloadableTrustPolicy, SecureChannelTrustInfo, TrustIdentifierPolicy,
+        TrustPolicy,
+    };
+
+    #[tokio::test]
+    async fn test() {
+        let alice = IdentityIdentifier::random();
+        let bob = IdentityIdentifier::random();
+
+        let policy = ReloadableTrustPolicy::new(TrustIdentifierPolicy::new(alice.clone()));
+        let listener_policy = policy.clone();
+        assert!(listener_policy
+            .check(&SecureChannelTrustInfo::new(alice.clone()))
+            .await
+            .unwrap());
+        assert!(!listener_policy
+            .check(&SecureChannelTrustInfo::new(bob.clone()))
+            .await
+            .unwrap());
+
+        policy.reload(TrustIdentifierPolicy::new(bob.clone()));
+        assert!(!listener_policy
+            .check(&SecureChannelTrustInfo::new(alice))
+            .await
+            .unwrap());
+        assert!(listener_policy
+            .check(&SecureChannelTrustInfo::new(bob))
+            .await
+            .unwrap());
+    }
+}
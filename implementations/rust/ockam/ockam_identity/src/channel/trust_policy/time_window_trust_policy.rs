@@ -0,0 +1,342 @@
+use crate::credential::{Clock, SystemClock, Timestamp};
+use crate::{IdentityError, SecureChannelTrustInfo, TrustPolicy};
+use core::time::Duration;
+use ockam_core::Result;
+use ockam_core::{async_trait, compat::boxed::Box};
+use tracing::debug;
+
+const SECS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Offset of a timezone from UTC
+///
+/// Offsets are fixed: a timezone with daylight saving time has a different
+/// offset in summer and in winter, and a window must pick one of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UtcOffset {
+    secs: i32,
+}
+
+impl UtcOffset {
+    pub const UTC: UtcOffset = UtcOffset { secs: 0 };
+
+    /// An offset of `minutes` east of UTC, at most 18 hours either way
+    pub fn from_minutes(minutes: i32) -> Result<Self> {
+        if minutes.abs() > 18 * 60 {
+            return Err(IdentityError::InvalidTimeWindow.into());
+        }
+        Ok(Self { secs: minutes * 60 })
+    }
+
+    /// An offset of `hours` east of UTC
+    pub fn from_hours(hours: i32) -> Result<Self> {
+        Self::from_minutes(hours.saturating_mul(60))
+    }
+}
+
+/// Time of the day, with a minute precision
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TimeOfDay {
+    secs: i64,
+}
+
+impl TimeOfDay {
+    pub fn new(hour: u8, minute: u8) -> Result<Self> {
+        if hour > 23 || minute > 59 {
+            return Err(IdentityError::InvalidTimeWindow.into());
+        }
+        Ok(Self {
+            secs: i64::from(hour) * 3600 + i64::from(minute) * 60,
+        })
+    }
+}
+
+/// Day of the week
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Weekday {
+    const ALL: [Weekday; 7] = [
+        Weekday::Monday,
+        Weekday::Tuesday,
+        Weekday::Wednesday,
+        Weekday::Thursday,
+        Weekday::Friday,
+        Weekday::Saturday,
+        Weekday::Sunday,
+    ];
+
+    /// Day of the week of the `days`th day since 1970-01-01, a Thursday
+    fn from_days_since_epoch(days: i64) -> Self {
+        Self::ALL[(days + 3).rem_euclid(7) as usize]
+    }
+}
+
+/// Set of days of the week
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Weekdays(u8);
+
+impl Weekdays {
+    pub const ALL: Weekdays = Weekdays(0b111_1111);
+    /// Monday to Friday
+    pub const WORKDAYS: Weekdays = Weekdays(0b001_1111);
+
+    pub fn new(days: &[Weekday]) -> Self {
+        Self(days.iter().fold(0, |set, day| set | Self::bit(*day)))
+    }
+
+    pub fn contains(&self, day: Weekday) -> bool {
+        self.0 & Self::bit(day) != 0
+    }
+
+    fn bit(day: Weekday) -> u8 {
+        1 << day as u8
+    }
+}
+
+/// When a [`TimeWindowTrustPolicy`] admits peers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeWindow {
+    /// From `start`, included, to `end`, excluded
+    Between { start: Timestamp, end: Timestamp },
+    /// Every day from `start`, included, to `end`, excluded, in the
+    /// timezone at `offset` from UTC. The window runs past midnight if
+    /// `end` is before `start`. `days` are the days of the week, in that
+    /// timezone, the window is open on.
+    Daily {
+        start: TimeOfDay,
+        end: TimeOfDay,
+        offset: UtcOffset,
+        days: Weekdays,
+    },
+}
+
+impl TimeWindow {
+    /// The `duration` following `since`, e.g. an enrollment
+    pub fn following(since: Timestamp, duration: Duration) -> Self {
+        let end = u64::from(since).saturating_add(duration.as_secs());
+        TimeWindow::Between {
+            start: since,
+            end: end.into(),
+        }
+    }
+
+    /// Every day from `start` to `end` in the timezone at `offset` from UTC
+    pub fn daily(start: TimeOfDay, end: TimeOfDay, offset: UtcOffset) -> Self {
+        TimeWindow::Daily {
+            start,
+            end,
+            offset,
+            days: Weekdays::ALL,
+        }
+    }
+
+    /// Restrict a daily window to some days of the week
+    pub fn on(self, weekdays: Weekdays) -> Self {
+        match self {
+            TimeWindow::Daily {
+                start, end, offset, ..
+            } => TimeWindow::Daily {
+                start,
+                end,
+                offset,
+                days: weekdays,
+            },
+            window => window,
+        }
+    }
+
+    /// Whether `now` is inside the window
+    pub fn contains(&self, now: Timestamp) -> bool {
+        match *self {
+            TimeWindow::Between { start, end } => start <= now && now < end,
+            TimeWindow::Daily {
+                start,
+                end,
+                offset,
+                days,
+            } => {
+                let local = u64::from(now) as i64 + i64::from(offset.secs);
+                let day = local.div_euclid(SECS_PER_DAY);
+                let time = local.rem_euclid(SECS_PER_DAY);
+                let inside = if start.secs <= end.secs {
+                    start.secs <= time && time < end.secs
+                } else {
+                    start.secs <= time || time < end.secs
+                };
+                inside && days.contains(Weekday::from_days_since_epoch(day))
+            }
+        }
+    }
+}
+
+/// Trust the peers trusted by another policy, only inside a [`TimeWindow`]
+///
+/// Peers are rejected if the current time is unknown.
+pub struct TimeWindowTrustPolicy<P: TrustPolicy, C: Clock = SystemClock> {
+    policy: P,
+    window: TimeWindow,
+    clock: C,
+}
+
+impl<P: TrustPolicy> TimeWindowTrustPolicy<P> {
+    pub fn new(policy: P, window: TimeWindow) -> Self {
+        Self::with_clock(policy, window, SystemClock)
+    }
+}
+
+impl<P: TrustPolicy, C: Clock> TimeWindowTrustPolicy<P, C> {
+    /// Read the current time from `clock` rather than from the system
+    pub fn with_clock(policy: P, window: TimeWindow, clock: C) -> Self {
+        Self {
+            policy,
+            window,
+            clock,
+        }
+    }
+}
+
+#[async_trait]
+impl<P: TrustPolicy, C: Clock> TrustPolicy for TimeWindowTrustPolicy<P, C> {
+    async fn check(&self, trust_info: &SecureChannelTrustInfo) -> Result<bool> {
+        if !self.in_window(trust_info) {
+            return Ok(false);
+        }
+        self.policy.check(trust_info).await
+    }
+
+    async fn check_anonymous(&self, trust_info: &SecureChannelTrustInfo) -> Result<bool> {
+        if !self.in_window(trust_info) {
+            return Ok(false);
+        }
+        self.policy.check_anonymous(trust_info).await
+    }
+}
+
+impl<P: TrustPolicy, C: Clock> TimeWindowTrustPolicy<P, C> {
+    fn in_window(&self, trust_info: &SecureChannelTrustInfo) -> bool {
+        match self.clock.now() {
+            Some(now) if self.window.contains(now) => true,
+            now => {
+                debug!(
+                    "Rejecting {} outside of trust window {:?} at {:?}",
+                    trust_info.their_identity_id(),
+                    self.window,
+                    now
+                );
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{IdentityIdentifier, TrustEveryonePolicy};
+    use core::sync::atomic::{AtomicU64, Ordering};
+    use ockam_core::compat::sync::Arc;
+
+    #[derive(Clone, Default)]
+    struct MockClock(Arc<AtomicU64>);
+
+    impl MockClock {
+        fn set(&self, secs: u64) {
+            self.0.store(secs, Ordering::Relaxed)
+        }
+    }
+
+    impl Clock for MockClock {
+        fn now(&self) -> Option<Timestamp> {
+            Some(self.0.load(Ordering::Relaxed).into())
+        }
+    }
+
+    // Monday 2022-10-03 00:00:00 UTC
+    const MONDAY: u64 = 1_664_755_200;
+    const HOUR: u64 = 3600;
+
+    #[tokio::test]
+    async fn business_hours() -> Result<()> {
+        let clock = MockClock::default();
+        let window = TimeWindow::daily(
+            TimeOfDay::new(9, 0)?,
+            TimeOfDay::new(17, 30)?,
+            UtcOffset::from_hours(2)?,
+        )
+        .on(Weekdays::WORKDAYS);
+        let policy = TimeWindowTrustPolicy::with_clock(TrustEveryonePolicy, window, clock.clone());
+        let trust_info = SecureChannelTrustInfo::new(IdentityIdentifier::random());
+
+        // 9:00 and 17:29 at UTC+2 on Monday
+        for now in [MONDAY + 7 * HOUR, MONDAY + 15 * HOUR + 29 * 60] {
+            clock.set(now);
+            assert!(policy.check(&trust_info).await?);
+        }
+
+        // 8:59 and 17:30 at UTC+2, 10:00 at UTC+2 on Saturday
+        for now in [
+            MONDAY + 7 * HOUR - 60,
+            MONDAY + 15 * HOUR + 30 * 60,
+            MONDAY + 5 * 24 * HOUR + 8 * HOUR,
+        ] {
+            clock.set(now);
+            assert!(!policy.check(&trust_info).await?);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn window_past_midnight_uses_local_weekday() -> Result<()> {
+        let clock = MockClock::default();
+        let window = TimeWindow::daily(
+            TimeOfDay::new(22, 0)?,
+            TimeOfDay::new(2, 0)?,
+            UtcOffset::from_hours(-5)?,
+        )
+        .on(Weekdays::new(&[Weekday::Sunday]));
+        let policy = TimeWindowTrustPolicy::with_clock(TrustEveryonePolicy, window, clock.clone());
+        let trust_info = SecureChannelTrustInfo::new(IdentityIdentifier::random());
+
+        // Monday 01:00 UTC is Sunday 20:00 at UTC-5, 03:00 UTC is 22:00
+        clock.set(MONDAY + HOUR);
+        assert!(!policy.check(&trust_info).await?);
+        clock.set(MONDAY + 3 * HOUR);
+        assert!(policy.check(&trust_info).await?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn following_an_event() -> Result<()> {
+        let clock = MockClock::default();
+        let window = TimeWindow::following(MONDAY.into(), Duration::from_secs(10 * 60));
+        let policy = TimeWindowTrustPolicy::with_clock(TrustEveryonePolicy, window, clock.clone());
+        let trust_info = SecureChannelTrustInfo::new(IdentityIdentifier::random());
+
+        clock.set(MONDAY - 1);
+        assert!(!policy.check(&trust_info).await?);
+        clock.set(MONDAY + 9 * 60);
+        assert!(policy.check(&trust_info).await?);
+        clock.set(MONDAY + 10 * 60);
+        assert!(!policy.check(&trust_info).await?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_windows_are_rejected() {
+        assert!(TimeOfDay::new(24, 0).is_err());
+        assert!(TimeOfDay::new(12, 60).is_err());
+        assert!(UtcOffset::from_hours(19).is_err());
+        assert!(UtcOffset::from_minutes(-18 * 60).is_ok());
+    }
+}
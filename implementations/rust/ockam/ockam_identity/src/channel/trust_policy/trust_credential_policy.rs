@@ -0,0 +1,31 @@
+use crate::authenticated_storage::AuthenticatedStorage;
+use crate::credential::AttributesStorageUtils;
+use crate::{SecureChannelTrustInfo, TrustPolicy};
+use ockam_core::Result;
+use ockam_core::{async_trait, compat::boxed::Box};
+
+/// Trust identities that presented a valid credential
+///
+/// The credential must have been verified and its attributes stored in
+/// `storage` before the secure channel is created, e.g. by a previous
+/// credential exchange. Expired attributes aren't trusted.
+#[derive(Clone)]
+pub struct TrustCredentialPolicy<S: AuthenticatedStorage> {
+    storage: S,
+}
+
+impl<S: AuthenticatedStorage> TrustCredentialPolicy<S> {
+    pub fn new(storage: S) -> Self {
+        Self { storage }
+    }
+}
+
+#[async_trait]
+impl<S: AuthenticatedStorage> TrustPolicy for TrustCredentialPolicy<S> {
+    async fn check(&self, trust_info: &SecureChannelTrustInfo) -> Result<bool> {
+        let attributes =
+            AttributesStorageUtils::get_attributes(trust_info.their_identity_id(), &self.storage)
+                .await?;
+        Ok(attributes.is_some())
+    }
+}
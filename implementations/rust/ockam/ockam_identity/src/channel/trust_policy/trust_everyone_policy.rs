@@ -1,6 +1,9 @@
 use crate::{SecureChannelTrustInfo, TrustPolicy};
 use ockam_core::{allow, Result};
-use ockam_core::{async_trait, compat::boxed::Box};
+use ockam_core::{
+    async_trait,
+    compat::{boxed::Box, string::String},
+};
 
 #[derive(Clone)]
 pub struct TrustEveryonePolicy;
@@ -10,4 +13,8 @@ impl TrustPolicy for TrustEveryonePolicy {
     async fn check(&self, _trust_info: &SecureChannelTrustInfo) -> Result<bool> {
         allow()
     }
+
+    fn pool_key(&self) -> Option<String> {
+        Some("everyone".into())
+    }
 }
@@ -1,6 +1,9 @@
 use crate::{IdentityIdentifier, SecureChannelTrustInfo, TrustPolicy};
 use ockam_core::Result;
-use ockam_core::{async_trait, compat::boxed::Box};
+use ockam_core::{
+    async_trait,
+    compat::{boxed::Box, format, string::String},
+};
 
 #[derive(Clone)]
 pub struct TrustIdentifierPolicy {
@@ -18,4 +21,8 @@ impl TrustPolicy for TrustIdentifierPolicy {
     async fn check(&self, trust_info: &SecureChannelTrustInfo) -> Result<bool> {
         Ok(trust_info.their_identity_id == self.their_identity_id)
     }
+
+    fn pool_key(&self) -> Option<String> {
+        Some(format!("identifier({})", self.their_identity_id))
+    }
 }
@@ -1,7 +1,12 @@
 use crate::{IdentityIdentifier, SecureChannelTrustInfo, TrustPolicy};
 use ockam_core::{
     async_trait,
-    compat::{boxed::Box, vec::Vec},
+    compat::{
+        boxed::Box,
+        format,
+        string::{String, ToString},
+        vec::Vec,
+    },
     Result,
 };
 
@@ -29,4 +34,12 @@ impl TrustPolicy for TrustMultiIdentifiersPolicy {
     async fn check(&self, trust_info: &SecureChannelTrustInfo) -> Result<bool> {
         Ok(self.contains(trust_info.their_identity_id()))
     }
+
+    fn pool_key(&self) -> Option<String> {
+        let mut identity_ids: Vec<String> =
+            self.identity_ids.iter().map(|id| id.to_string()).collect();
+        identity_ids.sort();
+        identity_ids.dedup();
+        Some(format!("identifiers({})", identity_ids.join(",")))
+    }
 }
@@ -0,0 +1,113 @@
+use crate::authenticated_storage::{
+    AuthenticatedStorage, AuthenticatedStorageEvent, AuthenticatedStorageListener,
+};
+use crate::{SecureChannelTrustInfo, TrustPolicy};
+use ockam_core::async_trait;
+use ockam_core::compat::{
+    boxed::Box,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+use ockam_core::{Address, Processor, Result};
+use ockam_node::channel_types::{message_channel, MessageReceiver, MessageSender};
+use ockam_node::tokio::sync::mpsc::error::TrySendError;
+use ockam_node::Context;
+use tracing::{debug, warn};
+
+/// Passes storage changes concerning the channel's peer on to its [`TrustPolicyWatcher`]
+struct PeerStorageListener {
+    their_identity_id: String,
+    sender: MessageSender<AuthenticatedStorageEvent>,
+}
+
+impl AuthenticatedStorageListener for PeerStorageListener {
+    fn on_event(&self, event: &AuthenticatedStorageEvent) -> bool {
+        if event.id() != self.their_identity_id {
+            return !self.sender.is_closed();
+        }
+        match self.sender.try_send(event.clone()) {
+            // A full queue already holds a pending re-check for this peer
+            Ok(()) | Err(TrySendError::Full(_)) => true,
+            // The watcher is gone, so is the channel
+            Err(TrySendError::Closed(_)) => false,
+        }
+    }
+}
+
+/// Re-evaluates a channel's [`TrustPolicy`] whenever the storage entries of
+/// its peer change, and closes the channel once the peer is no longer trusted
+pub(crate) struct TrustPolicyWatcher {
+    trust_info: SecureChannelTrustInfo,
+    trust_policy: Arc<dyn TrustPolicy>,
+    channel_addresses: Vec<Address>,
+    receiver: MessageReceiver<AuthenticatedStorageEvent>,
+}
+
+impl TrustPolicyWatcher {
+    /// Start a watcher for the channel made of `channel_addresses`. Returns
+    /// `None` if `storage` doesn't support subscriptions.
+    pub async fn create(
+        ctx: &Context,
+        storage: &impl AuthenticatedStorage,
+        trust_info: SecureChannelTrustInfo,
+        trust_policy: Arc<dyn TrustPolicy>,
+        channel_addresses: Vec<Address>,
+    ) -> Result<Option<Address>> {
+        let their_identity_id = trust_info.their_identity_id();
+        let (sender, receiver) = message_channel();
+        let listener = PeerStorageListener {
+            their_identity_id: their_identity_id.to_string(),
+            sender,
+        };
+        if let Err(e) = storage.subscribe(Arc::new(listener)) {
+            debug!(
+                "Not watching trust policy for SecureChannel with {}: {}",
+                their_identity_id, e
+            );
+            return Ok(None);
+        }
+
+        let address = Address::random_local();
+        let watcher = Self {
+            trust_info,
+            trust_policy,
+            channel_addresses,
+            receiver,
+        };
+        ctx.start_processor(address.clone(), watcher).await?;
+
+        Ok(Some(address))
+    }
+}
+
+#[async_trait]
+impl Processor for TrustPolicyWatcher {
+    type Context = Context;
+
+    async fn process(&mut self, ctx: &mut Self::Context) -> Result<bool> {
+        let event = match self.receiver.recv().await {
+            Some(event) => event,
+            None => return Ok(false),
+        };
+        debug!(
+            "Re-checking trust policy for SecureChannel with {} after {:?}",
+            self.trust_info.their_identity_id(),
+            event
+        );
+
+        if self.trust_policy.check(&self.trust_info).await? {
+            return Ok(true);
+        }
+
+        warn!(
+            "Closing SecureChannel with {}: trust policy no longer satisfied",
+            self.trust_info.their_identity_id()
+        );
+        for address in &self.channel_addresses {
+            let _ = ctx.stop_worker(address.clone()).await;
+        }
+
+        Ok(false)
+    }
+}
@@ -1,5 +1,6 @@
 #![allow(missing_docs)]
 
+mod bundle;
 mod identity;
 mod public_identity;
 mod storage_utils;
@@ -7,6 +8,7 @@ mod worker;
 
 pub mod access_control;
 
+pub use bundle::*;
 pub use storage_utils::*;
 
 use crate::IdentityIdentifier;
@@ -174,6 +176,9 @@ impl<'a> CredentialData<'a, Unverified> {
     pub fn unverfied_key_label(&self) -> &str {
         &self.issuer_key_label
     }
+    pub fn unverified_expires_at(&self) -> Timestamp {
+        self.expires
+    }
 }
 
 impl<'a, 'b: 'a> TryFrom<&'b Credential<'a>> for CredentialData<'a, Unverified> {
@@ -268,6 +273,28 @@ impl From<Timestamp> for u64 {
     }
 }
 
+impl From<u64> for Timestamp {
+    fn from(secs: u64) -> Self {
+        Timestamp(secs)
+    }
+}
+
+/// Source of the current time, which tests can replace
+pub trait Clock: Send + Sync + 'static {
+    /// The current time, if it is known
+    fn now(&self) -> Option<Timestamp>;
+}
+
+/// [`Clock`] reading the system time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Option<Timestamp> {
+        Timestamp::now()
+    }
+}
+
 /// A schema identifier allows discriminate sets of credential attributes.
 #[derive(Debug, Clone, Copy, Encode, Decode, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[cbor(transparent)]
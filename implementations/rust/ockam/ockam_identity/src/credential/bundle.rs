@@ -0,0 +1,38 @@
+use crate::credential::Credential;
+use crate::PublicIdentity;
+use minicbor::{Decode, Encode};
+use ockam_core::compat::vec::Vec;
+use ockam_core::{CowBytes, Result};
+
+/// A peer's identity shipped together with a credential an authority
+/// issued for it, see [`crate::Identity::import_bundle`].
+#[derive(Clone, Debug, Decode, Encode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct IdentityBundle<'a> {
+    /// Exported change history of the identity.
+    #[b(1)] identity: CowBytes<'a>,
+    /// Credential attesting the identity's attributes.
+    #[b(2)] credential: Credential<'a>,
+}
+
+impl<'a> IdentityBundle<'a> {
+    pub fn new(identity: &PublicIdentity, credential: Credential<'a>) -> Result<Self> {
+        Ok(Self {
+            identity: CowBytes(identity.export()?.into()),
+            credential,
+        })
+    }
+
+    pub fn identity(&self) -> &[u8] {
+        &self.identity
+    }
+
+    pub fn credential(&self) -> &Credential<'a> {
+        &self.credential
+    }
+
+    pub fn to_vec(&self) -> Result<Vec<u8>> {
+        Ok(minicbor::to_vec(self)?)
+    }
+}
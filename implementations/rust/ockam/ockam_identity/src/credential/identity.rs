@@ -1,8 +1,8 @@
 use crate::authenticated_storage::AuthenticatedStorage;
 use crate::credential::worker::CredentialExchangeWorker;
 use crate::credential::{
-    AttributesEntry, AttributesStorageUtils, Credential, CredentialBuilder, CredentialData,
-    Timestamp, Unverified, Verified,
+    AttributeMap, AttributesEntry, AttributesStorageUtils, Credential, CredentialBuilder,
+    CredentialData, IdentityBundle, Timestamp, Unverified, Verified,
 };
 use crate::{
     Identity, IdentityError, IdentityIdentifier, IdentitySecureChannelLocalInfo,
@@ -11,7 +11,7 @@ use crate::{
 use core::marker::PhantomData;
 use minicbor::Decoder;
 use ockam_core::api::{Request, Response, Status};
-use ockam_core::compat::vec::Vec;
+use ockam_core::compat::{string::ToString, vec::Vec};
 use ockam_core::errcode::{Kind, Origin};
 use ockam_core::vault::SignatureVec;
 use ockam_core::{Address, AsyncTryClone, CowStr, Error, Result, Route};
@@ -27,6 +27,19 @@ impl<V: IdentityVault> Identity<V> {
         self.credential.read().await.clone()
     }
 
+    /// Return all authenticated non-expired attributes of `peer_id`, along
+    /// with the authority which attested them.
+    ///
+    /// The attributes are those of the last credential verified for the peer
+    /// and stored in `authenticated_storage`, e.g. during a credential exchange.
+    pub async fn attributes_of(
+        &self,
+        peer_id: &IdentityIdentifier,
+        authenticated_storage: &impl AuthenticatedStorage,
+    ) -> Result<AttributeMap> {
+        AttributesStorageUtils::get_attribute_map(peer_id, authenticated_storage).await
+    }
+
     /// Create a signed credential based on the given values.
     pub async fn issue_credential<'a>(
         &self,
@@ -204,11 +217,83 @@ impl<V: IdentityVault> Identity<V> {
 
         AttributesStorageUtils::put_attributes(
             &sender,
-            AttributesEntry::new(credential_data.attributes, credential_data.expires),
+            AttributesEntry::new(
+                credential_data.attributes,
+                credential_data.expires,
+                Some(credential_data.issuer),
+            ),
             authenticated_storage,
         )
         .await?;
 
         Ok(())
     }
+
+    /// Import a peer's identity and its attributes from an encoded [`IdentityBundle`].
+    ///
+    /// The bundle's credential must be issued for the bundled identity by one of
+    /// `authorities`. The change history and the attributes are only written once
+    /// the whole bundle is verified, and the history is restored if writing the
+    /// attributes fails.
+    pub async fn import_bundle(
+        &self,
+        bytes: &[u8],
+        authorities: impl IntoIterator<Item = &PublicIdentity>,
+        authenticated_storage: &impl AuthenticatedStorage,
+    ) -> Result<PublicIdentity> {
+        let bundle: IdentityBundle =
+            minicbor::decode(bytes).map_err(|_| IdentityError::InvalidIdentityBundle)?;
+
+        let their_identity = self.import_public_identity(bundle.identity()).await?;
+        let their_identity_id = their_identity.identifier();
+
+        let credential_data = Self::verify_credential(
+            their_identity_id,
+            bundle.credential(),
+            authorities,
+            &self.vault,
+        )
+        .await?;
+
+        let id = their_identity_id.to_string();
+        let known_history = authenticated_storage
+            .get(&id, IdentityStateConst::CHANGE_HISTORY_KEY)
+            .await?;
+
+        self.update_known_identity(their_identity_id, &their_identity, authenticated_storage)
+            .await?;
+
+        if let Err(e) = AttributesStorageUtils::put_attributes(
+            their_identity_id,
+            AttributesEntry::new(
+                credential_data.attributes,
+                credential_data.expires,
+                Some(credential_data.issuer),
+            ),
+            authenticated_storage,
+        )
+        .await
+        {
+            // Roll back the change history so that the import is all-or-nothing
+            let _ = match known_history {
+                Some(known_history) => {
+                    authenticated_storage
+                        .set(
+                            &id,
+                            IdentityStateConst::CHANGE_HISTORY_KEY.to_string(),
+                            known_history,
+                        )
+                        .await
+                }
+                None => {
+                    authenticated_storage
+                        .del(&id, IdentityStateConst::CHANGE_HISTORY_KEY)
+                        .await
+                }
+            };
+            return Err(e);
+        }
+
+        Ok(their_identity)
+    }
 }
@@ -16,11 +16,20 @@ use ockam_core::Result;
 pub struct AttributesEntry<'a> {
     #[b(1)] attrs: Attributes<'a>,
     #[n(2)] expires: Timestamp,
+    #[n(3)] attested_by: Option<IdentityIdentifier>,
 }
 
 impl<'a> AttributesEntry<'a> {
-    pub fn new(attrs: Attributes<'a>, expires: Timestamp) -> Self {
-        Self { attrs, expires }
+    pub fn new(
+        attrs: Attributes<'a>,
+        expires: Timestamp,
+        attested_by: Option<IdentityIdentifier>,
+    ) -> Self {
+        Self {
+            attrs,
+            expires,
+            attested_by,
+        }
     }
     pub fn attrs(&self) -> &Attributes<'a> {
         &self.attrs
@@ -28,6 +37,55 @@ impl<'a> AttributesEntry<'a> {
     pub fn expires(&self) -> Timestamp {
         self.expires
     }
+    /// The authority which issued the credential the attributes come from
+    pub fn attested_by(&self) -> Option<&IdentityIdentifier> {
+        self.attested_by.as_ref()
+    }
+}
+
+/// All authenticated non-expired attributes of an Identity
+///
+/// The map is empty if no credential was received for the Identity, or if
+/// its attributes expired.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AttributeMap {
+    attrs: BTreeMap<String, Vec<u8>>,
+    expires: Option<Timestamp>,
+    attested_by: Option<IdentityIdentifier>,
+}
+
+impl AttributeMap {
+    pub fn get(&self, name: &str) -> Option<&[u8]> {
+        self.attrs.get(name).map(Vec::as_slice)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[u8])> {
+        self.attrs.iter().map(|(k, v)| (k.as_str(), v.as_slice()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.attrs.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.attrs.len()
+    }
+
+    /// When the attributes expire
+    pub fn expires(&self) -> Option<Timestamp> {
+        self.expires
+    }
+
+    /// The authority which attested the attributes
+    ///
+    /// This is `None` for attributes stored before authorities were recorded.
+    pub fn attested_by(&self) -> Option<&IdentityIdentifier> {
+        self.attested_by.as_ref()
+    }
+
+    pub fn into_attributes(self) -> BTreeMap<String, Vec<u8>> {
+        self.attrs
+    }
 }
 
 pub struct AttributesStorageUtils;
@@ -38,13 +96,27 @@ impl AttributesStorageUtils {
         identity_id: &IdentityIdentifier,
         authenticated_storage: &impl AuthenticatedStorage,
     ) -> Result<Option<BTreeMap<String, Vec<u8>>>> {
+        let map = Self::get_attribute_map(identity_id, authenticated_storage).await?;
+        if map.expires().is_none() {
+            return Ok(None);
+        }
+
+        Ok(Some(map.into_attributes()))
+    }
+
+    /// Return authenticated non-expired attributes attached to that Identity,
+    /// along with the authority which attested them
+    pub async fn get_attribute_map(
+        identity_id: &IdentityIdentifier,
+        authenticated_storage: &impl AuthenticatedStorage,
+    ) -> Result<AttributeMap> {
         let id = identity_id.to_string();
         let entry = match authenticated_storage
             .get(&id, IdentityStateConst::ATTRIBUTES_KEY)
             .await?
         {
             Some(e) => e,
-            None => return Ok(None),
+            None => return Ok(AttributeMap::default()),
         };
 
         let entry: AttributesEntry = minicbor::decode(&entry)?;
@@ -56,12 +128,14 @@ impl AttributesStorageUtils {
             authenticated_storage
                 .del(&id, IdentityStateConst::ATTRIBUTES_KEY)
                 .await?;
-            return Ok(None);
+            return Ok(AttributeMap::default());
         }
 
-        let attrs = entry.attrs().to_owned();
-
-        Ok(Some(attrs))
+        Ok(AttributeMap {
+            attrs: entry.attrs().to_owned(),
+            expires: Some(entry.expires()),
+            attested_by: entry.attested_by().cloned(),
+        })
     }
 
     pub(crate) async fn put_attributes(
@@ -20,6 +20,32 @@ pub enum IdentityError {
     InvalidCredentialFormat,
     UnknownAuthority,
     CredentialVerificationFailed,
+    EmptySecureChannelRoute,
+    InvalidSecureChannelRoute,
+    UnknownSecureChannelRouteHop,
+    SecureChannelLimitReached,
+    StorageSubscriptionNotSupported,
+    InvalidIdentityBundle,
+    StorageCapacityExceeded,
+    SecureChannelPaddingMismatch,
+    StorageTransactionNotSupported,
+    SecureChannelClosed,
+    SecureChannelPingTimeout,
+    StorageMetricsNotSupported,
+    SecureChannelPreSharedKeyMismatch,
+    InvalidPreSharedKey,
+    StorageOverlayEntryInvalid,
+    SecureChannelByteBudgetExceeded,
+    InvalidTimeWindow,
+    StorageWatchClosed,
+    SecureChannelHandshakeLimitReached,
+    StorageExportNotSupported,
+    InvalidStorageSnapshot,
+    IdentityReadOnly,
+    InvalidRootKey,
+    StorageQuotaExceeded,
+    StorageEntryTooLarge,
+    SecureChannelSendOnlyRejected,
 }
 
 impl ockam_core::compat::error::Error for IdentityError {}
@@ -32,8 +58,19 @@ impl core::fmt::Display for IdentityError {
 impl From<IdentityError> for Error {
     #[track_caller]
     fn from(err: IdentityError) -> Self {
-        let kind = Kind::Unknown; // FIXME: fill these in with more
-                                  // meaningful error kinds
+        let kind = match err {
+            IdentityError::SecureChannelClosed => Kind::NotFound,
+            IdentityError::SecureChannelPingTimeout => Kind::Timeout,
+            IdentityError::SecureChannelByteBudgetExceeded => Kind::ResourceExhausted,
+            IdentityError::SecureChannelHandshakeLimitReached => Kind::ResourceExhausted,
+            IdentityError::IdentityReadOnly => Kind::Misuse,
+            IdentityError::InvalidRootKey => Kind::Invalid,
+            IdentityError::StorageQuotaExceeded => Kind::ResourceExhausted,
+            IdentityError::StorageEntryTooLarge => Kind::ResourceExhausted,
+            IdentityError::SecureChannelSendOnlyRejected => Kind::Unsupported,
+            _ => Kind::Unknown, // FIXME: fill these in with more
+                                // meaningful error kinds
+        };
         Error::new(Origin::Identity, kind, err)
     }
 }
@@ -18,6 +18,7 @@ pub struct IdentityIdentifier(#[n(0)] KeyId);
 /// Unique [`crate::Identity`] identifier, computed as SHA256 of root public key
 impl IdentityIdentifier {
     const PREFIX: &'static str = "P";
+    const ANONYMOUS_PREFIX: &'static str = "anonymous-";
 
     /// Create an IdentityIdentifier from a KeyId
     pub fn from_key_id(key_id: &str) -> Self {
@@ -29,6 +30,22 @@ impl IdentityIdentifier {
         &self.0[Self::PREFIX.len()..]
     }
 
+    /// Identifier of a SecureChannel peer which presented no identity,
+    /// unique to the channel whose key exchange has `auth_hash`
+    pub fn anonymous(auth_hash: &[u8; 32]) -> Self {
+        Self::from_key_id(&format!(
+            "{}{}",
+            Self::ANONYMOUS_PREFIX,
+            hex::encode(&auth_hash[..16])
+        ))
+    }
+
+    /// Whether this identifies a SecureChannel peer which presented no
+    /// identity, see [`IdentityIdentifier::anonymous`]
+    pub fn is_anonymous(&self) -> bool {
+        self.key_id().starts_with(Self::ANONYMOUS_PREFIX)
+    }
+
     pub(crate) fn ct_eq(&self, o: &Self) -> subtle::Choice {
         use subtle::ConstantTimeEq;
         self.0.as_bytes().ct_eq(o.0.as_bytes())
@@ -3,9 +3,11 @@ use crate::change::IdentitySignedChange;
 use crate::change_history::{IdentityChangeHistory, IdentityHistoryComparison};
 use crate::credential::Credential;
 use crate::{
-    ChangeIdentifier, IdentityError, IdentityIdentifier, IdentityVault, KeyAttributes,
-    PublicIdentity,
+    ChangeIdentifier, HandshakeLimit, IdentityError, IdentityIdentifier, IdentityVault,
+    KeyAttributes, PublicIdentity, SecureChannelNegotiations, SecureChannelPool,
+    VerifiedHistoryCache,
 };
+use core::sync::atomic::{AtomicBool, Ordering};
 use ockam_core::compat::{
     boxed::Box,
     string::{String, ToString},
@@ -28,6 +30,14 @@ pub struct Identity<V: IdentityVault> {
     pub(crate) change_history: Arc<RwLock<IdentityChangeHistory>>,
     pub(crate) ctx: Context,
     pub(crate) vault: V,
+    pub(crate) channel_pool: Arc<SecureChannelPool>,
+    /// Change histories of other identities verified so far
+    pub(crate) verified_histories: Arc<VerifiedHistoryCache>,
+    pub(crate) handshake_limit: HandshakeLimit,
+    /// Parameters of the established channels
+    pub(crate) channel_negotiations: Arc<SecureChannelNegotiations>,
+    /// Whether changes to the change history are rejected
+    read_only: Arc<AtomicBool>,
 }
 
 pub struct IdentityStateConst;
@@ -38,6 +48,10 @@ impl IdentityStateConst {
     pub const INITIAL_CHANGE: &'static [u8] = "OCKAM_INITIAL_CHANGE".as_bytes();
     /// Label for [`crate::Identity`] update key
     pub const ROOT_LABEL: &'static str = "OCKAM_RK";
+    /// Label for [`crate::Identity`] key used for identity proofs
+    pub const SIGNING_LABEL: &'static str = "OCKAM_SK";
+    /// Label for [`crate::Identity`] key used for key agreement during handshakes
+    pub const KEY_AGREEMENT_LABEL: &'static str = "OCKAM_KAK";
     /// Current version of change structure
     pub const CURRENT_CHANGE_VERSION: u8 = 1;
     /// Change history key for AuthenticatedStorage
@@ -60,6 +74,11 @@ impl<V: IdentityVault> Identity<V> {
             change_history: Arc::new(RwLock::new(change_history)),
             ctx,
             vault,
+            channel_pool: Arc::new(SecureChannelPool::new()),
+            verified_histories: Default::default(),
+            handshake_limit: Default::default(),
+            channel_negotiations: Default::default(),
+            read_only: Default::default(),
         }
     }
 
@@ -93,20 +112,72 @@ impl<V: IdentityVault> Identity<V> {
 
     /// Create Identity
     pub async fn create(ctx: &Context, vault: &V) -> Result<Self> {
-        let child_ctx = ctx.new_detached(Address::random_local()).await?;
-        let initial_change_id = ChangeIdentifier::initial(vault).await;
+        Self::create_with_root_key(ctx, vault, None).await
+    }
+
+    /// Create an Identity whose root key is `secret`, an Ed25519 secret
+    /// key of 32 bytes, e.g. exported from another system
+    ///
+    /// The key is imported into `vault`. The same key always yields the
+    /// same [`IdentityIdentifier`].
+    pub async fn create_with_key(ctx: &Context, vault: &V, secret: &[u8]) -> Result<Self> {
+        let attributes = Self::root_key_attributes();
+        if secret.len() != attributes.secret_attributes().length() as usize {
+            return Err(IdentityError::InvalidRootKey.into());
+        }
+        let root_key = vault
+            .secret_import(secret, attributes.secret_attributes())
+            .await?;
+        Self::create_with_root_key(ctx, vault, Some(&root_key)).await
+    }
+
+    /// Create an Identity whose root key is `key_id`, a key already in
+    /// `vault`, e.g. provisioned in an HSM
+    ///
+    /// The key must be an Ed25519 secret key. It is only used to sign,
+    /// `vault` isn't written to. The same key always yields the same
+    /// [`IdentityIdentifier`].
+    pub async fn create_with_existing_key(
+        ctx: &Context,
+        vault: &V,
+        key_id: &KeyId,
+    ) -> Result<Self> {
+        let expected = Self::root_key_attributes();
+        let attributes = vault.secret_attributes_get(key_id).await?;
+        if attributes.stype() != expected.secret_attributes().stype()
+            || attributes.length() != expected.secret_attributes().length()
+        {
+            return Err(IdentityError::InvalidRootKey.into());
+        }
+        // The public key is needed to sign the identity's first change
+        vault.secret_public_key_get(key_id).await?;
+        Self::create_with_root_key(ctx, vault, Some(key_id)).await
+    }
 
-        let key_attribs = KeyAttributes::new(
+    fn root_key_attributes() -> KeyAttributes {
+        KeyAttributes::new(
             IdentityStateConst::ROOT_LABEL.to_string(),
             SecretAttributes::new(
                 SecretType::Ed25519,
                 SecretPersistence::Persistent,
                 CURVE25519_SECRET_LENGTH_U32,
             ),
-        );
+        )
+    }
+
+    /// Create an Identity around `root_key`, generating it if not given
+    async fn create_with_root_key(
+        ctx: &Context,
+        vault: &V,
+        root_key: Option<&KeyId>,
+    ) -> Result<Self> {
+        let child_ctx = ctx.new_detached(Address::random_local()).await?;
+        let initial_change_id = ChangeIdentifier::initial(vault).await;
+
+        let key_attribs = Self::root_key_attributes();
 
         let create_key_change = Self::make_create_key_change_static(
-            None,
+            root_key,
             initial_change_id,
             key_attribs.clone(),
             None,
@@ -147,6 +218,7 @@ impl<V: IdentityVault> Identity<V> {
     }
 
     async fn add_change(&self, change: IdentitySignedChange) -> Result<()> {
+        self.check_writable()?;
         self.change_history
             .write()
             .await
@@ -159,7 +231,30 @@ impl<V: IdentityVault> Identity<V> {
         &self.id
     }
 
+    /// Reject, or allow again, the operations changing the change history
+    /// of this [`Identity`], such as key creation and rotation
+    ///
+    /// A read-only identity can still sign and run secure channel
+    /// handshakes. The mode is shared by the clones of this identity.
+    pub fn set_read_only(&self, read_only: bool) {
+        self.read_only.store(read_only, Ordering::Relaxed);
+    }
+
+    /// Whether the change history of this [`Identity`] can't be changed
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.load(Ordering::Relaxed)
+    }
+
+    /// Fail before any key is created for a change of a read-only identity
+    fn check_writable(&self) -> Result<()> {
+        if self.is_read_only() {
+            return Err(IdentityError::IdentityReadOnly.into());
+        }
+        Ok(())
+    }
+
     pub async fn create_key(&self, label: String) -> Result<()> {
+        self.check_writable()?;
         let key_attribs = KeyAttributes::default_with_label(label);
 
         let change = self.make_create_key_change(None, key_attribs).await?;
@@ -168,6 +263,7 @@ impl<V: IdentityVault> Identity<V> {
     }
 
     pub async fn add_key(&self, label: String, secret: &KeyId) -> Result<()> {
+        self.check_writable()?;
         let secret_attributes = self.vault.secret_attributes_get(secret).await?;
         let key_attribs = KeyAttributes::new(label, secret_attributes);
 
@@ -179,6 +275,7 @@ impl<V: IdentityVault> Identity<V> {
     }
 
     pub async fn rotate_key(&self, label: &str) -> Result<()> {
+        self.check_writable()?;
         let change = self
             .make_rotate_key_change(KeyAttributes::default_with_label(label.to_string()))
             .await?;
@@ -187,6 +284,7 @@ impl<V: IdentityVault> Identity<V> {
     }
 
     pub async fn rotate_root_key(&self) -> Result<()> {
+        self.check_writable()?;
         let change = self
             .make_rotate_key_change(KeyAttributes::default_with_label(
                 IdentityStateConst::ROOT_LABEL.to_string(),
@@ -196,6 +294,51 @@ impl<V: IdentityVault> Identity<V> {
         self.add_change(change).await
     }
 
+    /// Create distinct signing and key-agreement keys.
+    ///
+    /// Once created, identity proofs are signed with the signing key and the
+    /// secure channel handshake uses the key-agreement key as its static key,
+    /// so the root key is only used to sign [`crate::Identity`] changes.
+    pub async fn create_purpose_keys(&self) -> Result<()> {
+        self.check_writable()?;
+        let signing_attribs = KeyAttributes::default_with_label(IdentityStateConst::SIGNING_LABEL);
+        let change = self.make_create_key_change(None, signing_attribs).await?;
+        self.add_change(change).await?;
+
+        let key_agreement_attribs = KeyAttributes::new(
+            IdentityStateConst::KEY_AGREEMENT_LABEL.to_string(),
+            SecretAttributes::new(
+                SecretType::X25519,
+                SecretPersistence::Persistent,
+                CURVE25519_SECRET_LENGTH_U32,
+            ),
+        );
+        let change = self
+            .make_create_key_change(None, key_agreement_attribs)
+            .await?;
+        self.add_change(change).await
+    }
+
+    /// Label of the key used for identity proofs
+    pub async fn signing_key_label(&self) -> &'static str {
+        IdentityChangeHistory::signing_key_label(self.change_history.read().await.as_ref())
+    }
+
+    /// Key-agreement key, if this [`crate::Identity`] has one
+    pub(crate) async fn get_key_agreement_secret_key(&self) -> Result<Option<KeyId>> {
+        let change = match IdentityChangeHistory::find_last_key_change(
+            self.change_history.read().await.as_ref(),
+            IdentityStateConst::KEY_AGREEMENT_LABEL,
+        ) {
+            Ok(change) => change.clone(),
+            Err(_) => return Ok(None),
+        };
+
+        Ok(Some(
+            Self::get_secret_key_from_change(&change, &self.vault).await?,
+        ))
+    }
+
     /// Get [`Secret`] key. Key is uniquely identified by label in [`KeyAttributes`]
     pub(crate) async fn get_root_secret_key(&self) -> Result<KeyId> {
         self.get_secret_key(IdentityStateConst::ROOT_LABEL).await
@@ -226,6 +369,13 @@ impl<V: IdentityVault> Identity<V> {
         self.vault.sign(&secret, data).await
     }
 
+    /// Import the exported change history of another identity
+    ///
+    /// Histories this identity already verified aren't verified again.
+    pub(crate) async fn import_public_identity(&self, data: &[u8]) -> Result<PublicIdentity> {
+        self.verified_histories.import(data, &self.vault).await
+    }
+
     pub async fn get_known_identity(
         &self,
         their_identity_id: &IdentityIdentifier,
@@ -238,7 +388,7 @@ impl<V: IdentityVault> Identity<V> {
             )
             .await?
         {
-            let known = PublicIdentity::import(&known, &self.vault).await?;
+            let known = self.import_public_identity(&known).await?;
 
             Ok(Some(known))
         } else {
@@ -293,7 +443,7 @@ impl<V: IdentityVault> Identity<V> {
 mod test {
     use super::*;
     use ockam_core::errcode::{Kind, Origin};
-    use ockam_core::vault::PublicKey;
+    use ockam_core::vault::{PublicKey, SecretVault};
     use ockam_core::Error;
     use ockam_vault::Vault;
 
@@ -319,6 +469,81 @@ mod test {
         }
     }
 
+    #[ockam_macros::test]
+    async fn test_create_with_key(ctx: &mut Context) -> Result<()> {
+        let secret = [0x2a; 32];
+
+        let vault = Vault::create();
+        let identity = Identity::create_with_key(ctx, &vault, &secret).await?;
+        assert!(identity.verify_changes().await?);
+
+        // The root key is the imported one
+        let root_key = identity.get_root_secret_key().await?;
+        assert_eq!(vault.secret_export(&root_key).await?.as_ref(), &secret);
+
+        // The identifier only depends on the key
+        let other_vault = Vault::create();
+        let same_identity = Identity::create_with_key(ctx, &other_vault, &secret).await?;
+        assert_eq!(identity.identifier(), same_identity.identifier());
+        assert_eq!(
+            identity.get_root_public_key().await?,
+            same_identity.get_root_public_key().await?
+        );
+
+        let other_identity = Identity::create_with_key(ctx, &vault, &[0x2b; 32]).await?;
+        assert_ne!(identity.identifier(), other_identity.identifier());
+
+        // Only Ed25519 secret keys of 32 bytes are accepted
+        for secret in [&[0x2a; 31][..], &[0x2a; 64][..], &[][..]] {
+            let err = Identity::create_with_key(ctx, &vault, secret)
+                .await
+                .err()
+                .unwrap();
+            assert_eq!(err.code().kind, Kind::Invalid);
+        }
+
+        ctx.stop().await
+    }
+
+    #[ockam_macros::test]
+    async fn test_create_with_existing_key(ctx: &mut Context) -> Result<()> {
+        let secret = [0x2a; 32];
+
+        let vault = Vault::create();
+        let attributes = Identity::<Vault>::root_key_attributes();
+        let key_id = vault
+            .secret_import(&secret, attributes.secret_attributes())
+            .await?;
+        let identity = Identity::create_with_existing_key(ctx, &vault, &key_id).await?;
+        assert!(identity.verify_changes().await?);
+        assert_eq!(identity.get_root_secret_key().await?, key_id);
+
+        // Same identity as with the key material itself
+        let same_identity = Identity::create_with_key(ctx, &Vault::create(), &secret).await?;
+        assert_eq!(identity.identifier(), same_identity.identifier());
+
+        // Unknown keys and keys which can't sign are rejected
+        assert!(
+            Identity::create_with_existing_key(ctx, &vault, &"unknown".to_string())
+                .await
+                .is_err()
+        );
+        let aes_key = vault
+            .secret_generate(SecretAttributes::new(
+                SecretType::Aes,
+                SecretPersistence::Ephemeral,
+                32,
+            ))
+            .await?;
+        let err = Identity::create_with_existing_key(ctx, &vault, &aes_key)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.code().kind, Kind::Invalid);
+
+        ctx.stop().await
+    }
+
     #[ockam_macros::test]
     async fn test_basic_identity_key_ops(ctx: &mut Context) -> Result<()> {
         let vault = Vault::create();
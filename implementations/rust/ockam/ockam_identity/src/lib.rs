@@ -39,6 +39,8 @@ mod identity;
 mod identity_builder;
 mod key_attributes;
 mod public_identity;
+mod split_vault;
+mod verified_history_cache;
 
 pub use channel::*;
 pub use identifiers::*;
@@ -46,11 +48,15 @@ pub use identity::*;
 pub use identity_builder::*;
 pub use key_attributes::*;
 pub use public_identity::*;
+pub use split_vault::*;
+pub(crate) use verified_history_cache::*;
 
 mod signature;
 
 #[cfg(test)]
 mod invalid_signatures_tests;
+#[cfg(test)]
+mod vault_failure_tests;
 
 /// Traits required for a Vault implementation suitable for use in an Identity
 pub trait IdentityVault:
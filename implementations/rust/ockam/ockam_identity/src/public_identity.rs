@@ -38,6 +38,11 @@ impl PublicIdentity {
         &self.change_history
     }
 
+    /// Number of changes in the history, the creation of the root key included
+    pub fn changes_count(&self) -> usize {
+        self.change_history.as_ref().len()
+    }
+
     pub fn compare(&self, known: &Self) -> IdentityHistoryComparison {
         self.change_history.compare(&known.change_history)
     }
@@ -46,6 +51,11 @@ impl PublicIdentity {
         &self.id
     }
 
+    /// Label of the key used for identity proofs
+    pub fn signing_key_label(&self) -> &'static str {
+        IdentityChangeHistory::signing_key_label(self.change_history.as_ref())
+    }
+
     pub(crate) fn get_root_public_key(&self) -> Result<PublicKey> {
         self.change_history.get_root_public_key()
     }
@@ -0,0 +1,274 @@
+use crate::IdentityVault;
+use ockam_core::vault::{
+    AsymmetricVault, Buffer, Hasher, KeyId, PublicKey, SecretAttributes, SecretKey, SecretType,
+    SecretVault, Signature, Signer, SmallBuffer, SymmetricVault, Verifier,
+};
+use ockam_core::{async_trait, compat::boxed::Box};
+use ockam_core::{AsyncTryClone, Result};
+
+/// Vault keeping signing keys apart from key exchange keys
+///
+/// Signing keys, e.g. the keys of an [`Identity`](crate::Identity), are
+/// created in and used from the `signing` vault, which can be backed by an
+/// HSM. X25519 keys, the secrets they agree on and the keys derived from
+/// those are kept in the `key_exchange` vault, which is usually a software
+/// vault. Operations on an existing key go to the vault holding it.
+#[derive(AsyncTryClone)]
+#[async_try_clone(crate = "ockam_core")]
+pub struct SplitVault<S: IdentityVault, K: IdentityVault> {
+    signing: S,
+    key_exchange: K,
+}
+
+impl<S: IdentityVault, K: IdentityVault> SplitVault<S, K> {
+    /// Constructor
+    pub fn new(signing: S, key_exchange: K) -> Self {
+        Self {
+            signing,
+            key_exchange,
+        }
+    }
+
+    /// Vault holding the signing keys
+    pub fn signing(&self) -> &S {
+        &self.signing
+    }
+
+    /// Vault holding the key exchange keys
+    pub fn key_exchange(&self) -> &K {
+        &self.key_exchange
+    }
+
+    /// Whether keys of this type are created in the signing vault
+    fn is_signing_type(stype: SecretType) -> bool {
+        !matches!(
+            stype,
+            SecretType::X25519 | SecretType::Buffer | SecretType::Aes
+        )
+    }
+
+    /// Whether the key is held by the signing vault
+    async fn is_signing_key(&self, key_id: &KeyId) -> bool {
+        self.signing.secret_attributes_get(key_id).await.is_ok()
+    }
+}
+
+#[async_trait]
+impl<S: IdentityVault, K: IdentityVault> SecretVault for SplitVault<S, K> {
+    async fn secret_generate(&self, attributes: SecretAttributes) -> Result<KeyId> {
+        if Self::is_signing_type(attributes.stype()) {
+            self.signing.secret_generate(attributes).await
+        } else {
+            self.key_exchange.secret_generate(attributes).await
+        }
+    }
+
+    async fn secret_import(&self, secret: &[u8], attributes: SecretAttributes) -> Result<KeyId> {
+        if Self::is_signing_type(attributes.stype()) {
+            self.signing.secret_import(secret, attributes).await
+        } else {
+            self.key_exchange.secret_import(secret, attributes).await
+        }
+    }
+
+    async fn secret_export(&self, key_id: &KeyId) -> Result<SecretKey> {
+        if self.is_signing_key(key_id).await {
+            self.signing.secret_export(key_id).await
+        } else {
+            self.key_exchange.secret_export(key_id).await
+        }
+    }
+
+    async fn secret_attributes_get(&self, key_id: &KeyId) -> Result<SecretAttributes> {
+        match self.signing.secret_attributes_get(key_id).await {
+            Ok(attributes) => Ok(attributes),
+            Err(_) => self.key_exchange.secret_attributes_get(key_id).await,
+        }
+    }
+
+    async fn secret_public_key_get(&self, key_id: &KeyId) -> Result<PublicKey> {
+        if self.is_signing_key(key_id).await {
+            self.signing.secret_public_key_get(key_id).await
+        } else {
+            self.key_exchange.secret_public_key_get(key_id).await
+        }
+    }
+
+    async fn secret_destroy(&self, key_id: KeyId) -> Result<()> {
+        if self.is_signing_key(&key_id).await {
+            self.signing.secret_destroy(key_id).await
+        } else {
+            self.key_exchange.secret_destroy(key_id).await
+        }
+    }
+}
+
+#[async_trait]
+impl<S: IdentityVault, K: IdentityVault> SymmetricVault for SplitVault<S, K> {
+    async fn aead_aes_gcm_encrypt(
+        &self,
+        key_id: &KeyId,
+        plaintext: &[u8],
+        nonce: &[u8],
+        aad: &[u8],
+    ) -> Result<Buffer<u8>> {
+        self.key_exchange
+            .aead_aes_gcm_encrypt(key_id, plaintext, nonce, aad)
+            .await
+    }
+
+    async fn aead_aes_gcm_decrypt(
+        &self,
+        key_id: &KeyId,
+        cipher_text: &[u8],
+        nonce: &[u8],
+        aad: &[u8],
+    ) -> Result<Buffer<u8>> {
+        self.key_exchange
+            .aead_aes_gcm_decrypt(key_id, cipher_text, nonce, aad)
+            .await
+    }
+}
+
+#[async_trait]
+impl<S: IdentityVault, K: IdentityVault> Hasher for SplitVault<S, K> {
+    async fn sha256(&self, data: &[u8]) -> Result<[u8; 32]> {
+        self.key_exchange.sha256(data).await
+    }
+
+    async fn hkdf_sha256(
+        &self,
+        salt: &KeyId,
+        info: &[u8],
+        ikm: Option<&KeyId>,
+        output_attributes: SmallBuffer<SecretAttributes>,
+    ) -> Result<SmallBuffer<KeyId>> {
+        self.key_exchange
+            .hkdf_sha256(salt, info, ikm, output_attributes)
+            .await
+    }
+}
+
+#[async_trait]
+impl<S: IdentityVault, K: IdentityVault> AsymmetricVault for SplitVault<S, K> {
+    async fn ec_diffie_hellman(
+        &self,
+        secret: &KeyId,
+        peer_public_key: &PublicKey,
+    ) -> Result<KeyId> {
+        self.key_exchange
+            .ec_diffie_hellman(secret, peer_public_key)
+            .await
+    }
+
+    async fn compute_key_id_for_public_key(&self, public_key: &PublicKey) -> Result<KeyId> {
+        if Self::is_signing_type(public_key.stype()) {
+            self.signing.compute_key_id_for_public_key(public_key).await
+        } else {
+            self.key_exchange
+                .compute_key_id_for_public_key(public_key)
+                .await
+        }
+    }
+}
+
+#[async_trait]
+impl<S: IdentityVault, K: IdentityVault> Signer for SplitVault<S, K> {
+    async fn sign(&self, key_id: &KeyId, data: &[u8]) -> Result<Signature> {
+        if self.is_signing_key(key_id).await {
+            self.signing.sign(key_id, data).await
+        } else {
+            self.key_exchange.sign(key_id, data).await
+        }
+    }
+}
+
+#[async_trait]
+impl<S: IdentityVault, K: IdentityVault> Verifier for SplitVault<S, K> {
+    async fn verify(
+        &self,
+        signature: &Signature,
+        public_key: &PublicKey,
+        data: &[u8],
+    ) -> Result<bool> {
+        // Only needs the public key, which any vault can use
+        self.key_exchange.verify(signature, public_key, data).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::authenticated_storage::mem::InMemoryStorage;
+    use crate::{Identity, TrustEveryonePolicy};
+    use ockam_core::compat::sync::Arc;
+    use ockam_core::route;
+    use ockam_node::Context;
+    use ockam_vault::{InMemoryAuditSink, Vault, VaultOperation};
+
+    #[ockam_macros::test]
+    async fn test_channel_with_split_vault(ctx: &mut Context) -> Result<()> {
+        let signing_audit = InMemoryAuditSink::new();
+        let key_exchange_audit = InMemoryAuditSink::new();
+        let alice_vault = SplitVault::new(
+            Vault::create().with_audit_sink(Arc::new(signing_audit.clone())),
+            Vault::create().with_audit_sink(Arc::new(key_exchange_audit.clone())),
+        );
+        let bob_vault = Vault::create();
+
+        let alice = Identity::create(ctx, &alice_vault).await?;
+        let bob = Identity::create(ctx, &bob_vault).await?;
+
+        bob.create_secure_channel_listener(
+            "bob_listener",
+            TrustEveryonePolicy,
+            &InMemoryStorage::new(),
+        )
+        .await?;
+        let alice_channel = alice
+            .create_secure_channel(
+                route!["bob_listener"],
+                TrustEveryonePolicy,
+                &InMemoryStorage::new(),
+            )
+            .await?;
+
+        ctx.send(
+            route![alice_channel, ctx.address()],
+            "Hello, Bob!".to_string(),
+        )
+        .await?;
+        let msg = ctx.receive::<String>().await?.take();
+        assert_eq!("Hello, Bob!", msg.body());
+        ctx.send(msg.return_route(), "Hello, Alice!".to_string())
+            .await?;
+        assert_eq!(
+            "Hello, Alice!",
+            ctx.receive::<String>().await?.take().body()
+        );
+
+        // Alice's identity proof was signed with the signing vault, while
+        // the key exchange only used the other vault
+        let signing_ops = signing_audit.entries();
+        let key_exchange_ops = key_exchange_audit.entries();
+        assert!(!signing_ops.is_empty());
+        assert!(signing_ops
+            .iter()
+            .all(|e| e.operation() == VaultOperation::Sign));
+        assert!(!key_exchange_ops.is_empty());
+        assert!(key_exchange_ops
+            .iter()
+            .all(|e| e.operation() == VaultOperation::EcDiffieHellman));
+
+        // Signing keys never reach the key exchange vault
+        let root_key = alice.get_root_secret_key().await?;
+        assert!(alice_vault.signing().secret_export(&root_key).await.is_ok());
+        assert!(alice_vault
+            .key_exchange()
+            .secret_export(&root_key)
+            .await
+            .is_err());
+
+        ctx.stop().await
+    }
+}
@@ -0,0 +1,201 @@
+use crate::authenticated_storage::mem::InMemoryStorage;
+use crate::{Identity, IdentityVault, TrustEveryonePolicy};
+use core::time::Duration;
+use ockam_core::errcode::{Kind, Origin};
+use ockam_core::vault::{
+    AsymmetricVault, Buffer, Hasher, KeyId, PublicKey, SecretAttributes, SecretKey, SecretVault,
+    Signature, Signer, SmallBuffer, SymmetricVault, Verifier,
+};
+use ockam_core::{async_trait, compat::boxed::Box};
+use ockam_core::{route, Address, AsyncTryClone, Error, Result};
+use ockam_node::Context;
+use ockam_vault::Vault;
+use std::time::Instant;
+use tokio::time::sleep;
+
+/// Vault whose ECDH fails, like a HSM which disconnected
+#[derive(AsyncTryClone)]
+#[async_try_clone(crate = "ockam_core")]
+struct FailingEcdhVault<V: IdentityVault> {
+    vault: V,
+}
+
+#[async_trait]
+impl<V: IdentityVault> SecretVault for FailingEcdhVault<V> {
+    async fn secret_generate(&self, attributes: SecretAttributes) -> Result<KeyId> {
+        self.vault.secret_generate(attributes).await
+    }
+
+    async fn secret_import(&self, secret: &[u8], attributes: SecretAttributes) -> Result<KeyId> {
+        self.vault.secret_import(secret, attributes).await
+    }
+
+    async fn secret_export(&self, key_id: &KeyId) -> Result<SecretKey> {
+        self.vault.secret_export(key_id).await
+    }
+
+    async fn secret_attributes_get(&self, key_id: &KeyId) -> Result<SecretAttributes> {
+        self.vault.secret_attributes_get(key_id).await
+    }
+
+    async fn secret_public_key_get(&self, key_id: &KeyId) -> Result<PublicKey> {
+        self.vault.secret_public_key_get(key_id).await
+    }
+
+    async fn secret_destroy(&self, key_id: KeyId) -> Result<()> {
+        self.vault.secret_destroy(key_id).await
+    }
+}
+
+#[async_trait]
+impl<V: IdentityVault> SymmetricVault for FailingEcdhVault<V> {
+    async fn aead_aes_gcm_encrypt(
+        &self,
+        key_id: &KeyId,
+        plaintext: &[u8],
+        nonce: &[u8],
+        aad: &[u8],
+    ) -> Result<Buffer<u8>> {
+        self.vault
+            .aead_aes_gcm_encrypt(key_id, plaintext, nonce, aad)
+            .await
+    }
+
+    async fn aead_aes_gcm_decrypt(
+        &self,
+        key_id: &KeyId,
+        cipher_text: &[u8],
+        nonce: &[u8],
+        aad: &[u8],
+    ) -> Result<Buffer<u8>> {
+        self.vault
+            .aead_aes_gcm_decrypt(key_id, cipher_text, nonce, aad)
+            .await
+    }
+}
+
+#[async_trait]
+impl<V: IdentityVault> Hasher for FailingEcdhVault<V> {
+    async fn sha256(&self, data: &[u8]) -> Result<[u8; 32]> {
+        self.vault.sha256(data).await
+    }
+
+    async fn hkdf_sha256(
+        &self,
+        salt: &KeyId,
+        info: &[u8],
+        ikm: Option<&KeyId>,
+        output_attributes: SmallBuffer<SecretAttributes>,
+    ) -> Result<SmallBuffer<KeyId>> {
+        self.vault
+            .hkdf_sha256(salt, info, ikm, output_attributes)
+            .await
+    }
+}
+
+#[async_trait]
+impl<V: IdentityVault> AsymmetricVault for FailingEcdhVault<V> {
+    async fn ec_diffie_hellman(
+        &self,
+        _secret: &KeyId,
+        _peer_public_key: &PublicKey,
+    ) -> Result<KeyId> {
+        Err(Error::new(Origin::Vault, Kind::Io, "HSM disconnected"))
+    }
+
+    async fn compute_key_id_for_public_key(&self, public_key: &PublicKey) -> Result<KeyId> {
+        self.vault.compute_key_id_for_public_key(public_key).await
+    }
+}
+
+#[async_trait]
+impl<V: IdentityVault> Signer for FailingEcdhVault<V> {
+    async fn sign(&self, key_id: &KeyId, data: &[u8]) -> Result<Signature> {
+        self.vault.sign(key_id, data).await
+    }
+}
+
+#[async_trait]
+impl<V: IdentityVault> Verifier for FailingEcdhVault<V> {
+    async fn verify(
+        &self,
+        signature: &Signature,
+        public_key: &PublicKey,
+        data: &[u8],
+    ) -> Result<bool> {
+        self.vault.verify(signature, public_key, data).await
+    }
+}
+
+async fn sorted_workers(ctx: &Context) -> Result<Vec<Address>> {
+    let mut workers = ctx.list_workers().await?;
+    workers.sort();
+    Ok(workers)
+}
+
+#[ockam_macros::test]
+async fn test_initiator_vault_failure_aborts_handshake(ctx: &mut Context) -> Result<()> {
+    let alice_vault = FailingEcdhVault {
+        vault: Vault::create(),
+    };
+    let alice = Identity::create(ctx, &alice_vault).await?;
+    let bob = Identity::create(ctx, &Vault::create()).await?;
+    bob.create_secure_channel_listener(
+        "bob_listener",
+        TrustEveryonePolicy,
+        &InMemoryStorage::new(),
+    )
+    .await?;
+
+    // The failure is reported well before the handshake would time out
+    let timeout = Duration::from_secs(30);
+    let started = Instant::now();
+    let err = alice
+        .create_secure_channel_extended(
+            route!["bob_listener"],
+            TrustEveryonePolicy,
+            &InMemoryStorage::new(),
+            timeout,
+        )
+        .await
+        .unwrap_err();
+    assert!(started.elapsed() < timeout);
+    assert_eq!(err.code().kind, Kind::Io);
+    let message = err.to_string();
+    assert!(message.contains("vault operation failed"), "{}", message);
+    assert!(message.contains("HSM disconnected"), "{}", message);
+
+    ctx.stop().await
+}
+
+#[ockam_macros::test]
+async fn test_responder_vault_failure_leaves_no_workers(ctx: &mut Context) -> Result<()> {
+    let alice = Identity::create(ctx, &Vault::create()).await?;
+    let bob_vault = FailingEcdhVault {
+        vault: Vault::create(),
+    };
+    let bob = Identity::create(ctx, &bob_vault).await?;
+    bob.create_secure_channel_listener(
+        "bob_listener",
+        TrustEveryonePolicy,
+        &InMemoryStorage::new(),
+    )
+    .await?;
+    let workers = sorted_workers(ctx).await?;
+
+    let res = alice
+        .create_secure_channel_extended(
+            route!["bob_listener"],
+            TrustEveryonePolicy,
+            &InMemoryStorage::new(),
+            Duration::from_millis(500),
+        )
+        .await;
+    assert!(res.is_err());
+
+    // Bob's side stopped when its vault failed, Alice's once it timed out
+    sleep(Duration::from_secs(1)).await;
+    assert_eq!(workers, sorted_workers(ctx).await?);
+
+    ctx.stop().await
+}
@@ -0,0 +1,43 @@
+use crate::change_history::IdentityChangeHistory;
+use crate::{IdentityError, IdentityIdentifier, IdentityVault, PublicIdentity};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use ockam_core::compat::{collections::BTreeMap, sync::RwLock};
+use ockam_core::Result;
+
+/// Change histories an [`Identity`](crate::Identity) already verified
+///
+/// Verifying a change history checks the signature of every change, which
+/// gets expensive for long histories presented on every handshake. The
+/// cache keeps the hash of the last verified history of each identity, so
+/// a peer presenting the same history again isn't verified again. Any other
+/// history of that identity is verified, and replaces the cached one.
+#[derive(Default)]
+pub(crate) struct VerifiedHistoryCache {
+    verified: RwLock<BTreeMap<IdentityIdentifier, [u8; 32]>>,
+    verifications: AtomicUsize,
+}
+
+impl VerifiedHistoryCache {
+    /// Import an exported change history, verifying it unless it was verified before
+    pub async fn import(&self, data: &[u8], vault: &impl IdentityVault) -> Result<PublicIdentity> {
+        let change_history = IdentityChangeHistory::import(data)?;
+        let id = change_history.compute_identity_id(vault).await?;
+        let hash = vault.sha256(data).await?;
+
+        if self.verified.read().unwrap().get(&id) != Some(&hash) {
+            self.verifications.fetch_add(1, Ordering::Relaxed);
+            if !change_history.verify_all_existing_changes(vault).await? {
+                return Err(IdentityError::IdentityVerificationFailed.into());
+            }
+            self.verified.write().unwrap().insert(id.clone(), hash);
+        }
+
+        Ok(PublicIdentity::new(id, change_history))
+    }
+
+    /// Number of change histories verified so far
+    #[cfg(test)]
+    pub fn verifications(&self) -> usize {
+        self.verifications.load(Ordering::Relaxed)
+    }
+}
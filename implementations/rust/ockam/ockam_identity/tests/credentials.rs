@@ -3,8 +3,8 @@ use ockam_core::{async_trait, Any};
 use ockam_core::{route, Result, Routed, Worker};
 use ockam_identity::authenticated_storage::mem::InMemoryStorage;
 use ockam_identity::credential::access_control::CredentialAccessControl;
-use ockam_identity::credential::{AttributesStorageUtils, Credential};
-use ockam_identity::{Identity, TrustEveryonePolicy, TrustIdentifierPolicy};
+use ockam_identity::credential::{AttributesStorageUtils, Credential, IdentityBundle};
+use ockam_identity::{Identity, TrustCredentialPolicy, TrustEveryonePolicy, TrustIdentifierPolicy};
 use ockam_node::{Context, WorkerBuilder};
 use ockam_vault::Vault;
 use std::sync::atomic::{AtomicI8, Ordering};
@@ -66,6 +66,126 @@ async fn full_flow_oneway(ctx: &mut Context) -> Result<()> {
     ctx.stop().await
 }
 
+#[ockam_macros::test]
+async fn attributes_of_peer(ctx: &mut Context) -> Result<()> {
+    let vault = Vault::create();
+
+    let authority = Identity::create(ctx, &vault).await?;
+
+    let server = Identity::create(ctx, &vault).await?;
+    let server_storage = InMemoryStorage::new();
+
+    server
+        .create_secure_channel_listener("listener", TrustEveryonePolicy, &server_storage)
+        .await?;
+    server
+        .start_credentials_exchange_worker(
+            vec![authority.to_public().await?],
+            "credential_exchange",
+            false,
+            server_storage.clone(),
+        )
+        .await?;
+
+    let client = Identity::create(ctx, &vault).await?;
+    let client_storage = InMemoryStorage::new();
+    let channel = client
+        .create_secure_channel(route!["listener"], TrustEveryonePolicy, &client_storage)
+        .await?;
+
+    // Nothing is known about the client before it presents a credential
+    let attrs = server
+        .attributes_of(client.identifier(), &server_storage)
+        .await?;
+    assert!(attrs.is_empty());
+    assert!(attrs.attested_by().is_none());
+
+    let credential = Credential::builder(client.identifier().clone())
+        .with_attribute("project", b"ockam")
+        .with_attribute("role", b"admin");
+    let credential = authority.issue_credential(credential).await?;
+    client.set_credential(Some(credential)).await;
+    client
+        .present_credential(route![channel, "credential_exchange"])
+        .await?;
+
+    let attrs = server
+        .attributes_of(client.identifier(), &server_storage)
+        .await?;
+    assert_eq!(attrs.len(), 2);
+    assert_eq!(attrs.get("project"), Some(&b"ockam"[..]));
+    assert_eq!(attrs.get("role"), Some(&b"admin"[..]));
+    assert_eq!(attrs.attested_by(), Some(authority.identifier()));
+    assert!(attrs.expires().is_some());
+
+    ctx.stop().await
+}
+
+#[ockam_macros::test]
+async fn credential_trust_policy(ctx: &mut Context) -> Result<()> {
+    let vault = Vault::create();
+
+    let authority = Identity::create(ctx, &vault).await?;
+
+    let server = Identity::create(ctx, &vault).await?;
+    let server_storage = InMemoryStorage::new();
+
+    server
+        .create_secure_channel_listener("listener", TrustEveryonePolicy, &server_storage)
+        .await?;
+    server
+        .create_secure_channel_listener(
+            "members_listener",
+            TrustCredentialPolicy::new(server_storage.clone()),
+            &server_storage,
+        )
+        .await?;
+    server
+        .start_credentials_exchange_worker(
+            vec![authority.to_public().await?],
+            "credential_exchange",
+            false,
+            server_storage.clone(),
+        )
+        .await?;
+
+    let client = Identity::create(ctx, &vault).await?;
+    let client_storage = InMemoryStorage::new();
+
+    // Without a credential the client isn't trusted
+    let res = client
+        .create_secure_channel_extended(
+            route!["members_listener"],
+            TrustEveryonePolicy,
+            &client_storage,
+            Duration::from_secs(1),
+        )
+        .await;
+    assert!(res.is_err());
+
+    let credential =
+        Credential::builder(client.identifier().clone()).with_attribute("role", b"member");
+    let credential = authority.issue_credential(credential).await?;
+    client.set_credential(Some(credential)).await;
+    let channel = client
+        .create_secure_channel(route!["listener"], TrustEveryonePolicy, &client_storage)
+        .await?;
+    client
+        .present_credential(route![channel, "credential_exchange"])
+        .await?;
+
+    // Once its credential was presented it is
+    client
+        .create_secure_channel(
+            route!["members_listener"],
+            TrustEveryonePolicy,
+            &client_storage,
+        )
+        .await?;
+
+    ctx.stop().await
+}
+
 #[ockam_macros::test]
 async fn full_flow_twoway(ctx: &mut Context) -> Result<()> {
     let vault = Vault::create();
@@ -223,3 +343,77 @@ async fn access_control(ctx: &mut Context) -> Result<()> {
 
     ctx.stop().await
 }
+
+#[ockam_macros::test]
+async fn import_bundle(ctx: &mut Context) -> Result<()> {
+    let vault = Vault::create();
+
+    let authority = Identity::create(ctx, &vault).await?;
+    let authorities = vec![authority.to_public().await?];
+
+    let server = Identity::create(ctx, &vault).await?;
+    let server_storage = InMemoryStorage::new();
+
+    // A valid bundle installs the identity and its attributes
+    let client = Identity::create(ctx, &vault).await?;
+    let credential =
+        Credential::builder(client.identifier().clone()).with_attribute("role", b"user");
+    let credential = authority.issue_credential(credential).await?;
+    let bundle = IdentityBundle::new(&client.to_public().await?, credential)?.to_vec()?;
+
+    let imported = server
+        .import_bundle(&bundle, &authorities, &server_storage)
+        .await?;
+    assert_eq!(imported.identifier(), client.identifier());
+    assert!(server
+        .get_known_identity(client.identifier(), &server_storage)
+        .await?
+        .is_some());
+    let attrs = AttributesStorageUtils::get_attributes(client.identifier(), &server_storage)
+        .await?
+        .unwrap();
+    assert_eq!(attrs.get("role").unwrap().as_slice(), b"user");
+
+    // A bundle with a tampered signature is rejected and nothing is stored
+    let other = Identity::create(ctx, &vault).await?;
+    let credential =
+        Credential::builder(other.identifier().clone()).with_attribute("role", b"user");
+    let credential = authority.issue_credential(credential).await?;
+    let mut bundle = IdentityBundle::new(&other.to_public().await?, credential)?.to_vec()?;
+    // The credential signature is encoded last
+    *bundle.last_mut().unwrap() ^= 0xff;
+
+    let err = server
+        .import_bundle(&bundle, &authorities, &server_storage)
+        .await
+        .unwrap_err();
+    assert_eq!(err.to_string(), "CredentialVerificationFailed");
+    assert!(server
+        .get_known_identity(other.identifier(), &server_storage)
+        .await?
+        .is_none());
+    assert!(
+        AttributesStorageUtils::get_attributes(other.identifier(), &server_storage)
+            .await?
+            .is_none()
+    );
+
+    // A bundle signed by an unknown authority is rejected
+    let rogue = Identity::create(ctx, &vault).await?;
+    let credential =
+        Credential::builder(other.identifier().clone()).with_attribute("role", b"admin");
+    let credential = rogue.issue_credential(credential).await?;
+    let bundle = IdentityBundle::new(&other.to_public().await?, credential)?.to_vec()?;
+
+    let err = server
+        .import_bundle(&bundle, &authorities, &server_storage)
+        .await
+        .unwrap_err();
+    assert_eq!(err.to_string(), "UnknownAuthority");
+    assert!(server
+        .get_known_identity(other.identifier(), &server_storage)
+        .await?
+        .is_none());
+
+    ctx.stop().await
+}
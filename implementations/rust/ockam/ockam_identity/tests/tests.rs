@@ -1,9 +1,10 @@
 use ockam_core::errcode::{Kind, Origin};
-use ockam_core::vault::{SecretAttributes, SecretPersistence, SecretType, SecretVault};
-use ockam_core::{Error, Result};
+use ockam_core::vault::{SecretAttributes, SecretPersistence, SecretType, SecretVault, Signature};
+use ockam_core::{async_trait, route, Address, AsyncTryClone, Error, Result, Routed, Worker};
 use ockam_identity::authenticated_storage::mem::InMemoryStorage;
-use ockam_identity::Identity;
+use ockam_identity::{Identity, PublicIdentity, TrustEveryonePolicy};
 use ockam_node::Context;
+use ockam_transport_tcp::{TcpTransport, TCP};
 use ockam_vault::Vault;
 use rand::{thread_rng, RngCore};
 
@@ -96,6 +97,64 @@ async fn test_key_rotation(ctx: &mut Context) -> Result<()> {
     Ok(())
 }
 
+#[ockam_macros::test]
+async fn test_read_only_identity(ctx: &mut Context) -> Result<()> {
+    let alice_vault = Vault::create();
+    let bob_vault = Vault::create();
+
+    let alice = Identity::create(ctx, &alice_vault).await?;
+    let bob = Identity::create(ctx, &bob_vault).await?;
+    alice.set_read_only(true);
+
+    // The change history can't be changed, by the identity nor its clones
+    let before = alice.export().await?;
+    let err = alice.rotate_root_key().await.unwrap_err();
+    assert_eq!(err.code().kind, Kind::Misuse);
+    assert!(alice.create_key("key".to_string()).await.is_err());
+    let clone = alice.async_try_clone().await?;
+    assert!(clone.rotate_key("key").await.is_err());
+    assert!(clone.create_purpose_keys().await.is_err());
+    assert_eq!(alice.export().await?, before);
+
+    // Handshakes and signing still work
+    bob.create_secure_channel_listener(
+        "bob_listener",
+        TrustEveryonePolicy,
+        &InMemoryStorage::new(),
+    )
+    .await?;
+    let channel = alice
+        .create_secure_channel(
+            route!["bob_listener"],
+            TrustEveryonePolicy,
+            &InMemoryStorage::new(),
+        )
+        .await?;
+    let mut child = ctx.new_detached(Address::random_local()).await?;
+    child
+        .send(route![channel, child.address()], "Hello, Bob!".to_string())
+        .await?;
+    assert_eq!(
+        child.receive::<String>().await?.take().body(),
+        "Hello, Bob!"
+    );
+
+    let signature = alice.create_signature(b"data", None).await?;
+    assert!(
+        alice
+            .to_public()
+            .await?
+            .verify_signature(&signature, b"data", None, &alice_vault)
+            .await?
+    );
+
+    // The change history can be changed again once writable
+    alice.set_read_only(false);
+    alice.rotate_root_key().await?;
+
+    ctx.stop().await
+}
+
 #[ockam_macros::test]
 async fn test_update_contact_and_reprove(ctx: &mut Context) -> Result<()> {
     let alice_vault = Vault::create();
@@ -180,3 +239,87 @@ async fn add_key(ctx: &mut Context) -> Result<()> {
 
     ctx.stop().await
 }
+
+/// Checks application signatures sent along with the signer's identity
+struct SignatureVerifier {
+    vault: Vault,
+}
+
+#[async_trait]
+impl Worker for SignatureVerifier {
+    type Message = Vec<u8>;
+    type Context = Context;
+
+    async fn handle_message(&mut self, ctx: &mut Context, msg: Routed<Vec<u8>>) -> Result<()> {
+        let (identity, data, signature): (Vec<u8>, Vec<u8>, Signature) =
+            serde_bare::from_slice(msg.as_body()).unwrap();
+        let identity = PublicIdentity::import(&identity, &self.vault).await?;
+        let valid = identity
+            .verify_signature(&signature, &data, None, &self.vault)
+            .await?;
+        ctx.send(msg.return_route(), valid.to_string()).await
+    }
+}
+
+#[ockam_macros::test]
+async fn sign_and_verify_across_nodes(ctx: &mut Context) -> Result<()> {
+    // The verifier is only reachable over TCP, as if it ran on Bob's node
+    let tcp = TcpTransport::create(ctx).await?;
+    let bob_address = tcp.listen("127.0.0.1:0").await?;
+    ctx.start_worker(
+        "verifier",
+        SignatureVerifier {
+            vault: Vault::create(),
+        },
+    )
+    .await?;
+
+    let alice_vault = Vault::create();
+    let alice = Identity::create(ctx, &alice_vault).await?;
+    let verifier = route![(TCP, bob_address.to_string()), "verifier"];
+
+    let data = b"some application data".to_vec();
+    let signature = alice.create_signature(&data, None).await?;
+    let mut child = ctx.new_detached(Address::random_local()).await?;
+
+    let request = |identity: Vec<u8>, data: &[u8], signature: &Signature| {
+        serde_bare::to_vec(&(identity, data.to_vec(), signature.clone())).unwrap()
+    };
+    let alice_exported = alice.to_public().await?.export()?;
+
+    child
+        .send(
+            verifier.clone(),
+            request(alice_exported.clone(), &data, &signature),
+        )
+        .await?;
+    assert_eq!(child.receive::<String>().await?.take().body(), "true");
+
+    // Tampered data
+    child
+        .send(
+            verifier.clone(),
+            request(alice_exported, b"some other data", &signature),
+        )
+        .await?;
+    assert_eq!(child.receive::<String>().await?.take().body(), "false");
+
+    // The key which created the signature was rotated away
+    alice.rotate_root_key().await?;
+    let alice_exported = alice.to_public().await?.export()?;
+    child
+        .send(
+            verifier.clone(),
+            request(alice_exported.clone(), &data, &signature),
+        )
+        .await?;
+    assert_eq!(child.receive::<String>().await?.take().body(), "false");
+
+    let signature = alice.create_signature(&data, None).await?;
+    child
+        .send(verifier, request(alice_exported, &data, &signature))
+        .await?;
+    assert_eq!(child.receive::<String>().await?.take().body(), "true");
+
+    ctx.stop().await
+}
@@ -0,0 +1,141 @@
+use core::fmt::Debug;
+use ockam_core::{route, Address, Result};
+use ockam_identity::authenticated_storage::mem::InMemoryStorage;
+use ockam_identity::{Identity, TrustEveryonePolicy};
+use ockam_node::NodeBuilder;
+use ockam_transport_tcp::{TcpTransport, TCP};
+use ockam_vault::Vault;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+/// A span and the fields recorded on it
+#[derive(Debug, Default)]
+struct RecordedSpan {
+    name: &'static str,
+    fields: BTreeMap<&'static str, String>,
+}
+
+impl Visit for RecordedSpan {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.fields.insert(field.name(), format!("{:?}", value));
+    }
+}
+
+/// Subscriber keeping every span created
+#[derive(Clone, Default)]
+struct SpanRecorder {
+    spans: Arc<Mutex<Vec<RecordedSpan>>>,
+}
+
+impl SpanRecorder {
+    /// Values of `field` on the spans named `name`
+    fn values(&self, name: &str, field: &str) -> Vec<String> {
+        self.spans
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|span| span.name == name)
+            .filter_map(|span| span.fields.get(field).cloned())
+            .collect()
+    }
+}
+
+impl Subscriber for SpanRecorder {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, attributes: &Attributes<'_>) -> Id {
+        let mut span = RecordedSpan {
+            name: attributes.metadata().name(),
+            ..Default::default()
+        };
+        attributes.record(&mut span);
+        let mut spans = self.spans.lock().unwrap();
+        spans.push(span);
+        Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, id: &Id, values: &Record<'_>) {
+        let mut spans = self.spans.lock().unwrap();
+        values.record(&mut spans[id.into_u64() as usize - 1]);
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, _event: &Event<'_>) {}
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
+
+#[test]
+fn spans_follow_a_message_across_workers() {
+    // The recorder must be the global subscriber before the node starts
+    let recorder = SpanRecorder::default();
+    tracing::subscriber::set_global_default(recorder.clone()).unwrap();
+
+    let (mut ctx, mut executor) = NodeBuilder::without_access_control().no_logging().build();
+    let (bob_address, alice_id) = executor
+        .execute(async move {
+            let tcp = TcpTransport::create(&ctx).await?;
+            let bob_address = tcp.listen("127.0.0.1:0").await?;
+
+            let alice = Identity::create(&ctx, &Vault::create()).await?;
+            let bob = Identity::create(&ctx, &Vault::create()).await?;
+            bob.create_secure_channel_listener(
+                "bob_listener",
+                TrustEveryonePolicy,
+                &InMemoryStorage::new(),
+            )
+            .await?;
+            let channel = alice
+                .create_secure_channel(
+                    route![(TCP, bob_address.to_string()), "bob_listener"],
+                    TrustEveryonePolicy,
+                    &InMemoryStorage::new(),
+                )
+                .await?;
+
+            let mut child = ctx.new_detached(Address::random_local()).await?;
+            child
+                .send(route![channel, child.address()], "Hello, Bob!".to_string())
+                .await?;
+            child.receive::<String>().await?;
+
+            ctx.stop().await?;
+            Result::Ok((bob_address, alice.identifier().to_string()))
+        })
+        .unwrap()
+        .unwrap();
+
+    // Every end of the channel names itself and its peer
+    for name in ["secure_channel_encryptor", "secure_channel_decryptor"] {
+        assert!(!recorder.values(name, "channel").is_empty());
+        assert!(!recorder.values(name, "peer").is_empty());
+    }
+    assert!(recorder
+        .values("identity_secure_channel_decryptor", "peer")
+        .contains(&alice_id));
+    assert!(recorder
+        .values("tcp_router", "peer")
+        .contains(&format!("{}#{}", TCP, bob_address)));
+    assert!(recorder
+        .values("tcp_send_worker", "peer")
+        .contains(&bob_address.to_string()));
+
+    // Every encrypted message can be followed to the decryptor at the other
+    // end under the same id
+    let encrypted = recorder.values("secure_channel_encryptor", "message_id");
+    assert!(!encrypted.is_empty());
+    for name in ["tcp_router", "tcp_send_worker", "secure_channel_decryptor"] {
+        let seen = recorder.values(name, "message_id");
+        for message_id in &encrypted {
+            assert!(seen.contains(message_id), "{} missed {}", name, message_id);
+        }
+    }
+}
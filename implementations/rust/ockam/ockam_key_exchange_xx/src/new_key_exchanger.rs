@@ -1,5 +1,6 @@
 use crate::state::State;
 use crate::{Initiator, Responder, XXVault};
+use ockam_core::vault::KeyId;
 use ockam_core::{async_trait, compat::boxed::Box, AsyncTryClone, Result};
 
 use ockam_key_exchange_core::NewKeyExchanger;
@@ -9,12 +10,25 @@ use ockam_key_exchange_core::NewKeyExchanger;
 #[async_try_clone(crate = "ockam_core")]
 pub struct XXNewKeyExchanger<V: XXVault> {
     vault: V,
+    static_key: Option<KeyId>,
 }
 
 impl<V: XXVault> XXNewKeyExchanger<V> {
     /// Create a new XXNewKeyExchanger
     pub fn new(vault: V) -> Self {
-        Self { vault }
+        Self {
+            vault,
+            static_key: None,
+        }
+    }
+
+    /// Create a new XXNewKeyExchanger that uses the given X25519 key as
+    /// the static key `s` instead of generating one per handshake
+    pub fn new_with_static_key(vault: V, static_key: KeyId) -> Self {
+        Self {
+            vault,
+            static_key: Some(static_key),
+        }
     }
 }
 
@@ -25,13 +39,13 @@ impl<V: XXVault> NewKeyExchanger for XXNewKeyExchanger<V> {
 
     /// Create a new initiator using the provided backing vault
     async fn initiator(&self) -> Result<Initiator<V>> {
-        let ss = State::new(&self.vault).await?;
+        let ss = State::new(&self.vault, self.static_key.clone()).await?;
         Ok(Initiator::new(ss))
     }
 
     /// Create a new responder using the provided backing vault
     async fn responder(&self) -> Result<Responder<V>> {
-        let ss = State::new(&self.vault).await?;
+        let ss = State::new(&self.vault, self.static_key.clone()).await?;
         Ok(Responder::new(ss))
     }
 }
@@ -38,10 +38,10 @@ impl<V: XXVault> core::fmt::Debug for State<V> {
 }
 
 impl<V: XXVault> State<V> {
-    pub(crate) async fn new(vault: &V) -> Result<Self> {
+    pub(crate) async fn new(vault: &V, identity_key: Option<KeyId>) -> Result<Self> {
         Ok(Self {
             run_prologue: true,
-            identity_key: None,
+            identity_key,
             identity_public_key: None,
             ephemeral_secret: None,
             ephemeral_public: None,
@@ -403,7 +403,7 @@ mod tests {
                 126, 100, 252, 104, 43, 230, 163, 171, 75, 104, 44, 141, 182, 75,
             ];
 
-            let mut state = State::new(&vault).await.unwrap();
+            let mut state = State::new(&vault, None).await.unwrap();
             let res = state.prologue().await;
             assert!(res.is_ok());
             assert_eq!(state.h.unwrap(), exp_h);
@@ -1,5 +1,5 @@
 use super::{Buffer, Checked, Code, Codec, Protocol};
-use crate::proto::{DnsAddr, Node, Project, Secure, Service, Space, Tcp};
+use crate::proto::{DnsAddr, Node, Project, Secure, Service, Space, Tcp, Udp};
 use crate::{Error, ProtoValue};
 use core::fmt;
 use unsigned_varint::decode;
@@ -42,9 +42,9 @@ impl Codec for StdCodec {
                 let (x, y) = input.split_at(16);
                 Ok((Checked(x), y))
             }
-            Tcp::CODE => {
+            c @ Tcp::CODE | c @ Udp::CODE => {
                 if input.len() < 2 {
-                    return Err(Error::required_bytes(Tcp::CODE, 2));
+                    return Err(Error::required_bytes(c, 2));
                 }
                 let (x, y) = input.split_at(2);
                 Ok((Checked(x), y))
@@ -73,6 +73,7 @@ impl Codec for StdCodec {
             #[cfg(feature = "std")]
             crate::proto::Ip6::CODE => crate::proto::Ip6::read_bytes(input).is_ok(),
             Tcp::CODE => Tcp::read_bytes(input).is_ok(),
+            Udp::CODE => Udp::read_bytes(input).is_ok(),
             DnsAddr::CODE => DnsAddr::read_bytes(input).is_ok(),
             Service::CODE => Service::read_bytes(input).is_ok(),
             Node::CODE => Node::read_bytes(input).is_ok(),
@@ -90,6 +91,7 @@ impl Codec for StdCodec {
             #[cfg(feature = "std")]
             crate::proto::Ip6::CODE => crate::proto::Ip6::read_bytes(val.data())?.write_bytes(buf),
             Tcp::CODE => Tcp::read_bytes(val.data())?.write_bytes(buf),
+            Udp::CODE => Udp::read_bytes(val.data())?.write_bytes(buf),
             DnsAddr::CODE => DnsAddr::read_bytes(val.data())?.write_bytes(buf),
             Service::CODE => Service::read_bytes(val.data())?.write_bytes(buf),
             Node::CODE => Node::read_bytes(val.data())?.write_bytes(buf),
@@ -122,6 +124,10 @@ impl Codec for StdCodec {
                 Tcp::read_str(value)?.write_bytes(buf);
                 Ok(())
             }
+            Udp::PREFIX => {
+                Udp::read_str(value)?.write_bytes(buf);
+                Ok(())
+            }
             DnsAddr::PREFIX => {
                 DnsAddr::read_str(value)?.write_bytes(buf);
                 Ok(())
@@ -171,6 +177,10 @@ impl Codec for StdCodec {
                 Tcp::read_bytes(value)?.write_str(f)?;
                 Ok(())
             }
+            Udp::CODE => {
+                Udp::read_bytes(value)?.write_str(f)?;
+                Ok(())
+            }
             DnsAddr::CODE => {
                 DnsAddr::read_bytes(value)?.write_str(f)?;
                 Ok(())
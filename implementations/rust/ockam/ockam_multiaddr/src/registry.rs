@@ -1,6 +1,6 @@
 use super::{Code, Codec, Protocol};
 use crate::codec::StdCodec;
-use crate::proto::{DnsAddr, Node, Project, Secure, Service, Space, Tcp};
+use crate::proto::{DnsAddr, Node, Project, Secure, Service, Space, Tcp, Udp};
 use alloc::collections::btree_map::BTreeMap;
 use alloc::sync::Arc;
 use core::fmt;
@@ -26,6 +26,7 @@ impl Default for Registry {
         let std_codec = Arc::new(StdCodec);
         let mut r = RegistryBuilder::new();
         r.register(Tcp::CODE, Tcp::PREFIX, std_codec.clone());
+        r.register(Udp::CODE, Udp::PREFIX, std_codec.clone());
         r.register(DnsAddr::CODE, DnsAddr::PREFIX, std_codec.clone());
         #[allow(clippy::redundant_clone)]
         r.register(Service::CODE, Service::PREFIX, std_codec.clone());
@@ -0,0 +1,141 @@
+use crate::tokio::sync::oneshot;
+use crate::Context;
+use ockam_core::compat::{
+    collections::BTreeMap,
+    rand::random,
+    sync::{Arc, Mutex},
+    vec::Vec,
+};
+use ockam_core::errcode::{Kind, Origin};
+use ockam_core::{Address, Decodable, Encodable, Encoded, Error, Message, NeutralMessage, Result};
+
+/// A request sent by [`Context::call`], or its reply, with the id
+/// matching the reply to its request
+///
+/// A worker answering calls handles `Correlated` requests and sends the
+/// [`reply`](Self::reply) to their return route. The encoded message is
+/// the id as a big-endian `u64`, then the encoded body.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Correlated<M> {
+    id: u64,
+    body: M,
+}
+
+impl<M> Correlated<M> {
+    /// Create a message with the given correlation id
+    pub fn new(id: u64, body: M) -> Self {
+        Self { id, body }
+    }
+
+    /// Return the correlation id
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Return a reference to the body
+    pub fn body(&self) -> &M {
+        &self.body
+    }
+
+    /// Consume the message and return its body
+    pub fn into_body(self) -> M {
+        self.body
+    }
+
+    /// Create the reply to this request, with the same id
+    pub fn reply<N>(&self, body: N) -> Correlated<N> {
+        Correlated::new(self.id, body)
+    }
+}
+
+impl<M: Encodable> Encodable for Correlated<M> {
+    fn encode(&self) -> Result<Encoded> {
+        let mut encoded = self.id.to_be_bytes().to_vec();
+        encoded.extend_from_slice(&self.body.encode()?);
+        Ok(encoded)
+    }
+}
+
+impl<M: Decodable> Decodable for Correlated<M> {
+    fn decode(encoded: &[u8]) -> Result<Self> {
+        Ok(Self {
+            id: correlation_id(encoded)?,
+            body: M::decode(&encoded[8..])?,
+        })
+    }
+}
+
+impl<M: Message> Message for Correlated<M> {}
+
+fn correlation_id(encoded: &[u8]) -> Result<u64> {
+    encoded
+        .get(..8)
+        .and_then(|id| id.try_into().ok())
+        .map(u64::from_be_bytes)
+        .ok_or_else(|| Error::new(Origin::Node, Kind::Invalid, "missing correlation id"))
+}
+
+/// Receives the replies to the calls of a context on a single address,
+/// and hands each one to the call waiting for it
+#[derive(Clone)]
+pub(crate) struct CallReplies {
+    address: Address,
+    waiting: Arc<Mutex<BTreeMap<u64, oneshot::Sender<Vec<u8>>>>>,
+}
+
+impl CallReplies {
+    /// Receive the replies on a new detached context, until `owner` is
+    /// dropped
+    pub(crate) async fn start(owner: &Context) -> Result<Self> {
+        let mut ctx = owner.new_detached(Address::random_local()).await?;
+        let replies = Self {
+            address: ctx.address(),
+            waiting: Arc::new(Mutex::new(BTreeMap::new())),
+        };
+
+        let waiting = replies.waiting.clone();
+        owner.spawn(async move {
+            while let Ok(msg) = ctx.receive_block::<NeutralMessage>().await {
+                let encoded: Vec<u8> = msg.take().body().into();
+                let id = match correlation_id(&encoded) {
+                    Ok(id) => id,
+                    Err(_) => {
+                        warn!("Dropping a reply without correlation id");
+                        continue;
+                    }
+                };
+                match waiting.lock().unwrap().remove(&id) {
+                    Some(call) => {
+                        let _ = call.send(encoded);
+                    }
+                    // The call timed out, or the reply is a duplicate
+                    None => debug!("Dropping a reply to unknown call {}", id),
+                }
+            }
+        });
+        Ok(replies)
+    }
+
+    /// Address the replies are sent to
+    pub(crate) fn address(&self) -> &Address {
+        &self.address
+    }
+
+    /// Pick an id for a new call, whose encoded reply is sent to the
+    /// returned receiver
+    pub(crate) fn register(&self) -> (u64, oneshot::Receiver<Vec<u8>>) {
+        let (tx, rx) = oneshot::channel();
+        let mut waiting = self.waiting.lock().unwrap();
+        let mut id = random();
+        while waiting.contains_key(&id) {
+            id = random();
+        }
+        waiting.insert(id, tx);
+        (id, rx)
+    }
+
+    /// Forget a call, once it got its reply or gave up waiting for it
+    pub(crate) fn unregister(&self, id: u64) {
+        self.waiting.lock().unwrap().remove(&id);
+    }
+}
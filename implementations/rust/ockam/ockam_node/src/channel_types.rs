@@ -5,7 +5,12 @@ pub type MessageReceiver<T> = crate::tokio::sync::mpsc::Receiver<T>;
 
 /// Create message channel
 pub fn message_channel<T>() -> (MessageSender<T>, MessageReceiver<T>) {
-    crate::tokio::sync::mpsc::channel(16)
+    message_channel_with_capacity(16)
+}
+
+/// Create message channel holding at most `capacity` messages
+pub fn message_channel_with_capacity<T>(capacity: usize) -> (MessageSender<T>, MessageReceiver<T>) {
+    crate::tokio::sync::mpsc::channel(capacity)
 }
 
 /// Router sender
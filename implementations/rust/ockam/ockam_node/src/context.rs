@@ -1,28 +1,49 @@
 use crate::async_drop::AsyncDrop;
-use crate::channel_types::{message_channel, small_channel, SmallReceiver, SmallSender};
+use crate::call::CallReplies;
+use crate::channel_types::{
+    message_channel, small_channel, MessageReceiver, MessageSender, SmallReceiver, SmallSender,
+};
+use crate::compat::asynchronous::Mutex as AsyncMutex;
 use crate::tokio::{self, runtime::Handle, time::timeout};
 use crate::{
     error::*,
     parser,
+    priority_queue::PriorityQueue,
     relay::{CtrlSignal, ProcessorRelay, RelayMessage},
     router::SenderPair,
-    Cancel, NodeMessage, ShutdownType, WorkerBuilder,
+    Cancel, Correlated, NodeMessage, ShutdownType, WorkerBuilder, WorkerInfo,
 };
 use core::{
-    sync::atomic::{AtomicUsize, Ordering},
+    future::Future,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
     time::Duration,
 };
-use ockam_core::compat::{boxed::Box, string::String, sync::Arc, vec::Vec};
+use futures::future::{AbortHandle, Abortable};
+use futures::FutureExt;
+use ockam_core::compat::{
+    boxed::Box,
+    string::String,
+    sync::{Arc, Mutex},
+    vec::Vec,
+};
 use ockam_core::{
     errcode::{Kind, Origin},
-    Address, AddressSet, AllowAll, AsyncTryClone, Error, LocalMessage, Mailbox, Mailboxes, Message,
-    Processor, Result, Route, TransportMessage, TransportType, Worker,
+    Address, AddressSet, AllowAll, AsyncTryClone, Decodable, Encodable, Error, LocalMessage,
+    Mailbox, Mailboxes, Message, MessagePriority, Processor, Result, Route, TransportMessage,
+    TransportType, Worker,
 };
 use ockam_core::{AccessControl, LocalInfo};
 
 /// A default timeout in seconds
 pub const DEFAULT_TIMEOUT: u64 = 30;
 
+/// The prefix marking a local address as a reference to a route alias
+pub const ROUTE_ALIAS_PREFIX: char = '@';
+
+/// How many aliases may be expanded at the front of a route before
+/// it is considered to expand into itself
+const MAX_ALIAS_EXPANSIONS: usize = 16;
+
 enum AddressType {
     Worker,
     Processor,
@@ -48,18 +69,37 @@ pub type DetachedContext = Context;
 /// access control which is not inherited from its parent `Context.
 pub type RepeaterContext = Context;
 
+/// Messages a worker takes from its channel ahead of the one it handles,
+/// to find the most urgent one
+///
+/// Taking more would empty the channel and let senders queue messages
+/// without bound, rather than wait for a slow worker, e.g. a transport
+/// sender whose socket doesn't drain.
+const MAX_PENDING_MESSAGES: usize = 16;
+
 /// Context contains Node state and references to the runtime.
 pub struct Context {
     mailboxes: Mailboxes,
     sender: SmallSender<NodeMessage>,
     rt: Handle,
     receiver: SmallReceiver<RelayMessage>,
+    /// Messages taken from `receiver`, waiting for the higher priority ones
+    pending: PriorityQueue,
+    /// How many messages can be taken from `receiver` ahead of time
+    max_pending: usize,
     async_drop_sender: Option<AsyncDropSender>,
     mailbox_count: Arc<AtomicUsize>,
+    /// Tasks started with [`Context::spawn`], and whether they completed
+    spawned: Mutex<Vec<(AbortHandle, Arc<AtomicBool>)>>,
+    /// Receives the replies to [`Context::call`], once there was a call
+    calls: AsyncMutex<Option<CallReplies>>,
 }
 
 impl Drop for Context {
     fn drop(&mut self) {
+        for (handle, _) in self.spawned.lock().unwrap().drain(..) {
+            handle.abort();
+        }
         if let Some(sender) = self.async_drop_sender.take() {
             trace!("De-allocated detached context {}", self.address());
             if let Err(e) = sender.send(self.address()) {
@@ -92,21 +132,42 @@ impl Context {
         &self.sender
     }
 
+    /// Only take a message from the mailbox once the previous one is handled
+    ///
+    /// Messages taken ahead of time don't count against the capacity of a
+    /// bounded mailbox, which would then queue more messages than it
+    /// allows before its senders wait or its messages are dropped.
+    #[cfg(feature = "std")]
+    pub(crate) fn disable_prefetch(&mut self) {
+        self.max_pending = 0;
+    }
+
     /// Wait for the next message from the mailbox
+    ///
+    /// Messages of a higher [`MessagePriority`] overtake the ones which
+    /// are already queued, among the next few messages of the mailbox.
     pub(crate) async fn receiver_next(&mut self) -> Result<Option<RelayMessage>> {
         loop {
-            let relay_msg = if let Some(msg) = self.receiver.recv().await.map(|msg| {
-                trace!("{}: received new message!", self.address());
-
-                // First we update the mailbox fill metrics
-                self.mailbox_count.fetch_sub(1, Ordering::Acquire);
+            // Take the messages which are already waiting, so that the
+            // most urgent one is handled first
+            while self.pending.len() < self.max_pending {
+                match self.receiver.recv().now_or_never() {
+                    Some(Some(msg)) => self.pending.push(msg),
+                    _ => break,
+                }
+            }
 
-                msg
-            }) {
-                msg
-            } else {
-                return Ok(None);
+            let relay_msg = match self.pending.pop() {
+                Some(msg) => msg,
+                None => match self.receiver.recv().await {
+                    Some(msg) => msg,
+                    None => return Ok(None),
+                },
             };
+            trace!("{}: received new message!", self.address());
+
+            // First we update the mailbox fill metrics
+            self.mailbox_count.fetch_sub(1, Ordering::Acquire);
 
             if !self
                 .mailboxes
@@ -137,6 +198,25 @@ impl Context {
         async_drop_sender: Option<AsyncDropSender>,
     ) -> (Self, SenderPair, SmallReceiver<CtrlSignal>) {
         let (mailbox_tx, receiver) = message_channel();
+        Self::new_with_mailbox(
+            rt,
+            sender,
+            mailboxes,
+            async_drop_sender,
+            mailbox_tx,
+            receiver,
+        )
+    }
+
+    /// Create a new context reading its messages from `receiver`
+    pub(crate) fn new_with_mailbox(
+        rt: Handle,
+        sender: SmallSender<NodeMessage>,
+        mailboxes: Mailboxes,
+        async_drop_sender: Option<AsyncDropSender>,
+        mailbox_tx: MessageSender<RelayMessage>,
+        receiver: MessageReceiver<RelayMessage>,
+    ) -> (Self, SenderPair, SmallReceiver<CtrlSignal>) {
         let (ctrl_tx, ctrl_rx) = small_channel();
         (
             Self {
@@ -144,8 +224,12 @@ impl Context {
                 sender,
                 mailboxes,
                 receiver,
+                pending: PriorityQueue::default(),
+                max_pending: MAX_PENDING_MESSAGES,
                 async_drop_sender,
                 mailbox_count: Arc::new(0.into()),
+                spawned: Mutex::new(Vec::new()),
+                calls: AsyncMutex::new(None),
             },
             SenderPair {
                 msgs: mailbox_tx,
@@ -155,6 +239,31 @@ impl Context {
         )
     }
 
+    /// Spawn `future` as a task which is cancelled once this context is
+    /// dropped, i.e. when the worker or processor owning it stops
+    pub fn spawn<F>(&self, future: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let (handle, reg) = AbortHandle::new_pair();
+        let done = Arc::new(AtomicBool::new(false));
+        let task_done = done.clone();
+        let future = Abortable::new(
+            async move {
+                future.await;
+                task_done.store(true, Ordering::Release);
+            },
+            reg,
+        );
+        {
+            let mut spawned = self.spawned.lock().unwrap();
+            // Forget the tasks which already completed
+            spawned.retain(|(_, done)| !done.load(Ordering::Acquire));
+            spawned.push((handle, done));
+        }
+        self.rt.spawn(future);
+    }
+
     /// Return the primary address of the current worker
     pub fn address(&self) -> Address {
         self.mailboxes.main_address()
@@ -457,6 +566,57 @@ impl Context {
             .body())
     }
 
+    /// Send a request and wait for the reply matching it
+    ///
+    /// The request is sent as a [`Correlated`] message with a new id, and
+    /// the worker at `route` answers with [`Correlated::reply`]. The
+    /// replies to all the calls of this context come back to the same
+    /// address, where each one is matched to its request by id, so
+    /// concurrent calls to the same worker get their own reply. A reply
+    /// coming after its call timed out is dropped.
+    ///
+    /// This operation has a [default timeout](DEFAULT_TIMEOUT).
+    pub async fn call<R, M, N>(&self, route: R, msg: M) -> Result<N>
+    where
+        R: Into<Route>,
+        M: Message + Send + 'static,
+        N: Message,
+    {
+        let replies = self.call_replies().await?;
+        let (id, reply) = replies.register();
+        let res = async {
+            let payload = Correlated::new(id, msg).encode()?;
+            self.route_payload(
+                route.into(),
+                payload,
+                replies.address().clone(),
+                Vec::new(),
+                MessagePriority::Normal,
+            )
+            .await?;
+            let reply = timeout(Duration::from_secs(DEFAULT_TIMEOUT), reply)
+                .await
+                .map_err(|e| NodeError::Data.with_elapsed(e))?
+                .map_err(|_| NodeError::NodeState(NodeReason::Unknown).internal())?;
+            Ok(Correlated::<N>::decode(&reply)?.into_body())
+        }
+        .await;
+        replies.unregister(id);
+        res
+    }
+
+    /// Return the receiver of the replies to the calls, starting it on
+    /// the first call
+    async fn call_replies(&self) -> Result<CallReplies> {
+        let mut calls = self.calls.lock().await;
+        if let Some(replies) = calls.as_ref() {
+            return Ok(replies.clone());
+        }
+        let replies = CallReplies::start(self).await?;
+        *calls = Some(replies.clone());
+        Ok(replies)
+    }
+
     /// Send a message to another address associated with this worker
     ///
     /// This function is a simple wrapper around `Self::send()` which
@@ -513,6 +673,46 @@ impl Context {
             .await
     }
 
+    /// Send the same message to several addresses or routes
+    ///
+    /// The message is encoded once for all routes. Each route is sent
+    /// to even if sending to another one failed, and the result of
+    /// each send is returned, in the order of `routes`.
+    pub async fn send_multicast<R, M>(
+        &self,
+        routes: impl IntoIterator<Item = R>,
+        msg: M,
+    ) -> Vec<Result<()>>
+    where
+        R: Into<Route>,
+        M: Message + Send + 'static,
+    {
+        let payload = match msg.encode() {
+            Ok(payload) => payload,
+            Err(e) => {
+                let code = e.code();
+                return routes
+                    .into_iter()
+                    .map(|_| Err(Error::new_without_cause(code.origin, code.kind)))
+                    .collect();
+            }
+        };
+        let mut results = Vec::new();
+        for route in routes {
+            let result = self
+                .send_payload_from_address(
+                    route.into(),
+                    payload.clone(),
+                    self.address(),
+                    Vec::new(),
+                    MessagePriority::Normal,
+                )
+                .await;
+            results.push(result);
+        }
+        results
+    }
+
     /// Send a message to an address or via a fully-qualified route
     /// after attaching the given [`LocalInfo`] to the message.
     pub async fn send_with_local_info<R, M>(
@@ -525,7 +725,34 @@ impl Context {
         R: Into<Route>,
         M: Message + Send + 'static,
     {
-        self.send_from_address_impl(route.into(), msg, self.address(), local_info)
+        self.send_from_address_impl(
+            route.into(),
+            msg,
+            self.address(),
+            local_info,
+            MessagePriority::Normal,
+        )
+        .await
+    }
+
+    /// Send a message to an address or via a fully-qualified route
+    /// with the given [`MessagePriority`]
+    ///
+    /// The receiving worker handles a message of a higher priority
+    /// before the ones of lower priorities which are already waiting in
+    /// its mailbox, which suits control messages sent alongside bulk
+    /// data.
+    pub async fn send_with_priority<R, M>(
+        &self,
+        route: R,
+        msg: M,
+        priority: MessagePriority,
+    ) -> Result<()>
+    where
+        R: Into<Route>,
+        M: Message + Send + 'static,
+    {
+        self.send_from_address_impl(route.into(), msg, self.address(), Vec::new(), priority)
             .await
     }
 
@@ -552,8 +779,14 @@ impl Context {
         R: Into<Route>,
         M: Message + Send + 'static,
     {
-        self.send_from_address_impl(route.into(), msg, sending_address, Vec::new())
-            .await
+        self.send_from_address_impl(
+            route.into(),
+            msg,
+            sending_address,
+            Vec::new(),
+            MessagePriority::Normal,
+        )
+        .await
     }
 
     async fn send_from_address_impl<M>(
@@ -562,14 +795,44 @@ impl Context {
         msg: M,
         sending_address: Address,
         local_info: Vec<LocalInfo>,
+        priority: MessagePriority,
     ) -> Result<()>
     where
         M: Message + Send + 'static,
     {
+        let payload = msg.encode().unwrap();
+        self.send_payload_from_address(route, payload, sending_address, local_info, priority)
+            .await
+    }
+
+    async fn send_payload_from_address(
+        &self,
+        route: Route,
+        payload: Vec<u8>,
+        sending_address: Address,
+        local_info: Vec<LocalInfo>,
+        priority: MessagePriority,
+    ) -> Result<()> {
         // Check if the sender address exists
         if !self.mailboxes.contains(&sending_address) {
             return Err(Error::new_without_cause(Origin::Node, Kind::Invalid));
         }
+        self.route_payload(route, payload, sending_address, local_info, priority)
+            .await
+    }
+
+    /// Send a payload with `return_address` as return route, which may
+    /// belong to a detached context of this one
+    async fn route_payload(
+        &self,
+        route: Route,
+        payload: Vec<u8>,
+        return_address: Address,
+        local_info: Vec<LocalInfo>,
+        priority: MessagePriority,
+    ) -> Result<()> {
+        // Replace a leading `@alias` with the route it stands for
+        let route = self.expand_route_alias(route).await?;
 
         // First resolve the next hop in the route
         let (reply_tx, mut reply_rx) = small_channel();
@@ -586,12 +849,11 @@ impl Context {
             .take_sender()?;
 
         // Pack the payload into a TransportMessage
-        let payload = msg.encode().unwrap();
         let mut transport_msg = TransportMessage::v1(route.clone(), Route::new(), payload);
-        transport_msg.return_route.modify().append(sending_address);
+        transport_msg.return_route.modify().append(return_address);
 
         // Pack transport message into a LocalMessage wrapper
-        let local_msg = LocalMessage::new(transport_msg, local_info);
+        let local_msg = LocalMessage::new(transport_msg, local_info).with_priority(priority);
 
         // Pack local message into a RelayMessage wrapper
         let msg = RelayMessage::new(addr, local_msg, route, needs_wrapping);
@@ -614,7 +876,23 @@ impl Context {
     ///
     /// [`Context::send`]: crate::Context::send
     /// [`TransportMessage`]: ockam_core::TransportMessage
-    pub async fn forward(&self, local_msg: LocalMessage) -> Result<()> {
+    pub async fn forward(&self, mut local_msg: LocalMessage) -> Result<()> {
+        // Drop messages that have been forwarded too many times, this
+        // catches routing loops which would otherwise spin forever
+        if !local_msg.decrement_ttl() {
+            let onward = local_msg.transport().onward_route.clone();
+            warn!("Dropping message with expired TTL on route {}", onward);
+            return Err(Error::new(
+                Origin::Node,
+                Kind::Invalid,
+                NodeError::TtlExpired(onward),
+            ));
+        }
+
+        // Replace a leading `@alias` with the route it stands for
+        let onward = local_msg.transport().onward_route.clone();
+        local_msg.transport_mut().onward_route = self.expand_route_alias(onward).await?;
+
         // First resolve the next hop in the route
         let (reply_tx, mut reply_rx) = small_channel();
         let next = local_msg.transport().onward_route.next().unwrap(); // TODO: communicate bad routes
@@ -747,6 +1025,136 @@ impl Context {
             .is_ok()
     }
 
+    /// Tag one of the addresses of a worker with the purpose it serves
+    ///
+    /// Workers with several addresses, e.g. one for routed messages
+    /// and one for their API, tag them so that other workers can
+    /// find the right one with [`Context::address_of_type`].
+    pub async fn set_address_tag(
+        &self,
+        addr: impl Into<Address>,
+        tag: impl Into<String>,
+    ) -> Result<()> {
+        let (msg, mut rx) = NodeMessage::set_tag(addr.into(), tag.into());
+        self.sender
+            .send(msg)
+            .await
+            .map_err(NodeError::from_send_err)?;
+        rx.recv()
+            .await
+            .ok_or_else(|| NodeError::NodeState(NodeReason::Unknown).internal())??
+            .is_ok()
+    }
+
+    /// Return the address tagged with `tag` of the worker running at
+    /// `addr`, which can be any of the worker's addresses
+    ///
+    /// Returns `None` if no address of the worker has this tag, and an
+    /// error if no worker runs at `addr`.
+    pub async fn address_of_type(&self, addr: &Address, tag: &str) -> Result<Option<Address>> {
+        let (msg, mut rx) = NodeMessage::get_tagged(addr.clone(), tag.into());
+        self.sender
+            .send(msg)
+            .await
+            .map_err(NodeError::from_send_err)?;
+        rx.recv()
+            .await
+            .ok_or_else(|| NodeError::NodeState(NodeReason::Unknown).internal())??
+            .take_address()
+    }
+
+    /// Define a route alias on this node
+    ///
+    /// Once defined, `@name` can be used as a hop in any route sent
+    /// from this node and is replaced with `route` at routing time.
+    /// Defining an existing alias again replaces its route.
+    pub async fn set_route_alias(
+        &self,
+        name: impl Into<String>,
+        route: impl Into<Route>,
+    ) -> Result<()> {
+        let name = alias_name(name.into());
+        let route = route.into();
+        if route.iter().next().is_none() {
+            return Err(Error::new(
+                Origin::Node,
+                Kind::Invalid,
+                NodeError::InvalidRouteAlias(name),
+            ));
+        }
+
+        let (msg, mut rx) = NodeMessage::set_route_alias(name, route);
+        self.sender
+            .send(msg)
+            .await
+            .map_err(NodeError::from_send_err)?;
+        rx.recv()
+            .await
+            .ok_or_else(|| NodeError::NodeState(NodeReason::Unknown).internal())??
+            .is_ok()
+    }
+
+    /// Remove a route alias from this node
+    pub async fn remove_route_alias(&self, name: impl Into<String>) -> Result<()> {
+        let (msg, mut rx) = NodeMessage::remove_route_alias(alias_name(name.into()));
+        self.sender
+            .send(msg)
+            .await
+            .map_err(NodeError::from_send_err)?;
+        rx.recv()
+            .await
+            .ok_or_else(|| NodeError::NodeState(NodeReason::Unknown).internal())??
+            .is_ok()
+    }
+
+    /// Return the route a route alias stands for, if it is defined
+    pub async fn route_alias(&self, name: impl Into<String>) -> Result<Option<Route>> {
+        let (msg, mut rx) = NodeMessage::get_route_alias(alias_name(name.into()));
+        self.sender
+            .send(msg)
+            .await
+            .map_err(NodeError::from_send_err)?;
+        rx.recv()
+            .await
+            .ok_or_else(|| NodeError::NodeState(NodeReason::Unknown).internal())??
+            .take_route()
+    }
+
+    /// Replace `@alias` hops at the front of `route` with the routes
+    /// they stand for
+    async fn expand_route_alias(&self, mut route: Route) -> Result<Route> {
+        for _ in 0..MAX_ALIAS_EXPANSIONS {
+            let name = match route.next() {
+                Ok(next) if next.transport_type().is_local() => {
+                    match next.address().strip_prefix(ROUTE_ALIAS_PREFIX) {
+                        Some(name) => String::from(name),
+                        None => return Ok(route),
+                    }
+                }
+                _ => return Ok(route),
+            };
+
+            let alias = self.route_alias(name.as_str()).await?.ok_or_else(|| {
+                Error::new(
+                    Origin::Node,
+                    Kind::NotFound,
+                    NodeError::UnknownRouteAlias(name),
+                )
+            })?;
+            route.modify().pop_front().prepend_route(alias);
+        }
+
+        let name = route
+            .next()?
+            .address()
+            .trim_start_matches(ROUTE_ALIAS_PREFIX);
+        Err(Error::new(
+            Origin::Node,
+            Kind::Invalid,
+            NodeError::InvalidRouteAlias(name.into()),
+        ))
+    }
+
     /// Return a list of all available worker addresses on a node
     pub async fn list_workers(&self) -> Result<Vec<Address>> {
         let (msg, mut reply_rx) = NodeMessage::list_workers();
@@ -763,6 +1171,44 @@ impl Context {
             .take_workers()
     }
 
+    /// Return the addresses and cluster of every worker and processor
+    /// running on a node
+    pub async fn list_worker_info(&self) -> Result<Vec<WorkerInfo>> {
+        let (msg, mut reply_rx) = NodeMessage::list_worker_info();
+
+        self.sender
+            .send(msg)
+            .await
+            .map_err(NodeError::from_send_err)?;
+
+        reply_rx
+            .recv()
+            .await
+            .ok_or_else(|| NodeError::NodeState(NodeReason::Unknown).internal())??
+            .take_worker_info()
+    }
+
+    /// Check whether a local address (primary or alias) resolves to a
+    /// running worker or processor on this node
+    pub async fn is_address_resolvable(&self, addr: &Address) -> Result<bool> {
+        if !addr.is_local() {
+            return Err(NodeError::Address(addr.clone()).internal());
+        }
+
+        let (msg, mut reply_rx) = NodeMessage::sender_request(addr.clone());
+
+        self.sender
+            .send(msg)
+            .await
+            .map_err(NodeError::from_send_err)?;
+
+        Ok(reply_rx
+            .recv()
+            .await
+            .ok_or_else(|| NodeError::NodeState(NodeReason::Unknown).internal())?
+            .is_ok())
+    }
+
     /// Register a router for a specific address type
     pub async fn register<A: Into<Address>>(&self, type_: TransportType, addr: A) -> Result<()> {
         self.register_impl(type_, addr.into()).await
@@ -849,3 +1295,11 @@ impl Context {
         Ok(())
     }
 }
+
+/// Accept alias names with or without their `@` prefix
+fn alias_name(name: String) -> String {
+    match name.strip_prefix(ROUTE_ALIAS_PREFIX) {
+        Some(name) => name.into(),
+        None => name,
+    }
+}
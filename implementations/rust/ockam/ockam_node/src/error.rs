@@ -24,6 +24,14 @@ pub enum NodeError {
     WorkerState(WorkerReason),
     /// A failure occurred because of invalid address router state
     RouterState(RouterReason),
+    /// A message exceeded its hop limit, most likely because of a routing loop
+    TtlExpired(Route),
+    /// A route referenced an alias which is not defined on this node
+    UnknownRouteAlias(String),
+    /// A route alias is empty or expands into itself
+    InvalidRouteAlias(String),
+    /// A runtime configuration is out of bounds
+    InvalidRuntimeConfig(String),
 }
 
 impl NodeError {
@@ -39,6 +47,10 @@ impl NodeError {
     pub fn conflict(self) -> Error {
         Error::new(Origin::Node, Kind::Conflict, self)
     }
+    /// Turn a NodeError into a Kind::ResourceExhausted ockam_core::Error
+    pub fn resource_exhausted(self) -> Error {
+        Error::new(Origin::Node, Kind::ResourceExhausted, self)
+    }
     /// Turn a NodeError into a Kind::Internal ockam_core::Error
     pub fn internal(self) -> Error {
         Error::new(Origin::Node, Kind::Internal, self)
@@ -73,6 +85,16 @@ impl fmt::Display for NodeError {
                 Self::NodeState(reason) => format!("failed because node state: {}", reason),
                 Self::WorkerState(reason) => format!("failed because worker state: {}", reason),
                 Self::RouterState(reason) => format!("failed because router state: {}", reason),
+                Self::TtlExpired(route) => format!("message TTL expired on route {}", route),
+                Self::UnknownRouteAlias(name) => {
+                    format!("route alias '@{}' is not defined on this node", name)
+                }
+                Self::InvalidRouteAlias(name) => {
+                    format!("route alias '@{}' is empty or expands into itself", name)
+                }
+                Self::InvalidRuntimeConfig(reason) => {
+                    format!("invalid runtime configuration: {}", reason)
+                }
             }
         )
     }
@@ -116,6 +138,10 @@ pub enum NodeReason {
     Shutdown,
     /// The node has been corrupted
     Corrupt,
+    /// The node runs as many workers as its resource limits allow
+    WorkerLimit,
+    /// The node uses more memory than its resource limits hint at
+    MemoryLimit,
 }
 
 impl fmt::Display for NodeReason {
@@ -127,6 +153,8 @@ impl fmt::Display for NodeReason {
                 Self::Unknown => "unknown node state",
                 Self::Shutdown => "ockam node is shutting down",
                 Self::Corrupt => "ockam node is corrupt and can not be recovered",
+                Self::WorkerLimit => "ockam node reached its maximum number of workers",
+                Self::MemoryLimit => "ockam node exceeded its memory hint",
             }
         )
     }
@@ -142,6 +170,8 @@ pub enum WorkerReason {
     Faulty,
     /// The worker is otherwise corrupt and can not be recovered
     Corrupt,
+    /// The worker panicked while handling a message
+    Panicked,
 }
 
 impl fmt::Display for WorkerReason {
@@ -153,6 +183,7 @@ impl fmt::Display for WorkerReason {
                 Self::Shutdown => "target worker is shutting down",
                 Self::Faulty => "target worker is faulty and waiting for supervisor",
                 Self::Corrupt => "target worker is corrupt and can not be recovered",
+                Self::Panicked => "target worker panicked while handling a message",
             }
         )
     }
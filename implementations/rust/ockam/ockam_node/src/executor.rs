@@ -4,7 +4,7 @@ use crate::channel_types::SmallSender;
 use crate::{
     router::{Router, SenderPair},
     tokio::runtime::{Handle, Runtime},
-    NodeMessage,
+    NodeMessage, ResourceLimits,
 };
 use core::future::Future;
 use ockam_core::{Address, Result};
@@ -40,7 +40,18 @@ pub struct Executor {
 
 impl Default for Executor {
     fn default() -> Self {
-        let rt = Runtime::new().unwrap();
+        Self::with_runtime(Runtime::new().unwrap())
+    }
+}
+
+impl Executor {
+    /// Create a new Ockam node [`Executor`] instance
+    pub fn new() -> Self {
+        Executor::default()
+    }
+
+    /// Create an [`Executor`] running on `rt`
+    pub(crate) fn with_runtime(rt: Runtime) -> Self {
         let router = Router::new();
         #[cfg(feature = "metrics")]
         let metrics = Metrics::new(&rt, router.get_metrics_readout());
@@ -51,13 +62,6 @@ impl Default for Executor {
             metrics,
         }
     }
-}
-
-impl Executor {
-    /// Create a new Ockam node [`Executor`] instance
-    pub fn new() -> Self {
-        Executor::default()
-    }
 
     /// Get access to the internal message sender
     pub(crate) fn sender(&self) -> SmallSender<NodeMessage> {
@@ -69,6 +73,11 @@ impl Executor {
         self.rt.handle()
     }
 
+    /// Reject new workers past `limits`
+    pub(crate) fn set_resource_limits(&mut self, limits: ResourceLimits) {
+        self.router.set_resource_limits(limits);
+    }
+
     /// Initialize the root application worker
     pub(crate) fn initialize_system<S: Into<Address>>(&mut self, address: S, senders: SenderPair) {
         trace!("Initializing node executor");
@@ -94,11 +103,21 @@ impl Executor {
         #[cfg(feature = "metrics")]
         self.rt.spawn(self.metrics.clone().run(alive.clone()));
 
+        // Then the memory sampler, if the resource limits need one
+        let memory_sampler = self
+            .router
+            .memory_sampler()
+            .map(|sampler| self.rt.spawn(sampler));
+
         // Spawn user code second
         let join_body = self.rt.spawn(future);
 
         // Then block on the execution of the router
-        self.rt.block_on(self.router.run())?;
+        let router_result = self.rt.block_on(self.router.run());
+        if let Some(memory_sampler) = memory_sampler {
+            memory_sampler.abort();
+        }
+        router_result?;
 
         // Shut down metrics collector
         #[cfg(feature = "metrics")]
@@ -0,0 +1,211 @@
+use crate::access_control::LocalOriginOnly;
+use crate::{Context, WorkerBuilder};
+use ockam_core::compat::{boxed::Box, sync::Arc};
+use ockam_core::{
+    async_trait, AccessControl, Address, Any, Encodable, LocalInfo, LocalMessage, Result, Routed,
+    Worker,
+};
+
+/// Type of the [`LocalInfo`] an interceptor marks the messages it let
+/// through with, holding its address
+const INTERCEPTOR_LOCAL_INFO: &str = "INTERCEPTOR_LOCAL_INFO";
+
+/// Observes, modifies or drops the messages sent to a worker before the
+/// worker receives them
+///
+/// Where an [`AccessControl`](ockam_core::AccessControl) can only let a
+/// message through or not, an interceptor can also change it, which suits
+/// logging, metrics and authorization alike. An interceptor wrapped in an
+/// [`Arc`] can sit in front of several workers.
+#[async_trait]
+pub trait Interceptor: Send + Sync + 'static {
+    /// Process `local_msg` on its way to the worker, returning false to
+    /// drop it
+    async fn intercept(&self, local_msg: &mut LocalMessage) -> Result<bool>;
+}
+
+#[async_trait]
+impl<T: Interceptor> Interceptor for Arc<T> {
+    async fn intercept(&self, local_msg: &mut LocalMessage) -> Result<bool> {
+        T::intercept(self, local_msg).await
+    }
+}
+
+/// Only admits the messages let through by the interceptor at
+/// `interceptor`, which sits in front of the worker
+#[derive(Debug)]
+struct InterceptorOnly {
+    interceptor: Address,
+}
+
+#[async_trait]
+impl AccessControl for InterceptorOnly {
+    async fn is_authorized(&self, local_msg: &LocalMessage) -> Result<bool> {
+        let marked = local_msg.local_info().iter().any(|info| {
+            info.type_identifier() == INTERCEPTOR_LOCAL_INFO
+                && self.interceptor.encode().ok().as_deref() == Some(info.data())
+        });
+        Ok(marked && LocalOriginOnly.is_authorized(local_msg).await?)
+    }
+}
+
+/// A worker which passes the messages it receives through an
+/// [`Interceptor`], then forwards them to the worker it sits in front of
+///
+/// Replies of that worker go directly to the sender. The worker behind
+/// the interceptor is started at a random address, and only accepts the
+/// messages the interceptor let through.
+pub struct InterceptorWorker<I> {
+    interceptor: I,
+    inner_address: Address,
+}
+
+impl<I: Interceptor> InterceptorWorker<I> {
+    /// Start an interceptor at `address`, in front of `worker`
+    pub async fn create<W>(
+        ctx: &Context,
+        address: impl Into<Address>,
+        worker: W,
+        interceptor: I,
+    ) -> Result<()>
+    where
+        W: Worker<Context = Context>,
+    {
+        let address = address.into();
+        let inner_address = Address::random_local();
+        let access_control = InterceptorOnly {
+            interceptor: address.clone(),
+        };
+        WorkerBuilder::with_access_control(access_control, inner_address.clone(), worker)
+            .start(ctx)
+            .await?;
+
+        let interceptor = Self {
+            interceptor,
+            inner_address,
+        };
+        ctx.start_worker(address, interceptor).await
+    }
+}
+
+#[async_trait]
+impl<I: Interceptor> Worker for InterceptorWorker<I> {
+    type Message = Any;
+    type Context = Context;
+
+    async fn handle_message(&mut self, ctx: &mut Context, msg: Routed<Any>) -> Result<()> {
+        let mut local_msg = msg.into_local_message();
+        if !self.interceptor.intercept(&mut local_msg).await? {
+            debug!(
+                "Message for {} was dropped by its interceptor",
+                self.inner_address
+            );
+            return Ok(());
+        }
+
+        local_msg.replace_local_info(LocalInfo::new(
+            INTERCEPTOR_LOCAL_INFO.into(),
+            ctx.address().encode()?,
+        ));
+        let transport = local_msg.transport_mut();
+        transport.onward_route.step()?;
+        transport
+            .onward_route
+            .modify()
+            .prepend(self.inner_address.clone());
+        ctx.forward(local_msg).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Context, Interceptor, InterceptorWorker};
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use core::time::Duration;
+    use ockam_core::compat::{
+        boxed::Box,
+        string::{String, ToString},
+        sync::Arc,
+    };
+    use ockam_core::{
+        async_trait, route, Address, Decodable, LocalMessage, Result, Routed, Worker,
+    };
+
+    /// Counts the messages for the workers it sits in front of, dropping
+    /// the empty ones
+    #[derive(Default)]
+    struct LoggingInterceptor {
+        msgs_count: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Interceptor for LoggingInterceptor {
+        async fn intercept(&self, local_msg: &mut LocalMessage) -> Result<bool> {
+            self.msgs_count.fetch_add(1, Ordering::Relaxed);
+            debug!("Intercepted {}", local_msg.transport().onward_route);
+            let body = String::decode(&local_msg.transport().payload)?;
+            Ok(!body.is_empty())
+        }
+    }
+
+    struct Echoer;
+
+    #[async_trait]
+    impl Worker for Echoer {
+        type Message = String;
+        type Context = Context;
+
+        async fn handle_message(&mut self, ctx: &mut Context, msg: Routed<String>) -> Result<()> {
+            ctx.send(msg.return_route(), msg.body()).await
+        }
+    }
+
+    #[allow(non_snake_case)]
+    #[ockam_macros::test(crate = "crate")]
+    async fn interceptor__counts_messages__for_downstream_workers(ctx: &mut Context) -> Result<()> {
+        let interceptor = Arc::new(LoggingInterceptor::default());
+        for name in ["echoer_1", "echoer_2"] {
+            InterceptorWorker::create(ctx, name, Echoer, interceptor.clone()).await?;
+        }
+
+        ctx.send(route!["echoer_1"], "Hello".to_string()).await?;
+        assert_eq!(ctx.receive::<String>().await?.take().body(), "Hello");
+        ctx.send(route!["echoer_2"], "World".to_string()).await?;
+        assert_eq!(ctx.receive::<String>().await?.take().body(), "World");
+
+        // Dropped before it reaches the worker
+        ctx.send(route!["echoer_1"], "".to_string()).await?;
+        assert!(ctx
+            .receive_duration_timeout::<String>(Duration::from_millis(200))
+            .await
+            .is_err());
+
+        assert_eq!(interceptor.msgs_count.load(Ordering::Relaxed), 3);
+        ctx.stop().await
+    }
+
+    #[allow(non_snake_case)]
+    #[ockam_macros::test(crate = "crate")]
+    async fn interceptor__is_not_bypassed__by_sending_to_the_worker(
+        ctx: &mut Context,
+    ) -> Result<()> {
+        let interceptor = Arc::new(LoggingInterceptor::default());
+        InterceptorWorker::create(ctx, "echoer", Echoer, interceptor.clone()).await?;
+
+        ctx.send(route!["echoer"], "Hello".to_string()).await?;
+        let msg = ctx.receive::<String>().await?.take();
+        assert_eq!(msg.body(), "Hello");
+
+        // The echoer replies directly, which reveals its address
+        let inner_address = msg.return_route().next()?.clone();
+        assert_ne!(inner_address, Address::from_string("echoer"));
+        ctx.send(route![inner_address], "Hello".to_string()).await?;
+        assert!(ctx
+            .receive_duration_timeout::<String>(Duration::from_millis(200))
+            .await
+            .is_err());
+
+        assert_eq!(interceptor.msgs_count.load(Ordering::Relaxed), 1);
+        ctx.stop().await
+    }
+}
@@ -45,28 +45,45 @@ pub mod access_control;
 pub mod api;
 
 mod async_drop;
+mod call;
 mod cancel;
 mod context;
 mod delayed;
 mod error;
 mod executor;
+mod interceptor;
 mod local_info;
 mod messages;
 mod node;
 mod parser;
+mod priority_queue;
 mod relay;
+mod resource_limits;
 mod router;
 mod worker_builder;
 
+#[cfg(feature = "std")]
+mod mailbox_limit;
+#[cfg(feature = "std")]
+mod runtime_config;
+
+pub use call::Correlated;
 pub use cancel::*;
 pub use context::*;
 pub use delayed::*;
 pub use error::*;
 pub use executor::*;
+pub use interceptor::*;
 pub use local_info::*;
 pub use messages::*;
+pub use resource_limits::ResourceLimits;
 pub use worker_builder::WorkerBuilder;
 
+#[cfg(feature = "std")]
+pub use mailbox_limit::MailboxOverflow;
+#[cfg(feature = "std")]
+pub use runtime_config::*;
+
 pub use node::{NodeBuilder, NullWorker};
 
 #[cfg(feature = "std")]
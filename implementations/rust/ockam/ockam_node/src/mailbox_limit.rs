@@ -0,0 +1,112 @@
+use crate::channel_types::{message_channel, MessageReceiver, MessageSender};
+use crate::relay::RelayMessage;
+use crate::tokio::runtime::Handle;
+use ockam_core::compat::collections::VecDeque;
+
+/// What happens to a message sent to a worker whose mailbox is full
+///
+/// See [`WorkerBuilder::with_mailbox_limit`](crate::WorkerBuilder::with_mailbox_limit)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MailboxOverflow {
+    /// The sender waits until the worker has made room
+    Block,
+    /// The message being sent is dropped
+    DropNewest,
+    /// The oldest queued message is dropped to make room
+    DropOldest,
+}
+
+/// Bound on the number of messages queued for a worker
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct MailboxLimit {
+    capacity: usize,
+    overflow: MailboxOverflow,
+}
+
+impl MailboxLimit {
+    pub(crate) fn new(capacity: usize, overflow: MailboxOverflow) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            overflow,
+        }
+    }
+
+    /// Capacity of the channel the worker's context reads from
+    pub(crate) fn channel_capacity(&self) -> usize {
+        match self.overflow {
+            MailboxOverflow::Block => self.capacity,
+            // The rest of the mailbox is queued by the pump
+            MailboxOverflow::DropNewest | MailboxOverflow::DropOldest => 1,
+        }
+    }
+
+    /// Return the sender to register with the router in place of
+    /// `mailbox`, the sender of the context's channel
+    ///
+    /// Senders are never blocked when messages are dropped on
+    /// overflow, so the policy is applied by a task queueing messages
+    /// between the router and the worker.
+    pub(crate) fn wrap_sender(
+        &self,
+        rt: &Handle,
+        mailbox: MessageSender<RelayMessage>,
+    ) -> MessageSender<RelayMessage> {
+        if self.overflow == MailboxOverflow::Block {
+            return mailbox;
+        }
+
+        let (tx, rx) = message_channel();
+        rt.spawn(pump(rx, mailbox, self.capacity - 1, self.overflow));
+        tx
+    }
+}
+
+/// Move messages from `rx` to the worker's single-slot `mailbox`,
+/// queueing at most `queue_size` of them in between
+async fn pump(
+    mut rx: MessageReceiver<RelayMessage>,
+    mailbox: MessageSender<RelayMessage>,
+    queue_size: usize,
+    overflow: MailboxOverflow,
+) {
+    let mut queue = VecDeque::new();
+    loop {
+        crate::tokio::select! {
+            msg = rx.recv() => {
+                let msg = match msg {
+                    Some(msg) => msg,
+                    // The worker was stopped
+                    None => break,
+                };
+                queue.push_back(msg);
+
+                // Fill the worker's slot first if it's free
+                while !queue.is_empty() {
+                    match mailbox.try_reserve() {
+                        Ok(permit) => permit.send(queue.pop_front().unwrap()),
+                        Err(_) => break,
+                    }
+                }
+
+                if queue.len() > queue_size {
+                    let dropped = match overflow {
+                        MailboxOverflow::DropOldest => queue.pop_front(),
+                        _ => queue.pop_back(),
+                    };
+                    if let Some(dropped) = dropped {
+                        warn!(
+                            "Mailbox of '{}' is full, dropping a message ({:?})",
+                            dropped.addr, overflow
+                        );
+                    }
+                }
+            },
+            permit = mailbox.reserve(), if !queue.is_empty() => {
+                match permit {
+                    Ok(permit) => permit.send(queue.pop_front().unwrap()),
+                    Err(_) => break,
+                }
+            },
+        }
+    }
+}
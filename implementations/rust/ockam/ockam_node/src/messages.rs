@@ -6,7 +6,7 @@ use crate::{
 };
 use core::{fmt, sync::atomic::AtomicUsize};
 use ockam_core::compat::{string::String, sync::Arc, vec::Vec};
-use ockam_core::{Address, AddressSet, Error, Result, TransportType};
+use ockam_core::{Address, AddressSet, Error, Result, Route, TransportType};
 
 /// Messages sent from the Node to the Executor
 #[derive(Debug)]
@@ -26,8 +26,20 @@ pub enum NodeMessage {
     },
     /// Return a list of all worker addresses
     ListWorkers(SmallSender<NodeReplyResult>),
+    /// Return the addresses and cluster of every worker and processor
+    ListWorkerInfo(SmallSender<NodeReplyResult>),
     /// Add an existing address to a cluster
     SetCluster(Address, String, SmallSender<NodeReplyResult>),
+    /// Tag an existing address with the purpose it serves
+    SetTag(Address, String, SmallSender<NodeReplyResult>),
+    /// Find the address with a given tag among the addresses of a worker
+    GetTagged(Address, String, SmallSender<NodeReplyResult>),
+    /// Define a named route which can be referenced as `@name`
+    SetRouteAlias(String, Route, SmallSender<NodeReplyResult>),
+    /// Remove a named route
+    RemoveRouteAlias(String, SmallSender<NodeReplyResult>),
+    /// Look up the route a name refers to
+    GetRouteAlias(String, SmallSender<NodeReplyResult>),
     /// Stop an existing worker
     StopWorker(Address, bool, SmallSender<NodeReplyResult>),
     /// Start a new processor
@@ -55,7 +67,13 @@ impl fmt::Display for NodeMessage {
         match self {
             NodeMessage::StartWorker { .. } => write!(f, "StartWorker"),
             NodeMessage::ListWorkers(_) => write!(f, "ListWorkers"),
+            NodeMessage::ListWorkerInfo(_) => write!(f, "ListWorkerInfo"),
             NodeMessage::SetCluster(_, _, _) => write!(f, "SetCluster"),
+            NodeMessage::SetTag(_, _, _) => write!(f, "SetTag"),
+            NodeMessage::GetTagged(_, _, _) => write!(f, "GetTagged"),
+            NodeMessage::SetRouteAlias(_, _, _) => write!(f, "SetRouteAlias"),
+            NodeMessage::RemoveRouteAlias(_, _) => write!(f, "RemoveRouteAlias"),
+            NodeMessage::GetRouteAlias(_, _) => write!(f, "GetRouteAlias"),
             NodeMessage::StopWorker(_, _, _) => write!(f, "StopWorker"),
             NodeMessage::StartProcessor(_, _, _) => write!(f, "StartProcessor"),
             NodeMessage::StopProcessor(_, _) => write!(f, "StopProcessor"),
@@ -119,12 +137,48 @@ impl NodeMessage {
         (Self::ListWorkers(tx), rx)
     }
 
+    /// Create a list worker info message and reply receiver
+    pub fn list_worker_info() -> (Self, SmallReceiver<NodeReplyResult>) {
+        let (tx, rx) = small_channel();
+        (Self::ListWorkerInfo(tx), rx)
+    }
+
     /// Create a set cluster message and reply receiver
     pub fn set_cluster(addr: Address, label: String) -> (Self, SmallReceiver<NodeReplyResult>) {
         let (tx, rx) = small_channel();
         (Self::SetCluster(addr, label, tx), rx)
     }
 
+    /// Create a set tag message and reply receiver
+    pub fn set_tag(addr: Address, tag: String) -> (Self, SmallReceiver<NodeReplyResult>) {
+        let (tx, rx) = small_channel();
+        (Self::SetTag(addr, tag, tx), rx)
+    }
+
+    /// Create a get tagged message and reply receiver
+    pub fn get_tagged(addr: Address, tag: String) -> (Self, SmallReceiver<NodeReplyResult>) {
+        let (tx, rx) = small_channel();
+        (Self::GetTagged(addr, tag, tx), rx)
+    }
+
+    /// Create a set route alias message and reply receiver
+    pub fn set_route_alias(name: String, route: Route) -> (Self, SmallReceiver<NodeReplyResult>) {
+        let (tx, rx) = small_channel();
+        (Self::SetRouteAlias(name, route, tx), rx)
+    }
+
+    /// Create a remove route alias message and reply receiver
+    pub fn remove_route_alias(name: String) -> (Self, SmallReceiver<NodeReplyResult>) {
+        let (tx, rx) = small_channel();
+        (Self::RemoveRouteAlias(name, tx), rx)
+    }
+
+    /// Create a get route alias message and reply receiver
+    pub fn get_route_alias(name: String) -> (Self, SmallReceiver<NodeReplyResult>) {
+        let (tx, rx) = small_channel();
+        (Self::GetRouteAlias(name, tx), rx)
+    }
+
     /// Create a stop worker message and reply receiver
     pub fn stop_worker(address: Address, detached: bool) -> (Self, SmallReceiver<NodeReplyResult>) {
         let (tx, rx) = small_channel();
@@ -165,6 +219,8 @@ pub enum RouterReply {
     Ok,
     /// A list of worker addresses
     Workers(Vec<Address>),
+    /// Registration details of every worker and processor
+    WorkerInfo(Vec<WorkerInfo>),
     /// Message sender to a specific worker
     Sender {
         /// The address a message is being sent to
@@ -177,6 +233,23 @@ pub enum RouterReply {
     },
     /// Indicate the 'ready' state of an address
     State(bool),
+    /// An address, if one was found
+    Address(Option<Address>),
+    /// A route, if one was found
+    Route(Option<Route>),
+}
+
+/// Registration details of a worker or processor running on a node
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkerInfo {
+    /// The primary address of the worker
+    pub address: Address,
+    /// All addresses of the worker, starting with the primary one
+    pub addresses: Vec<Address>,
+    /// The cluster the worker was added to, if any
+    pub cluster: Option<String>,
+    /// Whether this is a processor rather than a worker
+    pub processor: bool,
 }
 
 /// Specify the type of node shutdown
@@ -253,6 +326,11 @@ impl RouterReply {
         Err(NodeError::NodeState(reason).conflict())
     }
 
+    /// Return [NodeReply::Rejected(reason)] for a node out of resources
+    pub fn node_exhausted(reason: NodeReason) -> NodeReplyResult {
+        Err(NodeError::NodeState(reason).resource_exhausted())
+    }
+
     /// Return [NodeReply::Rejected(reason)]
     pub fn worker_rejected(reason: WorkerReason) -> NodeReplyResult {
         Err(NodeError::WorkerState(reason).conflict())
@@ -263,6 +341,21 @@ impl RouterReply {
         Ok(Self::Workers(v))
     }
 
+    /// Return [NodeReply::WorkerInfo] for the given workers
+    pub fn worker_info(v: Vec<WorkerInfo>) -> NodeReplyResult {
+        Ok(Self::WorkerInfo(v))
+    }
+
+    /// Return [NodeReply::Address] for the given address
+    pub fn address(a: Option<Address>) -> NodeReplyResult {
+        Ok(Self::Address(a))
+    }
+
+    /// Return [NodeReply::Route] for the given route
+    pub fn route(r: Option<Route>) -> NodeReplyResult {
+        Ok(Self::Route(r))
+    }
+
     /// Return [NodeReply::Sender] for the given information
     pub fn sender(
         addr: Address,
@@ -288,6 +381,30 @@ impl RouterReply {
         }
     }
 
+    /// Consume the wrapper and return [NodeReply::WorkerInfo]
+    pub fn take_worker_info(self) -> Result<Vec<WorkerInfo>> {
+        match self {
+            Self::WorkerInfo(w) => Ok(w),
+            _ => Err(NodeError::NodeState(NodeReason::Unknown).internal()),
+        }
+    }
+
+    /// Consume the wrapper and return [NodeReply::Address]
+    pub fn take_address(self) -> Result<Option<Address>> {
+        match self {
+            Self::Address(a) => Ok(a),
+            _ => Err(NodeError::NodeState(NodeReason::Unknown).internal()),
+        }
+    }
+
+    /// Consume the wrapper and return [NodeReply::Route]
+    pub fn take_route(self) -> Result<Option<Route>> {
+        match self {
+            Self::Route(r) => Ok(r),
+            _ => Err(NodeError::NodeState(NodeReason::Unknown).internal()),
+        }
+    }
+
     /// Consume the wrapper and return [NodeReply::State]
     pub fn take_state(self) -> Result<bool> {
         match self {
@@ -1,4 +1,6 @@
-use crate::{Context, Executor};
+#[cfg(feature = "std")]
+use crate::RuntimeConfig;
+use crate::{Context, Executor, ResourceLimits};
 use ockam_core::compat::sync::Arc;
 use ockam_core::{AccessControl, Address, AllowAll, Mailbox, Mailboxes};
 
@@ -21,6 +23,9 @@ where
 {
     access_control: AC,
     logging: bool,
+    resource_limits: ResourceLimits,
+    #[cfg(feature = "std")]
+    runtime_config: Option<RuntimeConfig>,
 }
 
 impl NodeBuilder<AllowAll> {
@@ -29,6 +34,9 @@ impl NodeBuilder<AllowAll> {
         Self {
             access_control: AllowAll,
             logging: true,
+            resource_limits: ResourceLimits::default(),
+            #[cfg(feature = "std")]
+            runtime_config: None,
         }
     }
 }
@@ -42,6 +50,9 @@ where
         Self {
             access_control,
             logging: true,
+            resource_limits: ResourceLimits::default(),
+            #[cfg(feature = "std")]
+            runtime_config: None,
         }
     }
 
@@ -53,6 +64,24 @@ where
         }
     }
 
+    /// Run the node on a runtime sized by `runtime_config`, instead of the
+    /// runtime defaults
+    #[cfg(feature = "std")]
+    pub fn with_runtime_config(self, runtime_config: RuntimeConfig) -> Self {
+        Self {
+            runtime_config: Some(runtime_config),
+            ..self
+        }
+    }
+
+    /// Limit the resources the node may use, see [`ResourceLimits`]
+    pub fn with_resource_limits(self, resource_limits: ResourceLimits) -> Self {
+        Self {
+            resource_limits,
+            ..self
+        }
+    }
+
     /// Consume this builder and yield a new Ockam Node
     #[inline]
     pub fn build(self) -> (Context, Executor) {
@@ -65,7 +94,14 @@ where
             self.access_control
         );
 
+        #[cfg(feature = "std")]
+        let mut exe = match self.runtime_config {
+            Some(runtime_config) => Executor::with_runtime(runtime_config.build_runtime()),
+            None => Executor::new(),
+        };
+        #[cfg(not(feature = "std"))]
         let mut exe = Executor::new();
+        exe.set_resource_limits(self.resource_limits);
         let addr: Address = "app".into();
 
         // The root application worker needs a mailbox and relay to accept
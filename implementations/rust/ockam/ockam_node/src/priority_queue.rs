@@ -0,0 +1,27 @@
+use crate::relay::RelayMessage;
+use ockam_core::compat::collections::VecDeque;
+use ockam_core::MessagePriority;
+
+/// Messages taken from a worker's channel but not handled yet
+///
+/// Messages are kept in one FIFO queue per [`MessagePriority`], and the
+/// oldest message of the highest priority comes out first.
+#[derive(Default)]
+pub(crate) struct PriorityQueue {
+    queues: [VecDeque<RelayMessage>; MessagePriority::COUNT],
+}
+
+impl PriorityQueue {
+    pub(crate) fn push(&mut self, msg: RelayMessage) {
+        let priority = msg.local_msg.priority();
+        self.queues[priority.index()].push_back(msg);
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<RelayMessage> {
+        self.queues.iter_mut().rev().find_map(VecDeque::pop_front)
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.queues.iter().map(VecDeque::len).sum()
+    }
+}
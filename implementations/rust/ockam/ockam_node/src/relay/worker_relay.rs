@@ -3,6 +3,7 @@ use crate::relay::CtrlSignal;
 use crate::relay::RelayMessage;
 use crate::tokio::runtime::Handle;
 use crate::{parser, Context};
+use core::future::Future;
 use core::marker::PhantomData;
 use ockam_core::{Message, Result, Routed, Worker};
 
@@ -75,7 +76,8 @@ where
 
         // Call the worker authorization function - pass errors up
         let routed = Self::wrap_direct_message(&relay_msg)?;
-        if !self.worker.is_authorized(&mut self.ctx, routed).await? {
+        let authorized = catch_panic(&relay_msg, self.worker.is_authorized(&mut self.ctx, routed));
+        if !authorized.await? {
             warn!(
                 "Message for {} did not pass worker relay access control",
                 relay_msg.addr
@@ -85,7 +87,11 @@ where
 
         // Call the worker handle function - pass errors up
         let routed = Self::wrap_direct_message(&relay_msg)?;
-        self.worker.handle_message(&mut self.ctx, routed).await?;
+        catch_panic(
+            &relay_msg,
+            self.worker.handle_message(&mut self.ctx, routed),
+        )
+        .await?;
 
         // Signal to the outer loop that we would like to run again
         Ok(true)
@@ -153,8 +159,10 @@ where
             }
         }
 
-        // Run the shutdown hook for this worker
-        match self.worker.shutdown(&mut self.ctx).await {
+        // Run the shutdown hook for this worker, exactly once whichever
+        // way the loop ended
+        let shutdown = catch_shutdown_panic(&address, self.worker.shutdown(&mut self.ctx));
+        match shutdown.await {
             Ok(()) => {}
             Err(e) => {
                 error!(
@@ -178,3 +186,89 @@ where
         rt.spawn(relay.run(ctrl_rx));
     }
 }
+
+/// Run a worker callback for `relay_msg`, turning a panic into an error
+///
+/// Without this a panic unwinds out of the relay task, which then never
+/// handles another message nor acknowledges its shutdown. The worker is
+/// kept running, as it is after a callback returns an error.
+#[cfg(feature = "std")]
+async fn catch_panic<T>(
+    relay_msg: &RelayMessage,
+    call: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    use crate::compat::futures::FutureExt;
+    use crate::{NodeError, WorkerReason};
+
+    match std::panic::AssertUnwindSafe(call).catch_unwind().await {
+        Ok(result) => result,
+        Err(payload) => {
+            error!(
+                "Worker '{}' panicked handling a message from {}: {}",
+                relay_msg.addr,
+                relay_msg.local_msg.transport().return_route,
+                panic_message(&*payload)
+            );
+            Err(NodeError::WorkerState(WorkerReason::Panicked).internal())
+        }
+    }
+}
+
+#[cfg(not(feature = "std"))]
+async fn catch_panic<T>(
+    _relay_msg: &RelayMessage,
+    call: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    call.await
+}
+
+/// How long the shutdown hook of a worker may run before its stop is
+/// acknowledged anyway
+#[cfg(feature = "std")]
+pub(crate) const WORKER_SHUTDOWN_TIMEOUT: core::time::Duration = core::time::Duration::from_secs(5);
+
+/// Run the shutdown hook of the worker at `address` for at most
+/// [`WORKER_SHUTDOWN_TIMEOUT`], turning a panic or a timeout into an error
+/// so that the stop is still acknowledged
+#[cfg(feature = "std")]
+async fn catch_shutdown_panic(
+    address: &ockam_core::Address,
+    call: impl Future<Output = Result<()>>,
+) -> Result<()> {
+    use crate::compat::futures::FutureExt;
+    use crate::{NodeError, WorkerReason};
+
+    let call = std::panic::AssertUnwindSafe(call).catch_unwind();
+    match crate::tokio::time::timeout(WORKER_SHUTDOWN_TIMEOUT, call).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(payload)) => {
+            error!(
+                "Worker '{}' panicked during shutdown: {}",
+                address,
+                panic_message(&*payload)
+            );
+            Err(NodeError::WorkerState(WorkerReason::Panicked).internal())
+        }
+        Err(e) => Err(NodeError::WorkerState(WorkerReason::Shutdown).with_elapsed(e)),
+    }
+}
+
+#[cfg(not(feature = "std"))]
+async fn catch_shutdown_panic(
+    _address: &ockam_core::Address,
+    call: impl Future<Output = Result<()>>,
+) -> Result<()> {
+    call.await
+}
+
+/// The message a panic was raised with, if it has one
+#[cfg(feature = "std")]
+pub(crate) fn panic_message(payload: &(dyn core::any::Any + Send)) -> &str {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s
+    } else {
+        "<non-string panic payload>"
+    }
+}
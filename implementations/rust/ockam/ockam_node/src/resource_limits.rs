@@ -0,0 +1,122 @@
+use crate::NodeReason;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use ockam_core::compat::sync::Arc;
+
+/// How often the resident memory is measured when a memory hint is set
+#[cfg(feature = "std")]
+const MEMORY_SAMPLE_INTERVAL: core::time::Duration = core::time::Duration::from_secs(1);
+
+/// Resources a node may use, so that a runaway node doesn't starve the
+/// other nodes running on the same host
+///
+/// Once a limit is reached, the node rejects new workers, and thus new
+/// channels, while the workers already running keep running. Unset limits
+/// don't apply.
+///
+/// ```
+/// # use ockam_node::{NodeBuilder, ResourceLimits};
+/// let limits = ResourceLimits::new()
+///     .max_workers(1000)
+///     .memory_hint(256 * 1024 * 1024);
+/// let builder = NodeBuilder::without_access_control().with_resource_limits(limits);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ResourceLimits {
+    max_workers: Option<usize>,
+    memory_hint: Option<usize>,
+}
+
+impl ResourceLimits {
+    /// No limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run at most `max_workers` workers. Detached contexts and processors
+    /// aren't counted
+    pub fn max_workers(self, max_workers: usize) -> Self {
+        Self {
+            max_workers: Some(max_workers),
+            ..self
+        }
+    }
+
+    /// Reject new workers while the resident memory of the process exceeds
+    /// `memory_hint` bytes
+    ///
+    /// The memory is only measured on Linux, the hint is ignored elsewhere.
+    pub fn memory_hint(self, memory_hint: usize) -> Self {
+        Self {
+            memory_hint: Some(memory_hint),
+            ..self
+        }
+    }
+
+    /// Whether the resident memory needs to be sampled
+    #[cfg(feature = "std")]
+    pub(crate) fn has_memory_hint(&self) -> bool {
+        self.memory_hint.is_some()
+    }
+
+    /// Why a new worker must be rejected, when `workers` are running and
+    /// the process last used `memory`
+    pub(crate) fn check(&self, workers: usize, memory: &MemoryUsage) -> Option<NodeReason> {
+        if matches!(self.max_workers, Some(max_workers) if workers >= max_workers) {
+            return Some(NodeReason::WorkerLimit);
+        }
+        let memory_hint = self.memory_hint?;
+        match memory.get() {
+            Some(memory) if memory > memory_hint => Some(NodeReason::MemoryLimit),
+            _ => None,
+        }
+    }
+}
+
+/// Resident memory of the process, as last sampled
+///
+/// Measuring it means reading `/proc`, which the router mustn't wait for
+/// when starting a worker, so it's sampled by a separate task and the
+/// router only reads the last sample.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct MemoryUsage(Arc<AtomicUsize>);
+
+impl MemoryUsage {
+    /// Last sampled resident memory, in bytes, unless never measured
+    pub(crate) fn get(&self) -> Option<usize> {
+        match self.0.load(Ordering::Relaxed) {
+            0 => None,
+            memory => Some(memory),
+        }
+    }
+
+    /// Sample the resident memory every [`MEMORY_SAMPLE_INTERVAL`], until
+    /// the task is aborted
+    #[cfg(feature = "std")]
+    pub(crate) async fn sample(self) {
+        loop {
+            if let Some(memory) = resident_memory() {
+                self.0.store(memory, Ordering::Relaxed);
+            }
+            crate::tokio::time::sleep(MEMORY_SAMPLE_INTERVAL).await;
+        }
+    }
+}
+
+/// Resident memory of the process, in bytes
+#[cfg(all(feature = "std", target_os = "linux"))]
+fn resident_memory() -> Option<usize> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let kb = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .split_whitespace()
+        .next()?
+        .parse::<usize>()
+        .ok()?;
+    Some(kb * 1024)
+}
+
+#[cfg(all(feature = "std", not(target_os = "linux")))]
+fn resident_memory() -> Option<usize> {
+    None
+}
@@ -17,10 +17,11 @@ use crate::channel_types::{router_channel, MessageSender, RouterReceiver, SmallS
 use crate::{
     error::{NodeError, NodeReason},
     relay::{CtrlSignal, RelayMessage},
-    NodeMessage, NodeReplyResult, RouterReply, ShutdownType,
+    resource_limits::MemoryUsage,
+    NodeMessage, NodeReplyResult, ResourceLimits, RouterReply, ShutdownType,
 };
-use ockam_core::compat::{collections::BTreeMap, sync::Arc};
-use ockam_core::{Address, Result, TransportType};
+use ockam_core::compat::{collections::BTreeMap, string::String, sync::Arc};
+use ockam_core::{Address, Result, Route, TransportType};
 
 /// A pair of senders to a worker relay
 #[derive(Debug)]
@@ -45,6 +46,12 @@ pub struct Router {
     map: InternalMap,
     /// Externally registered router components
     external: BTreeMap<TransportType, Address>,
+    /// Named routes which can be referenced as `@name` in other routes
+    aliases: BTreeMap<String, Route>,
+    /// Resources the node may use
+    limits: ResourceLimits,
+    /// Last sampled memory of the process, checked against `limits`
+    memory: MemoryUsage,
     /// Receiver for messages from node
     receiver: Option<RouterReceiver<NodeMessage>>,
 }
@@ -69,6 +76,9 @@ impl Router {
             state: RouterState::new(sender),
             map: InternalMap::default(),
             external: BTreeMap::new(),
+            aliases: BTreeMap::new(),
+            limits: ResourceLimits::default(),
+            memory: MemoryUsage::default(),
             receiver: Some(receiver),
         }
     }
@@ -102,6 +112,20 @@ impl Router {
         self.map.addr_map.insert(addr.clone(), addr);
     }
 
+    /// Reject new workers past `limits`
+    pub(crate) fn set_resource_limits(&mut self, limits: ResourceLimits) {
+        self.limits = limits;
+    }
+
+    /// The task sampling the memory of the process, when the resource
+    /// limits need it
+    #[cfg(feature = "std")]
+    pub(crate) fn memory_sampler(&self) -> Option<impl core::future::Future<Output = ()>> {
+        self.limits
+            .has_memory_hint()
+            .then(|| self.memory.clone().sample())
+    }
+
     pub fn sender(&self) -> SmallSender<NodeMessage> {
         self.state.sender.clone()
     }
@@ -247,6 +271,11 @@ impl Router {
                 .await
                 .map_err(|_| NodeError::NodeState(NodeReason::Unknown).internal())?,
 
+            ListWorkerInfo(sender) => sender
+                .send(RouterReply::worker_info(self.map.worker_info()))
+                .await
+                .map_err(|_| NodeError::NodeState(NodeReason::Unknown).internal())?,
+
             SetCluster(addr, label, reply) => {
                 debug!("Setting cluster on address {}", addr);
                 let msg = self.map.set_cluster(label, addr);
@@ -256,6 +285,52 @@ impl Router {
                     .map_err(|_| NodeError::NodeState(NodeReason::Unknown).internal())?;
             }
 
+            SetTag(addr, tag, reply) => {
+                debug!("Tagging address {} as '{}'", addr, tag);
+                let msg = self.map.set_tag(addr, tag);
+                reply
+                    .send(msg)
+                    .await
+                    .map_err(|_| NodeError::NodeState(NodeReason::Unknown).internal())?;
+            }
+
+            GetTagged(addr, tag, reply) => {
+                let msg = self.map.get_tagged(addr, &tag);
+                reply
+                    .send(msg)
+                    .await
+                    .map_err(|_| NodeError::NodeState(NodeReason::Unknown).internal())?;
+            }
+
+            SetRouteAlias(name, route, reply) => {
+                debug!("Setting route alias @{} to {}", name, route);
+                self.aliases.insert(name, route);
+                reply
+                    .send(RouterReply::ok())
+                    .await
+                    .map_err(|_| NodeError::NodeState(NodeReason::Unknown).internal())?;
+            }
+
+            RemoveRouteAlias(name, reply) => {
+                debug!("Removing route alias @{}", name);
+                let msg = match self.aliases.remove(&name) {
+                    Some(_) => RouterReply::ok(),
+                    None => Err(NodeError::UnknownRouteAlias(name).not_found()),
+                };
+                reply
+                    .send(msg)
+                    .await
+                    .map_err(|_| NodeError::NodeState(NodeReason::Unknown).internal())?;
+            }
+
+            GetRouteAlias(name, reply) => {
+                let msg = RouterReply::route(self.aliases.get(&name).cloned());
+                reply
+                    .send(msg)
+                    .await
+                    .map_err(|_| NodeError::NodeState(NodeReason::Unknown).internal())?;
+            }
+
             SetReady(addr) => {
                 trace!("Marking address {} as ready!", addr);
                 match self.map.set_ready(addr) {
@@ -2,7 +2,7 @@ use crate::channel_types::{MessageSender, SmallSender};
 use crate::relay::{CtrlSignal, RelayMessage};
 use crate::{
     error::{NodeError, NodeReason},
-    NodeReplyResult, RouterReply,
+    NodeReplyResult, RouterReply, WorkerInfo,
 };
 use core::sync::atomic::{AtomicUsize, Ordering};
 use ockam_core::{
@@ -74,6 +74,56 @@ impl InternalMap {
         RouterReply::ok()
     }
 
+    /// Tag an address with the purpose it serves for its worker
+    pub(super) fn set_tag(&mut self, addr: Address, tag: String) -> NodeReplyResult {
+        let primary = self
+            .addr_map
+            .get(&addr)
+            .ok_or_else(|| NodeError::Address(addr.clone()).not_found())?;
+        let rec = self
+            .internal
+            .get_mut(primary)
+            .ok_or_else(|| NodeError::Address(addr.clone()).not_found())?;
+        rec.set_tag(tag, addr);
+        RouterReply::ok()
+    }
+
+    /// Find the address tagged with `tag` among the addresses of the
+    /// worker `addr` belongs to
+    pub(super) fn get_tagged(&self, addr: Address, tag: &str) -> NodeReplyResult {
+        let rec = self
+            .addr_map
+            .get(&addr)
+            .and_then(|primary| self.internal.get(primary))
+            .ok_or_else(|| NodeError::Address(addr).not_found())?;
+        RouterReply::address(rec.tagged(tag).cloned())
+    }
+
+    /// Number of workers, without detached contexts and processors
+    pub(super) fn worker_count(&self) -> usize {
+        self.internal
+            .values()
+            .filter(|rec| !rec.meta.detached && !rec.meta.processor)
+            .count()
+    }
+
+    /// Describe every registered worker and processor
+    pub(super) fn worker_info(&self) -> Vec<WorkerInfo> {
+        self.internal
+            .iter()
+            .map(|(primary, rec)| WorkerInfo {
+                address: primary.clone(),
+                addresses: rec.address_set().iter().cloned().collect(),
+                cluster: self
+                    .clusters
+                    .iter()
+                    .find(|(_, addrs)| addrs.contains(primary))
+                    .map(|(label, _)| label.clone()),
+                processor: rec.meta.processor,
+            })
+            .collect()
+    }
+
     /// Set an address as ready and return the list of waiting pollers
     pub(super) fn set_ready(&mut self, addr: Address) -> Result<Vec<SmallSender<NodeReplyResult>>> {
         let addr_record = self
@@ -169,6 +219,8 @@ pub struct AddressRecord {
     ready: ReadyState,
     meta: AddressMeta,
     msg_count: Arc<AtomicUsize>,
+    /// Addresses of the set, by the purpose they were tagged with
+    tags: BTreeMap<String, Address>,
 }
 
 impl AddressRecord {
@@ -196,6 +248,7 @@ impl AddressRecord {
             ready: ReadyState::Initialising(vec![]),
             msg_count,
             meta,
+            tags: BTreeMap::new(),
         }
     }
 
@@ -234,6 +287,16 @@ impl AddressRecord {
         }
     }
 
+    /// Tag one of the addresses of this record
+    pub fn set_tag(&mut self, tag: String, addr: Address) {
+        self.tags.insert(tag, addr);
+    }
+
+    /// The address tagged with `tag`, if any
+    pub fn tagged(&self, tag: &str) -> Option<&Address> {
+        self.tags.get(tag)
+    }
+
     /// Mark this address as 'ready' and return the list of active pollers
     pub fn set_ready(&mut self) -> Vec<SmallSender<NodeReplyResult>> {
         let waiting = core::mem::replace(&mut self.ready, ReadyState::Ready);
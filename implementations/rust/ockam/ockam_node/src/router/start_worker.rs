@@ -17,8 +17,30 @@ pub(super) async fn exec(
     reply: &SmallSender<NodeReplyResult>,
 ) -> Result<()> {
     match router.state.node_state() {
-        NodeState::Running => start(router, addrs, senders, detached, metrics, reply).await,
-        NodeState::Stopping(_) => reject(reply).await,
+        NodeState::Running => {
+            // Detached contexts aren't workers, and aren't limited
+            let limit = match detached {
+                true => None,
+                false => router
+                    .limits
+                    .check(router.map.worker_count(), &router.memory),
+            };
+            match limit {
+                Some(reason) => {
+                    warn!(
+                        "Not starting worker '{}': {}",
+                        addrs.first(),
+                        NodeError::NodeState(reason)
+                    );
+                    reject(reply, RouterReply::node_exhausted(reason)).await
+                }
+                None => start(router, addrs, senders, detached, metrics, reply).await,
+            }
+        }
+        NodeState::Stopping(_) => {
+            trace!("StartWorker command rejected: node shutting down");
+            reject(reply, RouterReply::node_rejected(NodeReason::Shutdown)).await
+        }
         NodeState::Dead => unreachable!(),
     }?;
     Ok(())
@@ -81,10 +103,9 @@ async fn start(
     Ok(())
 }
 
-async fn reject(reply: &SmallSender<NodeReplyResult>) -> Result<()> {
-    trace!("StartWorker command rejected: node shutting down");
+async fn reject(reply: &SmallSender<NodeReplyResult>, rejection: NodeReplyResult) -> Result<()> {
     reply
-        .send(RouterReply::node_rejected(NodeReason::Shutdown))
+        .send(rejection)
         .await
         .map_err(|_| NodeError::NodeState(NodeReason::Unknown).internal())?;
     Ok(())
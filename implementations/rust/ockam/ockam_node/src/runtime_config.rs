@@ -0,0 +1,89 @@
+use crate::tokio::runtime::{Builder, Runtime};
+use crate::NodeError;
+use ockam_core::errcode::{Kind, Origin};
+use ockam_core::{Error, Result};
+
+/// Most worker threads a node runtime may be configured with
+pub const MAX_WORKER_THREADS: usize = 512;
+
+/// Most threads the blocking pool of a node runtime may be configured with
+pub const MAX_BLOCKING_THREADS: usize = 4096;
+
+/// Sizes of the thread pools of the async runtime a node runs on
+///
+/// Unset sizes keep the runtime defaults: one worker thread per CPU core,
+/// and up to 512 threads for blocking tasks.
+///
+/// ```
+/// # use ockam_node::{NodeBuilder, RuntimeConfig};
+/// # fn main() -> ockam_core::Result<()> {
+/// let config = RuntimeConfig::new()
+///     .worker_threads(2)?
+///     .max_blocking_threads(16)?;
+/// let builder = NodeBuilder::without_access_control().with_runtime_config(config);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RuntimeConfig {
+    worker_threads: Option<usize>,
+    max_blocking_threads: Option<usize>,
+}
+
+impl RuntimeConfig {
+    /// Configuration keeping the runtime defaults
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run async tasks on `worker_threads` threads, between 1 and
+    /// [`MAX_WORKER_THREADS`]
+    pub fn worker_threads(self, worker_threads: usize) -> Result<Self> {
+        check_bounds("worker_threads", worker_threads, MAX_WORKER_THREADS)?;
+        Ok(Self {
+            worker_threads: Some(worker_threads),
+            ..self
+        })
+    }
+
+    /// Run blocking tasks on at most `max_blocking_threads` threads,
+    /// between 1 and [`MAX_BLOCKING_THREADS`]
+    pub fn max_blocking_threads(self, max_blocking_threads: usize) -> Result<Self> {
+        check_bounds(
+            "max_blocking_threads",
+            max_blocking_threads,
+            MAX_BLOCKING_THREADS,
+        )?;
+        Ok(Self {
+            max_blocking_threads: Some(max_blocking_threads),
+            ..self
+        })
+    }
+
+    /// Build a runtime with this configuration
+    pub(crate) fn build_runtime(&self) -> Runtime {
+        let mut builder = Builder::new_multi_thread();
+        builder.enable_all();
+        if let Some(worker_threads) = self.worker_threads {
+            builder.worker_threads(worker_threads);
+        }
+        if let Some(max_blocking_threads) = self.max_blocking_threads {
+            builder.max_blocking_threads(max_blocking_threads);
+        }
+        builder.build().unwrap()
+    }
+}
+
+fn check_bounds(name: &str, value: usize, max: usize) -> Result<()> {
+    if value == 0 || value > max {
+        return Err(Error::new(
+            Origin::Node,
+            Kind::Invalid,
+            NodeError::InvalidRuntimeConfig(format!(
+                "{} must be between 1 and {}, got {}",
+                name, max, value
+            )),
+        ));
+    }
+    Ok(())
+}
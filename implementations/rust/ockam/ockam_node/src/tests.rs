@@ -1,5 +1,7 @@
 use crate::compat::futures::FutureExt;
-use crate::{Context, NodeBuilder};
+use crate::{
+    Context, Correlated, MailboxOverflow, NodeBuilder, ResourceLimits, RuntimeConfig, WorkerBuilder,
+};
 use core::sync::atomic::{AtomicBool, Ordering};
 use core::time::Duration;
 use ockam_core::compat::{
@@ -7,10 +9,12 @@ use ockam_core::compat::{
     string::{String, ToString},
     sync::Arc,
 };
-use ockam_core::{async_trait, Address, Any, Decodable, Message, LOCAL};
+use ockam_core::errcode::Kind;
+use ockam_core::{async_trait, Address, Any, Decodable, Message, MessagePriority, LOCAL};
 use ockam_core::{route, Processor, Result, Routed, Worker};
 use serde::{Deserialize, Serialize};
-use std::sync::atomic::{AtomicI8, AtomicU32};
+use std::sync::atomic::{AtomicI8, AtomicU32, AtomicUsize};
+use std::sync::Mutex;
 use tokio::time::sleep;
 
 #[allow(non_snake_case)]
@@ -530,6 +534,55 @@ async fn use_context_send_and_receive(ctx: &mut Context) -> Result<()> {
     ctx.stop().await
 }
 
+/// Answers the requests in the reverse order, once it got all of them
+struct ReversingWorker {
+    expected: usize,
+    requests: Vec<Routed<Correlated<String>>>,
+}
+
+#[async_trait]
+impl Worker for ReversingWorker {
+    type Context = Context;
+    type Message = Correlated<String>;
+
+    async fn handle_message(
+        &mut self,
+        ctx: &mut Context,
+        msg: Routed<Correlated<String>>,
+    ) -> Result<()> {
+        self.requests.push(msg);
+        if self.requests.len() < self.expected {
+            return Ok(());
+        }
+        while let Some(request) = self.requests.pop() {
+            let reply = request.reply(format!("reply to {}", request.as_body().body()));
+            ctx.send(request.return_route(), reply).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Test that concurrent calls sharing the same return address each get
+/// the reply to their own request
+#[ockam_macros::test(crate = "crate")]
+async fn concurrent_calls_get_their_own_reply(ctx: &mut Context) -> Result<()> {
+    ctx.start_worker(
+        "reversing_worker",
+        ReversingWorker {
+            expected: 4,
+            requests: Vec::new(),
+        },
+    )
+    .await?;
+
+    let calls = (0..4).map(|i| ctx.call::<_, _, String>("reversing_worker", i.to_string()));
+    let replies = futures::future::join_all(calls).await;
+    for (i, reply) in replies.into_iter().enumerate() {
+        assert_eq!(reply?, format!("reply to {}", i));
+    }
+    ctx.stop().await
+}
+
 struct DummyWorker;
 
 #[async_trait]
@@ -560,3 +613,595 @@ async fn starting_worker_with_dup_address_should_fail(ctx: &mut Context) -> Resu
     assert!(ctx.start_worker("dummy_worker", DummyWorker).await.is_err());
     ctx.stop().await
 }
+
+struct LoopWorker {
+    next: Address,
+    report: Address,
+    hops: Arc<AtomicU32>,
+}
+
+#[async_trait]
+impl Worker for LoopWorker {
+    type Message = Any;
+    type Context = Context;
+
+    async fn handle_message(&mut self, ctx: &mut Context, msg: Routed<Any>) -> Result<()> {
+        self.hops.fetch_add(1, Ordering::Relaxed);
+        let mut local_msg = msg.into_local_message();
+        local_msg.transport_mut().onward_route = route![self.next.clone()];
+        if ctx.forward(local_msg).await.is_err() {
+            ctx.send(self.report.clone(), String::from("dropped"))
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+/// Test that a message caught in a routing loop is dropped once its
+/// TTL is exhausted instead of being forwarded forever.
+#[ockam_macros::test(crate = "crate")]
+async fn routing_loop_is_dropped_after_ttl(ctx: &mut Context) -> Result<()> {
+    let hops = Arc::new(AtomicU32::new(0));
+    ctx.start_worker(
+        "ping",
+        LoopWorker {
+            next: "pong".into(),
+            report: ctx.address(),
+            hops: hops.clone(),
+        },
+    )
+    .await?;
+    ctx.start_worker(
+        "pong",
+        LoopWorker {
+            next: "ping".into(),
+            report: ctx.address(),
+            hops: hops.clone(),
+        },
+    )
+    .await?;
+
+    ctx.send(route!["ping"], String::from("loop")).await?;
+    let msg = ctx.receive::<String>().await?;
+    assert_eq!(msg.take().body(), "dropped");
+
+    // The initial delivery plus one per allowed forward
+    assert_eq!(
+        hops.load(Ordering::Relaxed),
+        ockam_core::DEFAULT_TTL as u32 + 1
+    );
+    ctx.stop().await
+}
+
+/// Records its messages, and holds on to the first one until released
+struct StuckWorker {
+    received: Arc<Mutex<Vec<String>>>,
+    release: Arc<AtomicBool>,
+}
+
+#[async_trait]
+impl Worker for StuckWorker {
+    type Message = String;
+    type Context = Context;
+
+    async fn handle_message(&mut self, _ctx: &mut Context, msg: Routed<String>) -> Result<()> {
+        self.received.lock().unwrap().push(msg.body());
+        while !self.release.load(Ordering::Relaxed) {
+            sleep(Duration::from_millis(10)).await;
+        }
+        Ok(())
+    }
+}
+
+/// Start a `StuckWorker` with a mailbox of 2 messages and flood it
+/// while it's stuck on its first message
+async fn flood_bounded_mailbox(
+    ctx: &mut Context,
+    overflow: MailboxOverflow,
+) -> Result<(Vec<String>, usize)> {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let release = Arc::new(AtomicBool::new(false));
+    WorkerBuilder::without_access_control(
+        "stuck",
+        StuckWorker {
+            received: received.clone(),
+            release: release.clone(),
+        },
+    )
+    .with_mailbox_limit(2, overflow)
+    .start(ctx)
+    .await?;
+
+    ctx.send(route!["stuck"], "0".to_string()).await?;
+    while received.lock().unwrap().is_empty() {
+        sleep(Duration::from_millis(10)).await;
+    }
+
+    let mut accepted = 0;
+    for i in 1..=5 {
+        let send = ctx.send(route!["stuck"], i.to_string());
+        if tokio::time::timeout(Duration::from_millis(100), send)
+            .await
+            .is_ok()
+        {
+            accepted += 1;
+        }
+    }
+
+    release.store(true, Ordering::Relaxed);
+    sleep(Duration::from_millis(200)).await;
+    let received = received.lock().unwrap().clone();
+    Ok((received, accepted))
+}
+
+#[ockam_macros::test(crate = "crate")]
+async fn bounded_mailbox_blocks_senders(ctx: &mut Context) -> Result<()> {
+    let (received, accepted) = flood_bounded_mailbox(ctx, MailboxOverflow::Block).await?;
+    assert_eq!(accepted, 2);
+    assert_eq!(received, ["0", "1", "2"]);
+    ctx.stop().await
+}
+
+#[ockam_macros::test(crate = "crate")]
+async fn bounded_mailbox_drops_newest(ctx: &mut Context) -> Result<()> {
+    let (received, accepted) = flood_bounded_mailbox(ctx, MailboxOverflow::DropNewest).await?;
+    assert_eq!(accepted, 5);
+    assert_eq!(received, ["0", "1", "2"]);
+    ctx.stop().await
+}
+
+#[ockam_macros::test(crate = "crate")]
+async fn bounded_mailbox_drops_oldest(ctx: &mut Context) -> Result<()> {
+    let (received, accepted) = flood_bounded_mailbox(ctx, MailboxOverflow::DropOldest).await?;
+    assert_eq!(accepted, 5);
+    assert_eq!(received, ["0", "1", "5"]);
+    ctx.stop().await
+}
+
+/// Takes a while to handle each message
+struct SlowWorker {
+    started: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl Worker for SlowWorker {
+    type Message = String;
+    type Context = Context;
+
+    async fn handle_message(&mut self, _ctx: &mut Context, _msg: Routed<String>) -> Result<()> {
+        self.started.fetch_add(1, Ordering::SeqCst);
+        sleep(Duration::from_millis(20)).await;
+        Ok(())
+    }
+}
+
+#[ockam_macros::test(crate = "crate")]
+async fn bounded_mailbox_blocks_senders_of_a_slow_worker(ctx: &mut Context) -> Result<()> {
+    let started = Arc::new(AtomicUsize::new(0));
+    WorkerBuilder::without_access_control(
+        "slow",
+        SlowWorker {
+            started: started.clone(),
+        },
+    )
+    .with_mailbox_limit(2, MailboxOverflow::Block)
+    .start(ctx)
+    .await?;
+
+    // Messages sent but not handled yet, one of them being possibly on
+    // its way to the worker
+    let mut max_queued = 0;
+    for sent in 1..=20 {
+        ctx.send(route!["slow"], sent.to_string()).await?;
+        max_queued = max_queued.max(sent - started.load(Ordering::SeqCst));
+    }
+    assert!(max_queued <= 3, "{} messages were queued", max_queued);
+
+    ctx.stop().await
+}
+
+#[ockam_macros::test(crate = "crate")]
+async fn high_priority_message_overtakes_backlog(ctx: &mut Context) -> Result<()> {
+    let mut receiver = ctx.new_detached("priority_receiver").await?;
+    for i in 0..5 {
+        ctx.send(route!["priority_receiver"], format!("bulk {}", i))
+            .await?;
+    }
+    ctx.send_with_priority(
+        route!["priority_receiver"],
+        "control".to_string(),
+        MessagePriority::High,
+    )
+    .await?;
+
+    assert_eq!(receiver.receive::<String>().await?, "control".to_string());
+    // Bulk messages keep their order
+    for i in 0..5 {
+        assert_eq!(receiver.receive::<String>().await?, format!("bulk {}", i));
+    }
+    ctx.stop().await
+}
+
+#[ockam_macros::test(crate = "crate")]
+async fn address_of_type_selects_tagged_address(ctx: &mut Context) -> Result<()> {
+    let main: Address = "tagged_main".into();
+    let api: Address = "tagged_api".into();
+    ctx.start_worker(vec![main.clone(), api.clone()], DummyWorker)
+        .await?;
+    ctx.set_address_tag(api.clone(), "api").await?;
+
+    // Any address of the worker can be used to look up the tagged one
+    assert_eq!(ctx.address_of_type(&main, "api").await?, Some(api.clone()));
+    assert_eq!(ctx.address_of_type(&api, "api").await?, Some(api.clone()));
+    assert_eq!(ctx.address_of_type(&main, "metrics").await?, None);
+
+    let unknown: Address = "unknown".into();
+    assert!(ctx.address_of_type(&unknown, "api").await.is_err());
+    assert!(ctx.set_address_tag(unknown, "api").await.is_err());
+
+    // Tags go away with the worker
+    ctx.stop_worker(main.clone()).await?;
+    sleep(Duration::from_millis(100)).await;
+    assert!(ctx.address_of_type(&main, "api").await.is_err());
+
+    ctx.stop().await
+}
+
+#[ockam_macros::test(crate = "crate")]
+async fn list_worker_info_reports_addresses_and_clusters(ctx: &mut Context) -> Result<()> {
+    let main: Address = "info_main".into();
+    let api: Address = "info_api".into();
+    ctx.start_worker(vec![main.clone(), api.clone()], DummyWorker)
+        .await?;
+    let clustered = ctx.new_detached("info_clustered").await?;
+    clustered.set_cluster("info_cluster").await?;
+
+    let info = ctx.list_worker_info().await?;
+    let worker = info.iter().find(|w| w.address == main).unwrap();
+    assert_eq!(worker.addresses, vec![main.clone(), api.clone()]);
+    assert_eq!(worker.cluster, None);
+    assert!(!worker.processor);
+
+    let worker = info
+        .iter()
+        .find(|w| w.address == clustered.address())
+        .unwrap();
+    assert_eq!(worker.cluster, Some("info_cluster".to_string()));
+
+    // Aliases aren't listed as workers of their own
+    assert!(info.iter().all(|w| w.address != api));
+
+    ctx.stop().await
+}
+
+/// Sets its flag when dropped
+struct DropFlag(Arc<AtomicBool>);
+
+impl Drop for DropFlag {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+struct SpawningWorker {
+    cancelled: Arc<AtomicBool>,
+}
+
+#[async_trait]
+impl Worker for SpawningWorker {
+    type Message = String;
+    type Context = Context;
+
+    async fn initialize(&mut self, ctx: &mut Self::Context) -> Result<()> {
+        let flag = DropFlag(self.cancelled.clone());
+        ctx.spawn(async move {
+            let _flag = flag;
+            core::future::pending::<()>().await;
+        });
+        Ok(())
+    }
+
+    async fn handle_message(&mut self, _ctx: &mut Context, _msg: Routed<String>) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[ockam_macros::test(crate = "crate")]
+async fn spawned_task_is_cancelled_when_owner_stops(ctx: &mut Context) -> Result<()> {
+    let cancelled = Arc::new(AtomicBool::new(false));
+    ctx.start_worker(
+        "spawning_worker",
+        SpawningWorker {
+            cancelled: cancelled.clone(),
+        },
+    )
+    .await?;
+
+    sleep(Duration::from_millis(100)).await;
+    assert!(!cancelled.load(Ordering::SeqCst));
+
+    ctx.stop_worker("spawning_worker").await?;
+    sleep(Duration::from_millis(100)).await;
+    assert!(cancelled.load(Ordering::SeqCst));
+
+    ctx.stop().await
+}
+
+/// Forwards messages to the next hop of their onward route
+struct HopWorker;
+
+#[async_trait]
+impl Worker for HopWorker {
+    type Message = Any;
+    type Context = Context;
+
+    async fn handle_message(&mut self, ctx: &mut Context, msg: Routed<Any>) -> Result<()> {
+        let mut local_msg = msg.into_local_message();
+        let transport = local_msg.transport_mut();
+        transport.onward_route.step()?;
+        transport.return_route.modify().prepend(ctx.address());
+        ctx.forward(local_msg).await
+    }
+}
+
+#[ockam_macros::test(crate = "crate")]
+async fn route_alias_is_expanded_at_routing_time(ctx: &mut Context) -> Result<()> {
+    ctx.start_worker("hop_a", HopWorker).await?;
+    ctx.start_worker("hop_b", HopWorker).await?;
+    let mut sink = ctx.new_detached("alias_sink").await?;
+
+    ctx.set_route_alias("payments", route!["hop_a", "hop_b", "alias_sink"])
+        .await?;
+    ctx.set_route_alias("@via_a", route!["hop_a", "@unused"])
+        .await?;
+    assert_eq!(
+        ctx.route_alias("@payments").await?,
+        Some(route!["hop_a", "hop_b", "alias_sink"])
+    );
+
+    ctx.send(route!["@payments"], String::from("hello")).await?;
+    let msg = sink.receive::<String>().await?.take();
+    assert_eq!(msg.return_route(), route!["hop_b", "hop_a", ctx.address()]);
+    assert_eq!(msg.body(), "hello");
+
+    // Aliases are expanded by every hop which routes the message
+    ctx.set_route_alias("unused", route!["alias_sink"]).await?;
+    ctx.send(route!["@via_a"], String::from("nested")).await?;
+    assert_eq!(sink.receive::<String>().await?.take().body(), "nested");
+
+    // Undefined aliases are reported to the sender
+    let err = ctx
+        .send(route!["@missing"], String::from("lost"))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("'@missing' is not defined"));
+
+    // Aliases which only ever expand into themselves are rejected
+    ctx.set_route_alias("cycle", route!["@cycle"]).await?;
+    assert!(ctx.send(route!["@cycle"], String::from("x")).await.is_err());
+
+    ctx.remove_route_alias("payments").await?;
+    assert!(ctx.route_alias("payments").await?.is_none());
+    assert!(ctx.remove_route_alias("payments").await.is_err());
+
+    ctx.stop().await
+}
+
+struct ShutdownCountingWorker {
+    fail_initialize: bool,
+    panic_on_shutdown: bool,
+    shutdowns: Arc<AtomicU32>,
+}
+
+#[async_trait]
+impl Worker for ShutdownCountingWorker {
+    type Message = String;
+    type Context = Context;
+
+    async fn initialize(&mut self, _ctx: &mut Context) -> Result<()> {
+        if self.fail_initialize {
+            return Err(crate::NodeError::WorkerState(crate::WorkerReason::Faulty).internal());
+        }
+        Ok(())
+    }
+
+    async fn shutdown(&mut self, _ctx: &mut Context) -> Result<()> {
+        self.shutdowns.fetch_add(1, Ordering::SeqCst);
+        if self.panic_on_shutdown {
+            panic!("worker was asked to panic");
+        }
+        Ok(())
+    }
+
+    async fn handle_message(&mut self, _ctx: &mut Context, _msg: Routed<String>) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[ockam_macros::test(crate = "crate")]
+async fn shutdown_runs_once_when_a_worker_is_stopped(ctx: &mut Context) -> Result<()> {
+    for (fail_initialize, panic_on_shutdown) in [(false, false), (true, false), (false, true)] {
+        let shutdowns = Arc::new(AtomicU32::new(0));
+        let worker = ShutdownCountingWorker {
+            fail_initialize,
+            panic_on_shutdown,
+            shutdowns: shutdowns.clone(),
+        };
+        ctx.start_worker("counting", worker).await?;
+        ctx.send(route!["counting"], String::from("hello")).await?;
+
+        // A panicking hook doesn't keep the worker from stopping
+        ctx.stop_worker("counting").await?;
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(shutdowns.load(Ordering::SeqCst), 1);
+        assert!(!ctx.list_workers().await?.contains(&"counting".into()));
+    }
+
+    ctx.stop().await
+}
+
+struct HangingShutdownWorker;
+
+#[async_trait]
+impl Worker for HangingShutdownWorker {
+    type Message = String;
+    type Context = Context;
+
+    async fn shutdown(&mut self, _ctx: &mut Context) -> Result<()> {
+        futures::future::pending().await
+    }
+
+    async fn handle_message(&mut self, _ctx: &mut Context, _msg: Routed<String>) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[ockam_macros::test(crate = "crate", timeout = 15000)]
+async fn hanging_shutdown_does_not_keep_a_worker_running(ctx: &mut Context) -> Result<()> {
+    ctx.start_worker("hanging", HangingShutdownWorker).await?;
+    ctx.stop_worker("hanging").await?;
+
+    let deadline = crate::relay::WORKER_SHUTDOWN_TIMEOUT * 2;
+    let start = std::time::Instant::now();
+    while ctx.list_workers().await?.contains(&"hanging".into()) {
+        assert!(start.elapsed() < deadline, "the worker is still running");
+        sleep(Duration::from_millis(100)).await;
+    }
+
+    ctx.stop().await
+}
+
+#[test]
+fn panic_message_reads_payload() {
+    let payload = std::panic::catch_unwind(|| panic!("static message")).unwrap_err();
+    assert_eq!(crate::relay::panic_message(&*payload), "static message");
+
+    let payload = std::panic::catch_unwind(|| panic!("formatted {}", 42)).unwrap_err();
+    assert_eq!(crate::relay::panic_message(&*payload), "formatted 42");
+
+    let payload = std::panic::catch_unwind(|| std::panic::panic_any(42)).unwrap_err();
+    assert_eq!(
+        crate::relay::panic_message(&*payload),
+        "<non-string panic payload>"
+    );
+}
+
+/// Replies to every message with its body prefixed by the worker's name
+struct EchoWorker(&'static str);
+
+#[async_trait]
+impl Worker for EchoWorker {
+    type Message = String;
+    type Context = Context;
+
+    async fn handle_message(&mut self, ctx: &mut Context, msg: Routed<String>) -> Result<()> {
+        let reply = format!("{}: {}", self.0, msg.body());
+        ctx.send(msg.return_route(), reply).await
+    }
+}
+
+#[ockam_macros::test(crate = "crate")]
+async fn send_multicast_reports_each_route(ctx: &mut Context) -> Result<()> {
+    for name in ["echo1", "echo2", "echo3"] {
+        ctx.start_worker(name, EchoWorker(name)).await?;
+    }
+
+    let results = ctx
+        .send_multicast(
+            [
+                route!["echo1"],
+                route!["missing"],
+                route!["echo2"],
+                route!["echo3"],
+            ],
+            String::from("hello"),
+        )
+        .await;
+    assert_eq!(results.len(), 4);
+    assert!(results[0].is_ok());
+    assert!(results[1].is_err());
+    assert!(results[2].is_ok() && results[3].is_ok());
+
+    let mut replies = Vec::new();
+    for _ in 0..3 {
+        replies.push(ctx.receive::<String>().await?.take().body());
+    }
+    replies.sort();
+    assert_eq!(replies, ["echo1: hello", "echo2: hello", "echo3: hello"]);
+
+    ctx.stop().await
+}
+
+#[test]
+fn node_runs_on_a_single_worker_thread() {
+    let config = RuntimeConfig::new()
+        .worker_threads(1)
+        .unwrap()
+        .max_blocking_threads(1)
+        .unwrap();
+    let (mut ctx, mut executor) = NodeBuilder::without_access_control()
+        .no_logging()
+        .with_runtime_config(config)
+        .build();
+    executor
+        .execute(async move {
+            ctx.start_worker("echo", EchoWorker("echo")).await?;
+            for i in 0..10 {
+                let reply: String = ctx.send_and_receive("echo", i.to_string()).await?;
+                assert_eq!(reply, format!("echo: {}", i));
+            }
+            ctx.stop().await
+        })
+        .unwrap()
+        .unwrap()
+}
+
+#[test]
+fn runtime_config_is_bounded() {
+    assert!(RuntimeConfig::new().worker_threads(0).is_err());
+    assert!(RuntimeConfig::new()
+        .worker_threads(crate::MAX_WORKER_THREADS + 1)
+        .is_err());
+    assert!(RuntimeConfig::new().max_blocking_threads(0).is_err());
+    assert!(RuntimeConfig::new()
+        .max_blocking_threads(crate::MAX_BLOCKING_THREADS + 1)
+        .is_err());
+    assert!(RuntimeConfig::new()
+        .worker_threads(crate::MAX_WORKER_THREADS)
+        .is_ok());
+}
+
+#[test]
+fn worker_limit_rejects_new_workers() {
+    let limits = ResourceLimits::new().max_workers(8);
+    let (mut ctx, mut executor) = NodeBuilder::without_access_control()
+        .no_logging()
+        .with_resource_limits(limits)
+        .build();
+    executor
+        .execute(async move {
+            // Whatever the node already runs, new workers are rejected at
+            // the latest once the limit is reached
+            let mut started = 0;
+            let err = loop {
+                let address = format!("echo{}", started);
+                match ctx.start_worker(address.as_str(), EchoWorker("echo")).await {
+                    Ok(()) => started += 1,
+                    Err(e) => break e,
+                }
+                assert!(started <= 8);
+            };
+            assert_eq!(err.code().kind, Kind::ResourceExhausted);
+
+            // Workers already running keep running
+            let reply: String = ctx.send_and_receive("echo0", "hello".to_string()).await?;
+            assert_eq!(reply, "echo: hello");
+
+            // Detached contexts aren't limited
+            ctx.new_detached(Address::random_local()).await?;
+
+            ctx.stop().await
+        })
+        .unwrap()
+        .unwrap()
+}
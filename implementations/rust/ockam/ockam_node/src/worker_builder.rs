@@ -1,4 +1,7 @@
+use crate::channel_types::message_channel_with_capacity;
 use crate::error::{NodeError, NodeReason};
+#[cfg(feature = "std")]
+use crate::mailbox_limit::{MailboxLimit, MailboxOverflow};
 use crate::{relay::WorkerRelay, Context, NodeMessage};
 use ockam_core::compat::sync::Arc;
 use ockam_core::{
@@ -20,6 +23,8 @@ use ockam_core::{
 pub struct WorkerBuilder<W> {
     mailboxes: Mailboxes,
     worker: W,
+    #[cfg(feature = "std")]
+    mailbox_limit: Option<MailboxLimit>,
 }
 
 impl<M, W> WorkerBuilder<W>
@@ -34,7 +39,7 @@ where
     {
         let mailboxes = Mailboxes::from_address_set(address_set.into(), Arc::new(AllowAll));
 
-        Self { mailboxes, worker }
+        Self::with_mailboxes(mailboxes, worker)
     }
 
     /// Create a worker which inherits access control from the given context
@@ -56,7 +61,7 @@ where
 
         let mailboxes = Mailboxes::from_address_set(address_set, access_control);
 
-        Self { mailboxes, worker }
+        Self::with_mailboxes(mailboxes, worker)
     }
 
     /// Create a worker which uses the given access control
@@ -67,13 +72,29 @@ where
     {
         let mailboxes = Mailboxes::main(address.into(), Arc::new(access_control));
 
-        Self { mailboxes, worker }
+        Self::with_mailboxes(mailboxes, worker)
     }
 
     /// Create a worker which uses the access control from the given
     /// [`Mailboxes`]
     pub fn with_mailboxes(mailboxes: Mailboxes, worker: W) -> Self {
-        Self { mailboxes, worker }
+        Self {
+            mailboxes,
+            worker,
+            #[cfg(feature = "std")]
+            mailbox_limit: None,
+        }
+    }
+
+    /// Queue at most `capacity` messages for the worker, handling
+    /// messages beyond that according to `overflow`
+    ///
+    /// Without a limit senders wait once a small default number of
+    /// messages is queued.
+    #[cfg(feature = "std")]
+    pub fn with_mailbox_limit(mut self, capacity: usize, overflow: MailboxOverflow) -> Self {
+        self.mailbox_limit = Some(MailboxLimit::new(capacity, overflow));
+        self
     }
 
     /// Consume this builder and start a new Ockam [`Worker`] from the given context
@@ -88,14 +109,30 @@ where
         let addresses = mailboxes.addresses();
         let main_address = mailboxes.main_address().clone();
 
+        #[cfg(feature = "std")]
+        let mailbox_capacity = self.mailbox_limit.map(|limit| limit.channel_capacity());
+        #[cfg(not(feature = "std"))]
+        let mailbox_capacity = None;
+        let (mailbox_tx, mailbox_rx) =
+            message_channel_with_capacity(mailbox_capacity.unwrap_or(16));
+
         // Pass it to the context
-        let (ctx, sender, ctrl_rx) = Context::new(
+        #[allow(unused_mut)]
+        let (mut ctx, mut sender, ctrl_rx) = Context::new_with_mailbox(
             context.runtime().clone(),
             context.sender().clone(),
             mailboxes,
             None,
+            mailbox_tx,
+            mailbox_rx,
         );
 
+        #[cfg(feature = "std")]
+        if let Some(limit) = self.mailbox_limit {
+            sender.msgs = limit.wrap_sender(context.runtime(), sender.msgs);
+            ctx.disable_prefetch();
+        }
+
         // Then initialise the worker message relay
         WorkerRelay::<W, M>::init(context.runtime(), self.worker, ctx, ctrl_rx);
 
@@ -0,0 +1,101 @@
+//! A worker panicking while handling a message is logged, and the worker
+//! keeps running
+//!
+//! This test has its own binary since it installs the global tracing
+//! subscriber, to capture the logs of the node.
+
+use ockam_core::{async_trait, route, Result, Routed, Worker};
+use ockam_node::{Context, NodeBuilder};
+use std::io;
+use std::sync::{Arc, Mutex};
+use tracing_subscriber::fmt::MakeWriter;
+
+struct PanickingWorker;
+
+#[async_trait]
+impl Worker for PanickingWorker {
+    type Message = String;
+    type Context = Context;
+
+    async fn handle_message(&mut self, ctx: &mut Context, msg: Routed<String>) -> Result<()> {
+        if msg.as_body() == "panic" {
+            panic!("worker was asked to panic");
+        }
+        ctx.send(msg.return_route(), msg.body()).await
+    }
+}
+
+/// Log lines written by the node
+#[derive(Clone, Default)]
+struct Logs(Arc<Mutex<Vec<u8>>>);
+
+impl Logs {
+    fn lines(&self) -> Vec<String> {
+        String::from_utf8_lossy(&self.0.lock().unwrap())
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+}
+
+impl io::Write for Logs {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for Logs {
+    type Writer = Logs;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+#[test]
+fn worker_panic_is_logged_and_does_not_stop_the_node() {
+    let logs = Logs::default();
+    tracing_subscriber::fmt()
+        .with_writer(logs.clone())
+        .with_ansi(false)
+        .init();
+
+    let (mut ctx, mut executor) = NodeBuilder::without_access_control().no_logging().build();
+    let (panic_replied, reply) = executor
+        .execute(async move {
+            let res = async {
+                ctx.start_worker("panicking", PanickingWorker).await?;
+
+                ctx.send(route!["panicking"], String::from("panic")).await?;
+                let panic_replied = ctx.receive_timeout::<String>(1).await.is_ok();
+
+                // The worker keeps handling messages after the panic
+                ctx.send(route!["panicking"], String::from("hello")).await?;
+                let reply = ctx.receive::<String>().await?.take().body();
+
+                // And still acknowledges its shutdown
+                ctx.stop_worker("panicking").await?;
+                Ok::<_, ockam_core::Error>((panic_replied, reply))
+            }
+            .await;
+            ctx.stop().await?;
+            res
+        })
+        .unwrap()
+        .unwrap();
+
+    assert!(!panic_replied);
+    assert_eq!(reply, "hello");
+    let line = logs
+        .lines()
+        .into_iter()
+        .find(|line| line.contains("Worker 'panicking' panicked handling a message"))
+        .expect("the panic is logged");
+    assert!(line.contains("ERROR"), "{line}");
+    assert!(line.contains("worker was asked to panic"), "{line}");
+}
@@ -38,6 +38,12 @@ pub enum TransportError {
     PortalInvalidState,
     /// InvalidRouterResponseType
     InvalidRouterResponseType,
+    /// Connection was closed by the peer in an orderly way
+    ConnectionClosed,
+    /// A route of the message holds more addresses than allowed
+    RouteTooLong,
+    /// The peers have no transport protocol version in common
+    IncompatibleVersions,
 }
 
 impl ockam_core::compat::error::Error for TransportError {}
@@ -59,6 +65,14 @@ impl core::fmt::Display for TransportError {
             Self::GenericIo => write!(f, "generic I/O failure"),
             Self::PortalInvalidState => write!(f, "portal entered invalid state"),
             Self::InvalidRouterResponseType => write!(f, "router responded with invalid type"),
+            Self::ConnectionClosed => write!(f, "connection was closed by the peer"),
+            Self::RouteTooLong => write!(f, "message route is too long"),
+            Self::IncompatibleVersions => {
+                write!(
+                    f,
+                    "no transport protocol version is supported by both peers"
+                )
+            }
         }
     }
 }
@@ -83,6 +97,9 @@ impl From<TransportError> for Error {
             GenericIo => Kind::Io,
             PortalInvalidState => Kind::Invalid,
             InvalidRouterResponseType => Kind::Invalid,
+            ConnectionClosed => Kind::Shutdown,
+            RouteTooLong => Kind::ResourceExhausted,
+            IncompatibleVersions => Kind::Unsupported,
         };
 
         Error::new(Origin::Transport, kind, err)
@@ -93,6 +110,10 @@ impl From<io::Error> for TransportError {
     fn from(e: io::Error) -> Self {
         match e.kind() {
             io::ErrorKind::ConnectionRefused => Self::PeerNotFound,
+            io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::UnexpectedEof => Self::ConnectionDrop,
             _ => Self::GenericIo,
         }
     }
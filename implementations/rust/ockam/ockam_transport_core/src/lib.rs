@@ -1,5 +1,13 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
 pub use error::TransportError;
+#[cfg(feature = "std")]
+pub use log_throttle::*;
+pub use route_limit::*;
+pub use versions::*;
 
 mod error;
+#[cfg(feature = "std")]
+mod log_throttle;
+mod route_limit;
+mod versions;
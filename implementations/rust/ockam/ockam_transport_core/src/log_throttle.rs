@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{error, warn};
+
+/// Interval over which identical lines are collapsed by default
+pub const DEFAULT_LOG_THROTTLE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Lines held at most, the ones whose interval is over are forgotten first
+const MAX_TRACKED_LINES: usize = 1024;
+
+/// What to do with a line given to a [`LogThrottle`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Throttled {
+    /// First occurrence of the line in its interval, log it
+    Log,
+    /// Repeat of a line already logged in its interval, drop it
+    Suppressed,
+    /// First occurrence after an interval in which the line was repeated
+    /// this many more times, log it with the count
+    Summary(u64),
+}
+
+struct LineState {
+    since: Instant,
+    suppressed: u64,
+}
+
+/// Collapses repeated log lines, e.g. the errors of a flapping peer
+///
+/// The first occurrence of a line is logged, then its repeats are only
+/// counted until `interval` elapsed. The next occurrence is logged with the
+/// number of repeats in between. Repeats which aren't followed by another
+/// occurrence aren't reported.
+pub struct LogThrottle {
+    interval: Duration,
+    lines: Mutex<HashMap<String, LineState>>,
+}
+
+impl Default for LogThrottle {
+    fn default() -> Self {
+        Self::new(DEFAULT_LOG_THROTTLE_INTERVAL)
+    }
+}
+
+impl LogThrottle {
+    /// Log each distinct line at most once per `interval`
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            lines: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Log `line` as a warning unless it was logged recently, returns
+    /// whether it was logged
+    pub fn warn(&self, line: impl Display) -> bool {
+        let line = line.to_string();
+        match self.check(&line) {
+            Throttled::Log => warn!("{}", line),
+            Throttled::Suppressed => return false,
+            Throttled::Summary(n) => warn!("{} (repeated {} times)", line, n),
+        }
+        true
+    }
+
+    /// Log `line` as an error unless it was logged recently, returns
+    /// whether it was logged
+    pub fn error(&self, line: impl Display) -> bool {
+        let line = line.to_string();
+        match self.check(&line) {
+            Throttled::Log => error!("{}", line),
+            Throttled::Suppressed => return false,
+            Throttled::Summary(n) => error!("{} (repeated {} times)", line, n),
+        }
+        true
+    }
+
+    /// Account for an occurrence of `line`
+    pub fn check(&self, line: &str) -> Throttled {
+        self.check_at(line, Instant::now())
+    }
+
+    fn check_at(&self, line: &str, now: Instant) -> Throttled {
+        let mut lines = self.lines.lock().unwrap();
+        if let Some(state) = lines.get_mut(line) {
+            if now.duration_since(state.since) < self.interval {
+                state.suppressed += 1;
+                return Throttled::Suppressed;
+            }
+            let suppressed = state.suppressed;
+            state.since = now;
+            state.suppressed = 0;
+            return if suppressed > 0 {
+                Throttled::Summary(suppressed)
+            } else {
+                Throttled::Log
+            };
+        }
+
+        if lines.len() >= MAX_TRACKED_LINES {
+            let interval = self.interval;
+            lines.retain(|_, state| now.duration_since(state.since) < interval);
+            // Still full of recent lines, don't let it grow
+            if lines.len() >= MAX_TRACKED_LINES {
+                return Throttled::Log;
+            }
+        }
+        lines.insert(
+            line.to_string(),
+            LineState {
+                since: now,
+                suppressed: 0,
+            },
+        );
+        Throttled::Log
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_lines_are_collapsed() {
+        let throttle = LogThrottle::new(Duration::from_secs(10));
+        let start = Instant::now();
+
+        let logged = (0..1000)
+            .map(|i| throttle.check_at("peer unreachable", start + Duration::from_millis(i)))
+            .filter(|outcome| outcome != &Throttled::Suppressed)
+            .count();
+        assert_eq!(logged, 1);
+
+        // Other lines are throttled on their own
+        assert_eq!(throttle.check_at("other error", start), Throttled::Log);
+
+        // Once the interval is over, the count of repeats is reported
+        let later = start + Duration::from_secs(11);
+        assert_eq!(
+            throttle.check_at("peer unreachable", later),
+            Throttled::Summary(999)
+        );
+        assert_eq!(
+            throttle.check_at("peer unreachable", later + Duration::from_secs(11)),
+            Throttled::Log
+        );
+    }
+
+    #[test]
+    fn quiet_lines_are_forgotten() {
+        let throttle = LogThrottle::new(Duration::from_secs(10));
+        let start = Instant::now();
+        for i in 0..MAX_TRACKED_LINES {
+            throttle.check_at(&i.to_string(), start);
+        }
+
+        let later = start + Duration::from_secs(11);
+        assert_eq!(throttle.check_at("new line", later), Throttled::Log);
+        assert_eq!(throttle.lines.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,85 @@
+use crate::TransportError;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use ockam_core::compat::sync::Arc;
+use ockam_core::{Result, TransportMessage};
+
+/// Number of addresses the routes of a message may hold by default when
+/// going through a transport router
+pub const DEFAULT_MAX_ROUTE_LENGTH: usize = 64;
+
+/// Bound on the length of the onward and return routes of the messages
+/// going through a transport router
+///
+/// Routers prepend addresses to the onward route of every message they
+/// forward, so a message sent around a loop would otherwise grow without
+/// bound. The limit is shared by the clones of a transport and its router.
+#[derive(Clone, Debug)]
+pub struct RouteLengthLimit {
+    max_length: Arc<AtomicUsize>,
+}
+
+impl Default for RouteLengthLimit {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_ROUTE_LENGTH)
+    }
+}
+
+impl RouteLengthLimit {
+    /// Limit routes to `max_length` addresses
+    pub fn new(max_length: usize) -> Self {
+        Self {
+            max_length: Arc::new(AtomicUsize::new(max_length)),
+        }
+    }
+
+    /// Change the limit, for the messages routed from now on
+    pub fn set(&self, max_length: usize) {
+        self.max_length.store(max_length, Ordering::Relaxed);
+    }
+
+    /// Number of addresses a route may hold
+    pub fn max_length(&self) -> usize {
+        self.max_length.load(Ordering::Relaxed)
+    }
+
+    /// Fail with [`TransportError::RouteTooLong`] if the onward or the
+    /// return route of `msg` holds more addresses than the limit
+    pub fn check(&self, msg: &TransportMessage) -> Result<()> {
+        let max_length = self.max_length();
+        if msg.onward_route.iter().count() > max_length
+            || msg.return_route.iter().count() > max_length
+        {
+            return Err(TransportError::RouteTooLong.into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ockam_core::{errcode::Kind, route, Route};
+
+    fn message(onward_route: Route, return_route: Route) -> TransportMessage {
+        TransportMessage::v1(onward_route, return_route, Vec::new())
+    }
+
+    #[test]
+    fn over_long_routes_are_rejected() {
+        let limit = RouteLengthLimit::new(2);
+        assert!(limit.check(&message(route!["a", "b"], route!["c"])).is_ok());
+
+        let err = limit
+            .check(&message(route!["a", "b", "c"], route!["d"]))
+            .unwrap_err();
+        assert_eq!(err.code().kind, Kind::ResourceExhausted);
+        assert!(limit
+            .check(&message(route!["a"], route!["b", "c", "d"]))
+            .is_err());
+
+        limit.set(3);
+        assert!(limit
+            .check(&message(route!["a", "b", "c"], route!["d"]))
+            .is_ok());
+    }
+}
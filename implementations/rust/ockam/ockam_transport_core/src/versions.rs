@@ -0,0 +1,164 @@
+use crate::TransportError;
+use core::sync::atomic::{AtomicU16, Ordering};
+use ockam_core::compat::{sync::Arc, vec::Vec};
+use ockam_core::{Result, LATEST_TRANSPORT_VERSION, MIN_TRANSPORT_VERSION};
+
+/// Tells the payload of a version offer apart from the one of a
+/// heartbeat, which also has empty routes
+const OFFER_TAG: &[u8] = b"ockam_transport_versions";
+
+/// Range of [`TransportMessage`](ockam_core::TransportMessage) wire format
+/// versions a peer speaks
+///
+/// Peers offer their ranges when they connect, and both encode their
+/// messages in the highest version of both ranges once they got the
+/// offer of the other end. A peer which doesn't offer any range only
+/// speaks version 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TransportVersions {
+    min: u8,
+    max: u8,
+}
+
+impl Default for TransportVersions {
+    fn default() -> Self {
+        Self {
+            min: MIN_TRANSPORT_VERSION,
+            max: LATEST_TRANSPORT_VERSION,
+        }
+    }
+}
+
+impl TransportVersions {
+    /// Speak the versions from `min` to `max`, within the versions this
+    /// crate supports
+    pub fn new(min: u8, max: u8) -> Self {
+        let max = max.clamp(MIN_TRANSPORT_VERSION, LATEST_TRANSPORT_VERSION);
+        Self {
+            min: min.clamp(MIN_TRANSPORT_VERSION, max),
+            max,
+        }
+    }
+
+    /// Oldest version spoken
+    pub fn min(&self) -> u8 {
+        self.min
+    }
+
+    /// Latest version spoken
+    pub fn max(&self) -> u8 {
+        self.max
+    }
+
+    /// Payload of a version 1 message with empty routes, which offers
+    /// these versions to a peer
+    ///
+    /// Peers which don't negotiate versions take the message for a
+    /// heartbeat, and ignore it.
+    pub fn to_offer(&self) -> Vec<u8> {
+        let mut offer = OFFER_TAG.to_vec();
+        offer.extend_from_slice(&[self.min, self.max]);
+        offer
+    }
+
+    /// Versions offered by a peer in `payload`, or `None` if it isn't an
+    /// offer
+    ///
+    /// The range is taken as offered, including the versions this crate
+    /// doesn't speak.
+    pub fn from_offer(payload: &[u8]) -> Option<Self> {
+        match payload.strip_prefix(OFFER_TAG)? {
+            [min, max] if min <= max => Some(Self {
+                min: *min,
+                max: *max,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Whether `version` is one of these versions
+    pub fn contains(&self, version: u8) -> bool {
+        (self.min..=self.max).contains(&version)
+    }
+
+    /// Highest version spoken by both `self` and `theirs`, failing with
+    /// [`TransportError::IncompatibleVersions`] if there is none
+    pub fn negotiate(&self, theirs: &TransportVersions) -> Result<u8> {
+        let version = self.max.min(theirs.max);
+        if version < self.min.max(theirs.min) {
+            return Err(TransportError::IncompatibleVersions.into());
+        }
+        Ok(version)
+    }
+}
+
+/// [`TransportVersions`] offered on new connections, shared by the clones
+/// of a transport and its router
+#[derive(Clone, Debug)]
+pub struct SupportedVersions {
+    versions: Arc<AtomicU16>,
+}
+
+impl Default for SupportedVersions {
+    fn default() -> Self {
+        Self {
+            versions: Arc::new(AtomicU16::new(pack(TransportVersions::default()))),
+        }
+    }
+}
+
+impl SupportedVersions {
+    /// Change the versions, for the connections made from now on
+    pub fn set(&self, versions: TransportVersions) {
+        self.versions.store(pack(versions), Ordering::Relaxed);
+    }
+
+    /// Versions offered on new connections
+    pub fn get(&self) -> TransportVersions {
+        let packed = self.versions.load(Ordering::Relaxed);
+        TransportVersions {
+            min: (packed >> 8) as u8,
+            max: packed as u8,
+        }
+    }
+}
+
+fn pack(versions: TransportVersions) -> u16 {
+    (versions.min as u16) << 8 | versions.max as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ockam_core::errcode::Kind;
+
+    #[test]
+    fn highest_common_version_is_chosen() {
+        let v1 = TransportVersions::new(1, 1);
+        let v2 = TransportVersions::default();
+        assert_eq!(v2.negotiate(&v2).unwrap(), 2);
+        assert_eq!(v2.negotiate(&v1).unwrap(), 1);
+        assert_eq!(v1.negotiate(&v2).unwrap(), 1);
+
+        let v2_only = TransportVersions::new(2, 2);
+        let err = v2_only.negotiate(&v1).unwrap_err();
+        assert_eq!(err.code().kind, Kind::Unsupported);
+
+        // A peer offering only versions this crate doesn't speak
+        let mut offer = v2.to_offer();
+        let len = offer.len();
+        offer[len - 2..].copy_from_slice(&[3, 3]);
+        let v3 = TransportVersions::from_offer(&offer).unwrap();
+        assert_eq!((v3.min(), v3.max()), (3, 3));
+        let err = v2.negotiate(&v3).unwrap_err();
+        assert_eq!(err.code().kind, Kind::Unsupported);
+
+        assert_eq!(TransportVersions::from_offer(&v1.to_offer()), Some(v1));
+        assert_eq!(TransportVersions::from_offer(&[]), None);
+
+        let supported = SupportedVersions::default();
+        assert_eq!(supported.get(), v2);
+        supported.set(v1);
+        assert_eq!(supported.get(), v1);
+    }
+}
@@ -33,8 +33,13 @@ pub(crate) use portal::*;
 pub(crate) use router::*;
 pub(crate) use workers::*;
 
+mod metrics;
+mod resolver;
 mod transport;
 
+pub use metrics::*;
+pub(crate) use resolver::TcpResolver;
+pub use resolver::{Resolver, SystemResolver};
 pub use transport::*;
 
 use ockam_core::compat::net::SocketAddr;
@@ -0,0 +1,198 @@
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use core::time::Duration;
+use ockam_core::compat::collections::BTreeMap;
+use ockam_core::compat::net::SocketAddr;
+use ockam_core::compat::sync::{Arc, Mutex};
+use ockam_core::compat::vec::Vec;
+use std::time::{Instant, SystemTime};
+
+/// Activity of the connections of a [`TcpTransport`](crate::TcpTransport)
+///
+/// Heartbeats don't count as activity.
+#[derive(Debug)]
+pub struct TcpTransportMetrics {
+    created_at: Instant,
+    active_connections: AtomicUsize,
+    /// Milliseconds from `created_at` to the last message sent or received
+    last_activity_ms: AtomicU64,
+    connections: Mutex<BTreeMap<SocketAddr, Arc<ConnectionCounters>>>,
+    messages_sent: AtomicU64,
+    messages_received: AtomicU64,
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+}
+
+impl TcpTransportMetrics {
+    pub(crate) fn new() -> Self {
+        Self {
+            created_at: Instant::now(),
+            active_connections: AtomicUsize::new(0),
+            last_activity_ms: AtomicU64::new(0),
+            connections: Mutex::new(BTreeMap::new()),
+            messages_sent: AtomicU64::new(0),
+            messages_received: AtomicU64::new(0),
+            bytes_sent: AtomicU64::new(0),
+            bytes_received: AtomicU64::new(0),
+        }
+    }
+
+    /// Number of currently open connections, inbound and outbound
+    pub fn active_connections(&self) -> usize {
+        self.active_connections.load(Ordering::Acquire)
+    }
+
+    /// Time elapsed since a message was last sent or received, or since
+    /// the transport was created if there was no traffic yet
+    pub fn idle_for(&self) -> Duration {
+        let last_activity = Duration::from_millis(self.last_activity_ms.load(Ordering::Acquire));
+        self.created_at.elapsed().saturating_sub(last_activity)
+    }
+
+    /// Traffic of the open connection with `peer`, if any
+    pub fn connection(&self, peer: &SocketAddr) -> Option<TcpConnectionMetrics> {
+        self.connections
+            .lock()
+            .unwrap()
+            .get(peer)
+            .map(|counters| counters.snapshot())
+    }
+
+    /// Traffic of every open connection, by peer
+    pub fn connections(&self) -> Vec<(SocketAddr, TcpConnectionMetrics)> {
+        self.connections
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(peer, counters)| (*peer, counters.snapshot()))
+            .collect()
+    }
+
+    /// Traffic since the transport was created, or since its counters
+    /// were last reset
+    pub fn counters(&self) -> TcpTransportCounters {
+        TcpTransportCounters {
+            messages_sent: self.messages_sent.load(Ordering::Acquire),
+            messages_received: self.messages_received.load(Ordering::Acquire),
+            bytes_sent: self.bytes_sent.load(Ordering::Acquire),
+            bytes_received: self.bytes_received.load(Ordering::Acquire),
+        }
+    }
+
+    /// Reset the traffic counters to zero, returning their values before
+    /// the reset
+    ///
+    /// Every counter is swapped at once, so traffic is counted either
+    /// before or after the reset, and deltas add up to the whole traffic.
+    /// The number of open connections, the idle time and the traffic of
+    /// each connection are not reset.
+    pub fn reset_counters(&self) -> TcpTransportCounters {
+        TcpTransportCounters {
+            messages_sent: self.messages_sent.swap(0, Ordering::AcqRel),
+            messages_received: self.messages_received.swap(0, Ordering::AcqRel),
+            bytes_sent: self.bytes_sent.swap(0, Ordering::AcqRel),
+            bytes_received: self.bytes_received.swap(0, Ordering::AcqRel),
+        }
+    }
+
+    pub(crate) fn message_sent(&self, bytes: usize) {
+        self.messages_sent.fetch_add(1, Ordering::AcqRel);
+        self.bytes_sent.fetch_add(bytes as u64, Ordering::AcqRel);
+        self.record_activity();
+    }
+
+    pub(crate) fn message_received(&self, bytes: usize) {
+        self.messages_received.fetch_add(1, Ordering::AcqRel);
+        self.bytes_received
+            .fetch_add(bytes as u64, Ordering::AcqRel);
+        self.record_activity();
+    }
+
+    fn record_activity(&self) {
+        let now = self.created_at.elapsed().as_millis() as u64;
+        self.last_activity_ms.fetch_max(now, Ordering::AcqRel);
+    }
+
+    pub(crate) fn connection_opened(&self, peer: SocketAddr) -> Arc<ConnectionCounters> {
+        let counters = Arc::new(ConnectionCounters::new());
+        self.connections
+            .lock()
+            .unwrap()
+            .insert(peer, counters.clone());
+        self.active_connections.fetch_add(1, Ordering::AcqRel);
+        self.record_activity();
+        counters
+    }
+
+    pub(crate) fn connection_closed(&self, peer: SocketAddr, counters: &Arc<ConnectionCounters>) {
+        let mut connections = self.connections.lock().unwrap();
+        // A newer connection with the same peer may have replaced this one
+        if connections
+            .get(&peer)
+            .map_or(false, |c| Arc::ptr_eq(c, counters))
+        {
+            connections.remove(&peer);
+        }
+        drop(connections);
+        self.active_connections.fetch_sub(1, Ordering::AcqRel);
+        self.record_activity();
+    }
+}
+
+/// Messages sent and received by all the connections of a
+/// [`TcpTransport`](crate::TcpTransport), heartbeats excluded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TcpTransportCounters {
+    /// Messages sent
+    pub messages_sent: u64,
+    /// Messages received
+    pub messages_received: u64,
+    /// Bytes of the messages sent, length prefixes included
+    pub bytes_sent: u64,
+    /// Bytes of the messages received, length prefixes included
+    pub bytes_received: u64,
+}
+
+/// Traffic of a single TCP connection, heartbeats excluded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TcpConnectionMetrics {
+    /// Bytes received, length prefixes included
+    pub bytes_in: u64,
+    /// Bytes sent, length prefixes included
+    pub bytes_out: u64,
+    /// When the connection was established
+    pub connected_at: SystemTime,
+}
+
+/// Counters shared by the sender and receiver of a connection
+#[derive(Debug)]
+pub(crate) struct ConnectionCounters {
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+    connected_at: SystemTime,
+}
+
+impl ConnectionCounters {
+    fn new() -> Self {
+        Self {
+            bytes_in: AtomicU64::new(0),
+            bytes_out: AtomicU64::new(0),
+            connected_at: SystemTime::now(),
+        }
+    }
+
+    pub(crate) fn received(&self, bytes: usize) {
+        self.bytes_in.fetch_add(bytes as u64, Ordering::AcqRel);
+    }
+
+    pub(crate) fn sent(&self, bytes: usize) {
+        self.bytes_out.fetch_add(bytes as u64, Ordering::AcqRel);
+    }
+
+    fn snapshot(&self) -> TcpConnectionMetrics {
+        TcpConnectionMetrics {
+            bytes_in: self.bytes_in.load(Ordering::Acquire),
+            bytes_out: self.bytes_out.load(Ordering::Acquire),
+            connected_at: self.connected_at,
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{PortalMessage, TcpPortalWorker, TcpRouterHandle};
+use crate::{PortalMessage, TcpPortalWorker, TcpResolver};
 use ockam_core::{async_trait, AccessControl, Result, Routed, Worker};
 use ockam_node::Context;
 use ockam_transport_core::TransportError;
@@ -13,14 +13,20 @@ use tracing::debug;
 pub(crate) struct TcpOutletListenWorker {
     peer: String,
     access_control: Arc<dyn AccessControl>,
+    resolver: Arc<TcpResolver>,
 }
 
 impl TcpOutletListenWorker {
     /// Create a new `TcpOutletListenWorker`
-    pub(crate) fn new(peer: String, access_control: Arc<dyn AccessControl>) -> Self {
+    pub(crate) fn new(
+        peer: String,
+        access_control: Arc<dyn AccessControl>,
+        resolver: Arc<TcpResolver>,
+    ) -> Self {
         Self {
             peer,
             access_control,
+            resolver,
         }
     }
 }
@@ -42,7 +48,7 @@ impl Worker for TcpOutletListenWorker {
             return Err(TransportError::Protocol.into());
         }
 
-        let (peer_addr, _) = TcpRouterHandle::resolve_peer(self.peer.clone())?;
+        let (peer_addr, _) = self.resolver.resolve_peer(self.peer.clone()).await?;
 
         let address = TcpPortalWorker::start_new_outlet(
             ctx,
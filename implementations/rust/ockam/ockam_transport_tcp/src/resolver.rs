@@ -0,0 +1,377 @@
+use crate::parse_socket_addr;
+use core::time::Duration;
+use ockam_core::compat::collections::BTreeMap;
+use ockam_core::compat::net::{Ipv4Addr, SocketAddr};
+use ockam_core::compat::sync::{Arc, Mutex};
+use ockam_core::compat::{
+    boxed::Box,
+    string::{String, ToString},
+    vec::Vec,
+};
+use ockam_core::{async_trait, Result};
+use ockam_transport_core::TransportError;
+use std::time::Instant;
+use tokio::net::UdpSocket;
+use tracing::{debug, warn};
+
+/// DNS record type of IPv4 addresses
+const TYPE_A: u16 = 1;
+/// DNS record class of internet addresses
+const CLASS_IN: u16 = 1;
+
+/// How long a nameserver is given to answer
+const QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Hostnames whose resolved address is remembered, the ones resolved the
+/// longest ago are forgotten first
+const MAX_RESOLVED: usize = 256;
+
+/// Resolves the hostnames of peers into socket addresses
+///
+/// Peers given as socket addresses are used as is, a resolver is only
+/// asked for `host:port` peers. Implement this trait to look hosts up in a
+/// service registry, or to resolve them deterministically in tests.
+#[async_trait]
+pub trait Resolver: Send + Sync + 'static {
+    /// Socket addresses of the `host:port` peer, in preference order
+    async fn resolve(&self, peer: &str) -> Result<Vec<SocketAddr>>;
+}
+
+/// [`Resolver`] using the system's configuration, without blocking the
+/// runtime's threads
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemResolver;
+
+#[async_trait]
+impl Resolver for SystemResolver {
+    async fn resolve(&self, peer: &str) -> Result<Vec<SocketAddr>> {
+        let addrs = tokio::net::lookup_host(peer)
+            .await
+            .map_err(|_| TransportError::InvalidAddress)?;
+        Ok(addrs.collect())
+    }
+}
+
+/// [`Resolver`] asking nameservers for the IPv4 address of a host, in
+/// order, until one of them answers
+#[derive(Debug)]
+struct NameserverResolver {
+    nameservers: Vec<SocketAddr>,
+}
+
+#[async_trait]
+impl Resolver for NameserverResolver {
+    async fn resolve(&self, peer: &str) -> Result<Vec<SocketAddr>> {
+        let (host, port) = peer
+            .rsplit_once(':')
+            .ok_or(TransportError::InvalidAddress)?;
+        let port: u16 = port.parse().map_err(|_| TransportError::InvalidAddress)?;
+
+        for nameserver in &self.nameservers {
+            match Self::query(nameserver, host).await {
+                Ok(Some(ip)) => {
+                    debug!(%host, %ip, %nameserver, "Resolved hostname");
+                    return Ok(vec![SocketAddr::new(ip.into(), port)]);
+                }
+                Ok(None) => debug!(%host, %nameserver, "No IPv4 address for hostname"),
+                Err(e) => warn!(%host, %nameserver, "Failed to query nameserver: {}", e),
+            }
+        }
+        Err(TransportError::InvalidAddress.into())
+    }
+}
+
+impl NameserverResolver {
+    /// Ask a nameserver for the first IPv4 address of `host`
+    async fn query(nameserver: &SocketAddr, host: &str) -> std::io::Result<Option<Ipv4Addr>> {
+        let bind_addr: SocketAddr = if nameserver.is_ipv4() {
+            ([0u8; 4], 0).into()
+        } else {
+            ([0u16; 8], 0).into()
+        };
+        let socket = UdpSocket::bind(bind_addr).await?;
+        socket.connect(nameserver).await?;
+
+        let id: u16 = rand::random();
+        socket.send(&encode_query(id, host)?).await?;
+
+        let mut buf = [0u8; 512];
+        tokio::time::timeout(QUERY_TIMEOUT, async {
+            loop {
+                let len = socket.recv(&mut buf).await?;
+                // Answers to other queries are ignored
+                if let Some(answer) = decode_answer(id, &buf[..len]) {
+                    return Ok::<_, std::io::Error>(answer);
+                }
+            }
+        })
+        .await
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::TimedOut, e))?
+    }
+}
+
+/// Resolution of the peers of a TCP transport
+///
+/// Socket addresses are parsed, hostnames are given to a [`Resolver`], the
+/// system's unless another one is injected. The transport only dials
+/// IPv4 addresses for now. The address each hostname was last resolved
+/// to is remembered, for at most [`MAX_RESOLVED`] hostnames, to route to a
+/// hostname and look up the connections made to it without resolving it
+/// again.
+pub(crate) struct TcpResolver {
+    resolver: Arc<dyn Resolver>,
+    resolved: Mutex<BTreeMap<String, (Instant, SocketAddr)>>,
+}
+
+impl Default for TcpResolver {
+    fn default() -> Self {
+        Self::new(SystemResolver)
+    }
+}
+
+impl TcpResolver {
+    /// Resolve hostnames with `resolver`
+    pub(crate) fn new(resolver: impl Resolver) -> Self {
+        Self {
+            resolver: Arc::new(resolver),
+            resolved: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Resolve hostnames with the given nameservers, or with the system
+    /// resolver if there are none
+    pub(crate) fn with_nameservers(nameservers: Vec<SocketAddr>) -> Self {
+        if nameservers.is_empty() {
+            Self::default()
+        } else {
+            Self::new(NameserverResolver { nameservers })
+        }
+    }
+
+    /// Resolve the given peer to a [`SocketAddr`](std::net::SocketAddr),
+    /// returning the hostnames it was resolved from
+    pub(crate) async fn resolve_peer(
+        &self,
+        peer: impl Into<String>,
+    ) -> Result<(SocketAddr, Vec<String>)> {
+        let peer_str = peer.into();
+
+        // Try to parse as SocketAddr
+        if let Ok(peer_addr) = parse_socket_addr(peer_str.clone()) {
+            return Ok((peer_addr, vec![]));
+        }
+
+        // Try to resolve hostname
+        let peer_addr = self
+            .resolver
+            .resolve(&peer_str)
+            .await?
+            .into_iter()
+            .find(|x| x.is_ipv4())
+            .ok_or(TransportError::InvalidAddress)?;
+        self.remember(peer_str.clone(), peer_addr);
+
+        Ok((peer_addr, vec![peer_str]))
+    }
+
+    /// The socket address of a peer and the hostnames it was resolved
+    /// from, if it's a socket address or a hostname which was already
+    /// resolved
+    pub(crate) fn resolved(&self, peer: &str) -> Option<(SocketAddr, Vec<String>)> {
+        if let Ok(peer_addr) = parse_socket_addr(peer) {
+            return Some((peer_addr, vec![]));
+        }
+        let resolved = self.resolved.lock().unwrap();
+        let (_, peer_addr) = resolved.get(peer)?;
+        Some((*peer_addr, vec![peer.to_string()]))
+    }
+
+    fn remember(&self, hostname: String, peer_addr: SocketAddr) {
+        let mut resolved = self.resolved.lock().unwrap();
+        if resolved.len() >= MAX_RESOLVED && !resolved.contains_key(&hostname) {
+            let oldest = resolved
+                .iter()
+                .min_by_key(|(_, (resolved_at, _))| *resolved_at)
+                .map(|(hostname, _)| hostname.clone());
+            if let Some(oldest) = oldest {
+                resolved.remove(&oldest);
+            }
+        }
+        resolved.insert(hostname, (Instant::now(), peer_addr));
+    }
+}
+
+/// Encode a recursive query for the A records of `host`
+fn encode_query(id: u16, host: &str) -> std::io::Result<Vec<u8>> {
+    let invalid = || std::io::Error::new(std::io::ErrorKind::InvalidInput, "invalid hostname");
+    let mut query = Vec::with_capacity(18 + host.len());
+    query.extend_from_slice(&id.to_be_bytes());
+    // Recursion desired, one question
+    query.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in host.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(invalid());
+        }
+        query.push(label.len() as u8);
+        query.extend_from_slice(label.as_bytes());
+    }
+    query.push(0);
+    query.extend_from_slice(&TYPE_A.to_be_bytes());
+    query.extend_from_slice(&CLASS_IN.to_be_bytes());
+    Ok(query)
+}
+
+/// Decode the answer to the query `id`, `None` if the message isn't one
+///
+/// The answer holds the first IPv4 address found, if any.
+fn decode_answer(id: u16, msg: &[u8]) -> Option<Option<Ipv4Addr>> {
+    let u16_at = |pos: usize| -> Option<u16> {
+        Some(u16::from_be_bytes([*msg.get(pos)?, *msg.get(pos + 1)?]))
+    };
+    if u16_at(0)? != id {
+        return None;
+    }
+    let flags = u16_at(2)?;
+    let is_response = flags & 0x8000 != 0;
+    if !is_response {
+        return None;
+    }
+    let rcode = flags & 0x000f;
+    if rcode != 0 {
+        return Some(None);
+    }
+    let questions = u16_at(4)?;
+    let answers = u16_at(6)?;
+
+    let mut pos = 12;
+    for _ in 0..questions {
+        pos = skip_name(msg, pos)? + 4;
+    }
+    for _ in 0..answers {
+        pos = skip_name(msg, pos)?;
+        let rtype = u16_at(pos)?;
+        let class = u16_at(pos + 2)?;
+        let len = u16_at(pos + 8)? as usize;
+        let data = msg.get(pos + 10..pos + 10 + len)?;
+        if rtype == TYPE_A && class == CLASS_IN && len == 4 {
+            return Some(Some(Ipv4Addr::new(data[0], data[1], data[2], data[3])));
+        }
+        pos += 10 + len;
+    }
+    Some(None)
+}
+
+/// Position after the possibly compressed name starting at `pos`
+fn skip_name(msg: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let len = *msg.get(pos)? as usize;
+        match len {
+            0 => return Some(pos + 1),
+            // A pointer ends the name
+            _ if len & 0xc0 == 0xc0 => return Some(pos + 2),
+            _ => pos += 1 + len,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_answer() {
+        let query = encode_query(42, "peer.ockam.test").unwrap();
+
+        // Header, question, then a CNAME and an A record pointing at the question name
+        let mut answer = query.clone();
+        answer[2..4].copy_from_slice(&[0x81, 0x80]);
+        answer[6..8].copy_from_slice(&[0, 2]);
+        answer.extend_from_slice(&[0xc0, 12, 0, 5, 0, 1, 0, 0, 0, 60, 0, 2, 0xc0, 12]);
+        answer.extend_from_slice(&[0xc0, 12, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4, 10, 1, 2, 3]);
+        assert_eq!(
+            decode_answer(42, &answer),
+            Some(Some(Ipv4Addr::new(10, 1, 2, 3)))
+        );
+
+        // Answers to other queries and queries are ignored
+        assert_eq!(decode_answer(43, &answer), None);
+        assert_eq!(decode_answer(42, &query), None);
+
+        // NXDOMAIN
+        answer[3] = 0x83;
+        assert_eq!(decode_answer(42, &answer), Some(None));
+    }
+
+    struct MockResolver;
+
+    #[async_trait]
+    impl Resolver for MockResolver {
+        async fn resolve(&self, peer: &str) -> Result<Vec<SocketAddr>> {
+            match peer {
+                "peer.test:4000" => Ok(vec![
+                    "[2001:db8::1]:4000".parse().unwrap(),
+                    "192.0.2.1:4000".parse().unwrap(),
+                ]),
+                "v6only.test:4000" => Ok(vec!["[2001:db8::1]:4000".parse().unwrap()]),
+                _ => Err(TransportError::InvalidAddress.into()),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_peer_with_injected_resolver() {
+        let resolver = TcpResolver::new(MockResolver);
+        assert!(resolver.resolved("peer.test:4000").is_none());
+
+        let (peer_addr, hostnames) = resolver.resolve_peer("peer.test:4000").await.unwrap();
+        assert_eq!(peer_addr, "192.0.2.1:4000".parse().unwrap());
+        assert_eq!(hostnames, vec!["peer.test:4000".to_string()]);
+        assert_eq!(
+            resolver.resolved("peer.test:4000"),
+            Some((peer_addr, hostnames))
+        );
+
+        // Socket addresses aren't given to the resolver
+        let (peer_addr, hostnames) = resolver.resolve_peer("127.0.0.1:5000").await.unwrap();
+        assert_eq!(peer_addr, "127.0.0.1:5000".parse().unwrap());
+        assert!(hostnames.is_empty());
+
+        assert!(resolver.resolve_peer("v6only.test:4000").await.is_err());
+        assert!(resolver.resolve_peer("unknown.test:4000").await.is_err());
+    }
+
+    /// Resolves every hostname to the same address
+    struct SameAddressResolver;
+
+    #[async_trait]
+    impl Resolver for SameAddressResolver {
+        async fn resolve(&self, _peer: &str) -> Result<Vec<SocketAddr>> {
+            Ok(vec!["192.0.2.1:4000".parse().unwrap()])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolved_hostnames_are_bounded() {
+        let resolver = TcpResolver::new(SameAddressResolver);
+        for i in 0..=MAX_RESOLVED {
+            resolver
+                .resolve_peer(format!("host{}:4000", i))
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(resolver.resolved.lock().unwrap().len(), MAX_RESOLVED);
+        // The hostname resolved the longest ago is forgotten
+        assert!(resolver.resolved("host0:4000").is_none());
+        assert!(resolver
+            .resolved(&format!("host{}:4000", MAX_RESOLVED))
+            .is_some());
+    }
+
+    #[test]
+    fn test_encode_query_rejects_invalid_hostnames() {
+        assert!(encode_query(1, "peer..test").is_err());
+        assert!(encode_query(1, &"a".repeat(64)).is_err());
+        assert!(encode_query(1, "peer.test.").is_ok());
+    }
+}
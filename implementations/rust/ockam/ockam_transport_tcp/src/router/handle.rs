@@ -1,13 +1,14 @@
 use crate::{
-    parse_socket_addr, TcpInletListenProcessor, TcpListenProcessor, TcpRouterRequest,
-    TcpRouterResponse, WorkerPair, TCP,
+    ListenerOptions, TcpInletListenProcessor, TcpListenProcessor, TcpResolver, TcpRouterRequest,
+    TcpRouterResponse, TcpSendWorker, TcpTransportMetrics, WorkerPair, TCP,
 };
-use ockam_core::compat::net::{SocketAddr, ToSocketAddrs};
+use ockam_core::compat::net::SocketAddr;
 use ockam_core::{async_trait, compat::boxed::Box, AccessControl};
 use ockam_core::{Address, AsyncTryClone, Result, Route};
 use ockam_node::Context;
-use ockam_transport_core::TransportError;
+use ockam_transport_core::{RouteLengthLimit, SupportedVersions, TransportError};
 use std::sync::Arc;
+use tokio::net::TcpStream;
 use tracing::debug;
 
 /// A handle to connect to a TcpRouter
@@ -16,52 +17,125 @@ use tracing::debug;
 pub(crate) struct TcpRouterHandle {
     ctx: Context,
     api_addr: Address,
+    metrics: Arc<TcpTransportMetrics>,
+    resolver: Arc<TcpResolver>,
+    route_limit: RouteLengthLimit,
+    versions: SupportedVersions,
 }
 
 #[async_trait]
 impl AsyncTryClone for TcpRouterHandle {
     async fn async_try_clone(&self) -> Result<Self> {
         let child_ctx = self.ctx.new_detached(Address::random_local()).await?;
-        Ok(Self::new(child_ctx, self.api_addr.clone()))
+        Ok(Self::new(
+            child_ctx,
+            self.api_addr.clone(),
+            self.metrics.clone(),
+            self.resolver.clone(),
+            self.route_limit.clone(),
+            self.versions.clone(),
+        ))
     }
 }
 
 impl TcpRouterHandle {
     /// Create a new `TcpRouterHandle` with the given address
-    pub(crate) fn new(ctx: Context, api_addr: Address) -> Self {
-        TcpRouterHandle { ctx, api_addr }
+    pub(crate) fn new(
+        ctx: Context,
+        api_addr: Address,
+        metrics: Arc<TcpTransportMetrics>,
+        resolver: Arc<TcpResolver>,
+        route_limit: RouteLengthLimit,
+        versions: SupportedVersions,
+    ) -> Self {
+        TcpRouterHandle {
+            ctx,
+            api_addr,
+            metrics,
+            resolver,
+            route_limit,
+            versions,
+        }
     }
 
     /// Return a reference to the router handle's [`Context`]
     pub fn ctx(&self) -> &Context {
         &self.ctx
     }
+
+    /// Return the activity metrics shared by the router's connections
+    pub fn metrics(&self) -> &Arc<TcpTransportMetrics> {
+        &self.metrics
+    }
+
+    /// Return the bound on message routes shared with the router
+    pub(crate) fn route_limit(&self) -> &RouteLengthLimit {
+        &self.route_limit
+    }
+
+    /// Return the transport versions offered on new connections, shared
+    /// with the router
+    pub(crate) fn versions(&self) -> &SupportedVersions {
+        &self.versions
+    }
+
+    /// Return the resolver of peer hostnames shared with the router
+    pub(crate) fn resolver(&self) -> &Arc<TcpResolver> {
+        &self.resolver
+    }
 }
 
 impl TcpRouterHandle {
     /// Bind an incoming connection listener for this router
-    pub async fn bind(&self, addr: impl Into<SocketAddr>) -> Result<SocketAddr> {
+    pub async fn bind(
+        &self,
+        addr: impl Into<SocketAddr>,
+        options: ListenerOptions,
+    ) -> Result<SocketAddr> {
         let socket_addr = addr.into();
-        TcpListenProcessor::start(&self.ctx, self.async_try_clone().await?, socket_addr).await
+        TcpListenProcessor::start(
+            &self.ctx,
+            self.async_try_clone().await?,
+            socket_addr,
+            options,
+        )
+        .await
     }
 
     /// Establish an outgoing TCP connection on an existing transport
+    ///
+    /// The connection is dialed before returning, so that an unreachable
+    /// peer is reported to the caller rather than when routing to it.
+    /// Connecting to a peer which is already connected to fails with
+    /// [`TransportError::AlreadyConnected`], even when both connections
+    /// are dialed at the same time.
     pub async fn connect<S: AsRef<str>>(&self, peer: S) -> Result<Address> {
-        let response = self
-            .ctx
-            .send_and_receive(
-                self.api_addr.clone(),
-                TcpRouterRequest::Connect {
-                    peer: peer.as_ref().to_string(),
-                },
-            )
-            .await?;
+        let (peer_addr, hostnames) = self.resolver.resolve_peer(peer.as_ref()).await?;
 
-        if let TcpRouterResponse::Connect(res) = response {
-            res
-        } else {
-            Err(TransportError::InvalidRouterResponseType.into())
+        // Dialing here rather than in the router doesn't hold up its routing
+        debug!(addr = %peer_addr, "Connecting");
+        let stream = TcpStream::connect(peer_addr)
+            .await
+            .map_err(TransportError::from)?;
+
+        let pair = TcpSendWorker::start_pair(
+            &self.ctx,
+            self.async_try_clone().await?,
+            Some(stream),
+            peer_addr,
+            hostnames,
+        )
+        .await?;
+
+        // The router registers one of the connections dialed at the same
+        // time to the same peer, the others are closed
+        if let Err(err) = self.register(&pair).await {
+            debug!(addr = %peer_addr, %err, "Closing the connection which wasn't registered");
+            let _ = self.ctx.stop_worker(pair.tx_addr()).await;
+            return Err(err);
         }
+
+        Ok(pair.tx_addr())
     }
 
     /// Disconnect an outgoing TCP connection on an existing transport
@@ -131,36 +205,6 @@ impl TcpRouterHandle {
             Err(TransportError::InvalidRouterResponseType.into())
         }
     }
-
-    /// Resolve the given peer to a [`SocketAddr`](std::net::SocketAddr)
-    pub(crate) fn resolve_peer(peer: impl Into<String>) -> Result<(SocketAddr, Vec<String>)> {
-        let peer_str = peer.into();
-        let peer_addr;
-        let hostnames;
-
-        // Try to parse as SocketAddr
-        if let Ok(p) = parse_socket_addr(peer_str.clone()) {
-            peer_addr = p;
-            hostnames = vec![];
-        }
-        // Try to resolve hostname
-        else if let Ok(mut iter) = peer_str.to_socket_addrs() {
-            // FIXME: We only take ipv4 for now
-            if let Some(p) = iter.find(|x| x.is_ipv4()) {
-                peer_addr = p;
-            } else {
-                return Err(TransportError::InvalidAddress.into());
-            }
-
-            hostnames = vec![peer_str];
-        }
-        // Nothing worked, return an error
-        else {
-            return Err(TransportError::InvalidAddress.into());
-        }
-
-        Ok((peer_addr, hostnames))
-    }
 }
 
 impl TcpRouterHandle {
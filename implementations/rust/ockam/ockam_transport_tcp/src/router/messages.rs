@@ -12,7 +12,7 @@ pub enum TcpRouterRequest {
     },
     /// Connect
     Connect { peer: String },
-    /// Connect
+    /// Disconnect
     Disconnect { peer: String },
     /// Unregister (usually, after disconnection)
     Unregister {
@@ -1,11 +1,25 @@
-use crate::{TcpRouterHandle, TcpRouterRequest, TcpRouterResponse, TcpSendWorker, TCP};
+use crate::{
+    parse_socket_addr, TcpResolver, TcpRouterHandle, TcpRouterRequest, TcpRouterResponse,
+    TcpSendWorker, TcpTransportMetrics, TCP,
+};
 use core::ops::Deref;
 use ockam_core::{async_trait, Any};
-use ockam_core::{Address, Decodable, LocalMessage, Result, Routed, Worker};
+use ockam_core::{Address, Decodable, LocalMessage, MessageId, Result, Route, Routed, Worker};
 use ockam_node::Context;
-use ockam_transport_core::TransportError;
+use ockam_transport_core::{LogThrottle, RouteLengthLimit, SupportedVersions, TransportError};
 use std::collections::BTreeMap;
-use tracing::{debug, error, trace};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::field::{self, display};
+use tracing::{debug, debug_span, error, trace, Instrument};
+
+/// Dropped connections whose aliases are kept, the oldest ones are
+/// forgotten first
+const MAX_DROPPED: usize = 256;
+
+/// How long the aliases of a dropped connection are kept
+const DROPPED_TTL: Duration = Duration::from_secs(10 * 60);
 
 /// A TCP address router and connection listener
 ///
@@ -20,12 +34,23 @@ pub(crate) struct TcpRouter {
     main_addr: Address,
     api_addr: Address,
     map: BTreeMap<Address, Address>,
+    /// Aliases of the connections which dropped, by the address of their
+    /// peer, registered again when the peer is connected to again, with
+    /// when they dropped
+    dropped: BTreeMap<Address, (Instant, Vec<Address>)>,
     allow_auto_connection: bool,
+    metrics: Arc<TcpTransportMetrics>,
+    resolver: Arc<TcpResolver>,
+    route_limit: RouteLengthLimit,
+    versions: SupportedVersions,
+    /// Collapses the errors repeated for every message, e.g. of a loop
+    log_throttle: Arc<LogThrottle>,
 }
 
 impl TcpRouter {
-    /// Create and register a new TCP router with the node context
-    pub async fn register(ctx: &Context) -> Result<TcpRouterHandle> {
+    /// Create and register a new TCP router with the node context,
+    /// resolving peer hostnames with `resolver`
+    pub async fn register(ctx: &Context, resolver: TcpResolver) -> Result<TcpRouterHandle> {
         let main_addr = Address::random_local();
         let api_addr = Address::random_local();
         debug!("Initialising new TcpRouter with address {}", &main_addr);
@@ -37,7 +62,13 @@ impl TcpRouter {
             main_addr: main_addr.clone(),
             api_addr: api_addr.clone(),
             map: BTreeMap::new(),
+            dropped: BTreeMap::new(),
             allow_auto_connection: true,
+            metrics: Arc::new(TcpTransportMetrics::new()),
+            resolver: Arc::new(resolver),
+            route_limit: RouteLengthLimit::default(),
+            versions: SupportedVersions::default(),
+            log_throttle: Arc::new(LogThrottle::default()),
         };
 
         let handle = router.create_self_handle().await?;
@@ -53,7 +84,14 @@ impl TcpRouter {
     /// Create a new `TcpRouterHandle` representing this router
     async fn create_self_handle(&self) -> Result<TcpRouterHandle> {
         let handle_ctx = self.ctx.new_detached(Address::random_local()).await?;
-        let handle = TcpRouterHandle::new(handle_ctx, self.api_addr.clone());
+        let handle = TcpRouterHandle::new(
+            handle_ctx,
+            self.api_addr.clone(),
+            self.metrics.clone(),
+            self.resolver.clone(),
+            self.route_limit.clone(),
+            self.versions.clone(),
+        );
         Ok(handle)
     }
 }
@@ -80,7 +118,19 @@ impl TcpRouter {
         }
 
         for accept in accepts {
-            self.map.insert(accept.clone(), self_addr.clone());
+            // Routes through the aliases of a dropped connection to the
+            // same peer keep working, without resolving them again
+            if let Some((_, aliases)) = self
+                .dropped
+                .remove(&accept)
+                .filter(|(dropped_at, _)| dropped_at.elapsed() < DROPPED_TTL)
+            {
+                debug!("Restoring the aliases of {} under {}", accept, self_addr);
+                for alias in aliases {
+                    self.map.entry(alias).or_insert_with(|| self_addr.clone());
+                }
+            }
+            self.map.insert(accept, self_addr.clone());
         }
 
         Ok(())
@@ -88,26 +138,59 @@ impl TcpRouter {
 
     /// Handle any [`TcpRouterRequest::Unregister`] messages received by
     /// this node's worker
+    ///
+    /// Connection workers unregister when their connection drops, the
+    /// aliases of the connection are kept until its peer is connected to
+    /// again, for at most [`DROPPED_TTL`] and [`MAX_DROPPED`] connections.
     async fn handle_unregister(&mut self, self_addr: Address) -> Result<()> {
         trace!("TCP unregistration request: {}", &self_addr);
 
+        let (peers, aliases): (Vec<Address>, Vec<Address>) = self
+            .map
+            .iter()
+            .filter(|(_, self_addr_i)| *self_addr_i == &self_addr)
+            .map(|(accept, _)| accept.clone())
+            .partition(|accept| parse_socket_addr(accept.address()).is_ok());
         self.map.retain(|_, self_addr_i| self_addr_i != &self_addr);
 
+        if let (Some(peer), false) = (peers.into_iter().next(), aliases.is_empty()) {
+            self.forget_expired_drops();
+            if self.dropped.len() >= MAX_DROPPED {
+                let oldest = self
+                    .dropped
+                    .iter()
+                    .min_by_key(|(_, (dropped_at, _))| *dropped_at)
+                    .map(|(peer, _)| peer.clone());
+                if let Some(oldest) = oldest {
+                    self.dropped.remove(&oldest);
+                }
+            }
+            self.dropped.insert(peer, (Instant::now(), aliases));
+        }
+
         Ok(())
     }
+
+    /// Forget the aliases of the connections which dropped too long ago
+    fn forget_expired_drops(&mut self) {
+        self.dropped
+            .retain(|_, (dropped_at, _)| dropped_at.elapsed() < DROPPED_TTL);
+    }
 }
 
 impl TcpRouter {
     /// Handle any [`TcpRouterRequest::Connect`] messages received by this
-    /// nodes worker
+    /// nodes worker, and connect to a peer that a message is routed to,
+    /// when auto connection is allowed
     ///
     /// This handler starts a `(TcpSendWorker, TcpRecvProcessor)` pair
-    /// that open and manage a connection to the given peer and
+    /// that open and manage a connection to the given resolved peer and
     /// finally register the given peer with this `TcpRouter`.
-    async fn handle_connect(&mut self, peer: String) -> Result<Address> {
-        // Resolve peer address
-        let (peer_addr, hostnames) = TcpRouterHandle::resolve_peer(peer)?;
-
+    async fn handle_connect(
+        &mut self,
+        peer_addr: SocketAddr,
+        hostnames: Vec<String>,
+    ) -> Result<Address> {
         // Start a new `WorkerPair` for the given peer containing a
         // `TcpSendWorker` and `TcpRecvprocessor`
         let router_handle = self.create_self_handle().await?;
@@ -129,8 +212,17 @@ impl TcpRouter {
 
     /// Handle any [`TcpRouterRequest::Disconnect`] messages received by this
     /// nodes worker
+    ///
+    /// A hostname `peer` is looked up among the hostnames the transport
+    /// already resolved, it isn't resolved again.
     async fn handle_disconnect(&mut self, peer: String) -> Result<()> {
-        let (peer_addr, _hostnames) = TcpRouterHandle::resolve_peer(peer)?;
+        let (peer_addr, _hostnames) = match self.resolver.resolved(&peer) {
+            Some(resolved) => resolved,
+            None => {
+                error!("Failed to disconnect, peer not found: {}", peer);
+                return Err(TransportError::PeerNotFound.into());
+            }
+        };
         let tcp_address: Address = format!("{}#{}", TCP, peer_addr).into();
 
         let self_address = if let Some(self_address) = self.map.get(&tcp_address) {
@@ -141,6 +233,8 @@ impl TcpRouter {
         };
 
         self.handle_unregister(self_address.clone()).await?;
+        // The aliases of a connection closed on purpose are not restored
+        self.dropped.remove(&tcp_address);
 
         self.ctx.stop_worker(self_address).await?;
 
@@ -151,15 +245,23 @@ impl TcpRouter {
     /// nodes worker
     async fn handle_route(&mut self, ctx: &Context, mut msg: LocalMessage) -> Result<()> {
         trace!(
-            "TCP route request: {:?}",
-            msg.transport().onward_route.next()
+            "TCP route request: {}",
+            msg.transport().onward_route.pretty()
         );
 
         // Get the next hop
         let onward = msg.transport().onward_route.next()?;
 
         // Resolve route to the connection worker responsible for the next hop
-        let next = self.resolve_route(onward).await?;
+        let next = match self.resolve_route(onward).await? {
+            Some(next) => next,
+            None => {
+                // The message is routed again once its peer is resolved
+                let peer = String::from_utf8(onward.deref().clone())
+                    .map_err(|_| TransportError::UnknownRoute)?;
+                return self.resolve_in_background(peer, msg, None).await;
+            }
+        };
 
         // Modify the transport message route
         let _ = msg.transport_mut().onward_route.step()?;
@@ -167,6 +269,7 @@ impl TcpRouter {
             .onward_route
             .modify()
             .prepend(next.clone());
+        self.route_limit.check(msg.transport())?;
 
         // Send the transport message to the connection worker
         ctx.send(next.clone(), msg).await?;
@@ -174,17 +277,21 @@ impl TcpRouter {
         Ok(())
     }
 
-    /// Resolve the route to the provided onward address
-    async fn resolve_route(&mut self, onward: &Address) -> Result<Address> {
+    /// Resolve the route to the provided onward address, unless it's a
+    /// hostname which isn't resolved yet
+    async fn resolve_route(&mut self, onward: &Address) -> Result<Option<Address>> {
         // Check if the connection already exists
         if let Some(n) = self.map.get(onward) {
-            return Ok(n.clone());
+            return Ok(Some(n.clone()));
         }
 
         // Try resolve a tcp address for the onward address
         let peer =
             String::from_utf8(onward.deref().clone()).map_err(|_| TransportError::UnknownRoute)?;
-        let (peer_addr, hostnames) = TcpRouterHandle::resolve_peer(peer.clone())?;
+        let (peer_addr, hostnames) = match self.resolver.resolved(&peer) {
+            Some(resolved) => resolved,
+            None => return Ok(None),
+        };
         let tcp_address = Address::new(TCP, peer_addr.to_string());
 
         // Check for existing connection under different name
@@ -194,20 +301,54 @@ impl TcpRouter {
                 self.map.insert(accept, n.clone());
             }
 
-            return Ok(n);
+            return Ok(Some(n));
         }
 
         // No existing connection
         if self.allow_auto_connection {
-            self.handle_connect(peer).await
+            self.handle_connect(peer_addr, hostnames).await.map(Some)
         } else {
-            error!(
+            self.log_throttle.error(format_args!(
                 "Failed to resolve route, no existing connection to peer: {}",
                 peer
-            );
+            ));
             Err(TransportError::UnknownRoute.into())
         }
     }
+
+    /// Resolve the hostname `peer` without holding up the router, then
+    /// hand `msg` back to the router, which then finds `peer` resolved
+    ///
+    /// A failure is answered to `connect_reply`, the return route of a
+    /// [`TcpRouterRequest::Connect`] message, if given, and logged
+    /// otherwise.
+    async fn resolve_in_background(
+        &self,
+        peer: String,
+        msg: LocalMessage,
+        connect_reply: Option<Route>,
+    ) -> Result<()> {
+        trace!("Resolving {} before routing to it", peer);
+        let ctx = self.ctx.new_detached(Address::random_local()).await?;
+        let resolver = self.resolver.clone();
+        let log_throttle = self.log_throttle.clone();
+        self.ctx.runtime().spawn(async move {
+            let res = match resolver.resolve_peer(peer).await {
+                Ok(_) => ctx.forward(msg).await,
+                Err(err) => match connect_reply {
+                    Some(route) => ctx.send(route, TcpRouterResponse::Connect(Err(err))).await,
+                    None => Err(err),
+                },
+            };
+            if let Err(err) = res {
+                log_throttle.error(format_args!(
+                    "TCP router failed to route a message: {}",
+                    err
+                ));
+            }
+        });
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -220,14 +361,37 @@ impl Worker for TcpRouter {
         Ok(())
     }
 
+    async fn shutdown(&mut self, _ctx: &mut Self::Context) -> Result<()> {
+        // No connection is registered again once the router stops
+        self.dropped.clear();
+        Ok(())
+    }
+
     async fn handle_message(&mut self, ctx: &mut Context, msg: Routed<Any>) -> Result<()> {
         let return_route = msg.return_route();
         let msg_addr = msg.msg_addr();
 
         if msg_addr == self.main_addr {
-            self.handle_route(ctx, msg.into_local_message()).await?;
+            let msg = msg.into_local_message();
+            let span = debug_span!("tcp_router", peer = field::Empty, message_id = field::Empty);
+            if !span.is_disabled() {
+                if let Ok(peer) = msg.transport().onward_route.next() {
+                    span.record("peer", &display(peer));
+                }
+                span.record(
+                    "message_id",
+                    &display(MessageId::of(&msg.transport().payload)),
+                );
+            }
+            if let Err(err) = self.handle_route(ctx, msg).instrument(span).await {
+                self.log_throttle.error(format_args!(
+                    "TCP router failed to route a message: {}",
+                    err
+                ));
+            }
         } else if msg_addr == self.api_addr {
-            let msg = TcpRouterRequest::decode(msg.payload())?;
+            let local_msg = msg.into_local_message();
+            let msg = TcpRouterRequest::decode(&local_msg.transport().payload)?;
             match msg {
                 TcpRouterRequest::Register { accepts, self_addr } => {
                     let res = self.handle_register(accepts, self_addr).await;
@@ -242,7 +406,18 @@ impl Worker for TcpRouter {
                         .await?;
                 }
                 TcpRouterRequest::Connect { peer } => {
-                    let res = self.handle_connect(peer).await;
+                    let res = match self.resolver.resolved(&peer) {
+                        Some((peer_addr, hostnames)) => {
+                            self.handle_connect(peer_addr, hostnames).await
+                        }
+                        None => {
+                            // The request is handled again once the peer
+                            // is resolved
+                            return self
+                                .resolve_in_background(peer, local_msg, Some(return_route))
+                                .await;
+                        }
+                    };
 
                     ctx.send(return_route, TcpRouterResponse::Connect(res))
                         .await?;
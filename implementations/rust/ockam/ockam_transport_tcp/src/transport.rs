@@ -2,9 +2,13 @@ use ockam_core::access_control::AccessControl;
 use ockam_core::compat::{boxed::Box, net::SocketAddr};
 use ockam_core::{Address, AllowAll, AsyncTryClone, Result, Route};
 use ockam_node::Context;
+use ockam_transport_core::TransportVersions;
 use std::sync::Arc;
 
-use crate::{parse_socket_addr, TcpOutletListenWorker, TcpRouter, TcpRouterHandle};
+use crate::{
+    parse_socket_addr, Resolver, TcpConnectionMetrics, TcpOutletListenWorker, TcpResolver,
+    TcpRouter, TcpRouterHandle, TcpTransportMetrics,
+};
 
 /// High level management interface for TCP transports
 ///
@@ -62,7 +66,56 @@ impl TcpTransport {
     /// # Ok(()) }
     /// ```
     pub async fn create(ctx: &Context) -> Result<Self> {
-        let router = TcpRouter::register(ctx).await?;
+        let router = TcpRouter::register(ctx, TcpResolver::default()).await?;
+
+        Ok(Self {
+            router_handle: router,
+        })
+    }
+
+    /// Create a new TCP transport resolving peer hostnames with the given
+    /// nameservers rather than with the system resolver
+    ///
+    /// The nameservers are asked for the IPv4 address of a host in order,
+    /// until one of them answers. The system resolver is used when the list
+    /// is empty.
+    ///
+    /// ```rust
+    /// use ockam_transport_tcp::TcpTransport;
+    /// # use ockam_node::Context;
+    /// # use ockam_core::Result;
+    /// # async fn test(ctx: Context) -> Result<()> {
+    /// let nameservers = vec!["10.0.0.53:53".parse().unwrap()];
+    /// let tcp = TcpTransport::create_with_nameservers(&ctx, nameservers).await?;
+    /// tcp.connect("service.internal:5000").await?;
+    /// # Ok(()) }
+    /// ```
+    pub async fn create_with_nameservers(
+        ctx: &Context,
+        nameservers: Vec<SocketAddr>,
+    ) -> Result<Self> {
+        let router = TcpRouter::register(ctx, TcpResolver::with_nameservers(nameservers)).await?;
+
+        Ok(Self {
+            router_handle: router,
+        })
+    }
+
+    /// Create a new TCP transport resolving peer hostnames with `resolver`
+    ///
+    /// Peers given as socket addresses aren't resolved. The first IPv4
+    /// address the resolver returns for a hostname is dialed.
+    ///
+    /// ```rust
+    /// use ockam_transport_tcp::{SystemResolver, TcpTransport};
+    /// # use ockam_node::Context;
+    /// # use ockam_core::Result;
+    /// # async fn test(ctx: Context) -> Result<()> {
+    /// let tcp = TcpTransport::create_with_resolver(&ctx, SystemResolver).await?;
+    /// # Ok(()) }
+    /// ```
+    pub async fn create_with_resolver(ctx: &Context, resolver: impl Resolver) -> Result<Self> {
+        let router = TcpRouter::register(ctx, TcpResolver::new(resolver)).await?;
 
         Ok(Self {
             router_handle: router,
@@ -73,6 +126,8 @@ impl TcpTransport {
     /// This step is optional because the underlying TcpRouter is capable of lazily establishing
     /// a connection upon arrival of the initial message.
     ///
+    /// Returns an error if the peer can't be reached.
+    ///
     /// ```rust
     /// use ockam_transport_tcp::TcpTransport;
     /// # use ockam_node::Context;
@@ -108,8 +163,123 @@ impl TcpTransport {
     /// tcp.listen("127.0.0.1:8000").await?;
     /// # Ok(()) }
     pub async fn listen<S: AsRef<str>>(&self, bind_addr: S) -> Result<SocketAddr> {
+        self.listen_with_options(bind_addr, ListenerOptions::default())
+            .await
+    }
+
+    /// Start listening to incoming connections with the given socket options
+    ///
+    /// See [`ListenerOptions`] for the available options.
+    ///
+    /// ```rust
+    /// use ockam_transport_tcp::{ListenerOptions, TcpTransport};
+    /// # use ockam_node::Context;
+    /// # use ockam_core::Result;
+    /// # async fn test(ctx: Context) -> Result<()> {
+    /// let tcp = TcpTransport::create(&ctx).await?;
+    /// let options = ListenerOptions::default().with_reuse_port(true);
+    /// tcp.listen_with_options("127.0.0.1:8000", options).await?;
+    /// # Ok(()) }
+    /// ```
+    pub async fn listen_with_options<S: AsRef<str>>(
+        &self,
+        bind_addr: S,
+        options: ListenerOptions,
+    ) -> Result<SocketAddr> {
         let bind_addr = parse_socket_addr(bind_addr.as_ref())?;
-        self.router_handle.bind(bind_addr).await
+        self.router_handle.bind(bind_addr, options).await
+    }
+
+    /// Activity of this transport's connections, updated as traffic flows
+    pub fn metrics(&self) -> Arc<TcpTransportMetrics> {
+        self.router_handle.metrics().clone()
+    }
+
+    /// Traffic of the open connection with `peer`, if any
+    ///
+    /// A hostname `peer` is looked up among the hostnames the transport
+    /// already resolved, it isn't resolved again.
+    pub fn connection_metrics<S: AsRef<str>>(&self, peer: S) -> Option<TcpConnectionMetrics> {
+        let (peer, _) = self.router_handle.resolver().resolved(peer.as_ref())?;
+        self.router_handle.metrics().connection(&peer)
+    }
+
+    /// Drop the messages whose onward or return route holds more than
+    /// `max_length` addresses, [`DEFAULT_MAX_ROUTE_LENGTH`] by default
+    ///
+    /// Such messages are rejected by the router with
+    /// [`TransportError::RouteTooLong`](ockam_transport_core::TransportError::RouteTooLong).
+    ///
+    /// [`DEFAULT_MAX_ROUTE_LENGTH`]: ockam_transport_core::DEFAULT_MAX_ROUTE_LENGTH
+    pub fn set_max_route_length(&self, max_length: usize) {
+        self.router_handle.route_limit().set(max_length)
+    }
+
+    /// Offer `versions` of the message wire format on the connections
+    /// made from now on, all the versions this crate speaks by default
+    ///
+    /// Both ends of a connection offer the versions they speak as soon as
+    /// it's established, and encode their messages in the oldest version
+    /// they speak until they got the offer of the other end, then in the
+    /// highest version both speak. A peer which doesn't offer any version
+    /// only speaks version 1. A connection without a common version is
+    /// closed, and reported with
+    /// [`TransportError::IncompatibleVersions`](ockam_transport_core::TransportError::IncompatibleVersions).
+    pub fn set_transport_versions(&self, versions: TransportVersions) {
+        self.router_handle.versions().set(versions)
+    }
+}
+
+/// Socket options of a TCP listener
+///
+/// By default `SO_REUSEADDR` is set everywhere but on Windows, so that
+/// a restarted node can bind its port again while connections of the
+/// previous run are still in `TIME_WAIT`. On Windows the same option
+/// allows another socket to take over a port which is in active use,
+/// so it is only set there when asked for.
+///
+/// `SO_REUSEPORT` is off by default. It lets several sockets listen on
+/// the same port, with the kernel spreading incoming connections among
+/// them. **Any other process running as the same user can then bind
+/// the port too and will receive part of the connections meant for
+/// this node.** Only enable it on hosts where every process of that
+/// user is trusted. It is ignored on platforms which don't support it.
+#[derive(Clone, Debug)]
+pub struct ListenerOptions {
+    reuse_address: bool,
+    reuse_port: bool,
+}
+
+impl Default for ListenerOptions {
+    fn default() -> Self {
+        Self {
+            reuse_address: !cfg!(windows),
+            reuse_port: false,
+        }
+    }
+}
+
+impl ListenerOptions {
+    /// Set or clear `SO_REUSEADDR` on the listening socket
+    pub fn with_reuse_address(mut self, reuse_address: bool) -> Self {
+        self.reuse_address = reuse_address;
+        self
+    }
+
+    /// Set or clear `SO_REUSEPORT` on the listening socket
+    pub fn with_reuse_port(mut self, reuse_port: bool) -> Self {
+        self.reuse_port = reuse_port;
+        self
+    }
+
+    /// Whether `SO_REUSEADDR` is set on the listening socket
+    pub fn reuse_address(&self) -> bool {
+        self.reuse_address
+    }
+
+    /// Whether `SO_REUSEPORT` is set on the listening socket
+    pub fn reuse_port(&self) -> bool {
+        self.reuse_port
     }
 }
 
@@ -218,7 +388,11 @@ impl TcpTransport {
 
     /// Create an Outlet
     pub async fn create_outlet_extended(&self, options: OutletOptions) -> Result<()> {
-        let worker = TcpOutletListenWorker::new(options.peer, options.access_control);
+        let worker = TcpOutletListenWorker::new(
+            options.peer,
+            options.access_control,
+            self.router_handle.resolver().clone(),
+        );
         self.router_handle
             .ctx()
             .start_worker(options.address, worker)
@@ -1,11 +1,36 @@
-use crate::{TcpRouterHandle, TcpSendWorker};
+use crate::{ListenerOptions, TcpRouterHandle, TcpSendWorker};
 use ockam_core::{async_trait, compat::net::SocketAddr, AsyncTryClone};
 use ockam_core::{Address, Processor, Result};
 use ockam_node::Context;
 use ockam_transport_core::TransportError;
-use tokio::net::TcpListener;
+use std::io;
+use tokio::net::{TcpListener, TcpSocket};
 use tracing::{debug, trace};
 
+/// The maximum number of pending connections of a listener
+const BACKLOG: u32 = 1024;
+
+/// Bind a listening socket to `addr` with the given socket options
+pub(crate) fn bind_listener(
+    addr: SocketAddr,
+    options: &ListenerOptions,
+) -> io::Result<TcpListener> {
+    let socket = if addr.is_ipv4() {
+        TcpSocket::new_v4()?
+    } else {
+        TcpSocket::new_v6()?
+    };
+    socket.set_reuseaddr(options.reuse_address())?;
+    if options.reuse_port() {
+        #[cfg(all(unix, not(target_os = "solaris"), not(target_os = "illumos")))]
+        socket.set_reuseport(true)?;
+        #[cfg(not(all(unix, not(target_os = "solaris"), not(target_os = "illumos"))))]
+        tracing::warn!("SO_REUSEPORT is not supported on this platform, ignoring it");
+    }
+    socket.bind(addr)?;
+    socket.listen(BACKLOG)
+}
+
 /// A TCP Listen processor
 ///
 /// TCP listen processors are created by `TcpTransport`
@@ -21,11 +46,10 @@ impl TcpListenProcessor {
         ctx: &Context,
         router_handle: TcpRouterHandle,
         addr: SocketAddr,
+        options: ListenerOptions,
     ) -> Result<SocketAddr> {
-        debug!("Binding TcpListener to {}", addr);
-        let inner = TcpListener::bind(addr)
-            .await
-            .map_err(TransportError::from)?;
+        debug!(?options, "Binding TcpListener to {}", addr);
+        let inner = bind_listener(addr, &options).map_err(TransportError::from)?;
         let saddr = inner.local_addr().map_err(TransportError::from)?;
         let worker = Self {
             inner,
@@ -75,3 +99,43 @@ impl Processor for TcpListenProcessor {
         Ok(true)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    #[tokio::test]
+    async fn test_rebind_immediately_after_close() {
+        let options = ListenerOptions::default().with_reuse_address(true);
+        let listener = bind_listener("127.0.0.1:0".parse().unwrap(), &options).unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // Closing the accepted side first leaves it in TIME_WAIT
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        let (mut server, _) = listener.accept().await.unwrap();
+        server.write_all(b"bye").await.unwrap();
+        drop(server);
+        drop(listener);
+        let mut buf = Vec::new();
+        client.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, b"bye");
+
+        let listener = bind_listener(addr, &options).unwrap();
+        assert_eq!(listener.local_addr().unwrap(), addr);
+    }
+
+    #[cfg(all(unix, not(target_os = "solaris"), not(target_os = "illumos")))]
+    #[tokio::test]
+    async fn test_reuse_port_allows_several_listeners() {
+        let options = ListenerOptions::default().with_reuse_port(true);
+        let first = bind_listener("127.0.0.1:0".parse().unwrap(), &options).unwrap();
+        let addr = first.local_addr().unwrap();
+        let second = bind_listener(addr, &options).unwrap();
+        assert_eq!(second.local_addr().unwrap(), addr);
+
+        // Without the option the port can't be shared
+        assert!(bind_listener(addr, &ListenerOptions::default()).is_err());
+    }
+}
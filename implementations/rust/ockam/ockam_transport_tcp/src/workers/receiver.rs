@@ -1,10 +1,13 @@
-use crate::{TcpSendWorkerMsg, TCP};
+use crate::{ConnectionCounters, TcpSendWorkerMsg, TcpTransportMetrics, TCP};
 use ockam_core::async_trait;
-use ockam_core::{Address, Decodable, LocalMessage, Processor, Result, TransportMessage};
+use ockam_core::errcode::Kind;
+use ockam_core::{Address, LocalMessage, Processor, Result, TransportMessage};
 use ockam_node::{Context, ExternalLocalInfo};
-use ockam_transport_core::TransportError;
-use tokio::{io::AsyncReadExt, net::tcp::OwnedReadHalf};
-use tracing::{error, info, trace};
+use ockam_transport_core::{TransportError, TransportVersions};
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::net::tcp::OwnedReadHalf;
+use tracing::{info, trace, warn};
 
 /// A TCP receiving message processor
 ///
@@ -18,15 +21,29 @@ pub(crate) struct TcpRecvProcessor {
     rx: OwnedReadHalf,
     peer_addr: Address,
     sender_internal_address: Address,
+    /// Wire formats we take the messages of the peer in
+    versions: TransportVersions,
+    metrics: Arc<TcpTransportMetrics>,
+    counters: Arc<ConnectionCounters>,
 }
 
 impl TcpRecvProcessor {
     /// Create a new `TcpRecvProcessor`
-    pub fn new(rx: OwnedReadHalf, peer_addr: Address, sender_internal_address: Address) -> Self {
+    pub fn new(
+        rx: OwnedReadHalf,
+        peer_addr: Address,
+        sender_internal_address: Address,
+        versions: TransportVersions,
+        metrics: Arc<TcpTransportMetrics>,
+        counters: Arc<ConnectionCounters>,
+    ) -> Self {
         Self {
             rx,
             peer_addr,
             sender_internal_address,
+            versions,
+            metrics,
+            counters,
         }
     }
 }
@@ -52,63 +69,209 @@ impl Processor for TcpRecvProcessor {
     ///    killed by the user or node.
     async fn process(&mut self, ctx: &mut Context) -> Result<bool> {
         // Run in a loop until TcpWorkerPair::stop() is called
-        // First read a message length header...
-        let len = match self.rx.read_u16().await {
-            Ok(len) => len,
-            Err(_e) => {
-                info!(
-                    "Connection to peer '{}' was closed; dropping stream",
-                    self.peer_addr
-                );
-
-                // Notify sender tx is closed
-                ctx.send(
-                    self.sender_internal_address.clone(),
-                    TcpSendWorkerMsg::ConnectionClosed,
-                )
-                .await?;
+        let buf = match read_message(&mut self.rx).await {
+            Ok(buf) => buf,
+            Err(err) => {
+                // Notify sender rx is closed, so that it closes its side
+                // gracefully if the peer did
+                let msg = if err == TransportError::ConnectionClosed {
+                    info!("Peer '{}' closed the connection", self.peer_addr);
+                    TcpSendWorkerMsg::ConnectionClosed
+                } else {
+                    warn!("Connection to peer '{}' failed: {}", self.peer_addr, err);
+                    TcpSendWorkerMsg::ConnectionDropped
+                };
+                ctx.send(self.sender_internal_address.clone(), msg).await?;
 
                 return Ok(false);
             }
         };
 
-        trace!("Received message header for {} bytes", len);
-
-        // Allocate a buffer of that size
-        let mut buf = vec![0; len as usize];
-
-        // Then read into the buffer
-        match self.rx.read_exact(&mut buf).await {
-            Ok(_) => {}
-            _ => {
-                error!("Failed to receive message of length: {}", len);
-                return Ok(true);
+        // Deserialize the message now, in the wire format it tells
+        let (mut msg, ttl) = match TransportMessage::decode_versioned(&buf) {
+            Ok(decoded) => decoded,
+            Err(e) if e.code().kind == Kind::Unsupported => {
+                return self.incompatible_versions(ctx, buf[0]).await;
             }
-        }
-
-        // Deserialize the message now
-        let mut msg = TransportMessage::decode(&buf).map_err(|_| TransportError::RecvBadMessage)?;
+            Err(_) => return Err(TransportError::RecvBadMessage.into()),
+        };
 
-        // Heartbeat message
+        // Heartbeat message, or the versions the peer offers
         if msg.onward_route.next().is_err() {
+            if let Some(theirs) = TransportVersions::from_offer(&msg.payload) {
+                return self.negotiate(ctx, theirs).await;
+            }
             trace!("Got heartbeat message from: {}", self.peer_addr);
             return Ok(true);
         }
 
+        if !self.versions.contains(msg.version) {
+            return self.incompatible_versions(ctx, msg.version).await;
+        }
+
+        self.counters.received(2 + buf.len());
+        self.metrics.message_received(2 + buf.len());
+
         // Insert the peer address into the return route so that
         // reply routing can be properly resolved
         msg.return_route.modify().prepend(self.peer_addr.clone());
 
-        trace!("Message onward route: {}", msg.onward_route);
-        trace!("Message return route: {}", msg.return_route);
+        trace!("Message onward route: {}", msg.onward_route.pretty());
+        trace!("Message return route: {}", msg.return_route.pretty());
 
         // Mark that message originates from some other node
         let local_info = ExternalLocalInfo::new(TCP).to_local_info()?;
 
         // Forward the message to the next hop in the route
-        ctx.forward(LocalMessage::new(msg, vec![local_info]))
+        ctx.forward(LocalMessage::new(msg, vec![local_info]).with_ttl(ttl))
             .await?;
 
         Ok(true)
     }
 }
+
+impl TcpRecvProcessor {
+    /// Have the sender use the highest version both ends speak, or close
+    /// the connection if there is none
+    async fn negotiate(&mut self, ctx: &Context, theirs: TransportVersions) -> Result<bool> {
+        match self.versions.negotiate(&theirs) {
+            Ok(version) => {
+                ctx.send(
+                    self.sender_internal_address.clone(),
+                    TcpSendWorkerMsg::VersionNegotiated(version),
+                )
+                .await?;
+                Ok(true)
+            }
+            Err(e) => {
+                warn!(
+                    "Closing the connection to peer '{}' which speaks transport versions {} to {}, we speak {} to {}: {}",
+                    self.peer_addr,
+                    theirs.min(),
+                    theirs.max(),
+                    self.versions.min(),
+                    self.versions.max(),
+                    e
+                );
+                self.close(ctx).await
+            }
+        }
+    }
+
+    /// Close the connection to a peer which sent a message in a version
+    /// we don't speak
+    async fn incompatible_versions(&mut self, ctx: &Context, version: u8) -> Result<bool> {
+        warn!(
+            "Closing the connection to peer '{}' which sent a version {} message, we speak {} to {}: {}",
+            self.peer_addr,
+            version,
+            self.versions.min(),
+            self.versions.max(),
+            TransportError::IncompatibleVersions
+        );
+        self.close(ctx).await
+    }
+
+    async fn close(&mut self, ctx: &Context) -> Result<bool> {
+        ctx.send(
+            self.sender_internal_address.clone(),
+            TcpSendWorkerMsg::ConnectionDropped,
+        )
+        .await?;
+        Ok(false)
+    }
+}
+
+/// Read the next length-prefixed message from `rx`
+///
+/// The peer closing its write half between two messages is an orderly
+/// shutdown, reported as [`TransportError::ConnectionClosed`]. A reset,
+/// or the stream ending in the middle of a message, is reported as
+/// [`TransportError::ConnectionDrop`].
+pub(crate) async fn read_message<R: AsyncRead + Unpin>(
+    rx: &mut R,
+) -> core::result::Result<Vec<u8>, TransportError> {
+    // First read a message length header...
+    let mut len = [0u8; 2];
+    match rx.read(&mut len).await? {
+        0 => return Err(TransportError::ConnectionClosed),
+        1 => {
+            rx.read_exact(&mut len[1..]).await?;
+        }
+        _ => {}
+    }
+    let len = u16::from_be_bytes(len);
+
+    trace!("Received message header for {} bytes", len);
+
+    // Then read the message itself
+    let mut buf = vec![0; len as usize];
+    rx.read_exact(&mut buf).await?;
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::time::Duration;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::{TcpListener, TcpStream};
+
+    async fn connected_pair() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (server, _) = listener.accept().await.unwrap();
+        (client, server)
+    }
+
+    #[tokio::test]
+    async fn test_half_close_is_an_orderly_shutdown() {
+        let (mut client, server) = connected_pair().await;
+        let (mut rx, mut tx) = server.into_split();
+
+        // A message still in flight when the peer closes its write half
+        client.write_all(&[0, 3, 1, 2, 3]).await.unwrap();
+        client.shutdown().await.unwrap();
+
+        assert_eq!(read_message(&mut rx).await, Ok(vec![1, 2, 3]));
+        assert_eq!(
+            read_message(&mut rx).await,
+            Err(TransportError::ConnectionClosed)
+        );
+
+        // The peer can still read what we send after its half-close
+        tx.write_all(&[0, 1, 7]).await.unwrap();
+        assert_eq!(read_message(&mut client).await, Ok(vec![7]));
+    }
+
+    #[tokio::test]
+    async fn test_reset_is_a_dropped_connection() {
+        let (client, server) = connected_pair().await;
+        let (mut rx, _tx) = server.into_split();
+
+        // Closing with a zero linger sends a RST instead of a FIN
+        client.set_linger(Some(Duration::ZERO)).unwrap();
+        drop(client);
+
+        assert_eq!(
+            read_message(&mut rx).await,
+            Err(TransportError::ConnectionDrop)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_truncated_message_is_a_dropped_connection() {
+        let (mut client, server) = connected_pair().await;
+        let (mut rx, _tx) = server.into_split();
+
+        client.write_all(&[0, 10, 1, 2]).await.unwrap();
+        client.shutdown().await.unwrap();
+
+        assert_eq!(
+            read_message(&mut rx).await,
+            Err(TransportError::ConnectionDrop)
+        );
+    }
+}
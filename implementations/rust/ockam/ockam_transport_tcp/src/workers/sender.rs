@@ -1,14 +1,18 @@
-use crate::{TcpRecvProcessor, TcpRouterHandle};
+use crate::{ConnectionCounters, TcpRecvProcessor, TcpRouterHandle};
 use core::time::Duration;
+use ockam_core::compat::sync::Arc;
 use ockam_core::{async_trait, compat::net::SocketAddr, route, Any, Decodable, LocalMessage};
-use ockam_core::{Address, Encodable, Message, Result, Routed, TransportMessage, Worker};
+use ockam_core::{
+    Address, Message, MessageId, Result, Routed, TransportMessage, Worker, DEFAULT_TTL,
+};
 use ockam_node::{Context, DelayedEvent};
 use ockam_transport_core::TransportError;
 use serde::{Deserialize, Serialize};
 use tokio::io::AsyncWriteExt;
 use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
 use tokio::net::TcpStream;
-use tracing::{debug, trace, warn};
+use tracing::field::{self, display};
+use tracing::{debug, debug_span, trace, warn, Instrument, Span};
 
 /// Provides the transmit and receive parts of a TCP connection
 #[derive(Debug)]
@@ -38,7 +42,13 @@ impl WorkerPair {
 #[derive(Serialize, Deserialize, Message, Clone)]
 pub(crate) enum TcpSendWorkerMsg {
     Heartbeat,
+    /// The peer closed its write half in an orderly way
     ConnectionClosed,
+    /// The connection was reset, or ended in the middle of a message
+    ConnectionDropped,
+    /// The peer offered its versions, and this is the highest one both
+    /// ends speak
+    VersionNegotiated(u8),
 }
 
 /// A TCP sending message worker
@@ -58,6 +68,11 @@ pub(crate) struct TcpSendWorker {
     rx_addr: Option<Address>,
     heartbeat: DelayedEvent<TcpSendWorkerMsg>,
     heartbeat_interval: Option<Duration>,
+    /// Wire format of the messages, the oldest we speak until the peer
+    /// offered its versions
+    version: u8,
+    /// Set while the connection is counted in the transport's metrics
+    counters: Option<Arc<ConnectionCounters>>,
 }
 
 impl TcpSendWorker {
@@ -86,6 +101,8 @@ impl TcpSendWorker {
             rx_addr: None,
             heartbeat,
             heartbeat_interval: Some(Duration::from_secs(5 * 60)),
+            version: ockam_core::MIN_TRANSPORT_VERSION,
+            counters: None,
         }
     }
 
@@ -146,6 +163,12 @@ impl TcpSendWorker {
         self.heartbeat.schedule(heartbeat_interval).await
     }
 
+    fn record_sent(&self, bytes: usize) {
+        if let Some(counters) = &self.counters {
+            counters.sent(bytes);
+        }
+    }
+
     async fn stop_and_unregister(&self, ctx: &Context) -> Result<()> {
         self.router_handle.unregister(ctx.address()).await?;
 
@@ -153,6 +176,93 @@ impl TcpSendWorker {
 
         Ok(())
     }
+
+    // TcpSendWorker will receive messages from the TcpRouter to send
+    // across the TcpStream to our friend
+    async fn send(&mut self, ctx: &mut Context, msg: Routed<Any>) -> Result<()> {
+        self.heartbeat.cancel();
+
+        let tx = match &mut self.tx {
+            Some(tx) => tx,
+            None => return Err(TransportError::PeerNotFound.into()),
+        };
+
+        let recipient = msg.msg_addr();
+        if recipient == self.internal_addr {
+            let msg = TcpSendWorkerMsg::decode(msg.payload())?;
+
+            match msg {
+                TcpSendWorkerMsg::Heartbeat => {
+                    let msg = TransportMessage::v1(route![], route![], vec![]);
+                    let msg = prepare_message(msg, DEFAULT_TTL, self.version)?;
+                    // Sending empty heartbeat
+                    if tx.write_all(&msg).await.is_err() {
+                        warn!("Failed to send heartbeat to peer {}", self.peer);
+                        self.stop_and_unregister(ctx).await?;
+
+                        return Ok(());
+                    }
+
+                    debug!("Sent heartbeat to peer {}", self.peer);
+                }
+                TcpSendWorkerMsg::ConnectionClosed => {
+                    debug!("Closing connection {} closed by the peer", self.peer);
+                    // Answer the peer's FIN with ours once what we already
+                    // wrote is flushed, rather than dropping the socket
+                    if let Err(e) = tx.shutdown().await {
+                        debug!(addr = %self.peer, err = %e, "Failed to shut down connection");
+                    }
+                    // No need to stop Receiver as it notified us about connection close and will
+                    // stop itself
+                    self.rx_addr = None;
+                    self.stop_and_unregister(ctx).await?;
+
+                    return Ok(());
+                }
+                TcpSendWorkerMsg::ConnectionDropped => {
+                    warn!("Stopping sender due to dropped connection {}", self.peer);
+                    // No need to stop Receiver as it notified us about connection drop and will
+                    // stop itself
+                    self.rx_addr = None;
+                    self.stop_and_unregister(ctx).await?;
+
+                    return Ok(());
+                }
+                TcpSendWorkerMsg::VersionNegotiated(version) => {
+                    debug!(addr = %self.peer, version, "Negotiated transport version");
+                    self.version = version;
+                    return Ok(());
+                }
+            }
+        } else {
+            let local_msg = LocalMessage::decode(msg.payload())?;
+            let ttl = local_msg.ttl();
+            let mut msg = local_msg.into_transport_message();
+            let span = Span::current();
+            if !span.is_disabled() {
+                span.record("message_id", &display(MessageId::of(&msg.payload)));
+            }
+            // Remove our own address from the route so the other end
+            // knows what to do with the incoming message
+            msg.onward_route.step()?;
+            // Create a message buffer with pre-pended length
+            let msg = prepare_message(msg, ttl, self.version)?;
+
+            if tx.write_all(msg.as_slice()).await.is_err() {
+                warn!("Failed to send message to peer {}", self.peer);
+                self.stop_and_unregister(ctx).await?;
+
+                return Ok(());
+            }
+
+            self.record_sent(msg.len());
+            self.router_handle.metrics().message_sent(msg.len());
+        }
+
+        self.schedule_heartbeat().await?;
+
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -183,12 +293,33 @@ impl Worker for TcpSendWorker {
         }
 
         let rx = self.rx.take().ok_or(TransportError::GenericIo)?;
+        let tx = self.tx.as_mut().ok_or(TransportError::GenericIo)?;
+
+        // Offer our versions before anything else, we speak the oldest one
+        // until the peer offers its versions. A peer which doesn't negotiate
+        // versions never does, and takes the offer for a heartbeat
+        let versions = self.router_handle.versions().get();
+        let offer = TransportMessage::v1(route![], route![], versions.to_offer());
+        let offer = prepare_message(offer, DEFAULT_TTL, ockam_core::MIN_TRANSPORT_VERSION)?;
+        if let Err(e) = tx.write_all(&offer).await {
+            warn!(addr = %self.peer, err = %e, "Failed to offer transport versions");
+            self.stop_and_unregister(ctx).await?;
+
+            return Err(TransportError::from(e).into());
+        }
+        self.version = versions.min();
+
+        let counters = self.router_handle.metrics().connection_opened(self.peer);
+        self.counters = Some(counters.clone());
 
         let rx_addr = Address::random_local();
         let receiver = TcpRecvProcessor::new(
             rx,
             format!("{}#{}", crate::TCP, self.peer).into(),
             self.internal_addr.clone(),
+            versions,
+            self.router_handle.metrics().clone(),
+            counters,
         );
         ctx.start_processor(rx_addr.clone(), receiver).await?;
 
@@ -204,80 +335,34 @@ impl Worker for TcpSendWorker {
             let _ = ctx.stop_processor(rx_addr).await;
         }
 
+        if let Some(counters) = self.counters.take() {
+            self.router_handle
+                .metrics()
+                .connection_closed(self.peer, &counters);
+        }
+
         Ok(())
     }
 
-    // TcpSendWorker will receive messages from the TcpRouter to send
-    // across the TcpStream to our friend
     async fn handle_message(
         &mut self,
         ctx: &mut Context,
         msg: Routed<Self::Message>,
     ) -> Result<()> {
-        self.heartbeat.cancel();
-
-        let tx = match &mut self.tx {
-            Some(tx) => tx,
-            None => return Err(TransportError::PeerNotFound.into()),
-        };
-
-        let recipient = msg.msg_addr();
-        if recipient == self.internal_addr {
-            let msg = TcpSendWorkerMsg::decode(msg.payload())?;
-
-            match msg {
-                TcpSendWorkerMsg::Heartbeat => {
-                    let msg = TransportMessage::v1(route![], route![], vec![]);
-                    let msg = prepare_message(msg)?;
-                    // Sending empty heartbeat
-                    if tx.write_all(&msg).await.is_err() {
-                        warn!("Failed to send heartbeat to peer {}", self.peer);
-                        self.stop_and_unregister(ctx).await?;
-
-                        return Ok(());
-                    }
-
-                    debug!("Sent heartbeat to peer {}", self.peer);
-                }
-                TcpSendWorkerMsg::ConnectionClosed => {
-                    warn!("Stopping sender due to closed connection {}", self.peer);
-                    // No need to stop Receiver as it notified us about connection drop and will
-                    // stop itself
-                    self.rx_addr = None;
-                    self.stop_and_unregister(ctx).await?;
-
-                    return Ok(());
-                }
-            }
-        } else {
-            let mut msg = LocalMessage::decode(msg.payload())?.into_transport_message();
-            // Remove our own address from the route so the other end
-            // knows what to do with the incoming message
-            msg.onward_route.step()?;
-            // Create a message buffer with pre-pended length
-            let msg = prepare_message(msg)?;
-
-            if tx.write_all(msg.as_slice()).await.is_err() {
-                warn!("Failed to send message to peer {}", self.peer);
-                self.stop_and_unregister(ctx).await?;
-
-                return Ok(());
-            }
-        }
-
-        self.schedule_heartbeat().await?;
-
-        Ok(())
+        let span = debug_span!("tcp_send_worker", peer = %self.peer, message_id = field::Empty);
+        self.send(ctx, msg).instrument(span).await
     }
 }
 
 /// Helper that creates a length-prefixed buffer containing the given
-/// `TransportMessage`'s payload
+/// `TransportMessage`'s payload, in the wire format of `version`
 ///
 /// The length-prefix is encoded as a big-endian 16-bit unsigned
 /// integer.
-fn prepare_message(msg: TransportMessage) -> Result<Vec<u8>> {
-    let mut msg_buf = msg.encode().map_err(|_| TransportError::SendBadMessage)?;
+fn prepare_message(msg: TransportMessage, ttl: u8, version: u8) -> Result<Vec<u8>> {
+    let mut msg_buf = msg
+        .encode_versioned(version, ttl)
+        .map_err(|_| TransportError::SendBadMessage)?;
 
     // Create a buffer that includes the message length in big endian
     let mut len = (msg_buf.len() as u16).to_be_bytes().to_vec();
@@ -1,8 +1,16 @@
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use core::time::Duration;
 use ockam_core::compat::rand::{self, Rng};
-use ockam_core::{route, Address, Result, Routed, Worker};
-use ockam_node::Context;
+use ockam_core::compat::sync::Arc;
+use ockam_core::{
+    route, Address, Any, Decodable, Encodable, MessagePriority, Result, Routed, TransportMessage,
+    Worker,
+};
+use ockam_node::{tokio, Context};
+use std::time::SystemTime;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
-use ockam_transport_tcp::{TcpTransport, TCP};
+use ockam_transport_tcp::{Resolver, TcpTransport, TCP};
 
 #[ockam_macros::test]
 async fn send_receive(ctx: &mut Context) -> Result<()> {
@@ -32,6 +40,122 @@ async fn send_receive(ctx: &mut Context) -> Result<()> {
     Ok(())
 }
 
+/// Start a nameserver answering every query with `127.0.0.1`
+fn start_stub_nameserver() -> std::io::Result<std::net::SocketAddr> {
+    let socket = std::net::UdpSocket::bind("127.0.0.1:0")?;
+    let addr = socket.local_addr()?;
+    std::thread::spawn(move || {
+        let mut buf = [0u8; 512];
+        while let Ok((len, from)) = socket.recv_from(&mut buf) {
+            // Keep the id and question, set the response flags and one answer
+            let mut answer = buf[..len].to_vec();
+            answer[2..4].copy_from_slice(&[0x81, 0x80]);
+            answer[6..8].copy_from_slice(&[0, 1]);
+            answer.extend_from_slice(&[0xc0, 12, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4, 127, 0, 0, 1]);
+            let _ = socket.send_to(&answer, from);
+        }
+    });
+    Ok(addr)
+}
+
+#[ockam_macros::test]
+async fn send_receive_with_nameservers(ctx: &mut Context) -> Result<()> {
+    let nameserver = start_stub_nameserver().unwrap();
+    let transport = TcpTransport::create_with_nameservers(ctx, vec![nameserver]).await?;
+    let listener_address = transport.listen("127.0.0.1:0").await?;
+    ctx.start_worker("echoer", Echoer).await?;
+
+    // The hostname is only known to the stub nameserver
+    let peer = format!("peer.ockam.test:{}", listener_address.port());
+    let r = route![(TCP, peer), "echoer"];
+    let reply = ctx
+        .send_and_receive::<_, _, String>(r, "Hello".to_string())
+        .await?;
+    assert_eq!(reply, "Hello");
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+/// Resolves every hostname to the loopback address, keeping the port
+struct LoopbackResolver;
+
+#[ockam_core::async_trait]
+impl Resolver for LoopbackResolver {
+    async fn resolve(&self, peer: &str) -> Result<Vec<std::net::SocketAddr>> {
+        let port = peer
+            .rsplit_once(':')
+            .and_then(|(_, port)| port.parse().ok());
+        Ok(port
+            .map(|port| ([127, 0, 0, 1], port).into())
+            .into_iter()
+            .collect())
+    }
+}
+
+#[ockam_macros::test]
+async fn send_receive_with_resolver(ctx: &mut Context) -> Result<()> {
+    let transport = TcpTransport::create_with_resolver(ctx, LoopbackResolver).await?;
+    let listener_address = transport.listen("127.0.0.1:0").await?;
+    ctx.start_worker("echoer", Echoer).await?;
+
+    // The hostname is only known to the injected resolver
+    let peer = format!("peer.ockam.test:{}", listener_address.port());
+    let r = route![(TCP, peer.clone()), "echoer"];
+    let reply = ctx
+        .send_and_receive::<_, _, String>(r, "Hello".to_string())
+        .await?;
+    assert_eq!(reply, "Hello");
+    assert!(transport.connection_metrics(&peer).is_some());
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+/// Never answers for `hanging.ockam.test`, resolves other hostnames like
+/// [`LoopbackResolver`]
+struct HangingResolver;
+
+#[ockam_core::async_trait]
+impl Resolver for HangingResolver {
+    async fn resolve(&self, peer: &str) -> Result<Vec<std::net::SocketAddr>> {
+        if peer.starts_with("hanging.ockam.test:") {
+            std::future::pending::<()>().await;
+        }
+        LoopbackResolver.resolve(peer).await
+    }
+}
+
+#[ockam_macros::test]
+async fn slow_resolution_does_not_hold_up_routing(ctx: &mut Context) -> Result<()> {
+    let transport = TcpTransport::create_with_resolver(ctx, HangingResolver).await?;
+    let listener_address = transport.listen("127.0.0.1:0").await?;
+    ctx.start_worker("echoer", Echoer).await?;
+
+    let hanging = format!("hanging.ockam.test:{}", listener_address.port());
+    ctx.send(route![(TCP, hanging), "echoer"], "Lost".to_string())
+        .await?;
+
+    // Messages to other peers are routed while the hostname is resolved
+    let peer = format!("peer.ockam.test:{}", listener_address.port());
+    let reply = ctx
+        .send_and_receive::<_, _, String>(route![(TCP, peer), "echoer"], "Hello".to_string())
+        .await?;
+    assert_eq!(reply, "Hello");
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
 pub struct Echoer;
 
 #[ockam_core::worker]
@@ -93,3 +217,356 @@ async fn tcp_lifecycle__reconnect__should_not_error(ctx: &mut Context) -> Result
 
     Ok(())
 }
+
+/// Resolves hostnames as [`LoopbackResolver`] once, then doesn't, as a
+/// nameserver which became unreachable
+#[derive(Default)]
+struct ResolveOnce(AtomicBool);
+
+#[ockam_core::async_trait]
+impl Resolver for ResolveOnce {
+    async fn resolve(&self, peer: &str) -> Result<Vec<std::net::SocketAddr>> {
+        if self.0.swap(true, Ordering::SeqCst) {
+            return Ok(vec![]);
+        }
+        LoopbackResolver.resolve(peer).await
+    }
+}
+
+#[ockam_macros::test]
+async fn reconnect_restores_the_routes_of_a_dropped_connection(ctx: &mut Context) -> Result<()> {
+    let transport = TcpTransport::create_with_resolver(ctx, ResolveOnce::default()).await?;
+    let listener_address = transport.listen("127.0.0.1:0").await?;
+    ctx.start_worker("echoer", Echoer).await?;
+    let mut child_ctx = ctx.new_detached(Address::random_local()).await?;
+
+    let peer = format!("peer.ockam.test:{}", listener_address.port());
+    let tx_address = transport.connect(&peer).await?;
+    let through_hostname = route![(TCP, peer.clone()), "echoer"];
+    child_ctx
+        .send(through_hostname.clone(), "Hello".to_string())
+        .await?;
+    let reply = child_ctx
+        .receive_duration_timeout::<String>(Duration::from_secs(2))
+        .await?;
+    assert_eq!(*reply, "Hello");
+
+    // Drop the connection from the listener's end
+    let inbound = transport
+        .metrics()
+        .connections()
+        .into_iter()
+        .map(|(peer, _)| peer)
+        .find(|peer| *peer != listener_address)
+        .unwrap();
+    transport.disconnect(inbound.to_string()).await?;
+    let mut dropped = false;
+    for _ in 0..50 {
+        if !ctx.list_workers().await?.contains(&tx_address) {
+            dropped = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(dropped, "The connection should drop");
+
+    // Restore it through the peer's socket address
+    child_ctx
+        .send(
+            route![(TCP, listener_address.to_string()), "echoer"],
+            "Hello".to_string(),
+        )
+        .await?;
+    let reply = child_ctx
+        .receive_duration_timeout::<String>(Duration::from_secs(2))
+        .await?;
+    assert_eq!(*reply, "Hello");
+
+    // The route through the hostname resolves to the new connection, the
+    // hostname can't be resolved again
+    child_ctx
+        .send(through_hostname, "Hello".to_string())
+        .await?;
+    let reply = child_ctx
+        .receive_duration_timeout::<String>(Duration::from_secs(2))
+        .await?;
+    assert_eq!(*reply, "Hello");
+    assert_eq!(transport.metrics().active_connections(), 2);
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+#[ockam_macros::test]
+async fn metrics_track_connections_and_traffic(ctx: &mut Context) -> Result<()> {
+    ctx.start_worker("echoer", Echoer).await?;
+
+    let transport = TcpTransport::create(ctx).await?;
+    let listener_address = transport.listen("127.0.0.1:0").await?.to_string();
+    let metrics = transport.metrics();
+    assert_eq!(metrics.active_connections(), 0);
+
+    transport.connect(&listener_address).await?;
+    let r = route![(TCP, listener_address.clone()), "echoer"];
+    let reply = ctx
+        .send_and_receive::<_, _, String>(r, "Hello".to_string())
+        .await?;
+    assert_eq!(reply, "Hello");
+
+    // Both ends of the connection live in this node
+    assert_eq!(metrics.active_connections(), 2);
+    assert!(metrics.idle_for() < Duration::from_secs(5));
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(metrics.idle_for() >= Duration::from_millis(200));
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+#[ockam_macros::test]
+async fn connection_metrics_count_bytes(ctx: &mut Context) -> Result<()> {
+    ctx.start_worker("echoer", Echoer).await?;
+
+    let transport = TcpTransport::create(ctx).await?;
+    let listener_address = transport.listen("127.0.0.1:0").await?.to_string();
+    assert!(transport.connection_metrics(&listener_address).is_none());
+
+    transport.connect(&listener_address).await?;
+    let r = route![(TCP, listener_address.clone()), "echoer"];
+    let reply = ctx
+        .send_and_receive::<_, _, String>(r, "Hello".to_string())
+        .await?;
+    assert_eq!(reply, "Hello");
+
+    let metrics = transport.connection_metrics(&listener_address).unwrap();
+    assert!(metrics.bytes_out > 0);
+    assert!(metrics.bytes_in > 0);
+    assert!(metrics.connected_at <= SystemTime::now());
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+#[ockam_macros::test]
+async fn connect_to_unreachable_peer_fails(ctx: &mut Context) -> Result<()> {
+    let transport = TcpTransport::create(ctx).await?;
+
+    // Nothing listens on a port that was just released
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    drop(listener);
+
+    assert!(transport.connect(&address).await.is_err());
+    assert_eq!(transport.metrics().active_connections(), 0);
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+/// Sends every message it gets back to itself through a TCP connection
+struct Looper {
+    route: ockam_core::Route,
+    report: Address,
+    hops: Arc<AtomicU32>,
+}
+
+#[ockam_core::worker]
+impl Worker for Looper {
+    type Message = Any;
+    type Context = Context;
+
+    async fn handle_message(&mut self, ctx: &mut Context, msg: Routed<Any>) -> Result<()> {
+        self.hops.fetch_add(1, Ordering::Relaxed);
+        let mut local_msg = msg.into_local_message();
+        local_msg.transport_mut().onward_route = self.route.clone();
+        if ctx.forward(local_msg).await.is_err() {
+            ctx.send(self.report.clone(), "dropped".to_string()).await?;
+        }
+        Ok(())
+    }
+}
+
+#[ockam_macros::test]
+async fn routing_loop_through_tcp_is_dropped(ctx: &mut Context) -> Result<()> {
+    let transport = TcpTransport::create(ctx).await?;
+    let listener_address = transport.listen("127.0.0.1:0").await?;
+
+    let hops = Arc::new(AtomicU32::new(0));
+    let looper = Looper {
+        route: route![(TCP, listener_address.to_string()), "looper"],
+        report: ctx.address(),
+        hops: hops.clone(),
+    };
+    ctx.start_worker("looper", looper).await?;
+
+    // The TTL crosses the connection, rather than starting over on each pass
+    ctx.send(route!["looper"], "loop".to_string()).await?;
+    let msg = ctx.receive_timeout::<String>(10).await?;
+    assert_eq!(*msg, "dropped");
+    let hops = hops.load(Ordering::Relaxed);
+    assert!(hops > 1 && hops <= ockam_core::DEFAULT_TTL as u32);
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+/// Replies with the priority of the messages it gets
+struct PriorityEchoer;
+
+#[ockam_core::worker]
+impl Worker for PriorityEchoer {
+    type Message = String;
+    type Context = Context;
+
+    async fn handle_message(&mut self, ctx: &mut Context, msg: Routed<String>) -> Result<()> {
+        let priority = format!("{:?}", msg.local_message().priority());
+        ctx.send(msg.return_route(), priority).await
+    }
+}
+
+#[ockam_macros::test]
+async fn remote_messages_have_the_normal_priority(ctx: &mut Context) -> Result<()> {
+    let transport = TcpTransport::create(ctx).await?;
+    let listener_address = transport.listen("127.0.0.1:0").await?;
+    ctx.start_worker("priority_echoer", PriorityEchoer).await?;
+
+    // A peer can't have its messages overtake the ones of this node
+    let r = route![(TCP, listener_address.to_string()), "priority_echoer"];
+    ctx.send_with_priority(r, "Hello".to_string(), MessagePriority::High)
+        .await?;
+    let reply = ctx.receive::<String>().await?;
+    assert_eq!(*reply, "Normal");
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+async fn read_frame(stream: &mut tokio::net::TcpStream) -> Option<Vec<u8>> {
+    let mut len = [0u8; 2];
+    stream.read_exact(&mut len).await.ok()?;
+    let mut buf = vec![0; u16::from_be_bytes(len) as usize];
+    stream.read_exact(&mut buf).await.ok()?;
+    Some(buf)
+}
+
+async fn write_frame(stream: &mut tokio::net::TcpStream, buf: &[u8]) {
+    stream
+        .write_all(&(buf.len() as u16).to_be_bytes())
+        .await
+        .unwrap();
+    stream.write_all(buf).await.unwrap();
+}
+
+#[ockam_macros::test]
+async fn peer_without_versions_is_spoken_to_in_v1(ctx: &mut Context) -> Result<()> {
+    let transport = TcpTransport::create(ctx).await?;
+
+    // A peer predating the versions, which echoes the messages it gets
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let peer = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        // It takes the version offer for a heartbeat
+        let (buf, msg) = loop {
+            let buf = read_frame(&mut stream).await.unwrap();
+            let msg = TransportMessage::decode(&buf).unwrap();
+            if msg.onward_route.next().is_ok() {
+                break (buf, msg);
+            }
+        };
+        let reply = TransportMessage::v1(msg.return_route, route![], msg.payload);
+        write_frame(&mut stream, &reply.encode().unwrap()).await;
+        (buf[0], stream)
+    });
+
+    ctx.send(route![(TCP, address), "echoer"], "Hello".to_string())
+        .await?;
+    let reply = ctx.receive::<String>().await?;
+    assert_eq!(*reply, "Hello");
+    let (version, _stream) = peer.await.unwrap();
+    assert_eq!(version, 1);
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+#[ockam_macros::test]
+async fn peer_without_a_common_version_is_disconnected(ctx: &mut Context) -> Result<()> {
+    let transport = TcpTransport::create(ctx).await?;
+
+    // A peer which only speaks version 3
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let peer = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let offer = TransportMessage::decode(&read_frame(&mut stream).await.unwrap()).unwrap();
+        let mut payload = offer.payload;
+        let len = payload.len();
+        payload[len - 2..].copy_from_slice(&[3, 3]);
+        let offer = TransportMessage::v1(route![], route![], payload);
+        write_frame(&mut stream, &offer.encode().unwrap()).await;
+        // Until the connection is closed
+        while read_frame(&mut stream).await.is_some() {}
+    });
+
+    transport.connect(&address).await?;
+    assert!(tokio::time::timeout(Duration::from_secs(10), peer)
+        .await
+        .is_ok());
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+#[ockam_macros::test]
+async fn concurrent_connects_to_the_same_peer(ctx: &mut Context) -> Result<()> {
+    let transport = TcpTransport::create(ctx).await?;
+
+    // Connections are accepted by the kernel without being accepted here
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+
+    let (first, second) = tokio::join!(transport.connect(&address), transport.connect(&address));
+    assert!(first.is_ok() != second.is_ok());
+
+    // The connection which lost the race is closed
+    let metrics = transport.metrics();
+    for _ in 0..50 {
+        if metrics.active_connections() == 1 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(metrics.active_connections(), 1);
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
@@ -0,0 +1,122 @@
+use std::collections::BTreeSet;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use futures_util::future::join_all;
+use ockam_core::{async_trait, Address, Any, Result, Routed, Worker};
+use ockam_node::Context;
+use tokio::time::timeout;
+use tracing::{trace, warn};
+
+/// How long a subscriber's mailbox may stay full before it misses a datagram
+const DELIVERY_TIMEOUT: Duration = Duration::from_millis(100);
+
+type Subscribers = Arc<RwLock<BTreeSet<Address>>>;
+
+/// A handle to a worker delivering each message it receives to all its
+/// subscribers
+///
+/// Route the datagrams of a multicast listener, see
+/// [`UdpTransport::listen_multicast`](crate::UdpTransport::listen_multicast),
+/// to the fan-out address to deliver them to several local workers.
+/// Subscribers get the rest of the onward route and the original return
+/// route, so they can answer the sender.
+///
+/// Subscribers are served concurrently. One whose mailbox stays full misses
+/// the message rather than delaying the others, as if the datagram had been
+/// lost on the network.
+///
+/// ```rust
+/// use ockam_transport_udp::{UdpFanOut, UdpTransport};
+/// # use ockam_node::Context;
+/// # use ockam_core::Result;
+/// # async fn test(ctx: Context) -> Result<()> {
+/// let udp = UdpTransport::create(&ctx).await?;
+/// udp.listen_multicast("0.0.0.0:5000", [239, 255, 0, 1].into()).await?;
+///
+/// // Datagrams routed to "discovery" reach both workers
+/// let fan_out = UdpFanOut::create(&ctx, "discovery").await?;
+/// fan_out.subscribe("peers");
+/// fan_out.subscribe("metrics");
+/// # Ok(()) }
+/// ```
+pub struct UdpFanOut {
+    address: Address,
+    subscribers: Subscribers,
+}
+
+impl UdpFanOut {
+    /// Start a fan-out worker at `address`, without subscribers
+    pub async fn create(ctx: &Context, address: impl Into<Address>) -> Result<UdpFanOut> {
+        let address = address.into();
+        let subscribers = Subscribers::default();
+        let worker = UdpFanOutWorker {
+            subscribers: subscribers.clone(),
+        };
+        ctx.start_worker(address.clone(), worker).await?;
+
+        Ok(Self {
+            address,
+            subscribers,
+        })
+    }
+
+    /// Return the address of the fan-out worker
+    pub fn address(&self) -> &Address {
+        &self.address
+    }
+
+    /// Deliver the following messages to `subscriber` too
+    ///
+    /// Returns `false` if it was already subscribed.
+    pub fn subscribe(&self, subscriber: impl Into<Address>) -> bool {
+        self.subscribers.write().unwrap().insert(subscriber.into())
+    }
+
+    /// Stop delivering messages to `subscriber`
+    ///
+    /// Returns `false` if it wasn't subscribed.
+    pub fn unsubscribe(&self, subscriber: &Address) -> bool {
+        self.subscribers.write().unwrap().remove(subscriber)
+    }
+
+    /// Return the current subscribers
+    pub fn subscribers(&self) -> Vec<Address> {
+        self.subscribers.read().unwrap().iter().cloned().collect()
+    }
+}
+
+struct UdpFanOutWorker {
+    subscribers: Subscribers,
+}
+
+#[async_trait]
+impl Worker for UdpFanOutWorker {
+    type Message = Any;
+    type Context = Context;
+
+    async fn handle_message(&mut self, ctx: &mut Context, msg: Routed<Any>) -> Result<()> {
+        let subscribers = self.subscribers.read().unwrap().clone();
+        let mut local_msg = msg.into_local_message();
+        local_msg.transport_mut().onward_route.step()?;
+        let ctx: &Context = ctx;
+
+        let deliveries = subscribers.into_iter().map(|subscriber| {
+            let mut msg = local_msg.clone();
+            msg.transport_mut()
+                .onward_route
+                .modify()
+                .prepend(subscriber.clone());
+            async move {
+                match timeout(DELIVERY_TIMEOUT, ctx.forward(msg)).await {
+                    Ok(Ok(())) => trace!("Delivered message to {}", subscriber),
+                    Ok(Err(e)) => warn!("Failed to deliver message to {}: {}", subscriber, e),
+                    Err(_) => warn!("Subscriber {} is too slow, dropping message", subscriber),
+                }
+            }
+        });
+        join_all(deliveries).await;
+
+        Ok(())
+    }
+}
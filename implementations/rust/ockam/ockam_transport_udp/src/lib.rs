@@ -1,9 +1,12 @@
 use std::net::SocketAddr;
 
+pub use fan_out::*;
 use ockam_core::{Result, TransportType};
 use ockam_transport_core::TransportError;
 pub use transport::*;
+pub use workers::DEFAULT_READ_BATCH_SIZE;
 
+mod fan_out;
 mod router;
 mod transport;
 mod workers;
@@ -1,22 +1,34 @@
 use std::{
-    net::{SocketAddr, ToSocketAddrs},
-    str::FromStr,
+    io,
+    net::{IpAddr, Ipv4Addr, SocketAddr, ToSocketAddrs},
+    sync::Arc,
+    time::Duration,
 };
 
 use futures_util::stream::StreamExt;
 use ockam_core::{async_trait, Address, AsyncTryClone, Result};
 use ockam_node::Context;
-use ockam_transport_core::TransportError;
+use ockam_transport_core::{RouteLengthLimit, TransportError};
 use tokio::net::UdpSocket;
 use tokio_util::udp::UdpFramed;
 
 use crate::{
     parse_socket_addr,
-    workers::{TransportMessageCodec, UdpListenProcessor, UdpSendWorker},
-    UdpAddress,
+    workers::{
+        ReadBatchSize, ReturnRoutability, TransportMessageCodec, UdpListenProcessor, UdpSendWorker,
+    },
+    UdpAddress, UDP,
 };
 
-use super::UdpRouterMessage;
+use super::{UdpRouterMessage, UdpRouterResponse};
+
+/// Resolves a `host:port` string into socket addresses, in preference order
+pub(crate) type PeerResolver = Arc<dyn Fn(&str) -> io::Result<Vec<SocketAddr>> + Send + Sync>;
+
+/// [`PeerResolver`] using the system's DNS configuration
+pub(crate) fn system_resolver() -> PeerResolver {
+    Arc::new(|peer| peer.to_socket_addrs().map(|addrs| addrs.collect()))
+}
 
 /// A handle to connect to a UdpRouter
 ///
@@ -24,75 +36,226 @@ use super::UdpRouterMessage;
 pub(crate) struct UdpRouterHandle {
     ctx: Context,
     api_addr: Address,
+    route_limit: RouteLengthLimit,
+    read_batch_size: ReadBatchSize,
 }
 
 #[async_trait]
 impl AsyncTryClone for UdpRouterHandle {
     async fn async_try_clone(&self) -> Result<Self> {
         let child_ctx = self.ctx.new_detached(Address::random_local()).await?;
-        Ok(Self::new(child_ctx, self.api_addr.clone()))
+        Ok(Self::new(
+            child_ctx,
+            self.api_addr.clone(),
+            self.route_limit.clone(),
+            self.read_batch_size.clone(),
+        ))
     }
 }
 
 impl UdpRouterHandle {
     /// Create a new `UdpRouterHandle` with given address
-    pub fn new(ctx: Context, api_addr: Address) -> Self {
-        Self { ctx, api_addr }
+    pub fn new(
+        ctx: Context,
+        api_addr: Address,
+        route_limit: RouteLengthLimit,
+        read_batch_size: ReadBatchSize,
+    ) -> Self {
+        Self {
+            ctx,
+            api_addr,
+            route_limit,
+            read_batch_size,
+        }
+    }
+
+    /// Return the bound on message routes shared with the router
+    pub fn route_limit(&self) -> &RouteLengthLimit {
+        &self.route_limit
+    }
+
+    /// Return the number of datagrams read per syscall, shared with the router
+    pub fn read_batch_size(&self) -> &ReadBatchSize {
+        &self.read_batch_size
+    }
+
+    /// Resolve the given peer to all its [`SocketAddr`](std::net::SocketAddr)s,
+    /// IPv4 and IPv6, in the order returned by the system resolver
+    pub fn resolve_peer(peer: impl Into<String>) -> Result<(Vec<SocketAddr>, Vec<String>)> {
+        Self::resolve_peer_with(peer, &system_resolver())
     }
 
-    /// Resolve the given peer to a [`SocketAddr`](std::net::SocketAddr)
-    pub fn resolve_peer(peer: impl Into<String>) -> Result<(SocketAddr, Vec<String>)> {
+    /// Resolve the given peer to all its [`SocketAddr`](std::net::SocketAddr)s
+    /// using `resolver` to look up hostnames
+    pub fn resolve_peer_with(
+        peer: impl Into<String>,
+        resolver: &PeerResolver,
+    ) -> Result<(Vec<SocketAddr>, Vec<String>)> {
         let peer_str = peer.into();
-        let peer_addr;
+        let peer_addrs;
         let hostnames;
 
         // Try to parse as SocketAddr
         if let Ok(p) = parse_socket_addr(peer_str.clone()) {
-            peer_addr = p;
+            peer_addrs = vec![p];
             hostnames = vec![];
-        } else if let Ok(mut iter) = peer_str.to_socket_addrs() {
+        } else if let Ok(addrs) = resolver(&peer_str) {
             // Try to resolve hostname
-            // FIXME: We only take ipv4 for now
-            if let Some(p) = iter.find(|x| x.is_ipv4()) {
-                peer_addr = p;
-            } else {
+            if addrs.is_empty() {
                 return Err(TransportError::InvalidAddress.into());
             }
 
+            peer_addrs = addrs;
             hostnames = vec![peer_str];
         } else {
             return Err(TransportError::InvalidAddress.into());
         }
 
-        Ok((peer_addr, hostnames))
+        Ok((peer_addrs, hostnames))
     }
 
     /// Bind a listener with given address for this router
-    pub async fn bind(&self, addr: impl Into<SocketAddr>) -> Result<()> {
+    ///
+    /// With `return_routability`, the datagrams of a peer are only
+    /// accepted once it echoed a cookie sent to its source address.
+    pub async fn bind(&self, addr: impl Into<SocketAddr>, return_routability: bool) -> Result<()> {
         let socket = UdpSocket::bind(addr.into())
             .await
             .map_err(TransportError::from)?;
-        let (sink, stream) = UdpFramed::new(socket, TransportMessageCodec).split();
+        let routability = if return_routability {
+            Some(ReturnRoutability::new())
+        } else {
+            None
+        };
+        self.listen_on(socket, routability).await
+    }
 
+    /// Bind a listener with given address, which also receives the
+    /// datagrams sent to the multicast `group`
+    pub async fn bind_multicast(&self, addr: impl Into<SocketAddr>, group: IpAddr) -> Result<()> {
+        if !group.is_multicast() {
+            return Err(TransportError::InvalidAddress.into());
+        }
+
+        let socket = UdpSocket::bind(addr.into())
+            .await
+            .map_err(TransportError::from)?;
+        // Let the system pick the interface
+        match group {
+            IpAddr::V4(group) => socket.join_multicast_v4(group, Ipv4Addr::UNSPECIFIED),
+            IpAddr::V6(group) => socket.join_multicast_v6(&group, 0),
+        }
+        .map_err(TransportError::from)?;
+        self.listen_on(socket, None).await
+    }
+
+    async fn listen_on(
+        &self,
+        socket: UdpSocket,
+        routability: Option<ReturnRoutability>,
+    ) -> Result<()> {
+        let socket = Arc::new(socket);
+        let (sink, _) = UdpFramed::new(socket.clone(), TransportMessageCodec).split();
+
+        // A listener serves every peer, so its socket stays unconnected
         let tx_addr = Address::random_local();
         let sender = UdpSendWorker::new(sink);
         self.ctx.start_worker(tx_addr.clone(), sender).await?;
-        UdpListenProcessor::start(&self.ctx, stream, tx_addr, self.async_try_clone().await?)
+        let rx_addr = UdpListenProcessor::start(
+            &self.ctx,
+            socket,
+            None,
+            tx_addr.clone(),
+            self.async_try_clone().await?,
+            routability,
+        )
+        .await?;
+
+        // Wait for the router to track the listener, so that a router
+        // stopped after this returns also stops the listener
+        let response = self
+            .ctx
+            .send_and_receive(
+                self.api_addr.clone(),
+                UdpRouterMessage::Listening {
+                    tx_addr: tx_addr.clone(),
+                    rx_addr: rx_addr.clone(),
+                },
+            )
+            .await;
+        let err = match response {
+            Ok(UdpRouterResponse::Listening) => return Ok(()),
+            Ok(_) => TransportError::InvalidRouterResponseType.into(),
+            Err(err) => err,
+        };
+        // Nothing else stops the workers of an untracked listener
+        let _ = self.ctx.stop_processor(rx_addr).await;
+        let _ = self.ctx.stop_worker(tx_addr).await;
+        Err(err)
+    }
+
+    /// Establish an outgoing UDP connection to the given peer
+    ///
+    /// Returns the address of the `UdpSendWorker` serving this peer,
+    /// and the resolved socket address it sends to.
+    pub async fn connect<S: AsRef<str>>(&self, peer: S) -> Result<(Address, SocketAddr)> {
+        let response = self
+            .ctx
+            .send_and_receive(
+                self.api_addr.clone(),
+                UdpRouterMessage::Connect {
+                    peer: peer.as_ref().to_string(),
+                },
+            )
+            .await?;
+
+        if let UdpRouterResponse::Connect(res) = response {
+            res
+        } else {
+            Err(TransportError::InvalidRouterResponseType.into())
+        }
+    }
+
+    /// Disconnect an outgoing UDP connection to the given peer
+    pub async fn disconnect<S: AsRef<str>>(&self, peer: S) -> Result<()> {
+        let response = self
+            .ctx
+            .send_and_receive(
+                self.api_addr.clone(),
+                UdpRouterMessage::Disconnect {
+                    peer: peer.as_ref().to_string(),
+                },
+            )
             .await?;
 
-        Ok(())
+        if let UdpRouterResponse::Disconnect(res) = response {
+            res
+        } else {
+            Err(TransportError::InvalidRouterResponseType.into())
+        }
+    }
+
+    /// Forget the peers idle for longer than `timeout`, stopping the
+    /// workers of the outgoing connections to them
+    pub async fn set_idle_timeout(&self, timeout: Duration) -> Result<()> {
+        self.ctx
+            .send(
+                self.api_addr.clone(),
+                UdpRouterMessage::IdleTimeout { timeout },
+            )
+            .await
+    }
+
+    /// Stop the router, along with the workers of its listeners and
+    /// outgoing connections
+    pub async fn stop(&self) -> Result<()> {
+        self.ctx.stop_worker(self.api_addr.clone()).await
     }
 
     /// Register a new worker with this router
     pub(crate) async fn register(&self, tx_addr: Address, peer: impl Into<String>) -> Result<()> {
-        let (peer, hostnames) = Self::resolve_peer(peer.into())?;
-        let mut accepts = vec![UdpAddress::from(peer).into()];
-        accepts.extend(
-            hostnames
-                .iter()
-                .filter_map(|s| UdpAddress::from_str(s).ok())
-                .map(|addr| addr.into()),
-        );
+        let (peers, hostnames) = Self::resolve_peer(peer.into())?;
+        let accepts = peer_aliases(&peers, &hostnames);
 
         // TODO: should we send a router request instead
         // and see if worker is already registered?
@@ -107,3 +270,12 @@ impl UdpRouterHandle {
             .await
     }
 }
+
+/// Every `type = 2` address a peer can be reached with
+pub(crate) fn peer_aliases(peers: &[SocketAddr], hostnames: &[String]) -> Vec<Address> {
+    peers
+        .iter()
+        .map(|p| Address::from(UdpAddress::from(*p)))
+        .chain(hostnames.iter().map(|h| Address::from((UDP, h.clone()))))
+        .collect()
+}
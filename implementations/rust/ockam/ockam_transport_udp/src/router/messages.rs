@@ -1,4 +1,7 @@
-use ockam_core::{Address, Message};
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use ockam_core::{Address, Message, Result};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug, Clone, Hash, Ord, PartialOrd, Eq, PartialEq, Message)]
@@ -10,4 +13,23 @@ pub(crate) enum UdpRouterMessage {
         /// The clients own worker bus address.
         self_addr: Address,
     },
+    /// Track the workers of a listener, which are stopped with the router,
+    /// answered with [`UdpRouterResponse::Listening`]
+    Listening { tx_addr: Address, rx_addr: Address },
+    /// Connect to a peer, answered with [`UdpRouterResponse::Connect`]
+    Connect { peer: String },
+    /// Disconnect from a peer, answered with [`UdpRouterResponse::Disconnect`]
+    Disconnect { peer: String },
+    /// Forget the peers idle for longer than `timeout`
+    IdleTimeout { timeout: Duration },
+    /// Forget the peers which are idle, sent periodically by the router
+    /// once an idle timeout is set
+    Sweep,
+}
+
+#[derive(Serialize, Deserialize, Debug, Message)]
+pub(crate) enum UdpRouterResponse {
+    Connect(Result<(Address, SocketAddr)>),
+    Disconnect(Result<()>),
+    Listening,
 }
@@ -1,7 +1,7 @@
-pub(crate) use handle::UdpRouterHandle;
+pub(crate) use handle::{system_resolver, UdpRouterHandle};
 pub(crate) use udp_router::UdpRouter;
 
-use self::messages::UdpRouterMessage;
+use self::messages::{UdpRouterMessage, UdpRouterResponse};
 
 mod handle;
 mod messages;
@@ -1,18 +1,28 @@
+use std::collections::BTreeMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::ops::Deref;
-use std::{collections::BTreeMap, str::FromStr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use futures_util::StreamExt;
-use ockam_core::{async_trait, Address, Any, Decodable, LocalMessage, Result, Routed, Worker};
-use ockam_node::Context;
+use ockam_core::{
+    async_trait, Address, Any, Decodable, LocalMessage, MessageId, Result, Routed, Worker,
+};
+use ockam_node::{Context, DelayedEvent};
 
-use ockam_transport_core::TransportError;
+use ockam_transport_core::{LogThrottle, RouteLengthLimit, TransportError};
 use tokio::net::UdpSocket;
 use tokio_util::udp::UdpFramed;
-use tracing::{error, trace};
+use tracing::field::{self, display};
+use tracing::{debug, debug_span, error, info, trace, Instrument};
 
-use crate::router::{UdpRouterHandle, UdpRouterMessage};
+use crate::router::handle::{peer_aliases, PeerResolver};
+use crate::router::{UdpRouterHandle, UdpRouterMessage, UdpRouterResponse};
 use crate::transport::UdpAddress;
-use crate::workers::{TransportMessageCodec, UdpListenProcessor, UdpSendWorker};
+use crate::workers::{ReadBatchSize, TransportMessageCodec, UdpListenProcessor, UdpSendWorker};
+
+/// Shortest period of the sweeps of idle peers
+const MIN_SWEEP_INTERVAL: Duration = Duration::from_millis(100);
 
 /// A UDP address router and listener
 ///
@@ -27,12 +37,29 @@ pub(crate) struct UdpRouter {
     main_addr: Address,
     api_addr: Address,
     map: BTreeMap<Address, Address>,
+    /// Listen processors spawned for outgoing connections, by sender address
+    processors: BTreeMap<Address, Address>,
+    /// Sender and listen processor of each listener
+    listeners: Vec<(Address, Address)>,
+    /// Socket address each alias of an outgoing connection is served on
+    peers: BTreeMap<Address, SocketAddr>,
+    /// When a message was last routed to, or received from, each alias
+    last_activity: BTreeMap<Address, Instant>,
+    /// Peers idle for longer are forgotten, if set
+    idle_timeout: Option<Duration>,
+    /// Schedules the sweeps of idle peers, once an idle timeout is set
+    sweep: Option<DelayedEvent<UdpRouterMessage>>,
+    resolver: PeerResolver,
     allow_auto_connection: bool,
+    route_limit: RouteLengthLimit,
+    read_batch_size: ReadBatchSize,
+    /// Collapses the errors repeated for every message, e.g. of a loop
+    log_throttle: LogThrottle,
 }
 
 impl UdpRouter {
     /// Create and register a new UDP router with the node context
-    pub(crate) async fn register(ctx: &Context) -> Result<UdpRouterHandle> {
+    pub(crate) async fn register(ctx: &Context, resolver: PeerResolver) -> Result<UdpRouterHandle> {
         let main_addr = Address::random_local();
         let api_addr = Address::random_local();
 
@@ -43,13 +70,25 @@ impl UdpRouter {
             main_addr: main_addr.clone(),
             api_addr: api_addr.clone(),
             map: BTreeMap::new(),
+            processors: BTreeMap::new(),
+            listeners: Vec::new(),
+            peers: BTreeMap::new(),
+            last_activity: BTreeMap::new(),
+            idle_timeout: None,
+            sweep: None,
+            resolver,
             allow_auto_connection: true,
+            route_limit: RouteLengthLimit::default(),
+            read_batch_size: ReadBatchSize::default(),
+            log_throttle: LogThrottle::default(),
         };
 
         let handle = router.create_self_handle(ctx).await?;
 
-        ctx.start_worker(vec![main_addr.clone(), api_addr], router)
+        ctx.start_worker(vec![main_addr.clone(), api_addr.clone()], router)
             .await?;
+        ctx.set_address_tag(main_addr.clone(), "router").await?;
+        ctx.set_address_tag(api_addr, "api").await?;
         trace!("Registering UDP router for type = {}", crate::UDP);
         ctx.register(crate::UDP, main_addr).await?;
 
@@ -59,19 +98,25 @@ impl UdpRouter {
     /// Create a new `UdpRouterHandle` representing this router
     async fn create_self_handle(&self, ctx: &Context) -> Result<UdpRouterHandle> {
         let handle_ctx = ctx.new_detached(Address::random_local()).await?;
-        let handle = UdpRouterHandle::new(handle_ctx, self.api_addr.clone());
+        let handle = UdpRouterHandle::new(
+            handle_ctx,
+            self.api_addr.clone(),
+            self.route_limit.clone(),
+            self.read_batch_size.clone(),
+        );
         Ok(handle)
     }
 
     async fn handle_route(&mut self, ctx: &Context, mut msg: LocalMessage) -> Result<()> {
         trace!(
-            "UDP route request: {:?}",
-            msg.transport().onward_route.next()
+            "UDP route request: {}",
+            msg.transport().onward_route.pretty()
         );
 
         let onward = msg.transport().onward_route.next()?.clone();
 
         let next = if let Some(n) = self.map.get(&onward) {
+            self.last_activity.insert(onward.clone(), Instant::now());
             n.clone()
         } else {
             let peer_str = match String::from_utf8(onward.deref().clone()) {
@@ -80,17 +125,25 @@ impl UdpRouter {
             };
 
             if self.allow_auto_connection {
-                self.connect(peer_str).await?
+                self.connect(peer_str).await?.0
             } else {
                 return Err(TransportError::UnknownRoute.into());
             }
         };
 
+        // Send to the socket address the connection was established with,
+        // whichever alias was used to reach it
+        let peer = match self.peers.get(&onward) {
+            Some(peer) => UdpAddress::from(*peer).into(),
+            None => onward,
+        };
+
         let transport_msg = msg.transport_mut();
         transport_msg.onward_route.step()?;
         // Prepend peer socket addr so that sender can use it
-        transport_msg.onward_route.modify().prepend(onward);
+        transport_msg.onward_route.modify().prepend(peer);
         transport_msg.onward_route.modify().prepend(next.clone());
+        self.route_limit.check(transport_msg)?;
 
         ctx.send(next.clone(), msg).await?;
 
@@ -105,49 +158,215 @@ impl UdpRouter {
             return Err(TransportError::InvalidAddress.into());
         }
 
+        // Listeners register their peers with every datagram
+        let now = Instant::now();
         for accept in &accepts {
             if self.map.contains_key(accept) {
+                self.last_activity.insert(accept.clone(), now);
                 // TODO: is returning OK right if addr(s) are already registered
                 return Ok(());
             }
         }
 
         for accept in accepts {
+            self.last_activity.insert(accept.clone(), now);
             self.map.insert(accept, self_addr.clone());
         }
 
         Ok(())
     }
 
-    async fn connect(&mut self, peer: String) -> Result<Address> {
-        let socket = UdpSocket::bind("127.0.0.1:0")
-            .await
-            .map_err(TransportError::from)?;
-        let (sink, stream) = UdpFramed::new(socket, TransportMessageCodec).split();
+    /// Bind a socket for an outgoing connection to `peer`, failing if the
+    /// system has no route to it
+    ///
+    /// The socket is connected to unicast peers, see [`is_unicast`].
+    async fn bind_for(peer: SocketAddr) -> Result<UdpSocket> {
+        let ip: IpAddr = match (peer.is_ipv4(), peer.ip().is_loopback()) {
+            (true, true) => Ipv4Addr::LOCALHOST.into(),
+            (true, false) => Ipv4Addr::UNSPECIFIED.into(),
+            (false, true) => Ipv6Addr::LOCALHOST.into(),
+            (false, false) => Ipv6Addr::UNSPECIFIED.into(),
+        };
+        let local = SocketAddr::new(ip, 0);
+
+        // Connecting a UDP socket sends nothing, but checks the peer is routable
+        let socket = UdpSocket::bind(local).await.map_err(TransportError::from)?;
+        socket.connect(peer).await.map_err(TransportError::from)?;
+        if is_unicast(&peer) {
+            return Ok(socket);
+        }
+
+        // Other peers only get a probe, the sender uses an unconnected socket
+        Ok(UdpSocket::bind(local).await.map_err(TransportError::from)?)
+    }
+
+    async fn connect(&mut self, peer: String) -> Result<(Address, SocketAddr)> {
+        let (peers, hostnames) = UdpRouterHandle::resolve_peer_with(peer, &self.resolver)?;
+        let accepts = peer_aliases(&peers, &hostnames);
+
+        // Reuse the sender already serving this peer
+        for accept in &accepts {
+            if let Some(tx_addr) = self.map.get(accept) {
+                let peer = match self.peers.get(accept) {
+                    Some(peer) => *peer,
+                    None => peers[0],
+                };
+                return Ok((tx_addr.clone(), peer));
+            }
+        }
+
+        // Try resolved addresses in order, keeping the first reachable one
+        let mut connected = None;
+        for peer in &peers {
+            match Self::bind_for(*peer).await {
+                Ok(socket) => {
+                    connected = Some((socket, *peer));
+                    break;
+                }
+                Err(e) => debug!("UDP peer {} is not reachable: {}", peer, e),
+            }
+        }
+        let (socket, peer) = connected.ok_or(TransportError::PeerNotFound)?;
+        let socket = Arc::new(socket);
+        let (sink, _) = UdpFramed::new(socket.clone(), TransportMessageCodec).split();
+        let (sender, connected_peer) = if is_unicast(&peer) {
+            (UdpSendWorker::connected(socket.clone(), peer), Some(peer))
+        } else {
+            (UdpSendWorker::new(sink), None)
+        };
 
         let tx_addr = Address::random_local();
-        let sender = UdpSendWorker::new(sink);
         self.ctx.start_worker(tx_addr.clone(), sender).await?;
-        UdpListenProcessor::start(
+        let rx_addr = UdpListenProcessor::start(
             &self.ctx,
-            stream,
+            socket,
+            connected_peer,
             tx_addr.clone(),
             self.create_self_handle(&self.ctx).await?,
+            None,
         )
         .await?;
+        self.processors.insert(tx_addr.clone(), rx_addr);
 
-        let (peer, hostnames) = UdpRouterHandle::resolve_peer(peer)?;
-        let mut accepts: Vec<Address> = vec![UdpAddress::from(peer).into()];
-        accepts.extend(
-            hostnames
-                .iter()
-                .filter_map(|s| UdpAddress::from_str(s).ok())
-                .map(|addr| addr.into()),
-        );
-
+        // Register every alias so return routing works whichever one was used
+        for accept in &accepts {
+            self.peers.insert(accept.clone(), peer);
+        }
         self.handle_register(accepts, tx_addr.clone()).await?;
 
-        Ok(tx_addr)
+        Ok((tx_addr, peer))
+    }
+
+    async fn handle_disconnect(&mut self, peer: String) -> Result<()> {
+        let (peers, hostnames) = UdpRouterHandle::resolve_peer_with(peer, &self.resolver)?;
+        let accepts = peer_aliases(&peers, &hostnames);
+
+        let (udp_address, tx_addr) =
+            if let Some(accept) = accepts.iter().find(|a| self.map.contains_key(*a)) {
+                (accept.clone(), self.map[accept].clone())
+            } else {
+                error!("Failed to disconnect, peer not found: {}", accepts[0]);
+                return Err(TransportError::PeerNotFound.into());
+            };
+
+        trace!("UDP disconnect request: {} => {}", udp_address, tx_addr);
+        self.map.retain(|_, self_addr| self_addr != &tx_addr);
+        let map = &self.map;
+        self.peers.retain(|alias, _| map.contains_key(alias));
+        self.last_activity
+            .retain(|alias, _| map.contains_key(alias));
+
+        // Only connections created by this router own their socket,
+        // a listener's sender is shared with every inbound peer
+        if let Some(rx_addr) = self.processors.remove(&tx_addr) {
+            self.ctx.stop_processor(rx_addr).await?;
+            self.ctx.stop_worker(tx_addr).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Set the idle timeout, and schedule the sweeps of idle peers
+    async fn handle_idle_timeout(&mut self, ctx: &Context, timeout: Duration) -> Result<()> {
+        self.idle_timeout = Some(timeout);
+        if self.sweep.is_none() {
+            let sweep = DelayedEvent::create(ctx, self.api_addr.clone(), UdpRouterMessage::Sweep);
+            self.sweep = Some(sweep.await?);
+        }
+        self.schedule_sweep().await
+    }
+
+    async fn schedule_sweep(&mut self) -> Result<()> {
+        match (&mut self.sweep, self.idle_timeout) {
+            (Some(sweep), Some(timeout)) => {
+                sweep.schedule((timeout / 2).max(MIN_SWEEP_INTERVAL)).await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Forget the peers idle for longer than the idle timeout
+    ///
+    /// An outgoing connection is closed once all the aliases of its peer
+    /// are idle, the peers of the listeners are forgotten alias by alias.
+    async fn sweep_idle(&mut self, now: Instant) -> Result<()> {
+        let timeout = match self.idle_timeout {
+            Some(timeout) => timeout,
+            None => return Ok(()),
+        };
+        let is_idle = |alias: &Address| {
+            self.last_activity
+                .get(alias)
+                .map_or(true, |last| now.saturating_duration_since(*last) > timeout)
+        };
+
+        let mut idle_connections = Vec::new();
+        let mut idle_aliases = Vec::new();
+        for (alias, tx_addr) in &self.map {
+            if !is_idle(alias) {
+                continue;
+            }
+            if !self.processors.contains_key(tx_addr) {
+                idle_aliases.push(alias.clone());
+            } else if !idle_connections.contains(tx_addr)
+                && self
+                    .map
+                    .iter()
+                    .filter(|(_, other)| *other == tx_addr)
+                    .all(|(alias, _)| is_idle(alias))
+            {
+                idle_connections.push(tx_addr.clone());
+            }
+        }
+
+        for alias in idle_aliases {
+            debug!("Forgetting idle UDP peer {}", alias);
+            self.map.remove(&alias);
+            self.last_activity.remove(&alias);
+        }
+        for tx_addr in idle_connections {
+            info!("Closing idle UDP connection {}", tx_addr);
+            self.map.retain(|_, self_addr| self_addr != &tx_addr);
+            if let Some(rx_addr) = self.processors.remove(&tx_addr) {
+                self.ctx.stop_processor(rx_addr).await?;
+                self.ctx.stop_worker(tx_addr).await?;
+            }
+        }
+        let map = &self.map;
+        self.peers.retain(|alias, _| map.contains_key(alias));
+        self.last_activity
+            .retain(|alias, _| map.contains_key(alias));
+
+        Ok(())
+    }
+}
+
+/// Whether a connected socket can be used for `peer`, broadcast and
+/// multicast peers answer from their own address, which it would drop
+fn is_unicast(peer: &SocketAddr) -> bool {
+    match peer.ip() {
+        IpAddr::V4(ip) => !ip.is_broadcast() && !ip.is_multicast(),
+        IpAddr::V6(ip) => !ip.is_multicast(),
     }
 }
 
@@ -161,11 +380,54 @@ impl Worker for UdpRouter {
         Ok(())
     }
 
+    /// Close the sockets of the listeners and outgoing connections, and
+    /// forget the peers
+    ///
+    /// Every listen processor is stopped before the senders, so that no
+    /// datagram is routed to a stopped sender.
+    async fn shutdown(&mut self, ctx: &mut Context) -> Result<()> {
+        let workers: Vec<(Address, Address)> = core::mem::take(&mut self.processors)
+            .into_iter()
+            .chain(core::mem::take(&mut self.listeners))
+            .collect();
+        // Workers may already be stopped by a node shutdown
+        for (_, rx_addr) in &workers {
+            let _ = ctx.stop_processor(rx_addr.clone()).await;
+        }
+        for (tx_addr, _) in workers {
+            let _ = ctx.stop_worker(tx_addr).await;
+        }
+        if let Some(mut sweep) = self.sweep.take() {
+            sweep.cancel();
+        }
+        self.map.clear();
+        self.peers.clear();
+        self.last_activity.clear();
+        Ok(())
+    }
+
     async fn handle_message(&mut self, ctx: &mut Context, msg: Routed<Any>) -> Result<()> {
+        let return_route = msg.return_route();
         let msg_addr = msg.msg_addr();
 
         if msg_addr == self.main_addr {
-            self.handle_route(ctx, msg.into_local_message()).await?;
+            let msg = msg.into_local_message();
+            let span = debug_span!("udp_router", peer = field::Empty, message_id = field::Empty);
+            if !span.is_disabled() {
+                if let Ok(peer) = msg.transport().onward_route.next() {
+                    span.record("peer", &display(peer));
+                }
+                span.record(
+                    "message_id",
+                    &display(MessageId::of(&msg.transport().payload)),
+                );
+            }
+            if let Err(err) = self.handle_route(ctx, msg).instrument(span).await {
+                self.log_throttle.error(format_args!(
+                    "UDP router failed to route a message: {}",
+                    err
+                ));
+            }
         } else if msg_addr == self.api_addr {
             let msg = UdpRouterMessage::decode(msg.payload())?;
             match msg {
@@ -173,6 +435,33 @@ impl Worker for UdpRouter {
                     trace!("handle_message register: {:?} => {:?}", accepts, self_addr);
                     self.handle_register(accepts, self_addr).await?;
                 }
+                UdpRouterMessage::Listening { tx_addr, rx_addr } => {
+                    self.listeners.push((tx_addr, rx_addr));
+
+                    ctx.send(return_route, UdpRouterResponse::Listening).await?;
+                }
+                UdpRouterMessage::Connect { peer } => {
+                    let res = self.connect(peer).await;
+
+                    ctx.send(return_route, UdpRouterResponse::Connect(res))
+                        .await?;
+                }
+                UdpRouterMessage::Disconnect { peer } => {
+                    let res = self.handle_disconnect(peer).await;
+
+                    ctx.send(return_route, UdpRouterResponse::Disconnect(res))
+                        .await?;
+                }
+                UdpRouterMessage::IdleTimeout { timeout } => {
+                    self.handle_idle_timeout(ctx, timeout).await?;
+                }
+                UdpRouterMessage::Sweep => {
+                    // Keep sweeping even if a connection failed to close
+                    if let Err(err) = self.sweep_idle(Instant::now()).await {
+                        error!("UDP router failed to close an idle connection: {}", err);
+                    }
+                    self.schedule_sweep().await?;
+                }
             };
         } else {
             return Err(TransportError::InvalidAddress.into());
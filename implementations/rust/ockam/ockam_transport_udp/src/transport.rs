@@ -1,12 +1,18 @@
 use std::fmt;
-use std::{net::SocketAddr, str::FromStr};
+use std::sync::Arc;
+use std::time::Duration;
+use std::{
+    io,
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+};
 
-use ockam_core::{Address, Result};
+use ockam_core::{Address, AsyncTryClone, Result};
 use ockam_node::Context;
 
 use crate::{
     parse_socket_addr,
-    router::{UdpRouter, UdpRouterHandle},
+    router::{system_resolver, UdpRouter, UdpRouterHandle},
     UDP,
 };
 
@@ -18,17 +24,152 @@ pub struct UdpTransport {
 impl UdpTransport {
     /// Create a new UDP transport and router for the current node
     pub async fn create(ctx: &Context) -> Result<UdpTransport> {
-        let router_handle = UdpRouter::register(ctx).await?;
+        let router_handle = UdpRouter::register(ctx, system_resolver()).await?;
+        Ok(Self { router_handle })
+    }
+
+    /// Create a new UDP transport resolving peer hostnames with `resolver`
+    ///
+    /// The resolver is given a `host:port` string and returns the peer's
+    /// socket addresses, which are tried in order when connecting.
+    pub async fn create_with_resolver<F>(ctx: &Context, resolver: F) -> Result<UdpTransport>
+    where
+        F: Fn(&str) -> io::Result<Vec<SocketAddr>> + Send + Sync + 'static,
+    {
+        let router_handle = UdpRouter::register(ctx, Arc::new(resolver)).await?;
         Ok(Self { router_handle })
     }
 
     /// Start listening to incoming datagrams on an existing transport
     pub async fn listen<S: AsRef<str>>(&self, bind_addr: S) -> Result<()> {
         let bind_addr = parse_socket_addr(bind_addr)?;
-        self.router_handle.bind(bind_addr).await
+        self.router_handle.bind(bind_addr, false).await
+    }
+
+    /// Start listening to incoming datagrams, accepting those of a peer
+    /// only once it proved it receives the datagrams sent to its source
+    /// address
+    ///
+    /// The listener answers the first datagram of a peer with a cookie,
+    /// which the UDP transport of the peer echoes back. Until then the
+    /// peer's datagrams are dropped and nothing is registered for it, so
+    /// datagrams with a spoofed source address can't make the listener
+    /// create state nor send anything bigger than a cookie. A peer's
+    /// first messages are dropped, which makes this check opt-in.
+    pub async fn listen_with_return_routability<S: AsRef<str>>(&self, bind_addr: S) -> Result<()> {
+        let bind_addr = parse_socket_addr(bind_addr)?;
+        self.router_handle.bind(bind_addr, true).await
+    }
+
+    /// Start listening to the datagrams sent to the multicast `group`
+    ///
+    /// `bind_addr` is usually the unspecified address with the port the
+    /// group is sent to, e.g. `0.0.0.0:5000`. Messages are routed like
+    /// those received by [`listen`](Self::listen), use a
+    /// [`UdpFanOut`](crate::UdpFanOut) to deliver them to several workers.
+    pub async fn listen_multicast<S: AsRef<str>>(&self, bind_addr: S, group: IpAddr) -> Result<()> {
+        let bind_addr = parse_socket_addr(bind_addr)?;
+        self.router_handle.bind_multicast(bind_addr, group).await
+    }
+
+    /// Manually establish an outgoing UDP connection to the given peer
+    ///
+    /// When a hostname resolves to several addresses, they are tried in
+    /// order and the first reachable one is used. This step is optional because the underlying router is capable
+    /// of lazily connecting upon arrival of the initial message.  The
+    /// returned [`UdpConnection`] can be used to manage the peer
+    /// explicitly.
+    pub async fn connect<S: AsRef<str>>(&self, peer: S) -> Result<UdpConnection> {
+        let (sender_addr, peer) = self.router_handle.connect(peer).await?;
+
+        Ok(UdpConnection {
+            router_handle: self.router_handle.async_try_clone().await?,
+            peer,
+            sender_addr,
+        })
+    }
+
+    /// Disconnect from peer
+    pub async fn disconnect<S: AsRef<str>>(&self, peer: S) -> Result<()> {
+        self.router_handle.disconnect(peer.as_ref()).await
+    }
+
+    /// Forget the peers which neither sent nor were sent anything for
+    /// longer than `timeout`
+    ///
+    /// The outgoing connections to these peers are closed, releasing their
+    /// socket, as if [`disconnect`](Self::disconnect) was called. Peers
+    /// which send to a listener are forgotten too, and registered again
+    /// with their next datagram. Idle peers are looked for every half
+    /// `timeout`, so a peer can stay up to one and a half `timeout` idle.
+    pub async fn set_idle_timeout(&self, timeout: Duration) -> Result<()> {
+        self.router_handle.set_idle_timeout(timeout).await
     }
 
-    // TODO: connect method for manually connecting.
+    /// Stop the transport, closing the sockets of its listeners and
+    /// outgoing connections
+    ///
+    /// Messages can't be routed through the transport afterwards.
+    pub async fn stop(&self) -> Result<()> {
+        self.router_handle.stop().await
+    }
+
+    /// Drop the messages whose onward or return route holds more than
+    /// `max_length` addresses, [`DEFAULT_MAX_ROUTE_LENGTH`] by default
+    ///
+    /// Such messages are rejected by the router with
+    /// [`TransportError::RouteTooLong`](ockam_transport_core::TransportError::RouteTooLong).
+    ///
+    /// [`DEFAULT_MAX_ROUTE_LENGTH`]: ockam_transport_core::DEFAULT_MAX_ROUTE_LENGTH
+    pub fn set_max_route_length(&self, max_length: usize) {
+        self.router_handle.route_limit().set(max_length)
+    }
+
+    /// Read up to `batch_size` datagrams per syscall,
+    /// [`DEFAULT_READ_BATCH_SIZE`](crate::DEFAULT_READ_BATCH_SIZE) by default
+    ///
+    /// Batches are read with `recvmmsg` on Linux, other platforms read
+    /// datagrams one by one whatever the size. Every listener and
+    /// connection keeps a 2 KiB buffer per datagram of a batch. Datagrams
+    /// too large for it are dropped, and the datagrams of their socket are
+    /// then read one at a time into a 64 KiB buffer. The size applies to
+    /// the batches read from now on.
+    pub fn set_read_batch_size(&self, batch_size: usize) {
+        self.router_handle.read_batch_size().set(batch_size)
+    }
+}
+
+/// A handle to an outgoing UDP connection
+///
+/// Created by [`UdpTransport::connect`](crate::UdpTransport::connect).
+/// Dropping this handle does not close the connection, use
+/// [`disconnect`](Self::disconnect) instead.
+pub struct UdpConnection {
+    router_handle: UdpRouterHandle,
+    peer: SocketAddr,
+    sender_addr: Address,
+}
+
+impl UdpConnection {
+    /// Return the address of the `UdpSendWorker` assigned to this peer
+    pub fn sender_address(&self) -> &Address {
+        &self.sender_addr
+    }
+
+    /// Return the socket address the peer was reached on
+    pub fn peer(&self) -> SocketAddr {
+        self.peer
+    }
+
+    /// Return the `type = 2` address of the peer, to be used as a route hop
+    pub fn peer_address(&self) -> Address {
+        UdpAddress::from(self.peer).into()
+    }
+
+    /// Close the connection and stop its workers
+    pub async fn disconnect(self) -> Result<()> {
+        self.router_handle.disconnect(self.peer.to_string()).await
+    }
 }
 
 #[derive(Clone)]
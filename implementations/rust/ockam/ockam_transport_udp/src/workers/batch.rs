@@ -0,0 +1,294 @@
+use std::collections::VecDeque;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use bytes::BytesMut;
+use tokio::net::UdpSocket;
+
+/// Number of datagrams a listen processor reads per syscall by default
+pub const DEFAULT_READ_BATCH_SIZE: usize = 8;
+
+/// Size of the buffer of single reads, the largest UDP payload fits in it
+const MAX_DATAGRAM_SIZE: usize = 65_535;
+
+/// Size of the buffers of batched reads, enough for the datagrams which
+/// aren't fragmented on common paths
+#[cfg(target_os = "linux")]
+const BATCH_DATAGRAM_SIZE: usize = 2048;
+
+/// Number of datagrams the listen processors of a transport read per
+/// syscall, on platforms with `recvmmsg`
+///
+/// Every datagram of a batch gets a `BATCH_DATAGRAM_SIZE` buffer, so a
+/// processor holds a few KiB per datagram of the batch. The size is shared
+/// by the clones of a transport and its router.
+#[derive(Clone, Debug)]
+pub(crate) struct ReadBatchSize {
+    size: Arc<AtomicUsize>,
+}
+
+impl Default for ReadBatchSize {
+    fn default() -> Self {
+        Self {
+            size: Arc::new(AtomicUsize::new(DEFAULT_READ_BATCH_SIZE)),
+        }
+    }
+}
+
+impl ReadBatchSize {
+    /// Change the size, for the batches read from now on
+    pub fn set(&self, size: usize) {
+        self.size.store(size.max(1), Ordering::Relaxed);
+    }
+
+    /// Number of datagrams read at once
+    pub fn get(&self) -> usize {
+        self.size.load(Ordering::Relaxed)
+    }
+}
+
+/// Reads the datagrams of a socket in batches, handing them out one by one
+///
+/// A datagram larger than the buffers of a batch is truncated by the
+/// system, so it is dropped and the datagrams of the socket are read one at
+/// a time from then on, its peers sending large datagrams.
+pub(crate) struct DatagramReader {
+    /// Buffer of single reads, allocated by the first one
+    buffer: Vec<u8>,
+    /// Buffers of batched reads, allocated by the first batch
+    #[cfg(target_os = "linux")]
+    batch: Vec<Vec<u8>>,
+    /// Datagrams read but not handed out yet, with their source address
+    pending: VecDeque<(BytesMut, SocketAddr)>,
+    /// Whether datagrams are read one at a time, because `recvmmsg` failed
+    /// as unsupported, e.g. by a sandbox, or a datagram didn't fit a batch
+    #[cfg(target_os = "linux")]
+    single_reads: bool,
+}
+
+impl DatagramReader {
+    pub fn new() -> Self {
+        Self {
+            buffer: Vec::new(),
+            #[cfg(target_os = "linux")]
+            batch: Vec::new(),
+            pending: VecDeque::new(),
+            #[cfg(target_os = "linux")]
+            single_reads: false,
+        }
+    }
+
+    /// Return the next datagram received on `socket`, reading up to
+    /// `batch_size` of them at once when the platform supports it
+    #[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
+    pub async fn next(
+        &mut self,
+        socket: &UdpSocket,
+        batch_size: usize,
+    ) -> io::Result<(BytesMut, SocketAddr)> {
+        loop {
+            if let Some(datagram) = self.pending.pop_front() {
+                return Ok(datagram);
+            }
+
+            #[cfg(target_os = "linux")]
+            if batch_size > 1 && !self.single_reads {
+                match self.recv_batch(socket, batch_size).await {
+                    Err(e) if e.raw_os_error() == Some(libc::ENOSYS) => {
+                        tracing::debug!("recvmmsg is unsupported, reading datagrams one at a time");
+                        self.single_reads = true;
+                    }
+                    res => res?,
+                }
+                continue;
+            }
+
+            self.recv_one(socket).await?;
+        }
+    }
+
+    async fn recv_one(&mut self, socket: &UdpSocket) -> io::Result<()> {
+        self.buffer.resize(MAX_DATAGRAM_SIZE, 0);
+        let (len, addr) = socket.recv_from(&mut self.buffer).await?;
+        self.pending
+            .push_back((BytesMut::from(&self.buffer[..len]), addr));
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn recv_batch(&mut self, socket: &UdpSocket, batch_size: usize) -> io::Result<()> {
+        use std::os::unix::io::AsRawFd;
+        use tokio::io::Interest;
+
+        self.batch
+            .resize_with(batch_size, || vec![0; BATCH_DATAGRAM_SIZE]);
+        let fd = socket.as_raw_fd();
+        loop {
+            socket.readable().await?;
+            let batch = &mut self.batch;
+            match socket.try_io(Interest::READABLE, || mmsg::recv_mmsg(fd, batch)) {
+                Ok(received) => {
+                    for (buffer, datagram) in self.batch.iter().zip(received) {
+                        if datagram.truncated {
+                            tracing::debug!(
+                                "Dropping a datagram from {} too large for a batch",
+                                datagram.addr
+                            );
+                            self.single_reads = true;
+                            continue;
+                        }
+                        self.pending
+                            .push_back((BytesMut::from(&buffer[..datagram.len]), datagram.addr));
+                    }
+                    return Ok(());
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod mmsg {
+    use std::io;
+    use std::mem;
+    use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+    use std::os::unix::io::RawFd;
+    use std::ptr;
+
+    /// A datagram read by [`recv_mmsg`]
+    pub(super) struct Received {
+        pub(super) len: usize,
+        pub(super) addr: SocketAddr,
+        /// The datagram didn't fit its buffer, which holds its beginning
+        pub(super) truncated: bool,
+    }
+
+    /// Read up to one datagram per buffer from the non-blocking socket
+    /// `fd`, returning the length and source address of each one read
+    pub(super) fn recv_mmsg(fd: RawFd, buffers: &mut [Vec<u8>]) -> io::Result<Vec<Received>> {
+        // SAFETY: all-zero is a valid value of these plain C structs
+        let mut addrs: Vec<libc::sockaddr_storage> = vec![unsafe { mem::zeroed() }; buffers.len()];
+        let mut iovecs: Vec<libc::iovec> = buffers
+            .iter_mut()
+            .map(|buffer| libc::iovec {
+                iov_base: buffer.as_mut_ptr() as *mut libc::c_void,
+                iov_len: buffer.len(),
+            })
+            .collect();
+        let mut headers: Vec<libc::mmsghdr> = iovecs
+            .iter_mut()
+            .zip(addrs.iter_mut())
+            .map(|(iovec, addr)| {
+                // SAFETY: as above
+                let mut header: libc::mmsghdr = unsafe { mem::zeroed() };
+                header.msg_hdr.msg_name = addr as *mut libc::sockaddr_storage as *mut libc::c_void;
+                header.msg_hdr.msg_namelen =
+                    mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+                header.msg_hdr.msg_iov = iovec;
+                header.msg_hdr.msg_iovlen = 1;
+                header
+            })
+            .collect();
+
+        // SAFETY: every header points to a buffer and an address which
+        // outlive the call, with their actual lengths
+        let count = unsafe {
+            libc::recvmmsg(
+                fd,
+                headers.as_mut_ptr(),
+                headers.len() as libc::c_uint,
+                libc::MSG_DONTWAIT as _,
+                ptr::null_mut(),
+            )
+        };
+        if count < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        headers
+            .iter()
+            .zip(addrs.iter())
+            .take(count as usize)
+            .map(|(header, addr)| {
+                Ok(Received {
+                    len: header.msg_len as usize,
+                    addr: socket_addr(addr)?,
+                    truncated: header.msg_hdr.msg_flags & libc::MSG_TRUNC != 0,
+                })
+            })
+            .collect()
+    }
+
+    fn socket_addr(addr: &libc::sockaddr_storage) -> io::Result<SocketAddr> {
+        match addr.ss_family as libc::c_int {
+            libc::AF_INET => {
+                // SAFETY: the family tells the actual type of the address
+                let addr = unsafe { &*(addr as *const _ as *const libc::sockaddr_in) };
+                Ok(SocketAddr::V4(SocketAddrV4::new(
+                    Ipv4Addr::from(u32::from_be(addr.sin_addr.s_addr)),
+                    u16::from_be(addr.sin_port),
+                )))
+            }
+            libc::AF_INET6 => {
+                // SAFETY: as above
+                let addr = unsafe { &*(addr as *const _ as *const libc::sockaddr_in6) };
+                Ok(SocketAddr::V6(SocketAddrV6::new(
+                    Ipv6Addr::from(addr.sin6_addr.s6_addr),
+                    u16::from_be(addr.sin6_port),
+                    addr.sin6_flowinfo,
+                    addr.sin6_scope_id,
+                )))
+            }
+            family => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported address family {}", family),
+            )),
+        }
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn datagrams_are_read_in_batches() {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        let peer = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        for i in 0..4u8 {
+            peer.send_to(&[i], addr).await.unwrap();
+        }
+
+        let mut reader = DatagramReader::new();
+        let (datagram, _) = reader.next(&socket, 8).await.unwrap();
+        if reader.single_reads {
+            // recvmmsg is unsupported here, there is no batch to check
+            return;
+        }
+        assert_eq!(&datagram[..], &[0]);
+        // The other datagrams were read by the same syscall
+        assert_eq!(reader.pending.len(), 3);
+        for i in 1..4u8 {
+            assert_eq!(&reader.next(&socket, 8).await.unwrap().0[..], &[i]);
+        }
+
+        // A datagram which doesn't fit a batch is dropped, the next ones
+        // are read whole
+        peer.send_to(&[1; BATCH_DATAGRAM_SIZE + 1], addr)
+            .await
+            .unwrap();
+        reader.recv_batch(&socket, 8).await.unwrap();
+        assert!(reader.single_reads);
+        assert!(reader.pending.is_empty());
+        peer.send_to(&[2; BATCH_DATAGRAM_SIZE + 1], addr)
+            .await
+            .unwrap();
+        let (datagram, _) = reader.next(&socket, 8).await.unwrap();
+        assert_eq!(&datagram[..], &[2; BATCH_DATAGRAM_SIZE + 1][..]);
+    }
+}
@@ -4,14 +4,24 @@ use ockam_core::{Decodable, Encodable};
 use ockam_transport_core::TransportError;
 use tokio_util::codec::{Decoder, Encoder};
 
+/// Length of the prefix holding the length of a frame's message
+const LENGTH_PREFIX: usize = 2;
+
+/// Frames every message behind its length, as a big endian `u16`
+///
+/// Decoding an incomplete frame returns `None` and consumes nothing, so
+/// that the rest of the frame can be appended to the buffer. Only a
+/// complete frame which doesn't hold a message is an error.
 pub(crate) struct TransportMessageCodec;
 
 impl Encoder<TransportMessage> for TransportMessageCodec {
     type Error = TransportError;
     fn encode(&mut self, item: TransportMessage, dst: &mut BytesMut) -> Result<(), Self::Error> {
         let msg_buf = item.encode().map_err(|_| TransportError::SendBadMessage)?;
-        let len = msg_buf.len();
-        dst.put_u16(len as u16);
+        // A longer message would be framed with a truncated length
+        let len = u16::try_from(msg_buf.len()).map_err(|_| TransportError::Capacity)?;
+        dst.reserve(LENGTH_PREFIX + msg_buf.len());
+        dst.put_u16(len);
         dst.put(&msg_buf[..]);
         Ok(())
     }
@@ -21,14 +31,130 @@ impl Decoder for TransportMessageCodec {
     type Item = TransportMessage;
     type Error = TransportError;
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        if src.is_empty() {
+        if src.len() < LENGTH_PREFIX {
+            return Ok(None);
+        }
+
+        let len = u16::from_be_bytes([src[0], src[1]]) as usize;
+        if src.len() < LENGTH_PREFIX + len {
+            src.reserve(LENGTH_PREFIX + len - src.len());
             return Ok(None);
         }
 
-        let len = src.get_u16() as usize;
+        src.advance(LENGTH_PREFIX);
         let msg = TransportMessage::decode(&src.split_to(len)[..])
             .map_err(|_| TransportError::RecvBadMessage)?;
 
         Ok(Some(msg))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::TransportMessageCodec;
+    use bytes::BytesMut;
+    use ockam_core::{Route, TransportMessage, LOCAL};
+    use ockam_transport_core::TransportError;
+    use quickcheck::{quickcheck, Arbitrary, Gen};
+    use tokio_util::codec::{Decoder, Encoder};
+
+    #[derive(Debug, Clone)]
+    struct Msg(TransportMessage);
+
+    impl Arbitrary for Msg {
+        fn arbitrary(g: &mut Gen) -> Self {
+            let route = |g: &mut Gen| -> Route {
+                Vec::<String>::arbitrary(g)
+                    .into_iter()
+                    .fold(Route::new(), |route, addr| route.append_t(LOCAL, addr))
+                    .into()
+            };
+            Self(TransportMessage::v1(route(g), route(g), Vec::arbitrary(g)))
+        }
+    }
+
+    fn frame(msg: &TransportMessage) -> BytesMut {
+        let mut buf = BytesMut::new();
+        TransportMessageCodec.encode(msg.clone(), &mut buf).unwrap();
+        buf
+    }
+
+    quickcheck! {
+        fn prop_chunked_input_decodes_once_complete(msg: Msg, chunks: Vec<u8>) -> bool {
+            let frame = frame(&msg.0);
+            let mut src = BytesMut::new();
+            let mut rest = &frame[..];
+            let mut chunks = chunks.into_iter().map(|n| n as usize + 1);
+            while !rest.is_empty() {
+                let size = chunks.next().unwrap_or(rest.len()).min(rest.len());
+                let (chunk, tail) = rest.split_at(size);
+                src.extend_from_slice(chunk);
+                rest = tail;
+                match TransportMessageCodec.decode(&mut src) {
+                    Ok(None) if !rest.is_empty() => continue,
+                    Ok(Some(decoded)) if rest.is_empty() => {
+                        return decoded == msg.0 && src.is_empty()
+                    }
+                    _ => return false,
+                }
+            }
+            false
+        }
+
+        fn prop_incomplete_frame_is_not_consumed(msg: Msg, cut: usize) -> bool {
+            let frame = frame(&msg.0);
+            let cut = cut % frame.len();
+            let mut src = BytesMut::from(&frame[..cut]);
+            matches!(TransportMessageCodec.decode(&mut src), Ok(None)) && src[..] == frame[..cut]
+        }
+
+        fn prop_consecutive_frames_decode_in_order(msgs: Vec<Msg>) -> bool {
+            let mut src = BytesMut::new();
+            for msg in &msgs {
+                src.extend_from_slice(&frame(&msg.0));
+            }
+            msgs.iter().all(|msg| {
+                let decoded = TransportMessageCodec.decode(&mut src);
+                matches!(decoded, Ok(Some(decoded)) if decoded == msg.0)
+            }) && src.is_empty()
+        }
+
+        fn prop_arbitrary_input_does_not_panic(input: Vec<u8>) -> bool {
+            let mut src = BytesMut::from(&input[..]);
+            while let Ok(Some(_)) = TransportMessageCodec.decode(&mut src) {}
+            true
+        }
+    }
+
+    #[test]
+    fn oversized_message_is_not_framed() {
+        let msg = TransportMessage::v1(Route::new(), Route::new(), vec![0; u16::MAX as usize]);
+        let mut dst = BytesMut::new();
+        let res = TransportMessageCodec.encode(msg, &mut dst);
+        assert!(matches!(res, Err(TransportError::Capacity)));
+        assert!(dst.is_empty());
+    }
+
+    #[test]
+    fn empty_payload_is_a_complete_frame() {
+        let msg = TransportMessage::v1(Route::new().append_t(LOCAL, "app"), Route::new(), vec![]);
+        let mut src = frame(&msg);
+        assert!(src.len() > 2);
+        let decoded = TransportMessageCodec.decode(&mut src).unwrap();
+        assert_eq!(decoded, Some(msg));
+        assert!(src.is_empty());
+    }
+
+    #[test]
+    fn malformed_frame_is_rejected() {
+        // A complete frame holding only a message version
+        let mut src = BytesMut::from(&[0, 1, 1][..]);
+        let res = TransportMessageCodec.decode(&mut src);
+        assert!(matches!(res, Err(TransportError::RecvBadMessage)));
+
+        // A zero-length frame doesn't hold a message either
+        let mut src = BytesMut::from(&[0, 0][..]);
+        let res = TransportMessageCodec.decode(&mut src);
+        assert!(matches!(res, Err(TransportError::RecvBadMessage)));
+    }
+}
@@ -1,13 +1,21 @@
-use futures_util::stream::SplitStream;
-use futures_util::StreamExt;
-use ockam_core::{async_trait, Address, LocalMessage, Processor, Result};
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use ockam_core::{
+    async_trait, route, Address, Decodable, LocalMessage, Processor, Result, TransportMessage,
+};
 use ockam_node::Context;
-use tokio_util::udp::UdpFramed;
-use tracing::{debug, info};
+use ockam_transport_core::{LogThrottle, TransportError};
+use tokio::net::UdpSocket;
+use tokio_util::codec::Decoder;
+use tracing::{debug, info, warn};
 
 use crate::{router::UdpRouterHandle, transport::UdpAddress};
 
-use super::TransportMessageCodec;
+use super::{
+    DatagramReader, ReturnRoutability, TransportMessageCodec, ROUTABILITY_CHALLENGE_ADDRESS,
+    ROUTABILITY_RESPONSE_ADDRESS,
+};
 
 /// A UDP listen processor
 ///
@@ -15,30 +23,140 @@ use super::TransportMessageCodec;
 /// after a call is made to
 /// [`UdpTransport::listen`](crate::UdpTransport::listen).
 pub(crate) struct UdpListenProcessor {
-    /// The read half of the udnerlying UDP socket.
-    stream: SplitStream<UdpFramed<TransportMessageCodec>>,
+    /// The underlying UDP socket, shared with the sender worker.
+    socket: Arc<UdpSocket>,
+    /// Reads the datagrams of the socket in batches.
+    reader: DatagramReader,
+    /// The peer the socket is connected to, if any.
+    peer: Option<SocketAddr>,
     /// The address of the sender worker which owns
     /// the write half of the underlying UDP socket.
     tx_addr: Address,
     /// Handle of a registered UDP router.
     router_handle: UdpRouterHandle,
+    /// Check of the peers' source addresses, if enabled for a listener.
+    routability: Option<ReturnRoutability>,
+    /// Collapses the warnings about the datagrams of misbehaving peers,
+    /// which anyone can send.
+    log_throttle: LogThrottle,
 }
 
 impl UdpListenProcessor {
     pub(crate) async fn start(
         ctx: &Context,
-        stream: SplitStream<UdpFramed<TransportMessageCodec>>,
+        socket: Arc<UdpSocket>,
+        peer: Option<SocketAddr>,
         tx_addr: Address,
         router_handle: UdpRouterHandle,
-    ) -> Result<()> {
+        routability: Option<ReturnRoutability>,
+    ) -> Result<Address> {
         let processor = Self {
-            stream,
+            socket,
+            reader: DatagramReader::new(),
+            peer,
             tx_addr,
             router_handle,
+            routability,
+            log_throttle: LogThrottle::default(),
+        };
+        let addr = Address::random_local();
+        ctx.start_processor(addr.clone(), processor).await?;
+        Ok(addr)
+    }
+
+    /// Read the next datagram and decode the message it holds
+    ///
+    /// A datagram without a complete frame, or whose frame doesn't hold a
+    /// message, is dropped rather than stop the listener. A message with an
+    /// empty payload is still a complete, non-empty frame and is returned.
+    async fn recv(
+        &mut self,
+        batch_size: usize,
+    ) -> core::result::Result<Option<(TransportMessage, SocketAddr)>, TransportError> {
+        let (mut datagram, addr) = self.reader.next(&self.socket, batch_size).await?;
+        match TransportMessageCodec.decode(&mut datagram) {
+            Ok(msg) => Ok(msg.map(|msg| (msg, addr))),
+            Err(e) => {
+                // The source address is left out of the warning so that
+                // datagrams from spoofed addresses are collapsed too
+                debug!("Dropping a malformed datagram from {}: {}", addr, e);
+                self.log_throttle
+                    .warn(format_args!("Dropping a malformed datagram: {}", e));
+                Ok(None)
+            }
+        }
+    }
+
+    /// Send `cookie` to the peer at `addr` through the sender worker
+    async fn send_cookie(
+        &self,
+        ctx: &Context,
+        addr: SocketAddr,
+        onward: &str,
+        cookie: Vec<u8>,
+    ) -> Result<()> {
+        ctx.send(
+            route![self.tx_addr.clone(), UdpAddress::from(addr), onward],
+            cookie,
+        )
+        .await
+    }
+
+    /// Handle the return-routability messages, returns whether `msg` was one
+    async fn handle_routability(
+        &mut self,
+        ctx: &Context,
+        msg: &TransportMessage,
+        addr: SocketAddr,
+    ) -> Result<bool> {
+        let onward = match msg.onward_route.next() {
+            Ok(onward) => onward,
+            Err(_) => return Ok(false),
+        };
+
+        if onward == &Address::from(ROUTABILITY_CHALLENGE_ADDRESS) {
+            // Only the listener a connection was made to can challenge it,
+            // otherwise a challenge from a spoofed source would have the
+            // cookie reflected to that source
+            if self.peer != Some(addr) {
+                debug!("Ignoring a challenge from UDP peer {}", addr);
+                return Ok(true);
+            }
+            // Prove to the listener that we receive what is sent to us
+            if let Ok(cookie) = Vec::<u8>::decode(&msg.payload) {
+                self.send_cookie(ctx, addr, ROUTABILITY_RESPONSE_ADDRESS, cookie)
+                    .await?;
+            }
+            return Ok(true);
+        }
+
+        let routability = match &mut self.routability {
+            Some(routability) => routability,
+            None => return Ok(false),
         };
-        ctx.start_processor(Address::random_local(), processor)
+
+        if onward == &Address::from(ROUTABILITY_RESPONSE_ADDRESS) {
+            let cookie = Vec::<u8>::decode(&msg.payload).unwrap_or_default();
+            if routability.verify(addr, &cookie) {
+                debug!("UDP peer {} passed the return-routability check", addr);
+            } else {
+                debug!("UDP peer {} echoed an invalid cookie", addr);
+                self.log_throttle
+                    .warn("A UDP peer echoed an invalid cookie");
+            }
+            return Ok(true);
+        }
+
+        if routability.is_verified(&addr) {
+            return Ok(false);
+        }
+
+        // Nothing is registered nor forwarded until the peer echoes its cookie
+        debug!("Challenging unverified UDP peer {}", addr);
+        let cookie = routability.cookie(&addr);
+        self.send_cookie(ctx, addr, ROUTABILITY_CHALLENGE_ADDRESS, cookie)
             .await?;
-        Ok(())
+        Ok(true)
     }
 }
 
@@ -52,20 +170,31 @@ impl Processor for UdpListenProcessor {
 
     async fn process(&mut self, ctx: &mut Self::Context) -> Result<bool> {
         debug!("Waiting for incoming UDP datagram...");
-        let (mut msg, addr) = match self.stream.next().await {
-            Some(res) => match res {
-                Ok((msg, addr)) => (msg, addr),
-                Err(_e) => {
-                    info!("Failed to read message from UDP socket.");
-                    return Ok(false);
-                }
-            },
-            None => {
-                info!("No message read, keep socket alive.");
+        let batch_size = self.router_handle.read_batch_size().get();
+        let (mut msg, addr) = match self.recv(batch_size).await {
+            Ok(Some((msg, addr))) => (msg, addr),
+            Ok(None) => {
+                debug!("No message read, keep socket alive.");
                 return Ok(true);
             }
+            Err(e) => {
+                match self.peer {
+                    // A connected socket reports the peer's ICMP errors,
+                    // close the connection rather than send into the void
+                    Some(peer) if e == TransportError::PeerNotFound => {
+                        warn!("UDP peer {} refused the connection", peer);
+                        let _ = self.router_handle.disconnect(peer.to_string()).await;
+                    }
+                    _ => info!("Failed to read message from UDP socket."),
+                }
+                return Ok(false);
+            }
         };
 
+        if self.handle_routability(ctx, &msg, addr).await? {
+            return Ok(true);
+        }
+
         // Register peer addr with sender half
         // TODO: should `register` be called for every TransportMessage received?
         self.router_handle
@@ -74,8 +203,8 @@ impl Processor for UdpListenProcessor {
 
         msg.return_route.modify().prepend(UdpAddress::from(addr));
 
-        debug!("Message onward route: {}", msg.onward_route);
-        debug!("Message return route: {}", msg.return_route);
+        debug!("Message onward route: {}", msg.onward_route.pretty());
+        debug!("Message return route: {}", msg.return_route.pretty());
 
         ctx.forward(LocalMessage::new(msg, vec![])).await?;
 
@@ -1,7 +1,12 @@
+pub use batch::DEFAULT_READ_BATCH_SIZE;
+pub(crate) use batch::*;
 pub(crate) use codec::*;
 pub(crate) use listener::*;
+pub(crate) use routability::*;
 pub(crate) use sender::*;
 
+mod batch;
 mod codec;
 mod listener;
+mod routability;
 mod sender;
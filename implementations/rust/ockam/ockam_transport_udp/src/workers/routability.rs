@@ -0,0 +1,50 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashSet;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::net::SocketAddr;
+
+/// Onward address of the cookie a listener sends to an unverified peer
+pub(crate) const ROUTABILITY_CHALLENGE_ADDRESS: &str = "_internal.udp.routability.challenge";
+
+/// Onward address of the cookie a peer echoes back to the listener
+pub(crate) const ROUTABILITY_RESPONSE_ADDRESS: &str = "_internal.udp.routability.response";
+
+/// Return-routability check of the peers of a listener
+///
+/// The datagrams of a peer are only accepted once it echoed the cookie
+/// the listener sent to its source address, which a peer spoofing that
+/// address never receives. Cookies are a keyed hash of the address, so
+/// nothing is kept for the peers which don't answer.
+pub(crate) struct ReturnRoutability {
+    key: RandomState,
+    verified: HashSet<SocketAddr>,
+}
+
+impl ReturnRoutability {
+    pub(crate) fn new() -> Self {
+        Self {
+            key: RandomState::new(),
+            verified: HashSet::new(),
+        }
+    }
+
+    /// Cookie of the peer at `addr`
+    pub(crate) fn cookie(&self, addr: &SocketAddr) -> Vec<u8> {
+        let mut hasher = self.key.build_hasher();
+        addr.hash(&mut hasher);
+        hasher.finish().to_be_bytes().to_vec()
+    }
+
+    pub(crate) fn is_verified(&self, addr: &SocketAddr) -> bool {
+        self.verified.contains(addr)
+    }
+
+    /// Accept the peer at `addr` if it echoed its cookie
+    pub(crate) fn verify(&mut self, addr: SocketAddr, cookie: &[u8]) -> bool {
+        if cookie != self.cookie(&addr).as_slice() {
+            return false;
+        }
+        self.verified.insert(addr);
+        true
+    }
+}
@@ -1,57 +1,87 @@
-use std::{net::SocketAddr, ops::Deref};
+use std::{net::SocketAddr, ops::Deref, sync::Arc};
 
+use bytes::BytesMut;
 use futures_util::{stream::SplitSink, SinkExt};
 use ockam_core::{
-    async_trait, Any, Decodable, LocalMessage, Result, Routed, TransportMessage, Worker,
+    async_trait, Any, Decodable, LocalMessage, MessageId, Result, Routed, TransportMessage, Worker,
 };
 use ockam_node::Context;
 use ockam_transport_core::TransportError;
+use tokio::net::UdpSocket;
+use tokio_util::codec::Encoder;
 use tokio_util::udp::UdpFramed;
-use tracing::warn;
+use tracing::field::{self, display};
+use tracing::{debug_span, warn, Instrument, Span};
 
 use crate::router::UdpRouterHandle;
 
 use super::TransportMessageCodec;
 
+/// The write half of a socket shared by every peer, sending with `send_to`
+pub(crate) type UdpSink =
+    SplitSink<UdpFramed<TransportMessageCodec, Arc<UdpSocket>>, (TransportMessage, SocketAddr)>;
+
+enum Socket {
+    Unconnected(UdpSink),
+    /// Connected to a single peer, sending with `send`
+    Connected(Arc<UdpSocket>, SocketAddr),
+}
+
 /// A UDP message sending worker
 ///
 /// This worker is created when `UdpTransport::listen` is called.
 /// When auto connection is enabled, this work can be created
 /// automatically by the router.
 pub(crate) struct UdpSendWorker {
-    sink: SplitSink<UdpFramed<TransportMessageCodec>, (TransportMessage, SocketAddr)>,
+    socket: Socket,
 }
 
 impl UdpSendWorker {
     /// Create a new `UdpSendWorker`
-    pub(crate) fn new(
-        sink: SplitSink<UdpFramed<TransportMessageCodec>, (TransportMessage, SocketAddr)>,
-    ) -> Self {
-        Self { sink }
+    pub(crate) fn new(sink: UdpSink) -> Self {
+        Self {
+            socket: Socket::Unconnected(sink),
+        }
     }
-}
 
-#[async_trait]
-impl Worker for UdpSendWorker {
-    type Message = Any;
-    type Context = Context;
+    /// Create a new `UdpSendWorker` for a socket connected to `peer`
+    pub(crate) fn connected(socket: Arc<UdpSocket>, peer: SocketAddr) -> Self {
+        Self {
+            socket: Socket::Connected(socket, peer),
+        }
+    }
 
-    async fn handle_message(
-        &mut self,
-        ctx: &mut Context,
-        msg: Routed<Self::Message>,
-    ) -> Result<()> {
+    /// Send a message routed to this worker to its peer
+    async fn send(&mut self, ctx: &mut Context, msg: Routed<Any>) -> Result<()> {
         let mut msg = LocalMessage::decode(msg.payload())?.into_transport_message();
+        let span = Span::current();
+        if !span.is_disabled() {
+            span.record("message_id", &display(MessageId::of(&msg.payload)));
+        }
 
         // Remove sender address
         msg.onward_route.step()?;
 
-        let (peer_addr, _) = match String::from_utf8(msg.onward_route.step()?.deref().clone()) {
-            Ok(s) => UdpRouterHandle::resolve_peer(s)?,
-            Err(_e) => return Err(TransportError::UnknownRoute.into()),
+        let peer = msg.onward_route.step()?;
+
+        let (sent, peer_addr) = match &mut self.socket {
+            Socket::Unconnected(sink) => {
+                let peer_addr = match String::from_utf8(peer.deref().clone()) {
+                    Ok(s) => UdpRouterHandle::resolve_peer(s)?.0[0],
+                    Err(_e) => return Err(TransportError::UnknownRoute.into()),
+                };
+                span.record("peer", &display(peer_addr));
+                (sink.send((msg, peer_addr)).await.is_ok(), peer_addr)
+            }
+            Socket::Connected(socket, peer_addr) => {
+                span.record("peer", &display(*peer_addr));
+                let mut buf = BytesMut::new();
+                TransportMessageCodec.encode(msg, &mut buf)?;
+                (socket.send(&buf).await.is_ok(), *peer_addr)
+            }
         };
 
-        if self.sink.send((msg, peer_addr)).await.is_err() {
+        if !sent {
             warn!("Failed to send message to peer {}", peer_addr);
             ctx.stop_worker(ctx.address()).await?;
         }
@@ -59,3 +89,22 @@ impl Worker for UdpSendWorker {
         Ok(())
     }
 }
+
+#[async_trait]
+impl Worker for UdpSendWorker {
+    type Message = Any;
+    type Context = Context;
+
+    async fn handle_message(
+        &mut self,
+        ctx: &mut Context,
+        msg: Routed<Self::Message>,
+    ) -> Result<()> {
+        let span = debug_span!(
+            "udp_send_worker",
+            peer = field::Empty,
+            message_id = field::Empty
+        );
+        self.send(ctx, msg).instrument(span).await
+    }
+}
@@ -1,8 +1,13 @@
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+
 use ockam_core::compat::rand::{self, Rng};
-use ockam_core::{route, Address, Result, Routed, Worker};
+use ockam_core::{
+    route, Address, Any, Decodable, Encodable, Result, Routed, TransportMessage, Worker,
+};
 use ockam_node::Context;
 
-use ockam_transport_udp::{UdpTransport, UDP};
+use ockam_transport_udp::{UdpFanOut, UdpTransport, UDP};
 use tracing::debug;
 
 #[ockam_macros::test]
@@ -39,6 +44,481 @@ async fn send_receive(ctx: &mut Context) -> Result<()> {
     Ok(())
 }
 
+#[ockam_macros::test]
+async fn connect_disconnect(ctx: &mut Context) -> Result<()> {
+    let rand_port = rand::thread_rng().gen_range(10000..65535);
+    let bind_address = format!("127.0.0.1:{}", rand_port);
+    let bind_address = bind_address.as_str();
+
+    let transport = UdpTransport::create(ctx).await?;
+    transport.listen(bind_address).await?;
+    ctx.start_worker("echoer", Echoer).await?;
+
+    let connection = transport.connect(bind_address).await?;
+    let sender_address = connection.sender_address().clone();
+    assert!(ctx.list_workers().await?.contains(&sender_address));
+
+    // Connecting again reuses the same sender
+    let again = transport.connect(bind_address).await?;
+    assert_eq!(again.sender_address(), &sender_address);
+
+    let msg = "Hello Ockam!".to_string();
+    let r = route![connection.peer_address(), "echoer"];
+    ctx.send(r, msg.clone()).await?;
+
+    let reply = ctx.receive::<String>().await?;
+    assert_eq!(reply, msg, "Should receive the same message");
+
+    connection.disconnect().await?;
+    assert!(!ctx.list_workers().await?.contains(&sender_address));
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+    Ok(())
+}
+
+#[ockam_macros::test]
+async fn stop_closes_listeners_and_connections(ctx: &mut Context) -> Result<()> {
+    let rand_port = rand::thread_rng().gen_range(10000..65535);
+    let bind_address = format!("127.0.0.1:{}", rand_port);
+    let bind_address = bind_address.as_str();
+
+    let transport = UdpTransport::create(ctx).await?;
+    transport.listen(bind_address).await?;
+    let connection = transport.connect(bind_address).await?;
+    let sender_address = connection.sender_address().clone();
+    assert!(ctx.list_workers().await?.contains(&sender_address));
+
+    transport.stop().await?;
+
+    // The workers stop asynchronously, and the listener's port can be
+    // bound again once its socket is closed
+    let mut closed = false;
+    for _ in 0..50 {
+        if !ctx.list_workers().await?.contains(&sender_address)
+            && std::net::UdpSocket::bind(bind_address).is_ok()
+        {
+            closed = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(closed, "Stopping the transport should close its sockets");
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+    Ok(())
+}
+
+#[ockam_macros::test]
+async fn stop_right_after_listen_closes_the_listener(ctx: &mut Context) -> Result<()> {
+    let rand_port = rand::thread_rng().gen_range(10000..65535);
+    let bind_address = format!("127.0.0.1:{}", rand_port);
+    let bind_address = bind_address.as_str();
+
+    let transport = UdpTransport::create(ctx).await?;
+    transport.listen(bind_address).await?;
+    transport.stop().await?;
+
+    let mut closed = false;
+    for _ in 0..50 {
+        if std::net::UdpSocket::bind(bind_address).is_ok() {
+            closed = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(closed, "Stopping the transport should close the listener");
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+    Ok(())
+}
+
+#[ockam_macros::test]
+async fn connect_tries_all_resolved_addresses(ctx: &mut Context) -> Result<()> {
+    let rand_port = rand::thread_rng().gen_range(10000..65535);
+    let bind_address = format!("127.0.0.1:{}", rand_port);
+    let hostname = format!("echoer.ockam.test:{}", rand_port);
+
+    // Broadcast isn't routable without SO_BROADCAST, so connecting has to
+    // fall back to the next resolved address
+    let expected_hostname = hostname.clone();
+    let transport = UdpTransport::create_with_resolver(ctx, move |peer| {
+        assert_eq!(peer, expected_hostname);
+        Ok(vec![
+            SocketAddr::from(([255, 255, 255, 255], rand_port)),
+            SocketAddr::from(([127, 0, 0, 1], rand_port)),
+        ])
+    })
+    .await?;
+    transport.listen(&bind_address).await?;
+    ctx.start_worker("echoer", Echoer).await?;
+
+    let connection = transport.connect(&hostname).await?;
+    assert_eq!(
+        connection.peer(),
+        bind_address.parse::<SocketAddr>().unwrap()
+    );
+
+    // Both the hostname and the resolved addresses route to the same sender
+    let again = transport.connect(&bind_address).await?;
+    assert_eq!(again.sender_address(), connection.sender_address());
+
+    let msg = "Hello Ockam!".to_string();
+    ctx.send(route![(UDP, hostname.as_str()), "echoer"], msg.clone())
+        .await?;
+    let reply = ctx.receive::<String>().await?;
+    assert_eq!(reply, msg, "Should receive the same message");
+
+    connection.disconnect().await?;
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+    Ok(())
+}
+
+#[ockam_macros::test]
+async fn connect_to_closed_port_fails_promptly(ctx: &mut Context) -> Result<()> {
+    // Find a port nobody listens on
+    let closed_address = std::net::UdpSocket::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let transport = UdpTransport::create(ctx).await?;
+    let connection = transport.connect(closed_address.to_string()).await?;
+    let sender_address = connection.sender_address().clone();
+
+    // The connected socket reports the ICMP port unreachable answer,
+    // which closes the connection
+    ctx.send(
+        route![connection.peer_address(), "echoer"],
+        "Hello Ockam!".to_string(),
+    )
+    .await?;
+    let mut closed = false;
+    for _ in 0..20 {
+        if !ctx.list_workers().await?.contains(&sender_address) {
+            closed = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(closed, "Connection to a closed port should be closed");
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+    Ok(())
+}
+
+#[ockam_macros::test]
+async fn multicast_fan_out(ctx: &mut Context) -> Result<()> {
+    let rand_port = rand::thread_rng().gen_range(10000..65535);
+    let group = IpAddr::from(Ipv4Addr::new(239, 255, 42, 99));
+
+    let transport = UdpTransport::create(ctx).await?;
+    transport
+        .listen_multicast(format!("0.0.0.0:{}", rand_port), group)
+        .await?;
+
+    let fan_out = UdpFanOut::create(ctx, "fan_out").await?;
+    let mut subscriber1 = ctx.new_detached("subscriber1").await?;
+    let mut subscriber2 = ctx.new_detached("subscriber2").await?;
+    assert!(fan_out.subscribe("subscriber1"));
+    assert!(fan_out.subscribe("subscriber2"));
+    assert!(!fan_out.subscribe("subscriber2"));
+
+    // Sent once, delivered to both subscribers
+    let r = route![(UDP, format!("{}:{}", group, rand_port)), "fan_out"];
+    let msg = "Hello Ockam!".to_string();
+    ctx.send(r.clone(), msg.clone()).await?;
+    assert_eq!(subscriber1.receive::<String>().await?, msg);
+    assert_eq!(subscriber2.receive::<String>().await?, msg);
+
+    assert!(fan_out.unsubscribe(&"subscriber2".into()));
+    assert_eq!(fan_out.subscribers(), vec!["subscriber1".into()]);
+
+    let msg = "Hello again".to_string();
+    ctx.send(r, msg.clone()).await?;
+    assert_eq!(subscriber1.receive::<String>().await?, msg);
+    assert!(subscriber2
+        .receive_duration_timeout::<String>(Duration::from_millis(500))
+        .await
+        .is_err());
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+    Ok(())
+}
+
+#[ockam_macros::test]
+async fn over_long_routes_are_dropped(ctx: &mut Context) -> Result<()> {
+    let rand_port = rand::thread_rng().gen_range(10000..65535);
+    let bind_address = format!("127.0.0.1:{}", rand_port);
+    let bind_address = bind_address.as_str();
+
+    let transport = UdpTransport::create(ctx).await?;
+    transport.listen(bind_address).await?;
+    ctx.start_worker("echoer", Echoer).await?;
+
+    // The router prepends the sender and the peer to the onward route
+    transport.set_max_route_length(4);
+
+    let msg = "Hello Ockam!".to_string();
+    ctx.send(route![(UDP, bind_address), "echoer"], msg.clone())
+        .await?;
+    assert_eq!(ctx.receive::<String>().await?, msg);
+
+    // Trailing hops are ignored by the echoer, but count against the limit
+    ctx.send(
+        route![(UDP, bind_address), "echoer", "extra1", "extra2", "extra3"],
+        msg.clone(),
+    )
+    .await?;
+    assert!(ctx
+        .receive_duration_timeout::<String>(Duration::from_millis(500))
+        .await
+        .is_err());
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+    Ok(())
+}
+
+#[ockam_macros::test]
+async fn spoofed_sources_fail_return_routability(ctx: &mut Context) -> Result<()> {
+    let rand_port = rand::thread_rng().gen_range(10000..65535);
+    let bind_address = format!("127.0.0.1:{}", rand_port);
+    let bind_address = bind_address.as_str();
+
+    let transport = UdpTransport::create(ctx).await?;
+    transport
+        .listen_with_return_routability(bind_address)
+        .await?;
+    ctx.start_worker("echoer", Echoer).await?;
+
+    // A datagram whose source never answers, as if it was spoofed
+    let spoofed = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let spoofed_address = spoofed.local_addr().unwrap();
+    let msg = TransportMessage::v1(route!["echoer"], route![], "Hello".to_string().encode()?);
+    let mut datagram = Vec::new();
+    let body = msg.encode()?;
+    datagram.extend_from_slice(&(body.len() as u16).to_be_bytes());
+    datagram.extend_from_slice(&body);
+    spoofed.send_to(&datagram, bind_address).await.unwrap();
+
+    // The source only gets a challenge, not the echoer's reply
+    let mut buf = [0; 1024];
+    let (len, from) = spoofed.recv_from(&mut buf).await.unwrap();
+    assert_eq!(from.to_string(), bind_address);
+    let challenge = TransportMessage::decode(&buf[2..len])?;
+    assert_eq!(
+        challenge.onward_route.next()?,
+        &Address::from("_internal.udp.routability.challenge")
+    );
+    let reply = tokio::time::timeout(Duration::from_millis(500), spoofed.recv_from(&mut buf));
+    assert!(reply.await.is_err());
+
+    // No peer was registered with the listener, so the router connects
+    // to the source from another socket
+    ctx.send(
+        route![(UDP, spoofed_address.to_string()), "echoer"],
+        "Hello".to_string(),
+    )
+    .await?;
+    let (_, from) = spoofed.recv_from(&mut buf).await.unwrap();
+    assert_ne!(from.to_string(), bind_address);
+
+    // A real peer echoes the challenge and is served afterwards
+    let msg = "Hello Ockam!".to_string();
+    ctx.send(route![(UDP, bind_address), "echoer"], msg.clone())
+        .await?;
+    assert!(ctx
+        .receive_duration_timeout::<String>(Duration::from_millis(500))
+        .await
+        .is_err());
+    ctx.send(route![(UDP, bind_address), "echoer"], msg.clone())
+        .await?;
+    assert_eq!(ctx.receive::<String>().await?, msg);
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+    Ok(())
+}
+
+#[ockam_macros::test]
+async fn challenges_are_only_answered_on_connections(ctx: &mut Context) -> Result<()> {
+    let rand_port = rand::thread_rng().gen_range(10000..65535);
+    let bind_address = format!("127.0.0.1:{}", rand_port);
+    let bind_address = bind_address.as_str();
+
+    let transport = UdpTransport::create(ctx).await?;
+    transport.listen(bind_address).await?;
+
+    // A challenge the listener didn't ask for, e.g. with the spoofed source
+    // of a third party, isn't echoed
+    let sender = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let msg = TransportMessage::v1(
+        route!["_internal.udp.routability.challenge"],
+        route![],
+        vec![0u8; 8].encode()?,
+    );
+    let mut datagram = Vec::new();
+    let body = msg.encode()?;
+    datagram.extend_from_slice(&(body.len() as u16).to_be_bytes());
+    datagram.extend_from_slice(&body);
+    sender.send_to(&datagram, bind_address).await.unwrap();
+
+    let mut buf = [0; 1024];
+    let reply = tokio::time::timeout(Duration::from_millis(500), sender.recv_from(&mut buf));
+    assert!(reply.await.is_err());
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+    Ok(())
+}
+
+#[ockam_macros::test]
+async fn burst_of_datagrams_is_received(ctx: &mut Context) -> Result<()> {
+    let transport = UdpTransport::create(ctx).await?;
+    let mut sink = ctx.new_detached("burst_sink").await?;
+    let peer = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+    // Batched reads, then one datagram per read
+    for batch_size in [16, 1] {
+        transport.set_read_batch_size(batch_size);
+        let rand_port = rand::thread_rng().gen_range(10000..65535);
+        let bind_address = format!("127.0.0.1:{}", rand_port);
+        transport.listen(&bind_address).await?;
+
+        let count = 64;
+        for i in 0..count {
+            let msg = TransportMessage::v1(route!["burst_sink"], route![], i.to_string().encode()?);
+            let body = msg.encode()?;
+            let mut datagram = Vec::new();
+            datagram.extend_from_slice(&(body.len() as u16).to_be_bytes());
+            datagram.extend_from_slice(&body);
+            peer.send_to(&datagram, &bind_address).await.unwrap();
+        }
+
+        let mut received = Vec::new();
+        for _ in 0..count {
+            let msg = sink
+                .receive_duration_timeout::<String>(Duration::from_secs(2))
+                .await?;
+            received.push(msg.body().parse::<u32>().unwrap());
+        }
+        received.sort_unstable();
+        assert_eq!(received, (0..count).collect::<Vec<_>>());
+    }
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+    Ok(())
+}
+
+#[ockam_macros::test]
+async fn empty_messages_are_delivered(ctx: &mut Context) -> Result<()> {
+    let rand_port = rand::thread_rng().gen_range(10000..65535);
+    let bind_address = format!("127.0.0.1:{}", rand_port);
+    let transport = UdpTransport::create(ctx).await?;
+    transport.listen(&bind_address).await?;
+    let mut sink = ctx.new_detached("empty_sink").await?;
+    let peer = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+    let frame = |payload: Vec<u8>| -> Result<Vec<u8>> {
+        let body = TransportMessage::v1(route!["empty_sink"], route![], payload).encode()?;
+        let mut datagram = (body.len() as u16).to_be_bytes().to_vec();
+        datagram.extend_from_slice(&body);
+        Ok(datagram)
+    };
+
+    // An empty datagram, a zero-length frame and a malformed frame are
+    // dropped without stopping the listener
+    for datagram in [vec![], vec![0, 0], vec![0, 1, 1]] {
+        peer.send_to(&datagram, &bind_address).await.unwrap();
+    }
+    // A message with an empty payload is delivered as such
+    peer.send_to(&frame(vec![])?, &bind_address).await.unwrap();
+    peer.send_to(&frame("Hello".to_string().encode()?)?, &bind_address)
+        .await
+        .unwrap();
+
+    let msg = sink
+        .receive_duration_timeout::<Any>(Duration::from_secs(2))
+        .await?
+        .take();
+    assert!(msg.payload().is_empty());
+    let msg = sink
+        .receive_duration_timeout::<String>(Duration::from_secs(2))
+        .await?;
+    assert_eq!(*msg, "Hello");
+
+    // The same goes for an empty message sent through the transport
+    ctx.send(route![(UDP, bind_address.as_str()), "empty_sink"], ())
+        .await?;
+    let msg = sink
+        .receive_duration_timeout::<Any>(Duration::from_secs(2))
+        .await?
+        .take();
+    assert!(msg.payload().is_empty());
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+    Ok(())
+}
+
+#[ockam_macros::test]
+async fn idle_connections_are_closed(ctx: &mut Context) -> Result<()> {
+    let transport = UdpTransport::create(ctx).await?;
+    ctx.start_worker("echoer", Echoer).await?;
+    let mut connections = Vec::new();
+    for _ in 0..2 {
+        let rand_port = rand::thread_rng().gen_range(10000..65535);
+        let bind_address = format!("127.0.0.1:{}", rand_port);
+        transport.listen(&bind_address).await?;
+        connections.push(transport.connect(&bind_address).await?);
+    }
+    let (idle, active) = (&connections[0], &connections[1]);
+
+    transport
+        .set_idle_timeout(Duration::from_millis(300))
+        .await?;
+
+    // Keep one connection busy for several idle timeouts
+    for _ in 0..20 {
+        let msg = "Hello Ockam!".to_string();
+        ctx.send(route![active.peer_address(), "echoer"], msg.clone())
+            .await?;
+        let reply = ctx
+            .receive_duration_timeout::<String>(Duration::from_secs(2))
+            .await?;
+        assert_eq!(reply, msg);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    let workers = ctx.list_workers().await?;
+    assert!(!workers.contains(idle.sender_address()));
+    assert!(workers.contains(active.sender_address()));
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+    Ok(())
+}
+
 pub struct Echoer;
 
 #[ockam_core::worker]
@@ -0,0 +1,29 @@
+//! Unix domain socket transport for the Ockam Routing Protocol.
+//!
+//! Nodes on the same host can exchange messages over a Unix domain socket
+//! instead of TCP, without going through the network stack nor using a
+//! port. Peers are addressed by the path of the socket they listen on,
+//! e.g. `route![(UDS, "/tmp/node.sock"), "echoer"]`.
+#![deny(unsafe_code)]
+#![warn(
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unused_import_braces,
+    unused_qualifications
+)]
+
+mod router;
+mod transport;
+mod workers;
+
+pub use transport::*;
+
+use ockam_core::TransportType;
+
+/// Transport type of Unix domain socket addresses
+pub const UDS: TransportType = TransportType::new(5);
+
+/// Cluster of the workers of the transport, stopped after the others on
+/// node shutdown
+pub const CLUSTER_NAME: &str = "_internals.transport.uds";
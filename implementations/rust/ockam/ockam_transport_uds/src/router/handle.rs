@@ -0,0 +1,145 @@
+use std::io;
+use std::os::unix::fs::FileTypeExt;
+use std::path::Path;
+
+use ockam_core::{async_trait, Address, AsyncTryClone, Result};
+use ockam_node::Context;
+use ockam_transport_core::TransportError;
+use tokio::net::{UnixListener, UnixStream};
+use tracing::warn;
+
+use crate::workers::UdsListenProcessor;
+
+use super::{UdsRouterMessage, UdsRouterResponse};
+
+/// A handle to connect to a UdsRouter
+///
+/// Dropping this handle is harmless.
+pub(crate) struct UdsRouterHandle {
+    ctx: Context,
+    api_addr: Address,
+}
+
+#[async_trait]
+impl AsyncTryClone for UdsRouterHandle {
+    async fn async_try_clone(&self) -> Result<Self> {
+        let child_ctx = self.ctx.new_detached(Address::random_local()).await?;
+        Ok(Self::new(child_ctx, self.api_addr.clone()))
+    }
+}
+
+impl UdsRouterHandle {
+    /// Create a new `UdsRouterHandle` with given address
+    pub fn new(ctx: Context, api_addr: Address) -> Self {
+        Self { ctx, api_addr }
+    }
+
+    /// Listen for connections on the socket at `path`
+    pub async fn bind(&self, path: &Path) -> Result<()> {
+        let listener = bind_listener(path).await?;
+        let rx_addr = UdsListenProcessor::start(
+            &self.ctx,
+            listener,
+            path.to_path_buf(),
+            self.async_try_clone().await?,
+        )
+        .await?;
+
+        self.ctx
+            .send(
+                self.api_addr.clone(),
+                UdsRouterMessage::Listening { rx_addr },
+            )
+            .await
+    }
+
+    /// Connect to the listener at `path`, returning the address of the
+    /// worker sending to it
+    pub async fn connect(&self, path: impl Into<String>) -> Result<Address> {
+        let response = self
+            .ctx
+            .send_and_receive(
+                self.api_addr.clone(),
+                UdsRouterMessage::Connect { path: path.into() },
+            )
+            .await?;
+
+        if let UdsRouterResponse::Connect(res) = response {
+            res
+        } else {
+            Err(TransportError::InvalidRouterResponseType.into())
+        }
+    }
+
+    /// Disconnect from the listener at `path`
+    pub async fn disconnect(&self, path: impl Into<String>) -> Result<()> {
+        let response = self
+            .ctx
+            .send_and_receive(
+                self.api_addr.clone(),
+                UdsRouterMessage::Disconnect { path: path.into() },
+            )
+            .await?;
+
+        if let UdsRouterResponse::Disconnect(res) = response {
+            res
+        } else {
+            Err(TransportError::InvalidRouterResponseType.into())
+        }
+    }
+
+    /// Track a connection accepted by a listener, stopped with the router
+    pub(crate) async fn accepted(&self, tx_addr: Address, rx_addr: Address) -> Result<()> {
+        self.ctx
+            .send(
+                self.api_addr.clone(),
+                UdsRouterMessage::Accepted { tx_addr, rx_addr },
+            )
+            .await
+    }
+
+    /// Stop the workers of the connection whose sender is at `tx_addr`,
+    /// once the socket is closed
+    pub(crate) async fn closed(&self, tx_addr: Address) -> Result<()> {
+        self.ctx
+            .send(self.api_addr.clone(), UdsRouterMessage::Closed { tx_addr })
+            .await
+    }
+
+    /// Stop the router, along with its listeners and connections
+    pub async fn stop(&self) -> Result<()> {
+        self.ctx.stop_worker(self.api_addr.clone()).await
+    }
+}
+
+/// Bind a listener to `path`, replacing the socket file left by a
+/// listener which didn't shut down
+///
+/// Binding fails with [`TransportError::BindFailed`] if a listener still
+/// accepts connections on the socket, or if `path` is not a socket.
+async fn bind_listener(path: &Path) -> Result<UnixListener> {
+    match UnixListener::bind(path) {
+        Ok(listener) => return Ok(listener),
+        Err(e) if e.kind() == io::ErrorKind::AddrInUse => {}
+        Err(e) => return Err(TransportError::from(e).into()),
+    }
+
+    let is_socket = std::fs::symlink_metadata(path)
+        .map(|metadata| metadata.file_type().is_socket())
+        .unwrap_or(false);
+    if !is_socket {
+        warn!("Not listening on {}: not a socket", path.display());
+        return Err(TransportError::BindFailed.into());
+    }
+    if UnixStream::connect(path).await.is_ok() {
+        warn!(
+            "Not listening on {}: another listener uses it",
+            path.display()
+        );
+        return Err(TransportError::BindFailed.into());
+    }
+
+    warn!("Replacing the stale socket file {}", path.display());
+    std::fs::remove_file(path).map_err(TransportError::from)?;
+    Ok(UnixListener::bind(path).map_err(TransportError::from)?)
+}
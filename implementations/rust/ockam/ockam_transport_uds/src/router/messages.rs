@@ -0,0 +1,24 @@
+use ockam_core::{Address, Message, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq, Message)]
+pub(crate) enum UdsRouterMessage {
+    /// Track the processor of a listener, which is stopped with the router
+    Listening { rx_addr: Address },
+    /// Track the workers of a connection accepted by a listener
+    Accepted { tx_addr: Address, rx_addr: Address },
+    /// Connect to the socket at `path`, answered with
+    /// [`UdsRouterResponse::Connect`]
+    Connect { path: String },
+    /// Disconnect from the socket at `path`, answered with
+    /// [`UdsRouterResponse::Disconnect`]
+    Disconnect { path: String },
+    /// Forget the connection whose sender is at `tx_addr`, once it closed
+    Closed { tx_addr: Address },
+}
+
+#[derive(Serialize, Deserialize, Debug, Message)]
+pub(crate) enum UdsRouterResponse {
+    Connect(Result<Address>),
+    Disconnect(Result<()>),
+}
@@ -0,0 +1,8 @@
+pub(crate) use handle::UdsRouterHandle;
+pub(crate) use uds_router::UdsRouter;
+
+use self::messages::{UdsRouterMessage, UdsRouterResponse};
+
+mod handle;
+mod messages;
+mod uds_router;
@@ -0,0 +1,203 @@
+use std::collections::BTreeMap;
+
+use ockam_core::{async_trait, Address, Any, Decodable, LocalMessage, Result, Routed, Worker};
+use ockam_node::Context;
+use ockam_transport_core::TransportError;
+use tokio::net::UnixStream;
+use tracing::{error, trace};
+
+use crate::router::{UdsRouterHandle, UdsRouterMessage, UdsRouterResponse};
+use crate::workers::UdsSendWorker;
+
+/// A Unix domain socket router and listener
+///
+/// In order to create new UDS workers you need a router
+/// to map remote addresses of `type = 5` to worker addresses.
+/// This type facilitates this.
+///
+/// Optionally you can also start listening for incoming connections
+/// if the local node is part of a server architecture.
+pub(crate) struct UdsRouter {
+    ctx: Context,
+    main_addr: Address,
+    api_addr: Address,
+    /// Sender of the outgoing connection to each socket path
+    map: BTreeMap<String, Address>,
+    /// Receive processor of every connection, by sender address
+    processors: BTreeMap<Address, Address>,
+    /// Processor of each listener
+    listeners: Vec<Address>,
+}
+
+impl UdsRouter {
+    /// Create and register a new UDS router with the node context
+    pub(crate) async fn register(ctx: &Context) -> Result<UdsRouterHandle> {
+        let main_addr = Address::random_local();
+        let api_addr = Address::random_local();
+
+        let child_ctx = ctx.new_detached(Address::random_local()).await?;
+
+        let router = Self {
+            ctx: child_ctx,
+            main_addr: main_addr.clone(),
+            api_addr: api_addr.clone(),
+            map: BTreeMap::new(),
+            processors: BTreeMap::new(),
+            listeners: Vec::new(),
+        };
+
+        let handle = router.create_self_handle(ctx).await?;
+
+        ctx.start_worker(vec![main_addr.clone(), api_addr.clone()], router)
+            .await?;
+        ctx.set_address_tag(main_addr.clone(), "router").await?;
+        ctx.set_address_tag(api_addr, "api").await?;
+        trace!("Registering UDS router for type = {}", crate::UDS);
+        ctx.register(crate::UDS, main_addr).await?;
+
+        Ok(handle)
+    }
+
+    /// Create a new `UdsRouterHandle` representing this router
+    async fn create_self_handle(&self, ctx: &Context) -> Result<UdsRouterHandle> {
+        let handle_ctx = ctx.new_detached(Address::random_local()).await?;
+        Ok(UdsRouterHandle::new(handle_ctx, self.api_addr.clone()))
+    }
+
+    async fn handle_route(&mut self, ctx: &Context, mut msg: LocalMessage) -> Result<()> {
+        trace!(
+            "UDS route request: {}",
+            msg.transport().onward_route.pretty()
+        );
+
+        let onward = msg.transport().onward_route.next()?;
+        let path = match String::from_utf8(onward.to_vec()) {
+            Ok(path) => path,
+            Err(_e) => return Err(TransportError::UnknownRoute.into()),
+        };
+        let next = self.connect(path).await?;
+
+        let transport_msg = msg.transport_mut();
+        transport_msg.onward_route.step()?;
+        transport_msg.onward_route.modify().prepend(next.clone());
+
+        ctx.forward(msg).await
+    }
+
+    async fn connect(&mut self, path: String) -> Result<Address> {
+        // Reuse the connection already open to this socket
+        if let Some(tx_addr) = self.map.get(&path) {
+            return Ok(tx_addr.clone());
+        }
+
+        let stream = UnixStream::connect(&path).await.map_err(|e| {
+            error!("Failed to connect to UDS listener {}: {}", path, e);
+            TransportError::PeerNotFound
+        })?;
+        let (tx_addr, rx_addr) =
+            UdsSendWorker::start_pair(&self.ctx, stream, self.create_self_handle(&self.ctx).await?)
+                .await?;
+        trace!("UDS connection to {} => {}", path, tx_addr);
+
+        self.processors.insert(tx_addr.clone(), rx_addr);
+        self.map.insert(path, tx_addr.clone());
+        Ok(tx_addr)
+    }
+
+    async fn handle_disconnect(&mut self, path: String) -> Result<()> {
+        let tx_addr = match self.map.remove(&path) {
+            Some(tx_addr) => tx_addr,
+            None => {
+                error!("Failed to disconnect, peer not found: {}", path);
+                return Err(TransportError::PeerNotFound.into());
+            }
+        };
+
+        trace!("UDS disconnect request: {} => {}", path, tx_addr);
+        self.stop_connection(tx_addr).await
+    }
+
+    /// Stop the workers of the connection whose sender is at `tx_addr`
+    async fn stop_connection(&mut self, tx_addr: Address) -> Result<()> {
+        self.map.retain(|_, self_addr| self_addr != &tx_addr);
+        if let Some(rx_addr) = self.processors.remove(&tx_addr) {
+            // Either worker may already be stopped when the socket closed
+            let _ = self.ctx.stop_processor(rx_addr).await;
+            let _ = self.ctx.stop_worker(tx_addr).await;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Worker for UdsRouter {
+    type Message = Any;
+    type Context = Context;
+
+    async fn initialize(&mut self, ctx: &mut Context) -> Result<()> {
+        ctx.set_cluster(crate::CLUSTER_NAME).await?;
+        Ok(())
+    }
+
+    /// Stop the listeners, removing their socket files, then close the
+    /// connections
+    ///
+    /// The listeners are stopped first so that no connection is accepted
+    /// while the others are closed.
+    async fn shutdown(&mut self, ctx: &mut Context) -> Result<()> {
+        // Workers may already be stopped by a node shutdown
+        for rx_addr in core::mem::take(&mut self.listeners) {
+            let _ = ctx.stop_processor(rx_addr).await;
+        }
+        let connections = core::mem::take(&mut self.processors);
+        for rx_addr in connections.values() {
+            let _ = ctx.stop_processor(rx_addr.clone()).await;
+        }
+        for tx_addr in connections.into_keys() {
+            let _ = ctx.stop_worker(tx_addr).await;
+        }
+        self.map.clear();
+        Ok(())
+    }
+
+    async fn handle_message(&mut self, ctx: &mut Context, msg: Routed<Any>) -> Result<()> {
+        let return_route = msg.return_route();
+        let msg_addr = msg.msg_addr();
+
+        if msg_addr == self.main_addr {
+            if let Err(err) = self.handle_route(ctx, msg.into_local_message()).await {
+                error!("UDS router failed to route a message: {}", err);
+            }
+        } else if msg_addr == self.api_addr {
+            let msg = UdsRouterMessage::decode(msg.payload())?;
+            match msg {
+                UdsRouterMessage::Listening { rx_addr } => {
+                    self.listeners.push(rx_addr);
+                }
+                UdsRouterMessage::Accepted { tx_addr, rx_addr } => {
+                    self.processors.insert(tx_addr, rx_addr);
+                }
+                UdsRouterMessage::Connect { path } => {
+                    let res = self.connect(path).await;
+
+                    ctx.send(return_route, UdsRouterResponse::Connect(res))
+                        .await?;
+                }
+                UdsRouterMessage::Disconnect { path } => {
+                    let res = self.handle_disconnect(path).await;
+
+                    ctx.send(return_route, UdsRouterResponse::Disconnect(res))
+                        .await?;
+                }
+                UdsRouterMessage::Closed { tx_addr } => {
+                    trace!("UDS connection closed: {}", tx_addr);
+                    self.stop_connection(tx_addr).await?;
+                }
+            };
+        } else {
+            return Err(TransportError::InvalidAddress.into());
+        }
+
+        Ok(())
+    }
+}
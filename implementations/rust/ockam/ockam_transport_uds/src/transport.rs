@@ -0,0 +1,58 @@
+use std::path::Path;
+
+use ockam_core::{Address, AsyncTryClone, Result};
+use ockam_node::Context;
+
+use crate::router::{UdsRouter, UdsRouterHandle};
+
+/// High level management interface for Unix domain socket transports
+///
+/// Messages are routed to a peer with the path of the socket it listens
+/// on, e.g. `route![(UDS, "/tmp/node.sock"), "echoer"]`, connecting to it
+/// on first use.
+#[derive(AsyncTryClone)]
+#[async_try_clone(crate = "ockam_core")]
+pub struct UdsTransport {
+    router_handle: UdsRouterHandle,
+}
+
+impl UdsTransport {
+    /// Create a new UDS transport and router for the current node
+    pub async fn create(ctx: &Context) -> Result<UdsTransport> {
+        let router_handle = UdsRouter::register(ctx).await?;
+        Ok(Self { router_handle })
+    }
+
+    /// Start listening to incoming connections on the socket at `path`
+    ///
+    /// A socket file left at `path` by a listener which didn't shut down
+    /// is replaced, while a socket still accepting connections, or any
+    /// other file, makes this fail with
+    /// [`TransportError::BindFailed`](ockam_transport_core::TransportError::BindFailed).
+    /// The socket file is removed when the transport stops.
+    pub async fn listen<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.router_handle.bind(path.as_ref()).await
+    }
+
+    /// Manually establish an outgoing connection to the socket at `path`,
+    /// returning the address of the worker sending to it
+    ///
+    /// This step is optional because the underlying router is capable of
+    /// lazily connecting upon arrival of the initial message.
+    pub async fn connect<S: AsRef<str>>(&self, path: S) -> Result<Address> {
+        self.router_handle.connect(path.as_ref()).await
+    }
+
+    /// Disconnect from the socket at `path`
+    pub async fn disconnect<S: AsRef<str>>(&self, path: S) -> Result<()> {
+        self.router_handle.disconnect(path.as_ref()).await
+    }
+
+    /// Stop the transport, closing its connections and removing the
+    /// socket files of its listeners
+    ///
+    /// Messages can't be routed through the transport afterwards.
+    pub async fn stop(&self) -> Result<()> {
+        self.router_handle.stop().await
+    }
+}
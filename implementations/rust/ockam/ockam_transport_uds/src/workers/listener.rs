@@ -0,0 +1,79 @@
+use std::path::PathBuf;
+
+use ockam_core::{async_trait, Address, AsyncTryClone, Processor, Result};
+use ockam_node::Context;
+use tokio::net::UnixListener;
+use tracing::{debug, warn};
+
+use crate::router::UdsRouterHandle;
+
+use super::UdsSendWorker;
+
+/// A UDS listen processor
+///
+/// UDS listen processors are created by `UdsTransport`
+/// after a call is made to
+/// [`UdsTransport::listen`](crate::UdsTransport::listen).
+/// The socket file is removed when the processor stops.
+pub(crate) struct UdsListenProcessor {
+    inner: UnixListener,
+    path: PathBuf,
+    router_handle: UdsRouterHandle,
+}
+
+impl UdsListenProcessor {
+    pub(crate) async fn start(
+        ctx: &Context,
+        inner: UnixListener,
+        path: PathBuf,
+        router_handle: UdsRouterHandle,
+    ) -> Result<Address> {
+        let processor = Self {
+            inner,
+            path,
+            router_handle,
+        };
+        let addr = Address::random_local();
+        ctx.start_processor(addr.clone(), processor).await?;
+        Ok(addr)
+    }
+}
+
+#[async_trait]
+impl Processor for UdsListenProcessor {
+    type Context = Context;
+
+    async fn initialize(&mut self, ctx: &mut Context) -> Result<()> {
+        ctx.set_cluster(crate::CLUSTER_NAME).await
+    }
+
+    async fn shutdown(&mut self, _ctx: &mut Context) -> Result<()> {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            warn!(
+                "Failed to remove the socket file {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+        Ok(())
+    }
+
+    async fn process(&mut self, ctx: &mut Self::Context) -> Result<bool> {
+        debug!("Waiting for incoming UDS connection...");
+
+        let (stream, _) = match self.inner.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                warn!("Failed to accept a UDS connection: {}", e);
+                return Ok(true);
+            }
+        };
+        let (tx_addr, rx_addr) =
+            UdsSendWorker::start_pair(ctx, stream, self.router_handle.async_try_clone().await?)
+                .await?;
+        debug!("Accepted a UDS connection => {}", tx_addr);
+        self.router_handle.accepted(tx_addr, rx_addr).await?;
+
+        Ok(true)
+    }
+}
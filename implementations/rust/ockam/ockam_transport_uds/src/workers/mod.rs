@@ -0,0 +1,10 @@
+pub(crate) use listener::*;
+pub(crate) use receiver::*;
+pub(crate) use sender::*;
+
+mod listener;
+mod receiver;
+mod sender;
+
+/// Longest message the transport sends or accepts, in bytes
+pub(crate) const MAX_MESSAGE_LENGTH: usize = 16 * 1024 * 1024;
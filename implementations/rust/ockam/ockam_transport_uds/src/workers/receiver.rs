@@ -0,0 +1,86 @@
+use ockam_core::{
+    async_trait, Address, Decodable, LocalMessage, Processor, Result, TransportMessage,
+};
+use ockam_node::{Context, ExternalLocalInfo};
+use ockam_transport_core::TransportError;
+use tokio::io::AsyncReadExt;
+use tokio::net::unix::OwnedReadHalf;
+use tracing::{debug, trace, warn};
+
+use crate::router::UdsRouterHandle;
+use crate::UDS;
+
+use super::MAX_MESSAGE_LENGTH;
+
+/// A UDS receiving message processor
+///
+/// This half of the worker pair reads the messages written by the peer,
+/// and forwards them with the sender's address prepended to their return
+/// route, so that replies go back over the same connection.
+pub(crate) struct UdsRecvProcessor {
+    rx: OwnedReadHalf,
+    tx_addr: Address,
+    router_handle: UdsRouterHandle,
+}
+
+impl UdsRecvProcessor {
+    pub(crate) fn new(rx: OwnedReadHalf, tx_addr: Address, router_handle: UdsRouterHandle) -> Self {
+        Self {
+            rx,
+            tx_addr,
+            router_handle,
+        }
+    }
+
+    /// Read the next length-prefixed message
+    async fn read_message(&mut self) -> core::result::Result<Vec<u8>, TransportError> {
+        let len = self.rx.read_u32().await? as usize;
+        if len > MAX_MESSAGE_LENGTH {
+            return Err(TransportError::Capacity);
+        }
+        let mut buf = vec![0; len];
+        self.rx.read_exact(&mut buf).await?;
+        Ok(buf)
+    }
+}
+
+#[async_trait]
+impl Processor for UdsRecvProcessor {
+    type Context = Context;
+
+    async fn initialize(&mut self, ctx: &mut Context) -> Result<()> {
+        ctx.set_cluster(crate::CLUSTER_NAME).await
+    }
+
+    async fn process(&mut self, ctx: &mut Context) -> Result<bool> {
+        let buf = match self.read_message().await {
+            Ok(buf) => buf,
+            Err(e) => {
+                debug!("UDS connection of {} closed: {}", self.tx_addr, e);
+                self.router_handle.closed(self.tx_addr.clone()).await?;
+                return Ok(false);
+            }
+        };
+
+        // The stream can't be trusted past a message that doesn't decode
+        let mut msg = match TransportMessage::decode(&buf) {
+            Ok(msg) => msg,
+            Err(_) => {
+                warn!("Closing UDS connection of {}: bad message", self.tx_addr);
+                self.router_handle.closed(self.tx_addr.clone()).await?;
+                return Ok(false);
+            }
+        };
+        msg.return_route.modify().prepend(self.tx_addr.clone());
+
+        trace!("Message onward route: {}", msg.onward_route.pretty());
+        trace!("Message return route: {}", msg.return_route.pretty());
+
+        // Mark that message originates from some other node
+        let local_info = ExternalLocalInfo::new(UDS).to_local_info()?;
+        ctx.forward(LocalMessage::new(msg, vec![local_info]))
+            .await?;
+
+        Ok(true)
+    }
+}
@@ -0,0 +1,87 @@
+use ockam_core::{async_trait, Address, Any, Encodable, Result, Routed, TransportMessage, Worker};
+use ockam_node::Context;
+use ockam_transport_core::TransportError;
+use tokio::io::AsyncWriteExt;
+use tokio::net::unix::OwnedWriteHalf;
+use tokio::net::UnixStream;
+use tracing::{trace, warn};
+
+use crate::router::UdsRouterHandle;
+
+use super::{UdsRecvProcessor, MAX_MESSAGE_LENGTH};
+
+/// A UDS sending message worker
+///
+/// This half of the worker pair owns the write half of a connection, and
+/// writes the messages routed to it, each prefixed by its length as a
+/// big endian `u32`.
+pub(crate) struct UdsSendWorker {
+    tx: OwnedWriteHalf,
+    router_handle: UdsRouterHandle,
+}
+
+impl UdsSendWorker {
+    /// Start the sender and receiver of the connection `stream`, returning
+    /// their addresses
+    pub(crate) async fn start_pair(
+        ctx: &Context,
+        stream: UnixStream,
+        router_handle: UdsRouterHandle,
+    ) -> Result<(Address, Address)> {
+        let (rx, tx) = stream.into_split();
+        let tx_addr = Address::random_local();
+        let rx_addr = Address::random_local();
+
+        let receiver =
+            UdsRecvProcessor::new(rx, tx_addr.clone(), router_handle.async_try_clone().await?);
+        let sender = Self { tx, router_handle };
+
+        ctx.start_worker(tx_addr.clone(), sender).await?;
+        ctx.start_processor(rx_addr.clone(), receiver).await?;
+        Ok((tx_addr, rx_addr))
+    }
+}
+
+/// Encode `msg` behind its length
+fn prepare_message(msg: TransportMessage) -> Result<Vec<u8>> {
+    let msg = msg.encode().map_err(|_| TransportError::SendBadMessage)?;
+    if msg.len() > MAX_MESSAGE_LENGTH {
+        return Err(TransportError::Capacity.into());
+    }
+
+    let mut buf = Vec::with_capacity(4 + msg.len());
+    buf.extend_from_slice(&(msg.len() as u32).to_be_bytes());
+    buf.extend_from_slice(&msg);
+    Ok(buf)
+}
+
+#[async_trait]
+impl Worker for UdsSendWorker {
+    type Context = Context;
+    type Message = Any;
+
+    async fn initialize(&mut self, ctx: &mut Self::Context) -> Result<()> {
+        ctx.set_cluster(crate::CLUSTER_NAME).await
+    }
+
+    async fn handle_message(
+        &mut self,
+        ctx: &mut Context,
+        msg: Routed<Self::Message>,
+    ) -> Result<()> {
+        let mut msg = msg.into_transport_message();
+        // Remove our own address from the route so the other end
+        // knows what to do with the incoming message
+        msg.onward_route.step()?;
+
+        let buf = prepare_message(msg)?;
+        if let Err(e) = self.tx.write_all(&buf).await {
+            warn!("Failed to send a message over UDS: {}", e);
+            self.router_handle.closed(ctx.address()).await?;
+            return Err(TransportError::ConnectionDrop.into());
+        }
+        trace!("Sent {} bytes over UDS", buf.len());
+
+        Ok(())
+    }
+}
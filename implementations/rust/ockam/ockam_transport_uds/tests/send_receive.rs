@@ -0,0 +1,133 @@
+use std::path::PathBuf;
+
+use ockam_core::compat::rand::{self, Rng};
+use ockam_core::{route, Address, Result, Routed, Worker};
+use ockam_node::Context;
+
+use ockam_transport_uds::{UdsTransport, UDS};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::time::timeout;
+
+/// Path of a socket file in the temporary directory, unique to a test run
+fn socket_path() -> PathBuf {
+    let suffix: u64 = rand::thread_rng().gen();
+    std::env::temp_dir().join(format!("ockam-uds-{}.sock", suffix))
+}
+
+#[ockam_macros::test]
+async fn send_receive(ctx: &mut Context) -> Result<()> {
+    let path = socket_path();
+    let path_str = path.to_str().unwrap();
+
+    // Listener
+    {
+        let transport = UdsTransport::create(ctx).await?;
+        transport.listen(&path).await?;
+        ctx.start_worker("echoer", Echoer).await?;
+    };
+
+    // Sender
+    {
+        let mut ctx = ctx.new_detached(Address::random_local()).await?;
+        let msg: String = rand::thread_rng()
+            .sample_iter(&rand::distributions::Alphanumeric)
+            .take(256)
+            .map(char::from)
+            .collect();
+        let r = route![(UDS, path_str), "echoer"];
+        ctx.send(r, msg.clone()).await?;
+
+        let reply = ctx.receive::<String>().await?;
+        assert_eq!(reply, msg, "Should receive the same message");
+    };
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+    Ok(())
+}
+
+#[ockam_macros::test]
+async fn stop_removes_socket_file(ctx: &mut Context) -> Result<()> {
+    let path = socket_path();
+
+    let transport = UdsTransport::create(ctx).await?;
+    transport.listen(&path).await?;
+    assert!(path.exists());
+
+    // A second listener on a live socket is refused
+    let other = UdsTransport::create(ctx).await?;
+    assert!(other.listen(&path).await.is_err());
+
+    transport.stop().await?;
+    assert!(!path.exists());
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+    Ok(())
+}
+
+#[ockam_macros::test]
+async fn listen_replaces_stale_socket(ctx: &mut Context) -> Result<()> {
+    let path = socket_path();
+    let path_str = path.to_str().unwrap();
+
+    // Leave a socket file nothing listens on, as a crashed node would
+    drop(UnixListener::bind(&path).unwrap());
+    assert!(path.exists());
+
+    let transport = UdsTransport::create(ctx).await?;
+    transport.listen(&path).await?;
+    ctx.start_worker("echoer", Echoer).await?;
+
+    let msg = "Hello Ockam!".to_string();
+    ctx.send(route![(UDS, path_str), "echoer"], msg.clone())
+        .await?;
+    let reply = ctx.receive::<String>().await?;
+    assert_eq!(reply, msg, "Should receive the same message");
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+    Ok(())
+}
+
+#[ockam_macros::test]
+async fn bad_message_closes_connection(ctx: &mut Context) -> Result<()> {
+    let path = socket_path();
+
+    let transport = UdsTransport::create(ctx).await?;
+    transport.listen(&path).await?;
+
+    // A length prefix followed by bytes which aren't a transport message
+    let mut stream = UnixStream::connect(&path).await.unwrap();
+    stream.write_u32(4).await.unwrap();
+    stream.write_all(&[0xff; 4]).await.unwrap();
+
+    // The listener closes its side rather than waiting for more
+    let mut buf = [0; 1];
+    let read = timeout(Duration::from_secs(5), stream.read(&mut buf))
+        .await
+        .expect("the connection should be closed");
+    assert_eq!(read.unwrap(), 0);
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+    Ok(())
+}
+
+pub struct Echoer;
+
+#[ockam_core::worker]
+impl Worker for Echoer {
+    type Message = String;
+    type Context = Context;
+
+    async fn handle_message(&mut self, ctx: &mut Context, msg: Routed<String>) -> Result<()> {
+        ctx.send(msg.return_route(), msg.body()).await
+    }
+}
@@ -56,6 +56,9 @@ impl AsymmetricVault for Vault {
         // Prevent dead-lock by freeing entries lock, since we don't need it
         drop(entries);
 
+        #[cfg(feature = "std")]
+        self.audit(secret, crate::VaultOperation::EcDiffieHellman);
+
         let attributes = SecretAttributes::new(
             SecretType::Buffer,
             SecretPersistence::Ephemeral,
@@ -0,0 +1,73 @@
+use ockam_core::vault::KeyId;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// Key operation recorded in the audit log
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VaultOperation {
+    /// Signature created with the key
+    Sign,
+    /// Diffie-Hellman key agreement performed with the key
+    EcDiffieHellman,
+}
+
+/// A single audit log record. Never contains secret material.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VaultAuditEntry {
+    key_id: KeyId,
+    operation: VaultOperation,
+    timestamp: SystemTime,
+}
+
+impl VaultAuditEntry {
+    /// Create a new entry timestamped with the current time
+    pub fn new(key_id: KeyId, operation: VaultOperation) -> Self {
+        Self {
+            key_id,
+            operation,
+            timestamp: SystemTime::now(),
+        }
+    }
+    /// Id of the key that was used
+    pub fn key_id(&self) -> &KeyId {
+        &self.key_id
+    }
+    /// Operation the key was used for
+    pub fn operation(&self) -> VaultOperation {
+        self.operation
+    }
+    /// When the key was used
+    pub fn timestamp(&self) -> SystemTime {
+        self.timestamp
+    }
+}
+
+/// Destination for [`VaultAuditEntry`] records, see [`crate::Vault::with_audit_sink`]
+pub trait VaultAuditSink: Send + Sync + 'static {
+    /// Record a key usage
+    fn record(&self, entry: VaultAuditEntry);
+}
+
+/// [`VaultAuditSink`] that keeps records in memory
+#[derive(Clone, Default)]
+pub struct InMemoryAuditSink {
+    entries: Arc<Mutex<Vec<VaultAuditEntry>>>,
+}
+
+impl InMemoryAuditSink {
+    /// Create an empty sink
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records collected so far, oldest first
+    pub fn entries(&self) -> Vec<VaultAuditEntry> {
+        self.entries.lock().unwrap().clone()
+    }
+}
+
+impl VaultAuditSink for InMemoryAuditSink {
+    fn record(&self, entry: VaultAuditEntry) {
+        self.entries.lock().unwrap().push(entry);
+    }
+}
@@ -22,8 +22,11 @@ extern crate alloc;
 pub use ockam_core;
 
 mod asymmetric_impl;
+#[cfg(feature = "std")]
+mod audit;
 mod error;
 mod hasher_impl;
+mod rng;
 mod secret_impl;
 mod signer_impl;
 
@@ -42,8 +45,12 @@ pub use ockam_core::vault::{
 };
 
 pub use asymmetric_impl::*;
+#[cfg(feature = "std")]
+pub use audit::*;
 pub use error::*;
 pub use hasher_impl::*;
+#[cfg(feature = "std")]
+pub use rng::VaultRng;
 pub use secret_impl::*;
 pub use signer_impl::*;
 pub use symmetric_impl::*;
@@ -0,0 +1,53 @@
+use crate::Vault;
+use ockam_core::compat::rand::{thread_rng, CryptoRng, Error, RngCore};
+
+/// Source of randomness of a [`Vault`], see [`Vault::with_rng`]
+#[cfg(feature = "std")]
+pub trait VaultRng: RngCore + CryptoRng + Send + 'static {}
+
+#[cfg(feature = "std")]
+impl<R: RngCore + CryptoRng + Send + 'static> VaultRng for R {}
+
+/// Random number generator drawing from the source of randomness of a
+/// [`Vault`], the thread-local OS-seeded generator by default
+pub(crate) struct VaultRandom<'a> {
+    #[cfg_attr(not(feature = "std"), allow(dead_code))]
+    vault: &'a Vault,
+}
+
+impl Vault {
+    /// Random number generator used for the secrets and signatures of this vault
+    pub(crate) fn rng(&self) -> VaultRandom<'_> {
+        VaultRandom { vault: self }
+    }
+}
+
+impl RngCore for VaultRandom<'_> {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0u8; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0u8; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        #[cfg(feature = "std")]
+        if let Some(rng) = &self.vault.rng {
+            rng.lock().unwrap().fill_bytes(dest);
+            return;
+        }
+        thread_rng().fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl CryptoRng for VaultRandom<'_> {}
@@ -2,7 +2,7 @@ use crate::vault::Vault;
 use crate::VaultError;
 use arrayref::array_ref;
 use cfg_if::cfg_if;
-use ockam_core::compat::rand::{thread_rng, RngCore};
+use ockam_core::compat::rand::RngCore;
 use ockam_core::vault::{
     AsymmetricVault, KeyId, PublicKey, SecretAttributes, SecretKey, SecretPersistence, SecretType,
     SecretVault, VaultEntry, AES128_SECRET_LENGTH_U32, AES256_SECRET_LENGTH_U32,
@@ -60,7 +60,7 @@ impl Vault {
                 // that every time we import the same secret - it gets different KeyId value.
                 // However, if we decide to have persistent Buffer or Aes secrets, that should be
                 // change (probably to hash value of the secret)
-                let mut rng = thread_rng();
+                let mut rng = self.rng();
                 let mut rand = [0u8; 8];
                 rng.fill_bytes(&mut rand);
                 hex::encode(rand)
@@ -108,7 +108,7 @@ impl SecretVault for Vault {
         let key = match attributes.stype() {
             SecretType::X25519 | SecretType::Ed25519 => {
                 let bytes = {
-                    let mut rng = thread_rng();
+                    let mut rng = self.rng();
                     let mut bytes = vec![0u8; CURVE25519_SECRET_LENGTH_USIZE];
                     rng.fill_bytes(&mut bytes);
                     bytes
@@ -121,7 +121,7 @@ impl SecretVault for Vault {
                     return Err(VaultError::InvalidKeyType.into());
                 };
                 let key = {
-                    let mut rng = thread_rng();
+                    let mut rng = self.rng();
                     let mut key = vec![0u8; attributes.length() as usize];
                     rng.fill_bytes(key.as_mut_slice());
                     key
@@ -139,7 +139,7 @@ impl SecretVault for Vault {
                     return Err(VaultError::InvalidKeyType.into());
                 };
                 let key = {
-                    let mut rng = thread_rng();
+                    let mut rng = self.rng();
                     let mut key = vec![0u8; attributes.length() as usize];
                     rng.fill_bytes(key.as_mut_slice());
                     key
@@ -149,7 +149,7 @@ impl SecretVault for Vault {
             }
             #[cfg(feature = "bls")]
             SecretType::Bls => {
-                let mut rng = thread_rng();
+                let mut rng = self.rng();
                 let bls_secret_key = BlsSecretKey::random(&mut rng).unwrap();
 
                 SecretKey::new(bls_secret_key.to_bytes().to_vec())
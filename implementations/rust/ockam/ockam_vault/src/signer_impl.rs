@@ -13,17 +13,17 @@ impl Signer for Vault {
         let entry = entries.get(secret_key).ok_or(VaultError::EntryNotFound)?;
 
         let key = entry.key().as_ref();
-        match entry.key_attributes().stype() {
+        let signature: Result<Signature> = match entry.key_attributes().stype() {
             SecretType::X25519 => {
                 use crate::xeddsa::XEddsaSigner;
                 use arrayref::array_ref;
-                use ockam_core::compat::rand::{thread_rng, RngCore};
+                use ockam_core::compat::rand::RngCore;
                 use ockam_core::vault::CURVE25519_SECRET_LENGTH_USIZE;
                 if key.len() != CURVE25519_SECRET_LENGTH_USIZE {
                     return Err(VaultError::InvalidX25519SecretLength.into());
                 }
 
-                let mut rng = thread_rng();
+                let mut rng = self.rng();
                 let mut nonce = [0u8; 64];
                 rng.fill_bytes(&mut nonce);
                 let sig = x25519_dalek::StaticSecret::from(*array_ref!(
@@ -65,7 +65,14 @@ impl Signer for Vault {
                 }
             }
             SecretType::Buffer | SecretType::Aes => Err(VaultError::InvalidKeyType.into()),
+        };
+
+        #[cfg(feature = "std")]
+        if signature.is_ok() {
+            self.audit(secret_key, crate::VaultOperation::Sign);
         }
+
+        signature
     }
 }
 
@@ -35,6 +35,10 @@ use ockam_node::compat::asynchronous::RwLock;
 pub struct Vault {
     pub(crate) data: VaultData,
     pub(crate) storage: Option<Arc<dyn Storage>>,
+    #[cfg(feature = "std")]
+    pub(crate) audit_sink: Option<Arc<dyn crate::VaultAuditSink>>,
+    #[cfg(feature = "std")]
+    pub(crate) rng: Option<Arc<std::sync::Mutex<dyn crate::VaultRng>>>,
 }
 
 #[derive(Default, Clone)]
@@ -48,6 +52,10 @@ impl Vault {
         Self {
             data: Default::default(),
             storage,
+            #[cfg(feature = "std")]
+            audit_sink: None,
+            #[cfg(feature = "std")]
+            rng: None,
         }
     }
 
@@ -56,6 +64,31 @@ impl Vault {
         Self::new(None)
     }
 
+    /// Record every signing and Diffie-Hellman key usage of this vault (and its clones)
+    /// to the given sink
+    #[cfg(feature = "std")]
+    pub fn with_audit_sink(mut self, sink: Arc<dyn crate::VaultAuditSink>) -> Self {
+        self.audit_sink = Some(sink);
+        self
+    }
+
+    /// Draw the randomness of this vault (and its clones) from `rng`
+    /// instead of the OS-seeded generator, e.g. a mandated DRBG, or a
+    /// seeded generator for reproducible keys in tests
+    #[cfg(feature = "std")]
+    pub fn with_rng(mut self, rng: impl crate::VaultRng) -> Self {
+        let rng: Arc<std::sync::Mutex<dyn crate::VaultRng>> = Arc::new(std::sync::Mutex::new(rng));
+        self.rng = Some(rng);
+        self
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn audit(&self, key_id: &KeyId, operation: crate::VaultOperation) {
+        if let Some(sink) = &self.audit_sink {
+            sink.record(crate::VaultAuditEntry::new(key_id.clone(), operation));
+        }
+    }
+
     pub(crate) async fn preload_from_storage(&self, key_id: &KeyId) {
         // Do nothing if there is no Storage
         let storage = match &self.storage {